@@ -3,13 +3,28 @@ use std::process::Command;
 use std::path::PathBuf;
 
 fn main() {
+    // Non-macOS targets build the stub API only: no frameworks, no bridge
+    if env::var("CARGO_CFG_TARGET_OS").unwrap() != "macos" {
+        println!("cargo:warning=Building non-macOS stubs: every API call will fail at runtime");
+        napi_build::setup();
+        return;
+    }
+    
     // Link ScreenCaptureKit framework
     println!("cargo:rustc-link-lib=framework=ScreenCaptureKit");
     println!("cargo:rustc-link-lib=framework=CoreMedia");
     println!("cargo:rustc-link-lib=framework=CoreVideo");
     println!("cargo:rustc-link-lib=framework=AVFoundation");
+    println!("cargo:rustc-link-lib=framework=VideoToolbox");
+    println!("cargo:rustc-link-lib=framework=CoreAudio");
+    println!("cargo:rustc-link-lib=framework=AudioToolbox");
+    println!("cargo:rustc-link-lib=framework=CoreGraphics");
+    println!("cargo:rustc-link-lib=framework=CoreText");
     println!("cargo:rustc-link-lib=framework=Foundation");
     println!("cargo:rustc-link-lib=framework=AppKit");
+    println!("cargo:rustc-link-lib=framework=Carbon");
+    println!("cargo:rustc-link-lib=framework=Accelerate");
+    println!("cargo:rustc-link-lib=framework=CoreMediaIO");
     
     // Set minimum macOS version for ScreenCaptureKit
     println!("cargo:rustc-env=MACOSX_DEPLOYMENT_TARGET=12.3");
@@ -37,7 +52,7 @@ fn compile_objc_bridge() {
     
     // Compile the Objective-C file directly using clang
     let output = Command::new("clang")
-        .args(&[
+        .args([
             "-c",
             "-fobjc-arc", // Enable ARC
             "-fmodules", // Enable modules
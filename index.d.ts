@@ -9,41 +9,1005 @@ export interface ScreenSource {
   width: number
   height: number
   isDisplay: boolean
+  /** Sidecar, AirPlay or other software display (always false for windows) */
+  isVirtual: boolean
+  /**
+   * Display that mirrors another one. Each mirror set is listed as its primary display
+   * (not flagged) plus one flagged entry per mirroring display; skip flagged entries to list
+   * each distinct picture once (always false for windows)
+   */
+  isMirror: boolean
+  /**
+   * Window that's minimized, in a hidden app or on another Space, so recording it shows
+   * nothing until it's back on screen (always false for displays)
+   */
+  isMinimized: boolean
+  /** Window that covers a whole display, as a fullscreen window does (always false for displays) */
+  isFullscreen: boolean
+  /**
+   * The active window of its app; needs macOS 13.1 and is always false before (always false
+   * for displays)
+   */
+  isActive: boolean
+  /**
+   * The display's current EDR headroom: how far above SDR white (1.0) it can show HDR
+   * content right now. Reported only: recordings, 10-bit l10r included, aren't tone-mapped.
+   * 1.0 on SDR displays; null when unknown and for windows. AppKit gives it on the main
+   * thread, so it's unknown when that thread isn't running its run loop, as in plain Node
+   */
+  edrHeadroom?: number
+}
+/** A Space (virtual desktop) of a display, as listed by getSpaces */
+export interface SpaceInfo {
+  /** Window server id, passed to startRecordingSpace */
+  id: number
+  /** Stable identifier; empty for a display's first desktop */
+  uuid: string
+  /** Display the Space belongs to; null when it isn't connected */
+  displayId?: number
+  /** Position in Mission Control on its display, counting from 1 */
+  index: number
+  /** The Space showing on its display now, the only one that can be captured */
+  isCurrent: boolean
+  /** The Space of a full-screen app */
+  isFullscreen: boolean
+}
+/**
+ * Rectangle in global display points: the main display's top-left corner is 0,0 and other
+ * displays sit around it as arranged in System Settings
+ */
+export interface CaptureRegion {
+  x: number
+  y: number
+  width: number
+  height: number
+}
+/**
+ * Condition for `armTrigger`: set exactly one of frontmostApp or windowTitle. Apps are bundle
+ * ids or app names, matched case-insensitively
+ */
+export interface RecordingTriggerCondition {
+  /** Met while this app owns the frontmost window */
+  frontmostApp?: string
+  /** Met while an on-screen window's title contains this text */
+  windowTitle?: string
+  /** Only count windowTitle matches in this app's windows */
+  windowApp?: string
+}
+/** When an armed trigger starts and stops its recording */
+export interface RecordingTrigger {
+  start: RecordingTriggerCondition
+  /** Default: stop once the start condition no longer holds */
+  stop?: RecordingTriggerCondition
+  /** How often the conditions are checked (default 500, minimum 100) */
+  intervalMs?: number
+}
+/** Passed to the trigger callback */
+export interface TriggerEvent {
+  /** "started", "stopped" or "failed" */
+  action: string
+  outputPath: string
+  /** Why starting or stopping failed */
+  error?: string
+}
+/**
+ * Which apps' windows the source listing includes. Entries are bundle ids or app names,
+ * matched case-insensitively
+ */
+export interface SourceFilter {
+  /** Only list windows of these apps */
+  includeApps?: Array<string>
+  /** Never list windows of these apps */
+  excludeApps?: Array<string>
+  /**
+   * Hide the Dock, menu bar, Control Center and other system windows (default true); apps
+   * in includeApps are listed either way
+   */
+  excludeSystemApps?: boolean
+}
+/** What the system content picker offers */
+export interface SystemPickerOptions {
+  /** Offer single windows (default true) */
+  allowWindows?: boolean
+  /** Offer whole displays (default true) */
+  allowDisplays?: boolean
+  /** Bundle ids of apps whose windows the picker leaves out */
+  excludeApps?: Array<string>
+}
+/** The source chosen in the system content picker */
+export interface PickedSource {
+  /**
+   * ScreenSource id to pass to startRecording; missing before macOS 15.2, where the picker
+   * doesn't name what it captures
+   */
+  id?: string
+  isDisplay: boolean
 }
 export interface AudioDevice {
   id: string
   name: string
   deviceType: string
 }
+/** A camera usable for the webcam overlay */
+export interface CameraDevice {
+  id: string
+  name: string
+}
+/** An iPhone or iPad that can be recorded with startDeviceCapture */
+export interface CaptureDevice {
+  id: string
+  name: string
+  /** Hardware model, e.g. "iPhone15,2" */
+  model: string
+}
 export interface RecordingConfiguration {
+  /** Output size (default 1920x1080); odd values are rounded down to even unless strictDimensions */
   width?: number
   height?: number
+  /**
+   * Unit of width/height: "pixels" (default) or "points", converted with the captured
+   * display's scale factor so 1440 points on a Retina display records 2880 pixels
+   */
+  dimensionUnit?: string
   fps?: number
   showCursor?: boolean
+  /**
+   * Record system audio. Starting fails rather than recording video only when the audio
+   * writer can't be set up
+   */
   captureAudio?: boolean
+  /** Microphone to capture; requires macOS 15 (see Capabilities.features) */
   audioDeviceId?: string
+  /**
+   * Where the video is written. The container follows an .mp4/.m4v (MPEG-4) or .mov
+   * (QuickTime) extension; any other extension, or none, is replaced with the container's
+   * own (.mp4, or .mov for "hevc_alpha"), and stopRecording returns the path actually used
+   */
   outputPath: string
+  /**
+   * Stream the recording as fragmented MP4 to this open file descriptor (e.g. a pipe to
+   * another process) instead of writing outputPath, which must still end in .mp4 or .m4v.
+   * The stream is video only: captureAudio, replayBufferSeconds, emitFrameIndex and
+   * resolutionChangeBehavior "segment" need a file and are refused
+   */
+  outputFd?: number
+  /**
+   * Like outputFd, for the named pipe (FIFO) at this path; starting fails unless a reader has
+   * it open
+   */
+  outputPipe?: string
+  /**
+   * Capture pixel format, in any case: "bgra" (default), "420v" or "l10r" (10-bit, requires
+   * videoCodec "hevc")
+   */
   pixelFormat?: string
+  /**
+   * Colour space frames are captured in and the file is tagged with (BT.709 YCbCr matrix
+   * throughout), in any case: "bt709" (default; BT.709 primaries and transfer), "srgb" (BT.709
+   * primaries, sRGB transfer), "p3" (P3 D65 primaries, sRGB transfer) or "linear" (BT.709
+   * primaries, linear transfer). sRGB and linear transfer tags need macOS 15; older systems
+   * tag those files with the BT.709 transfer
+   */
   colorSpace?: string
+  /** Presenter overlay privacy alert: "system" (default), "never" or "always" (macOS 14.2+) */
+  presenterOverlayPrivacyAlert?: string
+  /** Closed-GOP output without frame reordering for editing workflows (larger files, off by default) */
+  editingFriendly?: boolean
+  /** What to do when stopped before any frame was captured: "error" (default) or "blackFrame" */
+  emptyRecordingBehavior?: string
+  /** Leave this process's own windows out of display captures (no effect on window captures) */
+  excludeSelf?: boolean
+  /**
+   * Metadata tags embedded in the output; "title", "artist", "creator", "comment" and
+   * "copyright" map to common keys, anything else is written as QuickTime metadata under its
+   * own name
+   */
+  metadata?: Record<string, string>
+  /**
+   * Instant replay: keep only the last N seconds of video in memory (outputPath is not
+   * written); call `saveReplay` to write the window to a file. Video only: not with captureAudio
+   */
+  replayBufferSeconds?: number
+  /**
+   * Video codec: "h264" (default), "hevc" or "hevc_alpha" (HEVC with transparency; window
+   * targets only, requires pixelFormat "bgra" and a .mov outputPath)
+   */
+  videoCodec?: string
+  /**
+   * Pin the encoder profile and level, e.g. "H264_Main_AutoLevel" or "H264_High_4_2"; unset
+   * lets the encoder choose (HEVC Main10 for 10-bit capture). H.264 accepts H264_Baseline_,
+   * H264_Main_ and H264_High_ followed by 3_0-3_2, 4_0-4_2, 5_0-5_2 or AutoLevel, plus
+   * H264_ConstrainedBaseline_AutoLevel and H264_ConstrainedHigh_AutoLevel; HEVC accepts
+   * HEVC_Main_AutoLevel and HEVC_Main10_AutoLevel (required with "l10r"). Must match videoCodec
+   */
+  profileLevel?: string
+  /**
+   * Average video bitrate in kbps (default: the encoder's choice for the size); can't be
+   * combined with quality
+   */
+  videoBitrateKbps?: number
+  /**
+   * Size budget for the file in bytes, e.g. 500000000 for an upload limit: the video
+   * bitrate is chosen so maxDurationSeconds of recording fit, leaving room for audio when
+   * captureAudio is on. Requires maxDurationSeconds; can't be combined with
+   * videoBitrateKbps or quality. The chosen bitrate is in getEffectiveSettings
+   */
+  targetFileSizeBytes?: number
+  /**
+   * Constant-quality encoding, 0.0 (smallest) to 1.0 (best), instead of a target bitrate:
+   * static content then costs almost nothing. Can't be combined with videoBitrateKbps
+   */
+  quality?: number
+  /** How many times to recreate the writer when it fails to start (default 2, max 10) */
+  writerStartRetries?: number
+  /**
+   * Hold frames while the encoder is busy instead of dropping them (default false), for short
+   * clips where no frame may be lost. Up to 8 uncompressed frames wait in memory and capture
+   * then stalls for up to 500ms, which adds latency and makes ScreenCaptureKit skip frames if
+   * the encoder never catches up; live capture should keep dropping
+   */
+  blockOnBackpressure?: boolean
+  /**
+   * How much audio arriving before the first video frame is held for it, in ms (default
+   * 1000, max 10000). Only video starts the file, so early audio can't offset the tracks;
+   * what overlaps the first frame is kept and anything older is dropped
+   */
+  earlyAudioBufferMs?: number
+  /**
+   * How the source is fitted to width/height when aspect ratios differ: "fit" (default,
+   * letterbox/pillarbox in black), "fill" (crop the centre of the source, macOS 14+) or
+   * "stretch" (distort to the output size)
+   */
+  scalingMode?: string
+  /**
+   * Where scaling happens: "fast" (default, during capture) or "high" (capture at native size
+   * and scale in the encoder, macOS 14+; more GPU/memory bandwidth)
+   */
+  scalingQuality?: string
+  /**
+   * Draw recently pressed keys onto the video (requires accessibility permission and the
+   * "bgra" pixel format); recording fails to start when permission is missing
+   */
+  keystrokeOverlay?: boolean
+  /**
+   * Draw a camera as a picture-in-picture over the video (requires the "bgra" pixel format
+   * and camera access; the host app needs NSCameraUsageDescription). When the camera is
+   * unavailable the screen is recorded without it and a warning is logged
+   */
+  webcam?: WebcamConfiguration
+  /**
+   * Composite a static image such as a logo into a corner of every frame (off by default;
+   * requires the "bgra" pixel format). The image is decoded once; each frame then costs an
+   * alpha blend over the watermark's pixels, so CPU use grows with its size
+   */
+  watermark?: WatermarkConfiguration
+  /**
+   * Frame delivery: "fixed_fps" (default, up to fps frames per second) or "on_change" (a frame
+   * only when the screen changes, each held until the next). On-change capture costs almost
+   * no CPU or encoder time while the screen is static and produces much smaller files for
+   * slides, code walkthroughs and other mostly-still content
+   */
+  captureMode?: string
+  /**
+   * Frame rate of the recorded file when it should differ from the capture fps, e.g.
+   * capture at 60 and write 30. Frames are dropped or repeated by capture time so motion
+   * keeps its speed; a frame after a gap is repeated for at most a second (1-120; not with
+   * "on_change" capture)
+   */
+  outputFps?: number
+  /**
+   * Capture API: "screencapturekit" (default), "cgdisplaystream" (the older API, for
+   * systems where ScreenCaptureKit delivers no frames; video only) or "auto"
+   * (ScreenCaptureKit, switching to CGDisplayStream when no frame arrives within 2s of
+   * starting; audio is lost then). Both alternatives need a display target
+   */
+  captureBackend?: string
+  /**
+   * Call the disk space callback when free space on the output volume drops below this
+   * many MB (default 2048)
+   */
+  lowDiskSpaceWarningMb?: number
+  /**
+   * Refuse to start, or stop and finalize the recording, when free space on the output
+   * volume drops below this many MB (default 512)
+   */
+  minFreeDiskSpaceMb?: number
+  /**
+   * Recording length used for the pre-start free space check (default maxDurationSeconds,
+   * or 60)
+   */
+  expectedDurationSeconds?: number
+  /**
+   * Gain in dB applied to system audio before it shares the audio track with the
+   * microphone (default 0, clamped to -60..20; boosted peaks are soft-limited, not clipped)
+   */
+  systemAudioGain?: number
+  /** Gain in dB applied to microphone audio (default 0, clamped to -60..20) */
+  microphoneGain?: number
+  /**
+   * Write system audio and the microphone as two audio tracks, system first, so they can be
+   * balanced in an editor. Default false: both sources' buffers are appended to one track as
+   * they arrive rather than mixed together. Requires captureAudio and audioDeviceId;
+   * IntegratedRecordingManager rejects it
+   */
+  separateAudioTracks?: boolean
+  /**
+   * Record system audio from this output device (a UID from getAvailableAudioDevices with
+   * deviceType "speaker", e.g. an aggregate or BlackHole device) instead of the system mix,
+   * through a Core Audio process tap. Requires captureAudio and macOS 14.2
+   */
+  audioOutputDeviceId?: string
+  /**
+   * Output aspect ratio as "<width>:<height>" (e.g. "16:9"); the missing dimension is
+   * computed from height (default 1080) or width and rounded to even. The source is fitted
+   * according to scalingMode. Can't be combined with both width and height
+   */
+  aspectRatio?: string
+  /**
+   * Stop and finalize the recording after this many seconds of video; progress callbacks
+   * then report a 0-100 `progress`
+   */
+  maxDurationSeconds?: number
+  /**
+   * How often the progress callback fires while recording, in ms, whatever the frame rate
+   * (default 500)
+   */
+  progressIntervalMs?: number
+  /**
+   * Stop and finalize the recording once the screen has stayed unchanged for this many
+   * seconds (paused time doesn't count); the error callback then gets "IdleStopped".
+   * Changes confined to a couple of 16px tiles, like a blinking caret, are ignored, while
+   * slow content such as a progress bar still counts. Not supported for region recordings
+   */
+  idleStopSeconds?: number
+  /**
+   * Count down this many seconds (at most 60) before anything is recorded, so the file
+   * doesn't open on the click that started it; the stream runs meanwhile and progress
+   * callbacks report `countdown` once a second
+   */
+  startDelaySeconds?: number
+  /**
+   * How long stopRecording waits for ScreenCaptureKit to confirm the stream stopped, in ms
+   * (default 5000). ScreenCaptureKit occasionally never confirms; the file is then
+   * finalized anyway, a warning is logged and collectDiagnostics reports `stopTimedOut`
+   */
+  stopTimeoutMs?: number
+  /**
+   * Bundle ids of running apps whose audio is left out of a display recording while their
+   * windows stay in the video (macOS 15+; older systems record all audio). Every id must
+   * belong to a running application
+   */
+  excludeAudioAppBundleIds?: Array<string>
+  /**
+   * When screen recording permission is revoked mid-recording the capture is finalized and
+   * the error callback gets a "PermissionRevoked" error; "finalize" (default) keeps the
+   * partial file, "discard" deletes it
+   */
+  permissionRevokedBehavior?: string
+  /**
+   * What happens when the captured frames change size mid-recording, e.g. after a display
+   * resolution change: "scale" (default) scales them back to the recording's size;
+   * "segment" finalizes the file and continues in "<name>_part2.mp4" (and so on) at the new
+   * size, reporting each switch to the error callback as "NewSegment". Audio stays in one file
+   */
+  resolutionChangeBehavior?: string
+  /**
+   * Record the cursor's position and image whenever they change to "<outputPath
+   * without extension>.cursor.json", timed against the video (paused time excluded) and
+   * relative to the captured area; see `getCursorTrackPath`. Combine with showCursor: false
+   * to redraw the cursor in post
+   */
+  captureCursorTrack?: boolean
+  /**
+   * After the recording is finalized, write "<outputPath without extension>.frames.json"
+   * listing each video frame's presentation time, keyframe flag and byte range in the file
+   */
+  emitFrameIndex?: boolean
+  /**
+   * After the recording is finalized, write "<outputPath without extension>.chapters.json"
+   * with a chapter starting at each scene cut: a frame that changed much of the picture,
+   * such as switching apps or slides. Cuts less than 10 seconds apart are merged. Not
+   * supported for region recordings
+   */
+  detectChapters?: boolean
+  /**
+   * How small a change still starts a chapter, from 0 (only full-screen changes) to 1
+   * (default 0.5, about half the frame)
+   */
+  chapterSensitivity?: number
+  /**
+   * Debugging aid: check every video sample's pixel format, size and presentation time
+   * against the configured stream and log a warning when a mismatch starts, to trace
+   * green or wrongly sized output back to the frames ScreenCaptureKit delivered
+   */
+  validateBuffers?: boolean
+  /**
+   * Record the display the cursor is on when the recording starts instead of the given
+   * display (default false). The display is picked once: the recording stays on it when the
+   * cursor moves to another. Requires a display target
+   */
+  followActiveDisplay?: boolean
+  /**
+   * Leave stretches where secure input (e.g. a focused password field) is active out of
+   * the video, since macOS may blank the capture then; they're listed with the timeline's
+   * pauses. Secure input is reported to the error callback either way. Can't be combined
+   * with captureCursorTrack
+   */
+  pauseOnSecureInput?: boolean
+  /**
+   * Reject odd width/height instead of rounding them down to even (the encoder needs even
+   * dimensions)
+   */
+  strictDimensions?: boolean
+  /**
+   * Color behind areas the captured content doesn't cover, such as transparent regions of a
+   * recorded window, as "#RRGGBB" or "#RRGGBBAA" (default black, or transparent with
+   * "hevc_alpha"). Alpha reaches BGRA frames, but the h264 and hevc outputs have no alpha
+   * channel, so translucent colors are flattened onto black in the file
+   */
+  backgroundColor?: string
+  /**
+   * Advanced: pool hints for the writer's pixel buffer adaptor; unset keeps the minimal
+   * pixel-format-only attributes
+   */
+  pixelBufferPool?: PixelBufferPoolConfiguration
+}
+/**
+ * Pixel buffer pool hints for the writer; preallocating buffers of the final size and format
+ * avoids allocation churn at high resolutions
+ */
+export interface PixelBufferPoolConfiguration {
+  /** Buffers the pool keeps allocated (1-64; default: allocated on demand) */
+  minBufferCount?: number
+  /** Buffer size (default the output width/height) */
+  width?: number
+  height?: number
+  /** "bgra", "420v" or "l10r" (default pixelFormat) */
+  pixelFormat?: string
+}
+/** Picture-in-picture camera overlay */
+export interface WebcamConfiguration {
+  /** A camera id from getAvailableCameras (default: the system's default camera) */
+  deviceId?: string
+  /** "topLeft", "topRight", "bottomLeft" or "bottomRight" (default) */
+  corner?: string
+  /**
+   * Overlay width as a fraction of the video width (0.05-0.5, default 0.25); the height
+   * follows the camera's aspect ratio
+   */
+  size?: number
+}
+/** Static image drawn over the video */
+export interface WatermarkConfiguration {
+  /** Image file to draw (PNG, JPEG or anything else NSImage reads); set this or `image` */
+  path?: string
+  /** Encoded image bytes instead of a file */
+  image?: Buffer
+  /** "topLeft", "topRight", "bottomLeft" or "bottomRight" (default) */
+  corner?: string
+  /** 0.0-1.0, applied on top of the image's own transparency (default 1.0) */
+  opacity?: number
+  /**
+   * Width as a fraction of the video width (0.01-1.0); the height follows the image's
+   * aspect ratio. Default: the image's own pixel size
+   */
+  size?: number
+  /** Gap to the video edges in pixels (default 2% of the video width) */
+  margin?: number
+}
+/** System capture indicator state for compliance logging */
+export interface CaptureIndicatorState {
+  /** Whether the OS is showing the screen recording indicator for this process */
+  indicatorVisible: boolean
+  privacyAlertSetting: string
+  privacyAlertSupported: boolean
+}
+/** Rectangle in points */
+export interface FrameRect {
+  x: number
+  y: number
+  width: number
+  height: number
+}
+/** Settings the current recording actually uses after defaults and derived sizes are applied */
+export interface EffectiveSettings {
+  /** Output size in pixels */
+  width: number
+  height: number
+  /**
+   * The output size in points of the captured display, and the unit width/height were
+   * configured in
+   */
+  widthPoints: number
+  heightPoints: number
+  dimensionUnit: string
+  fps: number
+  scalingMode: string
+  /**
+   * Average video bitrate in kbps, from videoBitrateKbps or targetFileSizeBytes; null when
+   * the encoder picks it
+   */
+  videoBitrateKbps?: number
+  /**
+   * The part of the source ScreenCaptureKit was asked to capture (the fill crop, or the
+   * region); null when it captures the whole display or window
+   */
+  sourceRect?: FrameRect
+  /**
+   * Where ScreenCaptureKit placed the content in its frames, in points; a rect smaller
+   * than the output means letterboxing. Null until the first frame arrives
+   */
+  contentRect?: FrameRect
+  /** Scale ScreenCaptureKit applied to the content to fit the frame */
+  contentScale?: number
+  /** Pixels per point of the captured display */
+  scaleFactor?: number
+  /** Whether audio and video are being recorded, as set with setAudioEnabled/setVideoEnabled */
+  audioEnabled: boolean
+  videoEnabled: boolean
+}
+/** What this machine can record; values match the RecordingConfiguration strings */
+export interface Capabilities {
+  videoCodecs: Array<string>
+  /** Codecs with a hardware encoder (the rest fall back to software) */
+  hardwareVideoCodecs: Array<string>
+  audioCodecs: Array<string>
+  containers: Array<string>
+  pixelFormats: Array<string>
+  maxWidth: number
+  maxHeight: number
+  features: Features
+}
+/**
+ * Which optional features the running macOS has; features it lacks fail with
+ * "... requires macOS X or later"
+ */
+export interface Features {
+  /** The running macOS as "major.minor" */
+  macosVersion: string
+  /** Microphone capture with audioDeviceId (macOS 15) */
+  supportsMicrophoneCapture: boolean
+  /** Excluding an application's audio while showing its windows (macOS 15) */
+  supportsAppAudioExclusion: boolean
+  /** presentSystemPicker (macOS 14) */
+  supportsSystemPicker: boolean
+  /** presentSystemPicker reporting which windows and displays were picked (macOS 15.2) */
+  supportsPickerSourceIds: boolean
+  /** presenterOverlayPrivacyAlert (macOS 14.2) */
+  supportsPresenterOverlayPrivacyAlert: boolean
+  /** audioOutputDeviceId (macOS 14.2) */
+  supportsOutputDeviceTap: boolean
+  /** ScreenSource.isActive for windows (macOS 13.1) */
+  supportsWindowActiveState: boolean
+}
+/** Passed to the disk space callback */
+export interface DiskSpaceWarning {
+  outputPath: string
+  availableMb: number
+  /** The recording was stopped because free space fell below minFreeDiskSpaceMb */
+  autoStopped: boolean
+}
+/** Passed to the error callback when ScreenCaptureKit ends a recording on its own */
+export interface RecordingError {
+  /**
+   * "PermissionRevoked" when screen recording permission was taken away, "CaptureBlocked"
+   * when the screen locked or the session switched away, otherwise "StreamStopped".
+   * Recordings keep running after "NoFramesDelivered" (no frames within 2 seconds of
+   * starting), "SecureInputActive" (the capture may be blank, see pauseOnSecureInput) and
+   * "SecureInputEnded". "IdleStopped" reports a recording stopped by idleStopSeconds.
+   * "NewSegment" reports, with its finished file, a segment ended by a frame size change
+   * (resolutionChangeBehavior "segment"); the recording continues in the file named in
+   * the message
+   */
+  code: string
+  message: string
+  /** The finalized partial recording; null when nothing playable was written or it was discarded */
+  outputPath?: string
+}
+/** The last failure to start or finish a recording, from `getLastError` */
+export interface LastRecordingError {
+  /** Where it failed: "content-filter", "stream-create", "start" or "finalize" */
+  stage: string
+  /** Status of the error the call rejected with, e.g. "GenericFailure" */
+  code: string
+  message: string
+  /** The NSError behind it with its domain and code, when there was one */
+  underlyingError?: string
+  /** When it failed, in ms since the Unix epoch */
+  failedAt: number
+}
+/** Passed to the progress callback every progressIntervalMs (default 500) while recording */
+export interface RecordingProgress {
+  frames: number
+  /** Recorded video time (frames / fps; wall-clock time for on_change capture) */
+  elapsedSeconds: number
+  /** 0-100 of maxDurationSeconds, null without a maximum duration */
+  progress?: number
+  /** Seconds left of startDelaySeconds (3, 2, 1); null once recording has begun */
+  countdown?: number
+}
+/** Returned by RecordingHandle.getStats */
+export interface RecordingHandleStats {
+  outputPath: string
+  isRecording: boolean
+  isPaused: boolean
+  videoFrames: number
+  audioSamples: number
+  durationSeconds: number
+  averageFps: number
+  /** Why finalizing failed, once stopped */
+  error?: string
+}
+/** A recorded frame passed to the frames callback */
+export interface VideoFrame {
+  /**
+   * Pixels in `format`, `bytesPerRow` per row; "bgra" rows may be padded past width * 4,
+   * "rgba" and "rgb" are packed, "jpeg" is a whole JPEG file
+   */
+  data: Buffer
+  /** "bgra", "rgba", "rgb" or "jpeg", as requested in `onFrames` */
+  format: string
+  width: number
+  height: number
+  /** 0 for "jpeg" */
+  bytesPerRow: number
+  /** Presentation time in seconds on the capture clock */
+  timestamp: number
+}
+/** Output options for `transcode`; values match the RecordingConfiguration strings */
+export interface TranscodeConfiguration {
+  /**
+   * Output size; with only one set the other follows the source aspect ratio rounded to
+   * even, with neither the source size is kept
+   */
+  width?: number
+  height?: number
+  /** Video codec: "h264" (default) or "hevc" */
+  videoCodec?: string
+  /** Average video bitrate in kbps (default: the encoder's choice for the size) */
+  videoBitrateKbps?: number
+  /** Constant quality 0.0-1.0 instead of a bitrate; can't be combined with videoBitrateKbps */
+  quality?: number
+  /** How the source is fitted when the aspect ratio changes: "fit" (default), "fill" or "stretch" */
+  scalingMode?: string
+  /** Closed-GOP output without frame reordering, as for recordings */
+  editingFriendly?: boolean
+  /** Re-encode the first audio track as AAC (default true) */
+  includeAudio?: boolean
+  /**
+   * Decoder reading the source: "auto" (default; hardware when available), "hardware" or
+   * "software" for Macs whose hardware decoder fails or produces green frames. A failed
+   * decode is retried with the software decoder
+   */
+  decoderPreference?: string
+}
+/** Passed to the transcode progress callback */
+export interface TranscodeProgress {
+  processedSeconds: number
+  durationSeconds: number
+  /** 0-100 */
+  progress: number
+  /** "hardware" or "software", the decoder reading the source */
+  decoder: string
+}
+/** Options for `startTranscriptionJob` */
+export interface TranscriptionJobConfiguration {
+  /** "local" (default, the Whisper CLI) or "openaiWhisper" */
+  service?: string
+  /** Required for "openaiWhisper" */
+  apiKey?: string
+  /** Spoken language code (default "en") */
+  language?: string
+  /** Transcript saved next to the input: "text" (default), "srt", "vtt" or "json" */
+  outputFormat?: string
+}
+/** State of a background transcription, passed to the job callback on every change */
+export interface TranscriptionJob {
+  id: number
+  inputPath: string
+  /** "queued", "extractingAudio", "transcribing", "saving", "completed" or "failed" */
+  status: string
+  /** Once completed */
+  text?: string
+  language?: string
+  durationSeconds?: number
+  transcriptPath?: string
+  /** Once failed */
+  error?: string
+}
+/** When a recording started and when it was paused; times are ms since the Unix epoch */
+export interface RecordingTimeline {
+  startedAt: number
+  /** Completed pauses in order; a pause still open at stop ends at the stop time */
+  pausedIntervals: Array<PausedInterval>
+}
+export interface PausedInterval {
+  from: number
+  to: number
+}
+/** What verifyRecording found in a finished recording */
+export interface RecordingVerification {
+  durationSeconds: number
+  hasVideo: boolean
+  /** An audio track, in the movie or the separate audio file written next to it */
+  hasAudio: boolean
+  /**
+   * Whether the audio gets louder than -60 dBFS in its first five minutes; false for a
+   * silent track, e.g. when nothing played or the wrong device was captured
+   */
+  hasAudibleAudio: boolean
+  /**
+   * Colour primaries the video is tagged with: "P3_D65" for colorSpace "p3", otherwise
+   * "ITU_R_709_2"; null when untagged
+   */
+  colorPrimaries?: string
+  /** Problems worth telling the user about, such as a silent audio track */
+  warnings: Array<string>
+}
+/** Input level reported by the audio monitor, linear 0.0-1.0 */
+export interface AudioLevels {
+  rms: number
+  peak: number
 }
 export const kCVPixelFormatType_32BGRA: number
 export const kCGColorSpaceSRGB: number
 export declare function initScreencapturekit(): void
 export declare function getVersion(): string
+/**
+ * Start log lines with an emoji (the default unless the locale isn't UTF-8) or leave it out
+ * for terminals that can't show it; the rest of each line, window titles included, is
+ * unchanged
+ */
+export declare function setLogEmoji(enabled: boolean): void
+/** List the cameras usable for the webcam overlay */
+export declare function getAvailableCameras(): Array<CameraDevice>
+/**
+ * List microphones (deviceType "microphone", for audioDeviceId) and output devices
+ * (deviceType "speaker", for audioOutputDeviceId) by their Core Audio UIDs
+ */
+export declare function getAvailableAudioDevices(): Array<AudioDevice>
+/**
+ * Retry deleting intermediate files (extracted audio, Whisper output, the separate audio file
+ * of a failed recording) that couldn't be removed when their job ended; returns how many
+ * were deleted. Jobs clean up after themselves on success and failure, so this is only
+ * needed when a removal failed, e.g. because another process had the file open
+ */
+export declare function cleanupTempFiles(): number
+/**
+ * Check a finished recording: its duration, which tracks it has and whether its audio is
+ * audible, so a silent audio track can be flagged instead of found on playback
+ */
+export declare function verifyRecording(path: string): RecordingVerification
+/**
+ * List the attached iPhones and iPads that can be recorded with startDeviceCapture; a device
+ * must be unlocked and trust this Mac, and can take a moment to appear on the first call
+ */
+export declare function getAvailableCaptureDevices(): Array<CaptureDevice>
+/** List the codecs, containers, pixel formats and output sizes this machine supports */
+export declare function getCapabilities(): Capabilities
+/**
+ * Re-encode a finished recording into `outputPath` (.mp4, .m4v or .mov, must not exist)
+ * with the codec, bitrate and size from `config`; resolves with the output path
+ */
+export declare function transcode(inputPath: string, outputPath: string, config: TranscodeConfiguration, onProgress?: ((progress: TranscodeProgress) => void) | undefined | null): Promise<string>
+/**
+ * Join recording segments end to end into `outputPath` (.mp4, .m4v or .mov, must not exist)
+ * without re-encoding; the segments must share codec and size. Resolves with the output path
+ */
+export declare function concatenateSegments(segmentPaths: Array<string>, outputPath: string): Promise<string>
+/**
+ * Transcribe `inputPath` in the background and return the job's id straight away; `onUpdate`
+ * receives the job each time its status changes and `getTranscriptionJob` polls it
+ */
+export declare function startTranscriptionJob(inputPath: string, config?: TranscriptionJobConfiguration | undefined | null, onUpdate?: ((job: TranscriptionJob) => void) | undefined | null): number
+/** The transcription job with `id`, or null if it's unknown or long finished */
+export declare function getTranscriptionJob(id: number): TranscriptionJob | null
 export declare function checkScreenRecordingPermission(): boolean
 export declare function requestScreenRecordingPermission(): boolean
 /** Complete async ScreenCaptureKit recorder with full functionality */
 export declare class ScreenCaptureKitRecorder {
   constructor()
-  /** Get available screens using real ScreenCaptureKit async APIs */
-  getAvailableScreens(): Promise<Array<ScreenSource>>
-  /** Start recording using complete ScreenCaptureKit async APIs */
+  /**
+   * Get available screens using real ScreenCaptureKit async APIs
+   * Windows are listed too, narrowed by `filter` (system windows are hidden by default)
+   */
+  getAvailableScreens(filter?: SourceFilter | undefined | null): Promise<Array<ScreenSource>>
+  /**
+   * Call `callback` with the milliseconds waited so far when getAvailableScreens or
+   * getAvailableWindows is still gathering sources after a second, e.g. to show a spinner.
+   * It's called at most once per call, before that call resolves or times out
+   */
+  onContentRetrievalSlow(callback: (elapsedMs: number) => void): void
+  /**
+   * Let the user choose a window or display in the system content picker (macOS 14+)
+   * instead of listing sources; resolves to null when they cancel
+   */
+  presentSystemPicker(options?: SystemPickerOptions | undefined | null): Promise<PickedSource | null>
+  /**
+   * Start recording using complete ScreenCaptureKit async APIs
+   * `screenId` is a ScreenSource id ("display:<id>" or "window:<id>"); a window recording
+   * without width/height is sized to the window's full frame, even across displays
+   */
   startRecording(screenId: string, config: RecordingConfiguration): Promise<string>
+  /**
+   * Start recording `screenId` like startRecording, but on a recording manager of its own:
+   * the returned handle stops, pauses and reports on just this recording, so several can
+   * run at once. Callbacks registered on the recorder beforehand also apply to it
+   */
+  startRecordingHandle(screenId: string, config: RecordingConfiguration): Promise<RecordingHandle>
+  /**
+   * Record `region` of the desktop in global display points, even where it spans several
+   * displays; each display's part is captured separately and composited. Without
+   * width/height the output is the region's size in points. Requires pixelFormat "bgra"
+   */
+  startRecordingRegion(region: CaptureRegion, config: RecordingConfiguration): Promise<string>
+  /**
+   * Start a recording that continues `previousSegmentPath` after an interruption. Its
+   * timestamps pick up where that file ends, and videoCodec and the output size must match
+   * it; join the segments afterwards with `concatenateSegments`
+   */
+  resumeInto(previousSegmentPath: string, screenId: string, config: RecordingConfiguration): Promise<string>
   stopRecording(): Promise<string>
+  /**
+   * Keep the shareable content and content filter between recordings, so repeated short
+   * recordings of the same source only create a new stream each time. Recordings with
+   * excludeSelf or excludeAudioAppBundleIds still refresh the content and their filter
+   */
+  setKeepWarm(keepWarm: boolean): Promise<void>
+  /**
+   * Release everything kept between recordings, including the warm content filter, so the
+   * next recording starts from scratch. Fails while recording
+   */
+  reset(): Promise<void>
   isRecording(): Promise<boolean>
+  /**
+   * Whether the current recording received a video frame within the last second, also while
+   * paused. False when not recording; expected to be false for on_change capture of a static
+   * screen
+   */
+  isReceivingFrames(): Promise<boolean>
+  /**
+   * Start recording `screenId` with `config` once `trigger.start` is met and stop once its
+   * stop condition is, reporting both to `callback`. A trigger fires once and replaces any
+   * armed before; arm again for the next capture
+   */
+  armTrigger(trigger: RecordingTrigger, screenId: string, config: RecordingConfiguration, callback?: ((event: TriggerEvent) => void) | undefined | null): void
+  /** Stop watching for the armed trigger; a recording it started keeps going */
+  disarmTrigger(): void
+  /** Stop writing frames without ending the recording; paused time is left out of the file */
+  pauseRecording(): Promise<void>
+  resumeRecording(): Promise<void>
+  /**
+   * Turn the current recording's audio off and on without stopping; while off the audio
+   * track is silent, so it stays in step with the video
+   */
+  setAudioEnabled(enabled: boolean): Promise<void>
+  /**
+   * Turn the current recording's video off and on without stopping; while off the last
+   * recorded frame is held and no frames reach `onFrames`
+   */
+  setVideoEnabled(enabled: boolean): Promise<void>
+  /**
+   * Start time and pauses of the current recording, or of the last stopped one
+   * Null before the first recording
+   */
+  getRecordingTimeline(): Promise<RecordingTimeline | null>
+  /**
+   * Cursor track sidecar written by the last stopped recording (see captureCursorTrack)
+   * Null when that recording didn't capture one
+   */
+  getCursorTrackPath(): Promise<string | null>
+  /**
+   * Write the last `replayBufferSeconds` of an instant replay recording to `outputPath`
+   * Recording continues; call again to save later windows
+   */
+  saveReplay(outputPath: string): Promise<string>
   getStatus(): string
-  /** Get available windows */
-  getAvailableWindows(): Promise<Array<ScreenSource>>
+  /**
+   * Collect a JSON diagnostics report for support tickets: versions, permissions,
+   * detected content, transcription dependencies and the last recording's stats
+   */
+  collectDiagnostics(): Promise<string>
+  /**
+   * The last failure to start or finish a recording, with the stage and underlying NSError;
+   * null when none has failed
+   */
+  getLastError(): Promise<LastRecordingError | null>
+  /**
+   * Cancel any pending screen/window enumeration
+   * Pending `getAvailableScreens`/`getAvailableWindows` calls reject with a "cancelled" error
+   */
+  cancelContentRetrieval(): void
+  /** Report whether the OS recording indicator is shown and the configured privacy alert */
+  getCaptureIndicatorState(): Promise<CaptureIndicatorState>
+  /** Report the output size and frame rate of the current recording, or null when idle */
+  getEffectiveSettings(): Promise<EffectiveSettings | null>
+  /**
+   * Encode the latest frame of the current recording as "jpeg" (default) or "png", for a
+   * live preview from the frames already being captured. Requires pixelFormat "bgra"
+   */
+  grabCurrentFrame(format?: string | undefined | null): Promise<Buffer>
+  /**
+   * Stream input levels from `deviceId` (default input when omitted) to `callback`
+   * without recording; nothing is written to disk. Restarts the monitor if already running
+   */
+  startAudioMonitor(deviceId: string | undefined | null, callback: (levels: AudioLevels) => void): void
+  /**
+   * Call `callback` when the output volume runs low on space during a recording, and again
+   * with `autoStopped` set if the recording is stopped because the volume is nearly full
+   */
+  onDiskSpaceWarning(callback: (warning: DiskSpaceWarning) => void): void
+  /**
+   * Call `callback` with the full source list whenever a display or window appears,
+   * disappears or is renamed; polls every `intervalMs` (default 2000, minimum 250).
+   * `filter` narrows the windows as in getAvailableScreens
+   */
+  watchSources(callback: (sources: Array<ScreenSource>) => void, intervalMs?: number | undefined | null, filter?: SourceFilter | undefined | null): SourceWatcher
+  /**
+   * Call `callback` when ScreenCaptureKit ends a recording on its own, e.g. with a
+   * "PermissionRevoked" error when screen recording permission is taken away. The
+   * captured part has already been finalized (see permissionRevokedBehavior). A recording
+   * whose stream delivers no frames within 2 seconds of starting reports "NoFramesDelivered",
+   * and secure input starting or ending mid-recording is reported too
+   */
+  onError(callback: (error: RecordingError) => void): void
+  /**
+   * Call `callback` with copies of the recorded frames during the next recordings, which
+   * must use pixelFormat "bgra". Frames are delivered `batchSize` at a time (default 1, max
+   * 120) to cut per-call overhead at high fps; a partial batch is delivered at stop. When JS
+   * falls behind by more than a few batches, newer batches are dropped.
+   * `format` is "bgra" (default, the captured pixels copied as-is), "rgba" or "rgb" (packed,
+   * converted with vImage) or "jpeg". Conversion runs on the capture queue: RGB(A) adds
+   * roughly a copy's cost per frame, JPEG several milliseconds per 1080p frame, which at high
+   * fps can make ScreenCaptureKit drop frames
+   */
+  onFrames(callback: (frames: Array<VideoFrame>) => void, batchSize?: number | undefined | null, format?: string | undefined | null): void
+  /** Call `callback` with recording progress every progressIntervalMs (default 500) while recording */
+  onProgress(callback: (progress: RecordingProgress) => void): void
+  /** Stop the audio monitor and release the input device */
+  stopAudioMonitor(): void
+  /**
+   * Record an attached iPhone or iPad (an id from getAvailableCaptureDevices) to
+   * `config.outputPath`, video only. Runs separately from screen recording; the host app
+   * needs camera access (NSCameraUsageDescription, plus the com.apple.security.device.camera
+   * entitlement when sandboxed or hardened)
+   */
+  startDeviceCapture(deviceId: string, config: RecordingConfiguration): Promise<void>
+  /** Stop the device recording and return the path of the finished file */
+  stopDeviceCapture(): Promise<string>
+  /** Get available windows, narrowed by `filter` (system windows are hidden by default) */
+  getAvailableWindows(filter?: SourceFilter | undefined | null): Promise<Array<ScreenSource>>
+  /**
+   * List the Spaces of every display. macOS has no public API for Spaces, so this reads
+   * the window server's own list and fails where it isn't available
+   */
+  getSpaces(): Array<SpaceInfo>
+  /**
+   * Record the display showing Space `spaceId`. ScreenCaptureKit only captures what's on
+   * screen, so this fails unless the Space is its display's current one; switching Spaces
+   * mid-recording records the new one
+   */
+  startRecordingSpace(spaceId: number, config: RecordingConfiguration): Promise<string>
+}
+/** Handle returned by `watchSources` */
+export declare class SourceWatcher {
+  /** Stop watching; safe to call more than once */
+  unsubscribe(): void
+}
+/** Handle returned by `startRecordingHandle`; controls only its own recording */
+export declare class RecordingHandle {
+  /** Stop and finalize the recording; resolves with the path of the file */
+  stop(): Promise<string>
+  /** Stop writing frames without ending the recording; paused time is left out of the file */
+  pause(): Promise<void>
+  resume(): Promise<void>
+  /** Counters of the recording so far; those of the finished recording once it stopped */
+  getStats(): Promise<RecordingHandleStats | null>
+  /**
+   * Call `callback` with this recording's progress every progressIntervalMs; replaces the
+   * callback inherited from the recorder
+   */
+  onProgress(callback: (progress: RecordingProgress) => void): void
 }
 /** Integrated recording manager with complete functionality */
 export declare class IntegratedRecordingManager {
@@ -52,6 +1016,6 @@ export declare class IntegratedRecordingManager {
   startRecording(config: RecordingConfiguration): Promise<string>
   stopRecording(): Promise<string>
   getAvailableScreens(): Promise<Array<ScreenSource>>
-  getAvailableWindows(): Promise<Array<ScreenSource>>
+  getAvailableWindows(filter?: SourceFilter | undefined | null): Promise<Array<ScreenSource>>
   isRecording(): boolean
 }
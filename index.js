@@ -310,13 +310,26 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { ScreenCaptureKitRecorder, IntegratedRecordingManager, kCVPixelFormatType_32BGRA, kCGColorSpaceSRGB, initScreencapturekit, getVersion, checkScreenRecordingPermission, requestScreenRecordingPermission } = nativeBinding
+const { ScreenCaptureKitRecorder, SourceWatcher, RecordingHandle, IntegratedRecordingManager, kCVPixelFormatType_32BGRA, kCGColorSpaceSRGB, initScreencapturekit, getVersion, setLogEmoji, getAvailableCameras, getAvailableAudioDevices, cleanupTempFiles, verifyRecording, getAvailableCaptureDevices, getCapabilities, transcode, concatenateSegments, startTranscriptionJob, getTranscriptionJob, checkScreenRecordingPermission, requestScreenRecordingPermission } = nativeBinding
 
 module.exports.ScreenCaptureKitRecorder = ScreenCaptureKitRecorder
+module.exports.SourceWatcher = SourceWatcher
+module.exports.RecordingHandle = RecordingHandle
 module.exports.IntegratedRecordingManager = IntegratedRecordingManager
 module.exports.kCVPixelFormatType_32BGRA = kCVPixelFormatType_32BGRA
 module.exports.kCGColorSpaceSRGB = kCGColorSpaceSRGB
 module.exports.initScreencapturekit = initScreencapturekit
 module.exports.getVersion = getVersion
+module.exports.setLogEmoji = setLogEmoji
+module.exports.getAvailableCameras = getAvailableCameras
+module.exports.getAvailableAudioDevices = getAvailableAudioDevices
+module.exports.cleanupTempFiles = cleanupTempFiles
+module.exports.verifyRecording = verifyRecording
+module.exports.getAvailableCaptureDevices = getAvailableCaptureDevices
+module.exports.getCapabilities = getCapabilities
+module.exports.transcode = transcode
+module.exports.concatenateSegments = concatenateSegments
+module.exports.startTranscriptionJob = startTranscriptionJob
+module.exports.getTranscriptionJob = getTranscriptionJob
 module.exports.checkScreenRecordingPermission = checkScreenRecordingPermission
 module.exports.requestScreenRecordingPermission = requestScreenRecordingPermission
@@ -3,17 +3,77 @@
 
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+#[cfg(target_os = "macos")]
+use napi::{JsFunction, JsObject};
+#[cfg(target_os = "macos")]
 use std::sync::Arc;
-use tokio::sync::Mutex;
+#[cfg(target_os = "macos")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(target_os = "macos")]
+use tokio::sync::{Mutex, watch};
 
+// Declares the crate's println!, so it comes before every module that logs
+#[macro_use]
+mod logging;
+
+#[cfg(target_os = "macos")]
 mod screencapturekit;
 
+// Same napi surface for other platforms, failing at runtime
+// Free functions are only reached through napi registration, which the test build doesn't see
+#[cfg(not(target_os = "macos"))]
+#[allow(dead_code)]
+mod stub;
+
+#[cfg(target_os = "macos")]
 use screencapturekit::{
     AsyncContentManager, 
     ShareableContent, 
     RecordingManager,
-    PermissionManager
+    PermissionManager,
+    Diagnostics
+};
+#[cfg(target_os = "macos")]
+use screencapturekit::audio_monitor::AudioMonitor;
+#[cfg(target_os = "macos")]
+use screencapturekit::device_capture::DeviceCapture;
+#[cfg(target_os = "macos")]
+use screencapturekit::capabilities::CapabilityProbe;
+#[cfg(target_os = "macos")]
+use screencapturekit::content::{AppFilter, SlowRetrievalListener};
+#[cfg(target_os = "macos")]
+use screencapturekit::disk_space::DiskSpaceEvent;
+#[cfg(target_os = "macos")]
+use screencapturekit::delegate::StreamStopEvent;
+#[cfg(target_os = "macos")]
+use screencapturekit::resolution_change::NewSegmentEvent;
+#[cfg(target_os = "macos")]
+use screencapturekit::frame_stream::{RawFrame, FrameFormat, ImageFormat, DEFAULT_FRAME_BATCH_SIZE, MAX_FRAME_BATCH_SIZE, MAX_PENDING_FRAME_BATCHES};
+#[cfg(target_os = "macos")]
+use screencapturekit::picker::{self, PickedContent, PickerOptions};
+#[cfg(target_os = "macos")]
+use screencapturekit::progress::ProgressEvent;
+#[cfg(target_os = "macos")]
+use screencapturekit::segments::{concatenate_segments as concatenate_segment_files, SegmentInfo};
+#[cfg(target_os = "macos")]
+use screencapturekit::transcription::{
+    TranscriptionConfig, TranscriptionJob as TranscriptionJobState, TranscriptionJobListener, TranscriptionJobs, TranscriptionManager,
+};
+#[cfg(target_os = "macos")]
+use screencapturekit::trigger::{
+    TriggerAction, TriggerCondition, TriggerState, TriggerWatch, DEFAULT_TRIGGER_POLL_INTERVAL_MS, MIN_TRIGGER_POLL_INTERVAL_MS,
 };
+#[cfg(target_os = "macos")]
+use screencapturekit::spaces;
+#[cfg(target_os = "macos")]
+use screencapturekit::source_watcher::{SourceWatch, DEFAULT_SOURCE_POLL_INTERVAL_MS, MIN_SOURCE_POLL_INTERVAL_MS};
+#[cfg(target_os = "macos")]
+use screencapturekit::transcode::{transcode_file, TranscodeSettings};
+#[cfg(target_os = "macos")]
+use screencapturekit::types::{CaptureTarget, GlobalRect, SCError};
 
 #[napi(object)]
 pub struct ScreenSource {
@@ -22,6 +82,120 @@ pub struct ScreenSource {
     pub width: u32,
     pub height: u32,
     pub is_display: bool,
+    /// Sidecar, AirPlay or other software display (always false for windows)
+    pub is_virtual: bool,
+    /// Display that mirrors another one. Each mirror set is listed as its primary display
+    /// (not flagged) plus one flagged entry per mirroring display; skip flagged entries to list
+    /// each distinct picture once (always false for windows)
+    pub is_mirror: bool,
+    /// Window that's minimized, in a hidden app or on another Space, so recording it shows
+    /// nothing until it's back on screen (always false for displays)
+    pub is_minimized: bool,
+    /// Window that covers a whole display, as a fullscreen window does (always false for displays)
+    pub is_fullscreen: bool,
+    /// The active window of its app; needs macOS 13.1 and is always false before (always false
+    /// for displays)
+    pub is_active: bool,
+    /// The display's current EDR headroom: how far above SDR white (1.0) it can show HDR
+    /// content right now. Reported only: recordings, 10-bit l10r included, aren't tone-mapped.
+    /// 1.0 on SDR displays; null when unknown and for windows. AppKit gives it on the main
+    /// thread, so it's unknown when that thread isn't running its run loop, as in plain Node
+    pub edr_headroom: Option<f64>,
+}
+
+/// A Space (virtual desktop) of a display, as listed by getSpaces
+#[napi(object)]
+pub struct SpaceInfo {
+    /// Window server id, passed to startRecordingSpace
+    pub id: i64,
+    /// Stable identifier; empty for a display's first desktop
+    pub uuid: String,
+    /// Display the Space belongs to; null when it isn't connected
+    pub display_id: Option<u32>,
+    /// Position in Mission Control on its display, counting from 1
+    pub index: u32,
+    /// The Space showing on its display now, the only one that can be captured
+    pub is_current: bool,
+    /// The Space of a full-screen app
+    pub is_fullscreen: bool,
+}
+
+/// Rectangle in global display points: the main display's top-left corner is 0,0 and other
+/// displays sit around it as arranged in System Settings
+#[napi(object)]
+pub struct CaptureRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Condition for `armTrigger`: set exactly one of frontmostApp or windowTitle. Apps are bundle
+/// ids or app names, matched case-insensitively
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct RecordingTriggerCondition {
+    /// Met while this app owns the frontmost window
+    pub frontmost_app: Option<String>,
+    /// Met while an on-screen window's title contains this text
+    pub window_title: Option<String>,
+    /// Only count windowTitle matches in this app's windows
+    pub window_app: Option<String>,
+}
+
+/// When an armed trigger starts and stops its recording
+#[napi(object)]
+pub struct RecordingTrigger {
+    pub start: RecordingTriggerCondition,
+    /// Default: stop once the start condition no longer holds
+    pub stop: Option<RecordingTriggerCondition>,
+    /// How often the conditions are checked (default 500, minimum 100)
+    pub interval_ms: Option<u32>,
+}
+
+/// Passed to the trigger callback
+#[napi(object)]
+pub struct TriggerEvent {
+    /// "started", "stopped" or "failed"
+    pub action: String,
+    pub output_path: String,
+    /// Why starting or stopping failed
+    pub error: Option<String>,
+}
+
+/// Which apps' windows the source listing includes. Entries are bundle ids or app names,
+/// matched case-insensitively
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SourceFilter {
+    /// Only list windows of these apps
+    pub include_apps: Option<Vec<String>>,
+    /// Never list windows of these apps
+    pub exclude_apps: Option<Vec<String>>,
+    /// Hide the Dock, menu bar, Control Center and other system windows (default true); apps
+    /// in includeApps are listed either way
+    pub exclude_system_apps: Option<bool>,
+}
+
+/// What the system content picker offers
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SystemPickerOptions {
+    /// Offer single windows (default true)
+    pub allow_windows: Option<bool>,
+    /// Offer whole displays (default true)
+    pub allow_displays: Option<bool>,
+    /// Bundle ids of apps whose windows the picker leaves out
+    pub exclude_apps: Option<Vec<String>>,
+}
+
+/// The source chosen in the system content picker
+#[napi(object)]
+pub struct PickedSource {
+    /// ScreenSource id to pass to startRecording; missing before macOS 15.2, where the picker
+    /// doesn't name what it captures
+    pub id: Option<String>,
+    pub is_display: bool,
 }
 
 #[napi(object)]
@@ -31,31 +205,629 @@ pub struct AudioDevice {
     pub device_type: String,
 }
 
-#[derive(Clone)]
+/// A camera usable for the webcam overlay
+#[napi(object)]
+pub struct CameraDevice {
+    pub id: String,
+    pub name: String,
+}
+
+/// An iPhone or iPad that can be recorded with startDeviceCapture
+#[napi(object)]
+pub struct CaptureDevice {
+    pub id: String,
+    pub name: String,
+    /// Hardware model, e.g. "iPhone15,2"
+    pub model: String,
+}
+
+#[derive(Clone, Default)]
 #[napi(object)]
 pub struct RecordingConfiguration {
+    /// Output size (default 1920x1080); odd values are rounded down to even unless strictDimensions
     pub width: Option<u32>,
     pub height: Option<u32>,
+    /// Unit of width/height: "pixels" (default) or "points", converted with the captured
+    /// display's scale factor so 1440 points on a Retina display records 2880 pixels
+    pub dimension_unit: Option<String>,
     pub fps: Option<u32>,
     pub show_cursor: Option<bool>,
+    /// Record system audio. Starting fails rather than recording video only when the audio
+    /// writer can't be set up
     pub capture_audio: Option<bool>,
+    /// Microphone to capture; requires macOS 15 (see Capabilities.features)
     pub audio_device_id: Option<String>,
+    /// Where the video is written. The container follows an .mp4/.m4v (MPEG-4) or .mov
+    /// (QuickTime) extension; any other extension, or none, is replaced with the container's
+    /// own (.mp4, or .mov for "hevc_alpha"), and stopRecording returns the path actually used
     pub output_path: String,
+    /// Stream the recording as fragmented MP4 to this open file descriptor (e.g. a pipe to
+    /// another process) instead of writing outputPath, which must still end in .mp4 or .m4v.
+    /// The stream is video only: captureAudio, replayBufferSeconds, emitFrameIndex and
+    /// resolutionChangeBehavior "segment" need a file and are refused
+    pub output_fd: Option<i32>,
+    /// Like outputFd, for the named pipe (FIFO) at this path; starting fails unless a reader has
+    /// it open
+    pub output_pipe: Option<String>,
+    /// Capture pixel format, in any case: "bgra" (default), "420v" or "l10r" (10-bit, requires
+    /// videoCodec "hevc")
     pub pixel_format: Option<String>,
+    /// Colour space frames are captured in and the file is tagged with (BT.709 YCbCr matrix
+    /// throughout), in any case: "bt709" (default; BT.709 primaries and transfer), "srgb" (BT.709
+    /// primaries, sRGB transfer), "p3" (P3 D65 primaries, sRGB transfer) or "linear" (BT.709
+    /// primaries, linear transfer). sRGB and linear transfer tags need macOS 15; older systems
+    /// tag those files with the BT.709 transfer
     pub color_space: Option<String>,
+    /// Presenter overlay privacy alert: "system" (default), "never" or "always" (macOS 14.2+)
+    pub presenter_overlay_privacy_alert: Option<String>,
+    /// Closed-GOP output without frame reordering for editing workflows (larger files, off by default)
+    pub editing_friendly: Option<bool>,
+    /// What to do when stopped before any frame was captured: "error" (default) or "blackFrame"
+    pub empty_recording_behavior: Option<String>,
+    /// Leave this process's own windows out of display captures (no effect on window captures)
+    pub exclude_self: Option<bool>,
+    /// Metadata tags embedded in the output; "title", "artist", "creator", "comment" and
+    /// "copyright" map to common keys, anything else is written as QuickTime metadata under its
+    /// own name
+    pub metadata: Option<HashMap<String, String>>,
+    /// Instant replay: keep only the last N seconds of video in memory (outputPath is not
+    /// written); call `saveReplay` to write the window to a file. Video only: not with captureAudio
+    pub replay_buffer_seconds: Option<u32>,
+    /// Video codec: "h264" (default), "hevc" or "hevc_alpha" (HEVC with transparency; window
+    /// targets only, requires pixelFormat "bgra" and a .mov outputPath)
+    pub video_codec: Option<String>,
+    /// Pin the encoder profile and level, e.g. "H264_Main_AutoLevel" or "H264_High_4_2"; unset
+    /// lets the encoder choose (HEVC Main10 for 10-bit capture). H.264 accepts H264_Baseline_,
+    /// H264_Main_ and H264_High_ followed by 3_0-3_2, 4_0-4_2, 5_0-5_2 or AutoLevel, plus
+    /// H264_ConstrainedBaseline_AutoLevel and H264_ConstrainedHigh_AutoLevel; HEVC accepts
+    /// HEVC_Main_AutoLevel and HEVC_Main10_AutoLevel (required with "l10r"). Must match videoCodec
+    pub profile_level: Option<String>,
+    /// Average video bitrate in kbps (default: the encoder's choice for the size); can't be
+    /// combined with quality
+    pub video_bitrate_kbps: Option<u32>,
+    /// Size budget for the file in bytes, e.g. 500000000 for an upload limit: the video
+    /// bitrate is chosen so maxDurationSeconds of recording fit, leaving room for audio when
+    /// captureAudio is on. Requires maxDurationSeconds; can't be combined with
+    /// videoBitrateKbps or quality. The chosen bitrate is in getEffectiveSettings
+    pub target_file_size_bytes: Option<f64>,
+    /// Constant-quality encoding, 0.0 (smallest) to 1.0 (best), instead of a target bitrate:
+    /// static content then costs almost nothing. Can't be combined with videoBitrateKbps
+    pub quality: Option<f64>,
+    /// How many times to recreate the writer when it fails to start (default 2, max 10)
+    pub writer_start_retries: Option<u32>,
+    /// Hold frames while the encoder is busy instead of dropping them (default false), for short
+    /// clips where no frame may be lost. Up to 8 uncompressed frames wait in memory and capture
+    /// then stalls for up to 500ms, which adds latency and makes ScreenCaptureKit skip frames if
+    /// the encoder never catches up; live capture should keep dropping
+    pub block_on_backpressure: Option<bool>,
+    /// How much audio arriving before the first video frame is held for it, in ms (default
+    /// 1000, max 10000). Only video starts the file, so early audio can't offset the tracks;
+    /// what overlaps the first frame is kept and anything older is dropped
+    pub early_audio_buffer_ms: Option<u32>,
+    /// How the source is fitted to width/height when aspect ratios differ: "fit" (default,
+    /// letterbox/pillarbox in black), "fill" (crop the centre of the source, macOS 14+) or
+    /// "stretch" (distort to the output size)
+    pub scaling_mode: Option<String>,
+    /// Where scaling happens: "fast" (default, during capture) or "high" (capture at native size
+    /// and scale in the encoder, macOS 14+; more GPU/memory bandwidth)
+    pub scaling_quality: Option<String>,
+    /// Draw recently pressed keys onto the video (requires accessibility permission and the
+    /// "bgra" pixel format); recording fails to start when permission is missing
+    pub keystroke_overlay: Option<bool>,
+    /// Draw a camera as a picture-in-picture over the video (requires the "bgra" pixel format
+    /// and camera access; the host app needs NSCameraUsageDescription). When the camera is
+    /// unavailable the screen is recorded without it and a warning is logged
+    pub webcam: Option<WebcamConfiguration>,
+    /// Composite a static image such as a logo into a corner of every frame (off by default;
+    /// requires the "bgra" pixel format). The image is decoded once; each frame then costs an
+    /// alpha blend over the watermark's pixels, so CPU use grows with its size
+    pub watermark: Option<WatermarkConfiguration>,
+    /// Frame delivery: "fixed_fps" (default, up to fps frames per second) or "on_change" (a frame
+    /// only when the screen changes, each held until the next). On-change capture costs almost
+    /// no CPU or encoder time while the screen is static and produces much smaller files for
+    /// slides, code walkthroughs and other mostly-still content
+    pub capture_mode: Option<String>,
+    /// Frame rate of the recorded file when it should differ from the capture fps, e.g.
+    /// capture at 60 and write 30. Frames are dropped or repeated by capture time so motion
+    /// keeps its speed; a frame after a gap is repeated for at most a second (1-120; not with
+    /// "on_change" capture)
+    pub output_fps: Option<u32>,
+    /// Capture API: "screencapturekit" (default), "cgdisplaystream" (the older API, for
+    /// systems where ScreenCaptureKit delivers no frames; video only) or "auto"
+    /// (ScreenCaptureKit, switching to CGDisplayStream when no frame arrives within 2s of
+    /// starting; audio is lost then). Both alternatives need a display target
+    pub capture_backend: Option<String>,
+    /// Call the disk space callback when free space on the output volume drops below this
+    /// many MB (default 2048)
+    pub low_disk_space_warning_mb: Option<u32>,
+    /// Refuse to start, or stop and finalize the recording, when free space on the output
+    /// volume drops below this many MB (default 512)
+    pub min_free_disk_space_mb: Option<u32>,
+    /// Recording length used for the pre-start free space check (default maxDurationSeconds,
+    /// or 60)
+    pub expected_duration_seconds: Option<u32>,
+    /// Gain in dB applied to system audio before it shares the audio track with the
+    /// microphone (default 0, clamped to -60..20; boosted peaks are soft-limited, not clipped)
+    pub system_audio_gain: Option<f64>,
+    /// Gain in dB applied to microphone audio (default 0, clamped to -60..20)
+    pub microphone_gain: Option<f64>,
+    /// Write system audio and the microphone as two audio tracks, system first, so they can be
+    /// balanced in an editor. Default false: both sources' buffers are appended to one track as
+    /// they arrive rather than mixed together. Requires captureAudio and audioDeviceId;
+    /// IntegratedRecordingManager rejects it
+    pub separate_audio_tracks: Option<bool>,
+    /// Record system audio from this output device (a UID from getAvailableAudioDevices with
+    /// deviceType "speaker", e.g. an aggregate or BlackHole device) instead of the system mix,
+    /// through a Core Audio process tap. Requires captureAudio and macOS 14.2
+    pub audio_output_device_id: Option<String>,
+    /// Output aspect ratio as "<width>:<height>" (e.g. "16:9"); the missing dimension is
+    /// computed from height (default 1080) or width and rounded to even. The source is fitted
+    /// according to scalingMode. Can't be combined with both width and height
+    pub aspect_ratio: Option<String>,
+    /// Stop and finalize the recording after this many seconds of video; progress callbacks
+    /// then report a 0-100 `progress`
+    pub max_duration_seconds: Option<u32>,
+    /// How often the progress callback fires while recording, in ms, whatever the frame rate
+    /// (default 500)
+    pub progress_interval_ms: Option<u32>,
+    /// Stop and finalize the recording once the screen has stayed unchanged for this many
+    /// seconds (paused time doesn't count); the error callback then gets "IdleStopped".
+    /// Changes confined to a couple of 16px tiles, like a blinking caret, are ignored, while
+    /// slow content such as a progress bar still counts. Not supported for region recordings
+    pub idle_stop_seconds: Option<u32>,
+    /// Count down this many seconds (at most 60) before anything is recorded, so the file
+    /// doesn't open on the click that started it; the stream runs meanwhile and progress
+    /// callbacks report `countdown` once a second
+    pub start_delay_seconds: Option<u32>,
+    /// How long stopRecording waits for ScreenCaptureKit to confirm the stream stopped, in ms
+    /// (default 5000). ScreenCaptureKit occasionally never confirms; the file is then
+    /// finalized anyway, a warning is logged and collectDiagnostics reports `stopTimedOut`
+    pub stop_timeout_ms: Option<u32>,
+    /// Bundle ids of running apps whose audio is left out of a display recording while their
+    /// windows stay in the video (macOS 15+; older systems record all audio). Every id must
+    /// belong to a running application
+    pub exclude_audio_app_bundle_ids: Option<Vec<String>>,
+    /// When screen recording permission is revoked mid-recording the capture is finalized and
+    /// the error callback gets a "PermissionRevoked" error; "finalize" (default) keeps the
+    /// partial file, "discard" deletes it
+    pub permission_revoked_behavior: Option<String>,
+    /// What happens when the captured frames change size mid-recording, e.g. after a display
+    /// resolution change: "scale" (default) scales them back to the recording's size;
+    /// "segment" finalizes the file and continues in "<name>_part2.mp4" (and so on) at the new
+    /// size, reporting each switch to the error callback as "NewSegment". Audio stays in one file
+    pub resolution_change_behavior: Option<String>,
+    /// Record the cursor's position and image whenever they change to "<outputPath
+    /// without extension>.cursor.json", timed against the video (paused time excluded) and
+    /// relative to the captured area; see `getCursorTrackPath`. Combine with showCursor: false
+    /// to redraw the cursor in post
+    pub capture_cursor_track: Option<bool>,
+    /// After the recording is finalized, write "<outputPath without extension>.frames.json"
+    /// listing each video frame's presentation time, keyframe flag and byte range in the file
+    pub emit_frame_index: Option<bool>,
+    /// After the recording is finalized, write "<outputPath without extension>.chapters.json"
+    /// with a chapter starting at each scene cut: a frame that changed much of the picture,
+    /// such as switching apps or slides. Cuts less than 10 seconds apart are merged. Not
+    /// supported for region recordings
+    pub detect_chapters: Option<bool>,
+    /// How small a change still starts a chapter, from 0 (only full-screen changes) to 1
+    /// (default 0.5, about half the frame)
+    pub chapter_sensitivity: Option<f64>,
+    /// Debugging aid: check every video sample's pixel format, size and presentation time
+    /// against the configured stream and log a warning when a mismatch starts, to trace
+    /// green or wrongly sized output back to the frames ScreenCaptureKit delivered
+    pub validate_buffers: Option<bool>,
+    /// Record the display the cursor is on when the recording starts instead of the given
+    /// display (default false). The display is picked once: the recording stays on it when the
+    /// cursor moves to another. Requires a display target
+    pub follow_active_display: Option<bool>,
+    /// Leave stretches where secure input (e.g. a focused password field) is active out of
+    /// the video, since macOS may blank the capture then; they're listed with the timeline's
+    /// pauses. Secure input is reported to the error callback either way. Can't be combined
+    /// with captureCursorTrack
+    pub pause_on_secure_input: Option<bool>,
+    /// Reject odd width/height instead of rounding them down to even (the encoder needs even
+    /// dimensions)
+    pub strict_dimensions: Option<bool>,
+    /// Color behind areas the captured content doesn't cover, such as transparent regions of a
+    /// recorded window, as "#RRGGBB" or "#RRGGBBAA" (default black, or transparent with
+    /// "hevc_alpha"). Alpha reaches BGRA frames, but the h264 and hevc outputs have no alpha
+    /// channel, so translucent colors are flattened onto black in the file
+    pub background_color: Option<String>,
+    /// Advanced: pool hints for the writer's pixel buffer adaptor; unset keeps the minimal
+    /// pixel-format-only attributes
+    pub pixel_buffer_pool: Option<PixelBufferPoolConfiguration>,
+}
+
+/// Pixel buffer pool hints for the writer; preallocating buffers of the final size and format
+/// avoids allocation churn at high resolutions
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct PixelBufferPoolConfiguration {
+    /// Buffers the pool keeps allocated (1-64; default: allocated on demand)
+    pub min_buffer_count: Option<u32>,
+    /// Buffer size (default the output width/height)
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// "bgra", "420v" or "l10r" (default pixelFormat)
+    pub pixel_format: Option<String>,
+}
+
+/// Picture-in-picture camera overlay
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct WebcamConfiguration {
+    /// A camera id from getAvailableCameras (default: the system's default camera)
+    pub device_id: Option<String>,
+    /// "topLeft", "topRight", "bottomLeft" or "bottomRight" (default)
+    pub corner: Option<String>,
+    /// Overlay width as a fraction of the video width (0.05-0.5, default 0.25); the height
+    /// follows the camera's aspect ratio
+    pub size: Option<f64>,
+}
+
+/// Encoded image bytes passed as a Buffer, copied out of it so configurations can cross
+/// threads
+#[derive(Clone, Default)]
+pub struct ImageBytes(pub Vec<u8>);
+
+impl TypeName for ImageBytes {
+    fn type_name() -> &'static str {
+        "Buffer"
+    }
+
+    fn value_type() -> ValueType {
+        ValueType::Object
+    }
+}
+
+impl FromNapiValue for ImageBytes {
+    unsafe fn from_napi_value(env: sys::napi_env, value: sys::napi_value) -> Result<Self> {
+        Ok(Self(Buffer::from_napi_value(env, value)?.to_vec()))
+    }
+}
+
+impl ToNapiValue for ImageBytes {
+    unsafe fn to_napi_value(env: sys::napi_env, value: Self) -> Result<sys::napi_value> {
+        Buffer::to_napi_value(env, Buffer::from(value.0))
+    }
+}
+
+/// Static image drawn over the video
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct WatermarkConfiguration {
+    /// Image file to draw (PNG, JPEG or anything else NSImage reads); set this or `image`
+    pub path: Option<String>,
+    /// Encoded image bytes instead of a file
+    #[napi(ts_type = "Buffer")]
+    pub image: Option<ImageBytes>,
+    /// "topLeft", "topRight", "bottomLeft" or "bottomRight" (default)
+    pub corner: Option<String>,
+    /// 0.0-1.0, applied on top of the image's own transparency (default 1.0)
+    pub opacity: Option<f64>,
+    /// Width as a fraction of the video width (0.01-1.0); the height follows the image's
+    /// aspect ratio. Default: the image's own pixel size
+    pub size: Option<f64>,
+    /// Gap to the video edges in pixels (default 2% of the video width)
+    pub margin: Option<u32>,
+}
+
+/// System capture indicator state for compliance logging
+#[napi(object)]
+pub struct CaptureIndicatorState {
+    /// Whether the OS is showing the screen recording indicator for this process
+    pub indicator_visible: bool,
+    pub privacy_alert_setting: String,
+    pub privacy_alert_supported: bool,
+}
+
+/// Rectangle in points
+#[napi(object)]
+pub struct FrameRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Settings the current recording actually uses after defaults and derived sizes are applied
+#[napi(object)]
+pub struct EffectiveSettings {
+    /// Output size in pixels
+    pub width: u32,
+    pub height: u32,
+    /// The output size in points of the captured display, and the unit width/height were
+    /// configured in
+    pub width_points: f64,
+    pub height_points: f64,
+    pub dimension_unit: String,
+    pub fps: u32,
+    pub scaling_mode: String,
+    /// Average video bitrate in kbps, from videoBitrateKbps or targetFileSizeBytes; null when
+    /// the encoder picks it
+    pub video_bitrate_kbps: Option<u32>,
+    /// The part of the source ScreenCaptureKit was asked to capture (the fill crop, or the
+    /// region); null when it captures the whole display or window
+    pub source_rect: Option<FrameRect>,
+    /// Where ScreenCaptureKit placed the content in its frames, in points; a rect smaller
+    /// than the output means letterboxing. Null until the first frame arrives
+    pub content_rect: Option<FrameRect>,
+    /// Scale ScreenCaptureKit applied to the content to fit the frame
+    pub content_scale: Option<f64>,
+    /// Pixels per point of the captured display
+    pub scale_factor: Option<f64>,
+    /// Whether audio and video are being recorded, as set with setAudioEnabled/setVideoEnabled
+    pub audio_enabled: bool,
+    pub video_enabled: bool,
+}
+
+/// What this machine can record; values match the RecordingConfiguration strings
+#[napi(object)]
+pub struct Capabilities {
+    pub video_codecs: Vec<String>,
+    /// Codecs with a hardware encoder (the rest fall back to software)
+    pub hardware_video_codecs: Vec<String>,
+    pub audio_codecs: Vec<String>,
+    pub containers: Vec<String>,
+    pub pixel_formats: Vec<String>,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub features: Features,
+}
+
+/// Which optional features the running macOS has; features it lacks fail with
+/// "... requires macOS X or later"
+#[napi(object)]
+pub struct Features {
+    /// The running macOS as "major.minor"
+    pub macos_version: String,
+    /// Microphone capture with audioDeviceId (macOS 15)
+    pub supports_microphone_capture: bool,
+    /// Excluding an application's audio while showing its windows (macOS 15)
+    pub supports_app_audio_exclusion: bool,
+    /// presentSystemPicker (macOS 14)
+    pub supports_system_picker: bool,
+    /// presentSystemPicker reporting which windows and displays were picked (macOS 15.2)
+    pub supports_picker_source_ids: bool,
+    /// presenterOverlayPrivacyAlert (macOS 14.2)
+    pub supports_presenter_overlay_privacy_alert: bool,
+    /// audioOutputDeviceId (macOS 14.2)
+    pub supports_output_device_tap: bool,
+    /// ScreenSource.isActive for windows (macOS 13.1)
+    pub supports_window_active_state: bool,
+}
+
+/// Passed to the disk space callback
+#[napi(object)]
+pub struct DiskSpaceWarning {
+    pub output_path: String,
+    pub available_mb: f64,
+    /// The recording was stopped because free space fell below minFreeDiskSpaceMb
+    pub auto_stopped: bool,
+}
+
+/// Passed to the error callback when ScreenCaptureKit ends a recording on its own
+#[napi(object)]
+pub struct RecordingError {
+    /// "PermissionRevoked" when screen recording permission was taken away, "CaptureBlocked"
+    /// when the screen locked or the session switched away, otherwise "StreamStopped".
+    /// Recordings keep running after "NoFramesDelivered" (no frames within 2 seconds of
+    /// starting), "SecureInputActive" (the capture may be blank, see pauseOnSecureInput) and
+    /// "SecureInputEnded". "IdleStopped" reports a recording stopped by idleStopSeconds.
+    /// "NewSegment" reports, with its finished file, a segment ended by a frame size change
+    /// (resolutionChangeBehavior "segment"); the recording continues in the file named in
+    /// the message
+    pub code: String,
+    pub message: String,
+    /// The finalized partial recording; null when nothing playable was written or it was discarded
+    pub output_path: Option<String>,
+}
+
+/// The last failure to start or finish a recording, from `getLastError`
+#[napi(object)]
+pub struct LastRecordingError {
+    /// Where it failed: "content-filter", "stream-create", "start" or "finalize"
+    pub stage: String,
+    /// Status of the error the call rejected with, e.g. "GenericFailure"
+    pub code: String,
+    pub message: String,
+    /// The NSError behind it with its domain and code, when there was one
+    pub underlying_error: Option<String>,
+    /// When it failed, in ms since the Unix epoch
+    pub failed_at: f64,
+}
+
+/// Passed to the progress callback every progressIntervalMs (default 500) while recording
+#[napi(object)]
+pub struct RecordingProgress {
+    pub frames: i64,
+    /// Recorded video time (frames / fps; wall-clock time for on_change capture)
+    pub elapsed_seconds: f64,
+    /// 0-100 of maxDurationSeconds, null without a maximum duration
+    pub progress: Option<f64>,
+    /// Seconds left of startDelaySeconds (3, 2, 1); null once recording has begun
+    pub countdown: Option<u32>,
+}
+
+/// Returned by RecordingHandle.getStats
+#[napi(object)]
+pub struct RecordingHandleStats {
+    pub output_path: String,
+    pub is_recording: bool,
+    pub is_paused: bool,
+    pub video_frames: i64,
+    pub audio_samples: i64,
+    pub duration_seconds: f64,
+    pub average_fps: f64,
+    /// Why finalizing failed, once stopped
+    pub error: Option<String>,
+}
+
+/// A recorded frame passed to the frames callback
+#[napi(object)]
+pub struct VideoFrame {
+    /// Pixels in `format`, `bytesPerRow` per row; "bgra" rows may be padded past width * 4,
+    /// "rgba" and "rgb" are packed, "jpeg" is a whole JPEG file
+    pub data: Buffer,
+    /// "bgra", "rgba", "rgb" or "jpeg", as requested in `onFrames`
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    /// 0 for "jpeg"
+    pub bytes_per_row: u32,
+    /// Presentation time in seconds on the capture clock
+    pub timestamp: f64,
+}
+
+/// Output options for `transcode`; values match the RecordingConfiguration strings
+#[napi(object)]
+pub struct TranscodeConfiguration {
+    /// Output size; with only one set the other follows the source aspect ratio rounded to
+    /// even, with neither the source size is kept
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Video codec: "h264" (default) or "hevc"
+    pub video_codec: Option<String>,
+    /// Average video bitrate in kbps (default: the encoder's choice for the size)
+    pub video_bitrate_kbps: Option<u32>,
+    /// Constant quality 0.0-1.0 instead of a bitrate; can't be combined with videoBitrateKbps
+    pub quality: Option<f64>,
+    /// How the source is fitted when the aspect ratio changes: "fit" (default), "fill" or "stretch"
+    pub scaling_mode: Option<String>,
+    /// Closed-GOP output without frame reordering, as for recordings
+    pub editing_friendly: Option<bool>,
+    /// Re-encode the first audio track as AAC (default true)
+    pub include_audio: Option<bool>,
+    /// Decoder reading the source: "auto" (default; hardware when available), "hardware" or
+    /// "software" for Macs whose hardware decoder fails or produces green frames. A failed
+    /// decode is retried with the software decoder
+    pub decoder_preference: Option<String>,
+}
+
+/// Passed to the transcode progress callback
+#[napi(object)]
+pub struct TranscodeProgress {
+    pub processed_seconds: f64,
+    pub duration_seconds: f64,
+    /// 0-100
+    pub progress: f64,
+    /// "hardware" or "software", the decoder reading the source
+    pub decoder: String,
+}
+
+/// Options for `startTranscriptionJob`
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct TranscriptionJobConfiguration {
+    /// "local" (default, the Whisper CLI) or "openaiWhisper"
+    pub service: Option<String>,
+    /// Required for "openaiWhisper"
+    pub api_key: Option<String>,
+    /// Spoken language code (default "en")
+    pub language: Option<String>,
+    /// Transcript saved next to the input: "text" (default), "srt", "vtt" or "json"
+    pub output_format: Option<String>,
+}
+
+/// State of a background transcription, passed to the job callback on every change
+#[napi(object)]
+pub struct TranscriptionJob {
+    pub id: u32,
+    pub input_path: String,
+    /// "queued", "extractingAudio", "transcribing", "saving", "completed" or "failed"
+    pub status: String,
+    /// Once completed
+    pub text: Option<String>,
+    pub language: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub transcript_path: Option<String>,
+    /// Once failed
+    pub error: Option<String>,
+}
+
+/// When a recording started and when it was paused; times are ms since the Unix epoch
+#[napi(object)]
+pub struct RecordingTimeline {
+    pub started_at: f64,
+    /// Completed pauses in order; a pause still open at stop ends at the stop time
+    pub paused_intervals: Vec<PausedInterval>,
+}
+
+#[napi(object)]
+pub struct PausedInterval {
+    pub from: f64,
+    pub to: f64,
+}
+
+/// What verifyRecording found in a finished recording
+#[napi(object)]
+pub struct RecordingVerification {
+    pub duration_seconds: f64,
+    pub has_video: bool,
+    /// An audio track, in the movie or the separate audio file written next to it
+    pub has_audio: bool,
+    /// Whether the audio gets louder than -60 dBFS in its first five minutes; false for a
+    /// silent track, e.g. when nothing played or the wrong device was captured
+    pub has_audible_audio: bool,
+    /// Colour primaries the video is tagged with: "P3_D65" for colorSpace "p3", otherwise
+    /// "ITU_R_709_2"; null when untagged
+    pub color_primaries: Option<String>,
+    /// Problems worth telling the user about, such as a silent audio track
+    pub warnings: Vec<String>,
+}
+
+/// Input level reported by the audio monitor, linear 0.0-1.0
+#[napi(object)]
+pub struct AudioLevels {
+    pub rms: f64,
+    pub peak: f64,
 }
 
+#[cfg(target_os = "macos")]
 /// Complete async ScreenCaptureKit recorder with full functionality
 #[napi]
 pub struct ScreenCaptureKitRecorder {
     recording_manager: Arc<Mutex<RecordingManager>>,
     content: Arc<Mutex<Option<ShareableContent>>>,
+    content_cancel: watch::Sender<()>,
+    audio_monitor: std::sync::Mutex<Option<AudioMonitor>>,
+    disk_space_callback: Arc<std::sync::Mutex<Option<ThreadsafeFunction<DiskSpaceWarning, ErrorStrategy::Fatal>>>>,
+    progress_callback: Arc<std::sync::Mutex<Option<ThreadsafeFunction<RecordingProgress, ErrorStrategy::Fatal>>>>,
+    error_callback: Arc<std::sync::Mutex<Option<ThreadsafeFunction<RecordingError, ErrorStrategy::Fatal>>>>,
+    frame_callback: Arc<std::sync::Mutex<Option<FrameCallback>>>,
+    content_slow_callback: std::sync::Mutex<Option<ThreadsafeFunction<u32, ErrorStrategy::Fatal>>>,
+    trigger: std::sync::Mutex<Option<TriggerWatch>>,
+    device_capture: std::sync::Mutex<Option<DeviceCapture>>,
 }
 
+#[cfg(target_os = "macos")]
+/// The parts of the recorder a recording start needs, so an armed trigger can start one
+#[derive(Clone)]
+struct RecorderHandle {
+    recording_manager: Arc<Mutex<RecordingManager>>,
+    disk_space_callback: Arc<std::sync::Mutex<Option<ThreadsafeFunction<DiskSpaceWarning, ErrorStrategy::Fatal>>>>,
+    progress_callback: Arc<std::sync::Mutex<Option<ThreadsafeFunction<RecordingProgress, ErrorStrategy::Fatal>>>>,
+    error_callback: Arc<std::sync::Mutex<Option<ThreadsafeFunction<RecordingError, ErrorStrategy::Fatal>>>>,
+    frame_callback: Arc<std::sync::Mutex<Option<FrameCallback>>>,
+}
+
+#[cfg(target_os = "macos")]
+/// Frames callback with its batch size and the number of batches JS hasn't received yet
+#[derive(Clone)]
+struct FrameCallback {
+    callback: ThreadsafeFunction<Vec<RawFrame>, ErrorStrategy::Fatal>,
+    batch_size: u32,
+    format: FrameFormat,
+    pending_batches: Arc<AtomicUsize>,
+}
+
+#[cfg(target_os = "macos")]
 // Safety: The internal data is protected by Mutex, making it safe to send between threads
 unsafe impl Send for ScreenCaptureKitRecorder {}
+#[cfg(target_os = "macos")]
 unsafe impl Sync for ScreenCaptureKitRecorder {}
 
+#[cfg(target_os = "macos")]
 #[napi]
 impl ScreenCaptureKitRecorder {
     #[napi(constructor)]
@@ -64,19 +836,29 @@ impl ScreenCaptureKitRecorder {
         Ok(Self {
             recording_manager: Arc::new(Mutex::new(RecordingManager::new())),
             content: Arc::new(Mutex::new(None)),
+            content_cancel: watch::channel(()).0,
+            audio_monitor: std::sync::Mutex::new(None),
+            disk_space_callback: Arc::new(std::sync::Mutex::new(None)),
+            progress_callback: Arc::new(std::sync::Mutex::new(None)),
+            error_callback: Arc::new(std::sync::Mutex::new(None)),
+            frame_callback: Arc::new(std::sync::Mutex::new(None)),
+            content_slow_callback: std::sync::Mutex::new(None),
+            trigger: std::sync::Mutex::new(None),
+            device_capture: std::sync::Mutex::new(None),
         })
     }
 
     /// Get available screens using real ScreenCaptureKit async APIs
+    /// Windows are listed too, narrowed by `filter` (system windows are hidden by default)
     #[napi]
-    pub async fn get_available_screens(&self) -> Result<Vec<ScreenSource>> {
+    pub async fn get_available_screens(&self, filter: Option<SourceFilter>) -> Result<Vec<ScreenSource>> {
         println!("📺 Getting screens via complete ScreenCaptureKit async APIs");
         
         // Get shareable content asynchronously
-        let content = AsyncContentManager::get_shareable_content().await?;
+        let content = self.retrieve_content().await?;
         
         // Extract screen sources
-        let sources = content.get_all_sources().await?;
+        let sources = content.get_all_sources(&AppFilter::from_config(filter.as_ref())).await?;
         
         // Store content for later use
         {
@@ -88,23 +870,130 @@ impl ScreenCaptureKitRecorder {
         Ok(sources)
     }
 
+    /// Call `callback` with the milliseconds waited so far when getAvailableScreens or
+    /// getAvailableWindows is still gathering sources after a second, e.g. to show a spinner.
+    /// It's called at most once per call, before that call resolves or times out
+    #[napi]
+    pub fn on_content_retrieval_slow(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(elapsedMs: number) => void")] callback: JsFunction,
+    ) -> Result<()> {
+        let mut on_slow: ThreadsafeFunction<u32, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        on_slow.unref(&env)?;
+        
+        *self.content_slow_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Content retrieval callback lock poisoned"))? = Some(on_slow);
+        Ok(())
+    }
+    
+    /// Shareable content, cancelled by stopAll and reported to onContentRetrievalSlow
+    async fn retrieve_content(&self) -> Result<ShareableContent> {
+        let on_slow = self.content_slow_callback.lock().ok().and_then(|callback| callback.clone())
+            .map(|callback| -> SlowRetrievalListener {
+                Box::new(move |elapsed| {
+                    callback.call(elapsed.as_millis() as u32, ThreadsafeFunctionCallMode::NonBlocking);
+                })
+            });
+        AsyncContentManager::get_shareable_content_with_progress(Some(self.content_cancel.subscribe()), on_slow).await
+    }
+
+    /// Let the user choose a window or display in the system content picker (macOS 14+)
+    /// instead of listing sources; resolves to null when they cancel
+    #[napi]
+    pub async fn present_system_picker(&self, options: Option<SystemPickerOptions>) -> Result<Option<PickedSource>> {
+        let options = options.unwrap_or_default();
+        let options = PickerOptions {
+            windows: options.allow_windows.unwrap_or(true),
+            displays: options.allow_displays.unwrap_or(true),
+            excluded_bundle_ids: options.exclude_apps.unwrap_or_default(),
+        };
+        Ok(picker::present_system_picker(&options).await?.map(|content| match content {
+            PickedContent::Display(id) => PickedSource { id: id.map(|id| format!("display:{}", id)), is_display: true },
+            PickedContent::Window(id) => PickedSource { id: id.map(|id| format!("window:{}", id)), is_display: false },
+        }))
+    }
+
     /// Start recording using complete ScreenCaptureKit async APIs
+    /// `screenId` is a ScreenSource id ("display:<id>" or "window:<id>"); a window recording
+    /// without width/height is sized to the window's full frame, even across displays
     #[napi]
     pub async fn start_recording(
         &self,
-        _screen_id: String,
+        screen_id: String,
         config: RecordingConfiguration,
     ) -> Result<String> {
         println!("🎬 Starting recording via complete ScreenCaptureKit");
         
-        // Initialize recording manager if needed
-        {
-            let mut manager = self.recording_manager.lock().await;
-            manager.initialize().await?;
-            
-            // Start the actual recording
-            manager.start_recording(config).await
+        let target = parse_screen_id(&screen_id)?;
+        self.start_recording_target(target, config).await
+    }
+
+    /// Start recording `screenId` like startRecording, but on a recording manager of its own:
+    /// the returned handle stops, pauses and reports on just this recording, so several can
+    /// run at once. Callbacks registered on the recorder beforehand also apply to it
+    #[napi]
+    pub async fn start_recording_handle(&self, screen_id: String, config: RecordingConfiguration) -> Result<RecordingHandle> {
+        println!("🎬 Starting a separately handled recording");
+        
+        let target = parse_screen_id(&screen_id)?;
+        let recorder = RecorderHandle {
+            recording_manager: Arc::new(Mutex::new(RecordingManager::new())),
+            disk_space_callback: copy_callback(&self.disk_space_callback)?,
+            progress_callback: copy_callback(&self.progress_callback)?,
+            error_callback: copy_callback(&self.error_callback)?,
+            frame_callback: copy_callback(&self.frame_callback)?,
+        };
+        recorder.start_recording(target, config).await?;
+        Ok(RecordingHandle { recorder })
+    }
+
+    /// Record `region` of the desktop in global display points, even where it spans several
+    /// displays; each display's part is captured separately and composited. Without
+    /// width/height the output is the region's size in points. Requires pixelFormat "bgra"
+    #[napi]
+    pub async fn start_recording_region(&self, region: CaptureRegion, config: RecordingConfiguration) -> Result<String> {
+        println!("🎬 Starting region recording via complete ScreenCaptureKit");
+        
+        if region.width == 0 || region.height == 0 {
+            return Err(Error::new(Status::InvalidArg, "Region width and height must be greater than 0"));
+        }
+        let target = CaptureTarget::Region(GlobalRect { x: region.x, y: region.y, width: region.width, height: region.height });
+        self.start_recording_target(target, config).await
+    }
+
+    /// Wire up the registered callbacks and start recording `target`
+    async fn start_recording_target(&self, target: CaptureTarget, config: RecordingConfiguration) -> Result<String> {
+        self.handle().start_recording(target, config).await
+    }
+
+    fn handle(&self) -> RecorderHandle {
+        RecorderHandle {
+            recording_manager: self.recording_manager.clone(),
+            disk_space_callback: self.disk_space_callback.clone(),
+            progress_callback: self.progress_callback.clone(),
+            error_callback: self.error_callback.clone(),
+            frame_callback: self.frame_callback.clone(),
+        }
+    }
+
+    /// Start a recording that continues `previousSegmentPath` after an interruption. Its
+    /// timestamps pick up where that file ends, and videoCodec and the output size must match
+    /// it; join the segments afterwards with `concatenateSegments`
+    #[napi]
+    pub async fn resume_into(
+        &self,
+        previous_segment_path: String,
+        screen_id: String,
+        config: RecordingConfiguration,
+    ) -> Result<String> {
+        if config.output_path == previous_segment_path {
+            return Err(Error::new(Status::InvalidArg, "outputPath must differ from the previous segment"));
         }
+        let segment = SegmentInfo::probe(&previous_segment_path)?;
+        self.recording_manager.lock().await.set_resume_segment(Some(segment));
+        self.start_recording(screen_id, config).await
     }
 
     #[napi]
@@ -115,12 +1004,170 @@ impl ScreenCaptureKitRecorder {
         manager.stop_recording().await
     }
 
+    /// Keep the shareable content and content filter between recordings, so repeated short
+    /// recordings of the same source only create a new stream each time. Recordings with
+    /// excludeSelf or excludeAudioAppBundleIds still refresh the content and their filter
+    #[napi]
+    pub async fn set_keep_warm(&self, keep_warm: bool) {
+        self.recording_manager.lock().await.set_keep_warm(keep_warm);
+    }
+
+    /// Release everything kept between recordings, including the warm content filter, so the
+    /// next recording starts from scratch. Fails while recording
+    #[napi]
+    pub async fn reset(&self) -> Result<()> {
+        self.recording_manager.lock().await.reset()?;
+        *self.content.lock().await = None;
+        Ok(())
+    }
+
     #[napi]
     pub async fn is_recording(&self) -> bool {
         let manager = self.recording_manager.lock().await;
         manager.is_recording()
     }
 
+    /// Whether the current recording received a video frame within the last second, also while
+    /// paused. False when not recording; expected to be false for on_change capture of a static
+    /// screen
+    #[napi]
+    pub async fn is_receiving_frames(&self) -> bool {
+        let manager = self.recording_manager.lock().await;
+        manager.is_receiving_frames()
+    }
+
+    /// Start recording `screenId` with `config` once `trigger.start` is met and stop once its
+    /// stop condition is, reporting both to `callback`. A trigger fires once and replaces any
+    /// armed before; arm again for the next capture
+    #[napi]
+    pub fn arm_trigger(
+        &self,
+        env: Env,
+        trigger: RecordingTrigger,
+        screen_id: String,
+        config: RecordingConfiguration,
+        #[napi(ts_arg_type = "(event: TriggerEvent) => void")] callback: Option<JsFunction>,
+    ) -> Result<()> {
+        let interval_ms = trigger.interval_ms.unwrap_or(DEFAULT_TRIGGER_POLL_INTERVAL_MS);
+        if interval_ms < MIN_TRIGGER_POLL_INTERVAL_MS {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("intervalMs must be at least {}", MIN_TRIGGER_POLL_INTERVAL_MS),
+            ));
+        }
+        let state = TriggerState::new(
+            TriggerCondition::from_config(&trigger.start)?,
+            trigger.stop.as_ref().map(TriggerCondition::from_config).transpose()?,
+        );
+        let target = parse_screen_id(&screen_id)?;
+        
+        let on_event: Option<ThreadsafeFunction<TriggerEvent, ErrorStrategy::Fatal>> = callback
+            .map(|callback| {
+                let mut on_event = callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+                // Don't keep Node alive just because a trigger is armed
+                on_event.unref(&env)?;
+                Ok::<_, Error>(on_event)
+            })
+            .transpose()?;
+        
+        let handle = self.handle();
+        let watch = TriggerWatch::start(state, interval_ms, move |action| {
+            let handle = handle.clone();
+            let config = config.clone();
+            let on_event = on_event.clone();
+            spawn(async move {
+                let output_path = config.output_path.clone();
+                let (name, result) = match action {
+                    TriggerAction::Start => ("started", handle.start_recording(target, config).await),
+                    TriggerAction::Stop => ("stopped", handle.recording_manager.lock().await.stop_recording().await),
+                };
+                if let Err(ref e) = result {
+                    println!("❌ Recording trigger couldn't act: {}", e.reason);
+                }
+                if let Some(callback) = on_event {
+                    callback.call(TriggerEvent {
+                        action: if result.is_ok() { name } else { "failed" }.to_string(),
+                        output_path,
+                        error: result.err().map(|e| e.reason),
+                    }, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            });
+        });
+        
+        *self.trigger.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Trigger lock poisoned"))? = Some(watch);
+        Ok(())
+    }
+
+    /// Stop watching for the armed trigger; a recording it started keeps going
+    #[napi]
+    pub fn disarm_trigger(&self) -> Result<()> {
+        self.trigger.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Trigger lock poisoned"))?
+            .take();
+        Ok(())
+    }
+
+    /// Stop writing frames without ending the recording; paused time is left out of the file
+    #[napi]
+    pub async fn pause_recording(&self) -> Result<()> {
+        let mut manager = self.recording_manager.lock().await;
+        manager.pause_recording()
+    }
+
+    #[napi]
+    pub async fn resume_recording(&self) -> Result<()> {
+        let mut manager = self.recording_manager.lock().await;
+        manager.resume_recording()
+    }
+
+    /// Turn the current recording's audio off and on without stopping; while off the audio
+    /// track is silent, so it stays in step with the video
+    #[napi]
+    pub async fn set_audio_enabled(&self, enabled: bool) -> Result<()> {
+        self.recording_manager.lock().await.set_audio_enabled(enabled)
+    }
+
+    /// Turn the current recording's video off and on without stopping; while off the last
+    /// recorded frame is held and no frames reach `onFrames`
+    #[napi]
+    pub async fn set_video_enabled(&self, enabled: bool) -> Result<()> {
+        self.recording_manager.lock().await.set_video_enabled(enabled)
+    }
+
+    /// Start time and pauses of the current recording, or of the last stopped one
+    /// Null before the first recording
+    #[napi]
+    pub async fn get_recording_timeline(&self) -> Option<RecordingTimeline> {
+        let manager = self.recording_manager.lock().await;
+        manager.recording_timeline().map(|timeline| RecordingTimeline {
+            started_at: timeline.started_at,
+            paused_intervals: timeline.paused_intervals.iter()
+                .map(|span| PausedInterval { from: span.from, to: span.to })
+                .collect(),
+        })
+    }
+
+    /// Cursor track sidecar written by the last stopped recording (see captureCursorTrack)
+    /// Null when that recording didn't capture one
+    #[napi]
+    pub async fn get_cursor_track_path(&self) -> Option<String> {
+        let manager = self.recording_manager.lock().await;
+        manager.last_cursor_track_path()
+    }
+
+    /// Write the last `replayBufferSeconds` of an instant replay recording to `outputPath`
+    /// Recording continues; call again to save later windows
+    #[napi]
+    pub async fn save_replay(&self, output_path: String) -> Result<String> {
+        let delegate = self.recording_manager.lock().await.replay_delegate(&output_path)?;
+        
+        // Flushing the encoder and writing the file block; keep them off the async workers
+        tokio::task::spawn_blocking(move || delegate.save_replay(&output_path))
+            .await
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Replay saving task failed: {}", e)))?
+    }
+
     #[napi]
     pub fn get_status(&self) -> String {
         serde_json::json!({
@@ -140,38 +1187,620 @@ impl ScreenCaptureKitRecorder {
         }).to_string()
     }
     
-    /// Get available windows
+    /// Collect a JSON diagnostics report for support tickets: versions, permissions,
+    /// detected content, transcription dependencies and the last recording's stats
+    #[napi]
+    pub async fn collect_diagnostics(&self) -> String {
+        let (is_recording, last_recording) = {
+            let manager = self.recording_manager.lock().await;
+            (manager.is_recording(), manager.last_recording_stats().cloned())
+        };
+        
+        Diagnostics::collect(is_recording, last_recording).await.to_string()
+    }
+    
+    /// The last failure to start or finish a recording, with the stage and underlying NSError;
+    /// null when none has failed
+    #[napi]
+    pub async fn get_last_error(&self) -> Option<LastRecordingError> {
+        let manager = self.recording_manager.lock().await;
+        manager.last_error().map(|failure| LastRecordingError {
+            stage: failure.stage.as_str().to_string(),
+            code: failure.status.to_string(),
+            message: failure.message.clone(),
+            underlying_error: failure.underlying_error.clone(),
+            failed_at: failure.failed_at,
+        })
+    }
+    
+    /// Cancel any pending screen/window enumeration
+    /// Pending `getAvailableScreens`/`getAvailableWindows` calls reject with a "cancelled" error
+    #[napi]
+    pub fn cancel_content_retrieval(&self) {
+        println!("🚫 Cancelling pending content retrieval");
+        let _ = self.content_cancel.send(());
+    }
+
+    /// Report whether the OS recording indicator is shown and the configured privacy alert
+    #[napi]
+    pub async fn get_capture_indicator_state(&self) -> CaptureIndicatorState {
+        let manager = self.recording_manager.lock().await;
+        manager.get_capture_indicator_state()
+    }
+    
+    /// Report the output size and frame rate of the current recording, or null when idle
+    #[napi]
+    pub async fn get_effective_settings(&self) -> Option<EffectiveSettings> {
+        let manager = self.recording_manager.lock().await;
+        manager.get_effective_settings()
+    }
+    
+    /// Encode the latest frame of the current recording as "jpeg" (default) or "png", for a
+    /// live preview from the frames already being captured. Requires pixelFormat "bgra"
+    #[napi]
+    pub async fn grab_current_frame(&self, format: Option<String>) -> Result<Buffer> {
+        let format = match format.as_deref() {
+            None => ImageFormat::Jpeg,
+            Some(value) => ImageFormat::from_config_str(value)
+                .ok_or_else(|| Error::new(Status::InvalidArg, "format must be \"jpeg\" or \"png\""))?,
+        };
+        let frame = self.recording_manager.lock().await.grab_current_frame()?;
+        
+        // A large frame takes a while to encode; keep it off the async workers
+        let image = tokio::task::spawn_blocking(move || frame.encode(format))
+            .await
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Frame encoding task failed: {}", e)))?;
+        image.map(Buffer::from).ok_or_else(|| Error::new(Status::GenericFailure, "Failed to encode the frame"))
+    }
+    
+    /// Stream input levels from `deviceId` (default input when omitted) to `callback`
+    /// without recording; nothing is written to disk. Restarts the monitor if already running
     #[napi]
-    pub async fn get_available_windows(&self) -> Result<Vec<ScreenSource>> {
+    pub fn start_audio_monitor(
+        &self,
+        device_id: Option<String>,
+        #[napi(ts_arg_type = "(levels: AudioLevels) => void")] callback: JsFunction,
+    ) -> Result<()> {
+        let on_levels: ThreadsafeFunction<AudioLevels, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        
+        let mut monitor = self.audio_monitor.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Audio monitor lock poisoned"))?;
+        // Tear the previous tap down before opening the device again
+        if let Some(previous) = monitor.take() {
+            previous.stop();
+        }
+        
+        *monitor = Some(AudioMonitor::start(device_id.as_deref(), move |levels| {
+            on_levels.call(levels, ThreadsafeFunctionCallMode::NonBlocking);
+        })?);
+        Ok(())
+    }
+    
+    /// Call `callback` when the output volume runs low on space during a recording, and again
+    /// with `autoStopped` set if the recording is stopped because the volume is nearly full
+    #[napi]
+    pub fn on_disk_space_warning(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(warning: DiskSpaceWarning) => void")] callback: JsFunction,
+    ) -> Result<()> {
+        let mut on_warning: ThreadsafeFunction<DiskSpaceWarning, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        // Don't keep Node alive just because a listener is registered
+        on_warning.unref(&env)?;
+        
+        *self.disk_space_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Disk space callback lock poisoned"))? = Some(on_warning);
+        Ok(())
+    }
+    
+    /// Call `callback` with the full source list whenever a display or window appears,
+    /// disappears or is renamed; polls every `intervalMs` (default 2000, minimum 250).
+    /// `filter` narrows the windows as in getAvailableScreens
+    #[napi]
+    pub fn watch_sources(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(sources: Array<ScreenSource>) => void")] callback: JsFunction,
+        interval_ms: Option<u32>,
+        filter: Option<SourceFilter>,
+    ) -> Result<SourceWatcher> {
+        let interval_ms = interval_ms.unwrap_or(DEFAULT_SOURCE_POLL_INTERVAL_MS);
+        if interval_ms < MIN_SOURCE_POLL_INTERVAL_MS {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("intervalMs must be at least {}", MIN_SOURCE_POLL_INTERVAL_MS),
+            ));
+        }
+        
+        let mut on_change: ThreadsafeFunction<Vec<ScreenSource>, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        // Don't keep Node alive just because a watcher is running
+        on_change.unref(&env)?;
+        
+        let watch = SourceWatch::start(interval_ms, AppFilter::from_config(filter.as_ref()), move |sources| {
+            on_change.call(sources, ThreadsafeFunctionCallMode::NonBlocking);
+        });
+        Ok(SourceWatcher { watch: std::sync::Mutex::new(Some(watch)) })
+    }
+    
+    /// Call `callback` when ScreenCaptureKit ends a recording on its own, e.g. with a
+    /// "PermissionRevoked" error when screen recording permission is taken away. The
+    /// captured part has already been finalized (see permissionRevokedBehavior). A recording
+    /// whose stream delivers no frames within 2 seconds of starting reports "NoFramesDelivered",
+    /// and secure input starting or ending mid-recording is reported too
+    #[napi]
+    pub fn on_error(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(error: RecordingError) => void")] callback: JsFunction,
+    ) -> Result<()> {
+        let mut on_error: ThreadsafeFunction<RecordingError, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        // Don't keep Node alive just because a listener is registered
+        on_error.unref(&env)?;
+        
+        *self.error_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Error callback lock poisoned"))? = Some(on_error);
+        Ok(())
+    }
+    
+    /// Call `callback` with copies of the recorded frames during the next recordings, which
+    /// must use pixelFormat "bgra". Frames are delivered `batchSize` at a time (default 1, max
+    /// 120) to cut per-call overhead at high fps; a partial batch is delivered at stop. When JS
+    /// falls behind by more than a few batches, newer batches are dropped.
+    /// `format` is "bgra" (default, the captured pixels copied as-is), "rgba" or "rgb" (packed,
+    /// converted with vImage) or "jpeg". Conversion runs on the capture queue: RGB(A) adds
+    /// roughly a copy's cost per frame, JPEG several milliseconds per 1080p frame, which at high
+    /// fps can make ScreenCaptureKit drop frames
+    #[napi]
+    pub fn on_frames(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(frames: Array<VideoFrame>) => void")] callback: JsFunction,
+        batch_size: Option<u32>,
+        format: Option<String>,
+    ) -> Result<()> {
+        let batch_size = batch_size.unwrap_or(DEFAULT_FRAME_BATCH_SIZE);
+        if batch_size < 1 || batch_size > MAX_FRAME_BATCH_SIZE {
+            return Err(Error::new(Status::InvalidArg, format!("batchSize must be between 1 and {}", MAX_FRAME_BATCH_SIZE)));
+        }
+        let format = match format.as_deref() {
+            Some(value) => FrameFormat::from_config_str(value).ok_or_else(|| {
+                Error::new(Status::InvalidArg, "format must be \"bgra\", \"rgba\", \"rgb\" or \"jpeg\"")
+            })?,
+            None => FrameFormat::default(),
+        };
+        
+        let pending_batches = Arc::new(AtomicUsize::new(0));
+        let delivered = pending_batches.clone();
+        let mut on_frames: ThreadsafeFunction<Vec<RawFrame>, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, move |ctx: ThreadSafeCallContext<Vec<RawFrame>>| {
+                delivered.fetch_sub(1, Ordering::SeqCst);
+                let frames: Vec<VideoFrame> = ctx.value.into_iter()
+                    .map(|frame| VideoFrame {
+                        data: frame.data.into(),
+                        format: frame.format.as_str().to_string(),
+                        width: frame.width,
+                        height: frame.height,
+                        bytes_per_row: frame.bytes_per_row,
+                        timestamp: frame.timestamp,
+                    })
+                    .collect();
+                Ok(vec![frames])
+            })?;
+        // Don't keep Node alive just because a listener is registered
+        on_frames.unref(&env)?;
+        
+        *self.frame_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Frame callback lock poisoned"))? = Some(FrameCallback {
+                callback: on_frames,
+                batch_size,
+                format,
+                pending_batches,
+            });
+        Ok(())
+    }
+    
+    /// Call `callback` with recording progress every progressIntervalMs (default 500) while recording
+    #[napi]
+    pub fn on_progress(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(progress: RecordingProgress) => void")] callback: JsFunction,
+    ) -> Result<()> {
+        let mut on_progress: ThreadsafeFunction<RecordingProgress, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        // Don't keep Node alive just because a listener is registered
+        on_progress.unref(&env)?;
+        
+        *self.progress_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Progress callback lock poisoned"))? = Some(on_progress);
+        Ok(())
+    }
+    
+    /// Stop the audio monitor and release the input device
+    #[napi]
+    pub fn stop_audio_monitor(&self) -> Result<()> {
+        let mut monitor = self.audio_monitor.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Audio monitor lock poisoned"))?;
+        if let Some(monitor) = monitor.take() {
+            monitor.stop();
+        }
+        Ok(())
+    }
+    
+    /// Record an attached iPhone or iPad (an id from getAvailableCaptureDevices) to
+    /// `config.outputPath`, video only. Runs separately from screen recording; the host app
+    /// needs camera access (NSCameraUsageDescription, plus the com.apple.security.device.camera
+    /// entitlement when sandboxed or hardened)
+    #[napi]
+    pub async fn start_device_capture(&self, device_id: String, config: RecordingConfiguration) -> Result<()> {
+        let mut capture = self.device_capture.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Device capture lock poisoned"))?;
+        if capture.is_some() {
+            return Err(Error::new(Status::GenericFailure, "A device is already being recorded"));
+        }
+        *capture = Some(DeviceCapture::start(&device_id, &config)?);
+        Ok(())
+    }
+    
+    /// Stop the device recording and return the path of the finished file
+    #[napi]
+    pub async fn stop_device_capture(&self) -> Result<String> {
+        let capture = self.device_capture.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Device capture lock poisoned"))?
+            .take()
+            .ok_or_else(|| Error::new(Status::GenericFailure, "No device is being recorded"))?;
+        capture.stop()
+    }
+    
+    /// Get available windows, narrowed by `filter` (system windows are hidden by default)
+    #[napi]
+    pub async fn get_available_windows(&self, filter: Option<SourceFilter>) -> Result<Vec<ScreenSource>> {
         println!("🪟 Getting windows via complete ScreenCaptureKit async APIs");
         
-        let content = AsyncContentManager::get_shareable_content().await?;
+        let content = self.retrieve_content().await?;
         let windows = content.get_windows()?;
+        let filter = AppFilter::from_config(filter.as_ref());
         
         // Convert to ScreenSource format
-        let sources: Vec<ScreenSource> = windows.into_iter().map(|window| ScreenSource {
+        let sources: Vec<ScreenSource> = windows.into_iter().filter(|window| filter.allows(window)).map(|window| ScreenSource {
             id: format!("window:{}", window.id),
             name: window.title,
             width: window.width,
             height: window.height,
             is_display: false,
+            is_virtual: false,
+            is_mirror: false,
+            is_minimized: window.is_minimized,
+            is_fullscreen: window.is_fullscreen,
+            is_active: window.is_active,
+            edr_headroom: None,
         }).collect();
         
         println!("✅ Found {} windows via complete ScreenCaptureKit", sources.len());
         Ok(sources)
     }
+    
+    /// List the Spaces of every display. macOS has no public API for Spaces, so this reads
+    /// the window server's own list and fails where it isn't available
+    #[napi]
+    pub fn get_spaces(&self) -> Result<Vec<SpaceInfo>> {
+        spaces::spaces()
+    }
+    
+    /// Record the display showing Space `spaceId`. ScreenCaptureKit only captures what's on
+    /// screen, so this fails unless the Space is its display's current one; switching Spaces
+    /// mid-recording records the new one
+    #[napi]
+    pub async fn start_recording_space(&self, space_id: i64, config: RecordingConfiguration) -> Result<String> {
+        let display_id = spaces::display_for_space(&spaces::spaces()?, space_id)?;
+        println!("🌌 Recording Space {} on display {}", space_id, display_id);
+        self.start_recording_target(CaptureTarget::Display(display_id), config).await
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl RecorderHandle {
+    /// Wire up the registered callbacks and start recording `target`
+    async fn start_recording(&self, target: CaptureTarget, config: RecordingConfiguration) -> Result<String> {
+        let on_disk_space = self.disk_space_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Disk space callback lock poisoned"))?
+            .clone();
+        // Read for every event, so a handle's onProgress also reaches a running recording
+        let progress_callback = self.progress_callback.clone();
+        let on_error = self.error_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Error callback lock poisoned"))?
+            .clone();
+        let on_frames = self.frame_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Frame callback lock poisoned"))?
+            .clone();
+        let output_path = config.output_path.clone();
+        let weak_manager = Arc::downgrade(&self.recording_manager);
+        let runtime = tokio::runtime::Handle::current();
+        let progress_manager = weak_manager.clone();
+        let progress_runtime = runtime.clone();
+        let stop_manager = weak_manager.clone();
+        let stop_runtime = runtime.clone();
+        let idle_manager = weak_manager.clone();
+        let idle_runtime = runtime.clone();
+        
+        // Initialize recording manager if needed
+        {
+            let mut manager = self.recording_manager.lock().await;
+            manager.initialize().await?;
+            
+            manager.set_disk_space_listener(Some(Arc::new(move |event: DiskSpaceEvent| {
+                if let Some(ref callback) = on_disk_space {
+                    callback.call(DiskSpaceWarning {
+                        output_path: output_path.clone(),
+                        available_mb: event.available_bytes as f64 / 1_048_576.0,
+                        auto_stopped: event.exhausted,
+                    }, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+                // Finalize while there is still room for the moov atom
+                if event.exhausted {
+                    if let Some(manager) = weak_manager.upgrade() {
+                        runtime.spawn(async move {
+                            if let Err(e) = manager.lock().await.stop_recording().await {
+                                println!("❌ Failed to stop recording on low disk space: {}", e.reason);
+                            }
+                        });
+                    }
+                }
+            })));
+            
+            manager.set_progress_listener(Some(Arc::new(move |event: ProgressEvent| {
+                let on_progress = progress_callback.lock().ok().and_then(|callback| callback.clone());
+                if let Some(ref callback) = on_progress {
+                    callback.call(RecordingProgress {
+                        frames: event.frames as i64,
+                        elapsed_seconds: event.elapsed_seconds,
+                        progress: event.progress,
+                        countdown: event.countdown,
+                    }, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+                if event.finished {
+                    if let Some(manager) = progress_manager.upgrade() {
+                        progress_runtime.spawn(async move {
+                            if let Err(e) = manager.lock().await.stop_recording().await {
+                                println!("❌ Failed to stop recording at its maximum duration: {}", e.reason);
+                            }
+                        });
+                    }
+                }
+            })));
+            
+            let on_secure_input = on_error.clone();
+            manager.set_secure_input_listener(Some(Arc::new(move |active: bool| {
+                if let Some(ref callback) = on_secure_input {
+                    callback.call(RecordingError {
+                        code: if active { "SecureInputActive" } else { "SecureInputEnded" }.to_string(),
+                        message: if active {
+                            "Secure input is active (e.g. a password field); the capture may be blank until it ends"
+                        } else {
+                            "Secure input ended"
+                        }.to_string(),
+                        output_path: None,
+                    }, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            })));
+            
+            let on_idle = on_error.clone();
+            manager.set_idle_stop_listener(Some(Arc::new(move || {
+                let Some(manager) = idle_manager.upgrade() else {
+                    return;
+                };
+                let on_idle = on_idle.clone();
+                idle_runtime.spawn(async move {
+                    let result = manager.lock().await.stop_recording().await;
+                    if let Err(ref e) = result {
+                        println!("❌ Failed to stop idle recording: {}", e.reason);
+                    }
+                    if let Some(ref callback) = on_idle {
+                        callback.call(RecordingError {
+                            code: "IdleStopped".to_string(),
+                            message: "Recording stopped: the screen stayed unchanged for idleStopSeconds".to_string(),
+                            output_path: result.ok(),
+                        }, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                });
+            })));
+            
+            let on_new_segment = on_error.clone();
+            manager.set_new_segment_listener(Some(Arc::new(move |event: NewSegmentEvent| {
+                if let Some(ref callback) = on_new_segment {
+                    callback.call(RecordingError {
+                        code: "NewSegment".to_string(),
+                        message: format!(
+                            "The capture changed to {}x{}; recording continues in {}",
+                            event.width, event.height, event.next_path,
+                        ),
+                        output_path: Some(event.finished_path),
+                    }, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            })));
+            
+            let on_missing_frames = on_error.clone();
+            manager.set_frame_delivery_listener(Some(Arc::new(move |message: String| {
+                if let Some(ref callback) = on_missing_frames {
+                    callback.call(RecordingError {
+                        code: "NoFramesDelivered".to_string(),
+                        message,
+                        output_path: None,
+                    }, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            })));
+            
+            manager.set_stream_stop_listener(Some(Arc::new(move |event: StreamStopEvent| {
+                if let Some(ref callback) = on_error {
+                    callback.call(RecordingError {
+                        code: match event.error {
+                            SCError::PermissionRevoked => "PermissionRevoked",
+                            SCError::CaptureBlocked(_) => "CaptureBlocked",
+                            _ => "StreamStopped",
+                        }.to_string(),
+                        message: event.error.to_string(),
+                        output_path: event.output_path.clone(),
+                    }, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+                // The stream is already gone; release it so a new recording can start
+                if let Some(manager) = stop_manager.upgrade() {
+                    stop_runtime.spawn(async move {
+                        manager.lock().await.finish_stopped_stream(&event.error);
+                    });
+                }
+            })));
+            
+            match on_frames {
+                Some(on_frames) => {
+                    let (batch_size, format) = (on_frames.batch_size, on_frames.format);
+                    manager.set_frame_listener(Some(Arc::new(move |frames: Vec<RawFrame>| {
+                        // Dropping the batch here frees its copies at once
+                        if on_frames.pending_batches.fetch_add(1, Ordering::SeqCst) >= MAX_PENDING_FRAME_BATCHES {
+                            on_frames.pending_batches.fetch_sub(1, Ordering::SeqCst);
+                            println!("⚠️ Frame callback is falling behind; dropped {} frames", frames.len());
+                            return;
+                        }
+                        on_frames.callback.call(frames, ThreadsafeFunctionCallMode::NonBlocking);
+                    })), batch_size, format);
+                }
+                None => manager.set_frame_listener(None, DEFAULT_FRAME_BATCH_SIZE, FrameFormat::default()),
+            }
+            
+            // Start the actual recording
+            manager.start_recording(target, config).await
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+/// Handle returned by `watchSources`
+#[napi]
+pub struct SourceWatcher {
+    watch: std::sync::Mutex<Option<SourceWatch>>,
+}
+
+#[cfg(target_os = "macos")]
+#[napi]
+impl SourceWatcher {
+    /// Stop watching; safe to call more than once
+    #[napi]
+    pub fn unsubscribe(&self) -> Result<()> {
+        let mut watch = self.watch.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Source watcher lock poisoned"))?;
+        if let Some(watch) = watch.take() {
+            watch.stop();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+/// Handle returned by `startRecordingHandle`; controls only its own recording
+#[napi]
+pub struct RecordingHandle {
+    recorder: RecorderHandle,
+}
+
+#[cfg(target_os = "macos")]
+// Safety: The recording manager is behind a Mutex, as in ScreenCaptureKitRecorder
+unsafe impl Send for RecordingHandle {}
+#[cfg(target_os = "macos")]
+unsafe impl Sync for RecordingHandle {}
+
+#[cfg(target_os = "macos")]
+#[napi]
+impl RecordingHandle {
+    /// Stop and finalize the recording; resolves with the path of the file
+    #[napi]
+    pub async fn stop(&self) -> Result<String> {
+        self.recorder.recording_manager.lock().await.stop_recording().await
+    }
+
+    /// Stop writing frames without ending the recording; paused time is left out of the file
+    #[napi]
+    pub async fn pause(&self) -> Result<()> {
+        self.recorder.recording_manager.lock().await.pause_recording()
+    }
+
+    #[napi]
+    pub async fn resume(&self) -> Result<()> {
+        self.recorder.recording_manager.lock().await.resume_recording()
+    }
+
+    /// Counters of the recording so far; those of the finished recording once it stopped
+    #[napi]
+    pub async fn get_stats(&self) -> Option<RecordingHandleStats> {
+        let manager = self.recorder.recording_manager.lock().await;
+        manager.current_stats().map(|stats| RecordingHandleStats {
+            output_path: stats.output_path,
+            is_recording: manager.is_recording(),
+            is_paused: manager.is_paused(),
+            video_frames: stats.video_frames as i64,
+            audio_samples: stats.audio_samples as i64,
+            duration_seconds: stats.duration_seconds,
+            average_fps: stats.average_fps,
+            error: stats.error,
+        })
+    }
+
+    /// Call `callback` with this recording's progress every progressIntervalMs; replaces the
+    /// callback inherited from the recorder
+    #[napi]
+    pub fn on_progress(
+        &self,
+        env: Env,
+        #[napi(ts_arg_type = "(progress: RecordingProgress) => void")] callback: JsFunction,
+    ) -> Result<()> {
+        let mut on_progress: ThreadsafeFunction<RecordingProgress, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+        on_progress.unref(&env)?;
+        
+        *self.recorder.progress_callback.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Progress callback lock poisoned"))? = Some(on_progress);
+        Ok(())
+    }
 }
 
+#[cfg(target_os = "macos")]
+/// The capture target named by a ScreenSource id; an empty id keeps the historical main
+/// display default
+fn parse_screen_id(screen_id: &str) -> Result<CaptureTarget> {
+    if screen_id.is_empty() {
+        return Ok(CaptureTarget::default());
+    }
+    CaptureTarget::from_screen_id(screen_id).ok_or_else(|| Error::new(
+        Status::InvalidArg,
+        format!("screenId must be \"display:<id>\" or \"window:<id>\", got \"{}\"", screen_id),
+    ))
+}
+
+#[cfg(target_os = "macos")]
+/// A new callback slot holding what `slot` holds now
+fn copy_callback<T: Clone>(slot: &Arc<std::sync::Mutex<Option<T>>>) -> Result<Arc<std::sync::Mutex<Option<T>>>> {
+    let callback = slot.lock()
+        .map_err(|_| Error::new(Status::GenericFailure, "Callback lock poisoned"))?
+        .clone();
+    Ok(Arc::new(std::sync::Mutex::new(callback)))
+}
+
+#[cfg(target_os = "macos")]
 /// Integrated recording manager with complete functionality
 #[napi]
 pub struct IntegratedRecordingManager {
     recording_manager: Arc<Mutex<RecordingManager>>,
 }
 
+#[cfg(target_os = "macos")]
 // Safety: The internal data is protected by Mutex, making it safe to send between threads
 unsafe impl Send for IntegratedRecordingManager {}
+#[cfg(target_os = "macos")]
 unsafe impl Sync for IntegratedRecordingManager {}
 
+#[cfg(target_os = "macos")]
 #[napi]
 impl IntegratedRecordingManager {
     #[napi(constructor)]
@@ -191,7 +1820,7 @@ impl IntegratedRecordingManager {
     #[napi]
     pub async fn start_recording(&self, config: RecordingConfiguration) -> Result<String> {
         let mut manager = self.recording_manager.lock().await;
-        manager.start_recording(config).await
+        manager.start_recording(CaptureTarget::default(), config).await
     }
     
     #[napi]
@@ -211,22 +1840,35 @@ impl IntegratedRecordingManager {
             width: display.width,
             height: display.height,
             is_display: true,
+            is_virtual: display.is_virtual,
+            is_mirror: display.is_mirror,
+            is_minimized: false,
+            is_fullscreen: false,
+            is_active: false,
+            edr_headroom: display.edr_headroom,
         }).collect();
         
         Ok(sources)
     }
     
     #[napi]
-    pub async fn get_available_windows(&self) -> Result<Vec<ScreenSource>> {
+    pub async fn get_available_windows(&self, filter: Option<SourceFilter>) -> Result<Vec<ScreenSource>> {
         let manager = self.recording_manager.lock().await;
         let windows = manager.get_available_windows().await?;
+        let filter = AppFilter::from_config(filter.as_ref());
         
-        let sources = windows.into_iter().map(|window| ScreenSource {
+        let sources = windows.into_iter().filter(|window| filter.allows(window)).map(|window| ScreenSource {
             id: format!("window:{}", window.id),
             name: window.title,
             width: window.width,
             height: window.height,
             is_display: false,
+            is_virtual: false,
+            is_mirror: false,
+            is_minimized: window.is_minimized,
+            is_fullscreen: window.is_fullscreen,
+            is_active: window.is_active,
+            edr_headroom: None,
         }).collect();
         
         Ok(sources)
@@ -245,9 +1887,11 @@ impl IntegratedRecordingManager {
 
 // Export pixel format constants
 #[napi]
+#[allow(non_upper_case_globals)]
 pub const kCVPixelFormatType_32BGRA: u32 = 1111970369; // 'BGRA'
 
 #[napi]
+#[allow(non_upper_case_globals)]
 pub const kCGColorSpaceSRGB: u32 = 1;
 
 #[napi]
@@ -261,12 +1905,179 @@ pub fn get_version() -> String {
     "1.0.0-complete-async".to_string()
 }
 
+/// Start log lines with an emoji (the default unless the locale isn't UTF-8) or leave it out
+/// for terminals that can't show it; the rest of each line, window titles included, is
+/// unchanged
+#[napi]
+pub fn set_log_emoji(enabled: bool) {
+    logging::set_emoji_enabled(enabled);
+}
+
+/// List the cameras usable for the webcam overlay
+#[cfg(target_os = "macos")]
+#[napi]
+pub fn get_available_cameras() -> Vec<CameraDevice> {
+    screencapturekit::webcam::available_cameras()
+}
+
+/// List microphones (deviceType "microphone", for audioDeviceId) and output devices
+/// (deviceType "speaker", for audioOutputDeviceId) by their Core Audio UIDs
+#[cfg(target_os = "macos")]
+#[napi]
+pub fn get_available_audio_devices() -> Result<Vec<AudioDevice>> {
+    screencapturekit::audio::AudioManager::get_available_audio_devices()
+}
+
+/// Retry deleting intermediate files (extracted audio, Whisper output, the separate audio file
+/// of a failed recording) that couldn't be removed when their job ended; returns how many
+/// were deleted. Jobs clean up after themselves on success and failure, so this is only
+/// needed when a removal failed, e.g. because another process had the file open
+#[cfg(target_os = "macos")]
+#[napi]
+pub fn cleanup_temp_files() -> u32 {
+    screencapturekit::temp_files::cleanup_temp_files()
+}
+
+/// Check a finished recording: its duration, which tracks it has and whether its audio is
+/// audible, so a silent audio track can be flagged instead of found on playback
+#[cfg(target_os = "macos")]
+#[napi]
+pub fn verify_recording(path: String) -> Result<RecordingVerification> {
+    screencapturekit::verify::verify_recording(&path)
+}
+
+/// List the attached iPhones and iPads that can be recorded with startDeviceCapture; a device
+/// must be unlocked and trust this Mac, and can take a moment to appear on the first call
+#[cfg(target_os = "macos")]
+#[napi]
+pub fn get_available_capture_devices() -> Vec<CaptureDevice> {
+    screencapturekit::device_capture::available_devices()
+}
+
+/// List the codecs, containers, pixel formats and output sizes this machine supports
+#[cfg(target_os = "macos")]
+#[napi]
+pub fn get_capabilities() -> Capabilities {
+    CapabilityProbe::probe()
+}
+
+/// Re-encode a finished recording into `outputPath` (.mp4, .m4v or .mov, must not exist)
+/// with the codec, bitrate and size from `config`; resolves with the output path
+#[cfg(target_os = "macos")]
+#[napi(ts_return_type = "Promise<string>")]
+pub fn transcode(
+    env: Env,
+    input_path: String,
+    output_path: String,
+    config: TranscodeConfiguration,
+    #[napi(ts_arg_type = "(progress: TranscodeProgress) => void")] on_progress: Option<JsFunction>,
+) -> Result<JsObject> {
+    let on_progress: Option<ThreadsafeFunction<TranscodeProgress, ErrorStrategy::Fatal>> = on_progress
+        .map(|callback| callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value])))
+        .transpose()?;
+    let settings = TranscodeSettings::from_config(&config);
+    
+    env.execute_tokio_future(
+        async move {
+            let settings = settings?;
+            // Reading and writing the whole file blocks; keep it off the async workers
+            tokio::task::spawn_blocking(move || {
+                transcode_file(&input_path, &output_path, &settings, |event| {
+                    if let Some(ref callback) = on_progress {
+                        callback.call(TranscodeProgress {
+                            processed_seconds: event.processed_seconds,
+                            duration_seconds: event.duration_seconds,
+                            progress: event.progress,
+                            decoder: event.decoder.to_string(),
+                        }, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                })
+            })
+            .await
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Transcoding task failed: {}", e)))?
+        },
+        |env, path| env.create_string(&path),
+    )
+}
+
+/// Join recording segments end to end into `outputPath` (.mp4, .m4v or .mov, must not exist)
+/// without re-encoding; the segments must share codec and size. Resolves with the output path
+#[cfg(target_os = "macos")]
+#[napi(ts_return_type = "Promise<string>")]
+pub fn concatenate_segments(env: Env, segment_paths: Vec<String>, output_path: String) -> Result<JsObject> {
+    env.execute_tokio_future(
+        async move {
+            // The export is awaited synchronously; keep it off the async workers
+            tokio::task::spawn_blocking(move || concatenate_segment_files(&segment_paths, &output_path))
+                .await
+                .map_err(|e| Error::new(Status::GenericFailure, format!("Concatenation task failed: {}", e)))?
+        },
+        |env, path| env.create_string(&path),
+    )
+}
+
+/// Transcribe `inputPath` in the background and return the job's id straight away; `onUpdate`
+/// receives the job each time its status changes and `getTranscriptionJob` polls it
+#[cfg(target_os = "macos")]
+#[napi]
+pub fn start_transcription_job(
+    input_path: String,
+    config: Option<TranscriptionJobConfiguration>,
+    #[napi(ts_arg_type = "(job: TranscriptionJob) => void")] on_update: Option<JsFunction>,
+) -> Result<u32> {
+    let config = TranscriptionConfig::from_job_config(&config.unwrap_or_default())?;
+    if !std::path::Path::new(&input_path).is_file() {
+        return Err(Error::new(Status::InvalidArg, format!("Input file does not exist: {}", input_path)));
+    }
+    let on_update: Option<ThreadsafeFunction<TranscriptionJob, ErrorStrategy::Fatal>> = on_update
+        .map(|callback| callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value])))
+        .transpose()?;
+    
+    let jobs = TranscriptionJobs::global();
+    let job = jobs.create(&input_path);
+    let id = job.id;
+    println!("🎤 Transcription job {} queued for {}", id, input_path);
+    
+    let listener: TranscriptionJobListener = Arc::new(move |job| {
+        if let Some(ref callback) = on_update {
+            callback.call(transcription_job_to_js(job), ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    });
+    spawn(async move {
+        jobs.run(id, TranscriptionManager::new(config), listener).await;
+    });
+    Ok(id)
+}
+
+/// The transcription job with `id`, or null if it's unknown or long finished
+#[cfg(target_os = "macos")]
+#[napi]
+pub fn get_transcription_job(id: u32) -> Option<TranscriptionJob> {
+    TranscriptionJobs::global().get(id).as_ref().map(transcription_job_to_js)
+}
+
+#[cfg(target_os = "macos")]
+fn transcription_job_to_js(job: &TranscriptionJobState) -> TranscriptionJob {
+    TranscriptionJob {
+        id: job.id,
+        input_path: job.input_path.clone(),
+        status: job.status.as_str().to_string(),
+        text: job.result.as_ref().map(|result| result.text.clone()),
+        language: job.result.as_ref().and_then(|result| result.language.clone()),
+        duration_seconds: job.result.as_ref().and_then(|result| result.duration).map(f64::from),
+        transcript_path: job.transcript_path.clone(),
+        error: job.error.clone(),
+    }
+}
+
+#[cfg(target_os = "macos")]
 #[napi]
 pub fn check_screen_recording_permission() -> Result<bool> {
     println!("🔐 Checking screen recording permission");
     Ok(PermissionManager::ensure_permission().is_ok())
 }
 
+#[cfg(target_os = "macos")]
 #[napi]
 pub fn request_screen_recording_permission() -> Result<bool> {
     println!("🔐 Requesting screen recording permission");
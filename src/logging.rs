@@ -0,0 +1,90 @@
+// Console output
+// Status lines start with an emoji ("🎬 Starting recording..."), which terminals without
+// UTF-8 show as mojibake. Every `println!` in the crate goes through the macro below, which
+// drops the emoji prefix when emoji output is off: by default when the locale (LC_ALL,
+// LC_CTYPE, LANG) names a non-UTF-8 encoding, or after setLogEmoji(false). The rest of the
+// line is printed untouched, so non-ASCII text such as window titles stays intact
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Emoji state: not yet decided, on, off
+const EMOJI_UNSET: u8 = 0;
+const EMOJI_ON: u8 = 1;
+const EMOJI_OFF: u8 = 2;
+
+static EMOJI: AtomicU8 = AtomicU8::new(EMOJI_UNSET);
+
+/// Shadows std's `println!` for the whole crate; must be declared before the modules using it
+macro_rules! println {
+    ($($arg:tt)*) => {
+        $crate::logging::print_line(format_args!($($arg)*))
+    };
+}
+
+/// Whether the locale from `lc_all`, `lc_ctype` and `lang` (first one set wins) can show
+/// emoji; an unset locale counts as UTF-8, as it's the macOS default
+pub fn locale_is_utf8(lc_all: Option<&str>, lc_ctype: Option<&str>, lang: Option<&str>) -> bool {
+    let locale = [lc_all, lc_ctype, lang].into_iter().flatten().find(|value| !value.is_empty());
+    locale.is_none_or(|locale| {
+        let locale = locale.to_ascii_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+fn emoji_enabled() -> bool {
+    match EMOJI.load(Ordering::Relaxed) {
+        EMOJI_ON => true,
+        EMOJI_OFF => false,
+        _ => {
+            let var = |name: &str| std::env::var(name).ok();
+            let enabled = locale_is_utf8(var("LC_ALL").as_deref(), var("LC_CTYPE").as_deref(), var("LANG").as_deref());
+            EMOJI.store(if enabled { EMOJI_ON } else { EMOJI_OFF }, Ordering::Relaxed);
+            enabled
+        }
+    }
+}
+
+/// Turn the emoji at the start of log lines on or off
+pub fn set_emoji_enabled(enabled: bool) {
+    EMOJI.store(if enabled { EMOJI_ON } else { EMOJI_OFF }, Ordering::Relaxed);
+}
+
+/// `line` without the emoji (and the space after it) that follows its indentation
+pub fn strip_emoji_prefix(line: &str) -> String {
+    let text = line.trim_start();
+    let indent = &line[..line.len() - text.len()];
+    // Emoji, their variation selectors and joiners are neither ASCII nor letters or digits
+    let rest = text.trim_start_matches(|c: char| !c.is_ascii() && !c.is_alphanumeric());
+    if rest.len() == text.len() {
+        return line.to_string();
+    }
+    format!("{}{}", indent, rest.trim_start())
+}
+
+/// Print a log line, minus its emoji prefix when emoji output is off
+pub fn print_line(args: fmt::Arguments) {
+    if emoji_enabled() {
+        std::println!("{}", args);
+    } else {
+        std::println!("{}", strip_emoji_prefix(&args.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_prefixes_are_stripped_but_text_is_kept() {
+        assert_eq!(strip_emoji_prefix("🎬 Starting recording"), "Starting recording");
+        assert_eq!(strip_emoji_prefix("   📹 Video frames: 30"), "   Video frames: 30");
+        assert_eq!(strip_emoji_prefix("⚠️ Low disk space"), "Low disk space", "Variation selectors go too");
+        assert_eq!(strip_emoji_prefix("📺 会議メモ 📝"), "会議メモ 📝", "Only the prefix is removed");
+        assert_eq!(strip_emoji_prefix("Ünïcode title"), "Ünïcode title");
+
+        assert!(locale_is_utf8(None, None, Some("en_US.UTF-8")));
+        assert!(locale_is_utf8(None, None, None));
+        assert!(!locale_is_utf8(Some("C"), None, Some("en_US.UTF-8")), "LC_ALL wins");
+    }
+}
@@ -1,175 +1,305 @@
-use crate::AudioDevice;
+use crate::{AudioDevice, AudioLevels};
 use napi::bindgen_prelude::*;
+use objc2::runtime::AnyObject;
 use objc2::{msg_send, class};
 use objc2_foundation::{NSArray, NSString};
+use objc2_core_media::CMSampleBuffer;
+use std::ffi::c_void;
 use std::ptr;
 
+use super::core_audio::{
+    AudioBufferList, CMAudioFormatDescriptionGetStreamBasicDescription, CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer,
+    CMSampleBufferGetDataBuffer, CMSampleBufferGetFormatDescription, AUDIO_FORMAT_FLAG_IS_FLOAT, AUDIO_FORMAT_LINEAR_PCM,
+};
+use super::encoder::AVMediaTypeAudio;
+use super::output_tap::output_devices;
+
 pub struct AudioManager;
 
+// Gain range accepted for the mic/system mix, in dB
+pub const MIN_AUDIO_GAIN_DB: f64 = -60.0;
+pub const MAX_AUDIO_GAIN_DB: f64 = 20.0;
+
+// Above this level boosted samples are compressed instead of clipped
+const SOFT_CLIP_KNEE: f32 = 0.8;
+
+// AVAuthorizationStatus values
+const AUTHORIZATION_NOT_DETERMINED: isize = 0;
+const AUTHORIZATION_RESTRICTED: isize = 1;
+const AUTHORIZATION_DENIED: isize = 2;
+
+extern "C" {
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Clamp a gain to the accepted range and convert it to a linear factor
+pub fn gain_db_to_linear(gain_db: f64) -> f32 {
+    let clamped = gain_db.clamp(MIN_AUDIO_GAIN_DB, MAX_AUDIO_GAIN_DB);
+    if clamped != gain_db {
+        println!("⚠️ Audio gain {:.1} dB clamped to {:.1} dB", gain_db, clamped);
+    }
+    10f64.powf(clamped / 20.0) as f32
+}
+
+/// Scale samples by `gain`, compressing peaks above the knee so boosted audio never clips;
+/// a gain of 1 or less can't clip, so it only scales
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    if gain <= 1.0 {
+        samples.iter_mut().for_each(|sample| *sample *= gain);
+        return;
+    }
+    for sample in samples.iter_mut() {
+        let scaled = *sample * gain;
+        let magnitude = scaled.abs();
+        *sample = if magnitude <= SOFT_CLIP_KNEE {
+            scaled
+        } else {
+            // tanh maps the excess above the knee into the remaining headroom below 1.0
+            let headroom = 1.0 - SOFT_CLIP_KNEE;
+            scaled.signum() * (SOFT_CLIP_KNEE + headroom * ((magnitude - SOFT_CLIP_KNEE) / headroom).tanh())
+        };
+    }
+}
+
+/// Apply `gain` in place to a float PCM sample buffer before it's encoded
+/// Returns false (buffer untouched) for formats other than 32-bit float PCM, and when the
+/// samples aren't stored contiguously, since scaling the copy made of them wouldn't reach
+/// the buffer that's encoded
+pub unsafe fn apply_gain_to_sample_buffer(sample_buffer: &CMSampleBuffer, gain: f32) -> bool {
+    float_samples(sample_buffer, true, |samples| apply_gain(samples, gain))
+}
+
+/// Call `f` with each buffer of samples in a 32-bit float PCM sample buffer, one per channel
+/// for non-interleaved audio; false (nothing called) for other formats
+pub unsafe fn with_float_samples(sample_buffer: &CMSampleBuffer, f: impl FnMut(&mut [f32])) -> bool {
+    float_samples(sample_buffer, false, f)
+}
+
+/// `with_float_samples`; with `in_place` also false (nothing called) unless the samples are
+/// the sample buffer's own rather than a contiguous copy of them
+unsafe fn float_samples(sample_buffer: &CMSampleBuffer, in_place: bool, mut f: impl FnMut(&mut [f32])) -> bool {
+    let format = CMSampleBufferGetFormatDescription(sample_buffer as *const CMSampleBuffer as *mut CMSampleBuffer);
+    if format.is_null() {
+        return false;
+    }
+    let description = CMAudioFormatDescriptionGetStreamBasicDescription(format);
+    if description.is_null()
+        || (*description).format_id != AUDIO_FORMAT_LINEAR_PCM
+        || (*description).format_flags & AUDIO_FORMAT_FLAG_IS_FLOAT == 0
+        || (*description).bits_per_channel != 32
+    {
+        return false;
+    }
+
+    // Non-interleaved audio has one AudioBuffer per channel; ask how large the list is
+    let mut size_needed: usize = 0;
+    let status = CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
+        sample_buffer, &mut size_needed, ptr::null_mut(), 0,
+        ptr::null(), ptr::null(), 0, ptr::null_mut(),
+    );
+    if status != 0 || size_needed == 0 {
+        return false;
+    }
+
+    // u64 storage keeps the list 8-byte aligned for the data pointers
+    let mut storage = vec![0u64; size_needed.div_ceil(8)];
+    let buffer_list = storage.as_mut_ptr() as *mut AudioBufferList;
+    let mut block_buffer: *mut c_void = ptr::null_mut();
+    let status = CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
+        sample_buffer, ptr::null_mut(), buffer_list, size_needed,
+        ptr::null(), ptr::null(), 0, &mut block_buffer,
+    );
+    if status != 0 {
+        return false;
+    }
+    // Samples spread over several memory blocks come back copied into a new block buffer
+    if in_place && block_buffer != CMSampleBufferGetDataBuffer(sample_buffer) {
+        if !block_buffer.is_null() {
+            CFRelease(block_buffer);
+        }
+        return false;
+    }
+
+    let buffers = std::slice::from_raw_parts_mut(
+        (*buffer_list).buffers.as_mut_ptr(),
+        (*buffer_list).number_buffers as usize,
+    );
+    for buffer in buffers {
+        if buffer.data.is_null() {
+            continue;
+        }
+        let samples = std::slice::from_raw_parts_mut(
+            buffer.data as *mut f32,
+            buffer.data_byte_size as usize / std::mem::size_of::<f32>(),
+        );
+        f(samples);
+    }
+
+    if !block_buffer.is_null() {
+        CFRelease(block_buffer);
+    }
+    true
+}
+
+/// RMS and peak level of a block of float samples, both linear 0.0-1.0
+pub fn compute_audio_levels(samples: &[f32]) -> AudioLevels {
+    if samples.is_empty() {
+        return AudioLevels { rms: 0.0, peak: 0.0 };
+    }
+    
+    let mut sum_squares = 0.0f64;
+    let mut peak = 0.0f32;
+    for &sample in samples {
+        sum_squares += (sample as f64) * (sample as f64);
+        peak = peak.max(sample.abs());
+    }
+    
+    AudioLevels {
+        rms: (sum_squares / samples.len() as f64).sqrt().min(1.0),
+        peak: (peak as f64).min(1.0),
+    }
+}
+
+/// Why recording the microphone would fail at AVAuthorizationStatus `status`; None when access
+/// is granted or not decided yet, in which case ScreenCaptureKit asks on first use
+pub fn microphone_access_error(status: isize) -> Option<Error> {
+    match status {
+        AUTHORIZATION_DENIED => Some(Error::new(
+            Status::GenericFailure,
+            "Microphone access is denied (System Settings > Privacy & Security > Microphone)",
+        )),
+        AUTHORIZATION_RESTRICTED => Some(Error::new(
+            Status::GenericFailure,
+            "Microphone access is restricted on this Mac, e.g. by a configuration profile",
+        )),
+        _ => None,
+    }
+}
+
+/// Microphones as (UID, name); the UIDs are the Core Audio device UIDs audioDeviceId takes
+fn microphones() -> Vec<(String, String)> {
+    unsafe {
+        let media_type = NSString::from_str(AVMediaTypeAudio);
+        let devices: *mut NSArray<AnyObject> = msg_send![class!(AVCaptureDevice), devicesWithMediaType: &*media_type];
+        if devices.is_null() {
+            return Vec::new();
+        }
+        (*devices).iter().filter_map(|device| {
+            let id: *mut NSString = msg_send![&*device, uniqueID];
+            let name: *mut NSString = msg_send![&*device, localizedName];
+            (!id.is_null()).then(|| ((*id).to_string(), if name.is_null() { String::new() } else { (*name).to_string() }))
+        }).collect()
+    }
+}
+
+// AVAudioSession is an iOS API: macOS has no audio session to fetch or configure, so devices
+// come from AVCaptureDevice and Core Audio, and the only thing to check up front is access
 impl AudioManager {
     pub fn get_available_audio_devices() -> Result<Vec<AudioDevice>> {
-        println!("🔊 Getting available audio devices via AVFoundation");
+        println!("🔊 Getting available audio devices via AVCaptureDevice and Core Audio");
         
-        let mut devices = Vec::new();
+        let mut devices: Vec<AudioDevice> = microphones().into_iter().map(|(uid, name)| AudioDevice {
+            id: uid,
+            name,
+            device_type: "microphone".to_string(),
+        }).collect();
         
-        unsafe {
-            // Get AVAudioSession
-            let session_class = class!(AVAudioSession);
-            let shared_instance: *mut objc2::runtime::AnyObject = msg_send![session_class, sharedInstance];
-            
-            if shared_instance.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to get AVAudioSession"));
-            }
-            
-            // Get available inputs
-            let available_inputs: *mut NSArray = msg_send![shared_instance, availableInputs];
-            if !available_inputs.is_null() {
-                let inputs_array = &*available_inputs;
-                let count = inputs_array.count();
-                
-                for i in 0..count {
-                    let input: *mut objc2::runtime::AnyObject = msg_send![inputs_array, objectAtIndex: i];
-                    if !input.is_null() {
-                        let port_name: *mut NSString = msg_send![input, portName];
-                        let uid: *mut NSString = msg_send![input, UID];
-                        
-                        if !port_name.is_null() && !uid.is_null() {
-                            let name_str = (*port_name).to_string();
-                            let uid_str = (*uid).to_string();
-                            
-                            devices.push(AudioDevice {
-                                id: uid_str,
-                                name: name_str,
-                                device_type: "microphone".to_string(),
-                            });
-                        }
-                    }
-                }
-            }
-            
-            // Get available outputs from current route
-            let current_route: *mut objc2::runtime::AnyObject = msg_send![shared_instance, currentRoute];
-            if !current_route.is_null() {
-                let outputs: *mut NSArray = msg_send![current_route, outputs];
-                if !outputs.is_null() {
-                    let outputs_array = &*outputs;
-                    let count = outputs_array.count();
-                    
-                    for i in 0..count {
-                        let output: *mut objc2::runtime::AnyObject = msg_send![outputs_array, objectAtIndex: i];
-                        if !output.is_null() {
-                            let port_name: *mut NSString = msg_send![output, portName];
-                            let uid: *mut NSString = msg_send![output, UID];
-                            
-                            if !port_name.is_null() && !uid.is_null() {
-                                let name_str = (*port_name).to_string();
-                                let uid_str = (*uid).to_string();
-                                
-                                devices.push(AudioDevice {
-                                    id: uid_str,
-                                    name: name_str,
-                                    device_type: "speaker".to_string(),
-                                });
-                            }
-                        }
-                    }
-                }
-            }
+        // Outputs come from Core Audio, which lists every output device (aggregate and virtual
+        // ones included) rather than just the current route; their UIDs are valid
+        // audioOutputDeviceId values
+        for (uid, name) in output_devices() {
+            devices.push(AudioDevice {
+                id: uid,
+                name,
+                device_type: "speaker".to_string(),
+            });
         }
         
-        // If no devices found via API, log the issue but don't add mock devices
         if devices.is_empty() {
-            println!("⚠️ No audio devices found via AVFoundation - this may indicate a permissions issue");
-            return Err(Error::new(Status::GenericFailure, "No audio devices available. Check microphone permissions."));
+            println!("⚠️ Neither AVCaptureDevice nor Core Audio reported an audio device");
+            return Err(Error::new(Status::GenericFailure, "No audio devices are connected"));
         }
         
         println!("✅ Found {} real audio devices", devices.len());
         Ok(devices)
     }
     
+    /// UID of the system's default input device; None without one
     pub fn get_preferred_microphone_device() -> Option<String> {
-        // Try to get the preferred device from AVAudioSession
         unsafe {
-            let session_class = class!(AVAudioSession);
-            let shared_instance: *mut objc2::runtime::AnyObject = msg_send![session_class, sharedInstance];
-            
-            if !shared_instance.is_null() {
-                // Get preferred input
-                let preferred_input: *mut objc2::runtime::AnyObject = msg_send![shared_instance, preferredInput];
-                if !preferred_input.is_null() {
-                    let uid: *mut NSString = msg_send![preferred_input, UID];
-                    if !uid.is_null() {
-                        return Some((*uid).to_string());
-                    }
-                }
-                
-                // Fallback to built-in microphone
-                let available_inputs: *mut NSArray = msg_send![shared_instance, availableInputs];
-                if !available_inputs.is_null() {
-                    let inputs_array = &*available_inputs;
-                    let count = inputs_array.count();
-                    
-                    for i in 0..count {
-                        let input: *mut objc2::runtime::AnyObject = msg_send![inputs_array, objectAtIndex: i];
-                        if !input.is_null() {
-                            let port_name: *mut NSString = msg_send![input, portName];
-                            if !port_name.is_null() {
-                                let name_str = (*port_name).to_string();
-                                if name_str.contains("Built-in") || name_str.contains("BuiltInMicrophoneDevice") {
-                                    let uid: *mut NSString = msg_send![input, UID];
-                                    if !uid.is_null() {
-                                        return Some((*uid).to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+            let media_type = NSString::from_str(AVMediaTypeAudio);
+            let device: *mut AnyObject = msg_send![class!(AVCaptureDevice), defaultDeviceWithMediaType: &*media_type];
+            if device.is_null() {
+                return None;
             }
+            let uid: *mut NSString = msg_send![device, uniqueID];
+            (!uid.is_null()).then(|| (*uid).to_string())
         }
-        
-        // Final fallback
-        Some("builtin-mic".to_string())
     }
     
+    /// Fail with the reason when the microphone can't be recorded; ScreenCaptureKit sets up
+    /// the capture itself, so there's nothing else to configure
     pub fn configure_audio_session() -> Result<()> {
-        println!("🔧 Configuring real audio session for recording");
-        
-        unsafe {
-            let session_class = class!(AVAudioSession);
-            let shared_instance: *mut objc2::runtime::AnyObject = msg_send![session_class, sharedInstance];
-            
-            if shared_instance.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to get AVAudioSession"));
-            }
-            
-            // Set category for recording
-            let category = NSString::from_str("AVAudioSessionCategoryPlayAndRecord");
-            let mut error: *mut objc2::runtime::AnyObject = ptr::null_mut();
-            let success: bool = msg_send![
-                shared_instance, 
-                setCategory: &*category,
-                error: &mut error
-            ];
-            
-            if !success {
-                return Err(Error::new(Status::GenericFailure, "Failed to set audio session category"));
-            }
-            
-            // Set active
-            let mut error: *mut objc2::runtime::AnyObject = ptr::null_mut();
-            let success: bool = msg_send![
-                shared_instance, 
-                setActive: true,
-                error: &mut error
-            ];
-            
-            if !success {
-                return Err(Error::new(Status::GenericFailure, "Failed to activate audio session"));
-            }
+        let media_type = NSString::from_str(AVMediaTypeAudio);
+        let status: isize = unsafe { msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: &*media_type] };
+        if status == AUTHORIZATION_NOT_DETERMINED {
+            println!("🎙️ Microphone access isn't decided yet; macOS asks when the capture starts");
+        }
+        match microphone_access_error(status) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_frames::ToneGenerator;
+
+    #[test]
+    fn test_gain_is_written_into_the_sample_buffer() {
+        let mut tone = ToneGenerator::new(48000, 2, 440.0, 0.0);
+        let sample = tone.next_sample();
+        let peak = |sample_buffer: &CMSampleBuffer| {
+            let mut peak = 0.0f32;
+            assert!(unsafe { with_float_samples(sample_buffer, |samples| peak = peak.max(compute_audio_levels(samples).peak as f32)) });
+            peak
+        };
+        let before = peak(sample.sample());
+        assert!(unsafe { apply_gain_to_sample_buffer(sample.sample(), 0.5) });
+        assert!((peak(sample.sample()) - before * 0.5).abs() < 1e-4, "The encoded buffer itself is scaled");
+    }
+
+    #[test]
+    fn test_only_boosts_are_soft_clipped() {
+        let mut attenuated = [0.95f32, -0.9];
+        apply_gain(&mut attenuated, 0.9);
+        assert_eq!(attenuated, [0.95 * 0.9, -0.9 * 0.9], "Attenuation is linear above the knee too");
+
+        let mut boosted = [0.5f32, -0.9];
+        apply_gain(&mut boosted, 4.0);
+        assert!(boosted.iter().all(|sample| sample.abs() < 1.0), "Boosted peaks stay below full scale");
+        assert!(boosted[0] > SOFT_CLIP_KNEE);
+    }
+
+    #[test]
+    fn test_microphone_access_errors_name_the_setting() {
+        assert!(microphone_access_error(AUTHORIZATION_NOT_DETERMINED).is_none(), "Undecided access is asked for on first use");
+        assert!(microphone_access_error(3).is_none());
+        let denied = microphone_access_error(AUTHORIZATION_DENIED).expect("Denied access is reported");
+        assert!(denied.reason.contains("Privacy & Security > Microphone"));
+        assert!(microphone_access_error(AUTHORIZATION_RESTRICTED).is_some());
+    }
+
+    #[test]
+    fn test_audio_devices_are_listed_without_an_audio_session() {
+        match AudioManager::get_available_audio_devices() {
+            Ok(devices) => assert!(devices.iter().all(|device| !device.id.is_empty())),
+            Err(e) => assert!(!e.reason.contains("AVAudioSession"), "Unexpected error: {}", e.reason),
+        }
+        if let Err(e) = AudioManager::configure_audio_session() {
+            assert!(e.reason.starts_with("Microphone access"), "Unexpected error: {}", e.reason);
         }
-        
-        println!("✅ Real audio session configured");
-        Ok(())
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,191 @@
+// Audio level monitor
+// Taps an input device with AVAudioEngine and reports RMS/peak levels without writing anything to disk
+
+use std::ffi::c_void;
+use std::ptr;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSString, NSError};
+use block2::RcBlock;
+use napi::{Result, Status, Error};
+
+use super::audio::compute_audio_levels;
+use super::core_audio::{
+    property_address, AudioObjectGetPropertyData,
+    AUDIO_HARDWARE_PROPERTY_TRANSLATE_UID_TO_DEVICE, AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL, AUDIO_OBJECT_SYSTEM_OBJECT,
+};
+use super::foundation::describe_nserror;
+use crate::AudioLevels;
+
+// Frames per tap callback (~21ms at 48kHz)
+const MONITOR_BUFFER_SIZE: u32 = 1024;
+
+// AudioUnit property and scope
+const kAudioOutputUnitProperty_CurrentDevice: u32 = 2000;
+const kAudioUnitScope_Global: u32 = 0;
+
+extern "C" {
+    fn AudioUnitSetProperty(
+        unit: *mut c_void,
+        property_id: u32,
+        scope: u32,
+        element: u32,
+        data: *const c_void,
+        data_size: u32,
+    ) -> i32;
+}
+
+/// Running input tap; dropping it tears the tap and engine down
+pub struct AudioMonitor {
+    engine: *mut AnyObject,
+}
+
+// Safety: the engine is only touched from start/stop, which the owner serializes
+unsafe impl Send for AudioMonitor {}
+
+impl AudioMonitor {
+    /// Start tapping `device_id` (an audio device UID, or the default input when None)
+    /// `on_levels` is called from the audio thread once per tap buffer
+    pub fn start<F>(device_id: Option<&str>, on_levels: F) -> Result<Self>
+    where
+        F: Fn(AudioLevels) + Send + 'static,
+    {
+        println!("🎚️ Starting audio monitor (device: {})", device_id.unwrap_or("default"));
+
+        unsafe {
+            let alloc: *mut AnyObject = msg_send![class!(AVAudioEngine), alloc];
+            let engine: *mut AnyObject = msg_send![alloc, init];
+            if engine.is_null() {
+                return Err(Error::new(Status::GenericFailure, "Failed to create AVAudioEngine"));
+            }
+            // From here on Drop releases the engine on every error path
+            let monitor = Self { engine };
+
+            let input_node: *mut AnyObject = msg_send![engine, inputNode];
+            if input_node.is_null() {
+                return Err(Error::new(Status::GenericFailure, "No audio input available"));
+            }
+
+            if let Some(device_id) = device_id {
+                Self::select_input_device(input_node, device_id)?;
+            }
+
+            let format: *mut AnyObject = msg_send![input_node, outputFormatForBus: 0usize];
+            let tap_block = RcBlock::new(move |buffer: *mut AnyObject, _when: *mut AnyObject| {
+                if let Some(levels) = Self::levels_for_buffer(buffer) {
+                    on_levels(levels);
+                }
+            });
+
+            let _: () = msg_send![
+                input_node,
+                installTapOnBus: 0usize,
+                bufferSize: MONITOR_BUFFER_SIZE,
+                format: format,
+                block: &*tap_block
+            ];
+
+            let _: () = msg_send![engine, prepare];
+            let mut error: *mut NSError = ptr::null_mut();
+            let started: bool = msg_send![engine, startAndReturnError: &mut error];
+            if !started {
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to start audio monitor: {}", describe_nserror(error)),
+                ));
+            }
+
+            println!("✅ Audio monitor running");
+            Ok(monitor)
+        }
+    }
+
+    /// Stop the tap and release the engine
+    pub fn stop(self) {
+        drop(self);
+    }
+
+    /// Point the input node's audio unit at the device with the given UID
+    unsafe fn select_input_device(input_node: *mut AnyObject, device_uid: &str) -> Result<()> {
+        let uid = NSString::from_str(device_uid);
+        let uid_ref: *const NSString = &*uid;
+        let address = property_address(AUDIO_HARDWARE_PROPERTY_TRANSLATE_UID_TO_DEVICE, AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL);
+
+        let mut device: u32 = 0;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let status = AudioObjectGetPropertyData(
+            AUDIO_OBJECT_SYSTEM_OBJECT,
+            &address,
+            std::mem::size_of::<*const NSString>() as u32,
+            &uid_ref as *const *const NSString as *const c_void,
+            &mut size,
+            &mut device as *mut u32 as *mut c_void,
+        );
+        if status != 0 || device == 0 {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("Audio device not found: {}", device_uid),
+            ));
+        }
+
+        let audio_unit: *mut c_void = msg_send![input_node, audioUnit];
+        let status = AudioUnitSetProperty(
+            audio_unit,
+            kAudioOutputUnitProperty_CurrentDevice,
+            kAudioUnitScope_Global,
+            0,
+            &device as *const u32 as *const c_void,
+            std::mem::size_of::<u32>() as u32,
+        );
+        if status != 0 {
+            return Err(Error::new(
+                Status::GenericFailure,
+                format!("Failed to select audio device {} (OSStatus {})", device_uid, status),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Compute levels across all channels of an AVAudioPCMBuffer
+    unsafe fn levels_for_buffer(buffer: *mut AnyObject) -> Option<AudioLevels> {
+        if buffer.is_null() {
+            return None;
+        }
+
+        let channel_data: *const *const f32 = msg_send![buffer, floatChannelData];
+        let frame_length: u32 = msg_send![buffer, frameLength];
+        if channel_data.is_null() || frame_length == 0 {
+            return None;
+        }
+
+        let format: *mut AnyObject = msg_send![buffer, format];
+        let channel_count: u32 = msg_send![format, channelCount];
+        let is_interleaved: bool = msg_send![format, isInterleaved];
+
+        let samples: Vec<f32> = if is_interleaved {
+            std::slice::from_raw_parts(*channel_data, (frame_length * channel_count) as usize).to_vec()
+        } else {
+            (0..channel_count as usize)
+                .flat_map(|channel| std::slice::from_raw_parts(*channel_data.add(channel), frame_length as usize))
+                .copied()
+                .collect()
+        };
+
+        Some(compute_audio_levels(&samples))
+    }
+}
+
+impl Drop for AudioMonitor {
+    fn drop(&mut self) {
+        unsafe {
+            let input_node: *mut AnyObject = msg_send![self.engine, inputNode];
+            if !input_node.is_null() {
+                let _: () = msg_send![input_node, removeTapOnBus: 0usize];
+            }
+            let _: () = msg_send![self.engine, stop];
+            let _: () = msg_send![self.engine, release];
+        }
+        println!("🛑 Audio monitor stopped");
+    }
+}
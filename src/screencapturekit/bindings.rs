@@ -2,17 +2,25 @@
 // This module provides direct access to ScreenCaptureKit APIs without complex logic
 
 use objc2::runtime::AnyObject;
-use objc2::{msg_send, class};
+use objc2::{msg_send, class, sel};
 use objc2_foundation::{NSString, NSError, NSArray};
 use objc2_core_media::{CMSampleBuffer, CMTime};
 use block2::{Block, StackBlock};
+use futures::future::BoxFuture;
+use napi::Result as NapiResult;
 use std::ptr;
 
 pub use super::types::*;
-use super::foundation::CGRect;
+use super::features::Feature;
+use super::foundation::{CGPoint, CGRect, PermissionHelpers, catch_objc_exception};
+use super::content::{AsyncContentManager, ShareableContent};
+use super::capture_queue::{on_capture_queue, debug_assert_on_capture_queue};
+use super::session::SessionState;
 
 /// Raw ScreenCaptureKit API bindings
 /// This provides direct access to ScreenCaptureKit APIs without complex logic
+/// SCStream and SCShareableContent calls hop onto the capture queue (see capture_queue); the
+/// `*_on_queue` halves assume they're already there
 pub struct ScreenCaptureKitAPI;
 
 impl ScreenCaptureKitAPI {
@@ -21,6 +29,14 @@ impl ScreenCaptureKitAPI {
     where
         F: FnOnce(*mut SCShareableContent, *mut NSError) + Send + 'static,
     {
+        on_capture_queue(|| Self::get_shareable_content_on_queue(completion))
+    }
+    
+    unsafe fn get_shareable_content_on_queue<F>(completion: F)
+    where
+        F: FnOnce(*mut SCShareableContent, *mut NSError) + Send + 'static,
+    {
+        debug_assert_on_capture_queue("getShareableContent");
         use std::sync::{Arc, Mutex};
         
         let completion = Arc::new(Mutex::new(Some(completion)));
@@ -42,13 +58,6 @@ impl ScreenCaptureKitAPI {
         ];
     }
     
-    /// Get shareable content synchronously (placeholder - not available in real ScreenCaptureKit)
-    pub unsafe fn get_shareable_content_sync() -> Result<*mut SCShareableContent, String> {
-        // This is a placeholder - real ScreenCaptureKit only has async methods
-        // Return an error to indicate this isn't supported
-        Err("Synchronous content retrieval not supported by ScreenCaptureKit".to_string())
-    }
-
     /// Create a content filter with display
     pub unsafe fn create_content_filter_with_display(display: *mut SCDisplay) -> *mut SCContentFilter {
         let class = class!(SCContentFilter);
@@ -56,6 +65,22 @@ impl ScreenCaptureKitAPI {
         msg_send![alloc, initWithDisplay: display]
     }
     
+    /// Create a content filter with display, leaving out the given windows
+    pub unsafe fn create_content_filter_with_display_excluding_windows(
+        display: *mut SCDisplay,
+        excluded_windows: &[*mut SCWindow],
+    ) -> *mut SCContentFilter {
+        let windows_array: *mut NSArray<SCWindow> = msg_send![
+            class!(NSArray),
+            arrayWithObjects: excluded_windows.as_ptr(),
+            count: excluded_windows.len()
+        ];
+        
+        let class = class!(SCContentFilter);
+        let alloc: *mut AnyObject = msg_send![class, alloc];
+        msg_send![alloc, initWithDisplay: display, excludingWindows: windows_array]
+    }
+    
     /// Create a content filter with window
     pub unsafe fn create_content_filter_with_window(window: *mut SCWindow) -> *mut SCContentFilter {
         let class = class!(SCContentFilter);
@@ -69,6 +94,13 @@ impl ScreenCaptureKitAPI {
         let alloc: *mut AnyObject = msg_send![class, alloc];
         msg_send![alloc, init]
     }
+
+    /// Release a reference owned through alloc/init or new
+    pub unsafe fn release_object(object: *mut AnyObject) {
+        if !object.is_null() {
+            let _: () = msg_send![object, release];
+        }
+    }
     
     /// Configure stream configuration
     pub unsafe fn configure_stream_configuration(
@@ -96,12 +128,111 @@ impl ScreenCaptureKitAPI {
         let _: () = msg_send![config, setPixelFormat: pixel_format];
     }
 
+    /// Set the captured frame size in pixels
+    pub unsafe fn set_output_size(config: *mut SCStreamConfiguration, width: u32, height: u32) {
+        let _: () = msg_send![config, setWidth: width];
+        let _: () = msg_send![config, setHeight: height];
+    }
+
+    /// Override the minimum interval between delivered frames (kCMTimeZero = no limit)
+    pub unsafe fn set_minimum_frame_interval(config: *mut SCStreamConfiguration, interval: CMTime) {
+        let _: () = msg_send![config, setMinimumFrameInterval: interval];
+    }
+    
+    /// Enable scaling to the configured size, optionally keeping the source aspect ratio
+    /// Returns false when `preservesAspectRatio` isn't available (before macOS 14)
+    pub unsafe fn set_scaling(
+        config: *mut SCStreamConfiguration,
+        scales_to_fit: bool,
+        preserves_aspect_ratio: bool,
+    ) -> bool {
+        let _: () = msg_send![config, setScalesToFit: scales_to_fit];
+        
+        let class = class!(SCStreamConfiguration);
+        let supported: bool = msg_send![class, instancesRespondToSelector: sel!(setPreservesAspectRatio:)];
+        if supported {
+            let _: () = msg_send![config, setPreservesAspectRatio: preserves_aspect_ratio];
+        }
+        supported
+    }
+    
+    /// Capture only part of the source, in source points (display/window top-left origin)
+    pub unsafe fn set_source_rect(config: *mut SCStreamConfiguration, rect: CGRect) {
+        let _: () = msg_send![config, setSourceRect: rect];
+    }
+    
+    /// Fill areas the captured content doesn't cover, such as transparent window regions
+    pub unsafe fn set_background_color(config: *mut SCStreamConfiguration, color: RgbaColor) {
+        extern "C" {
+            fn CGColorCreateSRGB(red: f64, green: f64, blue: f64, alpha: f64) -> *mut std::ffi::c_void;
+        }
+        
+        // The property doesn't retain the color, so the reference is kept for the process lifetime
+        let color = CGColorCreateSRGB(color.red, color.green, color.blue, color.alpha);
+        if !color.is_null() {
+            let _: () = msg_send![config, setBackgroundColor: color];
+        }
+    }
+    
+    /// Convert captured frames into the named CGColorSpace (e.g. "kCGColorSpaceSRGB")
+    pub unsafe fn set_color_space_name(config: *mut SCStreamConfiguration, name: &str) {
+        let name = NSString::from_str(name);
+        let _: () = msg_send![config, setColorSpaceName: &*name];
+    }
+    
+    /// Source size of a content filter in points, and its points-to-pixels scale (macOS 14+)
+    pub unsafe fn get_content_filter_rect(filter: *mut SCContentFilter) -> Option<(CGRect, f64)> {
+        if filter.is_null() {
+            return None;
+        }
+        
+        let class = class!(SCContentFilter);
+        let supported: bool = msg_send![class, instancesRespondToSelector: sel!(contentRect)];
+        if !supported {
+            return None;
+        }
+        
+        let rect: CGRect = msg_send![filter, contentRect];
+        let scale: f32 = msg_send![filter, pointPixelScale];
+        if rect.size.width <= 0.0 || rect.size.height <= 0.0 {
+            return None;
+        }
+        Some((rect, scale as f64))
+    }
+
+    /// Check whether SCStreamConfiguration supports the presenter overlay privacy alert (macOS 14.2+)
+    pub unsafe fn supports_presenter_overlay_privacy_alert() -> bool {
+        Feature::PresenterOverlayPrivacyAlert.is_available()
+    }
+
+    /// Set the presenter overlay privacy alert on a stream configuration
+    /// Returns false when the running OS doesn't support the setting
+    pub unsafe fn set_presenter_overlay_privacy_alert(
+        config: *mut SCStreamConfiguration,
+        setting: PresenterOverlayAlertSetting,
+    ) -> bool {
+        if !Self::supports_presenter_overlay_privacy_alert() {
+            return false;
+        }
+        let _: () = msg_send![config, setPresenterOverlayPrivacyAlertSetting: setting as isize];
+        true
+    }
+
     /// Create SCStream
     pub unsafe fn create_stream(
         filter: *mut SCContentFilter,
         configuration: *mut SCStreamConfiguration,
         delegate: *mut AnyObject,
     ) -> *mut SCStream {
+        on_capture_queue(|| Self::create_stream_on_queue(filter, configuration, delegate))
+    }
+    
+    unsafe fn create_stream_on_queue(
+        filter: *mut SCContentFilter,
+        configuration: *mut SCStreamConfiguration,
+        delegate: *mut AnyObject,
+    ) -> *mut SCStream {
+        debug_assert_on_capture_queue("SCStream init");
         println!("🔧 PRODUCTION: Creating SCStream with delegate: {:p}", delegate);
         
         let class = class!(SCStream);
@@ -135,16 +266,32 @@ impl ScreenCaptureKitAPI {
     where
         F: FnOnce(Option<&NSError>) + Send + 'static,
     {
+        on_capture_queue(|| Self::start_stream_capture_on_queue(stream, completion))
+    }
+    
+    unsafe fn start_stream_capture_on_queue<F>(stream: *mut SCStream, completion: F)
+    where
+        F: FnOnce(Option<&NSError>) + Send + 'static,
+    {
+        debug_assert_on_capture_queue("SCStream startCapture");
         println!("🚀 PRODUCTION: Starting ScreenCaptureKit with startCaptureWithCompletionHandler");
         
         // CRITICAL FIX: We need to use startCaptureWithCompletionHandler instead of startCapture
         // For now, let's use the synchronous version but add debug output to see if callbacks work
         
         // First, try the synchronous version with extra logging
+        // Starting a stream that is already running throws; catch it rather than abort
         println!("🔧 PRODUCTION: Calling startCapture on stream: {:p}", stream);
-        let result: bool = msg_send![stream, startCapture];
+        let result = catch_objc_exception(|| {
+            let started: bool = msg_send![stream, startCapture];
+            started
+        });
         
-        if result {
+        if let Err(exception) = result {
+            println!("❌ PRODUCTION: startCapture threw: {}", exception);
+            let error = Self::create_error(&format!("SCStream startCapture threw {}", exception), 1003);
+            completion(if error.is_null() { None } else { Some(&*error) });
+        } else if result == Ok(true) {
             println!("✅ PRODUCTION: startCapture returned success - checking if delegate callbacks work");
             
             // Add a small delay to let the stream initialize
@@ -155,15 +302,8 @@ impl ScreenCaptureKitAPI {
         } else {
             println!("❌ PRODUCTION: startCapture failed");
             
-            // Create a simple error
-            let error_class = class!(NSError);
-            let error_domain = NSString::from_str("ScreenCaptureKit");
-            let error: *mut NSError = msg_send![
-                error_class,
-                errorWithDomain: &*error_domain,
-                code: 1001,
-                userInfo: std::ptr::null::<*mut AnyObject>()
-            ];
+            // startCapture doesn't report an NSError; build one that says what failed
+            let error = Self::create_error("SCStream startCapture returned NO", 1001);
             
             if !error.is_null() {
                 completion(Some(&*error));
@@ -177,17 +317,61 @@ impl ScreenCaptureKitAPI {
         // if the delegate is properly set on the stream
     }
     
+    /// NSError in the "ScreenCaptureKit" domain for failures the API reports without one
+    unsafe fn create_error(description: &str, code: isize) -> *mut NSError {
+        let error_domain = NSString::from_str("ScreenCaptureKit");
+        let description_key = NSString::from_str("NSLocalizedDescription");
+        let description = NSString::from_str(description);
+        let user_info: *mut AnyObject = msg_send![
+            class!(NSDictionary),
+            dictionaryWithObject: &*description,
+            forKey: &*description_key
+        ];
+        msg_send![
+            class!(NSError),
+            errorWithDomain: &*error_domain,
+            code: code,
+            userInfo: user_info
+        ]
+    }
+    
     /// Stop stream capture asynchronously (simplified)
     pub unsafe fn stop_stream_capture_async<F>(stream: *mut SCStream, completion: F)
     where
         F: FnOnce(Option<&NSError>) + Send + 'static,
     {
-        // Use a simpler approach without StackBlock for now
-        // In a real implementation, this would use proper Objective-C blocks
-        let _: () = msg_send![stream, stopCapture];
+        on_capture_queue(|| Self::stop_stream_capture_on_queue(stream, completion))
+    }
+    
+    unsafe fn stop_stream_capture_on_queue<F>(stream: *mut SCStream, completion: F)
+    where
+        F: FnOnce(Option<&NSError>) + Send + 'static,
+    {
+        debug_assert_on_capture_queue("SCStream stopCapture");
+        use std::sync::{Arc, Mutex};
         
-        // Call completion immediately for now (placeholder)
-        completion(None);
+        // The handler fires once ScreenCaptureKit has stopped delivering samples, which it
+        // occasionally never does; callers bound the wait
+        let completion = Arc::new(Mutex::new(Some(completion)));
+        let block = StackBlock::new({
+            let completion = completion.clone();
+            move |error: *mut NSError| {
+                if let Some(completion) = completion.lock().unwrap().take() {
+                    completion(if error.is_null() { None } else { Some(&*error) });
+                }
+            }
+        });
+        let block = block.copy();
+        let result = catch_objc_exception(|| {
+            let _: () = msg_send![stream, stopCaptureWithCompletionHandler: &*block];
+        });
+        
+        if let Err(exception) = result {
+            if let Some(completion) = completion.lock().unwrap().take() {
+                let error = Self::create_error(&format!("SCStream stopCapture threw {}", exception), 1004);
+                completion(if error.is_null() { None } else { Some(&*error) });
+            }
+        }
     }
 
     /// Get display information from SCDisplay
@@ -277,6 +461,92 @@ impl ScreenCaptureKitAPI {
         Ok(windows)
     }
 
+    /// Extract running applications from shareable content
+    pub unsafe fn extract_applications(shareable_content: *mut SCShareableContent) -> Vec<*mut AnyObject> {
+        if shareable_content.is_null() {
+            return Vec::new();
+        }
+        
+        let applications: *mut NSArray<AnyObject> = msg_send![shareable_content, applications];
+        if applications.is_null() {
+            return Vec::new();
+        }
+        
+        let count: usize = msg_send![applications, count];
+        (0..count)
+            .map(|i| -> *mut AnyObject { msg_send![applications, objectAtIndex: i] })
+            .filter(|application| !application.is_null())
+            .collect()
+    }
+    
+    /// Bundle identifier of an SCRunningApplication (empty when it has none)
+    pub unsafe fn get_application_bundle_id(application: *mut AnyObject) -> String {
+        let bundle_id: *mut NSString = msg_send![application, bundleIdentifier];
+        if bundle_id.is_null() {
+            String::new()
+        } else {
+            (*bundle_id).to_string()
+        }
+    }
+    
+    /// Bundle identifiers of the applications in shareable content
+    pub unsafe fn get_application_bundle_ids(shareable_content: *mut SCShareableContent) -> Vec<String> {
+        Self::extract_applications(shareable_content).into_iter()
+            .map(|application| Self::get_application_bundle_id(application))
+            .filter(|bundle_id| !bundle_id.is_empty())
+            .collect()
+    }
+    
+    /// Per-application audio exclusion with windows kept on screen needs macOS 15
+    pub fn supports_app_audio_exclusion() -> bool {
+        Feature::AppAudioExclusion.is_available()
+    }
+    
+    /// Create a display filter that drops the audio of `audio_bundle_ids` while keeping their
+    /// windows, and drops process `excluded_process_id` (audio and windows) entirely
+    pub unsafe fn create_content_filter_with_display_excluding_app_audio(
+        shareable_content: *mut SCShareableContent,
+        display: *mut SCDisplay,
+        audio_bundle_ids: &[String],
+        excluded_process_id: Option<i32>,
+    ) -> *mut SCContentFilter {
+        let excluded_applications: Vec<*mut AnyObject> = Self::extract_applications(shareable_content).into_iter()
+            .filter(|&application| {
+                let process_id: i32 = msg_send![application, processID];
+                Some(process_id) == excluded_process_id
+                    || audio_bundle_ids.contains(&Self::get_application_bundle_id(application))
+            })
+            .collect();
+        
+        // Excepted windows stay visible even though their application is excluded
+        let excepted_windows: Vec<*mut SCWindow> = Self::extract_windows(shareable_content).unwrap_or_default().into_iter()
+            .filter(|&window| {
+                let owner: *mut AnyObject = msg_send![window, owningApplication];
+                if owner.is_null() {
+                    return false;
+                }
+                let process_id: i32 = msg_send![owner, processID];
+                Some(process_id) != excluded_process_id
+                    && audio_bundle_ids.contains(&Self::get_application_bundle_id(owner))
+            })
+            .collect();
+        
+        let applications_array: *mut NSArray<AnyObject> = msg_send![
+            class!(NSArray),
+            arrayWithObjects: excluded_applications.as_ptr(),
+            count: excluded_applications.len()
+        ];
+        let windows_array: *mut NSArray<SCWindow> = msg_send![
+            class!(NSArray),
+            arrayWithObjects: excepted_windows.as_ptr(),
+            count: excepted_windows.len()
+        ];
+        
+        let class = class!(SCContentFilter);
+        let alloc: *mut AnyObject = msg_send![class, alloc];
+        msg_send![alloc, initWithDisplay: display, excludingApplications: applications_array, exceptingWindows: windows_array]
+    }
+
     /// Create content filter with display ID (simpler approach)
     pub unsafe fn create_content_filter_with_display_id(display_id: u32) -> *mut SCContentFilter {
         // For now, create a basic filter that captures all content
@@ -292,10 +562,199 @@ impl ScreenCaptureKitAPI {
     }
 }
 
+/// Everything the recording pipeline asks of ScreenCaptureKit
+/// `ScreenCaptureKitAPI` talks to the real framework; tests substitute a mock. Pointers a
+/// backend returns are opaque handles that are only ever passed back to the same backend
+pub trait ScreenCaptureBackend: Send + Sync {
+    fn check_screen_recording_permission(&self) -> bool;
+    fn session_state(&self) -> SessionState;
+    /// Cursor position in global display points, top-left origin
+    fn cursor_location(&self) -> CGPoint;
+    fn get_shareable_content(&self) -> BoxFuture<'static, NapiResult<ShareableContent>>;
+
+    unsafe fn extract_displays(&self, shareable_content: *mut SCShareableContent) -> Result<Vec<*mut SCDisplay>, String>;
+    unsafe fn extract_windows(&self, shareable_content: *mut SCShareableContent) -> Result<Vec<*mut SCWindow>, String>;
+    unsafe fn get_display_info(&self, display: *mut SCDisplay) -> (u32, String, u32, u32);
+    unsafe fn get_window_info(&self, window: *mut SCWindow) -> (u32, String, u32, u32);
+
+    unsafe fn create_display_filter(&self, display: *mut SCDisplay, excluded_windows: &[*mut SCWindow]) -> *mut SCContentFilter;
+    unsafe fn get_application_bundle_ids(&self, shareable_content: *mut SCShareableContent) -> Vec<String>;
+    fn supports_app_audio_exclusion(&self) -> bool;
+    unsafe fn create_display_filter_excluding_app_audio(
+        &self,
+        shareable_content: *mut SCShareableContent,
+        display: *mut SCDisplay,
+        audio_bundle_ids: &[String],
+        excluded_process_id: Option<i32>,
+    ) -> *mut SCContentFilter;
+    unsafe fn create_window_filter(&self, window: *mut SCWindow) -> *mut SCContentFilter;
+    unsafe fn create_basic_filter(&self) -> *mut SCContentFilter;
+    unsafe fn get_content_filter_rect(&self, filter: *mut SCContentFilter) -> Option<(CGRect, f64)>;
+
+    unsafe fn create_stream_configuration(&self) -> *mut SCStreamConfiguration;
+    unsafe fn release_object(&self, object: *mut AnyObject);
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn configure_stream_configuration(
+        &self,
+        config: *mut SCStreamConfiguration,
+        width: u32,
+        height: u32,
+        fps: u32,
+        shows_cursor: bool,
+        captures_audio: bool,
+        pixel_format: u32,
+    );
+    unsafe fn set_output_size(&self, config: *mut SCStreamConfiguration, width: u32, height: u32);
+    unsafe fn set_minimum_frame_interval(&self, config: *mut SCStreamConfiguration, interval: CMTime);
+    unsafe fn set_scaling(&self, config: *mut SCStreamConfiguration, scales_to_fit: bool, preserves_aspect_ratio: bool) -> bool;
+    unsafe fn set_source_rect(&self, config: *mut SCStreamConfiguration, rect: CGRect);
+    unsafe fn set_color_space_name(&self, config: *mut SCStreamConfiguration, name: &str);
+    unsafe fn set_background_color(&self, config: *mut SCStreamConfiguration, color: RgbaColor);
+    fn supports_presenter_overlay_privacy_alert(&self) -> bool;
+    unsafe fn set_presenter_overlay_privacy_alert(&self, config: *mut SCStreamConfiguration, setting: PresenterOverlayAlertSetting) -> bool;
+
+    unsafe fn create_stream(&self, filter: *mut SCContentFilter, configuration: *mut SCStreamConfiguration, delegate: *mut AnyObject) -> *mut SCStream;
+    unsafe fn start_stream_capture(&self, stream: *mut SCStream, completion: Box<dyn FnOnce(Option<&NSError>) + Send>);
+    unsafe fn stop_stream_capture(&self, stream: *mut SCStream, completion: Box<dyn FnOnce(Option<&NSError>) + Send>);
+}
+
+impl ScreenCaptureBackend for ScreenCaptureKitAPI {
+    fn check_screen_recording_permission(&self) -> bool {
+        unsafe { PermissionHelpers::check_screen_recording_permission() }
+    }
+
+    fn session_state(&self) -> SessionState {
+        super::session::session_state()
+    }
+
+    fn cursor_location(&self) -> CGPoint {
+        unsafe { super::cursor_track::cursor_location() }
+    }
+
+    fn get_shareable_content(&self) -> BoxFuture<'static, NapiResult<ShareableContent>> {
+        Box::pin(AsyncContentManager::get_shareable_content())
+    }
+
+    unsafe fn extract_displays(&self, shareable_content: *mut SCShareableContent) -> Result<Vec<*mut SCDisplay>, String> {
+        Self::extract_displays(shareable_content)
+    }
+
+    unsafe fn extract_windows(&self, shareable_content: *mut SCShareableContent) -> Result<Vec<*mut SCWindow>, String> {
+        Self::extract_windows(shareable_content)
+    }
+
+    unsafe fn get_display_info(&self, display: *mut SCDisplay) -> (u32, String, u32, u32) {
+        Self::get_display_info(display)
+    }
+
+    unsafe fn get_window_info(&self, window: *mut SCWindow) -> (u32, String, u32, u32) {
+        Self::get_window_info(window)
+    }
+
+    unsafe fn create_display_filter(&self, display: *mut SCDisplay, excluded_windows: &[*mut SCWindow]) -> *mut SCContentFilter {
+        if excluded_windows.is_empty() {
+            Self::create_content_filter_with_display(display)
+        } else {
+            Self::create_content_filter_with_display_excluding_windows(display, excluded_windows)
+        }
+    }
+
+    unsafe fn get_application_bundle_ids(&self, shareable_content: *mut SCShareableContent) -> Vec<String> {
+        Self::get_application_bundle_ids(shareable_content)
+    }
+
+    fn supports_app_audio_exclusion(&self) -> bool {
+        Self::supports_app_audio_exclusion()
+    }
+
+    unsafe fn create_display_filter_excluding_app_audio(
+        &self,
+        shareable_content: *mut SCShareableContent,
+        display: *mut SCDisplay,
+        audio_bundle_ids: &[String],
+        excluded_process_id: Option<i32>,
+    ) -> *mut SCContentFilter {
+        Self::create_content_filter_with_display_excluding_app_audio(shareable_content, display, audio_bundle_ids, excluded_process_id)
+    }
+
+    unsafe fn create_window_filter(&self, window: *mut SCWindow) -> *mut SCContentFilter {
+        Self::create_content_filter_with_window(window)
+    }
+
+    unsafe fn create_basic_filter(&self) -> *mut SCContentFilter {
+        Self::create_content_filter_with_display_id(1)
+    }
+
+    unsafe fn get_content_filter_rect(&self, filter: *mut SCContentFilter) -> Option<(CGRect, f64)> {
+        Self::get_content_filter_rect(filter)
+    }
+
+    unsafe fn create_stream_configuration(&self) -> *mut SCStreamConfiguration {
+        Self::create_stream_configuration()
+    }
+
+    unsafe fn release_object(&self, object: *mut AnyObject) {
+        Self::release_object(object)
+    }
+
+    unsafe fn configure_stream_configuration(
+        &self,
+        config: *mut SCStreamConfiguration,
+        width: u32,
+        height: u32,
+        fps: u32,
+        shows_cursor: bool,
+        captures_audio: bool,
+        pixel_format: u32,
+    ) {
+        Self::configure_stream_configuration(config, width, height, fps, shows_cursor, captures_audio, pixel_format)
+    }
+
+    unsafe fn set_output_size(&self, config: *mut SCStreamConfiguration, width: u32, height: u32) {
+        Self::set_output_size(config, width, height)
+    }
+
+    unsafe fn set_minimum_frame_interval(&self, config: *mut SCStreamConfiguration, interval: CMTime) {
+        Self::set_minimum_frame_interval(config, interval)
+    }
+
+    unsafe fn set_scaling(&self, config: *mut SCStreamConfiguration, scales_to_fit: bool, preserves_aspect_ratio: bool) -> bool {
+        Self::set_scaling(config, scales_to_fit, preserves_aspect_ratio)
+    }
+
+    unsafe fn set_source_rect(&self, config: *mut SCStreamConfiguration, rect: CGRect) {
+        Self::set_source_rect(config, rect)
+    }
+
+    unsafe fn set_color_space_name(&self, config: *mut SCStreamConfiguration, name: &str) {
+        Self::set_color_space_name(config, name)
+    }
+
+    unsafe fn set_background_color(&self, config: *mut SCStreamConfiguration, color: RgbaColor) {
+        Self::set_background_color(config, color)
+    }
+
+    fn supports_presenter_overlay_privacy_alert(&self) -> bool {
+        unsafe { Self::supports_presenter_overlay_privacy_alert() }
+    }
+
+    unsafe fn set_presenter_overlay_privacy_alert(&self, config: *mut SCStreamConfiguration, setting: PresenterOverlayAlertSetting) -> bool {
+        Self::set_presenter_overlay_privacy_alert(config, setting)
+    }
+
+    unsafe fn create_stream(&self, filter: *mut SCContentFilter, configuration: *mut SCStreamConfiguration, delegate: *mut AnyObject) -> *mut SCStream {
+        Self::create_stream(filter, configuration, delegate)
+    }
+
+    unsafe fn start_stream_capture(&self, stream: *mut SCStream, completion: Box<dyn FnOnce(Option<&NSError>) + Send>) {
+        Self::start_stream_capture_async(stream, completion)
+    }
+
+    unsafe fn stop_stream_capture(&self, stream: *mut SCStream, completion: Box<dyn FnOnce(Option<&NSError>) + Send>) {
+        Self::stop_stream_capture_async(stream, completion)
+    }
+}
+
 // Pixel format constants for ScreenCaptureKit
 pub const kCVPixelFormatType_32BGRA: u32 = 0x42475241; // 'BGRA'
 pub const kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange: u32 = 0x34323076; // '420v' as hex
-
-// Color space constants
-pub const kCGColorSpaceDisplayP3: u32 = 0;
-pub const kCGColorSpaceSRGB: u32 = 1;
\ No newline at end of file
@@ -0,0 +1,165 @@
+// Sample buffer validation
+// A debugging aid for "green frames" and "wrong size" output: with validateBuffers on, every
+// video sample is compared with what the stream was configured to deliver (pixel format,
+// dimensions, a valid presentation time later than the last) before it's encoded. A mismatch
+// is reported once when it appears, with the frame it started at, instead of for every frame
+
+use std::sync::Mutex;
+use objc2_core_media::{CMSampleBuffer, CMTime};
+use objc2_core_video::CVPixelBuffer;
+
+use super::drift;
+
+// CMTimeFlags bits
+const TIME_FLAG_VALID: u32 = 1 << 0;
+const TIME_FLAGS_NOT_NUMERIC: u32 = (1 << 2) | (1 << 3) | (1 << 4); // +/-infinity, indefinite
+
+extern "C" {
+    fn CMSampleBufferGetImageBuffer(sbuf: &CMSampleBuffer) -> *mut CVPixelBuffer;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CVPixelBufferGetPixelFormatType(pixel_buffer: *mut CVPixelBuffer) -> u32;
+    fn CVPixelBufferGetWidth(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetHeight(pixel_buffer: *mut CVPixelBuffer) -> usize;
+}
+
+/// What the stream was configured to deliver
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExpectedBuffer {
+    pub pixel_format: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// What a sample actually carried
+#[derive(Debug, Clone, Copy)]
+pub struct BufferDescription {
+    pub pixel_format: u32,
+    pub width: usize,
+    pub height: usize,
+    pub presentation_time: CMTime,
+}
+
+/// A pixel format code as its four characters, e.g. "BGRA" or "420v"
+pub fn fourcc_string(code: u32) -> String {
+    code.to_be_bytes().iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '?' }).collect()
+}
+
+/// Whether `time` is a valid, finite presentation time
+fn is_numeric(time: CMTime) -> bool {
+    let (timescale, flags) = (time.timescale, time.flags.0);
+    flags & TIME_FLAG_VALID != 0 && flags & TIME_FLAGS_NOT_NUMERIC == 0 && timescale > 0
+}
+
+/// How `actual` differs from `expected`; `previous_time` is the last valid presentation time
+/// in seconds, which a new sample must come after
+pub fn buffer_problems(actual: &BufferDescription, expected: &ExpectedBuffer, previous_time: Option<f64>) -> Vec<String> {
+    let mut problems = Vec::new();
+    if actual.pixel_format != expected.pixel_format {
+        problems.push(format!(
+            "pixel format '{}' instead of the configured '{}'",
+            fourcc_string(actual.pixel_format),
+            fourcc_string(expected.pixel_format),
+        ));
+    }
+    if actual.width != expected.width as usize || actual.height != expected.height as usize {
+        problems.push(format!(
+            "{}x{} pixels instead of the configured {}x{}",
+            actual.width, actual.height, expected.width, expected.height,
+        ));
+    }
+
+    // CMTime is packed, so its fields are copied out before formatting
+    let time = actual.presentation_time;
+    let (value, timescale, flags) = (time.value, time.timescale, time.flags.0);
+    if !is_numeric(time) {
+        problems.push(format!("invalid presentation time (value {}, timescale {}, flags {:#x})", value, timescale, flags));
+    } else if let Some(previous) = previous_time.filter(|&previous| drift::seconds(time) <= previous) {
+        problems.push(format!(
+            "presentation time {:.6}s isn't after the previous sample's {:.6}s",
+            drift::seconds(time), previous,
+        ));
+    }
+    problems
+}
+
+#[derive(Debug, Default)]
+struct ValidatorState {
+    frames: u64,
+    previous_time: Option<f64>,
+    // What was last reported, so a lasting mismatch is only logged when it starts or changes
+    reported: Vec<String>,
+}
+
+/// Checks each video sample against the configured stream; see `buffer_problems`
+#[derive(Debug)]
+pub struct BufferValidator {
+    expected: ExpectedBuffer,
+    state: Mutex<ValidatorState>,
+}
+
+impl BufferValidator {
+    pub fn new(expected: ExpectedBuffer) -> Self {
+        Self { expected, state: Mutex::new(ValidatorState::default()) }
+    }
+
+    /// Check a sample, logging a warning when it shows a new problem. Samples without pixels
+    /// (idle frames) are skipped
+    pub fn check(&self, sample_buffer: &CMSampleBuffer) {
+        let actual = unsafe {
+            let pixel_buffer = CMSampleBufferGetImageBuffer(sample_buffer);
+            if pixel_buffer.is_null() {
+                return;
+            }
+            BufferDescription {
+                pixel_format: CVPixelBufferGetPixelFormatType(pixel_buffer),
+                width: CVPixelBufferGetWidth(pixel_buffer),
+                height: CVPixelBufferGetHeight(pixel_buffer),
+                presentation_time: CMSampleBufferGetPresentationTimeStamp(sample_buffer),
+            }
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.frames += 1;
+        let problems = buffer_problems(&actual, &self.expected, state.previous_time);
+        if is_numeric(actual.presentation_time) {
+            state.previous_time = Some(drift::seconds(actual.presentation_time));
+        }
+
+        if problems != state.reported {
+            if problems.is_empty() {
+                println!("✅ Buffer validation: frame {} matches the configured stream again", state.frames);
+            } else {
+                println!("⚠️ Buffer validation: frame {} has {}", state.frames, problems.join("; "));
+            }
+            state.reported = problems;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::recording::kCVPixelFormatType_32BGRA;
+
+    fn time(value: i64, flags: u32) -> CMTime {
+        CMTime { value, timescale: 600, flags: objc2_core_media::CMTimeFlags(flags), epoch: 0 }
+    }
+
+    #[test]
+    fn test_mismatched_buffers_are_described() {
+        let expected = ExpectedBuffer { pixel_format: kCVPixelFormatType_32BGRA, width: 1920, height: 1080 };
+        let good = BufferDescription { pixel_format: kCVPixelFormatType_32BGRA, width: 1920, height: 1080, presentation_time: time(600, 1) };
+        assert!(buffer_problems(&good, &expected, Some(0.5)).is_empty());
+
+        let wrong = BufferDescription { pixel_format: u32::from_be_bytes(*b"420v"), width: 1280, ..good };
+        let problems = buffer_problems(&wrong, &expected, None);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("'420v'") && problems[0].contains("'BGRA'"));
+        assert!(problems[1].contains("1280x1080"));
+
+        let invalid = BufferDescription { presentation_time: time(0, 0), ..good };
+        assert!(buffer_problems(&invalid, &expected, None)[0].starts_with("invalid presentation time"));
+        assert_eq!(buffer_problems(&good, &expected, Some(1.0)).len(), 1, "Time must move forward");
+    }
+}
@@ -0,0 +1,116 @@
+// Runtime capability probing
+// Reports which codecs, containers and pixel formats this machine can record with, and which
+// optional macOS features it has
+
+use std::ffi::c_void;
+use std::ptr;
+use objc2::runtime::AnyObject;
+use objc2::msg_send;
+use objc2_foundation::{NSArray, NSDictionary, NSNumber};
+
+use super::features;
+use super::types::{MAX_OUTPUT_WIDTH, MAX_OUTPUT_HEIGHT};
+use crate::Capabilities;
+
+// CoreMedia codec types
+const kCMVideoCodecType_H264: u32 = 0x61766331; // 'avc1'
+const kCMVideoCodecType_HEVC: u32 = 0x68766331; // 'hvc1'
+const kCMVideoCodecType_HEVCWithAlpha: u32 = 0x6d756861; // 'muha'
+
+extern "C" {
+    static kVTVideoEncoderList_CodecType: *const AnyObject;
+    static kVTVideoEncoderList_IsHardwareAccelerated: *const AnyObject;
+
+    fn VTCopyVideoEncoderList(options: *const c_void, list_of_video_encoders_out: *mut *mut NSArray<AnyObject>) -> i32;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// An installed VideoToolbox encoder
+struct EncoderInfo {
+    codec_type: u32,
+    is_hardware_accelerated: bool,
+}
+
+/// Probes the OS for what recordings it can produce
+pub struct CapabilityProbe;
+
+impl CapabilityProbe {
+    /// Collect capabilities; codec lists come from VideoToolbox's encoder list
+    pub fn probe() -> Capabilities {
+        println!("🔎 Probing recording capabilities");
+
+        let encoders = unsafe { Self::video_encoders() };
+        let has_encoder = |codec_type: u32| encoders.iter().any(|encoder| encoder.codec_type == codec_type);
+        let has_hardware_encoder = |codec_type: u32| {
+            encoders.iter().any(|encoder| encoder.codec_type == codec_type && encoder.is_hardware_accelerated)
+        };
+
+        let mut video_codecs = Vec::new();
+        let mut hardware_video_codecs = Vec::new();
+        let codecs = [
+            ("h264", kCMVideoCodecType_H264),
+            ("hevc", kCMVideoCodecType_HEVC),
+            ("hevc_alpha", kCMVideoCodecType_HEVCWithAlpha),
+        ];
+        for (name, codec_type) in codecs {
+            if has_encoder(codec_type) {
+                video_codecs.push(name.to_string());
+            }
+            if has_hardware_encoder(codec_type) {
+                hardware_video_codecs.push(name.to_string());
+            }
+        }
+
+        // 10-bit capture is only recorded through HEVC Main10
+        let mut pixel_formats = vec!["bgra".to_string(), "420v".to_string()];
+        if has_encoder(kCMVideoCodecType_HEVC) {
+            pixel_formats.push("l10r".to_string());
+        }
+
+        // Every writer produces MPEG-4 except hevc_alpha, which needs a QuickTime movie
+        let mut containers = vec!["mp4".to_string()];
+        if has_encoder(kCMVideoCodecType_HEVCWithAlpha) {
+            containers.push("mov".to_string());
+        }
+
+        Capabilities {
+            video_codecs,
+            hardware_video_codecs,
+            audio_codecs: vec!["aac".to_string()],
+            containers,
+            pixel_formats,
+            max_width: MAX_OUTPUT_WIDTH,
+            max_height: MAX_OUTPUT_HEIGHT,
+            features: features::features(),
+        }
+    }
+
+    /// List installed encoders; empty when VideoToolbox can't be queried
+    unsafe fn video_encoders() -> Vec<EncoderInfo> {
+        let mut list: *mut NSArray<AnyObject> = ptr::null_mut();
+        let status = VTCopyVideoEncoderList(ptr::null(), &mut list);
+        if status != 0 || list.is_null() {
+            println!("⚠️ VTCopyVideoEncoderList failed (OSStatus {})", status);
+            return Vec::new();
+        }
+
+        let count: usize = msg_send![list, count];
+        let mut encoders = Vec::with_capacity(count);
+        for i in 0..count {
+            let entry: *mut NSDictionary<AnyObject, AnyObject> = msg_send![list, objectAtIndex: i];
+            let codec_type: *mut NSNumber = msg_send![entry, objectForKey: kVTVideoEncoderList_CodecType];
+            if codec_type.is_null() {
+                continue;
+            }
+            let hardware: *mut NSNumber = msg_send![entry, objectForKey: kVTVideoEncoderList_IsHardwareAccelerated];
+
+            encoders.push(EncoderInfo {
+                codec_type: msg_send![codec_type, unsignedIntValue],
+                is_hardware_accelerated: !hardware.is_null() && msg_send![hardware, boolValue],
+            });
+        }
+
+        CFRelease(list as *const c_void);
+        encoders
+    }
+}
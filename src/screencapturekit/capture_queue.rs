@@ -0,0 +1,110 @@
+// ScreenCaptureKit call queue
+// SCStream and SCShareableContent calls used to run on whichever tokio worker polled the
+// future, so two recorders (or a stop racing a start) could drive the same objects from
+// different threads at once. Every such call now runs on one serial dispatch queue:
+// creating streams, starting and stopping them and requesting shareable content. Completion
+// handlers still arrive on ScreenCaptureKit's own queues; only the calls are serialized
+
+use std::any::Any;
+use std::ffi::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::OnceLock;
+
+const QUEUE_LABEL: &std::ffi::CStr = c"rustedscreencapture.capture";
+
+extern "C" {
+    fn dispatch_queue_create(label: *const c_char, attr: *const c_void) -> *mut c_void;
+    fn dispatch_sync_f(queue: *mut c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+    fn dispatch_queue_set_specific(
+        queue: *mut c_void,
+        key: *const c_void,
+        context: *mut c_void,
+        destructor: Option<extern "C" fn(*mut c_void)>,
+    );
+    fn dispatch_get_specific(key: *const c_void) -> *mut c_void;
+}
+
+// Its address tags the queue so code can tell whether it's running on it
+static QUEUE_KEY: u8 = 0;
+
+struct CaptureQueue(*mut c_void);
+
+// Safety: dispatch queues are thread-safe and this one is never released
+unsafe impl Send for CaptureQueue {}
+unsafe impl Sync for CaptureQueue {}
+
+fn queue_key() -> *const c_void {
+    &QUEUE_KEY as *const u8 as *const c_void
+}
+
+fn capture_queue() -> *mut c_void {
+    static QUEUE: OnceLock<CaptureQueue> = OnceLock::new();
+    QUEUE.get_or_init(|| unsafe {
+        // A null attribute is DISPATCH_QUEUE_SERIAL
+        let queue = dispatch_queue_create(QUEUE_LABEL.as_ptr(), ptr::null());
+        dispatch_queue_set_specific(queue, queue_key(), queue_key() as *mut c_void, None);
+        CaptureQueue(queue)
+    }).0
+}
+
+/// Whether the caller is running on the capture queue
+pub fn is_on_capture_queue() -> bool {
+    unsafe { dispatch_get_specific(queue_key()) == queue_key() as *mut c_void }
+}
+
+/// Flag, in debug builds, ScreenCaptureKit calls made off the capture queue
+pub fn debug_assert_on_capture_queue(call: &str) {
+    debug_assert!(is_on_capture_queue(), "{} must run on the capture queue (use on_capture_queue)", call);
+}
+
+/// Run `f` on the capture queue and wait for its result; runs it in place when already there,
+/// so nested calls don't deadlock. A panic in `f` resumes on the caller
+///
+/// # Safety
+/// `f` runs on another thread while the caller blocks, so it may borrow the caller's state but
+/// anything it touches must be safe to use from that thread, as ScreenCaptureKit objects are
+pub unsafe fn on_capture_queue<R, F: FnOnce() -> R>(f: F) -> R {
+    if is_on_capture_queue() {
+        return f();
+    }
+
+    struct Work<F, R> {
+        f: Option<F>,
+        result: Option<std::result::Result<R, Box<dyn Any + Send>>>,
+    }
+
+    extern "C" fn run<F: FnOnce() -> R, R>(context: *mut c_void) {
+        let work = unsafe { &mut *(context as *mut Work<F, R>) };
+        if let Some(f) = work.f.take() {
+            work.result = Some(panic::catch_unwind(AssertUnwindSafe(f)));
+        }
+    }
+
+    let mut work = Work { f: Some(f), result: None };
+    dispatch_sync_f(capture_queue(), &mut work as *mut Work<F, R> as *mut c_void, run::<F, R>);
+    match work.result {
+        Some(Ok(result)) => result,
+        Some(Err(payload)) => panic::resume_unwind(payload),
+        None => unreachable!("dispatch_sync_f returned without running the work"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calls_run_on_the_capture_queue() {
+        assert!(!is_on_capture_queue());
+
+        let (on_queue, nested) = unsafe {
+            on_capture_queue(|| (is_on_capture_queue(), on_capture_queue(is_on_capture_queue)))
+        };
+        assert!(on_queue && nested, "Nested calls run in place");
+        assert!(!is_on_capture_queue());
+
+        let result = panic::catch_unwind(|| unsafe { on_capture_queue(|| panic!("boom")) });
+        assert!(result.is_err(), "Panics reach the caller");
+    }
+}
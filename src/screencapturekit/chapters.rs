@@ -0,0 +1,126 @@
+// Chapter markers from scene cuts
+// A frame whose changed rectangles cover a large share of the picture (switching apps,
+// opening a slide, jumping to another page) starts a new chapter. Cuts closer together than
+// a minimum spacing are merged so a flurry of window switches gives one marker, and the
+// chapters are written to a JSON sidecar next to the video once it's finalized
+
+use napi::{Result, Status, Error};
+use serde::Serialize;
+
+use super::foundation::CGRect;
+
+/// Default sensitivity; at 0.5 a change covering about half the frame is a cut
+pub const DEFAULT_CHAPTER_SENSITIVITY: f64 = 0.5;
+/// Chapters shorter than this are merged into the next, in seconds
+const MIN_CHAPTER_SECONDS: f64 = 10.0;
+
+/// Sidecar path for a recording written to `output_path`
+pub fn sidecar_path(output_path: &str) -> String {
+    let path = std::path::Path::new(output_path);
+    path.with_extension("chapters.json").to_string_lossy().to_string()
+}
+
+/// Share of a `width` x `height` frame covered by `rects`, from 0 to 1; ScreenCaptureKit's
+/// dirty rectangles don't overlap
+pub fn changed_fraction(rects: &[CGRect], width: u32, height: u32) -> f64 {
+    let area = width as f64 * height as f64;
+    if area <= 0.0 {
+        return 0.0;
+    }
+    let changed: f64 = rects.iter().map(|rect| rect.size.width.max(0.0) * rect.size.height.max(0.0)).sum();
+    (changed / area).min(1.0)
+}
+
+/// Collects the times of scene cuts in a recording
+#[derive(Debug, Clone)]
+pub struct SceneCutDetector {
+    /// Share of the frame a change must cover to be a cut
+    threshold: f64,
+    cuts: Vec<f64>,
+}
+
+impl SceneCutDetector {
+    /// A detector for `sensitivity` from 0 (only full-screen changes) to 1 (changes to a
+    /// twentieth of the frame)
+    pub fn new(sensitivity: f64) -> Self {
+        Self { threshold: 1.0 - sensitivity.clamp(0.0, 1.0) * 0.95, cuts: Vec::new() }
+    }
+
+    /// Note a frame `seconds` into the recording that changed `fraction` of the picture;
+    /// true when it starts a chapter
+    pub fn observe(&mut self, seconds: f64, fraction: f64) -> bool {
+        if fraction < self.threshold {
+            return false;
+        }
+        let last = self.cuts.last().copied().unwrap_or(0.0);
+        if seconds - last < MIN_CHAPTER_SECONDS {
+            return false;
+        }
+        self.cuts.push(seconds);
+        true
+    }
+
+    /// Chapter start times, the first at 0
+    pub fn chapter_starts(&self) -> Vec<f64> {
+        std::iter::once(0.0).chain(self.cuts.iter().copied()).collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Chapter {
+    title: String,
+    /// Seconds from the start of the video
+    start: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct ChaptersFile {
+    version: u32,
+    chapters: Vec<Chapter>,
+}
+
+/// Write chapters starting at `starts` next to the video at `video_path`
+pub fn write_chapters(video_path: &str, starts: &[f64]) -> Result<String> {
+    let file = ChaptersFile {
+        version: 1,
+        chapters: starts.iter().enumerate().map(|(index, &start)| Chapter {
+            title: format!("Chapter {}", index + 1),
+            start,
+        }).collect(),
+    };
+    let path = sidecar_path(video_path);
+
+    let json = serde_json::to_string(&file)
+        .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to encode chapters: {}", e)))?;
+    std::fs::write(&path, json)
+        .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to write chapters {}: {}", path, e)))?;
+
+    println!("📑 Chapters saved: {} ({} chapters)", path, file.chapters.len());
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screencapturekit::foundation::{CGPoint, CGSize};
+
+    fn rect(width: f64, height: f64) -> CGRect {
+        CGRect { origin: CGPoint { x: 0.0, y: 0.0 }, size: CGSize { width, height } }
+    }
+
+    #[test]
+    fn test_large_changes_start_chapters_at_most_every_ten_seconds() {
+        assert_eq!(sidecar_path("/tmp/talk.mov"), "/tmp/talk.chapters.json");
+        assert_eq!(changed_fraction(&[rect(960.0, 1080.0)], 1920, 1080), 0.5);
+
+        let mut detector = SceneCutDetector::new(DEFAULT_CHAPTER_SENSITIVITY);
+        assert!(!detector.observe(30.0, 0.1), "Typing isn't a cut");
+        assert!(detector.observe(30.0, 0.9));
+        assert!(!detector.observe(33.0, 1.0), "Cuts right after another are merged");
+        assert!(detector.observe(45.0, 0.8));
+        assert_eq!(detector.chapter_starts(), vec![0.0, 30.0, 45.0]);
+
+        let mut sensitive = SceneCutDetector::new(1.0);
+        assert!(sensitive.observe(20.0, 0.1), "Full sensitivity takes much smaller changes");
+    }
+}
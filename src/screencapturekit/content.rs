@@ -1,11 +1,85 @@
 // src/screencapturekit/content.rs - Real Async ScreenCaptureKit Implementation
 
-use crate::ScreenSource;
+use crate::{ScreenSource, SourceFilter};
 use napi::bindgen_prelude::*;
 use super::types::*;
 use super::bindings::ScreenCaptureKitAPI;
+use super::features::Feature;
+use super::foundation::{describe_nserror, CGRect, CoreGraphicsHelpers};
+use super::run_loop::with_run_loop;
+use std::future::Future;
 use std::time::Duration;
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, watch};
+
+// kCGNormalWindowLevel, the layer of ordinary app windows
+const NORMAL_WINDOW_LAYER: isize = 0;
+
+/// How long a retrieval runs before the slow listener is told it's still working
+pub const SLOW_RETRIEVAL_NOTICE: Duration = Duration::from_secs(1);
+
+/// Told once when a content retrieval is still running after `SLOW_RETRIEVAL_NOTICE`
+pub type SlowRetrievalListener = Box<dyn FnOnce(Duration) + Send>;
+
+/// System and helper apps whose windows aren't useful capture sources: the menu bar, Dock,
+/// wallpaper and overlay windows. Matched against the owner's bundle id or name
+pub const DEFAULT_EXCLUDED_APPS: &[&str] = &[
+    "Window Server",
+    "com.apple.dock",
+    "com.apple.controlcenter",
+    "com.apple.systemuiserver",
+    "com.apple.notificationcenterui",
+    "com.apple.Spotlight",
+    "com.apple.WindowManager",
+    "com.apple.wallpaper.agent",
+    "com.apple.TextInputMenuAgent",
+    "com.apple.loginwindow",
+];
+
+/// Allow/deny list of apps for window enumeration
+/// Entries match a window's owner bundle id or name, case-insensitively. When `include` isn't
+/// empty only its apps are listed; an app named there is listed even if a default exclusion
+/// would hide it
+#[derive(Debug, Clone)]
+pub struct AppFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub exclude_system_apps: bool,
+}
+
+impl Default for AppFilter {
+    fn default() -> Self {
+        Self { include: Vec::new(), exclude: Vec::new(), exclude_system_apps: true }
+    }
+}
+
+impl AppFilter {
+    pub fn from_config(filter: Option<&SourceFilter>) -> Self {
+        let Some(filter) = filter else {
+            return Self::default();
+        };
+        Self {
+            include: filter.include_apps.clone().unwrap_or_default(),
+            exclude: filter.exclude_apps.clone().unwrap_or_default(),
+            exclude_system_apps: filter.exclude_system_apps.unwrap_or(true),
+        }
+    }
+    
+    /// Whether `window` should be listed
+    pub fn allows(&self, window: &WindowInfo) -> bool {
+        let matches = |app: &str| {
+            (!window.bundle_id.is_empty() && app.eq_ignore_ascii_case(&window.bundle_id))
+                || (!window.owner_name.is_empty() && app.eq_ignore_ascii_case(&window.owner_name))
+        };
+        
+        if !self.include.is_empty() {
+            return self.include.iter().any(|app| matches(app)) && !self.exclude.iter().any(|app| matches(app));
+        }
+        if self.exclude.iter().any(|app| matches(app)) {
+            return false;
+        }
+        !(self.exclude_system_apps && DEFAULT_EXCLUDED_APPS.iter().any(|app| matches(app)))
+    }
+}
 
 /// Async-only content manager that properly handles ScreenCaptureKit's async nature
 pub struct AsyncContentManager;
@@ -13,6 +87,23 @@ pub struct AsyncContentManager;
 impl AsyncContentManager {
     /// Get shareable content using real ScreenCaptureKit async APIs
     pub async fn get_shareable_content() -> Result<ShareableContent> {
+        Self::get_shareable_content_with_cancellation(None).await
+    }
+    
+    /// Get shareable content, abandoning the retrieval when `cancel` fires
+    /// A cancelled retrieval drops the pending receiver and returns a "cancelled" error promptly
+    /// instead of waiting for ScreenCaptureKit or the 10s timeout
+    pub async fn get_shareable_content_with_cancellation(cancel: Option<watch::Receiver<()>>) -> Result<ShareableContent> {
+        Self::get_shareable_content_with_progress(cancel, None).await
+    }
+    
+    /// Get shareable content like `get_shareable_content_with_cancellation`, telling `on_slow`
+    /// when the retrieval hasn't finished after `SLOW_RETRIEVAL_NOTICE`. The notice always
+    /// comes before the result; the timeout is unchanged
+    pub async fn get_shareable_content_with_progress(
+        cancel: Option<watch::Receiver<()>>,
+        on_slow: Option<SlowRetrievalListener>,
+    ) -> Result<ShareableContent> {
         println!("🔍 Getting shareable content via real ScreenCaptureKit async APIs");
         
         // Use tokio oneshot channel for async communication
@@ -32,28 +123,29 @@ impl AsyncContentManager {
                         }
                     }
                 } else {
-                    let error_msg = if !error.is_null() {
-                        use objc2::{msg_send};
-                        use objc2_foundation::NSString;
-                        
-                        let description: *mut NSString = msg_send![error, localizedDescription];
-                        if !description.is_null() {
-                            format!("ScreenCaptureKit error: {}", (*description).to_string())
-                        } else {
-                            "ScreenCaptureKit error (no description available)".to_string()
-                        }
-                    } else {
-                        "Unknown ScreenCaptureKit error".to_string()
-                    };
+                    let error_msg = format!("ScreenCaptureKit error: {}", describe_nserror(error));
                     
                     let _ = sender.send(Err(Error::new(Status::GenericFailure, error_msg)));
                 }
             });
         }
         
-        // Wait for the result with timeout
-        let content = tokio::time::timeout(Duration::from_secs(10), receiver)
-            .await
+        // Wait for the result with timeout, or until the caller cancels; on the main thread the
+        // run loop is pumped meanwhile so main-queue callbacks can fire
+        let retrieval = tokio::time::timeout(Duration::from_secs(10), with_run_loop(receiver));
+        let retrieval = notify_when_slow(retrieval, SLOW_RETRIEVAL_NOTICE, on_slow);
+        let result = match cancel {
+            Some(mut cancel) => tokio::select! {
+                result = retrieval => result,
+                Ok(()) = cancel.changed() => {
+                    println!("🚫 Shareable content retrieval cancelled");
+                    return Err(Error::new(Status::Cancelled, "ScreenCaptureKit content retrieval cancelled"));
+                }
+            },
+            None => retrieval.await,
+        };
+        
+        let content = result
             .map_err(|_| Error::new(Status::GenericFailure, "ScreenCaptureKit content retrieval timed out"))?
             .map_err(|_| Error::new(Status::GenericFailure, "Internal channel error"))??;
         
@@ -63,10 +155,36 @@ impl AsyncContentManager {
     
     /// Extract screen sources from async content
     pub async fn extract_screen_sources(content: &ShareableContent) -> Result<Vec<ScreenSource>> {
-        content.get_all_sources().await
+        content.get_all_sources(&AppFilter::default()).await
     }
 }
 
+/// Await `future`, calling `on_slow` once if it's still pending after `after`
+async fn notify_when_slow<F: Future>(future: F, after: Duration, on_slow: Option<SlowRetrievalListener>) -> F::Output {
+    let Some(on_slow) = on_slow else {
+        return future.await;
+    };
+    tokio::pin!(future);
+    tokio::select! {
+        output = &mut future => return output,
+        _ = tokio::time::sleep(after) => {
+            println!("⏳ Shareable content retrieval is taking longer than {:?}", after);
+            on_slow(after);
+        }
+    }
+    future.await
+}
+
+/// Whether `frame` exactly covers one of `displays`, allowing for rounding
+fn covers_display(frame: &CGRect, displays: &[DisplayInfo]) -> bool {
+    displays.iter().any(|display| {
+        (frame.origin.x - display.x as f64).abs() <= 1.0
+            && (frame.origin.y - display.y as f64).abs() <= 1.0
+            && (frame.size.width - display.width as f64).abs() <= 1.0
+            && (frame.size.height - display.height as f64).abs() <= 1.0
+    })
+}
+
 /// Async content manager for ScreenCaptureKit
 pub struct ShareableContent {
     displays: Vec<DisplayInfo>,
@@ -80,7 +198,10 @@ impl ShareableContent {
         println!("🔍 Processing real ScreenCaptureKit content");
         
         let displays = Self::extract_displays_from_content(sc_content_ptr)?;
-        let windows = Self::extract_windows_from_content(sc_content_ptr)?;
+        let windows = Self::extract_windows_from_content(sc_content_ptr, &displays)?;
+        
+        // Keep the content alive past the completion handler so filters can be built from it
+        let _: *mut SCShareableContent = objc2::msg_send![sc_content_ptr, retain];
         
         Ok(Self {
             displays,
@@ -89,6 +210,15 @@ impl ShareableContent {
         })
     }
     
+    /// Content built from already known displays and windows, without a ScreenCaptureKit object
+    pub fn from_parts(displays: Vec<DisplayInfo>, windows: Vec<WindowInfo>) -> Self {
+        Self {
+            displays,
+            windows,
+            sc_content_ptr: None,
+        }
+    }
+    
     /// Extract display information from ScreenCaptureKit content
     unsafe fn extract_displays_from_content(sc_content_ptr: *mut SCShareableContent) -> Result<Vec<DisplayInfo>> {
         use objc2::{msg_send};
@@ -102,6 +232,7 @@ impl ShareableContent {
         let displays = &*displays_array;
         let count = displays.count();
         let mut result = Vec::new();
+        let headrooms = CoreGraphicsHelpers::edr_headrooms();
         
         for i in 0..count {
             let display: *mut SCDisplay = msg_send![displays, objectAtIndex: i];
@@ -109,12 +240,18 @@ impl ShareableContent {
                 let display_id: u32 = msg_send![display, displayID];
                 let width: u32 = msg_send![display, width];
                 let height: u32 = msg_send![display, height];
+                let frame: super::foundation::CGRect = msg_send![display, frame];
                 
                 result.push(DisplayInfo {
                     id: display_id,
                     name: format!("Display {}", display_id),
+                    x: frame.origin.x as i32,
+                    y: frame.origin.y as i32,
                     width,
                     height,
+                    is_virtual: CoreGraphicsHelpers::is_virtual_display(display_id),
+                    is_mirror: CoreGraphicsHelpers::is_mirror_display(display_id),
+                    edr_headroom: headrooms.as_ref().and_then(|headrooms| headrooms.get(&display_id).copied()),
                 });
             }
         }
@@ -123,8 +260,9 @@ impl ShareableContent {
         Ok(result)
     }
     
-    /// Extract window information from ScreenCaptureKit content
-    unsafe fn extract_windows_from_content(sc_content_ptr: *mut SCShareableContent) -> Result<Vec<WindowInfo>> {
+    /// Extract window information from ScreenCaptureKit content; `displays` tell fullscreen
+    /// windows apart
+    unsafe fn extract_windows_from_content(sc_content_ptr: *mut SCShareableContent, displays: &[DisplayInfo]) -> Result<Vec<WindowInfo>> {
         use objc2::{msg_send};
         use objc2_foundation::{NSArray, NSString};
         
@@ -152,13 +290,28 @@ impl ShareableContent {
                 // Get frame information
                 let frame: super::foundation::CGRect = msg_send![window, frame];
                 
+                let (owner_name, bundle_id) = Self::owning_application(window);
+                let layer: isize = msg_send![window, windowLayer];
+                let on_screen: bool = msg_send![window, isOnScreen];
+                let is_active = Feature::WindowActiveState.is_available() && {
+                    let active: bool = msg_send![window, isActive];
+                    active
+                };
+                
                 // Only include windows with reasonable titles and sizes
                 if !title.is_empty() && frame.size.width > 50.0 && frame.size.height > 50.0 {
                     result.push(WindowInfo {
                         id: window_id,
                         title,
+                        x: frame.origin.x as i32,
+                        y: frame.origin.y as i32,
                         width: frame.size.width as u32,
                         height: frame.size.height as u32,
+                        owner_name,
+                        bundle_id,
+                        is_minimized: layer == NORMAL_WINDOW_LAYER && !on_screen,
+                        is_fullscreen: covers_display(&frame, displays),
+                        is_active,
                     });
                 }
             }
@@ -168,8 +321,25 @@ impl ShareableContent {
         Ok(result)
     }
     
-    /// Get all screen sources asynchronously
-    pub async fn get_all_sources(&self) -> Result<Vec<ScreenSource>> {
+    /// Name and bundle id of the app owning `window`, empty for ownerless windows
+    unsafe fn owning_application(window: *mut SCWindow) -> (String, String) {
+        use objc2::{msg_send};
+        use objc2::runtime::AnyObject;
+        use objc2_foundation::NSString;
+        
+        let application: *mut AnyObject = msg_send![window, owningApplication];
+        if application.is_null() {
+            return (String::new(), String::new());
+        }
+        
+        let read = |string: *mut NSString| if string.is_null() { String::new() } else { (*string).to_string() };
+        let name: *mut NSString = msg_send![application, applicationName];
+        let bundle_id: *mut NSString = msg_send![application, bundleIdentifier];
+        (read(name), read(bundle_id))
+    }
+    
+    /// Get all screen sources asynchronously, listing only the windows `filter` allows
+    pub async fn get_all_sources(&self, filter: &AppFilter) -> Result<Vec<ScreenSource>> {
         let mut sources = Vec::new();
         
         // Add displays
@@ -180,18 +350,30 @@ impl ShareableContent {
                 width: display.width,
                 height: display.height,
                 is_display: true,
+                is_virtual: display.is_virtual,
+                is_mirror: display.is_mirror,
+                is_minimized: false,
+                is_fullscreen: false,
+                is_active: false,
+                edr_headroom: display.edr_headroom,
             });
         }
         
         // Add windows (filter out small windows)
         for window in &self.windows {
-            if !window.title.is_empty() && window.width > 100 && window.height > 100 {
+            if !window.title.is_empty() && window.width > 100 && window.height > 100 && filter.allows(window) {
                 sources.push(ScreenSource {
                     id: format!("window:{}", window.id),
                     name: window.title.clone(),
                     width: window.width,
                     height: window.height,
                     is_display: false,
+                    is_virtual: false,
+                    is_mirror: false,
+                    is_minimized: window.is_minimized,
+                    is_fullscreen: window.is_fullscreen,
+                    is_active: window.is_active,
+                    edr_headroom: None,
                 });
             }
         }
@@ -223,10 +405,9 @@ impl ShareableContent {
         }
     }
     
-    /// Get the raw ScreenCaptureKit content pointer (not needed for async-only approach)
+    /// Get the raw ScreenCaptureKit content pointer (null when no content was retrieved)
     pub fn get_sc_content_ptr(&self) -> *mut SCShareableContent {
-        // In the async-only approach, we don't store raw pointers
-        std::ptr::null_mut()
+        self.sc_content_ptr.unwrap_or(std::ptr::null_mut())
     }
     
     /// Get displays
@@ -240,6 +421,102 @@ impl ShareableContent {
     }
 }
 
+impl Drop for ShareableContent {
+    fn drop(&mut self) {
+        if let Some(sc_content_ptr) = self.sc_content_ptr.take() {
+            unsafe {
+                let _: () = objc2::msg_send![sc_content_ptr, release];
+            }
+        }
+    }
+}
+
 // Safety: Raw pointers are only used within unsafe blocks and data is extracted immediately
 unsafe impl Send for ShareableContent {}
-unsafe impl Sync for ShareableContent {}
\ No newline at end of file
+unsafe impl Sync for ShareableContent {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screencapturekit::foundation::{CGPoint, CGSize};
+
+    fn window(owner_name: &str, bundle_id: &str) -> WindowInfo {
+        WindowInfo {
+            id: 1,
+            title: "Window".to_string(),
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+            owner_name: owner_name.to_string(),
+            bundle_id: bundle_id.to_string(),
+            ..WindowInfo::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_retrievals_are_noticed_before_they_finish() {
+        use std::sync::{Arc, Mutex};
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let listener = |events: &Arc<Mutex<Vec<&'static str>>>| -> Option<SlowRetrievalListener> {
+            let events = events.clone();
+            Some(Box::new(move |_| events.lock().unwrap().push("slow")))
+        };
+
+        let fast = notify_when_slow(async { "done" }, Duration::from_millis(50), listener(&events)).await;
+        events.lock().unwrap().push(fast);
+        let recorded = events.clone();
+        let slow = notify_when_slow(
+            async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                recorded.lock().unwrap().len()
+            },
+            Duration::from_millis(20),
+            listener(&events),
+        ).await;
+        assert_eq!(*events.lock().unwrap(), vec!["done", "slow"], "Only the slow retrieval is noticed");
+        assert_eq!(slow, 2, "The notice comes before the result");
+    }
+
+    #[tokio::test]
+    async fn test_multi_byte_window_titles_reach_screen_sources_intact() {
+        let title = "📝 会議メモ — Ünïcode 👩‍💻";
+        let listed = objc2_foundation::NSString::from_str(title).to_string();
+        assert_eq!(listed, title, "Titles survive the NSString conversion");
+
+        let content = ShareableContent::from_parts(Vec::new(), vec![WindowInfo { title: listed, ..window("Notes", "com.apple.Notes") }]);
+        let sources = content.get_all_sources(&AppFilter::default()).await.expect("Sources list");
+        assert_eq!(sources[0].name, title);
+        assert_eq!(sources[0].name.chars().count(), title.chars().count());
+    }
+
+    #[test]
+    fn test_app_filter_lists() {
+        let dock = window("Dock", "com.apple.dock");
+        let safari = window("Safari", "com.apple.Safari");
+        let notes = window("Notes", "com.apple.Notes");
+
+        let default = AppFilter::default();
+        assert!(!default.allows(&dock));
+        assert!(default.allows(&safari));
+        assert!(AppFilter { exclude_system_apps: false, ..AppFilter::default() }.allows(&dock));
+
+        let exclude = AppFilter { exclude: vec!["safari".to_string()], ..AppFilter::default() };
+        assert!(!exclude.allows(&safari));
+        assert!(exclude.allows(&notes));
+
+        let include = AppFilter { include: vec!["com.apple.safari".to_string(), "Dock".to_string()], ..AppFilter::default() };
+        assert!(include.allows(&safari));
+        assert!(include.allows(&dock));
+        assert!(!include.allows(&notes));
+    }
+
+    #[test]
+    fn test_fullscreen_windows_cover_a_display() {
+        let display = DisplayInfo { id: 2, name: "Display 2".to_string(), x: 1920, y: 0, width: 1512, height: 982, is_virtual: false, is_mirror: false, edr_headroom: Some(1.0) };
+        let rect = |x: f64, width: f64, height: f64| CGRect { origin: CGPoint { x, y: 0.0 }, size: CGSize { width, height } };
+        assert!(covers_display(&rect(1920.0, 1512.0, 982.0), &[display.clone()]));
+        assert!(covers_display(&rect(1920.5, 1512.0, 981.5), &[display.clone()]), "Rounded frames still match");
+        assert!(!covers_display(&rect(1920.0, 1512.0, 944.0), &[display.clone()]), "A zoomed window below the menu bar isn't fullscreen");
+        assert!(!covers_display(&rect(0.0, 1512.0, 982.0), &[display]));
+    }
+}
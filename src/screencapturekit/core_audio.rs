@@ -0,0 +1,124 @@
+// Core Audio and Core Media audio types
+// The PCM stream description and buffer list layouts, and the Core Media calls that turn them
+// into audio sample buffers and back, shared by gain and level processing, the output device
+// tap and the synthetic test tones; plus the audio object property calls the level monitor
+// and the output tap look devices up with
+
+use std::ffi::c_void;
+use objc2_core_media::{CMSampleBuffer, CMTime};
+
+pub const AUDIO_FORMAT_LINEAR_PCM: u32 = 0x6C70636D; // kAudioFormatLinearPCM 'lpcm'
+pub const AUDIO_FORMAT_FLAG_IS_FLOAT: u32 = 1 << 0; // kAudioFormatFlagIsFloat
+
+// CoreAudio property selectors and scopes
+pub const AUDIO_OBJECT_SYSTEM_OBJECT: u32 = 1; // kAudioObjectSystemObject
+pub const AUDIO_HARDWARE_PROPERTY_DEVICES: u32 = 0x64657623; // kAudioHardwarePropertyDevices 'dev#'
+pub const AUDIO_HARDWARE_PROPERTY_TRANSLATE_UID_TO_DEVICE: u32 = 0x75696464; // kAudioHardwarePropertyTranslateUIDToDevice 'uidd'
+pub const AUDIO_DEVICE_PROPERTY_STREAMS: u32 = 0x73746D23; // kAudioDevicePropertyStreams 'stm#'
+pub const AUDIO_DEVICE_PROPERTY_DEVICE_UID: u32 = 0x75696420; // kAudioDevicePropertyDeviceUID 'uid '
+pub const AUDIO_OBJECT_PROPERTY_NAME: u32 = 0x6C6E616D; // kAudioObjectPropertyName 'lnam'
+pub const AUDIO_TAP_PROPERTY_FORMAT: u32 = 0x74666D74; // kAudioTapPropertyFormat 'tfmt'
+pub const AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL: u32 = 0x676C6F62; // kAudioObjectPropertyScopeGlobal 'glob'
+pub const AUDIO_OBJECT_PROPERTY_SCOPE_OUTPUT: u32 = 0x6F757470; // kAudioObjectPropertyScopeOutput 'outp'
+pub const AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN: u32 = 0; // kAudioObjectPropertyElementMain
+
+#[repr(C)]
+pub struct AudioObjectPropertyAddress {
+    pub selector: u32,
+    pub scope: u32,
+    pub element: u32,
+}
+
+/// The address of `selector` in `scope`, on the main element
+pub fn property_address(selector: u32, scope: u32) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress { selector, scope, element: AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN }
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct AudioStreamBasicDescription {
+    pub sample_rate: f64,
+    pub format_id: u32,
+    pub format_flags: u32,
+    pub bytes_per_packet: u32,
+    pub frames_per_packet: u32,
+    pub bytes_per_frame: u32,
+    pub channels_per_frame: u32,
+    pub bits_per_channel: u32,
+    pub reserved: u32,
+}
+
+#[repr(C)]
+pub struct AudioBuffer {
+    pub number_channels: u32,
+    pub data_byte_size: u32,
+    pub data: *mut c_void,
+}
+
+/// Variable length: `number_buffers` buffers follow in memory, one per channel for
+/// non-interleaved audio
+#[repr(C)]
+pub struct AudioBufferList {
+    pub number_buffers: u32,
+    pub buffers: [AudioBuffer; 1],
+}
+
+extern "C" {
+    pub fn AudioObjectGetPropertyDataSize(
+        object_id: u32,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_data_size: u32,
+        qualifier_data: *const c_void,
+        data_size: *mut u32,
+    ) -> i32;
+    pub fn AudioObjectGetPropertyData(
+        object_id: u32,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_data_size: u32,
+        qualifier_data: *const c_void,
+        data_size: *mut u32,
+        data: *mut c_void,
+    ) -> i32;
+    pub fn CMSampleBufferGetFormatDescription(sbuf: *mut CMSampleBuffer) -> *mut c_void;
+    pub fn CMSampleBufferGetDataBuffer(sbuf: &CMSampleBuffer) -> *mut c_void;
+    pub fn CMAudioFormatDescriptionCreate(
+        allocator: *const c_void,
+        asbd: *const AudioStreamBasicDescription,
+        layout_size: usize,
+        layout: *const c_void,
+        magic_cookie_size: usize,
+        magic_cookie: *const c_void,
+        extensions: *const c_void,
+        format_description_out: *mut *mut c_void,
+    ) -> i32;
+    pub fn CMAudioFormatDescriptionGetStreamBasicDescription(desc: *const c_void) -> *const AudioStreamBasicDescription;
+    pub fn CMAudioSampleBufferCreateWithPacketDescriptions(
+        allocator: *const c_void,
+        data_buffer: *const c_void,
+        data_ready: u8,
+        make_data_ready_callback: *const c_void,
+        make_data_ready_refcon: *const c_void,
+        format_description: *mut c_void,
+        num_samples: isize,
+        presentation_time_stamp: CMTime,
+        packet_descriptions: *const c_void,
+        sample_buffer_out: *mut *mut CMSampleBuffer,
+    ) -> i32;
+    pub fn CMSampleBufferSetDataBufferFromAudioBufferList(
+        sample_buffer: *mut CMSampleBuffer,
+        block_buffer_structure_allocator: *const c_void,
+        block_buffer_block_allocator: *const c_void,
+        flags: u32,
+        buffer_list: *const AudioBufferList,
+    ) -> i32;
+    pub fn CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
+        sbuf: &CMSampleBuffer,
+        buffer_list_size_needed_out: *mut usize,
+        buffer_list_out: *mut AudioBufferList,
+        buffer_list_size: usize,
+        block_buffer_structure_allocator: *const c_void,
+        block_buffer_block_allocator: *const c_void,
+        flags: u32,
+        block_buffer_out: *mut *mut c_void,
+    ) -> i32;
+}
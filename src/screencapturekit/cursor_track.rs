@@ -0,0 +1,253 @@
+// Cursor track recording
+// Samples the cursor position and image on a background thread while recording and writes
+// them to a JSON sidecar next to the video, so editors can re-render the cursor in post
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::c_void;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSData, NSString};
+use napi::{Result, Status, Error};
+use serde::Serialize;
+
+use super::foundation::{CGPoint, CGSize};
+
+// Cursor images are compared less often than positions; reading them costs a TIFF encode
+const IMAGE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_SAMPLE_RATE_HZ: u32 = 60;
+
+// NSBitmapImageFileTypePNG
+const PNG_FILE_TYPE: usize = 4;
+
+extern "C" {
+    fn CGEventCreate(source: *const c_void) -> *mut c_void;
+    fn CGEventGetLocation(event: *mut c_void) -> CGPoint;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Sidecar path for a recording written to `output_path`
+pub fn sidecar_path(output_path: &str) -> String {
+    let path = std::path::Path::new(output_path);
+    path.with_extension("cursor.json").to_string_lossy().to_string()
+}
+
+/// Captured area in global display points (top-left origin) and the output video size
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorTrackGeometry {
+    pub source_x: f64,
+    pub source_y: f64,
+    pub source_width: f64,
+    pub source_height: f64,
+    pub video_width: u32,
+    pub video_height: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CursorImage {
+    id: usize,
+    /// Hot spot and size in points
+    hotspot_x: f64,
+    hotspot_y: f64,
+    width: f64,
+    height: f64,
+    /// Base64 PNG
+    png: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+struct CursorSample {
+    /// Seconds of recorded video, paused time excluded
+    t: f64,
+    /// Points relative to the captured area's top-left corner
+    x: f64,
+    y: f64,
+    /// Index into `cursors`; null until the first image could be read
+    cursor: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CursorTrackFile {
+    version: u32,
+    #[serde(flatten)]
+    geometry: CursorTrackGeometry,
+    cursors: Vec<CursorImage>,
+    samples: Vec<CursorSample>,
+}
+
+/// Records the cursor until `finish`; dropping it discards the track
+pub struct CursorTracker {
+    path: String,
+    stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    thread: Option<JoinHandle<CursorTrackFile>>,
+}
+
+impl CursorTracker {
+    /// Start sampling `sample_rate_hz` times a second (capped at 60); only changes are kept
+    pub fn start(path: String, geometry: CursorTrackGeometry, sample_rate_hz: u32) -> Self {
+        println!("🖱️ Recording cursor track to {}", path);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let paused = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread_paused = paused.clone();
+        let interval = Duration::from_secs_f64(1.0 / sample_rate_hz.clamp(1, MAX_SAMPLE_RATE_HZ) as f64);
+
+        let thread = std::thread::spawn(move || {
+            let mut track = CursorTrackFile { version: 1, geometry, cursors: Vec::new(), samples: Vec::new() };
+            let mut images_by_hash: HashMap<u64, usize> = HashMap::new();
+            let mut current_cursor = None;
+            let mut last_image_check: Option<Instant> = None;
+
+            // Recorded time advances only while not paused
+            let mut recorded = Duration::ZERO;
+            let mut last_tick = Instant::now();
+
+            while !thread_stop.load(Ordering::SeqCst) {
+                let now = Instant::now();
+                let elapsed = now.duration_since(last_tick);
+                last_tick = now;
+                if thread_paused.load(Ordering::SeqCst) {
+                    std::thread::sleep(interval);
+                    continue;
+                }
+                recorded += elapsed;
+
+                if last_image_check.is_none_or(|checked| checked.elapsed() >= IMAGE_CHECK_INTERVAL) {
+                    last_image_check = Some(Instant::now());
+                    if let Some((hash, image)) = unsafe { current_cursor_image() } {
+                        let next_id = track.cursors.len();
+                        let id = *images_by_hash.entry(hash).or_insert(next_id);
+                        if id == next_id {
+                            track.cursors.push(CursorImage { id, ..image });
+                        }
+                        current_cursor = Some(id);
+                    }
+                }
+
+                let location = unsafe { cursor_location() };
+                let sample = CursorSample {
+                    t: recorded.as_secs_f64(),
+                    x: location.x - geometry.source_x,
+                    y: location.y - geometry.source_y,
+                    cursor: current_cursor,
+                };
+                let changed = track.samples.last()
+                    .is_none_or(|last| last.x != sample.x || last.y != sample.y || last.cursor != sample.cursor);
+                if changed {
+                    track.samples.push(sample);
+                }
+
+                std::thread::sleep(interval);
+            }
+
+            track
+        });
+
+        Self { path, stop, paused, thread: Some(thread) }
+    }
+
+    /// Leave paused time out of the track, matching the video
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    /// Stop sampling and write the sidecar; returns its path
+    pub fn finish(mut self) -> Result<String> {
+        self.stop.store(true, Ordering::SeqCst);
+        let track = self.thread.take()
+            .ok_or_else(|| Error::new(Status::GenericFailure, "Cursor tracker already finished"))?
+            .join()
+            .map_err(|_| Error::new(Status::GenericFailure, "Cursor tracker thread panicked"))?;
+
+        let json = serde_json::to_string(&track)
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to encode cursor track: {}", e)))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to write cursor track {}: {}", self.path, e)))?;
+
+        println!("✅ Cursor track written: {} ({} samples, {} cursor images)", self.path, track.samples.len(), track.cursors.len());
+        Ok(self.path.clone())
+    }
+}
+
+impl Drop for CursorTracker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Cursor position in global display points, top-left origin
+pub(super) unsafe fn cursor_location() -> CGPoint {
+    let event = CGEventCreate(std::ptr::null());
+    if event.is_null() {
+        return CGPoint { x: 0.0, y: 0.0 };
+    }
+    let location = CGEventGetLocation(event);
+    CFRelease(event as *const c_void);
+    location
+}
+
+/// The system cursor's image as a PNG, with a hash of its pixels for deduplication
+unsafe fn current_cursor_image() -> Option<(u64, CursorImage)> {
+    let cursor: *mut AnyObject = msg_send![class!(NSCursor), currentSystemCursor];
+    if cursor.is_null() {
+        return None;
+    }
+    let image: *mut AnyObject = msg_send![cursor, image];
+    if image.is_null() {
+        return None;
+    }
+    let hotspot: CGPoint = msg_send![cursor, hotSpot];
+    let size: CGSize = msg_send![image, size];
+
+    let tiff: *mut NSData = msg_send![image, TIFFRepresentation];
+    if tiff.is_null() {
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    (*tiff).to_vec().hash(&mut hasher);
+    let hash = hasher.finish();
+
+    let bitmap: *mut AnyObject = msg_send![class!(NSBitmapImageRep), imageRepWithData: tiff];
+    if bitmap.is_null() {
+        return None;
+    }
+    let properties: *mut AnyObject = msg_send![class!(NSDictionary), dictionary];
+    let png: *mut NSData = msg_send![bitmap, representationUsingType: PNG_FILE_TYPE, properties: properties];
+    if png.is_null() {
+        return None;
+    }
+    let base64: *mut NSString = msg_send![png, base64EncodedStringWithOptions: 0usize];
+    if base64.is_null() {
+        return None;
+    }
+
+    Some((hash, CursorImage {
+        id: 0,
+        hotspot_x: hotspot.x,
+        hotspot_y: hotspot.y,
+        width: size.width,
+        height: size.height,
+        png: (*base64).to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_sits_next_to_the_recording() {
+        assert_eq!(sidecar_path("/tmp/demo.mp4"), "/tmp/demo.cursor.json");
+        assert_eq!(sidecar_path("/tmp/demo"), "/tmp/demo.cursor.json");
+    }
+}
@@ -0,0 +1,268 @@
+// Video decoding for transcode
+// AVAssetReader decodes with whichever decoder VideoToolbox picks, and on some older Intel
+// Macs the hardware decoder fails or produces green frames. Transcoding instead reads the
+// compressed samples and decodes them in a VTDecompressionSession whose decoder specification
+// allows, requires or forbids hardware decoding. Decoded frames come out in decode order; they
+// are held back until no later sample can present before them, so the writer gets BGRA frames
+// in presentation order as the reader would have delivered them
+
+use std::collections::VecDeque;
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::Mutex;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::NSString;
+use objc2_core_media::{CMSampleBuffer, CMTime};
+use objc2_core_video::{CVPixelBuffer, kCVPixelFormatType_32BGRA};
+use napi::{Result, Status, Error};
+
+use super::region::wrap_pixel_buffer;
+use super::transcode::{dictionary, number_u32};
+use super::types::DecoderPreference;
+
+// kVTDecodeInfo_FrameDropped
+const DECODE_INFO_FRAME_DROPPED: u32 = 1 << 1;
+
+#[repr(C)]
+struct VTDecompressionOutputCallbackRecord {
+    callback: extern "C" fn(*mut c_void, *mut c_void, i32, u32, *mut CVPixelBuffer, CMTime, CMTime),
+    refcon: *mut c_void,
+}
+
+extern "C" {
+    static kVTVideoDecoderSpecification_EnableHardwareAcceleratedVideoDecoder: *const NSString;
+    static kVTVideoDecoderSpecification_RequireHardwareAcceleratedVideoDecoder: *const NSString;
+    static kVTDecompressionPropertyKey_UsingHardwareAcceleratedVideoDecoder: *const c_void;
+    static kCVPixelBufferPixelFormatTypeKey: *const NSString;
+    static kCVPixelBufferIOSurfacePropertiesKey: *const NSString;
+    static kCFBooleanTrue: *const c_void;
+
+    fn VTDecompressionSessionCreate(
+        allocator: *const c_void,
+        format_description: *const c_void,
+        decoder_specification: *const c_void,
+        destination_image_buffer_attributes: *const c_void,
+        output_callback: *const VTDecompressionOutputCallbackRecord,
+        session_out: *mut *mut c_void,
+    ) -> i32;
+    fn VTDecompressionSessionDecodeFrame(
+        session: *mut c_void,
+        sample_buffer: &CMSampleBuffer,
+        decode_flags: u32,
+        source_frame_refcon: *mut c_void,
+        info_flags_out: *mut u32,
+    ) -> i32;
+    fn VTDecompressionSessionFinishDelayedFrames(session: *mut c_void) -> i32;
+    fn VTDecompressionSessionWaitForAsynchronousFrames(session: *mut c_void) -> i32;
+    fn VTDecompressionSessionInvalidate(session: *mut c_void);
+    fn VTSessionCopyProperty(session: *mut c_void, key: *const c_void, allocator: *const c_void, value_out: *mut *const c_void) -> i32;
+    fn CMSampleBufferGetNumSamples(sbuf: &CMSampleBuffer) -> isize;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMSampleBufferGetDecodeTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMTimeGetSeconds(time: CMTime) -> f64;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// A decoded frame in a sample buffer of its own, released when dropped
+pub struct DecodedSample(*mut CMSampleBuffer);
+
+impl DecodedSample {
+    /// Hand the sample buffer's reference to the caller, who releases it
+    pub fn into_raw(self) -> *mut CMSampleBuffer {
+        let sample_buffer = self.0;
+        std::mem::forget(self);
+        sample_buffer
+    }
+}
+
+impl Drop for DecodedSample {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0 as *const c_void) };
+    }
+}
+
+// Safety: the sample buffer is owned and only handed between the decoder and its reader
+unsafe impl Send for DecodedSample {}
+
+#[derive(Default)]
+struct DecodedFrames {
+    // Decoded frames by presentation time in seconds
+    frames: Vec<(f64, DecodedSample)>,
+    failure: Option<String>,
+}
+
+extern "C" fn decoded_frame(
+    refcon: *mut c_void,
+    _source_frame_refcon: *mut c_void,
+    status: i32,
+    info_flags: u32,
+    image_buffer: *mut CVPixelBuffer,
+    presentation_time: CMTime,
+    _duration: CMTime,
+) {
+    let decoded = unsafe { &*(refcon as *const Mutex<DecodedFrames>) };
+    let Ok(mut decoded) = decoded.lock() else {
+        return;
+    };
+    if status != 0 {
+        let seconds = unsafe { CMTimeGetSeconds(presentation_time) };
+        decoded.failure.get_or_insert(format!("Failed to decode the frame at {:.3}s (OSStatus {})", seconds, status));
+        return;
+    }
+    if image_buffer.is_null() || info_flags & DECODE_INFO_FRAME_DROPPED != 0 {
+        return;
+    }
+    if let Some(sample) = unsafe { wrap_pixel_buffer(image_buffer, presentation_time) } {
+        let seconds = unsafe { CMTimeGetSeconds(presentation_time) };
+        let index = decoded.frames.partition_point(|(time, _)| *time <= seconds);
+        decoded.frames.insert(index, (seconds, DecodedSample(sample)));
+    }
+}
+
+/// Decodes a video track's compressed samples into BGRA frames in presentation order
+pub struct FrameDecoder {
+    session: *mut c_void,
+    decoded: Box<Mutex<DecodedFrames>>,
+    ready: VecDeque<DecodedSample>,
+    // Decode time of the last sample fed; no later sample presents before it
+    last_decode_seconds: Option<f64>,
+    finished: bool,
+}
+
+// Safety: the session is only used by the thread driving the transcode
+unsafe impl Send for FrameDecoder {}
+
+impl FrameDecoder {
+    /// A decoder for samples described by `format_description` that follows `preference`;
+    /// fails when no matching decoder exists, e.g. "hardware" without a hardware decoder
+    pub unsafe fn new(format_description: *const c_void, preference: DecoderPreference) -> Result<Self> {
+        let yes: *mut AnyObject = msg_send![class!(NSNumber), numberWithBool: true];
+        let no: *mut AnyObject = msg_send![class!(NSNumber), numberWithBool: false];
+        let specification = match preference {
+            DecoderPreference::Auto => dictionary(&[(kVTVideoDecoderSpecification_EnableHardwareAcceleratedVideoDecoder, yes)]),
+            DecoderPreference::Hardware => dictionary(&[
+                (kVTVideoDecoderSpecification_EnableHardwareAcceleratedVideoDecoder, yes),
+                (kVTVideoDecoderSpecification_RequireHardwareAcceleratedVideoDecoder, yes),
+            ]),
+            DecoderPreference::Software => dictionary(&[(kVTVideoDecoderSpecification_EnableHardwareAcceleratedVideoDecoder, no)]),
+        };
+        let io_surface: *mut AnyObject = msg_send![class!(NSDictionary), dictionary];
+        let attributes = dictionary(&[
+            (kCVPixelBufferPixelFormatTypeKey, number_u32(kCVPixelFormatType_32BGRA)),
+            (kCVPixelBufferIOSurfacePropertiesKey, io_surface),
+        ]);
+
+        let decoded = Box::new(Mutex::new(DecodedFrames::default()));
+        let callback = VTDecompressionOutputCallbackRecord {
+            callback: decoded_frame,
+            refcon: &*decoded as *const Mutex<DecodedFrames> as *mut c_void,
+        };
+        let mut session: *mut c_void = ptr::null_mut();
+        let status = VTDecompressionSessionCreate(
+            ptr::null(),
+            format_description,
+            specification as *const c_void,
+            attributes as *const c_void,
+            &callback,
+            &mut session,
+        );
+        if status != 0 || session.is_null() {
+            return Err(Error::new(
+                Status::GenericFailure,
+                format!("No video decoder for this input matches decoder preference {:?} (OSStatus {})", preference, status),
+            ));
+        }
+        Ok(Self { session, decoded, ready: VecDeque::new(), last_decode_seconds: None, finished: false })
+    }
+
+    /// Whether VideoToolbox picked its hardware decoder
+    pub fn uses_hardware(&self) -> bool {
+        let mut value: *const c_void = ptr::null();
+        unsafe {
+            let status = VTSessionCopyProperty(
+                self.session,
+                kVTDecompressionPropertyKey_UsingHardwareAcceleratedVideoDecoder,
+                ptr::null(),
+                &mut value,
+            );
+            if status != 0 || value.is_null() {
+                return false;
+            }
+            let hardware = value == kCFBooleanTrue;
+            CFRelease(value);
+            hardware
+        }
+    }
+
+    /// The next frame in presentation order, reading compressed samples from the
+    /// AVAssetReaderTrackOutput `output` as needed; None once the track is fully decoded
+    pub unsafe fn next_frame(&mut self, output: *mut AnyObject) -> Result<Option<DecodedSample>> {
+        loop {
+            self.collect_ready()?;
+            if let Some(frame) = self.ready.pop_front() {
+                return Ok(Some(frame));
+            }
+            if self.finished {
+                return Ok(None);
+            }
+
+            let sample_buffer: *mut CMSampleBuffer = msg_send![output, copyNextSampleBuffer];
+            if sample_buffer.is_null() {
+                VTDecompressionSessionFinishDelayedFrames(self.session);
+                VTDecompressionSessionWaitForAsynchronousFrames(self.session);
+                self.finished = true;
+                continue;
+            }
+            let result = self.decode(&*sample_buffer);
+            CFRelease(sample_buffer as *const c_void);
+            result?;
+        }
+    }
+
+    unsafe fn decode(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
+        // Samples without media data only carry attachments
+        if CMSampleBufferGetNumSamples(sample_buffer) == 0 {
+            return Ok(());
+        }
+        let decode_seconds = CMTimeGetSeconds(CMSampleBufferGetDecodeTimeStamp(sample_buffer));
+        let presentation_seconds = CMTimeGetSeconds(CMSampleBufferGetPresentationTimeStamp(sample_buffer));
+        // Without decode times the samples are in presentation order
+        self.last_decode_seconds = Some(if decode_seconds.is_finite() { decode_seconds } else { presentation_seconds });
+
+        let mut info_flags = 0u32;
+        let status = VTDecompressionSessionDecodeFrame(self.session, sample_buffer, 0, ptr::null_mut(), &mut info_flags);
+        if status != 0 {
+            return Err(Error::new(
+                Status::GenericFailure,
+                format!("Failed to decode the frame at {:.3}s (OSStatus {})", presentation_seconds, status),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Move the frames no undecoded sample can precede to `ready`
+    fn collect_ready(&mut self) -> Result<()> {
+        let mut decoded = self.decoded.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Decoded frames lock poisoned"))?;
+        if let Some(failure) = decoded.failure.take() {
+            return Err(Error::new(Status::GenericFailure, failure));
+        }
+        let ready = match (self.finished, self.last_decode_seconds) {
+            (true, _) => decoded.frames.len(),
+            (false, Some(last_decode)) => decoded.frames.partition_point(|(time, _)| *time <= last_decode),
+            (false, None) => 0,
+        };
+        self.ready.extend(decoded.frames.drain(..ready).map(|(_, frame)| frame));
+        Ok(())
+    }
+}
+
+impl Drop for FrameDecoder {
+    fn drop(&mut self) {
+        unsafe {
+            VTDecompressionSessionInvalidate(self.session);
+            CFRelease(self.session);
+        }
+    }
+}
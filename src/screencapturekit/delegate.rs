@@ -1,5 +1,6 @@
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use objc2::runtime::AnyObject;
 use objc2::{msg_send};
 use objc2_foundation::NSError;
@@ -7,9 +8,66 @@ use objc2_core_media::{CMSampleBuffer, CMTime};
 use objc2_core_video::{CVImageBuffer, CVPixelBuffer};
 use napi::{Result, Error, Status};
 
-use super::encoder::{VideoEncoder, AudioEncoder};  // RE-ENABLED: Encoder module
-use super::types::{SCStream, SCStreamDelegate, SCStreamOutputType};
+use super::encoder::{VideoEncoder, AudioEncoder, VideoEncoderSettings, PixelBufferPoolSettings, WRITER_START_RETRY_DELAY_MS};  // RE-ENABLED: Encoder module
+use super::replay::ReplayEncoder;
+use super::frame_delivery::LastFrameTime;
+use super::idle_stop::{frame_changes, ChangeTracker, LastChangeTime};
+use super::chapters::{changed_fraction, SceneCutDetector};
+use super::buffer_check::{BufferValidator, ExpectedBuffer};
+use super::frame_info::{frame_geometry, FrameGeometry};
+use super::keystroke_overlay::KeystrokeOverlay;
+use super::watermark::Watermark;
+use super::webcam::WebcamOverlay;
+use super::frame_stream::{FrameBatcher, RetainedPixelBuffer};
+use super::stream_output::{create_output_directory, EarlyAudioBuffer, HeldSampleBuffer, DEFAULT_EARLY_AUDIO_BUFFER_MS};
+use super::audio::{gain_db_to_linear, apply_gain_to_sample_buffer};
+use super::resolution_change::{segment_path, NewSegmentEvent, NewSegmentListener, ResolutionWatch, ScaledSample};
+use super::types::{SCStream, SCStreamDelegate, SCStreamOutputType, SCError, PermissionRevokedBehavior, ResolutionChangeBehavior};
 use super::objc_bridge_rust::ObjCDelegateBridge;
+use super::foundation::{describe_nserror, is_permission_error};
+use super::session::session_state;
+use super::temp_files::TempFiles;
+use super::progress::{RateWindow, DEFAULT_PROGRESS_INTERVAL};
+use super::drift;
+
+/// Reported when ScreenCaptureKit stops the stream with an error, after the encoders finalized
+#[derive(Debug, Clone)]
+pub struct StreamStopEvent {
+    /// `PermissionRevoked` for permission errors, `CaptureBlocked` when the session can no longer
+    /// be captured, otherwise `SystemError` with the description
+    pub error: SCError,
+    /// The finalized partial recording; None when nothing playable was written or it was discarded
+    pub output_path: Option<String>,
+}
+
+extern "C" {
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMSampleBufferGetDuration(sbuf: &CMSampleBuffer) -> CMTime;
+}
+
+/// Receives streams stopped by ScreenCaptureKit rather than by `stop_recording`
+pub type StreamStopListener = Arc<dyn Fn(StreamStopEvent) + Send + Sync>;
+
+// Why encoding is paused; it stays paused while either holds
+#[derive(Debug, Clone, Copy, Default)]
+struct PauseState {
+    by_user: bool,
+    for_secure_input: bool,
+}
+
+impl PauseState {
+    fn is_paused(&self) -> bool {
+        self.by_user || self.for_secure_input
+    }
+}
+
+/// The separate audio file written next to a recording at `output_path`: its name with the
+/// extension replaced by `_audio.m4a`, whatever the container
+pub fn audio_path_for(output_path: &str) -> String {
+    let path = std::path::Path::new(output_path);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!("{}_audio.m4a", stem)).to_string_lossy().to_string()
+}
 
 /// Real delegate that implements proper ScreenCaptureKit callbacks
 /// PRODUCTION-READY: Blazingly fast with zero-copy frame processing
@@ -17,52 +75,160 @@ pub struct RealStreamDelegate {
     output_path: String,
     video_encoder: Option<Arc<Mutex<VideoEncoder>>>,
     audio_encoder: Option<Arc<Mutex<AudioEncoder>>>,
+    // Why the video writer couldn't be set up
+    video_encoder_error: Option<String>,
+    // Why the audio writer couldn't be set up
+    audio_encoder_error: Option<String>,
+    replay_encoder: Option<Arc<Mutex<ReplayEncoder>>>,
+    keystroke_overlay: Option<Arc<KeystrokeOverlay>>,
+    webcam_overlay: Option<WebcamOverlay>,
+    watermark: Option<Watermark>,
+    frame_batcher: Option<FrameBatcher>,
+    // Audio that arrived before the first video frame started the session; None once it has,
+    // or when there's no video session to wait for
+    early_audio: Mutex<Option<EarlyAudioBuffer<(HeldSampleBuffer, SCStreamOutputType)>>>,
+    // Linear gains applied before system and microphone audio share the audio track
+    system_audio_gain: f32,
+    microphone_gain: f32,
     frame_count: Arc<Mutex<u64>>,
     audio_frame_count: Arc<Mutex<u64>>,
     is_recording: Arc<Mutex<bool>>,
-    last_frame_time: Arc<Mutex<std::time::Instant>>,
+    // Samples are dropped rather than encoded while paused
+    pause_state: Mutex<PauseState>,
+    // Frame rate measured over each progress interval for the frame log
+    fps_window: Mutex<RateWindow>,
+    // Arrival of the last video sample, paused or not
+    last_sample_at: LastFrameTime,
     fps_counter: Arc<Mutex<f64>>,
     objc_bridge: Option<Arc<ObjCDelegateBridge>>,
+    stop_listener: Option<StreamStopListener>,
+    permission_revoked_behavior: PermissionRevokedBehavior,
+    // With idleStopSeconds: collects frame changes and when they last added up to activity
+    idle_tracker: Option<(Mutex<ChangeTracker>, LastChangeTime)>,
+    // Content geometry ScreenCaptureKit reported with the first frame that carried it
+    frame_geometry: Mutex<Option<FrameGeometry>>,
+    // The most recent frame, kept once it's been encoded, for grabbing previews
+    latest_frame: Mutex<Option<RetainedPixelBuffer>>,
+    // Tracks turned off mid-recording: disabled audio is written as silence and disabled
+    // video repeats the last frame, so both keep their timing
+    audio_enabled: AtomicBool,
+    video_enabled: AtomicBool,
+    // Set once CGDisplayStream took over: late ScreenCaptureKit frames would be recorded twice
+    stream_video_ignored: AtomicBool,
+    // With startDelaySeconds: samples arriving before this are dropped
+    starts_at: Option<Instant>,
+    // With detectChapters: finds scene cuts in frames of the given output size
+    chapter_detector: Option<(Mutex<SceneCutDetector>, u32, u32)>,
+    // With validateBuffers: compares every video sample with the configured stream
+    buffer_validator: Option<BufferValidator>,
+    // The separate audio file until the recording finishes cleanly; deleted if it doesn't
+    temp_files: Mutex<TempFiles>,
+    // Frames of another size than the first are scaled or start a new segment
+    resolution_watch: ResolutionWatch,
+    encoder_settings: VideoEncoderSettings,
+    // Number and file of the segment being written; 1 and output_path until the size changes
+    segment: Mutex<(u32, String)>,
+    new_segment_listener: Option<NewSegmentListener>,
 }
 
 impl RealStreamDelegate {
     /// Create new delegate with PRODUCTION-READY encoders
-    pub fn new(output_path: String, is_recording: Arc<Mutex<bool>>, width: u32, height: u32, fps: u32) -> Self {
+    pub fn new(output_path: String, is_recording: Arc<Mutex<bool>>, settings: VideoEncoderSettings) -> Self {
         println!("🎬 Creating RealStreamDelegate for recording: {}", output_path);
         
         // Ensure output directory exists
-        if let Some(parent) = std::path::Path::new(&output_path).parent() {
-            if !parent.exists() {
-                if let Err(e) = std::fs::create_dir_all(parent) {
-                    println!("⚠️ Failed to create output directory: {}", e);
+        if let Err(e) = create_output_directory(&output_path) {
+            println!("⚠️ {}", e.reason);
+        }
+        
+        // Instant replay mode keeps frames in memory only; nothing is written until save_replay
+        if let Some(window_seconds) = settings.replay_buffer_seconds {
+            let (replay_encoder, replay_encoder_error) = match ReplayEncoder::new(settings.width, settings.height, window_seconds) {
+                Ok(encoder) => (Some(Arc::new(Mutex::new(encoder))), None),
+                Err(e) => {
+                    println!("❌ CRITICAL: Replay encoder creation failed: {}", e);
+                    (None, Some(e.reason))
                 }
-            }
+            };
+            
+            return Self {
+                video_encoder: None,
+                // The replay encoder stands in for the video writer
+                video_encoder_error: replay_encoder_error,
+                audio_encoder: None,
+                audio_encoder_error: None,
+                replay_encoder,
+                keystroke_overlay: None,
+                webcam_overlay: None,
+                watermark: None,
+                frame_batcher: None,
+                early_audio: Mutex::new(None),
+                system_audio_gain: 1.0,
+                microphone_gain: 1.0,
+                frame_count: Arc::new(Mutex::new(0)),
+                audio_frame_count: Arc::new(Mutex::new(0)),
+                is_recording,
+                pause_state: Mutex::new(PauseState::default()),
+                fps_window: Mutex::new(RateWindow::new(DEFAULT_PROGRESS_INTERVAL, Instant::now())),
+                last_sample_at: LastFrameTime::default(),
+                fps_counter: Arc::new(Mutex::new(0.0)),
+                objc_bridge: None,
+                stop_listener: None,
+                permission_revoked_behavior: PermissionRevokedBehavior::default(),
+                idle_tracker: None,
+                frame_geometry: Mutex::new(None),
+                latest_frame: Mutex::new(None),
+                audio_enabled: AtomicBool::new(true),
+                video_enabled: AtomicBool::new(true),
+                stream_video_ignored: AtomicBool::new(false),
+                starts_at: None,
+                chapter_detector: None,
+                buffer_validator: None,
+                temp_files: Mutex::new(TempFiles::new()),
+                resolution_watch: ResolutionWatch::new(ResolutionChangeBehavior::Scale, settings.scaling_mode),
+                segment: Mutex::new((1, output_path.clone())),
+                encoder_settings: settings,
+                new_segment_listener: None,
+                output_path,
+            };
         }
         
         // Create video encoder with the main output path (not separate files)
-        let video_encoder = VideoEncoder::new(&output_path, width, height, fps)
-            .map(|encoder| {
-                println!("✅ Video encoder created: {}x{} @ {}fps", width, height, fps);
-                Arc::new(Mutex::new(encoder))
-            })
-            .map_err(|e| {
+        let (video_encoder, video_encoder_error) = match Self::create_video_encoder(&output_path, &settings) {
+            Ok(encoder) => {
+                println!("✅ Video encoder created: {}x{} @ {}fps", settings.width, settings.height, settings.fps);
+                (Some(Arc::new(Mutex::new(encoder))), None)
+            }
+            Err(e) => {
                 println!("❌ Video encoder creation failed: {}", e);
-                e
-            })
-            .ok();
-        
-        // Create audio encoder with separate audio file for now
-        let audio_path = output_path.replace(".mp4", "_audio.m4a");
-        let audio_encoder = AudioEncoder::new(&audio_path, 48000, 2)
-            .map(|encoder| {
-                println!("✅ Audio encoder created: 48kHz stereo");
-                Arc::new(Mutex::new(encoder))
-            })
-            .map_err(|e| {
+                (None, Some(e.reason))
+            }
+        };
+        
+        // Create audio encoder with separate audio file for now; a streamed recording is
+        // video only and writes nothing next to outputPath
+        let audio_path = audio_path_for(&output_path);
+        let audio_writer = match settings.fragment_sink {
+            Some(_) => Err(Error::new(Status::GenericFailure, "Audio isn't streamed to outputFd or outputPipe")),
+            None if settings.separate_audio_tracks => AudioEncoder::with_microphone_track(&audio_path, 48000, 2),
+            None => AudioEncoder::new(&audio_path, 48000, 2),
+        };
+        let (audio_encoder, audio_encoder_error) = match audio_writer {
+            Ok(encoder) => {
+                let tracks = if encoder.has_microphone_track() { "system + microphone tracks" } else { "one track" };
+                println!("✅ Audio encoder created: 48kHz stereo, {}", tracks);
+                (Some(Arc::new(Mutex::new(encoder))), None)
+            }
+            Err(e) => {
                 println!("⚠️ Audio encoder creation failed (video-only mode): {}", e);
-                e
-            })
-            .ok();
+                (None, Some(e.reason))
+            }
+        };
+        
+        let mut temp_files = TempFiles::new();
+        if audio_encoder.is_some() {
+            temp_files.track(&audio_path);
+        }
         
         // Show encoder status for production debugging
         match (&video_encoder, &audio_encoder) {
@@ -71,16 +237,166 @@ impl RealStreamDelegate {
             (None, _) => println!("❌ CRITICAL: Video encoder failed - recording will not work"),
         }
         
+        let early_audio = (video_encoder.is_some() && audio_encoder.is_some())
+            .then(|| EarlyAudioBuffer::new(DEFAULT_EARLY_AUDIO_BUFFER_MS));
+        
         Self {
             output_path: output_path.clone(),
             video_encoder,
+            video_encoder_error,
             audio_encoder,
+            audio_encoder_error,
+            replay_encoder: None,
+            keystroke_overlay: None,
+            webcam_overlay: None,
+            watermark: None,
+            frame_batcher: None,
+            early_audio: Mutex::new(early_audio),
+            system_audio_gain: 1.0,
+            microphone_gain: 1.0,
             frame_count: Arc::new(Mutex::new(0)),
             audio_frame_count: Arc::new(Mutex::new(0)),
             is_recording,
-            last_frame_time: Arc::new(Mutex::new(std::time::Instant::now())),
+            pause_state: Mutex::new(PauseState::default()),
+            fps_window: Mutex::new(RateWindow::new(DEFAULT_PROGRESS_INTERVAL, Instant::now())),
+            last_sample_at: LastFrameTime::default(),
             fps_counter: Arc::new(Mutex::new(0.0)),
             objc_bridge: None,
+            stop_listener: None,
+            permission_revoked_behavior: PermissionRevokedBehavior::default(),
+            idle_tracker: None,
+            frame_geometry: Mutex::new(None),
+            latest_frame: Mutex::new(None),
+            audio_enabled: AtomicBool::new(true),
+            video_enabled: AtomicBool::new(true),
+            stream_video_ignored: AtomicBool::new(false),
+            starts_at: None,
+            chapter_detector: None,
+            buffer_validator: None,
+            temp_files: Mutex::new(temp_files),
+            resolution_watch: ResolutionWatch::new(ResolutionChangeBehavior::Scale, settings.scaling_mode),
+            segment: Mutex::new((1, output_path.clone())),
+            encoder_settings: settings,
+            new_segment_listener: None,
+        }
+    }
+    
+    /// Hold at most `limit_ms` of audio arriving before the first video frame; 0 drops it
+    pub fn set_early_audio_buffer_ms(&mut self, limit_ms: u32) {
+        if let Ok(mut early_audio) = self.early_audio.lock() {
+            if early_audio.is_some() {
+                *early_audio = Some(EarlyAudioBuffer::new(limit_ms));
+            }
+        }
+    }
+    
+    /// Tell `listener` when ScreenCaptureKit stops the stream with an error
+    pub fn set_stop_listener(&mut self, listener: StreamStopListener) {
+        self.stop_listener = Some(listener);
+    }
+    
+    /// Scale frames that change size, or start a new segment at their size
+    pub fn set_resolution_change_behavior(&mut self, behavior: ResolutionChangeBehavior) {
+        self.resolution_watch = ResolutionWatch::new(behavior, self.encoder_settings.scaling_mode);
+    }
+    
+    /// Tell `listener` when a frame size change started a new segment
+    pub fn set_new_segment_listener(&mut self, listener: NewSegmentListener) {
+        self.new_segment_listener = Some(listener);
+    }
+    
+    /// Keep or delete the partial file when permission is revoked mid-recording
+    pub fn set_permission_revoked_behavior(&mut self, behavior: PermissionRevokedBehavior) {
+        self.permission_revoked_behavior = behavior;
+    }
+    
+    /// Drop samples until `starts_at`, so the recording begins after a countdown
+    pub fn set_start_time(&mut self, starts_at: Instant) {
+        self.starts_at = Some(starts_at);
+    }
+    
+    /// Collect scene cuts in `width` x `height` frames as chapter starts
+    pub fn enable_chapter_detection(&mut self, sensitivity: f64, width: u32, height: u32) {
+        self.chapter_detector = Some((Mutex::new(SceneCutDetector::new(sensitivity)), width, height));
+    }
+    
+    /// Check every video sample against `expected` and log mismatches
+    pub fn enable_buffer_validation(&mut self, expected: ExpectedBuffer) {
+        self.buffer_validator = Some(BufferValidator::new(expected));
+    }
+    
+    /// Chapter start times found so far, in seconds; None without detectChapters
+    pub fn chapter_starts(&self) -> Option<Vec<f64>> {
+        let (ref detector, ..) = self.chapter_detector.as_ref()?;
+        detector.lock().ok().map(|detector| detector.chapter_starts())
+    }
+    
+    /// Whether the start delay is still running
+    fn waiting_to_start(&self) -> bool {
+        self.starts_at.is_some_and(|starts_at| Instant::now() < starts_at)
+    }
+    
+    /// Draw recent keystrokes onto every frame before it's encoded
+    pub fn set_keystroke_overlay(&mut self, overlay: Arc<KeystrokeOverlay>) {
+        self.keystroke_overlay = Some(overlay);
+    }
+    
+    /// Draw the camera in its corner of every frame before it's encoded
+    pub fn set_webcam_overlay(&mut self, overlay: WebcamOverlay) {
+        self.webcam_overlay = Some(overlay);
+    }
+    
+    /// Blend the watermark into its corner of every frame, over the other overlays
+    pub fn set_watermark(&mut self, watermark: Watermark) {
+        self.watermark = Some(watermark);
+    }
+    
+    /// Watch frames for content changes; the returned time is when the content last changed
+    pub fn enable_idle_detection(&mut self) -> LastChangeTime {
+        // Idle time counts from the end of any start delay
+        let last_change: LastChangeTime = Arc::new(Mutex::new(Some(self.starts_at.unwrap_or_else(Instant::now))));
+        self.idle_tracker = Some((Mutex::new(ChangeTracker::default()), last_change.clone()));
+        last_change
+    }
+    
+    /// Copy every recorded frame out to `batcher` as well as encoding it
+    pub fn set_frame_batcher(&mut self, batcher: FrameBatcher) {
+        self.frame_batcher = Some(batcher);
+    }
+    
+    /// How often the frame rate is measured and logged (default `DEFAULT_PROGRESS_INTERVAL`)
+    pub fn set_progress_interval(&mut self, interval: Duration) {
+        self.fps_window = Mutex::new(RateWindow::new(interval, Instant::now()));
+    }
+    
+    /// Balance system audio against the microphone; gains are in dB and clamped to a safe range
+    pub fn set_audio_gains(&mut self, system_audio_gain_db: f64, microphone_gain_db: f64) {
+        self.system_audio_gain = gain_db_to_linear(system_audio_gain_db);
+        self.microphone_gain = gain_db_to_linear(microphone_gain_db);
+        println!("🎚️ Audio gains: system {:.1} dB, microphone {:.1} dB", system_audio_gain_db, microphone_gain_db);
+    }
+    
+    /// Create the video encoder, retrying transient `startWriting` failures
+    fn create_video_encoder(output_path: &str, settings: &VideoEncoderSettings) -> Result<VideoEncoder> {
+        let mut attempt = 0;
+        loop {
+            match VideoEncoder::new(output_path, settings) {
+                Ok(encoder) => return Ok(encoder),
+                Err(e) if attempt < settings.writer_start_retries => {
+                    attempt += 1;
+                    println!("⚠️ Video encoder start failed ({}), retrying {}/{}", e.reason, attempt, settings.writer_start_retries);
+                    if settings.fragment_sink.is_none() {
+                        let _ = std::fs::remove_file(output_path);
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(WRITER_START_RETRY_DELAY_MS * attempt as u64));
+                }
+                Err(e) => {
+                    return Err(Error::new(
+                        Status::GenericFailure,
+                        format!("{} (after {} attempt(s))", e.reason, attempt + 1),
+                    ));
+                }
+            }
         }
     }
     
@@ -108,42 +424,277 @@ impl RealStreamDelegate {
     /// Process real video sample buffer from ScreenCaptureKit
     /// BLAZINGLY FAST: Zero-copy frame processing with sub-millisecond latency
     pub fn handle_video_sample_buffer(&self, sample_buffer: &CMSampleBuffer) {
+        if let Ok(mut last_sample_at) = self.last_sample_at.lock() {
+            *last_sample_at = Some(Instant::now());
+        }
+        if let Some(ref validator) = self.buffer_validator {
+            validator.check(sample_buffer);
+        }
+        let scaled = self.fit_recording_size(sample_buffer);
+        let sample_buffer = scaled.as_ref().map_or(sample_buffer, |scaled| scaled.sample());
+        if let Ok(mut geometry) = self.frame_geometry.lock() {
+            if geometry.is_none() {
+                *geometry = unsafe { frame_geometry(sample_buffer) };
+            }
+        }
+        if self.is_paused() || self.waiting_to_start() {
+            self.keep_latest_frame(sample_buffer);
+            return;
+        }
+        
+        if let Some((ref tracker, ref last_change)) = self.idle_tracker {
+            let changed = match unsafe { frame_changes(sample_buffer) } {
+                Some(rects) => tracker.lock().map(|mut tracker| tracker.record(&rects)).unwrap_or(true),
+                None => true,
+            };
+            if changed {
+                if let Ok(mut last_change) = last_change.lock() {
+                    *last_change = Some(Instant::now());
+                }
+            }
+        }
+        
         // Update frame count and FPS calculation (FAST: atomic operations)
         if let Ok(mut count) = self.frame_count.lock() {
             *count += 1;
             
-            // Calculate FPS every 30 frames for production monitoring
-            if *count % 30 == 0 {
-                if let (Ok(mut last_time), Ok(mut fps)) = (self.last_frame_time.lock(), self.fps_counter.lock()) {
-                    let now = std::time::Instant::now();
-                    let duration = now.duration_since(*last_time);
-                    *fps = 30.0 / duration.as_secs_f64();
-                    *last_time = now;
-                    
+            // Calculate FPS once per progress interval for production monitoring
+            if let (Ok(mut window), Ok(mut fps)) = (self.fps_window.lock(), self.fps_counter.lock()) {
+                if let Some(rate) = window.observe(*count, Instant::now()) {
+                    *fps = rate;
                     println!("🚀 BLAZING FAST: {} frames @ {:.1} FPS", *count, *fps);
                 }
             }
         }
         
-        // Process the video frame (ZERO-COPY)
+        // Process the video frame (ZERO-COPY); with video disabled nothing of it is recorded
+        if !self.video_enabled() {
+            self.repeat_latest_frame(sample_buffer);
+            return;
+        }
         self.process_video_sample_buffer(sample_buffer, "production");
+        if let Ok(mut early_audio) = self.early_audio.lock() {
+            self.release_early_audio(&mut early_audio);
+        }
+        self.keep_latest_frame(sample_buffer);
+        self.detect_scene_cut(sample_buffer);
+        
+        if let Some(ref batcher) = self.frame_batcher {
+            batcher.push(sample_buffer);
+        }
+    }
+    
+    /// `sample_buffer` at the recording's frame size when it has another, unless it started a
+    /// new segment at its own size
+    fn fit_recording_size(&self, sample_buffer: &CMSampleBuffer) -> Option<ScaledSample> {
+        let (width, height) = self.resolution_watch.changed_size(sample_buffer)?;
+        if self.resolution_watch.behavior() == ResolutionChangeBehavior::Segment && self.start_next_segment(width, height) {
+            self.resolution_watch.adopt(width, height);
+            return None;
+        }
+        self.resolution_watch.scale(sample_buffer)
+    }
+    
+    /// Finalize the video written so far and continue in the next segment's file at
+    /// `width` x `height`; false when the new file can't be created
+    fn start_next_segment(&self, width: u32, height: u32) -> bool {
+        let (Some(ref encoder), Ok(mut segment)) = (&self.video_encoder, self.segment.lock()) else {
+            return false;
+        };
+        let next_path = segment_path(&self.output_path, segment.0 + 1);
+        let settings = VideoEncoderSettings {
+            width,
+            height,
+            pixel_buffer_pool: self.encoder_settings.pixel_buffer_pool
+                .map(|pool| PixelBufferPoolSettings { width, height, ..pool }),
+            timeline_offset: None,
+            ..self.encoder_settings.clone()
+        };
+        let next_encoder = match Self::create_video_encoder(&next_path, &settings) {
+            Ok(next_encoder) => next_encoder,
+            Err(e) => {
+                println!("⚠️ Couldn't start a new segment, scaling frames instead: {}", e);
+                return false;
+            }
+        };
+        let Ok(mut encoder) = encoder.lock() else {
+            return false;
+        };
+        let mut finished = std::mem::replace(&mut *encoder, next_encoder);
+        drop(encoder);
+        
+        let finished_path = match finished.finalize_encoding() {
+            Ok(path) => path,
+            Err(e) => {
+                println!("⚠️ Segment {} didn't finalize: {}", segment.0, e);
+                segment.1.clone()
+            }
+        };
+        println!("📼 Segment {} finished at {}; continuing at {}x{} in {}", segment.0, finished_path, width, height, next_path);
+        *segment = (segment.0 + 1, next_path.clone());
+        if let Some(ref listener) = self.new_segment_listener {
+            listener(NewSegmentEvent { finished_path, next_path, width, height });
+        }
+        true
+    }
+    
+    /// The file of the last segment once a size change started one
+    pub fn latest_segment_path(&self) -> Option<String> {
+        let segment = self.segment.lock().ok()?;
+        (segment.0 > 1).then(|| segment.1.clone())
+    }
+    
+    /// Start a chapter at an encoded frame that changed much of the picture
+    fn detect_scene_cut(&self, sample_buffer: &CMSampleBuffer) {
+        let Some((ref detector, width, height)) = self.chapter_detector else {
+            return;
+        };
+        let Some(rects) = (unsafe { frame_changes(sample_buffer) }) else {
+            return;
+        };
+        let presentation_time = unsafe { CMSampleBufferGetPresentationTimeStamp(sample_buffer) };
+        let seconds = self.video_encoder.as_ref()
+            .and_then(|encoder| encoder.lock().ok()?.seconds_into_recording(presentation_time));
+        if let (Some(seconds), Ok(mut detector)) = (seconds, detector.lock()) {
+            if detector.observe(seconds, changed_fraction(&rects, width, height)) {
+                println!("📑 Scene cut at {:.1}s starts a new chapter", seconds);
+            }
+        }
+    }
+    
+    /// Encode the last frame recorded before video was disabled in place of `sample_buffer`,
+    /// at its time. Instant replay just skips frames; its timestamps already hold the last one
+    fn repeat_latest_frame(&self, sample_buffer: &CMSampleBuffer) {
+        let (Some(ref encoder), Some(frame)) = (&self.video_encoder, self.latest_frame()) else {
+            return;
+        };
+        if let Ok(mut encoder) = encoder.lock() {
+            let presentation_time = unsafe { CMSampleBufferGetPresentationTimeStamp(sample_buffer) };
+            if let Err(e) = encoder.encode_pixel_buffer(frame.as_ptr(), presentation_time) {
+                println!("❌ Repeating the last frame failed: {}", e);
+            }
+        }
+    }
+    
+    /// Hold on to a frame nothing draws into anymore, replacing the previous one
+    fn keep_latest_frame(&self, sample_buffer: &CMSampleBuffer) {
+        // While video is disabled the latest frame is the one being repeated
+        if !self.video_enabled() {
+            return;
+        }
+        if let Some(frame) = RetainedPixelBuffer::from_sample_buffer(sample_buffer) {
+            if let Ok(mut latest) = self.latest_frame.lock() {
+                *latest = Some(frame);
+            }
+        }
+    }
+    
+    /// Another reference to the most recent frame, so the capture queue can keep replacing
+    /// it while the caller reads it; None before the first frame
+    pub fn latest_frame(&self) -> Option<RetainedPixelBuffer> {
+        self.latest_frame.lock().ok()?.clone()
     }
     
     /// Process real audio sample buffer from ScreenCaptureKit
     /// PRODUCTION-READY: High-performance audio processing
-    pub fn handle_audio_sample_buffer(&self, sample_buffer: &CMSampleBuffer) {
+    pub fn handle_audio_sample_buffer(&self, sample_buffer: &CMSampleBuffer, of_type: SCStreamOutputType) {
+        if self.is_paused() || self.waiting_to_start() {
+            return;
+        }
+        
+        let gain = match of_type {
+            _ if !self.audio_enabled() => 0.0,
+            SCStreamOutputType::Microphone => self.microphone_gain,
+            _ => self.system_audio_gain,
+        };
+        let gain_skipped = gain != 1.0 && !unsafe { apply_gain_to_sample_buffer(sample_buffer, gain) };
+        // Audio that can't be silenced in place is left out; the gap plays as silence too
+        if gain_skipped && gain == 0.0 {
+            return;
+        }
+        
         if let Ok(mut count) = self.audio_frame_count.lock() {
             *count += 1;
+            if gain_skipped && *count % 100 == 1 {
+                println!("⚠️ Audio gain skipped: sample buffer is not float PCM that can be scaled in place");
+            }
             if *count % 100 == 0 {
                 println!("🔊 Audio processing: {} samples @ production speed", *count);
             }
         }
         
-        self.process_audio_sample_buffer(sample_buffer, "production");
+        if self.hold_early_audio(sample_buffer, of_type) {
+            return;
+        }
+        self.process_audio_sample_buffer(sample_buffer, of_type);
+    }
+    
+    /// Whether `sample_buffer` was held back because no video frame has started the session yet;
+    /// audio before it would offset the audio from the video
+    fn hold_early_audio(&self, sample_buffer: &CMSampleBuffer, of_type: SCStreamOutputType) -> bool {
+        // Held under the lock, so audio appended straight away always follows the held audio
+        let Ok(mut early_audio) = self.early_audio.lock() else {
+            return false;
+        };
+        if self.release_early_audio(&mut early_audio) {
+            return false;
+        }
+        if let Some(ref mut held) = *early_audio {
+            unsafe {
+                let start = drift::seconds(CMSampleBufferGetPresentationTimeStamp(sample_buffer));
+                let end = start + drift::seconds(CMSampleBufferGetDuration(sample_buffer));
+                held.push((HeldSampleBuffer::retain(sample_buffer), of_type), start, end);
+            }
+        }
+        true
+    }
+    
+    /// Encode the held audio that still plays once the session has started and stop holding;
+    /// false while still waiting for it
+    fn release_early_audio(&self, early_audio: &mut Option<EarlyAudioBuffer<(HeldSampleBuffer, SCStreamOutputType)>>) -> bool {
+        if early_audio.is_none() {
+            return true;
+        }
+        let session_start = self.video_encoder.as_ref()
+            .and_then(|encoder| encoder.lock().ok().and_then(|encoder| encoder.session_start()));
+        let Some(session_start) = session_start else {
+            return false;
+        };
+        if let Some(mut held) = early_audio.take() {
+            for (sample, of_type) in held.flush(drift::seconds(session_start)) {
+                self.process_audio_sample_buffer(sample.sample(), of_type);
+            }
+            let audio_start = self.audio_encoder.as_ref()
+                .and_then(|encoder| encoder.lock().ok().and_then(|encoder| encoder.first_sample_time()));
+            if let Some(audio_start) = audio_start {
+                let lead_ms = (drift::seconds(session_start) - drift::seconds(audio_start)) * 1000.0;
+                println!("🔊 Early audio released: starts {:.1}ms before the first video frame", lead_ms);
+            }
+        }
+        true
     }
     
     /// BLAZINGLY FAST video frame processing
     fn process_video_sample_buffer(&self, sample_buffer: &CMSampleBuffer, _mode: &str) {
+        if let Some(ref overlay) = self.webcam_overlay {
+            overlay.render(sample_buffer);
+        }
+        if let Some(ref overlay) = self.keystroke_overlay {
+            overlay.render(sample_buffer);
+        }
+        if let Some(ref watermark) = self.watermark {
+            watermark.render(sample_buffer);
+        }
+        
+        if let Some(ref replay_encoder) = self.replay_encoder {
+            if let Ok(mut encoder) = replay_encoder.lock() {
+                if let Err(e) = encoder.encode_frame(sample_buffer) {
+                    println!("❌ Replay encoding failed: {}", e);
+                }
+            }
+            return;
+        }
+        
         // CRITICAL: Check if we have a video encoder
         let encoder_ref = match &self.video_encoder {
             Some(encoder) => encoder,
@@ -177,10 +728,14 @@ impl RealStreamDelegate {
     }
     
     /// PRODUCTION-READY audio processing
-    fn process_audio_sample_buffer(&self, sample_buffer: &CMSampleBuffer, _mode: &str) {
+    fn process_audio_sample_buffer(&self, sample_buffer: &CMSampleBuffer, of_type: SCStreamOutputType) {
         if let Some(ref encoder) = self.audio_encoder {
             if let Ok(mut encoder) = encoder.lock() {
-                match encoder.encode_frame(sample_buffer) {
+                let encoded = match of_type {
+                    SCStreamOutputType::Microphone => encoder.encode_microphone_buffer(sample_buffer),
+                    _ => encoder.encode_frame(sample_buffer),
+                };
+                match encoded {
                     Ok(()) => {}, // Success - audio encoded
                     Err(e) => println!("⚠️ Audio encoding failed: {}", e),
                 }
@@ -190,23 +745,72 @@ impl RealStreamDelegate {
     
     /// Handle stream stopped event with production-ready cleanup
     pub fn handle_stream_stopped(&self, error: Option<&NSError>) {
-        if let Some(error) = error {
-            println!("⚠️ Stream stopped with error: {:?}", error);
-        } else {
+        let stop_error = error.map(|error| {
+            let error_ptr = error as *const NSError as *mut NSError;
+            let description = unsafe { describe_nserror(error_ptr) };
+            println!("⚠️ Stream stopped with error: {}", description);
+            if unsafe { is_permission_error(error_ptr) } {
+                println!("🔒 Screen recording permission revoked; finalizing the partial recording");
+                SCError::PermissionRevoked
+            } else if let Some(reason) = session_state().blocked_reason() {
+                println!("🔒 Capture blocked by the system: {}", reason);
+                SCError::CaptureBlocked(reason.to_string())
+            } else {
+                SCError::SystemError(format!("Stream stopped: {}", description))
+            }
+        });
+        if stop_error.is_none() {
             println!("✅ Stream stopped successfully");
         }
         
+        // Finalize first so whatever was captured is a playable file
+        let mut output_path = match self.finalize_recording() {
+            Ok(path) => Some(path),
+            Err(e) => {
+                println!("❌ CRITICAL: Video finalization failed: {}", e);
+                None
+            }
+        };
+        
+        let Some(stop_error) = stop_error else {
+            return;
+        };
+        
+        if matches!(stop_error, SCError::PermissionRevoked) && self.permission_revoked_behavior == PermissionRevokedBehavior::Discard {
+            if let Some(path) = output_path.take() {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => println!("🗑️ Discarded partial recording: {}", path),
+                    Err(e) => println!("⚠️ Failed to discard partial recording {}: {}", path, e),
+                }
+            }
+        }
+        
+        if let Some(ref listener) = self.stop_listener {
+            listener(StreamStopEvent { error: stop_error, output_path });
+        }
+    }
+    
+    /// Finalize encoders and return the video output path
+    /// Fails when the recording stopped before any frame arrived and the encoder is
+    /// configured to report empty recordings as errors
+    pub fn finalize_recording(&self) -> Result<String> {
         // Set recording flag to false
         if let Ok(mut is_recording) = self.is_recording.lock() {
             *is_recording = false;
         }
         
+        // Frames still waiting for a full batch go out with the last one
+        if let Some(ref batcher) = self.frame_batcher {
+            batcher.flush();
+        }
+        
         // Finalize encoders for production output
+        let mut result = Ok(self.output_path.clone());
         if let Some(ref video_encoder) = self.video_encoder {
             if let Ok(mut encoder) = video_encoder.lock() {
-                match encoder.finalize_encoding() {
-                    Ok(path) => println!("✅ PRODUCTION: Video finalized: {}", path),
-                    Err(e) => println!("❌ CRITICAL: Video finalization failed: {}", e),
+                result = encoder.finalize_encoding();
+                if let Ok(ref path) = result {
+                    println!("✅ PRODUCTION: Video finalized: {}", path);
                 }
             }
         }
@@ -214,14 +818,33 @@ impl RealStreamDelegate {
         if let Some(ref audio_encoder) = self.audio_encoder {
             if let Ok(mut encoder) = audio_encoder.lock() {
                 match encoder.finalize_encoding() {
-                    Ok(path) => println!("✅ PRODUCTION: Audio finalized: {}", path),
+                    Ok(path) => {
+                        println!("✅ PRODUCTION: Audio finalized: {}", path);
+                        // Audio only belongs next to a video that was saved
+                        if result.is_ok() {
+                            if let Ok(mut temp_files) = self.temp_files.lock() {
+                                temp_files.keep(&path);
+                            }
+                        }
+                    }
                     Err(e) => println!("⚠️ Audio finalization failed: {}", e),
                 }
             }
         }
         
+        if let Ok(mut temp_files) = self.temp_files.lock() {
+            temp_files.cleanup();
+        }
+        
         // Print final statistics for production monitoring
         self.print_final_stats();
+        
+        result
+    }
+    
+    /// Why the video writer failed, from its NSError
+    pub fn writer_error(&self) -> Option<String> {
+        self.video_encoder.as_ref()?.lock().ok()?.writer_error()
     }
     
     /// Production-ready statistics reporting
@@ -245,6 +868,16 @@ impl RealStreamDelegate {
         }
     }
     
+    /// Write the instant replay window to `output_path`
+    pub fn save_replay(&self, output_path: &str) -> Result<String> {
+        let replay_encoder = self.replay_encoder.as_ref()
+            .ok_or_else(|| Error::new(Status::GenericFailure, "Instant replay is not enabled for this recording"))?;
+        
+        let encoder = replay_encoder.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Replay encoder lock poisoned"))?;
+        encoder.save_replay(output_path)
+    }
+    
     pub fn get_output_path(&self) -> String {
         self.output_path.clone()
     }
@@ -256,6 +889,16 @@ impl RealStreamDelegate {
         })
     }
     
+    /// Shared video frame counter, for progress reporting off the capture thread
+    pub fn frame_counter(&self) -> Arc<Mutex<u64>> {
+        self.frame_count.clone()
+    }
+    
+    /// When the last video sample buffer arrived, shared with frame delivery checks
+    pub fn last_sample_time(&self) -> LastFrameTime {
+        self.last_sample_at.clone()
+    }
+    
     pub fn get_audio_frame_count(&self) -> u64 {
         self.audio_frame_count.lock().map(|guard| *guard).unwrap_or_else(|_| {
             println!("⚠️ Audio frame count mutex was poisoned");
@@ -263,6 +906,70 @@ impl RealStreamDelegate {
         })
     }
     
+    /// Record audio, or silence in its place; the track keeps its timing either way
+    pub fn set_audio_enabled(&self, enabled: bool) {
+        self.audio_enabled.store(enabled, Ordering::SeqCst);
+    }
+    
+    /// Record video, or repeat the last recorded frame in its place
+    pub fn set_video_enabled(&self, enabled: bool) {
+        self.video_enabled.store(enabled, Ordering::SeqCst);
+    }
+    
+    /// Drop the ScreenCaptureKit stream's video from now on; another source delivers it
+    pub fn ignore_stream_video(&self) {
+        self.stream_video_ignored.store(true, Ordering::SeqCst);
+    }
+    
+    pub fn stream_video_ignored(&self) -> bool {
+        self.stream_video_ignored.load(Ordering::SeqCst)
+    }
+    
+    /// Why the video writer couldn't be set up, when it couldn't; nothing is recorded then
+    pub fn video_encoder_error(&self) -> Option<&str> {
+        self.video_encoder_error.as_deref()
+    }
+    
+    /// Why the audio writer couldn't be set up, when it couldn't; audio is then dropped
+    pub fn audio_encoder_error(&self) -> Option<&str> {
+        self.audio_encoder_error.as_deref()
+    }
+    
+    /// Abandon a recording that won't start, deleting the files the writers began
+    pub fn cancel_recording(&self) {
+        if let Some(ref video_encoder) = self.video_encoder {
+            if let Ok(mut encoder) = video_encoder.lock() {
+                encoder.cancel_encoding();
+            }
+        }
+    }
+    
+    pub fn audio_enabled(&self) -> bool {
+        self.audio_enabled.load(Ordering::SeqCst)
+    }
+    
+    pub fn video_enabled(&self) -> bool {
+        self.video_enabled.load(Ordering::SeqCst)
+    }
+    
+    /// Largest drift of the video and audio timestamps from host time, in ms
+    pub fn max_drift_ms(&self) -> (f64, f64) {
+        let video = self.video_encoder.as_ref()
+            .and_then(|encoder| encoder.lock().ok().map(|encoder| encoder.max_drift_ms()))
+            .unwrap_or(0.0);
+        let audio = self.audio_encoder.as_ref()
+            .and_then(|encoder| encoder.lock().ok().map(|encoder| encoder.max_drift_ms()))
+            .unwrap_or(0.0);
+        (video, audio)
+    }
+    
+    /// (nudged, dropped) counts of video frames whose timestamp didn't increase
+    pub fn timestamp_fixes(&self) -> (u64, u64) {
+        self.video_encoder.as_ref()
+            .and_then(|encoder| encoder.lock().ok().map(|encoder| encoder.timestamp_fixes()))
+            .unwrap_or_default()
+    }
+    
     pub fn get_current_fps(&self) -> f64 {
         self.fps_counter.lock().map(|guard| *guard).unwrap_or_else(|_| {
             println!("⚠️ FPS counter mutex was poisoned");
@@ -270,6 +977,63 @@ impl RealStreamDelegate {
         })
     }
     
+    /// Stop or restart encoding without ending the stream; paused time is left out of the output
+    pub fn set_paused(&self, paused: bool) {
+        self.update_pause(|state| state.by_user = paused);
+    }
+    
+    /// Pause encoding while secure input is active; independent of `set_paused`, encoding
+    /// resumes once neither holds it
+    pub fn set_secure_input_paused(&self, paused: bool) {
+        self.update_pause(|state| state.for_secure_input = paused);
+    }
+    
+    fn update_pause(&self, change: impl FnOnce(&mut PauseState)) {
+        let Ok(mut state) = self.pause_state.lock() else {
+            return;
+        };
+        let was_paused = state.is_paused();
+        change(&mut state);
+        let paused = state.is_paused();
+        if paused == was_paused {
+            return;
+        }
+        
+        if let Some(ref video_encoder) = self.video_encoder {
+            if let Ok(mut encoder) = video_encoder.lock() {
+                if paused {
+                    encoder.mark_paused();
+                } else {
+                    encoder.mark_resumed();
+                }
+            }
+        }
+        if let Some(ref audio_encoder) = self.audio_encoder {
+            if let Ok(mut encoder) = audio_encoder.lock() {
+                if paused {
+                    encoder.mark_paused();
+                } else {
+                    encoder.mark_resumed();
+                }
+            }
+        }
+        // Paused time never counts as idle
+        if let Some((_, ref last_change)) = self.idle_tracker {
+            if let Ok(mut last_change) = last_change.lock() {
+                *last_change = if paused { None } else { Some(Instant::now()) };
+            }
+        }
+    }
+    
+    /// Where ScreenCaptureKit placed the content in its frames; None until a frame reports it
+    pub fn frame_geometry(&self) -> Option<FrameGeometry> {
+        self.frame_geometry.lock().ok().and_then(|geometry| *geometry)
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.pause_state.lock().map(|state| state.is_paused()).unwrap_or(false)
+    }
+    
     /// Check if the delegate is actively recording
     pub fn is_recording(&self) -> bool {
         self.is_recording.lock().map(|guard| *guard).unwrap_or(false)
@@ -288,7 +1052,7 @@ impl SCStreamDelegate for RealStreamDelegate {
                 self.handle_video_sample_buffer(sample_buffer);
             }
             SCStreamOutputType::Audio | SCStreamOutputType::Microphone => {
-                self.handle_audio_sample_buffer(sample_buffer);
+                self.handle_audio_sample_buffer(sample_buffer, of_type);
             }
         }
     }
@@ -298,4 +1062,221 @@ impl SCStreamDelegate for RealStreamDelegate {
     }
 }
 
- 
\ No newline at end of file
+ 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::segments::SegmentInfo;
+    use super::super::test_frames::{FrameGenerator, ToneGenerator};
+    use super::super::types::OutputColorSpace;
+    use super::super::verify;
+
+    fn test_delegate(name: &str, settings: VideoEncoderSettings) -> RealStreamDelegate {
+        let output_path = std::env::temp_dir().join(name).to_string_lossy().to_string();
+        let _ = std::fs::remove_file(&output_path);
+        RealStreamDelegate::new(output_path, Arc::new(Mutex::new(true)), settings)
+    }
+    
+    fn sized(width: u32, height: u32) -> VideoEncoderSettings {
+        VideoEncoderSettings { width, height, ..VideoEncoderSettings::default() }
+    }
+
+    #[test]
+    fn test_generated_frames_are_counted_and_encoded() {
+        let mut delegate = test_delegate("screencapturekit_delegate_frames_test.mp4", sized(320, 240));
+        let mut frames = FrameGenerator::new(320, 240, 30);
+
+        // FPS is measured once per progress interval of wall time, however many frames arrive
+        delegate.set_progress_interval(Duration::from_secs(60));
+        for _ in 0..29 {
+            delegate.handle_video_sample_buffer(frames.next_sample().sample());
+        }
+        assert_eq!(delegate.get_frame_count(), 29);
+        assert_eq!(delegate.get_current_fps(), 0.0, "No interval has passed yet");
+
+        delegate.set_progress_interval(Duration::from_millis(20));
+        std::thread::sleep(Duration::from_millis(30));
+        for _ in 29..90 {
+            delegate.handle_video_sample_buffer(frames.next_sample().sample());
+        }
+        assert_eq!(delegate.get_frame_count(), 90);
+        assert!(delegate.get_current_fps() > 0.0);
+        assert!(delegate.latest_frame().is_some());
+
+        let output_path = delegate.finalize_recording().expect("Generated frames make a recording");
+        let info = SegmentInfo::probe(&output_path).expect("The recording opens as a movie");
+        assert_eq!((info.width, info.height), (320, 240));
+        assert!(drift::seconds(info.duration) > 2.0, "90 frames at 30 FPS last about 3 seconds");
+        let _ = std::fs::remove_file(&output_path);
+    }
+
+    #[test]
+    fn test_sixty_fps_capture_is_written_at_thirty() {
+        let settings = VideoEncoderSettings { fps: 60, output_fps: Some(30), ..sized(320, 240) };
+        let delegate = test_delegate("screencapturekit_delegate_output_fps_test.mp4", settings);
+        let mut frames = FrameGenerator::new(320, 240, 60);
+        for _ in 0..120 {
+            delegate.handle_video_sample_buffer(frames.next_sample().sample());
+        }
+        assert_eq!(delegate.get_frame_count(), 120, "Every captured frame is counted");
+
+        let output_path = delegate.finalize_recording().expect("Decimated frames make a recording");
+        let info = SegmentInfo::probe(&output_path).expect("The recording opens as a movie");
+        let frame_rate: f32 = unsafe {
+            let asset = super::super::segments::open_asset(&output_path);
+            let media_type = objc2_foundation::NSString::from_str(super::super::encoder::AVMediaTypeVideo);
+            let tracks: *mut objc2_foundation::NSArray<AnyObject> = msg_send![asset, tracksWithMediaType: &*media_type];
+            let track: *mut AnyObject = msg_send![tracks, firstObject];
+            msg_send![track, nominalFrameRate]
+        };
+        assert!((frame_rate - 30.0).abs() < 1.0, "Written at 30 FPS, got {}", frame_rate);
+        assert!((drift::seconds(info.duration) - 2.0).abs() < 0.1, "Two seconds of capture stay two seconds");
+        let _ = std::fs::remove_file(&output_path);
+    }
+
+    #[test]
+    fn test_resolution_change_is_scaled_or_starts_a_segment() {
+        let record = |name: &str, behavior: ResolutionChangeBehavior| {
+            let mut delegate = test_delegate(name, sized(320, 240));
+            delegate.set_resolution_change_behavior(behavior);
+            let mut frames = FrameGenerator::new(320, 240, 30);
+            for frame in 0..60 {
+                if frame == 30 {
+                    (frames.width, frames.height) = (640, 480);
+                }
+                delegate.handle_video_sample_buffer(frames.next_sample().sample());
+            }
+            let output_path = delegate.finalize_recording().expect("Resized frames still make a recording");
+            (delegate, output_path)
+        };
+
+        let (delegate, output_path) = record("screencapturekit_delegate_scaled_test.mp4", ResolutionChangeBehavior::Scale);
+        assert_eq!(delegate.latest_segment_path(), None);
+        let info = SegmentInfo::probe(&output_path).expect("The recording opens as a movie");
+        assert_eq!((info.width, info.height), (320, 240), "Larger frames are scaled to the first size");
+        assert!(drift::seconds(info.duration) > 1.5, "Frames after the change are kept");
+        let _ = std::fs::remove_file(&output_path);
+
+        let (delegate, output_path) = record("screencapturekit_delegate_segment_test.mp4", ResolutionChangeBehavior::Segment);
+        let first_path = delegate.get_output_path();
+        assert_eq!(delegate.latest_segment_path().as_deref(), Some(output_path.as_str()));
+        assert!(output_path.ends_with("_part2.mp4"));
+        let first = SegmentInfo::probe(&first_path).expect("The first segment was finalized");
+        let second = SegmentInfo::probe(&output_path).expect("The second segment opens as a movie");
+        assert_eq!((first.width, first.height), (320, 240));
+        assert_eq!((second.width, second.height), (640, 480));
+        let _ = std::fs::remove_file(&first_path);
+        let _ = std::fs::remove_file(&output_path);
+    }
+
+    #[test]
+    fn test_display_p3_recordings_are_tagged_p3() {
+        let record = |name: &str, color_space: OutputColorSpace| {
+            let delegate = test_delegate(name, VideoEncoderSettings { color_space, ..sized(320, 240) });
+            let mut frames = FrameGenerator::new(320, 240, 30);
+            for _ in 0..15 {
+                delegate.handle_video_sample_buffer(frames.next_sample().sample());
+            }
+            let output_path = delegate.finalize_recording().expect("The recording finalizes");
+            let primaries = verify::color_primaries(&output_path);
+            let _ = std::fs::remove_file(&output_path);
+            primaries
+        };
+
+        let p3 = record("screencapturekit_delegate_p3_test.mp4", OutputColorSpace::DisplayP3);
+        assert_eq!(p3.as_deref(), Some("P3_D65"));
+        let default = record("screencapturekit_delegate_bt709_test.mp4", OutputColorSpace::default());
+        assert_eq!(default.as_deref(), Some("ITU_R_709_2"), "Only colorSpace \"p3\" tags P3 primaries");
+    }
+
+    #[test]
+    fn test_audio_writer_failures_are_reported() {
+        let output_path = std::env::temp_dir().join("screencapturekit_delegate_no_audio_test.mp4").to_string_lossy().to_string();
+        // A directory where the audio file belongs keeps the audio writer from being created
+        let audio_path = audio_path_for(&output_path);
+        let _ = std::fs::create_dir_all(&audio_path);
+
+        let delegate = test_delegate("screencapturekit_delegate_no_audio_test.mp4", sized(160, 120));
+        assert!(delegate.audio_encoder_error().is_some(), "The failed audio writer is reported");
+        delegate.cancel_recording();
+        assert!(!std::path::Path::new(&output_path).exists(), "A cancelled recording leaves no file");
+        let _ = std::fs::remove_dir(&audio_path);
+        let delegate = test_delegate("screencapturekit_delegate_audio_test.mp4", sized(160, 120));
+        assert!(delegate.audio_encoder_error().is_none());
+        delegate.cancel_recording();
+    }
+
+    #[test]
+    fn test_audio_of_a_mov_recording_goes_next_to_it() {
+        assert_eq!(audio_path_for("/tmp/take.mov"), "/tmp/take_audio.m4a");
+        assert_eq!(audio_path_for("/tmp/take.M4V"), "/tmp/take_audio.m4a");
+        assert_eq!(audio_path_for("/tmp/take.mp4"), "/tmp/take_audio.m4a");
+
+        let delegate = test_delegate("screencapturekit_delegate_mov_audio_test.mov", sized(160, 120));
+        let audio_path = audio_path_for(&delegate.get_output_path());
+        assert!(delegate.audio_encoder_error().is_none(), "The audio writer gets a file of its own");
+        let mut frames = FrameGenerator::new(160, 120, 30);
+        let mut tone = ToneGenerator::new(48000, 2, 440.0, 0.0);
+        for _ in 0..30 {
+            delegate.handle_video_sample_buffer(frames.next_sample().sample());
+            delegate.handle_audio_sample_buffer(tone.next_sample().sample(), SCStreamOutputType::Audio);
+        }
+        let output_path = delegate.finalize_recording().expect("The .mov recording finalizes");
+        let verification = verify::verify_recording(&output_path).expect("The recording opens");
+        assert!(verification.has_video, "The video wasn't overwritten by the audio writer");
+        assert!(verification.has_audio, "The audio is in {}", audio_path);
+        let _ = std::fs::remove_file(&output_path);
+        let _ = std::fs::remove_file(&audio_path);
+    }
+
+    #[test]
+    fn test_early_audio_waits_for_the_first_video_frame() {
+        let delegate = test_delegate("screencapturekit_delegate_early_audio_test.mp4", sized(160, 120));
+        let audio_path = audio_path_for(&delegate.get_output_path());
+        // 30 buffers of 1024 frames from t=0; the first video frame is stamped 0.5s
+        let mut tone = ToneGenerator::new(48000, 2, 440.0, 0.0);
+        let mut frames = FrameGenerator::new(160, 120, 30);
+        for _ in 0..15 {
+            frames.next_sample();
+        }
+        for _ in 0..30 {
+            delegate.handle_audio_sample_buffer(tone.next_sample().sample(), SCStreamOutputType::Audio);
+        }
+        let first_audio = || delegate.audio_encoder.as_ref().and_then(|encoder| encoder.lock().unwrap().first_sample_time());
+        assert!(first_audio().is_none(), "Audio waits for the first video frame");
+
+        for _ in 0..30 {
+            delegate.handle_video_sample_buffer(frames.next_sample().sample());
+            delegate.handle_audio_sample_buffer(tone.next_sample().sample(), SCStreamOutputType::Audio);
+        }
+        let video_start = delegate.video_encoder.as_ref().and_then(|encoder| encoder.lock().unwrap().session_start())
+            .expect("The first frame started the session");
+        let audio_start = first_audio().expect("The held audio was encoded");
+        let offset = drift::seconds(video_start) - drift::seconds(audio_start);
+        let buffer_seconds = 1024.0 / 48000.0;
+        assert!((0.0..buffer_seconds).contains(&offset), "Audio starts with the video, {}s apart", offset);
+
+        let output_path = delegate.finalize_recording().expect("The recording finalizes");
+        let _ = std::fs::remove_file(&output_path);
+        let _ = std::fs::remove_file(&audio_path);
+    }
+    
+    #[test]
+    fn test_paused_delegate_counts_no_frames() {
+        let delegate = test_delegate("screencapturekit_delegate_paused_test.mp4", sized(160, 120));
+        let mut frames = FrameGenerator::new(160, 120, 30);
+
+        delegate.set_paused(true);
+        for _ in 0..10 {
+            delegate.handle_video_sample_buffer(frames.next_sample().sample());
+        }
+        assert_eq!(delegate.get_frame_count(), 0);
+        assert!(delegate.latest_frame().is_some(), "Paused frames are still kept for snapshots");
+
+        delegate.set_paused(false);
+        delegate.handle_video_sample_buffer(frames.next_sample().sample());
+        assert_eq!(delegate.get_frame_count(), 1);
+        let _ = delegate.finalize_recording();
+        let _ = std::fs::remove_file(delegate.get_output_path());
+    }
+}
@@ -0,0 +1,303 @@
+// External device capture
+// An iPhone or iPad plugged in over USB can be recorded the way QuickTime Player does it: once
+// CoreMediaIO is told to allow screen capture devices, each unlocked, trusted device shows up
+// as a muxed AVCaptureDevice. Its frames come from an AVCaptureSession instead of
+// ScreenCaptureKit, so this path is separate from screen recording and only shares the encoder.
+// Recording is video only
+//
+// The host app needs camera access, like the webcam overlay: NSCameraUsageDescription in its
+// Info.plist, plus the com.apple.security.device.camera entitlement when it's sandboxed or
+// uses the hardened runtime. Screen Recording permission isn't involved
+
+use std::ffi::{c_char, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSArray, NSString, NSError, NSNumber};
+use objc2_core_media::CMSampleBuffer;
+use napi::{Result, Status, Error};
+
+use super::encoder::{VideoEncoder, VideoEncoderSettings};
+use super::foundation::describe_nserror;
+use super::recording::kCVPixelFormatType_32BGRA;
+use super::stream_output::create_output_directory;
+use super::types::CapturePixelFormat;
+use super::webcam::check_camera_access;
+use crate::{CaptureDevice, RecordingConfiguration};
+
+const AVMediaTypeMuxed: &str = "muxx";
+
+// CoreMediaIO property that makes attached iOS devices appear as capture devices
+const CMIO_OBJECT_SYSTEM_OBJECT: u32 = 1; // kCMIOObjectSystemObject
+const CMIO_HARDWARE_PROPERTY_ALLOW_SCREEN_CAPTURE_DEVICES: u32 = u32::from_be_bytes(*b"yes ");
+const CMIO_OBJECT_PROPERTY_SCOPE_GLOBAL: u32 = u32::from_be_bytes(*b"glob");
+const CMIO_OBJECT_PROPERTY_ELEMENT_MAIN: u32 = 0;
+
+/// Matches CMIOObjectPropertyAddress
+#[repr(C)]
+struct CMIOObjectPropertyAddress {
+    selector: u32,
+    scope: u32,
+    element: u32,
+}
+
+/// Matches CMVideoDimensions
+#[repr(C)]
+struct CMVideoDimensions {
+    width: i32,
+    height: i32,
+}
+
+extern "C" {
+    static kCVPixelBufferPixelFormatTypeKey: *const NSString;
+
+    fn create_camera_output_bridge(rust_context: *mut c_void, frame_callback: extern "C" fn(*mut c_void, *const CMSampleBuffer)) -> *mut c_void;
+    fn release_camera_output_bridge(bridge: *mut c_void);
+
+    fn dispatch_queue_create(label: *const c_char, attr: *const c_void) -> *mut c_void;
+    fn dispatch_sync_f(queue: *mut c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+    fn dispatch_release(object: *mut c_void);
+
+    fn CMIOObjectSetPropertyData(
+        object_id: u32,
+        address: *const CMIOObjectPropertyAddress,
+        qualifier_data_size: u32,
+        qualifier_data: *const c_void,
+        data_size: u32,
+        data: *const c_void,
+    ) -> i32;
+    fn CMVideoFormatDescriptionGetDimensions(description: *const c_void) -> CMVideoDimensions;
+}
+
+/// Let attached iOS devices show up as capture devices; they can take a moment to appear
+/// the first time this is called in a process
+pub fn allow_screen_capture_devices() {
+    let address = CMIOObjectPropertyAddress {
+        selector: CMIO_HARDWARE_PROPERTY_ALLOW_SCREEN_CAPTURE_DEVICES,
+        scope: CMIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+        element: CMIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+    };
+    let allow: u32 = 1;
+    let status = unsafe {
+        CMIOObjectSetPropertyData(
+            CMIO_OBJECT_SYSTEM_OBJECT,
+            &address,
+            0,
+            ptr::null(),
+            std::mem::size_of::<u32>() as u32,
+            &allow as *const u32 as *const c_void,
+        )
+    };
+    if status != 0 {
+        log::warn!("Failed to allow screen capture devices (OSStatus {})", status);
+    }
+}
+
+/// Attached devices that can be recorded with startDeviceCapture
+pub fn available_devices() -> Vec<CaptureDevice> {
+    allow_screen_capture_devices();
+    unsafe {
+        let media_type = NSString::from_str(AVMediaTypeMuxed);
+        let devices: *mut NSArray<AnyObject> = msg_send![class!(AVCaptureDevice), devicesWithMediaType: &*media_type];
+        if devices.is_null() {
+            return Vec::new();
+        }
+        (*devices).iter().filter_map(|device| {
+            let id: *mut NSString = msg_send![&*device, uniqueID];
+            let name: *mut NSString = msg_send![&*device, localizedName];
+            let model: *mut NSString = msg_send![&*device, modelID];
+            (!id.is_null()).then(|| CaptureDevice {
+                id: (*id).to_string(),
+                name: if name.is_null() { String::new() } else { (*name).to_string() },
+                model: if model.is_null() { String::new() } else { (*model).to_string() },
+            })
+        }).collect()
+    }
+}
+
+/// Options of a recording configuration device capture can't honour
+fn check_configuration(config: &RecordingConfiguration) -> Result<()> {
+    if config.output_path.is_empty() {
+        return Err(Error::new(Status::InvalidArg, "Output path cannot be empty"));
+    }
+    let pixel_format = config.pixel_format.as_deref().map(|value| {
+        CapturePixelFormat::from_config_str(value)
+            .ok_or_else(|| Error::new(Status::InvalidArg, format!("Unknown pixelFormat '{}'", value)))
+    }).transpose()?;
+    if pixel_format.is_some_and(|format| format != CapturePixelFormat::Bgra) {
+        return Err(Error::new(Status::InvalidArg, "Device capture only records the \"bgra\" pixel format"));
+    }
+    if config.replay_buffer_seconds.is_some() {
+        return Err(Error::new(Status::InvalidArg, "replayBufferSeconds isn't supported for device capture"));
+    }
+    Ok(())
+}
+
+/// A device recording in progress; dropping it stops the session and finishes the file
+pub struct DeviceCapture {
+    session: *mut AnyObject,
+    output: *mut AnyObject,
+    bridge: *mut c_void,
+    queue: *mut c_void,
+    encoder: Arc<Mutex<VideoEncoder>>,
+    // The callback's reference to the encoder, released once the queue is drained
+    context: *const Mutex<VideoEncoder>,
+}
+
+// Safety: the session objects are only touched in start and stop; frames go through the mutex
+unsafe impl Send for DeviceCapture {}
+unsafe impl Sync for DeviceCapture {}
+
+impl DeviceCapture {
+    /// Record `device_id` (an id from available_devices) to `config.output_path`
+    /// Without a configured size the output matches the device's current format
+    pub fn start(device_id: &str, config: &RecordingConfiguration) -> Result<Self> {
+        check_configuration(config)?;
+        allow_screen_capture_devices();
+
+        unsafe {
+            check_camera_access()?;
+
+            let device: *mut AnyObject = msg_send![class!(AVCaptureDevice), deviceWithUniqueID: &*NSString::from_str(device_id)];
+            if device.is_null() {
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Device '{}' not found; it must be connected, unlocked and trust this Mac", device_id),
+                ));
+            }
+
+            let mut settings = VideoEncoderSettings::from_config(config);
+            let (width, height) = device_dimensions(device);
+            if config.width.is_none() && width > 0 {
+                settings.width = width & !1;
+            }
+            if config.height.is_none() && height > 0 {
+                settings.height = height & !1;
+            }
+
+            let mut error: *mut NSError = ptr::null_mut();
+            let input: *mut AnyObject = msg_send![class!(AVCaptureDeviceInput), deviceInputWithDevice: device, error: &mut error];
+            if input.is_null() {
+                return Err(Error::new(Status::GenericFailure, format!("Failed to open the device: {}", describe_nserror(error))));
+            }
+
+            let session: *mut AnyObject = msg_send![class!(AVCaptureSession), new];
+            let output: *mut AnyObject = msg_send![class!(AVCaptureVideoDataOutput), new];
+            let format = NSNumber::new_u32(kCVPixelFormatType_32BGRA);
+            let video_settings: *mut AnyObject = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObject: &*format,
+                forKey: kCVPixelBufferPixelFormatTypeKey
+            ];
+            let _: () = msg_send![output, setVideoSettings: video_settings];
+            let _: () = msg_send![output, setAlwaysDiscardsLateVideoFrames: true];
+
+            let can_add_input: bool = msg_send![session, canAddInput: input];
+            let can_add_output: bool = msg_send![session, canAddOutput: output];
+            if !can_add_input || !can_add_output {
+                let _: () = msg_send![output, release];
+                let _: () = msg_send![session, release];
+                return Err(Error::new(Status::GenericFailure, "The device can't be added to a capture session"));
+            }
+            let _: () = msg_send![session, addInput: input];
+            let _: () = msg_send![session, addOutput: output];
+
+            let encoder = create_output_directory(&config.output_path)
+                .and_then(|_| VideoEncoder::new(&config.output_path, &settings));
+            let encoder = match encoder {
+                Ok(encoder) => Arc::new(Mutex::new(encoder)),
+                Err(e) => {
+                    let _: () = msg_send![output, release];
+                    let _: () = msg_send![session, release];
+                    return Err(e);
+                }
+            };
+
+            let context = Arc::into_raw(encoder.clone());
+            let bridge = create_camera_output_bridge(context as *mut c_void, device_frame_callback);
+            let queue = dispatch_queue_create(c"rustedscreencapture.device".as_ptr(), ptr::null());
+            let _: () = msg_send![output, setSampleBufferDelegate: bridge as *mut AnyObject, queue: queue as *mut AnyObject];
+
+            // Blocks until the device is running
+            let _: () = msg_send![session, startRunning];
+            let capture = Self { session, output, bridge, queue, encoder, context };
+            let running: bool = msg_send![session, isRunning];
+            if !running {
+                return Err(Error::new(Status::GenericFailure, "The device capture session failed to start"));
+            }
+
+            println!("📱 Recording device {} to {} ({}x{})", device_id, config.output_path, settings.width, settings.height);
+            Ok(capture)
+        }
+    }
+
+    /// Stop the session and finish the file; returns its path
+    pub fn stop(mut self) -> Result<String> {
+        self.stop_session();
+        let output_path = self.encoder.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Device encoder lock poisoned"))?
+            .finalize_encoding()?;
+        println!("✅ Device recording saved: {}", output_path);
+        Ok(output_path)
+    }
+
+    fn stop_session(&mut self) {
+        extern "C" fn drained(_context: *mut c_void) {}
+
+        if self.session.is_null() {
+            return;
+        }
+        unsafe {
+            let _: () = msg_send![self.session, stopRunning];
+            let _: () = msg_send![self.output, setSampleBufferDelegate: ptr::null_mut::<AnyObject>(), queue: ptr::null_mut::<AnyObject>()];
+            // A frame callback already queued still uses the context; wait it out
+            dispatch_sync_f(self.queue, ptr::null_mut(), drained);
+            let _: () = msg_send![self.output, release];
+            let _: () = msg_send![self.session, release];
+            release_camera_output_bridge(self.bridge);
+            dispatch_release(self.queue);
+            drop(Arc::from_raw(self.context));
+        }
+        self.session = ptr::null_mut();
+    }
+}
+
+impl Drop for DeviceCapture {
+    fn drop(&mut self) {
+        self.stop_session();
+        // Finishing twice is harmless; this only does work when stop wasn't called
+        if let Ok(mut encoder) = self.encoder.lock() {
+            if let Err(e) = encoder.finalize_encoding() {
+                log::warn!("Failed to finish the device recording: {}", e);
+            }
+        }
+    }
+}
+
+/// Size of the frames the device currently delivers, 0x0 when it doesn't say
+unsafe fn device_dimensions(device: *mut AnyObject) -> (u32, u32) {
+    let format: *mut AnyObject = msg_send![device, activeFormat];
+    if format.is_null() {
+        return (0, 0);
+    }
+    let description: *const c_void = msg_send![format, formatDescription];
+    if description.is_null() {
+        return (0, 0);
+    }
+    let dimensions = CMVideoFormatDescriptionGetDimensions(description);
+    (dimensions.width.max(0) as u32, dimensions.height.max(0) as u32)
+}
+
+/// Encode a device frame
+extern "C" fn device_frame_callback(context: *mut c_void, sample_buffer: *const CMSampleBuffer) {
+    if context.is_null() || sample_buffer.is_null() {
+        return;
+    }
+    let encoder = unsafe { &*(context as *const Mutex<VideoEncoder>) };
+    if let Ok(mut encoder) = encoder.lock() {
+        if let Err(e) = encoder.encode_frame(unsafe { &*sample_buffer }) {
+            log::warn!("Failed to encode device frame: {}", e);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Diagnostics report
+// Gathers system, permission, content and recording state into one JSON document for support tickets
+
+use serde_json::json;
+
+use super::types::RecordingStats;
+use super::content::AsyncContentManager;
+use super::bindings::ScreenCaptureKitAPI;
+use super::foundation::PermissionHelpers;
+use super::permission_manager::PermissionManager;
+use super::session::current_session;
+use super::spaces;
+use super::transcription::TranscriptionManager;
+
+/// Collects everything a support ticket needs in one report
+pub struct Diagnostics;
+
+impl Diagnostics {
+    /// Collect the diagnostics report
+    /// Never fails: anything that can't be determined is reported inside the JSON instead
+    pub async fn collect(is_recording: bool, last_recording: Option<RecordingStats>) -> serde_json::Value {
+        println!("🩺 Collecting diagnostics");
+
+        // Preflight only - collecting diagnostics must never trigger a permission prompt
+        let screen_recording = unsafe { PermissionHelpers::check_screen_recording_permission() };
+        let accessibility = PermissionManager::check_accessibility_permission();
+        let session = current_session();
+        let state = session.state();
+
+        let content = if screen_recording {
+            match AsyncContentManager::get_shareable_content().await {
+                Ok(content) => json!({
+                    "displays": content.get_displays().map(|displays| displays.len()).unwrap_or(0),
+                    "windows": content.get_windows().map(|windows| windows.len()).unwrap_or(0),
+                    "error": null,
+                }),
+                Err(e) => json!({ "displays": null, "windows": null, "error": e.reason }),
+            }
+        } else {
+            json!({ "displays": null, "windows": null, "error": "Screen recording permission not granted" })
+        };
+
+        // What a display capture shows is the Space current on that display
+        let spaces = match spaces::current_space_ids() {
+            Ok(ids) => json!({ "current": ids, "error": null }),
+            Err(e) => json!({ "current": null, "error": e.reason }),
+        };
+
+        let missing_dependencies = TranscriptionManager::new(TranscriptionManager::default_config())
+            .check_dependencies()
+            .await
+            .unwrap_or_default();
+        let has_dependency = |name: &str| !missing_dependencies.iter().any(|missing| missing.starts_with(name));
+
+        json!({
+            "crateVersion": env!("CARGO_PKG_VERSION"),
+            "system": {
+                "macosVersion": PermissionManager::get_macos_version(),
+                "arch": std::env::consts::ARCH,
+                "macosCompatible": PermissionManager::check_macos_version_compatibility(),
+            },
+            "permissions": {
+                "screenRecording": screen_recording,
+                "accessibility": accessibility,
+            },
+            "screenCaptureKit": {
+                "available": PermissionManager::check_screencapturekit_availability(),
+                "presenterOverlayPrivacyAlertSupported": unsafe { ScreenCaptureKitAPI::supports_presenter_overlay_privacy_alert() },
+            },
+            "session": {
+                "state": state.as_str(),
+                "captureBlocked": state.blocked_reason(),
+                "flags": session,
+            },
+            "content": content,
+            "spaces": spaces,
+            "dependencies": {
+                "ffmpeg": has_dependency("FFmpeg"),
+                "whisper": has_dependency("Whisper"),
+                "missing": missing_dependencies,
+            },
+            "recording": {
+                "isRecording": is_recording,
+                "lastRecording": last_recording,
+            },
+        })
+    }
+}
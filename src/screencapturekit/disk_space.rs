@@ -0,0 +1,166 @@
+// Disk space monitoring
+// Checks the output volume before recording and polls it while recording so a full disk
+// stops the recording cleanly instead of failing at finalize
+
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSString, NSURL, NSNumber, NSError};
+use napi::{Result, Status, Error};
+
+use super::foundation::describe_nserror;
+use super::types::{CapturePixelFormat, VideoCodec};
+
+pub const DEFAULT_LOW_DISK_SPACE_WARNING_MB: u32 = 2048;
+pub const DEFAULT_MIN_FREE_DISK_SPACE_MB: u32 = 512;
+pub const DEFAULT_EXPECTED_DURATION_SECONDS: u32 = 60;
+
+// How often the volume is polled while recording
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Granularity of the stop check inside a poll interval
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+// AAC at 128 kbps
+const AUDIO_BYTES_PER_SECOND: u64 = 16_000;
+
+extern "C" {
+    static NSURLVolumeAvailableCapacityForImportantUsageKey: *const NSString;
+    static NSURLVolumeAvailableCapacityKey: *const NSString;
+}
+
+/// Reported to the disk space callback
+#[derive(Debug, Clone)]
+pub struct DiskSpaceEvent {
+    pub available_bytes: u64,
+    /// True when space fell below the minimum and the recording is being stopped
+    pub exhausted: bool,
+}
+
+/// Free space on the volume that holds `output_path`
+pub fn available_bytes(output_path: &str) -> Result<u64> {
+    // The output file doesn't exist yet; ask about the directory it will be written to
+    let directory = Path::new(output_path).parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    unsafe {
+        let path = NSString::from_str(&directory.to_string_lossy());
+        let url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*path];
+
+        // Important-usage capacity counts purgeable space macOS will free for us; it is not
+        // reported by every filesystem (e.g. some external drives), so fall back to raw capacity
+        for key in [NSURLVolumeAvailableCapacityForImportantUsageKey, NSURLVolumeAvailableCapacityKey] {
+            let mut value: *mut AnyObject = ptr::null_mut();
+            let mut error: *mut NSError = ptr::null_mut();
+            let ok: bool = msg_send![url, getResourceValue: &mut value, forKey: key, error: &mut error];
+            if !ok {
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to read free space for {}: {}", directory.display(), describe_nserror(error)),
+                ));
+            }
+            if !value.is_null() {
+                let capacity: i64 = msg_send![value as *mut NSNumber, longLongValue];
+                return Ok(capacity.max(0) as u64);
+            }
+        }
+    }
+
+    Err(Error::new(
+        Status::GenericFailure,
+        format!("Free space is not reported for {}", directory.display()),
+    ))
+}
+
+/// Rough output size per second; screen content usually compresses far better than this
+pub fn estimated_bytes_per_second(
+    width: u32,
+    height: u32,
+    fps: u32,
+    codec: VideoCodec,
+    pixel_format: CapturePixelFormat,
+    capture_audio: bool,
+) -> u64 {
+    // Bits per pixel per frame the hardware encoders typically land on at default quality
+    let bits_per_pixel = match codec {
+        VideoCodec::H264 => 0.10,
+        VideoCodec::Hevc => 0.07,
+        // The alpha plane is coded as a second layer
+        VideoCodec::HevcWithAlpha => 0.10,
+    } * if pixel_format.is_ten_bit() { 1.25 } else { 1.0 };
+
+    let video = (width as f64 * height as f64 * fps as f64 * bits_per_pixel / 8.0) as u64;
+    video + if capture_audio { AUDIO_BYTES_PER_SECOND } else { 0 }
+}
+
+/// Polls the output volume on a background thread; dropping it stops the thread
+pub struct DiskSpaceMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DiskSpaceMonitor {
+    /// Start polling. `on_event` fires once each time space falls below `warning_bytes`
+    /// (re-armed when space recovers) and once, with `exhausted` set, when it falls below
+    /// `min_free_bytes`; monitoring ends after that
+    pub fn start<F>(output_path: &str, warning_bytes: u64, min_free_bytes: u64, on_event: F) -> Self
+    where
+        F: Fn(DiskSpaceEvent) + Send + 'static,
+    {
+        println!(
+            "💾 Monitoring free space for {} (warn below {} MB, stop below {} MB)",
+            output_path, warning_bytes / 1_048_576, min_free_bytes / 1_048_576
+        );
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let output_path = output_path.to_string();
+
+        let thread = std::thread::spawn(move || {
+            let mut warned = false;
+            while !thread_stop.load(Ordering::SeqCst) {
+                match available_bytes(&output_path) {
+                    Ok(available) if available < min_free_bytes => {
+                        println!("🛑 Free space down to {} MB; stopping recording", available / 1_048_576);
+                        on_event(DiskSpaceEvent { available_bytes: available, exhausted: true });
+                        return;
+                    }
+                    Ok(available) if available < warning_bytes => {
+                        if !warned {
+                            println!("⚠️ Low disk space: {} MB free", available / 1_048_576);
+                            on_event(DiskSpaceEvent { available_bytes: available, exhausted: false });
+                            warned = true;
+                        }
+                    }
+                    Ok(_) => warned = false,
+                    Err(e) => println!("⚠️ {}", e.reason),
+                }
+
+                let mut waited = Duration::ZERO;
+                while waited < POLL_INTERVAL && !thread_stop.load(Ordering::SeqCst) {
+                    std::thread::sleep(STOP_CHECK_INTERVAL);
+                    waited += STOP_CHECK_INTERVAL;
+                }
+            }
+        });
+
+        Self { stop, thread: Some(thread) }
+    }
+}
+
+impl Drop for DiskSpaceMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            // The exhausted callback may be what is dropping us; never join our own thread
+            if thread.thread().id() != std::thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
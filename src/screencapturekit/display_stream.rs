@@ -0,0 +1,145 @@
+// CGDisplayStream capture
+// The display capture API macOS had before ScreenCaptureKit, kept as a fallback for systems
+// where a ScreenCaptureKit stream starts but never delivers a frame. It captures whole
+// displays only and no audio. Each frame's IOSurface is wrapped in a sample buffer stamped
+// with the frame's host time, so the delegate records it like a ScreenCaptureKit frame
+
+use std::ffi::{c_char, c_void};
+use std::ptr;
+use std::sync::Arc;
+use block2::RcBlock;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSNumber, NSString};
+use objc2_core_media::CMTime;
+use objc2_core_video::CVPixelBuffer;
+use napi::{Result, Status, Error};
+
+use super::delegate::RealStreamDelegate;
+use super::region::wrap_pixel_buffer;
+use super::transcode::dictionary;
+
+// CGDisplayStreamFrameStatus
+const FRAME_STATUS_COMPLETE: i32 = 0;
+const FRAME_STATUS_STOPPED: i32 = 3;
+
+extern "C" {
+    static kCGDisplayStreamMinimumFrameTime: *const NSString;
+    static kCGDisplayStreamShowCursor: *const NSString;
+
+    fn CGDisplayStreamCreateWithDispatchQueue(
+        display: u32,
+        output_width: usize,
+        output_height: usize,
+        pixel_format: i32,
+        properties: *const c_void,
+        queue: *mut c_void,
+        handler: *const c_void,
+    ) -> *mut c_void;
+    fn CGDisplayStreamStart(stream: *mut c_void) -> i32;
+    fn CGDisplayStreamStop(stream: *mut c_void) -> i32;
+    fn CVPixelBufferCreateWithIOSurface(
+        allocator: *const c_void,
+        surface: *mut c_void,
+        pixel_buffer_attributes: *const c_void,
+        pixel_buffer_out: *mut *mut CVPixelBuffer,
+    ) -> i32;
+    fn CVPixelBufferRelease(pixel_buffer: *mut CVPixelBuffer);
+    fn CMClockMakeHostTimeFromSystemUnits(host_time: u64) -> CMTime;
+    fn CFRelease(cf: *const c_void);
+
+    fn dispatch_queue_create(label: *const c_char, attr: *const c_void) -> *mut c_void;
+    fn dispatch_sync_f(queue: *mut c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+    fn dispatch_release(object: *mut c_void);
+}
+
+/// A running CGDisplayStream feeding a delegate; stopped when dropped
+pub struct DisplayStream {
+    stream: *mut c_void,
+    queue: *mut c_void,
+}
+
+// Safety: the stream is only started and stopped, which CGDisplayStream allows from any thread
+unsafe impl Send for DisplayStream {}
+unsafe impl Sync for DisplayStream {}
+
+impl DisplayStream {
+    /// Capture `display_id` at `width` x `height` and up to `fps` in `pixel_format` into `delegate`
+    pub fn start(
+        display_id: u32,
+        width: u32,
+        height: u32,
+        fps: u32,
+        pixel_format: u32,
+        show_cursor: bool,
+        delegate: Arc<RealStreamDelegate>,
+    ) -> Result<Self> {
+        unsafe {
+            let minimum_frame_time: *mut NSNumber = msg_send![class!(NSNumber), numberWithDouble: 1.0 / fps.max(1) as f64];
+            let show_cursor: *mut NSNumber = msg_send![class!(NSNumber), numberWithBool: show_cursor];
+            let properties = dictionary(&[
+                (kCGDisplayStreamMinimumFrameTime, minimum_frame_time as *mut AnyObject),
+                (kCGDisplayStreamShowCursor, show_cursor as *mut AnyObject),
+            ]);
+
+            let handler = RcBlock::new(move |status: i32, display_time: u64, surface: *mut c_void, _update: *mut c_void| {
+                if status != FRAME_STATUS_COMPLETE || surface.is_null() {
+                    if status == FRAME_STATUS_STOPPED {
+                        println!("🖥️ CGDisplayStream stopped");
+                    }
+                    return;
+                }
+                let mut pixel_buffer: *mut CVPixelBuffer = ptr::null_mut();
+                if CVPixelBufferCreateWithIOSurface(ptr::null(), surface, ptr::null(), &mut pixel_buffer) != 0 || pixel_buffer.is_null() {
+                    return;
+                }
+                if let Some(sample_buffer) = wrap_pixel_buffer(pixel_buffer, CMClockMakeHostTimeFromSystemUnits(display_time)) {
+                    delegate.handle_video_sample_buffer(&*sample_buffer);
+                    CFRelease(sample_buffer as *const c_void);
+                }
+                CVPixelBufferRelease(pixel_buffer);
+            });
+
+            let queue = dispatch_queue_create(c"rustedscreencapture.displaystream".as_ptr(), ptr::null());
+            // The stream copies the handler block
+            let stream = CGDisplayStreamCreateWithDispatchQueue(
+                display_id,
+                width as usize,
+                height as usize,
+                pixel_format as i32,
+                properties as *const c_void,
+                queue,
+                &*handler as *const _ as *const c_void,
+            );
+            if stream.is_null() {
+                dispatch_release(queue);
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("CGDisplayStream can't capture display {} at {}x{}", display_id, width, height),
+                ));
+            }
+
+            let display_stream = Self { stream, queue };
+            let status = CGDisplayStreamStart(stream);
+            if status != 0 {
+                return Err(Error::new(Status::GenericFailure, format!("CGDisplayStream failed to start (CGError {})", status)));
+            }
+            println!("🖥️ Capturing display {} with CGDisplayStream ({}x{} @ {}fps)", display_id, width, height, fps);
+            Ok(display_stream)
+        }
+    }
+}
+
+impl Drop for DisplayStream {
+    fn drop(&mut self) {
+        extern "C" fn drained(_context: *mut c_void) {}
+
+        unsafe {
+            CGDisplayStreamStop(self.stream);
+            // A frame already queued still reaches the delegate; wait it out
+            dispatch_sync_f(self.queue, ptr::null_mut(), drained);
+            CFRelease(self.stream);
+            dispatch_release(self.queue);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// Timestamp drift correction
+// Constant-rate video is stamped from its frame count, so dropped frames or a capture rate
+// that doesn't hold the nominal one slowly move the video away from real time, and from the
+// audio. Each track's elapsed presentation time is compared with elapsed host time (paused
+// time excluded); past a threshold later timestamps are nudged back a bounded step at a time,
+// small enough that they keep increasing
+
+use objc2_core_media::CMTime;
+
+/// Drift tolerated before timestamps are nudged, in seconds; below what viewers notice
+pub const DRIFT_THRESHOLD_SECONDS: f64 = 0.040;
+/// Largest nudge applied to one audio buffer, in seconds
+pub const MAX_AUDIO_NUDGE_SECONDS: f64 = 0.005;
+
+/// A CMTime in seconds
+pub fn seconds(time: CMTime) -> f64 {
+    if time.timescale == 0 { 0.0 } else { time.value as f64 / time.timescale as f64 }
+}
+
+/// Tracks the drift of one track and the correction that keeps it within the threshold
+#[derive(Debug, Clone)]
+pub struct DriftTracker {
+    max_step: f64,
+    correction: f64,
+    max_drift: f64,
+}
+
+impl DriftTracker {
+    /// A tracker that changes the correction by at most `max_step` seconds per timestamp
+    pub fn new(max_step: f64) -> Self {
+        Self { max_step, correction: 0.0, max_drift: 0.0 }
+    }
+
+    /// Compare a timestamp `media_elapsed` seconds into the track with `wall_elapsed` seconds
+    /// of real time; returns the correction to add to it and the timestamps after it
+    pub fn observe(&mut self, media_elapsed: f64, wall_elapsed: f64) -> f64 {
+        let drift = media_elapsed + self.correction - wall_elapsed;
+        self.max_drift = self.max_drift.max(drift.abs());
+        if drift.abs() > DRIFT_THRESHOLD_SECONDS {
+            self.correction -= drift.clamp(-self.max_step, self.max_step);
+        }
+        self.correction
+    }
+
+    /// Largest drift seen before correction, in milliseconds
+    pub fn max_drift_ms(&self) -> f64 {
+        self.max_drift * 1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_is_pulled_back_within_the_threshold() {
+        // Video stamped at 30fps while frames really arrive every 35ms
+        let frame = 1.0 / 30.0;
+        let mut tracker = DriftTracker::new(frame / 2.0);
+        let mut last = f64::MIN;
+        for count in 0..600 {
+            let stamped = count as f64 * frame + tracker.observe(count as f64 * frame, count as f64 * 0.035);
+            assert!(stamped > last, "Timestamps keep increasing");
+            last = stamped;
+            let drift = stamped - count as f64 * 0.035;
+            assert!(drift.abs() <= DRIFT_THRESHOLD_SECONDS + frame, "Frame {} drifted {:.3}s", count, drift);
+        }
+        assert!(tracker.max_drift_ms() > DRIFT_THRESHOLD_SECONDS * 1000.0);
+
+        let mut steady = DriftTracker::new(MAX_AUDIO_NUDGE_SECONDS);
+        assert_eq!(steady.observe(1.0, 1.01), 0.0, "Drift within the threshold is left alone");
+    }
+}
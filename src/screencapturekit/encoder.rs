@@ -1,25 +1,283 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use std::ptr;
-use objc2::runtime::AnyObject;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use objc2::runtime::{AnyClass, AnyObject};
 use objc2::{msg_send, class};
-use objc2_foundation::{NSString, NSURL, NSError, NSDictionary, NSNumber};
+use objc2_foundation::{NSString, NSURL, NSError, NSArray, NSDictionary, NSMutableDictionary, NSNumber};
 use objc2_av_foundation::{AVAssetWriter, AVAssetWriterInput, AVAssetWriterInputPixelBufferAdaptor};
 use objc2_core_video::{CVPixelBuffer, kCVPixelFormatType_32BGRA};
-use objc2_core_media::{CMTime, CMSampleBuffer, kCMTimeZero};
+use objc2_core_media::{CMTime, CMSampleBuffer, CMSampleTimingInfo, kCMTimeZero, kCMTimeInvalid};
 use napi::{Result, Status, Error};
 
+use crate::RecordingConfiguration;
+use super::types::{EmptyRecordingBehavior, CapturePixelFormat, VideoCodec, ScalingMode, CaptureMode, OutputColorSpace};
+use super::foundation::describe_nserror;
+use super::drift::{self, DriftTracker, MAX_AUDIO_NUDGE_SECONDS};
+use super::fragment_output::{FragmentBridge, FragmentSink};
+use super::stream_output::{PresentationTimeGuard, TimestampDecision};
+
 // AVFoundation constants
 pub const AVFileTypeQuickTimeMovie: &str = "com.apple.quicktime-movie";
 pub const AVFileTypeMPEG4: &str = "public.mpeg-4";
 pub const AVMediaTypeVideo: &str = "vide";
 pub const AVMediaTypeAudio: &str = "soun";
+#[allow(non_upper_case_globals)]
+pub const AVFileTypeProfileMPEG4AppleHLS: &str = "MPEG4AppleHLS";
+
+// Length of the media segments a streamed recording is written in
+const FRAGMENT_INTERVAL: CMTime = CMTime {
+    value: 1,
+    timescale: 1,
+    flags: objc2_core_media::CMTimeFlags(1), // kCMTimeFlags_Valid
+    epoch: 0,
+};
 
 // Video codec constants
 pub const AVVideoCodecTypeH264: &str = "avc1";
 pub const AVVideoCodecTypeHEVC: &str = "hvc1";
+pub const AVVideoCodecTypeHEVCWithAlpha: &str = "muxa";
+
+// AVAssetWriterStatusFailed
+const AV_WRITER_STATUS_FAILED: isize = 3;
+
+// Video profile constants
+pub const AVVideoProfileLevelHEVCMain10AutoLevel: &str = "HEVC_Main10_AutoLevel";
+
+// Levels accepted after an H.264 Baseline, Main or High profile name
+const H264_LEVELS: [&str; 10] = ["3_0", "3_1", "3_2", "4_0", "4_1", "4_2", "5_0", "5_1", "5_2", "AutoLevel"];
+
+/// Codec a `profileLevel` value belongs to, or None when it isn't supported
+pub fn profile_level_codec(value: &str) -> Option<VideoCodec> {
+    match value {
+        "HEVC_Main_AutoLevel" | "HEVC_Main10_AutoLevel" => Some(VideoCodec::Hevc),
+        "H264_ConstrainedBaseline_AutoLevel" | "H264_ConstrainedHigh_AutoLevel" => Some(VideoCodec::H264),
+        _ => {
+            let (profile, level) = value.strip_prefix("H264_")?.split_once('_')?;
+            (matches!(profile, "Baseline" | "Main" | "High") && H264_LEVELS.contains(&level)).then_some(VideoCodec::H264)
+        }
+    }
+}
 
 // Audio codec constants
 pub const AVFormatIDKeyAAC: u32 = 0x61616320; // 'aac ' as u32
 
+// AVMetadataKeySpace constants
+pub const AVMetadataKeySpaceCommon: &str = "comn";
+pub const AVMetadataKeySpaceQuickTimeMetadata: &str = "mdta";
+
+// startWriting retries for transient failures (e.g. right after permission changes)
+pub const DEFAULT_WRITER_START_RETRIES: u32 = 2;
+pub const WRITER_START_RETRY_DELAY_MS: u64 = 100;
+
+// Timescale used for editing-friendly output so segments share a common timeline base
+pub const EDITING_FRIENDLY_TIMESCALE: i32 = 600;
+
+/// Video encoder settings derived from the recording configuration
+#[derive(Debug, Clone)]
+pub struct VideoEncoderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// Frame rate written to the file when it differs from the capture `fps`; frames are
+    /// dropped or repeated by timestamp to reach it (see `output_frames_due`)
+    pub output_fps: Option<u32>,
+    pub codec: VideoCodec,
+    /// Must match the stream's pixel format; 10-bit formats encode as HEVC Main10
+    pub pixel_format: CapturePixelFormat,
+    /// Closed GOPs, no frame reordering (B-frames) and a fixed 600 timescale so files
+    /// concatenate cleanly in editors. Costs compression efficiency: expect larger files
+    /// at the same visual quality
+    pub editing_friendly: bool,
+    pub empty_recording_behavior: EmptyRecordingBehavior,
+    /// Metadata tags written into the movie (see `create_metadata_items`)
+    pub metadata: HashMap<String, String>,
+    /// Instant replay mode: keep only the last N seconds in memory instead of writing a file
+    pub replay_buffer_seconds: Option<u32>,
+    /// How many times to recreate the writer when `startWriting` fails
+    pub writer_start_retries: u32,
+    /// How frames that don't match width/height are scaled by the writer
+    pub scaling_mode: ScalingMode,
+    /// OnChange keeps the real capture timestamps so each frame lasts until the next one
+    pub capture_mode: CaptureMode,
+    /// Colour primaries, transfer function and matrix the output is tagged with
+    pub color_space: OutputColorSpace,
+    /// Average video bitrate in kbps; the encoder picks one when unset
+    pub bitrate_kbps: Option<u32>,
+    /// Constant-quality target 0.0-1.0 instead of a bitrate; never set together with `bitrate_kbps`
+    pub quality: Option<f64>,
+    /// Pool hints for the pixel buffer adaptor; None keeps pixel-format-only attributes
+    pub pixel_buffer_pool: Option<PixelBufferPoolSettings>,
+    /// Where the first frame is timestamped, e.g. a previous segment's duration when resuming
+    pub timeline_offset: Option<CMTime>,
+    /// ProfileLevel compression property; the codec picks one when unset (Main10 for 10-bit)
+    pub profile_level: Option<String>,
+    /// Hold frames while the input isn't ready instead of dropping them (see `VideoEncoder::queue_frame`)
+    pub block_on_backpressure: bool,
+    /// Stream fragmented MPEG-4 here instead of writing the output file (see `fragment_output`)
+    pub fragment_sink: Option<Arc<FragmentSink>>,
+    /// Write system audio and the microphone as two audio tracks instead of one
+    pub separate_audio_tracks: bool,
+}
+
+/// Resolved `pixelBufferPool` configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelBufferPoolSettings {
+    pub min_buffer_count: Option<u32>,
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: CapturePixelFormat,
+}
+
+pub const MAX_PIXEL_BUFFER_POOL_SIZE: u32 = 64;
+
+/// AAC bitrate of recorded audio
+pub const AUDIO_BITRATE_KBPS: u32 = 128;
+/// Lowest video bitrate a targetFileSizeBytes budget may leave, in kbps
+pub const MIN_TARGET_SIZE_BITRATE_KBPS: u32 = 100;
+// Share of a file taken by the container (sample tables, headers) rather than media
+const CONTAINER_OVERHEAD: f64 = 0.02;
+
+/// Frames held for the video input with `block_on_backpressure` before capture waits for it
+pub const MAX_BACKPRESSURE_FRAMES: usize = 8;
+// Longest capture waits for a full backlog to shrink before the frame is dropped
+const BACKPRESSURE_WAIT: Duration = Duration::from_millis(500);
+// Longest finishing waits for the backlog to be written
+const BACKLOG_FINISH_WAIT: Duration = Duration::from_secs(5);
+// How often a waiting caller checks the input again
+const BACKPRESSURE_POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+impl Default for VideoEncoderSettings {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            fps: 30,
+            output_fps: None,
+            codec: VideoCodec::default(),
+            pixel_format: CapturePixelFormat::default(),
+            editing_friendly: false,
+            empty_recording_behavior: EmptyRecordingBehavior::default(),
+            metadata: HashMap::new(),
+            replay_buffer_seconds: None,
+            writer_start_retries: DEFAULT_WRITER_START_RETRIES,
+            scaling_mode: ScalingMode::default(),
+            capture_mode: CaptureMode::default(),
+            color_space: OutputColorSpace::default(),
+            bitrate_kbps: None,
+            quality: None,
+            pixel_buffer_pool: None,
+            timeline_offset: None,
+            profile_level: None,
+            block_on_backpressure: false,
+            fragment_sink: None,
+            separate_audio_tracks: false,
+        }
+    }
+}
+
+impl VideoEncoderSettings {
+    pub fn from_config(config: &RecordingConfiguration) -> Self {
+        let defaults = Self::default();
+        let width = config.width.unwrap_or(defaults.width);
+        let height = config.height.unwrap_or(defaults.height);
+        let pixel_format = config.pixel_format.as_deref()
+            .and_then(CapturePixelFormat::from_config_str)
+            .unwrap_or(defaults.pixel_format);
+        Self {
+            width,
+            height,
+            fps: config.fps.unwrap_or(defaults.fps),
+            output_fps: config.output_fps,
+            codec: config.video_codec.as_deref()
+                .and_then(VideoCodec::from_config_str)
+                .unwrap_or(defaults.codec),
+            pixel_format,
+            editing_friendly: config.editing_friendly.unwrap_or(defaults.editing_friendly),
+            empty_recording_behavior: config.empty_recording_behavior.as_deref()
+                .and_then(EmptyRecordingBehavior::from_config_str)
+                .unwrap_or(defaults.empty_recording_behavior),
+            metadata: config.metadata.clone().unwrap_or_default(),
+            replay_buffer_seconds: config.replay_buffer_seconds,
+            writer_start_retries: config.writer_start_retries.unwrap_or(defaults.writer_start_retries),
+            scaling_mode: config.scaling_mode.as_deref()
+                .and_then(ScalingMode::from_config_str)
+                .unwrap_or(defaults.scaling_mode),
+            capture_mode: config.capture_mode.as_deref()
+                .and_then(CaptureMode::from_config_str)
+                .unwrap_or(defaults.capture_mode),
+            color_space: config.color_space.as_deref()
+                .and_then(OutputColorSpace::from_config_str)
+                .unwrap_or(defaults.color_space),
+            bitrate_kbps: config.video_bitrate_kbps.or_else(|| target_size_bitrate_kbps(config)),
+            quality: config.quality,
+            pixel_buffer_pool: config.pixel_buffer_pool.as_ref().map(|pool| PixelBufferPoolSettings {
+                min_buffer_count: pool.min_buffer_count,
+                width: pool.width.unwrap_or(width),
+                height: pool.height.unwrap_or(height),
+                pixel_format: pool.pixel_format.as_deref()
+                    .and_then(CapturePixelFormat::from_config_str)
+                    .unwrap_or(pixel_format),
+            }),
+            timeline_offset: None,
+            profile_level: config.profile_level.clone(),
+            block_on_backpressure: config.block_on_backpressure.unwrap_or(defaults.block_on_backpressure),
+            fragment_sink: None,
+            separate_audio_tracks: config.separate_audio_tracks.unwrap_or(defaults.separate_audio_tracks),
+        }
+    }
+    
+    /// Frame rate of the written video
+    pub fn encoded_fps(&self) -> u32 {
+        self.output_fps.unwrap_or(self.fps).max(1)
+    }
+    
+    /// Whether any option requires AVVideoCompressionPropertiesKey
+    pub fn needs_compression_properties(&self) -> bool {
+        self.editing_friendly || self.pixel_format.is_ten_bit() || self.bitrate_kbps.is_some() || self.quality.is_some()
+            || self.profile_level.is_some()
+    }
+    
+    /// AVVideoCodecKey value for the configured codec
+    pub fn codec_type(&self) -> &'static str {
+        match self.codec {
+            VideoCodec::H264 => AVVideoCodecTypeH264,
+            VideoCodec::Hevc => AVVideoCodecTypeHEVC,
+            VideoCodec::HevcWithAlpha => AVVideoCodecTypeHEVCWithAlpha,
+        }
+    }
+    
+    /// AVAssetWriter file type; alpha video only fits in a QuickTime movie
+    pub fn file_type(&self) -> &'static str {
+        match self.codec {
+            VideoCodec::HevcWithAlpha => AVFileTypeQuickTimeMovie,
+            _ => AVFileTypeMPEG4,
+        }
+    }
+}
+
+/// Writer file type for the output extension; None when the container isn't supported
+pub fn file_type_for(output_path: &str) -> Option<&'static str> {
+    let extension = Path::new(output_path).extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "mp4" | "m4v" => Some(AVFileTypeMPEG4),
+        "mov" => Some(AVFileTypeQuickTimeMovie),
+        _ => None,
+    }
+}
+
+/// Where a recording meant for `output_path` is written. An .mp4, .m4v or .mov extension wins
+/// and the container follows it; any other extension (or none) is replaced with the one
+/// matching `default_file_type`, so the name never misrepresents the file
+pub fn container_output_path(output_path: &str, default_file_type: &str) -> String {
+    if file_type_for(output_path).is_some() {
+        return output_path.to_string();
+    }
+    let extension = if default_file_type == AVFileTypeQuickTimeMovie { "mov" } else { "mp4" };
+    Path::new(output_path).with_extension(extension).to_string_lossy().to_string()
+}
+
 pub struct VideoEncoder {
     asset_writer: *mut AVAssetWriter,
     video_input: *mut AVAssetWriterInput,
@@ -28,6 +286,29 @@ pub struct VideoEncoder {
     is_recording: bool,
     frame_count: u64,
     start_time: Option<CMTime>,
+    width: u32,
+    height: u32,
+    empty_recording_behavior: EmptyRecordingBehavior,
+    capture_mode: CaptureMode,
+    // Host time spent paused; change-driven capture subtracts it from the real timestamps
+    paused_duration: CMTime,
+    paused_at: Option<CMTime>,
+    timeline_offset: Option<CMTime>,
+    // Added to every capture timestamp so the first frame lands on `timeline_offset`
+    time_shift: Option<CMTime>,
+    fps: u32,
+    // Pick frames by capture timestamp to reach `fps` instead of encoding each one
+    convert_frame_rate: bool,
+    // Keeps frame-count timestamps in step with host time
+    drift: DriftTracker,
+    // The writer fails on a frame time that doesn't increase; change-driven capture passes
+    // the capture timestamps through as they come
+    timestamp_guard: PresentationTimeGuard,
+    block_on_backpressure: bool,
+    // Retained frames and their output times, waiting for the input to become ready
+    backlog: VecDeque<(*mut CVPixelBuffer, CMTime)>,
+    // The writer's delegate while fragments are streamed instead of written to `output_url`
+    fragment_bridge: Option<FragmentBridge>,
 }
 
 // Safety: Raw pointers are only used within unsafe blocks and the encoder
@@ -35,29 +316,33 @@ pub struct VideoEncoder {
 unsafe impl Send for VideoEncoder {}
 unsafe impl Sync for VideoEncoder {}
 
+impl Drop for VideoEncoder {
+    fn drop(&mut self) {
+        unsafe { self.discard_backlog() };
+    }
+}
+
 impl VideoEncoder {
-    pub fn new(output_path: &str, width: u32, height: u32, fps: u32) -> Result<Self> {
+    pub fn new(output_path: &str, settings: &VideoEncoderSettings) -> Result<Self> {
         unsafe {
-            // Create file URL
-            let url_string = NSString::from_str(output_path);
-            let file_url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*url_string];
-            
-            // Create AVAssetWriter
-            let mut error: *mut NSError = ptr::null_mut();
-            let file_type = NSString::from_str(AVFileTypeMPEG4);
-            let asset_writer: *mut AVAssetWriter = msg_send![
-                class!(AVAssetWriter),
-                assetWriterWithURL: file_url,
-                fileType: &*file_type,
-                error: &mut error
-            ];
+            // Create AVAssetWriter, for the file or for streamed fragments
+            let (asset_writer, fragment_bridge) = match settings.fragment_sink {
+                Some(ref sink) => {
+                    let (writer, bridge) = Self::create_fragmenting_writer(sink.clone())?;
+                    (writer, Some(bridge))
+                }
+                None => (Self::create_file_writer(output_path, settings)?, None),
+            };
             
-            if asset_writer.is_null() || !error.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to create AVAssetWriter"));
+            if settings.editing_friendly {
+                let _: () = msg_send![asset_writer, setMovieTimeScale: EDITING_FRIENDLY_TIMESCALE];
             }
             
+            // Metadata can only be set before writing starts
+            apply_metadata(asset_writer, &settings.metadata);
+            
             // Create video input settings
-            let video_settings = Self::create_video_settings(width, height, fps);
+            let video_settings = Self::create_video_settings(settings);
             let media_type = NSString::from_str(AVMediaTypeVideo);
             let video_input: *mut AVAssetWriterInput = msg_send![
                 class!(AVAssetWriterInput),
@@ -67,9 +352,15 @@ impl VideoEncoder {
             
             // Configure video input
             let _: () = msg_send![video_input, setExpectsMediaDataInRealTime: true];
+            if settings.editing_friendly {
+                let _: () = msg_send![video_input, setMediaTimeScale: EDITING_FRIENDLY_TIMESCALE];
+            }
             
             // Create pixel buffer adaptor
-            let source_pixel_buffer_attributes = Self::create_pixel_buffer_attributes();
+            let source_pixel_buffer_attributes = match settings.pixel_buffer_pool {
+                Some(pool) => Self::create_pooled_pixel_buffer_attributes(pool),
+                None => Self::create_pixel_buffer_attributes(settings.pixel_format),
+            };
             let pixel_buffer_adaptor: *mut AVAssetWriterInputPixelBufferAdaptor = msg_send![
                 class!(AVAssetWriterInputPixelBufferAdaptor),
                 assetWriterInputPixelBufferAdaptorWithAssetWriterInput: video_input,
@@ -87,7 +378,10 @@ impl VideoEncoder {
             // Start writing session
             let started: bool = msg_send![asset_writer, startWriting];
             if !started {
-                return Err(Error::new(Status::GenericFailure, "Failed to start writing"));
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to start writing: {}", asset_writer_error_description(asset_writer)),
+                ));
             }
             
             Ok(Self {
@@ -98,10 +392,107 @@ impl VideoEncoder {
                 is_recording: true,
                 frame_count: 0,
                 start_time: None,
+                width: settings.width,
+                height: settings.height,
+                empty_recording_behavior: settings.empty_recording_behavior,
+                capture_mode: settings.capture_mode,
+                paused_duration: kCMTimeZero,
+                paused_at: None,
+                // Fragments are timed from zero, where the first segment starts
+                timeline_offset: settings.timeline_offset.or(fragment_bridge.as_ref().map(|_| kCMTimeZero)),
+                time_shift: None,
+                fps: settings.encoded_fps(),
+                convert_frame_rate: settings.output_fps.is_some(),
+                // Half a frame per nudge keeps frame times increasing
+                drift: DriftTracker::new(0.5 / settings.encoded_fps() as f64),
+                timestamp_guard: PresentationTimeGuard::new(settings.encoded_fps()),
+                block_on_backpressure: settings.block_on_backpressure,
+                backlog: VecDeque::new(),
+                fragment_bridge,
             })
         }
     }
     
+    unsafe fn create_file_writer(output_path: &str, settings: &VideoEncoderSettings) -> Result<*mut AVAssetWriter> {
+        let url_string = NSString::from_str(output_path);
+        let file_url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*url_string];
+        let mut error: *mut NSError = ptr::null_mut();
+        let file_type = NSString::from_str(file_type_for(output_path).unwrap_or(settings.file_type()));
+        let asset_writer: *mut AVAssetWriter = msg_send![
+            class!(AVAssetWriter),
+            assetWriterWithURL: file_url,
+            fileType: &*file_type,
+            error: &mut error
+        ];
+        if asset_writer.is_null() || !error.is_null() {
+            return Err(Error::new(
+                Status::GenericFailure,
+                format!("Failed to create AVAssetWriter: {}", describe_nserror(error)),
+            ));
+        }
+        Ok(asset_writer)
+    }
+    
+    /// A writer without a file that hands fragmented MPEG-4 to `sink` about every
+    /// `FRAGMENT_INTERVAL`, starting at time zero
+    unsafe fn create_fragmenting_writer(sink: Arc<FragmentSink>) -> Result<(*mut AVAssetWriter, FragmentBridge)> {
+        let content_type_class = AnyClass::get(c"UTType")
+            .ok_or_else(|| Error::new(Status::GenericFailure, "UTType is unavailable; streaming output requires macOS 11 or later"))?;
+        let identifier = NSString::from_str(AVFileTypeMPEG4);
+        let content_type: *mut AnyObject = msg_send![content_type_class, typeWithIdentifier: &*identifier];
+        let asset_writer: *mut AVAssetWriter = msg_send![class!(AVAssetWriter), alloc];
+        let asset_writer: *mut AVAssetWriter = msg_send![asset_writer, initWithContentType: content_type];
+        if asset_writer.is_null() {
+            return Err(Error::new(Status::GenericFailure, "Failed to create a streaming AVAssetWriter"));
+        }
+        let profile = NSString::from_str(AVFileTypeProfileMPEG4AppleHLS);
+        let _: () = msg_send![asset_writer, setOutputFileTypeProfile: &*profile];
+        let _: () = msg_send![asset_writer, setPreferredOutputSegmentInterval: FRAGMENT_INTERVAL];
+        let _: () = msg_send![asset_writer, setInitialSegmentStartTime: kCMTimeZero];
+        let bridge = FragmentBridge::new(sink)?;
+        let _: () = msg_send![asset_writer, setDelegate: bridge.as_delegate()];
+        Ok((asset_writer, bridge))
+    }
+    
+    /// Note that frames stop arriving because the recording is paused
+    pub fn mark_paused(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(unsafe { host_time_now() });
+        }
+    }
+    
+    /// Close the current pause so later frames follow on from the last one
+    pub fn mark_resumed(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            unsafe {
+                let paused_for = CMTimeSubtract(host_time_now(), paused_at);
+                self.paused_duration = CMTimeAdd(self.paused_duration, paused_for);
+            }
+        }
+    }
+    
+    /// Largest drift of the frame timestamps from host time, in ms
+    pub fn max_drift_ms(&self) -> f64 {
+        self.drift.max_drift_ms()
+    }
+    
+    /// (nudged, dropped) counts of frames whose output time didn't increase
+    pub fn timestamp_fixes(&self) -> (u64, u64) {
+        self.timestamp_guard.stats()
+    }
+    
+    /// Capture time of the first frame, which started the writer session; None before it
+    pub fn session_start(&self) -> Option<CMTime> {
+        self.start_time
+    }
+    
+    /// Seconds into the recording of a frame captured at `presentation_time`, paused time
+    /// left out; None before the first frame
+    pub fn seconds_into_recording(&self, presentation_time: CMTime) -> Option<f64> {
+        let start = self.start_time?;
+        Some(drift::seconds(unsafe { CMTimeSubtract(CMTimeSubtract(presentation_time, start), self.paused_duration) }))
+    }
+    
     pub fn encode_pixel_buffer(&mut self, pixel_buffer: *mut CVPixelBuffer, presentation_time: CMTime) -> Result<()> {
         unsafe {
             if !self.is_recording {
@@ -110,48 +501,155 @@ impl VideoEncoder {
             
             // Set start time on first frame
             if self.start_time.is_none() {
-                let _: () = msg_send![self.asset_writer, startSessionAtSourceTime: presentation_time];
+                self.time_shift = self.timeline_offset.map(|offset| CMTimeSubtract(offset, presentation_time));
+                let _: () = msg_send![self.asset_writer, startSessionAtSourceTime: self.shifted(presentation_time)];
                 self.start_time = Some(presentation_time);
             }
             
-            // Check if input is ready for more media data
+            // Check if input is ready for more media data; live capture drops the frame
+            // rather than fall behind
             let ready: bool = msg_send![self.video_input, isReadyForMoreMediaData];
-            if !ready {
+            if !ready && !self.block_on_backpressure {
                 log::warn!("Video input not ready for more data");
                 return Ok(());
             }
             
-            // Calculate frame time based on frame count
-            let frame_time = if let Some(start) = self.start_time {
-                CMTime {
-                    value: start.value + (self.frame_count as i64 * start.timescale as i64 / 30), // Assuming 30fps
-                    timescale: start.timescale,
-                    flags: start.flags,
-                    epoch: start.epoch,
+            // With an output frame rate, a frame fills every output slot up to its capture
+            // time: none when it comes too soon after the last one, several after a gap
+            let copies = match self.start_time {
+                Some(start) if self.convert_frame_rate => {
+                    let elapsed = drift::seconds(CMTimeSubtract(CMTimeSubtract(presentation_time, start), self.paused_duration));
+                    output_frames_due(elapsed, self.fps, self.frame_count)
                 }
-            } else {
-                presentation_time
+                _ => 1,
             };
+            let skipped = output_slots_skipped(copies, self.fps);
+            self.frame_count += skipped;
             
-            // Append pixel buffer
-            let success: bool = msg_send![
-                self.pixel_buffer_adaptor,
-                appendPixelBuffer: pixel_buffer,
-                withPresentationTime: frame_time
-            ];
-            
-            if !success {
-                log::error!("Failed to append pixel buffer");
-                return Err(Error::new(Status::GenericFailure, "Failed to encode frame"));
+            for _ in skipped..copies {
+                // Calculate frame time based on frame count at the nominal rate, nudged toward
+                // the host time elapsed outside pauses; change-driven capture keeps the real
+                // timestamps so a frame is shown until the content next changes. Converted
+                // frames already follow host time and keep exact slot times
+                let frame_time = if self.capture_mode == CaptureMode::OnChange {
+                    CMTimeSubtract(presentation_time, self.paused_duration)
+                } else if let Some(start) = self.start_time {
+                    let nominal = self.frame_count as f64 / self.fps as f64;
+                    let correction = if self.convert_frame_rate {
+                        0.0
+                    } else {
+                        let elapsed = drift::seconds(CMTimeSubtract(CMTimeSubtract(presentation_time, start), self.paused_duration));
+                        self.drift.observe(nominal, elapsed)
+                    };
+                    CMTime {
+                        value: start.value + ((nominal + correction) * start.timescale as f64).round() as i64,
+                        ..start
+                    }
+                } else {
+                    presentation_time
+                };
+                
+                let frame_time = self.shifted(frame_time);
+                if self.block_on_backpressure {
+                    if !self.queue_frame(pixel_buffer, frame_time)? {
+                        return Ok(());
+                    }
+                } else {
+                    self.append(pixel_buffer, frame_time)?;
+                }
+                
+                self.frame_count += 1;
+                
+                if self.frame_count % 30 == 0 {
+                    log::debug!("Encoded {} video frames", self.frame_count);
+                }
             }
             
-            self.frame_count += 1;
-            
-            if self.frame_count % 30 == 0 {
-                log::debug!("Encoded {} video frames", self.frame_count);
+            Ok(())
+        }
+    }
+    
+    /// Append a frame to the pixel buffer adaptor at `frame_time` on the output timeline
+    unsafe fn append(&mut self, pixel_buffer: *mut CVPixelBuffer, frame_time: CMTime) -> Result<()> {
+        let frame_time = match self.timestamp_guard.check(frame_time) {
+            TimestampDecision::Accept(time) | TimestampDecision::Nudge(time) => time,
+            TimestampDecision::Drop => return Ok(()),
+        };
+        let success: bool = msg_send![
+            self.pixel_buffer_adaptor,
+            appendPixelBuffer: pixel_buffer,
+            withPresentationTime: frame_time
+        ];
+        
+        if !success {
+            log::error!("Failed to append pixel buffer");
+            return Err(Error::new(Status::GenericFailure, "Failed to encode frame"));
+        }
+        Ok(())
+    }
+    
+    /// Append a frame once the input is ready, keeping it in the backlog until then; false
+    /// when the frame was dropped after the backlog stayed full for `BACKPRESSURE_WAIT`
+    /// Held frames keep ScreenCaptureKit from reusing their buffers and a full backlog stalls
+    /// the capture queue, so a slow encoder adds latency and memory instead of losing frames,
+    /// until the stream starts skipping them itself
+    unsafe fn queue_frame(&mut self, pixel_buffer: *mut CVPixelBuffer, frame_time: CMTime) -> Result<bool> {
+        // Frames go out in order, so a new one waits behind any backlog
+        self.drain_backlog(0, Duration::ZERO)?;
+        if self.backlog.is_empty() {
+            let ready: bool = msg_send![self.video_input, isReadyForMoreMediaData];
+            if ready {
+                self.append(pixel_buffer, frame_time)?;
+                return Ok(true);
+            }
+        }
+        
+        if self.backlog.len() >= MAX_BACKPRESSURE_FRAMES {
+            self.drain_backlog(MAX_BACKPRESSURE_FRAMES - 1, BACKPRESSURE_WAIT)?;
+            if self.backlog.len() >= MAX_BACKPRESSURE_FRAMES {
+                log::warn!("Video input stayed busy for {}ms; dropping a frame", BACKPRESSURE_WAIT.as_millis());
+                return Ok(false);
+            }
+        }
+        
+        CVPixelBufferRetain(pixel_buffer);
+        self.backlog.push_back((pixel_buffer, frame_time));
+        Ok(true)
+    }
+    
+    /// Append held frames while the input takes them; waits up to `wait` for the backlog to
+    /// shrink to `target_len`, and with no wait stops at the first frame the input refuses
+    /// This polls isReadyForMoreMediaData rather than using requestMediaDataWhenReadyOnQueue:
+    /// that hands the input to a block on AVFoundation's queue for the rest of the session, so
+    /// every frame, not just held ones, would have to be appended from there. Waiting only
+    /// happens with a full backlog or while finishing, and is bounded, so a short sleep is enough
+    unsafe fn drain_backlog(&mut self, target_len: usize, wait: Duration) -> Result<()> {
+        let started = Instant::now();
+        while !self.backlog.is_empty() {
+            let ready: bool = msg_send![self.video_input, isReadyForMoreMediaData];
+            if !ready {
+                if self.backlog.len() <= target_len || started.elapsed() >= wait {
+                    break;
+                }
+                std::thread::sleep(BACKPRESSURE_POLL_INTERVAL);
+                continue;
             }
             
-            Ok(())
+            let Some((pixel_buffer, frame_time)) = self.backlog.pop_front() else { break };
+            let appended = self.append(pixel_buffer, frame_time);
+            CVPixelBufferRelease(pixel_buffer);
+            appended?;
+        }
+        Ok(())
+    }
+    
+    /// Release held frames that will never be written
+    unsafe fn discard_backlog(&mut self) {
+        if !self.backlog.is_empty() {
+            log::warn!("Discarding {} video frames the input never accepted", self.backlog.len());
+        }
+        for (pixel_buffer, _) in self.backlog.drain(..) {
+            CVPixelBufferRelease(pixel_buffer);
         }
     }
     
@@ -171,6 +669,10 @@ impl VideoEncoder {
             };
             
             if pixel_buffer.is_null() {
+                // Idle (unchanged) frames carry no pixels; expected when capturing on change
+                if self.capture_mode == CaptureMode::OnChange {
+                    return Ok(());
+                }
                 return Err(Error::new(Status::GenericFailure, "No pixel buffer in sample"));
             }
             
@@ -187,56 +689,244 @@ impl VideoEncoder {
         }
     }
     
+    /// The writer's NSError, described, once it has failed
+    pub fn writer_error(&self) -> Option<String> {
+        unsafe {
+            let error: *mut NSError = msg_send![self.asset_writer, error];
+            (!error.is_null()).then(|| describe_nserror(error))
+        }
+    }
+    
+    /// Abandon the file without finishing it; AVAssetWriter deletes what it wrote
+    pub fn cancel_encoding(&mut self) {
+        if self.is_recording {
+            self.is_recording = false;
+            unsafe {
+                self.discard_backlog();
+                let _: () = msg_send![self.asset_writer, cancelWriting];
+            }
+        }
+    }
+    
     pub fn finalize_encoding(&mut self) -> Result<String> {
         unsafe {
             if !self.is_recording {
                 return Ok(self.output_url.clone());
             }
             
+            // Hold the last frame until the moment recording stopped; without this an
+            // on-change recording of static content ends at its last change
+            let extend_last_frame = self.capture_mode == CaptureMode::OnChange && self.start_time.is_some();
+            
+            // The writer session only starts on the first frame, so finishing now would
+            // leave an unplayable file behind
+            if self.start_time.is_none() {
+                match self.empty_recording_behavior {
+                    EmptyRecordingBehavior::Error => {
+                        self.is_recording = false;
+                        let _: () = msg_send![self.asset_writer, cancelWriting];
+                        log::warn!("Recording stopped before any video frame was captured: {}", self.output_url);
+                        return Err(Error::new(
+                            Status::GenericFailure,
+                            "Recording too short: no video frames were captured",
+                        ));
+                    }
+                    EmptyRecordingBehavior::BlackFrame => {
+                        log::info!("No video frames captured, writing a single black frame");
+                        self.append_black_frame()?;
+                    }
+                }
+            }
+            
             self.is_recording = false;
             
+            // Frames held back by backpressure still belong in the file
+            let drained = self.drain_backlog(0, BACKLOG_FINISH_WAIT);
+            self.discard_backlog();
+            drained?;
+            
             // Mark input as finished
             let _: () = msg_send![self.video_input, markAsFinished];
             
+            if extend_last_frame {
+                // A recording stopped while paused ends where the pause began
+                let end_time = CMTimeSubtract(self.paused_at.unwrap_or_else(|| host_time_now()), self.paused_duration);
+                let _: () = msg_send![self.asset_writer, endSessionAtSourceTime: self.shifted(end_time)];
+            }
+            
             // Finish writing
             let _: () = msg_send![self.asset_writer, finishWriting];
+            let status: isize = msg_send![self.asset_writer, status];
+            if status == AV_WRITER_STATUS_FAILED {
+                log::error!("Failed to finish writing {}", self.output_url);
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to finish writing: {}", asset_writer_error_description(self.asset_writer)),
+                ));
+            }
+            
+            if let Some(ref bridge) = self.fragment_bridge {
+                let sink = bridge.sink();
+                if sink.failed() {
+                    log::warn!("The stream's reader went away; only {} bytes were streamed", sink.bytes_written());
+                } else {
+                    log::info!("Streamed {} bytes of fragmented MP4", sink.bytes_written());
+                }
+            }
             
             log::info!("Video encoding finalized: {} ({} frames)", self.output_url, self.frame_count);
             Ok(self.output_url.clone())
         }
     }
     
-    unsafe fn create_video_settings(width: u32, height: u32, _fps: u32) -> *mut NSDictionary<NSString, AnyObject> {
+    /// `time` moved onto the output timeline when continuing a previous segment
+    unsafe fn shifted(&self, time: CMTime) -> CMTime {
+        match self.time_shift {
+            Some(shift) => CMTimeAdd(time, shift),
+            None => time,
+        }
+    }
+    
+    /// Append one black frame at time zero so an empty recording still yields a valid movie
+    unsafe fn append_black_frame(&mut self) -> Result<()> {
+        let pixel_buffer = create_black_pixel_buffer(self.width, self.height)?;
+        let result = self.encode_pixel_buffer(pixel_buffer, kCMTimeZero);
+        CVPixelBufferRelease(pixel_buffer);
+        result
+    }
+    
+    pub(crate) unsafe fn create_video_settings(settings: &VideoEncoderSettings) -> *mut NSDictionary<NSString, AnyObject> {
         // Create video settings dictionary
-        let codec_key = NSString::from_str("AVVideoCodecKey");
-        let codec_value = NSString::from_str(AVVideoCodecTypeH264);
+        let video_settings: *mut NSMutableDictionary<NSString, AnyObject> = msg_send![class!(NSMutableDictionary), dictionary];
         
-        let width_key = NSString::from_str("AVVideoWidthKey");
-        let width_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: width];
+        let codec_value = NSString::from_str(settings.codec_type());
+        Self::set_setting(video_settings, "AVVideoCodecKey", &*codec_value as *const NSString as *mut AnyObject);
         
-        let height_key = NSString::from_str("AVVideoHeightKey");
-        let height_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: height];
+        let width_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: settings.width];
+        Self::set_setting(video_settings, "AVVideoWidthKey", width_value as *mut AnyObject);
         
-        // Create main video settings dictionary (no compression properties for avc1 compatibility)
-        let settings: *mut NSDictionary<NSString, AnyObject> = msg_send![
-            class!(NSDictionary),
-            dictionaryWithObjects: &[
-                &*codec_value as *const NSString as *mut AnyObject,
-                width_value as *mut AnyObject,
-                height_value as *mut AnyObject
-            ],
-            forKeys: &[&*codec_key, &*width_key, &*height_key],
-            count: 3
+        let height_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: settings.height];
+        Self::set_setting(video_settings, "AVVideoHeightKey", height_value as *mut AnyObject);
+        
+        // Only takes effect when captured frames differ from the output size
+        let scaling_mode = NSString::from_str(settings.scaling_mode.av_scaling_mode());
+        Self::set_setting(video_settings, "AVVideoScalingModeKey", &*scaling_mode as *const NSString as *mut AnyObject);
+        
+        // Tag the file explicitly so players don't guess; untagged HD video is read as BT.709
+        let color_properties = Self::create_color_properties(settings.color_space);
+        Self::set_setting(video_settings, "AVVideoColorPropertiesKey", color_properties as *mut AnyObject);
+        
+        // Compression properties are only added when an option needs them (avc1 compatibility)
+        let compression_properties = Self::create_compression_properties(settings);
+        if !compression_properties.is_null() {
+            Self::set_setting(video_settings, "AVVideoCompressionPropertiesKey", compression_properties as *mut AnyObject);
+        }
+        
+        video_settings as *mut NSDictionary<NSString, AnyObject>
+    }
+    
+    /// Build AVVideoCompressionPropertiesKey values, or null when no option requires them
+    unsafe fn create_compression_properties(settings: &VideoEncoderSettings) -> *mut NSMutableDictionary<NSString, AnyObject> {
+        if !settings.needs_compression_properties() {
+            return ptr::null_mut();
+        }
+        
+        let properties: *mut NSMutableDictionary<NSString, AnyObject> = msg_send![class!(NSMutableDictionary), dictionary];
+        
+        if settings.editing_friendly {
+            // Closed GOPs without B-frames, with a keyframe every second
+            let no: *mut NSNumber = msg_send![class!(NSNumber), numberWithBool: false];
+            Self::set_setting(properties, "AllowOpenGOP", no as *mut AnyObject);
+            Self::set_setting(properties, "AllowFrameReordering", no as *mut AnyObject);
+            
+            let key_frame_interval: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: settings.encoded_fps()];
+            Self::set_setting(properties, "MaxKeyFrameInterval", key_frame_interval as *mut AnyObject);
+        }
+        
+        if let Some(bitrate_kbps) = settings.bitrate_kbps {
+            let bitrate: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedLongLong: bitrate_kbps as u64 * 1000];
+            Self::set_setting(properties, "AverageBitRate", bitrate as *mut AnyObject);
+        } else if let Some(quality) = settings.quality {
+            // Quality-targeted encode: static screens cost far fewer bits than a fixed rate
+            let quality: *mut NSNumber = msg_send![class!(NSNumber), numberWithDouble: quality.clamp(0.0, 1.0)];
+            Self::set_setting(properties, "Quality", quality as *mut AnyObject);
+        }
+        
+        // Validation only lets 10-bit capture through with a Main10 profile
+        let profile_level = settings.profile_level.as_deref()
+            .or(settings.pixel_format.is_ten_bit().then_some(AVVideoProfileLevelHEVCMain10AutoLevel));
+        if let Some(profile_level) = profile_level {
+            let profile = NSString::from_str(profile_level);
+            Self::set_setting(properties, "ProfileLevel", &*profile as *const NSString as *mut AnyObject);
+        }
+        
+        properties
+    }
+    
+    /// Build AVVideoColorPropertiesKey values for the configured colour space
+    unsafe fn create_color_properties(color_space: OutputColorSpace) -> *mut NSMutableDictionary<NSString, AnyObject> {
+        let properties: *mut NSMutableDictionary<NSString, AnyObject> = msg_send![class!(NSMutableDictionary), dictionary];
+        
+        let mut transfer_function = color_space.transfer_function();
+        if !transfer_function_supported(transfer_function) {
+            // sRGB and linear transfer tags need macOS 15; BT.709 is the closest older tag
+            log::warn!("Transfer function {} not supported on this macOS, tagging as ITU_R_709_2", transfer_function);
+            transfer_function = "ITU_R_709_2";
+        }
+        
+        for (key, value) in [
+            ("ColorPrimaries", color_space.color_primaries()),
+            ("TransferFunction", transfer_function),
+            ("YCbCrMatrix", color_space.ycbcr_matrix()),
+        ] {
+            let value = NSString::from_str(value);
+            Self::set_setting(properties, key, &*value as *const NSString as *mut AnyObject);
+        }
+        
+        log::info!("Output tagged as {:?} ({} / {} / {})",
+            color_space, color_space.color_primaries(), transfer_function, color_space.ycbcr_matrix());
+        properties
+    }
+    
+    unsafe fn set_setting(dictionary: *mut NSMutableDictionary<NSString, AnyObject>, key: &str, value: *mut AnyObject) {
+        let key = NSString::from_str(key);
+        let _: () = msg_send![dictionary, setObject: value, forKey: &*key];
+    }
+    
+    /// Adaptor attributes sized for the output so its pool can preallocate matching buffers
+    unsafe fn create_pooled_pixel_buffer_attributes(pool: PixelBufferPoolSettings) -> *mut NSDictionary<NSString, AnyObject> {
+        extern "C" {
+            static kCVPixelBufferPixelFormatTypeKey: *const NSString;
+            static kCVPixelBufferWidthKey: *const NSString;
+            static kCVPixelBufferHeightKey: *const NSString;
+            static kCVPixelBufferPoolMinimumBufferCountKey: *const NSString;
+        }
+        
+        let attributes: *mut NSMutableDictionary<NSString, AnyObject> = msg_send![class!(NSMutableDictionary), dictionary];
+        let mut entries = vec![
+            (kCVPixelBufferPixelFormatTypeKey, pool.pixel_format.fourcc()),
+            (kCVPixelBufferWidthKey, pool.width),
+            (kCVPixelBufferHeightKey, pool.height),
         ];
+        if let Some(count) = pool.min_buffer_count {
+            entries.push((kCVPixelBufferPoolMinimumBufferCountKey, count));
+        }
+        for (key, value) in entries {
+            let value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: value];
+            let _: () = msg_send![attributes, setObject: value as *mut AnyObject, forKey: &*key];
+        }
         
-        settings
+        log::info!("Pixel buffer pool: {}x{} {:?}, minimum {} buffers",
+            pool.width, pool.height, pool.pixel_format,
+            pool.min_buffer_count.map(|count| count.to_string()).unwrap_or_else(|| "on demand".to_string()));
+        attributes as *mut NSDictionary<NSString, AnyObject>
     }
     
-    unsafe fn create_pixel_buffer_attributes() -> *mut NSDictionary<NSString, AnyObject> {
+    unsafe fn create_pixel_buffer_attributes(pixel_format: CapturePixelFormat) -> *mut NSDictionary<NSString, AnyObject> {
         let pixel_format_key = NSString::from_str("kCVPixelBufferPixelFormatTypeKey");
         let pixel_format_value: *mut NSNumber = msg_send![
             class!(NSNumber), 
-            numberWithUnsignedInt: kCVPixelFormatType_32BGRA
+            numberWithUnsignedInt: pixel_format.fourcc()
         ];
         
         let attributes: *mut NSDictionary<NSString, AnyObject> = msg_send![
@@ -250,12 +940,230 @@ impl VideoEncoder {
     }
 }
 
+/// Whether AVFoundation on this OS accepts `transfer_function` as an AVVideoTransferFunctionKey value
+fn transfer_function_supported(transfer_function: &str) -> bool {
+    extern "C" {
+        fn dlsym(handle: *mut std::ffi::c_void, symbol: *const std::ffi::c_char) -> *mut std::ffi::c_void;
+    }
+    // RTLD_DEFAULT on macOS
+    const RTLD_DEFAULT: *mut std::ffi::c_void = -2isize as *mut std::ffi::c_void;
+    
+    // Each accepted value has an exported AVVideoTransferFunction_* constant
+    let symbol = match transfer_function {
+        "ITU_R_709_2" => return true,
+        "IEC_sRGB" => c"AVVideoTransferFunction_IEC_sRGB",
+        "Linear" => c"AVVideoTransferFunction_Linear",
+        _ => return false,
+    };
+    unsafe { !dlsym(RTLD_DEFAULT, symbol.as_ptr()).is_null() }
+}
+
+extern "C" {
+    fn CMTimeAdd(lhs: CMTime, rhs: CMTime) -> CMTime;
+    fn CMTimeSubtract(lhs: CMTime, rhs: CMTime) -> CMTime;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMSampleBufferGetDuration(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMSampleBufferCreateCopyWithNewTiming(
+        allocator: *const std::ffi::c_void,
+        original: &CMSampleBuffer,
+        num_sample_timing_entries: isize,
+        sample_timing_array: *const CMSampleTimingInfo,
+        sample_buffer_out: *mut *mut CMSampleBuffer,
+    ) -> i32;
+    fn CFRelease(cf: *const std::ffi::c_void);
+}
+
+/// A retained copy of `sample_buffer` presented `shift` seconds later, or null when it can't be made
+unsafe fn retimed_sample_buffer(sample_buffer: &CMSampleBuffer, shift: f64) -> *mut CMSampleBuffer {
+    let presentation_time = CMSampleBufferGetPresentationTimeStamp(sample_buffer);
+    let timing = CMSampleTimingInfo {
+        duration: CMSampleBufferGetDuration(sample_buffer),
+        presentationTimeStamp: CMTime {
+            value: presentation_time.value + (shift * presentation_time.timescale as f64).round() as i64,
+            ..presentation_time
+        },
+        decodeTimeStamp: kCMTimeInvalid,
+    };
+    let mut retimed: *mut CMSampleBuffer = ptr::null_mut();
+    if CMSampleBufferCreateCopyWithNewTiming(ptr::null(), sample_buffer, 1, &timing, &mut retimed) != 0 {
+        return ptr::null_mut();
+    }
+    retimed
+}
+
+/// Current time on the host clock, the clock ScreenCaptureKit timestamps frames with
+pub unsafe fn host_time_now() -> CMTime {
+    extern "C" {
+        fn CMClockGetHostTimeClock() -> *mut std::ffi::c_void;
+        fn CMClockGetTime(clock: *mut std::ffi::c_void) -> CMTime;
+    }
+    CMClockGetTime(CMClockGetHostTimeClock())
+}
+
+/// Average video bitrate in kbps that fits `duration_seconds` of recording into `target_bytes`,
+/// leaving room for the container and, `with_audio`, the audio track
+pub fn bitrate_for_target_size(target_bytes: f64, duration_seconds: u32, with_audio: bool) -> u32 {
+    let media_kbps = target_bytes * 8.0 * (1.0 - CONTAINER_OVERHEAD) / duration_seconds.max(1) as f64 / 1000.0;
+    let audio_kbps = if with_audio { AUDIO_BITRATE_KBPS as f64 } else { 0.0 };
+    (media_kbps - audio_kbps).max(0.0).floor() as u32
+}
+
+/// The bitrate `targetFileSizeBytes` asks for over `maxDurationSeconds`, when both are set
+pub fn target_size_bitrate_kbps(config: &RecordingConfiguration) -> Option<u32> {
+    let target_bytes = config.target_file_size_bytes?;
+    let duration_seconds = config.max_duration_seconds?;
+    Some(bitrate_for_target_size(target_bytes, duration_seconds, config.capture_audio.unwrap_or(false)))
+}
+
+/// How many output frames a capture `elapsed` seconds into the recording fills at `output_fps`
+/// once `frames_written` are out: 0 drops it (60 -> 30 keeps every other frame), more than
+/// one repeats it (15 -> 30 writes each twice)
+pub fn output_frames_due(elapsed: f64, output_fps: u32, frames_written: u64) -> u64 {
+    // Slots up to and including the one `elapsed` falls in; the tolerance keeps a capture
+    // right on a slot boundary from landing a frame late
+    let slots = (elapsed.max(0.0) * output_fps as f64 + 1e-6).floor() as u64 + 1;
+    slots.saturating_sub(frames_written)
+}
+
+/// Of the `due` output slots a frame fills, how many to leave empty before writing it: after
+/// a gap of more than a second (a stall, or a screen that didn't change) only the last second
+/// is filled, and the previous frame stays on screen through the rest instead of thousands of
+/// copies of this one being encoded at once
+pub fn output_slots_skipped(due: u64, output_fps: u32) -> u64 {
+    due.saturating_sub(output_fps.max(1) as u64)
+}
+
+/// Describe an asset writer's NSError (domain, code and description) for error messages
+pub unsafe fn asset_writer_error_description(asset_writer: *mut AVAssetWriter) -> String {
+    let error: *mut NSError = msg_send![asset_writer, error];
+    describe_nserror(error)
+}
+
+/// Map a metadata key to its AVMetadataCommonKey, if it has one
+fn common_metadata_key(key: &str) -> Option<&'static str> {
+    match key {
+        "title" => Some("title"),
+        "artist" => Some("artist"),
+        "creator" => Some("creator"),
+        "comment" | "description" => Some("description"),
+        "copyright" => Some("copyright"),
+        _ => None,
+    }
+}
+
+/// Build AVMetadataItems for the given tags
+/// Common keys (title, artist, creator, comment, copyright) use the common key space so players
+/// recognise them; any other key is written as QuickTime metadata under its own name, which,
+/// unlike user data and its four-character codes, takes keys of any length
+pub unsafe fn create_metadata_items(metadata: &HashMap<String, String>) -> *mut NSArray<AnyObject> {
+    let mut items: Vec<*mut AnyObject> = Vec::with_capacity(metadata.len());
+    
+    for (key, value) in metadata {
+        let (key_space, key) = match common_metadata_key(key) {
+            Some(common_key) => (AVMetadataKeySpaceCommon, common_key),
+            None => (AVMetadataKeySpaceQuickTimeMetadata, key.as_str()),
+        };
+        
+        let item: *mut AnyObject = msg_send![class!(AVMutableMetadataItem), metadataItem];
+        if item.is_null() {
+            continue;
+        }
+        
+        let key_space = NSString::from_str(key_space);
+        let key = NSString::from_str(key);
+        let value = NSString::from_str(value);
+        let _: () = msg_send![item, setKeySpace: &*key_space];
+        let _: () = msg_send![item, setKey: &*key];
+        let _: () = msg_send![item, setValue: &*value];
+        
+        items.push(item);
+    }
+    
+    msg_send![class!(NSArray), arrayWithObjects: items.as_ptr(), count: items.len()]
+}
+
+/// Attach metadata tags to an asset writer; must be called before `startWriting`
+pub unsafe fn apply_metadata(asset_writer: *mut AVAssetWriter, metadata: &HashMap<String, String>) {
+    if metadata.is_empty() {
+        return;
+    }
+    
+    let items = create_metadata_items(metadata);
+    if !items.is_null() {
+        let _: () = msg_send![asset_writer, setMetadata: items];
+        log::debug!("Attached {} metadata tag(s) to asset writer", metadata.len());
+    }
+}
+
+// Core Video functions used to build placeholder frames
+extern "C" {
+    fn CVPixelBufferCreate(
+        allocator: *const std::ffi::c_void,
+        width: usize,
+        height: usize,
+        pixel_format_type: u32,
+        pixel_buffer_attributes: *const std::ffi::c_void,
+        pixel_buffer_out: *mut *mut CVPixelBuffer,
+    ) -> i32;
+    fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut CVPixelBuffer, lock_flags: u64) -> i32;
+    fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut CVPixelBuffer, unlock_flags: u64) -> i32;
+    fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut CVPixelBuffer) -> *mut std::ffi::c_void;
+    fn CVPixelBufferGetDataSize(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferRelease(pixel_buffer: *mut CVPixelBuffer);
+    fn CVPixelBufferRetain(pixel_buffer: *mut CVPixelBuffer) -> *mut CVPixelBuffer;
+}
+
+/// Create a BGRA pixel buffer filled with black
+/// The caller owns the returned buffer and must release it with `CVPixelBufferRelease`
+pub unsafe fn create_black_pixel_buffer(width: u32, height: u32) -> Result<*mut CVPixelBuffer> {
+    let mut pixel_buffer: *mut CVPixelBuffer = ptr::null_mut();
+    let status = CVPixelBufferCreate(
+        ptr::null(),
+        width as usize,
+        height as usize,
+        kCVPixelFormatType_32BGRA,
+        ptr::null(),
+        &mut pixel_buffer,
+    );
+    
+    if status != 0 || pixel_buffer.is_null() {
+        return Err(Error::new(Status::GenericFailure, format!("Failed to create black frame (CVReturn {})", status)));
+    }
+    
+    if CVPixelBufferLockBaseAddress(pixel_buffer, 0) == 0 {
+        let base_address = CVPixelBufferGetBaseAddress(pixel_buffer);
+        if !base_address.is_null() {
+            ptr::write_bytes(base_address as *mut u8, 0, CVPixelBufferGetDataSize(pixel_buffer));
+        }
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+    }
+    
+    Ok(pixel_buffer)
+}
+
+/// Release a pixel buffer created by `create_black_pixel_buffer`
+pub unsafe fn release_pixel_buffer(pixel_buffer: *mut CVPixelBuffer) {
+    CVPixelBufferRelease(pixel_buffer);
+}
+
 pub struct AudioEncoder {
     asset_writer: *mut AVAssetWriter,
-    audio_input: *mut AVAssetWriterInput,
+    // System audio first, then the microphone when it has a track of its own
+    tracks: Vec<AudioTrack>,
     output_url: String,
     is_recording: bool,
+    // Host time spent paused; left out of the audio so it stays in step with the video
+    paused_duration: CMTime,
+    paused_at: Option<CMTime>,
+}
+
+/// One audio input of the writer; each source runs on its own clock, so drift is tracked per track
+struct AudioTrack {
+    input: *mut AVAssetWriterInput,
     sample_count: u64,
+    // Capture timestamp and host time of the first buffer
+    first_sample: Option<(CMTime, CMTime)>,
+    drift: DriftTracker,
 }
 
 // Safety: Raw pointers are only used within unsafe blocks and the encoder
@@ -264,7 +1172,17 @@ unsafe impl Send for AudioEncoder {}
 unsafe impl Sync for AudioEncoder {}
 
 impl AudioEncoder {
+    /// A writer with one track, shared by system audio and the microphone
     pub fn new(output_path: &str, sample_rate: u32, channels: u32) -> Result<Self> {
+        Self::create(output_path, sample_rate, channels, 1)
+    }
+    
+    /// A writer with system audio and the microphone in separate tracks, in that order
+    pub fn with_microphone_track(output_path: &str, sample_rate: u32, channels: u32) -> Result<Self> {
+        Self::create(output_path, sample_rate, channels, 2)
+    }
+    
+    fn create(output_path: &str, sample_rate: u32, channels: u32, track_count: usize) -> Result<Self> {
         unsafe {
             // Create file URL
             let url_string = NSString::from_str(output_path);
@@ -281,27 +1199,35 @@ impl AudioEncoder {
             ];
             
             if asset_writer.is_null() || !error.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to create audio AVAssetWriter"));
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to create audio AVAssetWriter: {}", describe_nserror(error)),
+                ));
             }
             
-            // Create audio input settings
+            // Create one audio input per track
             let audio_settings = Self::create_audio_settings(sample_rate, channels);
             let media_type = NSString::from_str(AVMediaTypeAudio);
-            let audio_input: *mut AVAssetWriterInput = msg_send![
-                class!(AVAssetWriterInput),
-                assetWriterInputWithMediaType: &*media_type,
-                outputSettings: audio_settings
-            ];
-            
-            // Configure audio input
-            let _: () = msg_send![audio_input, setExpectsMediaDataInRealTime: true];
-            
-            // Add input to writer
-            let can_add: bool = msg_send![asset_writer, canAddInput: audio_input];
-            if can_add {
+            let mut tracks = Vec::with_capacity(track_count);
+            for _ in 0..track_count {
+                let audio_input: *mut AVAssetWriterInput = msg_send![
+                    class!(AVAssetWriterInput),
+                    assetWriterInputWithMediaType: &*media_type,
+                    outputSettings: audio_settings
+                ];
+                let _: () = msg_send![audio_input, setExpectsMediaDataInRealTime: true];
+                
+                let can_add: bool = msg_send![asset_writer, canAddInput: audio_input];
+                if !can_add {
+                    return Err(Error::new(Status::GenericFailure, "Cannot add audio input"));
+                }
                 let _: () = msg_send![asset_writer, addInput: audio_input];
-            } else {
-                return Err(Error::new(Status::GenericFailure, "Cannot add audio input"));
+                tracks.push(AudioTrack {
+                    input: audio_input,
+                    sample_count: 0,
+                    first_sample: None,
+                    drift: DriftTracker::new(MAX_AUDIO_NUDGE_SECONDS),
+                });
             }
             
             // Start writing session
@@ -314,45 +1240,110 @@ impl AudioEncoder {
             
             Ok(Self {
                 asset_writer,
-                audio_input,
+                tracks,
                 output_url: output_path.to_string(),
                 is_recording: true,
-                sample_count: 0,
+                paused_duration: kCMTimeZero,
+                paused_at: None,
             })
         }
     }
     
+    /// Whether the microphone is written to a track of its own
+    pub fn has_microphone_track(&self) -> bool {
+        self.tracks.len() > 1
+    }
+    
+    /// Append system audio
     pub fn encode_audio_buffer(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
+        self.append(0, sample_buffer)
+    }
+    
+    /// Append microphone audio, to its own track when there is one
+    pub fn encode_microphone_buffer(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
+        self.append(self.tracks.len() - 1, sample_buffer)
+    }
+    
+    fn append(&mut self, track: usize, sample_buffer: &CMSampleBuffer) -> Result<()> {
         unsafe {
             if !self.is_recording {
                 return Ok(());
             }
+            let input = self.tracks[track].input;
             
             // Check if input is ready for more media data
-            let ready: bool = msg_send![self.audio_input, isReadyForMoreMediaData];
+            let ready: bool = msg_send![input, isReadyForMoreMediaData];
             if !ready {
                 log::warn!("Audio input not ready for more data");
                 return Ok(());
             }
             
-            // Append sample buffer
-            let success: bool = msg_send![self.audio_input, appendSampleBuffer: sample_buffer];
+            // Append sample buffer, moved past pauses and nudged toward host time
+            let shift = self.timestamp_shift(track, sample_buffer);
+            let retimed = if shift == 0.0 { ptr::null_mut() } else { retimed_sample_buffer(sample_buffer, shift) };
+            let success: bool = if retimed.is_null() {
+                msg_send![input, appendSampleBuffer: sample_buffer]
+            } else {
+                let success: bool = msg_send![input, appendSampleBuffer: retimed];
+                CFRelease(retimed as *const std::ffi::c_void);
+                success
+            };
             
             if !success {
                 log::error!("Failed to append audio sample buffer");
                 return Err(Error::new(Status::GenericFailure, "Failed to encode audio"));
             }
             
-            self.sample_count += 1;
+            let track = &mut self.tracks[track];
+            track.sample_count += 1;
             
-            if self.sample_count % 100 == 0 {
-                log::debug!("Encoded {} audio samples", self.sample_count);
+            if track.sample_count % 100 == 0 {
+                log::debug!("Encoded {} audio samples", track.sample_count);
             }
             
             Ok(())
         }
     }
     
+    /// Seconds to move a buffer of `track` by: back over completed pauses, plus the drift correction
+    unsafe fn timestamp_shift(&mut self, track: usize, sample_buffer: &CMSampleBuffer) -> f64 {
+        let paused_duration = self.paused_duration;
+        let track = &mut self.tracks[track];
+        let presentation_time = CMSampleBufferGetPresentationTimeStamp(sample_buffer);
+        let arrived = host_time_now();
+        let (first_time, first_arrival) = *track.first_sample.get_or_insert((presentation_time, arrived));
+        let elapsed = drift::seconds(CMTimeSubtract(CMTimeSubtract(presentation_time, first_time), paused_duration));
+        let wall_elapsed = drift::seconds(CMTimeSubtract(CMTimeSubtract(arrived, first_arrival), paused_duration));
+        track.drift.observe(elapsed, wall_elapsed) - drift::seconds(paused_duration)
+    }
+    
+    /// Note that buffers stop arriving because the recording is paused
+    pub fn mark_paused(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(unsafe { host_time_now() });
+        }
+    }
+    
+    /// Close the current pause so later buffers follow on from the last one
+    pub fn mark_resumed(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            unsafe {
+                let paused_for = CMTimeSubtract(host_time_now(), paused_at);
+                self.paused_duration = CMTimeAdd(self.paused_duration, paused_for);
+            }
+        }
+    }
+    
+    /// Largest drift of the audio timestamps from host time, in ms, over all tracks
+    pub fn max_drift_ms(&self) -> f64 {
+        self.tracks.iter().map(|track| track.drift.max_drift_ms()).fold(0.0, f64::max)
+    }
+    
+    /// Capture time of the first buffer appended to the system audio track
+    pub fn first_sample_time(&self) -> Option<CMTime> {
+        self.tracks[0].first_sample.map(|(presentation_time, _)| presentation_time)
+    }
+    
     /// Encode frame from sample buffer (used by delegate) - alias for encode_audio_buffer
     pub fn encode_frame(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
         self.encode_audio_buffer(sample_buffer)
@@ -366,18 +1357,21 @@ impl AudioEncoder {
             
             self.is_recording = false;
             
-            // Mark input as finished
-            let _: () = msg_send![self.audio_input, markAsFinished];
+            // Mark inputs as finished
+            for track in &self.tracks {
+                let _: () = msg_send![track.input, markAsFinished];
+            }
             
             // Finish writing
             let _: () = msg_send![self.asset_writer, finishWriting];
             
-            log::info!("Audio encoding finalized: {} ({} samples)", self.output_url, self.sample_count);
+            let samples: Vec<u64> = self.tracks.iter().map(|track| track.sample_count).collect();
+            log::info!("Audio encoding finalized: {} ({:?} samples per track)", self.output_url, samples);
             Ok(self.output_url.clone())
         }
     }
     
-    unsafe fn create_audio_settings(sample_rate: u32, channels: u32) -> *mut NSDictionary<NSString, AnyObject> {
+    pub(crate) unsafe fn create_audio_settings(sample_rate: u32, channels: u32) -> *mut NSDictionary<NSString, AnyObject> {
         let format_key = NSString::from_str("AVFormatIDKey");
         let format_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: AVFormatIDKeyAAC];
         
@@ -388,7 +1382,7 @@ impl AudioEncoder {
         let channels_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: channels];
         
         let bitrate_key = NSString::from_str("AVEncoderBitRateKey");
-        let bitrate_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: 128000u32]; // 128 kbps
+        let bitrate_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: AUDIO_BITRATE_KBPS * 1000];
         
         let settings: *mut NSDictionary<NSString, AnyObject> = msg_send![
             class!(NSDictionary),
@@ -404,4 +1398,219 @@ impl AudioEncoder {
         
         settings
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_metadata_round_trip() {
+        let output_path = std::env::temp_dir().join("screencapturekit_metadata_test.mp4");
+        let output_path = output_path.to_string_lossy().to_string();
+        let _ = std::fs::remove_file(&output_path);
+        
+        let mut metadata = HashMap::new();
+        metadata.insert("title".to_string(), "Metadata round trip".to_string());
+        metadata.insert("artist".to_string(), "rustedscreencapture".to_string());
+        metadata.insert("com.example.session".to_string(), "42".to_string());
+        
+        let settings = VideoEncoderSettings {
+            width: 320,
+            height: 240,
+            empty_recording_behavior: EmptyRecordingBehavior::BlackFrame,
+            metadata,
+            ..VideoEncoderSettings::default()
+        };
+        
+        let mut encoder = VideoEncoder::new(&output_path, &settings).expect("Encoder creation should succeed");
+        encoder.finalize_encoding().expect("Finalizing a black frame recording should succeed");
+        
+        unsafe {
+            let url_string = NSString::from_str(&output_path);
+            let file_url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*url_string];
+            let asset: *mut AnyObject = msg_send![class!(AVURLAsset), URLAssetWithURL: file_url, options: ptr::null_mut::<AnyObject>()];
+            assert!(!asset.is_null(), "Recorded file should open as an AVAsset");
+            
+            let items: *mut NSArray<AnyObject> = msg_send![asset, commonMetadata];
+            let count: usize = msg_send![items, count];
+            
+            let mut found: HashMap<String, String> = HashMap::new();
+            for i in 0..count {
+                let item: *mut AnyObject = msg_send![items, objectAtIndex: i];
+                let common_key: *mut NSString = msg_send![item, commonKey];
+                let value: *mut NSString = msg_send![item, stringValue];
+                if !common_key.is_null() && !value.is_null() {
+                    found.insert((*common_key).to_string(), (*value).to_string());
+                }
+            }
+            
+            assert_eq!(found.get("title").map(String::as_str), Some("Metadata round trip"));
+            assert_eq!(found.get("artist").map(String::as_str), Some("rustedscreencapture"));
+            
+            // Keys without a common one come back under their own name
+            let format = NSString::from_str("com.apple.quicktime.mdta"); // AVMetadataFormatQuickTimeMetadata
+            let items: *mut NSArray<AnyObject> = msg_send![asset, metadataForFormat: &*format];
+            let count: usize = msg_send![items, count];
+            let custom = (0..count).find_map(|i| {
+                let item: *mut AnyObject = msg_send![items, objectAtIndex: i];
+                let key: *mut NSString = msg_send![item, key];
+                let value: *mut NSString = msg_send![item, stringValue];
+                (!key.is_null() && !value.is_null() && (*key).to_string() == "com.example.session").then(|| (*value).to_string())
+            });
+            assert_eq!(custom.as_deref(), Some("42"));
+        }
+        
+        let _ = std::fs::remove_file(&output_path);
+    }
+    
+    #[test]
+    fn test_on_change_frame_times_keep_increasing() {
+        use super::super::test_frames::FrameGenerator;
+        let output_path = std::env::temp_dir().join("screencapturekit_on_change_times_test.mp4").to_string_lossy().to_string();
+        let _ = std::fs::remove_file(&output_path);
+        let settings = VideoEncoderSettings { width: 160, height: 120, capture_mode: CaptureMode::OnChange, ..VideoEncoderSettings::default() };
+        let mut encoder = VideoEncoder::new(&output_path, &settings).expect("Encoder creation should succeed");
+        
+        let mut frames = FrameGenerator::new(160, 120, 30);
+        let samples: Vec<_> = (0..4).map(|_| frames.next_sample()).collect();
+        // A repeated timestamp, then one from before it, as a stalled stream can deliver
+        for index in [0, 1, 1, 0, 3] {
+            encoder.encode_frame(samples[index].sample()).expect("Bad timestamps don't fail the writer");
+        }
+        assert_eq!(encoder.timestamp_fixes(), (1, 1), "The repeat is nudged and the earlier frame dropped");
+        encoder.finalize_encoding().expect("The recording finalizes");
+        let _ = std::fs::remove_file(&output_path);
+    }
+    
+    #[test]
+    fn test_sixty_fps_capture_is_decimated_to_thirty() {
+        let mut written = 0;
+        let mut kept = Vec::new();
+        for frame in 0..60u64 {
+            let due = output_frames_due(frame as f64 / 60.0, 30, written);
+            assert!(due <= 1, "Decimation never repeats a frame");
+            if due == 1 {
+                kept.push(frame);
+            }
+            written += due;
+        }
+        assert_eq!(written, 30);
+        assert_eq!(kept, (0..60).step_by(2).collect::<Vec<_>>(), "Every other frame is kept");
+        
+        assert_eq!(output_frames_due(0.0, 30, 0), 1, "The first frame is always written");
+        assert_eq!(output_frames_due(1.0 / 15.0, 30, 1), 2, "15 -> 30 repeats each frame");
+        assert_eq!(output_frames_due(0.5, 30, 20), 0, "Frames ahead of the clock wait");
+    }
+    
+    #[test]
+    fn test_long_gaps_repeat_at_most_a_second() {
+        assert_eq!(output_slots_skipped(2, 30), 0, "Normal repeats are all written");
+        assert_eq!(output_slots_skipped(30, 30), 0);
+        
+        let due = output_frames_due(600.0, 30, 1);
+        assert_eq!(due, 18_000, "Ten minutes without frames leaves 18000 slots");
+        let skipped = output_slots_skipped(due, 30);
+        assert_eq!(due - skipped, 30, "Only the last second of them is filled");
+    }
+    
+    #[test]
+    fn test_target_file_size_sets_the_bitrate() {
+        // 500 MB over 20 minutes: ~3267 kbps of media, 128 of it audio
+        assert_eq!(bitrate_for_target_size(500_000_000.0, 1200, true), 3138);
+        assert_eq!(bitrate_for_target_size(500_000_000.0, 1200, false), 3266);
+        assert_eq!(bitrate_for_target_size(1_000_000.0, 3600, true), 0, "Audio alone overflows a tiny budget");
+        
+        let config = RecordingConfiguration {
+            output_path: "budget.mp4".to_string(),
+            target_file_size_bytes: Some(500_000_000.0),
+            max_duration_seconds: Some(1200),
+            ..Default::default()
+        };
+        assert_eq!(VideoEncoderSettings::from_config(&config).bitrate_kbps, Some(3266));
+        let explicit = RecordingConfiguration { video_bitrate_kbps: Some(2000), ..config };
+        assert_eq!(VideoEncoderSettings::from_config(&explicit).bitrate_kbps, Some(2000));
+    }
+    
+    #[test]
+    fn test_output_extension_picks_the_container() {
+        assert_eq!(file_type_for("/tmp/take.MP4"), Some(AVFileTypeMPEG4));
+        assert_eq!(file_type_for("/tmp/take.mov"), Some(AVFileTypeQuickTimeMovie));
+        assert_eq!(container_output_path("/tmp/take.mov", AVFileTypeMPEG4), "/tmp/take.mov", "The extension wins");
+        assert_eq!(container_output_path("/tmp/take.avi", AVFileTypeMPEG4), "/tmp/take.mp4");
+        assert_eq!(container_output_path("/tmp/take", AVFileTypeQuickTimeMovie), "/tmp/take.mov");
+    }
+    
+    #[test]
+    fn test_pixel_buffer_pool_defaults_to_the_output() {
+        let config = RecordingConfiguration {
+            output_path: "pool.mp4".to_string(),
+            width: Some(3840),
+            height: Some(2160),
+            pixel_buffer_pool: Some(crate::PixelBufferPoolConfiguration { min_buffer_count: Some(8), ..Default::default() }),
+            ..Default::default()
+        };
+        let settings = VideoEncoderSettings::from_config(&config);
+        assert_eq!(settings.pixel_buffer_pool, Some(PixelBufferPoolSettings {
+            min_buffer_count: Some(8),
+            width: 3840,
+            height: 2160,
+            pixel_format: CapturePixelFormat::Bgra,
+        }));
+        
+        let output_path = std::env::temp_dir().join("screencapturekit_pool_test.mp4").to_string_lossy().to_string();
+        let _ = std::fs::remove_file(&output_path);
+        let settings = VideoEncoderSettings {
+            width: 320,
+            height: 240,
+            empty_recording_behavior: EmptyRecordingBehavior::BlackFrame,
+            pixel_buffer_pool: Some(PixelBufferPoolSettings { min_buffer_count: Some(4), width: 320, height: 240, pixel_format: CapturePixelFormat::Bgra }),
+            ..VideoEncoderSettings::default()
+        };
+        let mut encoder = VideoEncoder::new(&output_path, &settings).expect("Encoder with pool hints starts");
+        encoder.finalize_encoding().expect("Finalizing a black frame recording should succeed");
+        let _ = std::fs::remove_file(&output_path);
+    }    
+    #[test]
+    fn test_microphone_gets_its_own_track_when_asked() {
+        use super::super::segments::open_asset;
+        use super::super::test_frames::ToneGenerator;
+        let audio_tracks = |separate: bool| {
+            let output_path = std::env::temp_dir()
+                .join(format!("screencapturekit_audio_tracks_{}.m4a", separate))
+                .to_string_lossy()
+                .to_string();
+            let _ = std::fs::remove_file(&output_path);
+            let mut encoder = if separate {
+                AudioEncoder::with_microphone_track(&output_path, 48000, 2)
+            } else {
+                AudioEncoder::new(&output_path, 48000, 2)
+            }
+            .expect("Audio encoder starts");
+            
+            // A second of system audio, then a second of microphone
+            let mut system = ToneGenerator::new(48000, 2, 440.0, 0.0);
+            let mut microphone = ToneGenerator::new(48000, 2, 220.0, 1.0);
+            for _ in 0..47 {
+                encoder.encode_frame(system.next_sample().sample()).expect("System audio is written");
+            }
+            for _ in 0..47 {
+                encoder.encode_microphone_buffer(microphone.next_sample().sample()).expect("Microphone audio is written");
+            }
+            encoder.finalize_encoding().expect("Audio finalizes");
+            
+            let count = unsafe {
+                let asset = open_asset(&output_path);
+                assert!(!asset.is_null(), "The audio file opens");
+                let media_type = NSString::from_str(AVMediaTypeAudio);
+                let tracks: *mut NSArray<AnyObject> = msg_send![asset, tracksWithMediaType: &*media_type];
+                let count: usize = msg_send![tracks, count];
+                count
+            };
+            let _ = std::fs::remove_file(&output_path);
+            count
+        };
+        assert_eq!(audio_tracks(true), 2, "System audio and the microphone in separate tracks");
+        assert_eq!(audio_tracks(false), 1, "Both sources share one track");
+    }
+}
@@ -0,0 +1,132 @@
+// macOS feature gates
+// Optional features arrived in different macOS releases. Which of them the running system
+// has is decided once, from its version, and every code path for such a feature asks here
+// first: an unavailable feature fails with "... requires macOS X or later" instead of sending
+// a selector the OS doesn't know. getCapabilities reports the same map
+
+use std::sync::OnceLock;
+use napi::{Result, Status, Error};
+use objc2_foundation::NSProcessInfo;
+
+use crate::Features;
+
+/// A feature not every supported macOS has
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    /// SCStreamOutputTypeMicrophone, selected with audioDeviceId
+    MicrophoneCapture,
+    /// Excluding an application's audio while keeping its windows on screen
+    AppAudioExclusion,
+    /// SCContentSharingPicker
+    SystemPicker,
+    /// The windows and displays of a picked filter
+    PickerSourceIds,
+    /// SCStreamConfiguration.presenterOverlayPrivacyAlertSetting
+    PresenterOverlayPrivacyAlert,
+    /// Core Audio process taps, used for audioOutputDeviceId
+    OutputDeviceTap,
+    /// SCWindow.isActive
+    WindowActiveState,
+}
+
+impl Feature {
+    /// The first macOS release with the feature, as (major, minor)
+    pub fn min_version(self) -> (u32, u32) {
+        match self {
+            Feature::MicrophoneCapture => (15, 0),
+            Feature::AppAudioExclusion => (15, 0),
+            Feature::SystemPicker => (14, 0),
+            Feature::PickerSourceIds => (15, 2),
+            Feature::PresenterOverlayPrivacyAlert => (14, 2),
+            Feature::OutputDeviceTap => (14, 2),
+            Feature::WindowActiveState => (13, 1),
+        }
+    }
+
+    /// How the feature is named in errors
+    fn description(self) -> &'static str {
+        match self {
+            Feature::MicrophoneCapture => "Microphone capture (audioDeviceId)",
+            Feature::AppAudioExclusion => "Excluding application audio",
+            Feature::SystemPicker => "The system content picker",
+            Feature::PickerSourceIds => "Identifying picked sources",
+            Feature::PresenterOverlayPrivacyAlert => "presenterOverlayPrivacyAlert",
+            Feature::OutputDeviceTap => "audioOutputDeviceId",
+            Feature::WindowActiveState => "Window activity state",
+        }
+    }
+
+    /// Whether macOS `version` has the feature
+    pub fn available_on(self, version: (u32, u32)) -> bool {
+        version >= self.min_version()
+    }
+
+    /// Whether the running macOS has the feature
+    pub fn is_available(self) -> bool {
+        self.available_on(macos_version())
+    }
+
+    /// Fail with "<feature> requires macOS X or later" when the running macOS lacks the feature
+    pub fn require(self) -> Result<()> {
+        if self.is_available() {
+            return Ok(());
+        }
+        let (major, minor) = self.min_version();
+        let (running_major, running_minor) = macos_version();
+        Err(Error::new(
+            Status::GenericFailure,
+            format!(
+                "{} requires macOS {}.{} or later (running {}.{})",
+                self.description(), major, minor, running_major, running_minor,
+            ),
+        ))
+    }
+}
+
+/// The running macOS as (major, minor), read once
+pub fn macos_version() -> (u32, u32) {
+    static VERSION: OnceLock<(u32, u32)> = OnceLock::new();
+    *VERSION.get_or_init(|| {
+        let version = NSProcessInfo::processInfo().operatingSystemVersion();
+        (version.majorVersion as u32, version.minorVersion as u32)
+    })
+}
+
+/// The feature map of macOS `version`
+pub fn features_for(version: (u32, u32)) -> Features {
+    Features {
+        macos_version: format!("{}.{}", version.0, version.1),
+        supports_microphone_capture: Feature::MicrophoneCapture.available_on(version),
+        supports_app_audio_exclusion: Feature::AppAudioExclusion.available_on(version),
+        supports_system_picker: Feature::SystemPicker.available_on(version),
+        supports_picker_source_ids: Feature::PickerSourceIds.available_on(version),
+        supports_presenter_overlay_privacy_alert: Feature::PresenterOverlayPrivacyAlert.available_on(version),
+        supports_output_device_tap: Feature::OutputDeviceTap.available_on(version),
+        supports_window_active_state: Feature::WindowActiveState.available_on(version),
+    }
+}
+
+/// The feature map of the running macOS
+pub fn features() -> Features {
+    features_for(macos_version())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_features_follow_the_macos_version() {
+        let sonoma = features_for((14, 1));
+        assert_eq!(sonoma.macos_version, "14.1");
+        assert!(sonoma.supports_system_picker && sonoma.supports_window_active_state);
+        assert!(!sonoma.supports_presenter_overlay_privacy_alert && !sonoma.supports_output_device_tap);
+        assert!(!sonoma.supports_microphone_capture && !sonoma.supports_app_audio_exclusion);
+
+        let sequoia = features_for((15, 1));
+        assert!(sequoia.supports_microphone_capture && sequoia.supports_app_audio_exclusion);
+        assert!(!sequoia.supports_picker_source_ids, "Picked source ids arrived in 15.2");
+        assert!(features_for((26, 0)).supports_picker_source_ids);
+        assert!(!Feature::SystemPicker.available_on((13, 5)));
+    }
+}
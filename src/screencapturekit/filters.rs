@@ -3,16 +3,48 @@
 
 use napi::{Result, Status, Error};
 use std::ptr;
+use std::sync::Arc;
+use objc2::runtime::AnyObject;
 
 use super::types::*;
-use super::bindings::ScreenCaptureKitAPI;
-use super::foundation::PermissionHelpers;
+use super::bindings::ScreenCaptureBackend;
+use super::foundation::{CoreGraphicsHelpers, CGRect, CGPoint, CGSize};
+
+/// Ids of the displays a window's frame overlaps
+pub fn spanned_display_ids(window: &WindowInfo, displays: &[DisplayInfo]) -> Vec<u32> {
+    let (left, top) = (window.x as i64, window.y as i64);
+    let (right, bottom) = (left + window.width as i64, top + window.height as i64);
+    
+    displays.iter()
+        .filter(|display| {
+            let (display_left, display_top) = (display.x as i64, display.y as i64);
+            left < display_left + display.width as i64 && right > display_left
+                && top < display_top + display.height as i64 && bottom > display_top
+        })
+        .map(|display| display.id)
+        .collect()
+}
+
+/// Id of the display `point` (global points) is on; mirrors are skipped since they show the
+/// same picture as the display they mirror
+pub fn display_at(point: CGPoint, displays: &[DisplayInfo]) -> Option<u32> {
+    displays.iter()
+        .find(|display| {
+            !display.is_mirror
+                && point.x >= display.x as f64 && point.x < display.x as f64 + display.width as f64
+                && point.y >= display.y as f64 && point.y < display.y as f64 + display.height as f64
+        })
+        .map(|display| display.id)
+}
 
 /// Content filter wrapper that provides safe access to SCContentFilter
+/// Owns the +1 reference from the filter's alloc/init and releases it on drop; streams created
+/// from the filter hold their own reference
 pub struct ContentFilter {
     filter_ptr: *mut SCContentFilter,
     filter_type: ContentFilterType,
     is_valid: bool,
+    backend: Arc<dyn ScreenCaptureBackend>,
 }
 
 // Safety: Raw pointers are only used within unsafe blocks and the filter
@@ -23,29 +55,55 @@ unsafe impl Sync for ContentFilter {}
 impl ContentFilter {
     /// Create a new content filter for a display
     pub unsafe fn new_for_display(
+        backend: &Arc<dyn ScreenCaptureBackend>,
+        shareable_content: *mut SCShareableContent,
+        display_id: u32,
+    ) -> Result<Self> {
+        Self::new_for_display_excluding_windows(backend, shareable_content, display_id, &[])
+    }
+
+    /// Create a new content filter for a display that leaves out the given windows
+    /// Window ids that aren't part of the shareable content are ignored
+    pub unsafe fn new_for_display_excluding_windows(
+        backend: &Arc<dyn ScreenCaptureBackend>,
         shareable_content: *mut SCShareableContent,
         display_id: u32,
+        excluded_window_ids: &[u32],
     ) -> Result<Self> {
         // Check permissions first
-        if !PermissionHelpers::check_screen_recording_permission() {
+        if !backend.check_screen_recording_permission() {
             return Err(Error::new(Status::GenericFailure, "Screen recording permission required"));
         }
 
         // Extract the display from shareable content
-        let displays = ScreenCaptureKitAPI::extract_displays(shareable_content)
+        let displays = backend.extract_displays(shareable_content)
             .map_err(|e| Error::new(Status::GenericFailure, e))?;
 
         // Find the requested display
         let target_display = displays
             .into_iter()
             .find(|&display| {
-                let (id, _, _, _) = ScreenCaptureKitAPI::get_display_info(display);
+                let (id, _, _, _) = backend.get_display_info(display);
                 id == display_id
             })
             .ok_or_else(|| Error::new(Status::InvalidArg, format!("Display {} not found", display_id)))?;
 
         // Create content filter
-        let filter_ptr = ScreenCaptureKitAPI::create_content_filter_with_display(target_display);
+        let filter_ptr = if excluded_window_ids.is_empty() {
+            backend.create_display_filter(target_display, &[])
+        } else {
+            let excluded_windows: Vec<*mut SCWindow> = backend.extract_windows(shareable_content)
+                .map_err(|e| Error::new(Status::GenericFailure, e))?
+                .into_iter()
+                .filter(|&window| {
+                    let (id, _, _, _) = backend.get_window_info(window);
+                    excluded_window_ids.contains(&id)
+                })
+                .collect();
+            
+            println!("🙈 Excluding {} window(s) from display {}", excluded_windows.len(), display_id);
+            backend.create_display_filter(target_display, &excluded_windows)
+        };
         
         if filter_ptr.is_null() {
             return Err(Error::new(Status::GenericFailure, "Failed to create display content filter"));
@@ -55,34 +113,79 @@ impl ContentFilter {
             filter_ptr,
             filter_type: ContentFilterType::Display(display_id),
             is_valid: true,
+            backend: backend.clone(),
+        })
+    }
+
+    /// Create a new content filter for a display without the audio of `audio_bundle_ids`
+    /// Their windows stay in the video; with `exclude_self` this process is left out entirely
+    pub unsafe fn new_for_display_excluding_app_audio(
+        backend: &Arc<dyn ScreenCaptureBackend>,
+        shareable_content: *mut SCShareableContent,
+        display_id: u32,
+        audio_bundle_ids: &[String],
+        exclude_self: bool,
+    ) -> Result<Self> {
+        if !backend.check_screen_recording_permission() {
+            return Err(Error::new(Status::GenericFailure, "Screen recording permission required"));
+        }
+
+        let target_display = backend.extract_displays(shareable_content)
+            .map_err(|e| Error::new(Status::GenericFailure, e))?
+            .into_iter()
+            .find(|&display| {
+                let (id, _, _, _) = backend.get_display_info(display);
+                id == display_id
+            })
+            .ok_or_else(|| Error::new(Status::InvalidArg, format!("Display {} not found", display_id)))?;
+
+        let excluded_process_id = exclude_self.then(|| std::process::id() as i32);
+        println!("🔇 Excluding audio from {:?} on display {}", audio_bundle_ids, display_id);
+        let filter_ptr = backend.create_display_filter_excluding_app_audio(
+            shareable_content,
+            target_display,
+            audio_bundle_ids,
+            excluded_process_id,
+        );
+
+        if filter_ptr.is_null() {
+            return Err(Error::new(Status::GenericFailure, "Failed to create display content filter"));
+        }
+
+        Ok(Self {
+            filter_ptr,
+            filter_type: ContentFilterType::Display(display_id),
+            is_valid: true,
+            backend: backend.clone(),
         })
     }
 
     /// Create a new content filter for a window
     pub unsafe fn new_for_window(
+        backend: &Arc<dyn ScreenCaptureBackend>,
         shareable_content: *mut SCShareableContent,
         window_id: u32,
     ) -> Result<Self> {
         // Check permissions first
-        if !PermissionHelpers::check_screen_recording_permission() {
+        if !backend.check_screen_recording_permission() {
             return Err(Error::new(Status::GenericFailure, "Screen recording permission required"));
         }
 
         // Extract the window from shareable content
-        let windows = ScreenCaptureKitAPI::extract_windows(shareable_content)
+        let windows = backend.extract_windows(shareable_content)
             .map_err(|e| Error::new(Status::GenericFailure, e))?;
 
         // Find the requested window
         let target_window = windows
             .into_iter()
             .find(|&window| {
-                let (id, _, _, _) = ScreenCaptureKitAPI::get_window_info(window);
+                let (id, _, _, _) = backend.get_window_info(window);
                 id == window_id
             })
             .ok_or_else(|| Error::new(Status::InvalidArg, format!("Window {} not found", window_id)))?;
 
         // Create content filter
-        let filter_ptr = ScreenCaptureKitAPI::create_content_filter_with_window(target_window);
+        let filter_ptr = backend.create_window_filter(target_window);
         
         if filter_ptr.is_null() {
             return Err(Error::new(Status::GenericFailure, "Failed to create window content filter"));
@@ -92,15 +195,78 @@ impl ContentFilter {
             filter_ptr,
             filter_type: ContentFilterType::Window(window_id),
             is_valid: true,
+            backend: backend.clone(),
         })
     }
 
+    /// Configure how the source is scaled into a `width`x`height` stream configuration
+    /// Fit letterboxes, Fill crops the centre of the source, Stretch distorts; with High quality
+    /// the stream captures at native size instead and the writer does the scaling
+    /// Returns the source rect it crops to, if any
+    pub unsafe fn configure_scaling(
+        &self,
+        backend: &dyn ScreenCaptureBackend,
+        stream_config: *mut SCStreamConfiguration,
+        width: u32,
+        height: u32,
+        mode: ScalingMode,
+        quality: ScalingQuality,
+    ) -> Option<CGRect> {
+        let content_rect = backend.get_content_filter_rect(self.filter_ptr);
+        
+        if quality == ScalingQuality::High {
+            if let Some((rect, scale)) = content_rect {
+                let native_width = ((rect.size.width * scale).round() as u32) & !1;
+                let native_height = ((rect.size.height * scale).round() as u32) & !1;
+                backend.set_output_size(stream_config, native_width, native_height);
+                println!("📐 Capturing at native {}x{}, writer scales to {}x{}", native_width, native_height, width, height);
+                return None;
+            }
+            println!("⚠️ High scaling quality requires macOS 14+, scaling during capture instead");
+        }
+        
+        let preserves_aspect_ratio = mode != ScalingMode::Stretch;
+        if !backend.set_scaling(stream_config, true, preserves_aspect_ratio) {
+            println!("⚠️ Aspect ratio control requires macOS 14+, using the system default");
+        }
+        
+        if mode == ScalingMode::Fill {
+            match content_rect {
+                Some((rect, _)) => {
+                    let crop = Self::centered_crop(rect.size.width, rect.size.height, width as f64 / height as f64);
+                    backend.set_source_rect(stream_config, crop);
+                    println!("✂️ Cropping source to {}x{} points to fill output", crop.size.width, crop.size.height);
+                    return Some(crop);
+                }
+                None => println!("⚠️ Fill scaling requires macOS 14+, letterboxing instead"),
+            }
+        }
+        None
+    }
+    
+    /// Largest rect with `aspect` (width / height) centred in a `width`x`height` source
+    /// Its size is rounded down to even points so the cropped frames stay encodable
+    pub(crate) fn centered_crop(width: f64, height: f64, aspect: f64) -> CGRect {
+        let (crop_width, crop_height) = if width / height > aspect {
+            (height * aspect, height)
+        } else {
+            (width, width / aspect)
+        };
+        let even = |points: f64| (points / 2.0).floor() * 2.0;
+        let (crop_width, crop_height) = (even(crop_width), even(crop_height));
+        
+        CGRect {
+            origin: CGPoint { x: (width - crop_width) / 2.0, y: (height - crop_height) / 2.0 },
+            size: CGSize { width: crop_width, height: crop_height },
+        }
+    }
+
     /// Create a basic content filter (fallback)
-    pub unsafe fn new_basic() -> Result<Self> {
+    pub unsafe fn new_basic(backend: &Arc<dyn ScreenCaptureBackend>) -> Result<Self> {
         println!("🔧 Creating basic content filter using ScreenCaptureKit");
         
         // Create a simple filter without async operations to avoid Send issues
-        let filter_ptr = super::bindings::ScreenCaptureKitAPI::create_content_filter_with_display_id(1);
+        let filter_ptr = backend.create_basic_filter();
         
         if filter_ptr.is_null() {
             return Err(Error::new(Status::GenericFailure, "Failed to create basic content filter"));
@@ -111,6 +277,7 @@ impl ContentFilter {
             filter_ptr,
             filter_type: ContentFilterType::Desktop,
             is_valid: true,
+            backend: backend.clone(),
         })
     }
 
@@ -133,16 +300,19 @@ impl ContentFilter {
         self.filter_type
     }
 
-    /// Invalidate the filter
+    /// Invalidate the filter; the pointer stays owned until drop
     pub fn invalidate(&mut self) {
         self.is_valid = false;
-        // Note: We don't deallocate the filter_ptr as it's managed by the Objective-C runtime
     }
 }
 
 impl Drop for ContentFilter {
     fn drop(&mut self) {
         self.invalidate();
+        if !self.filter_ptr.is_null() {
+            unsafe { self.backend.release_object(self.filter_ptr as *mut AnyObject) };
+            self.filter_ptr = ptr::null_mut();
+        }
     }
 }
 
@@ -151,13 +321,33 @@ pub struct ContentFilterFactory;
 
 impl ContentFilterFactory {
     /// Create the best available content filter for a display
+    /// With `exclude_self`, windows owned by the current process are left out of the capture;
+    /// `excluded_audio_bundle_ids` are muted where supported (macOS 15), otherwise all audio is kept
     pub unsafe fn create_display_filter(
+        backend: &Arc<dyn ScreenCaptureBackend>,
         shareable_content: Option<*mut SCShareableContent>,
         display_id: u32,
+        exclude_self: bool,
+        excluded_audio_bundle_ids: &[String],
     ) -> Result<ContentFilter> {
         if let Some(content) = shareable_content {
+            if !excluded_audio_bundle_ids.is_empty() {
+                if backend.supports_app_audio_exclusion() {
+                    return ContentFilter::new_for_display_excluding_app_audio(
+                        backend, content, display_id, excluded_audio_bundle_ids, exclude_self,
+                    );
+                }
+                println!("⚠️ Excluding app audio requires macOS 15+, capturing all audio");
+            }
+            
+            let excluded_window_ids = if exclude_self {
+                CoreGraphicsHelpers::get_window_ids_for_process(std::process::id())
+            } else {
+                Vec::new()
+            };
+            
             // Try to create with real shareable content
-            match ContentFilter::new_for_display(content, display_id) {
+            match ContentFilter::new_for_display_excluding_windows(backend, content, display_id, &excluded_window_ids) {
                 Ok(filter) => return Ok(filter),
                 Err(e) => {
                     println!("⚠️ Failed to create display filter with shareable content: {}", e);
@@ -165,19 +355,24 @@ impl ContentFilterFactory {
             }
         }
 
+        if exclude_self {
+            println!("⚠️ Own windows can't be excluded without shareable content");
+        }
+
         // Fallback to basic filter
         println!("💡 Using basic content filter as fallback");
-        ContentFilter::new_basic()
+        ContentFilter::new_basic(backend)
     }
 
     /// Create the best available content filter for a window
     pub unsafe fn create_window_filter(
+        backend: &Arc<dyn ScreenCaptureBackend>,
         shareable_content: Option<*mut SCShareableContent>,
         window_id: u32,
     ) -> Result<ContentFilter> {
         if let Some(content) = shareable_content {
             // Try to create with real shareable content
-            match ContentFilter::new_for_window(content, window_id) {
+            match ContentFilter::new_for_window(backend, content, window_id) {
                 Ok(filter) => return Ok(filter),
                 Err(e) => {
                     println!("⚠️ Failed to create window filter with shareable content: {}", e);
@@ -187,11 +382,11 @@ impl ContentFilterFactory {
 
         // Fallback to basic filter
         println!("💡 Using basic content filter as fallback");
-        ContentFilter::new_basic()
+        ContentFilter::new_basic(backend)
     }
 
     /// Create a basic desktop capture filter
-    pub unsafe fn create_desktop_filter() -> Result<ContentFilter> {
-        ContentFilter::new_basic()
+    pub unsafe fn create_desktop_filter(backend: &Arc<dyn ScreenCaptureBackend>) -> Result<ContentFilter> {
+        ContentFilter::new_basic(backend)
     }
 } 
\ No newline at end of file
@@ -5,7 +5,16 @@ use objc2::{msg_send, class};
 use objc2_foundation::{NSString, NSError, NSArray, NSDictionary, NSNumber};
 use objc2::runtime::AnyObject;
 use napi::{Result, Status, Error};
+use std::collections::HashMap;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// Longest a worker thread waits for the main thread to read the screens' EDR headroom
+const EDR_HEADROOM_WAIT: Duration = Duration::from_millis(50);
+
+// Set while a headroom read waits on the main queue; at most one is queued at a time
+static EDR_READ_PENDING: AtomicBool = AtomicBool::new(false);
 
 // Core Graphics structures for frame handling
 #[repr(C)]
@@ -34,6 +43,10 @@ unsafe impl objc2::Encode for CGRect {
     const ENCODING: objc2::Encoding = objc2::Encoding::Struct("CGRect", &[CGPoint::ENCODING, CGSize::ENCODING]);
 }
 
+unsafe impl objc2::RefEncode for CGRect {
+    const ENCODING_REF: objc2::Encoding = objc2::Encoding::Pointer(&<Self as objc2::Encode>::ENCODING);
+}
+
 unsafe impl objc2::Encode for CGPoint {
     const ENCODING: objc2::Encoding = objc2::Encoding::Struct("CGPoint", &[f64::ENCODING, f64::ENCODING]);
 }
@@ -101,6 +114,101 @@ impl CoreGraphicsHelpers {
         CGMainDisplayID()
     }
 
+    /// Frame of `display_id` in global display points, top-left origin
+    pub unsafe fn get_display_bounds(display_id: u32) -> CGRect {
+        extern "C" {
+            fn CGDisplayBounds(display: u32) -> CGRect;
+        }
+        CGDisplayBounds(display_id)
+    }
+
+    /// Pixels per point of `display_id` in its current mode (2 on Retina); 1.0 when the mode
+    /// can't be read
+    pub unsafe fn get_display_scale_factor(display_id: u32) -> f64 {
+        extern "C" {
+            fn CGDisplayCopyDisplayMode(display: u32) -> *mut std::ffi::c_void;
+            fn CGDisplayModeGetWidth(mode: *mut std::ffi::c_void) -> usize;
+            fn CGDisplayModeGetPixelWidth(mode: *mut std::ffi::c_void) -> usize;
+            fn CGDisplayModeRelease(mode: *mut std::ffi::c_void);
+        }
+        let mode = CGDisplayCopyDisplayMode(display_id);
+        if mode.is_null() {
+            return 1.0;
+        }
+        let (points, pixels) = (CGDisplayModeGetWidth(mode), CGDisplayModeGetPixelWidth(mode));
+        CGDisplayModeRelease(mode);
+        if points == 0 || pixels == 0 { 1.0 } else { pixels as f64 / points as f64 }
+    }
+
+    /// Whether `display_id` mirrors another display (a non-primary member of a mirror set)
+    pub unsafe fn is_mirror_display(display_id: u32) -> bool {
+        extern "C" {
+            fn CGDisplayMirrorsDisplay(display: u32) -> u32;
+        }
+        // kCGNullDirectDisplay when the display isn't mirroring anything
+        CGDisplayMirrorsDisplay(display_id) != 0
+    }
+
+    /// Current EDR headroom of every screen by display ID: the largest colour component value
+    /// it shows now, with SDR white at 1.0 (1.0 before macOS 10.15). It changes with brightness
+    /// and content, so read it per listing
+    /// NSScreen is main-thread only, so all screens are read in one trip through the main
+    /// queue. None when the main thread doesn't get to it within `EDR_HEADROOM_WAIT`, and while
+    /// that read is still queued, so repeated listings don't pile up reads that never ran
+    pub unsafe fn edr_headrooms() -> Option<HashMap<u32, f64>> {
+        if EDR_READ_PENDING.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        super::run_loop::on_main_queue(EDR_HEADROOM_WAIT, || {
+            let headrooms = unsafe { Self::screen_edr_headrooms() };
+            EDR_READ_PENDING.store(false, Ordering::SeqCst);
+            headrooms
+        })
+    }
+    
+    unsafe fn screen_edr_headrooms() -> HashMap<u32, f64> {
+        let mut headrooms = HashMap::new();
+        let screens: *mut NSArray<AnyObject> = msg_send![class!(NSScreen), screens];
+        if screens.is_null() {
+            return headrooms;
+        }
+        let number_key = NSString::from_str("NSScreenNumber");
+        let count: usize = msg_send![screens, count];
+        for index in 0..count {
+            let screen: *mut AnyObject = msg_send![screens, objectAtIndex: index];
+            let description: *mut NSDictionary<NSString, AnyObject> = msg_send![screen, deviceDescription];
+            let number: *mut NSNumber = msg_send![description, objectForKey: &*number_key];
+            if number.is_null() {
+                continue;
+            }
+            let screen_id: u32 = msg_send![number, unsignedIntValue];
+            let supported: bool = msg_send![screen, respondsToSelector: objc2::sel!(maximumExtendedDynamicRangeColorComponentValue)];
+            let headroom: f64 = if supported { msg_send![screen, maximumExtendedDynamicRangeColorComponentValue] } else { 1.0 };
+            headrooms.insert(screen_id, if headroom.is_finite() { headroom.max(1.0) } else { 1.0 });
+        }
+        headrooms
+    }
+
+    /// Whether `display_id` is a virtual display (Sidecar, AirPlay, software displays)
+    /// There is no direct flag: virtual displays have no physical panel size and usually no
+    /// EDID vendor, while the built-in panel (including a Touch Bar host) always has both
+    pub unsafe fn is_virtual_display(display_id: u32) -> bool {
+        extern "C" {
+            fn CGDisplayIsBuiltin(display: u32) -> u32;
+            fn CGDisplayVendorNumber(display: u32) -> u32;
+            fn CGDisplayScreenSize(display: u32) -> CGSize;
+        }
+        // kDisplayVendorIDUnknown ('unkn')
+        const UNKNOWN_VENDOR: u32 = 0x756E6B6E;
+        
+        if CGDisplayIsBuiltin(display_id) != 0 {
+            return false;
+        }
+        let size = CGDisplayScreenSize(display_id);
+        let vendor = CGDisplayVendorNumber(display_id);
+        (size.width <= 0.0 && size.height <= 0.0) || vendor == 0 || vendor == UNKNOWN_VENDOR
+    }
+
     /// Get window information using Core Graphics
     pub unsafe fn get_window_list() -> Result<Vec<(u32, String, u32, u32)>> {
         extern "C" {
@@ -145,6 +253,50 @@ impl CoreGraphicsHelpers {
         }
     }
 
+    /// Get the ids of every window owned by a process (matched on kCGWindowOwnerPID)
+    /// Includes off-screen windows so they stay excluded if they appear mid-recording
+    pub unsafe fn get_window_ids_for_process(pid: u32) -> Vec<u32> {
+        extern "C" {
+            fn CGWindowListCopyWindowInfo(option: u32, relativeToWindow: u32) -> *mut NSArray;
+        }
+        
+        const kCGWindowListOptionAll: u32 = 0;
+        
+        let window_list_raw = CGWindowListCopyWindowInfo(kCGWindowListOptionAll, 0);
+        if window_list_raw.is_null() {
+            return Vec::new();
+        }
+        
+        let owner_pid_key = NSString::from_str("kCGWindowOwnerPID");
+        let window_number_key = NSString::from_str("kCGWindowNumber");
+        
+        let window_list: &NSArray = &*window_list_raw;
+        let mut window_ids = Vec::new();
+        
+        for i in 0..window_list.count() {
+            let window_dict_obj = window_list.objectAtIndex(i);
+            let Ok(window_dict) = window_dict_obj.downcast::<NSDictionary>() else {
+                continue;
+            };
+            
+            let owner_pid = window_dict.objectForKey(&owner_pid_key)
+                .and_then(|obj| obj.downcast::<NSNumber>().ok())
+                .map(|number| number.intValue() as u32);
+            
+            if owner_pid == Some(pid) {
+                if let Some(number) = window_dict.objectForKey(&window_number_key)
+                    .and_then(|obj| obj.downcast::<NSNumber>().ok())
+                {
+                    window_ids.push(number.unsignedIntValue());
+                }
+            }
+        }
+        
+        let _: () = msg_send![window_list_raw, release];
+        
+        window_ids
+    }
+
     unsafe fn extract_window_from_dict(window_dict: &NSDictionary, fallback_id: u32) -> Option<(u32, String, u32, u32)> {
         let window_number_key = NSString::from_str("kCGWindowNumber");
         let window_name_key = NSString::from_str("kCGWindowName");
@@ -241,6 +393,129 @@ impl CoreGraphicsHelpers {
     }
 }
 
+pub const SC_STREAM_ERROR_DOMAIN: &str = "com.apple.ScreenCaptureKit.SCStreamErrorDomain";
+
+/// SCStreamError case name for `code`
+pub fn scstream_error_name(code: isize) -> Option<&'static str> {
+    Some(match code {
+        -3801 => "userDeclined",
+        -3802 => "failedToStart",
+        -3803 => "missingEntitlements",
+        -3804 => "failedApplicationConnectionInvalid",
+        -3805 => "failedApplicationConnectionInterrupted",
+        -3806 => "failedNoMatchingApplicationContext",
+        -3807 => "attemptToStartStreamState",
+        -3808 => "attemptToStopStreamState",
+        -3809 => "attemptToUpdateFilterState",
+        -3810 => "attemptToConfigState",
+        -3811 => "internalError",
+        -3812 => "invalidParameter",
+        -3813 => "noWindowList",
+        -3814 => "noDisplayList",
+        -3815 => "noCaptureSource",
+        -3816 => "removingStream",
+        -3817 => "userStopped",
+        -3818 => "failedToStartAudioCapture",
+        -3819 => "failedToStopAudioCapture",
+        -3820 => "failedToStartMicrophoneCapture",
+        -3821 => "systemStoppedStream",
+        _ => return None,
+    })
+}
+
+/// SCStreamError codes meaning capture permission is missing or was taken away
+pub fn is_permission_error_code(code: isize) -> bool {
+    matches!(code, -3801 | -3803)
+}
+
+/// Whether `err` is a ScreenCaptureKit permission error
+pub unsafe fn is_permission_error(err: *mut NSError) -> bool {
+    if err.is_null() {
+        return false;
+    }
+    let domain: *mut NSString = msg_send![err, domain];
+    let code: isize = msg_send![err, code];
+    !domain.is_null() && (*domain).to_string() == SC_STREAM_ERROR_DOMAIN && is_permission_error_code(code)
+}
+
+/// Describe an NSError as "description (domain code N)", including one underlying error
+/// ScreenCaptureKit codes also carry their SCStreamError name, e.g. "code -3801 userDeclined"
+/// A null error gives a fixed fallback so callers can pass whatever an API returned
+pub unsafe fn describe_nserror(err: *mut NSError) -> String {
+    if err.is_null() {
+        return "Unknown error (no NSError was provided)".to_string();
+    }
+    
+    let domain: *mut NSString = msg_send![err, domain];
+    let code: isize = msg_send![err, code];
+    let description: *mut NSString = msg_send![err, localizedDescription];
+    
+    let domain = if domain.is_null() { "UnknownDomain".to_string() } else { (*domain).to_string() };
+    let code_name = if domain == SC_STREAM_ERROR_DOMAIN { scstream_error_name(code) } else { None };
+    let mut message = format!(
+        "{} ({} code {}{})",
+        if description.is_null() { "No description".to_string() } else { (*description).to_string() },
+        domain,
+        code,
+        code_name.map(|name| format!(" {}", name)).unwrap_or_default(),
+    );
+    
+    let user_info: *mut NSDictionary<NSString, AnyObject> = msg_send![err, userInfo];
+    if !user_info.is_null() {
+        let underlying_key = NSString::from_str("NSUnderlyingError");
+        let underlying: *mut NSError = msg_send![user_info, objectForKey: &*underlying_key];
+        if !underlying.is_null() {
+            let underlying_description: *mut NSString = msg_send![underlying, localizedDescription];
+            let underlying_domain: *mut NSString = msg_send![underlying, domain];
+            let underlying_code: isize = msg_send![underlying, code];
+            if !underlying_description.is_null() && !underlying_domain.is_null() {
+                message.push_str(&format!(
+                    "; underlying: {} ({} code {})",
+                    (*underlying_description).to_string(),
+                    (*underlying_domain).to_string(),
+                    underlying_code,
+                ));
+            }
+        }
+    }
+    
+    message
+}
+
+extern "C-unwind" {
+    // From objc_bridge.m; the body may throw, so both sides allow unwinding
+    fn sc_try_catch(body: unsafe extern "C-unwind" fn(*mut std::ffi::c_void), context: *mut std::ffi::c_void) -> *mut std::ffi::c_char;
+}
+
+extern "C" {
+    fn free(ptr: *mut std::ffi::c_void);
+}
+
+/// Run `f` behind an Objective-C @try boundary, returning the exception's "name: reason" as
+/// the error instead of letting it unwind into Rust, which aborts the process
+pub unsafe fn catch_objc_exception<F: FnOnce() -> R, R>(f: F) -> std::result::Result<R, String> {
+    struct Call<F, R> {
+        f: Option<F>,
+        result: Option<R>,
+    }
+    
+    unsafe extern "C-unwind" fn run<F: FnOnce() -> R, R>(context: *mut std::ffi::c_void) {
+        let call = &mut *(context as *mut Call<F, R>);
+        if let Some(f) = call.f.take() {
+            call.result = Some(f());
+        }
+    }
+    
+    let mut call = Call { f: Some(f), result: None };
+    let exception = sc_try_catch(run::<F, R>, &mut call as *mut Call<F, R> as *mut std::ffi::c_void);
+    if !exception.is_null() {
+        let message = std::ffi::CStr::from_ptr(exception).to_string_lossy().to_string();
+        free(exception as *mut std::ffi::c_void);
+        return Err(message);
+    }
+    call.result.ok_or_else(|| "Objective-C call did not complete".to_string())
+}
+
 /// Permission management for screen recording
 pub struct PermissionHelpers;
 
@@ -0,0 +1,215 @@
+// Streaming a recording to a file descriptor or named pipe
+// AVAssetWriter needs a seekable file for a regular movie: the index is written at the end.
+// Fragmented MPEG-4 doesn't, so with outputFd or outputPipe the writer is created without a
+// URL and hands its fragments (an initialization segment, then one media segment about every
+// second) to an AssetWriterSegmentBridge, which passes them here to be written in order.
+// Concatenated, the fragments are a playable fragmented MP4, so a reading process (ffmpeg,
+// an uploader) can consume it as it is produced without anything touching disk
+
+use std::ffi::c_void;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use objc2::runtime::AnyObject;
+use napi::{Result, Status, Error};
+
+extern "C" {
+    fn create_segment_bridge(
+        rust_context: *mut c_void,
+        segment_callback: extern "C" fn(*mut c_void, *const c_void, usize, isize),
+    ) -> *mut c_void;
+    fn release_segment_bridge(bridge: *mut c_void);
+    fn fcntl(fd: i32, cmd: i32, ...) -> i32;
+}
+
+// <fcntl.h> and <errno.h> on macOS
+const O_NONBLOCK: i32 = 0x0004;
+const F_GETFL: i32 = 3;
+const F_SETFL: i32 = 4;
+const ENXIO: i32 = 6;
+
+/// Where a streamed recording goes
+#[derive(Debug, Clone, PartialEq)]
+pub enum FragmentTarget {
+    /// An open descriptor of the caller's, e.g. the write end of a pipe; it stays open
+    Fd(i32),
+    /// A named pipe (FIFO), opened for writing when the recording starts
+    Pipe(String),
+}
+
+impl FragmentTarget {
+    /// The target configured by outputFd or outputPipe, if any
+    pub fn from_config(fd: Option<i32>, pipe: Option<&str>) -> Option<Self> {
+        fd.map(FragmentTarget::Fd).or_else(|| pipe.map(|path| FragmentTarget::Pipe(path.to_string())))
+    }
+}
+
+/// Whether `path` is a named pipe
+pub fn is_fifo(path: &str) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+/// Writes the fragments of a streamed recording, in the order they're produced
+#[derive(Debug)]
+pub struct FragmentSink {
+    // None once a write failed; the rest of the stream is dropped
+    file: Mutex<Option<File>>,
+    bytes_written: AtomicU64,
+}
+
+impl FragmentSink {
+    /// Open `target`; a named pipe must already be open for reading. Opening one without a
+    /// reader would block, and it runs while the recorder is locked, so it fails instead
+    pub fn open(target: &FragmentTarget) -> Result<Self> {
+        let file = match target {
+            FragmentTarget::Fd(fd) => {
+                // A copy of the descriptor, so finishing the recording leaves the caller's open
+                let borrowed = unsafe { BorrowedFd::borrow_raw(*fd) };
+                borrowed.try_clone_to_owned().map(File::from)
+                    .map_err(|e| Error::new(Status::InvalidArg, format!("outputFd {} can't be used: {}", fd, e)))?
+            }
+            FragmentTarget::Pipe(path) => {
+                let file = OpenOptions::new().write(true).custom_flags(O_NONBLOCK).open(path).map_err(|e| match e.raw_os_error() {
+                    Some(ENXIO) => Error::new(
+                        Status::GenericFailure,
+                        format!("outputPipe {} has no reader; open it for reading before starting", path),
+                    ),
+                    _ => Error::new(Status::GenericFailure, format!("Failed to open outputPipe {}: {}", path, e)),
+                })?;
+                // Writes block again once the reader is there, so a slow reader isn't dropped
+                unsafe {
+                    let flags = fcntl(file.as_raw_fd(), F_GETFL);
+                    if flags >= 0 {
+                        fcntl(file.as_raw_fd(), F_SETFL, flags & !O_NONBLOCK);
+                    }
+                }
+                println!("🚰 Streaming to {}", path);
+                file
+            }
+        };
+        Ok(Self { file: Mutex::new(Some(file)), bytes_written: AtomicU64::new(0) })
+    }
+
+    /// Write one fragment; after a failure (e.g. the reader went away) later ones are dropped
+    pub fn write(&self, fragment: &[u8]) {
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        let Some(ref mut output) = *file else {
+            return;
+        };
+        match output.write_all(fragment) {
+            Ok(()) => {
+                self.bytes_written.fetch_add(fragment.len() as u64, Ordering::Relaxed);
+            }
+            Err(e) => {
+                println!("❌ Failed to write a fragment ({}); the rest of the stream is dropped", e);
+                *file = None;
+            }
+        }
+    }
+
+    /// Bytes of the stream written so far
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Whether a write failed
+    pub fn failed(&self) -> bool {
+        self.file.lock().map_or(true, |file| file.is_none())
+    }
+}
+
+extern "C" fn segment_data(context: *mut c_void, bytes: *const c_void, length: usize, _segment_type: isize) {
+    let sink = unsafe { &*(context as *const FragmentSink) };
+    sink.write(unsafe { std::slice::from_raw_parts(bytes as *const u8, length) });
+}
+
+/// The AVAssetWriterDelegate that feeds a segmenting writer's output to a FragmentSink
+pub struct FragmentBridge {
+    bridge: *mut c_void,
+    // Kept alive for the bridge's callbacks
+    sink: Arc<FragmentSink>,
+}
+
+// Safety: the bridge only calls into the sink, which locks around its file
+unsafe impl Send for FragmentBridge {}
+unsafe impl Sync for FragmentBridge {}
+
+impl FragmentBridge {
+    pub fn new(sink: Arc<FragmentSink>) -> Result<Self> {
+        let bridge = unsafe { create_segment_bridge(Arc::as_ptr(&sink) as *mut c_void, segment_data) };
+        if bridge.is_null() {
+            return Err(Error::new(Status::GenericFailure, "Failed to create the asset writer segment bridge"));
+        }
+        Ok(Self { bridge, sink })
+    }
+
+    /// Where the fragments go
+    pub fn sink(&self) -> &FragmentSink {
+        &self.sink
+    }
+
+    /// The object to set as the writer's delegate; the writer holds it weakly
+    pub fn as_delegate(&self) -> *mut AnyObject {
+        self.bridge as *mut AnyObject
+    }
+}
+
+impl Drop for FragmentBridge {
+    fn drop(&mut self) {
+        unsafe { release_segment_bridge(self.bridge) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragments_reach_the_descriptor_in_order() {
+        let path = std::env::temp_dir().join("screencapturekit_fragment_sink_test.mp4");
+        let file = File::create(&path).unwrap();
+        let sink = FragmentSink::open(&FragmentTarget::Fd(file.as_raw_fd())).unwrap();
+        sink.write(b"ftypmoov");
+        sink.write(b"moofmdat");
+        assert_eq!(sink.bytes_written(), 16);
+        drop(sink);
+
+        // The caller's descriptor is still usable after the sink closed its copy
+        let mut file = file;
+        file.write_all(b"!").expect("The caller's descriptor stays open");
+        assert_eq!(std::fs::read(&path).unwrap(), b"ftypmoovmoofmdat!");
+        assert!(!is_fifo(&path.to_string_lossy()));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(FragmentSink::open(&FragmentTarget::Fd(987_654)).is_err(), "A closed descriptor is refused");
+    }
+
+    #[test]
+    fn test_pipe_without_a_reader_fails_without_blocking() {
+        let path = std::env::temp_dir().join("screencapturekit_fragment_fifo_test");
+        let _ = std::fs::remove_file(&path);
+        let status = std::process::Command::new("mkfifo").arg(&path).status().expect("mkfifo runs");
+        assert!(status.success());
+        let path_string = path.to_string_lossy().to_string();
+        assert!(is_fifo(&path_string));
+
+        let error = FragmentSink::open(&FragmentTarget::Pipe(path_string.clone())).expect_err("Nobody reads the pipe");
+        assert!(error.reason.contains("no reader"), "{}", error.reason);
+
+        // With a reader connected the sink opens and writes reach it
+        let reader = OpenOptions::new().read(true).custom_flags(O_NONBLOCK).open(&path).unwrap();
+        let sink = FragmentSink::open(&FragmentTarget::Pipe(path_string)).expect("The pipe has a reader");
+        sink.write(b"ftyp");
+        assert_eq!(sink.bytes_written(), 4);
+        drop(sink);
+        let mut received = Vec::new();
+        std::io::Read::read_to_end(&mut &reader, &mut received).unwrap();
+        assert_eq!(received, b"ftyp");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,104 @@
+// Frame delivery check
+// A stream can start without error and still never call the delegate: the output bridge
+// wasn't attached, or screen recording permission is stale (common after re-signing a
+// build). Frames are timestamped as they arrive so callers can ask whether capture is
+// live, and a one-off check shortly after start reports a stream that never delivered any
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// A recording counts as receiving frames when one arrived this recently
+pub const FRAME_DELIVERY_WINDOW: Duration = Duration::from_secs(1);
+/// How long after start a stream may take to deliver its first frame
+pub const FRAME_DELIVERY_GRACE: Duration = Duration::from_secs(2);
+
+// Granularity of the stop check inside the grace period
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// When the last video sample buffer arrived, None until the first one
+pub type LastFrameTime = Arc<Mutex<Option<Instant>>>;
+
+/// Whether a frame arrived within `window` of now
+pub fn received_within(last_frame: &LastFrameTime, window: Duration) -> bool {
+    last_frame.lock().ok()
+        .and_then(|last| *last)
+        .is_some_and(|last| last.elapsed() <= window)
+}
+
+/// The diagnostic reported when no frame arrived within the grace period
+pub fn no_frames_message() -> String {
+    format!(
+        "Capture started but no frames were delivered within {}s; the stream output (delegate bridge) \
+         may not be attached, or screen recording permission may be stale. Re-grant it in System \
+         Settings > Privacy & Security > Screen Recording and restart the app",
+        FRAME_DELIVERY_GRACE.as_secs(),
+    )
+}
+
+/// Waits out the grace period on a background thread; dropping it cancels the check
+pub struct FrameDeliveryCheck {
+    stop: Arc<AtomicBool>,
+}
+
+impl FrameDeliveryCheck {
+    /// Call `on_missing` with `no_frames_message` if no frame has arrived `grace` from now
+    pub fn start<F>(last_frame: LastFrameTime, grace: Duration, on_missing: F) -> Self
+    where
+        F: FnOnce(String) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let started = Instant::now();
+
+        std::thread::spawn(move || {
+            while started.elapsed() < grace {
+                if thread_stop.load(Ordering::SeqCst) {
+                    return;
+                }
+                std::thread::sleep(STOP_CHECK_INTERVAL);
+            }
+            let received = last_frame.lock().map(|last| last.is_some()).unwrap_or(true);
+            if !received && !thread_stop.load(Ordering::SeqCst) {
+                let message = no_frames_message();
+                println!("⚠️ {}", message);
+                on_missing(message);
+            }
+        });
+
+        Self { stop }
+    }
+}
+
+impl Drop for FrameDeliveryCheck {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_missing_frames_are_reported_once() {
+        let last_frame: LastFrameTime = Arc::default();
+        assert!(!received_within(&last_frame, FRAME_DELIVERY_WINDOW));
+
+        let (sender, receiver) = mpsc::channel();
+        let _check = FrameDeliveryCheck::start(last_frame.clone(), Duration::from_millis(100), move |message| {
+            let _ = sender.send(message);
+        });
+        let message = receiver.recv_timeout(Duration::from_secs(2)).expect("No frames is reported");
+        assert!(message.contains("no frames were delivered"));
+
+        *last_frame.lock().unwrap() = Some(Instant::now());
+        assert!(received_within(&last_frame, FRAME_DELIVERY_WINDOW));
+        let (sender, receiver) = mpsc::channel::<String>();
+        let _check = FrameDeliveryCheck::start(last_frame, Duration::from_millis(100), move |message| {
+            let _ = sender.send(message);
+        });
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err(), "Delivered frames aren't reported");
+    }
+}
@@ -0,0 +1,135 @@
+// Frame index sidecar
+// Lists every encoded video frame with its presentation time, whether it's a keyframe and
+// where its bytes sit in the file, so editors can seek frame-accurately without parsing the
+// movie. The sample buffers handed to the writer are uncompressed and carry no sync
+// attachments (the encoder picks keyframes), so the index is read back from the finished
+// file's sample table with AVSampleCursor
+
+use std::ptr;
+use objc2::runtime::AnyObject;
+use objc2::msg_send;
+use objc2_foundation::{NSArray, NSString};
+use objc2_core_media::CMTime;
+use objc2_av_foundation::{AVSampleCursorSyncInfo, AVSampleCursorStorageRange};
+use napi::{Result, Status, Error};
+use serde::Serialize;
+
+use super::encoder::AVMediaTypeVideo;
+use super::segments::open_asset;
+
+/// Sidecar path for a recording written to `output_path`
+pub fn sidecar_path(output_path: &str) -> String {
+    let path = std::path::Path::new(output_path);
+    path.with_extension("frames.json").to_string_lossy().to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct FrameEntry {
+    /// Presentation order, from 0
+    frame: usize,
+    /// Presentation timestamp in `timescale` units
+    pts: i64,
+    /// Seconds from the first frame
+    time: f64,
+    keyframe: bool,
+    /// Byte offset and size of the frame's sample data in the file
+    offset: i64,
+    length: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct FrameIndexFile {
+    version: u32,
+    timescale: i32,
+    frames: Vec<FrameEntry>,
+}
+
+/// Decode-order samples as (pts, keyframe, offset, length), with a shared timescale
+type Sample = (CMTime, bool, i64, i64);
+
+/// Write the frame index of the finished movie at `video_path` next to it
+pub fn write_frame_index(video_path: &str) -> Result<String> {
+    let samples = unsafe { read_video_samples(video_path)? };
+    let index = build_index(samples);
+    let path = sidecar_path(video_path);
+
+    let json = serde_json::to_string(&index)
+        .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to encode frame index: {}", e)))?;
+    std::fs::write(&path, json)
+        .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to write frame index {}: {}", path, e)))?;
+
+    println!("🎞️ Frame index saved: {} ({} frames)", path, index.frames.len());
+    Ok(path)
+}
+
+/// Sort decode-order samples into presentation order
+fn build_index(mut samples: Vec<Sample>) -> FrameIndexFile {
+    let timescale = samples.first().map(|(pts, ..)| pts.timescale).unwrap_or(600);
+    samples.sort_by_key(|(pts, ..)| pts.value);
+    let first = samples.first().map(|(pts, ..)| pts.value).unwrap_or(0);
+
+    let frames = samples.into_iter().enumerate().map(|(frame, (pts, keyframe, offset, length))| FrameEntry {
+        frame,
+        pts: pts.value,
+        time: (pts.value - first) as f64 / timescale as f64,
+        keyframe,
+        offset,
+        length,
+    }).collect();
+
+    FrameIndexFile { version: 1, timescale, frames }
+}
+
+unsafe fn read_video_samples(video_path: &str) -> Result<Vec<Sample>> {
+    let asset = open_asset(video_path);
+    if asset.is_null() {
+        return Err(Error::new(Status::GenericFailure, format!("Failed to open {}", video_path)));
+    }
+    let media_type = NSString::from_str(AVMediaTypeVideo);
+    let tracks: *mut NSArray<AnyObject> = msg_send![asset, tracksWithMediaType: &*media_type];
+    let track: *mut AnyObject = if tracks.is_null() { ptr::null_mut() } else { msg_send![tracks, firstObject] };
+    if track.is_null() {
+        return Err(Error::new(Status::GenericFailure, format!("No video track in {}", video_path)));
+    }
+
+    let can_provide: bool = msg_send![track, canProvideSampleCursors];
+    let cursor: *mut AnyObject = if can_provide { msg_send![track, makeSampleCursorAtFirstSampleInDecodeOrder] } else { ptr::null_mut() };
+    if cursor.is_null() {
+        return Err(Error::new(Status::GenericFailure, format!("Can't read the sample table of {}", video_path)));
+    }
+
+    let mut samples = Vec::new();
+    loop {
+        let pts: CMTime = msg_send![cursor, presentationTimeStamp];
+        let sync: AVSampleCursorSyncInfo = msg_send![cursor, currentSampleSyncInfo];
+        let storage: AVSampleCursorStorageRange = msg_send![cursor, currentSampleStorageRange];
+        samples.push((pts, sync.sampleIsFullSync.as_bool(), storage.offset, storage.length));
+
+        let stepped: i64 = msg_send![cursor, stepInDecodeOrderByCount: 1i64];
+        if stepped < 1 {
+            break;
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(value: i64, keyframe: bool, offset: i64) -> Sample {
+        (CMTime { value, timescale: 600, flags: objc2_core_media::CMTimeFlags(1), epoch: 0 }, keyframe, offset, 100)
+    }
+
+    #[test]
+    fn test_frames_are_listed_in_presentation_order() {
+        assert_eq!(sidecar_path("/tmp/take.mp4"), "/tmp/take.frames.json");
+
+        // B-frames decode before the frames they're shown between
+        let index = build_index(vec![sample(6000, true, 48), sample(6040, false, 148), sample(6020, false, 248)]);
+        assert_eq!(index.timescale, 600);
+        let order: Vec<(usize, i64, bool)> = index.frames.iter().map(|f| (f.frame, f.offset, f.keyframe)).collect();
+        assert_eq!(order, vec![(0, 48, true), (1, 248, false), (2, 148, false)]);
+        assert!((index.frames[2].time - 40.0 / 600.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,71 @@
+// Frame info attachments
+// ScreenCaptureKit attaches a dictionary to every frame describing it: its status, what
+// changed since the last one, and where the captured content sits in the frame and at what
+// scale. The geometry shows what a stream really captured once sourceRect cropping and
+// scaling are applied, which explains letterboxing or cropping the output didn't expect
+
+use objc2::runtime::AnyObject;
+use objc2::msg_send;
+use objc2_core_media::CMSampleBuffer;
+
+use super::foundation::{CGRect, CGPoint, CGSize};
+
+extern "C" {
+    static SCStreamFrameInfoContentRect: *const AnyObject;
+    static SCStreamFrameInfoContentScale: *const AnyObject;
+    static SCStreamFrameInfoScaleFactor: *const AnyObject;
+    fn CMSampleBufferGetSampleAttachmentsArray(sbuf: *mut CMSampleBuffer, create_if_necessary: bool) -> *mut AnyObject;
+    fn CGRectMakeWithDictionaryRepresentation(dict: *const AnyObject, rect: *mut CGRect) -> bool;
+}
+
+/// Where ScreenCaptureKit put the captured content in a frame
+#[derive(Debug, Clone, Copy)]
+pub struct FrameGeometry {
+    /// The part of the frame the content covers, in points; the rest is letterboxing
+    pub content_rect: CGRect,
+    /// Scale from the captured content to the frame; below 1 when it was shrunk to fit
+    pub content_scale: f64,
+    /// Pixels per point of the display the content came from
+    pub scale_factor: f64,
+}
+
+/// The frame info dictionary of a ScreenCaptureKit sample, null when it has none
+pub unsafe fn frame_info(sample_buffer: &CMSampleBuffer) -> *mut AnyObject {
+    let attachments = CMSampleBufferGetSampleAttachmentsArray(sample_buffer as *const CMSampleBuffer as *mut CMSampleBuffer, false);
+    if attachments.is_null() {
+        return std::ptr::null_mut();
+    }
+    let count: usize = msg_send![attachments, count];
+    if count == 0 {
+        return std::ptr::null_mut();
+    }
+    msg_send![attachments, objectAtIndex: 0usize]
+}
+
+/// Read a CGRect stored in its dictionary representation
+pub unsafe fn rect_from_dictionary(dictionary: *mut AnyObject) -> Option<CGRect> {
+    if dictionary.is_null() {
+        return None;
+    }
+    let mut rect = CGRect { origin: CGPoint { x: 0.0, y: 0.0 }, size: CGSize { width: 0.0, height: 0.0 } };
+    CGRectMakeWithDictionaryRepresentation(dictionary, &mut rect).then_some(rect)
+}
+
+/// The content geometry of a frame; None for frames without it, such as idle ones
+pub unsafe fn frame_geometry(sample_buffer: &CMSampleBuffer) -> Option<FrameGeometry> {
+    let info = frame_info(sample_buffer);
+    if info.is_null() {
+        return None;
+    }
+    let content_rect: *mut AnyObject = msg_send![info, objectForKey: SCStreamFrameInfoContentRect];
+    let content_rect = rect_from_dictionary(content_rect)?;
+    let number = |key: *const AnyObject| -> f64 {
+        let value: *mut AnyObject = msg_send![info, objectForKey: key];
+        if value.is_null() { 1.0 } else { msg_send![value, doubleValue] }
+    };
+    Some(FrameGeometry {
+        content_rect,
+        content_scale: number(SCStreamFrameInfoContentScale),
+        scale_factor: number(SCStreamFrameInfoScaleFactor),
+    })
+}
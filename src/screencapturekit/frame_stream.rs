@@ -0,0 +1,354 @@
+// Raw frame delivery
+// Copies captured BGRA frames out of their sample buffers and hands them to a listener in
+// batches, so high frame rates cross into JavaScript once per batch instead of once per frame.
+// Sample buffers are released as soon as they're copied; ScreenCaptureKit's pool never waits on JS.
+// Frames can be converted to RGBA, RGB or JPEG while they're copied; the conversion runs on
+// ScreenCaptureKit's sample queue, so it adds to the time each frame holds that queue
+
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSData, NSNumber, NSString};
+use objc2_core_media::{CMSampleBuffer, CMTime};
+use objc2_core_video::CVPixelBuffer;
+
+pub const DEFAULT_FRAME_BATCH_SIZE: u32 = 1;
+pub const MAX_FRAME_BATCH_SIZE: u32 = 120;
+/// Batches allowed to wait for the JS thread; newer batches are dropped beyond this so a busy
+/// event loop can't pile up copied frames
+pub const MAX_PENDING_FRAME_BATCHES: usize = 4;
+
+// kCVPixelBufferLock_ReadOnly
+const LOCK_READ_ONLY: u64 = 1;
+
+// NSBitmapImageFileTypeJPEG
+const JPEG_FILE_TYPE: usize = 3;
+// NSBitmapImageFileTypePNG
+const PNG_FILE_TYPE: usize = 4;
+const JPEG_QUALITY: f64 = 0.8;
+
+// BGRA -> RGBA channel order for vImagePermuteChannels_ARGB8888
+const BGRA_TO_RGBA: [u8; 4] = [2, 1, 0, 3];
+
+/// Matches vImage_Buffer
+#[repr(C)]
+struct VImageBuffer {
+    data: *mut c_void,
+    height: usize,
+    width: usize,
+    row_bytes: usize,
+}
+
+extern "C" {
+    fn CMSampleBufferGetImageBuffer(sbuf: &CMSampleBuffer) -> *mut CVPixelBuffer;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMTimeGetSeconds(time: CMTime) -> f64;
+    fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut CVPixelBuffer, lock_flags: u64) -> i32;
+    fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut CVPixelBuffer, unlock_flags: u64) -> i32;
+    fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut CVPixelBuffer) -> *mut c_void;
+    fn CVPixelBufferGetWidth(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetHeight(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetBytesPerRow(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferRetain(pixel_buffer: *mut CVPixelBuffer) -> *mut CVPixelBuffer;
+    fn CVPixelBufferRelease(pixel_buffer: *mut CVPixelBuffer);
+    fn vImagePermuteChannels_ARGB8888(src: *const VImageBuffer, dest: *const VImageBuffer, permute_map: *const u8, flags: u32) -> isize;
+    fn vImageConvert_BGRA8888toRGB888(src: *const VImageBuffer, dest: *const VImageBuffer, flags: u32) -> isize;
+}
+
+/// Pixel layout handed to the frames callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameFormat {
+    /// The captured pixels as they are, rows possibly padded
+    #[default]
+    Bgra,
+    /// Packed RGBA, width * 4 bytes per row
+    Rgba,
+    /// Packed RGB, width * 3 bytes per row
+    Rgb,
+    /// A JPEG file per frame
+    Jpeg,
+}
+
+impl FrameFormat {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "bgra" => Some(Self::Bgra),
+            "rgba" => Some(Self::Rgba),
+            "rgb" => Some(Self::Rgb),
+            "jpeg" => Some(Self::Jpeg),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bgra => "bgra",
+            Self::Rgba => "rgba",
+            Self::Rgb => "rgb",
+            Self::Jpeg => "jpeg",
+        }
+    }
+}
+
+/// File format of a single encoded frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+}
+
+/// A captured frame's pixel buffer, retained so it outlives its sample buffer
+/// Frames are only kept once they're encoded and overlays are drawn, so nothing writes to
+/// the pixels while they're read
+pub struct RetainedPixelBuffer(*mut CVPixelBuffer);
+
+// Safety: CVPixelBuffers are reference counted and safe to hand between threads
+unsafe impl Send for RetainedPixelBuffer {}
+
+impl RetainedPixelBuffer {
+    /// The pixel buffer of `sample_buffer`; None for status-only samples
+    pub fn from_sample_buffer(sample_buffer: &CMSampleBuffer) -> Option<Self> {
+        unsafe {
+            let pixel_buffer = CMSampleBufferGetImageBuffer(sample_buffer);
+            (!pixel_buffer.is_null()).then(|| Self(CVPixelBufferRetain(pixel_buffer)))
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut CVPixelBuffer {
+        self.0
+    }
+
+    /// The BGRA pixels as an image file; None when they can't be locked or encoded
+    pub fn encode(&self, format: ImageFormat) -> Option<Vec<u8>> {
+        unsafe {
+            with_locked_pixels(self.0, |source| {
+                let (rgb, row_bytes) = convert(source, FrameFormat::Rgb)?;
+                encode_image(&rgb, source.width, source.height, row_bytes, format)
+            })
+        }
+    }
+}
+
+impl Clone for RetainedPixelBuffer {
+    fn clone(&self) -> Self {
+        Self(unsafe { CVPixelBufferRetain(self.0) })
+    }
+}
+
+impl Drop for RetainedPixelBuffer {
+    fn drop(&mut self) {
+        unsafe { CVPixelBufferRelease(self.0) };
+    }
+}
+
+/// Run `read` on the pixels of `pixel_buffer` while they're locked for reading
+unsafe fn with_locked_pixels<T>(pixel_buffer: *mut CVPixelBuffer, read: impl FnOnce(&VImageBuffer) -> Option<T>) -> Option<T> {
+    if pixel_buffer.is_null() || CVPixelBufferLockBaseAddress(pixel_buffer, LOCK_READ_ONLY) != 0 {
+        return None;
+    }
+    let source = VImageBuffer {
+        data: CVPixelBufferGetBaseAddress(pixel_buffer),
+        height: CVPixelBufferGetHeight(pixel_buffer),
+        width: CVPixelBufferGetWidth(pixel_buffer),
+        row_bytes: CVPixelBufferGetBytesPerRow(pixel_buffer),
+    };
+    let result = if source.data.is_null() { None } else { read(&source) };
+    CVPixelBufferUnlockBaseAddress(pixel_buffer, LOCK_READ_ONLY);
+    result
+}
+
+/// A copied frame
+#[derive(Debug, Clone)]
+pub struct RawFrame {
+    pub data: Vec<u8>,
+    pub format: FrameFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Row stride of `data`, which may include padding past the packed width; 0 for JPEG
+    pub bytes_per_row: u32,
+    /// Presentation time in seconds on the capture clock
+    pub timestamp: f64,
+}
+
+impl RawFrame {
+    /// Copy the frame held by `sample_buffer` in `format`; None for buffers without an image
+    /// (status-only samples) or when the pixels can't be locked or converted
+    pub fn copy_from(sample_buffer: &CMSampleBuffer, format: FrameFormat) -> Option<Self> {
+        unsafe {
+            let pixel_buffer = CMSampleBufferGetImageBuffer(sample_buffer);
+            let (data, bytes_per_row, width, height) = with_locked_pixels(pixel_buffer, |source| {
+                convert(source, format).map(|(data, bytes_per_row)| (data, bytes_per_row, source.width, source.height))
+            })?;
+            Some(Self {
+                data,
+                format,
+                width: width as u32,
+                height: height as u32,
+                bytes_per_row: bytes_per_row as u32,
+                timestamp: CMTimeGetSeconds(CMSampleBufferGetPresentationTimeStamp(sample_buffer)),
+            })
+        }
+    }
+}
+
+/// Pixels of the locked BGRA `source` in `format`, with their row stride
+unsafe fn convert(source: &VImageBuffer, format: FrameFormat) -> Option<(Vec<u8>, usize)> {
+    // Into a packed buffer of `bytes_per_pixel`, or None when vImage fails
+    let packed = |bytes_per_pixel: usize, run: &dyn Fn(&VImageBuffer) -> isize| {
+        let row_bytes = source.width * bytes_per_pixel;
+        let mut data = vec![0u8; row_bytes * source.height];
+        let dest = VImageBuffer { data: data.as_mut_ptr() as *mut c_void, height: source.height, width: source.width, row_bytes };
+        (run(&dest) == 0).then_some((data, row_bytes))
+    };
+
+    match format {
+        FrameFormat::Bgra => {
+            let bytes = std::slice::from_raw_parts(source.data as *const u8, source.row_bytes * source.height);
+            Some((bytes.to_vec(), source.row_bytes))
+        }
+        FrameFormat::Rgba => packed(4, &|dest| vImagePermuteChannels_ARGB8888(source, dest, BGRA_TO_RGBA.as_ptr(), 0)),
+        FrameFormat::Rgb => packed(3, &|dest| vImageConvert_BGRA8888toRGB888(source, dest, 0)),
+        FrameFormat::Jpeg => {
+            let (rgb, row_bytes) = packed(3, &|dest| vImageConvert_BGRA8888toRGB888(source, dest, 0))?;
+            encode_image(&rgb, source.width, source.height, row_bytes, ImageFormat::Jpeg).map(|jpeg| (jpeg, 0))
+        }
+    }
+}
+
+/// Encode packed RGB pixels as an image file
+unsafe fn encode_image(rgb: &[u8], width: usize, height: usize, row_bytes: usize, format: ImageFormat) -> Option<Vec<u8>> {
+    let mut planes = [rgb.as_ptr() as *mut u8];
+    let color_space = NSString::from_str("NSDeviceRGBColorSpace");
+    let bitmap: *mut AnyObject = msg_send![class!(NSBitmapImageRep), alloc];
+    let bitmap: *mut AnyObject = msg_send![
+        bitmap,
+        initWithBitmapDataPlanes: planes.as_mut_ptr(),
+        pixelsWide: width as isize,
+        pixelsHigh: height as isize,
+        bitsPerSample: 8isize,
+        samplesPerPixel: 3isize,
+        hasAlpha: false,
+        isPlanar: false,
+        colorSpaceName: &*color_space,
+        bytesPerRow: row_bytes as isize,
+        bitsPerPixel: 24isize
+    ];
+    if bitmap.is_null() {
+        return None;
+    }
+
+    let (file_type, properties): (usize, *mut AnyObject) = match format {
+        ImageFormat::Jpeg => {
+            let quality: *mut NSNumber = msg_send![class!(NSNumber), numberWithDouble: JPEG_QUALITY];
+            let quality_key = NSString::from_str("NSImageCompressionFactor");
+            (JPEG_FILE_TYPE, msg_send![class!(NSDictionary), dictionaryWithObject: quality, forKey: &*quality_key])
+        }
+        ImageFormat::Png => (PNG_FILE_TYPE, msg_send![class!(NSDictionary), dictionary]),
+    };
+    let encoded: *mut NSData = msg_send![bitmap, representationUsingType: file_type, properties: properties];
+    let data = (!encoded.is_null()).then(|| (*encoded).to_vec());
+    let _: () = msg_send![bitmap, release];
+    data
+}
+
+/// Receives each full batch of frames
+pub type FrameListener = Arc<dyn Fn(Vec<RawFrame>) + Send + Sync>;
+
+/// Collects frames until `batch_size` are ready, then passes them to the listener
+pub struct FrameBatcher {
+    listener: FrameListener,
+    format: FrameFormat,
+    batch_size: usize,
+    pending: Mutex<Vec<RawFrame>>,
+}
+
+impl FrameBatcher {
+    pub fn new(listener: FrameListener, batch_size: u32, format: FrameFormat) -> Self {
+        let batch_size = batch_size.clamp(1, MAX_FRAME_BATCH_SIZE) as usize;
+        Self { listener, format, batch_size, pending: Mutex::new(Vec::with_capacity(batch_size)) }
+    }
+
+    /// Copy the frame in `sample_buffer` and deliver the batch once it's full
+    pub fn push(&self, sample_buffer: &CMSampleBuffer) {
+        if let Some(frame) = RawFrame::copy_from(sample_buffer, self.format) {
+            self.push_frame(frame);
+        }
+    }
+
+    fn push_frame(&self, frame: RawFrame) {
+        let batch = match self.pending.lock() {
+            Ok(mut pending) => {
+                pending.push(frame);
+                if pending.len() < self.batch_size {
+                    return;
+                }
+                std::mem::replace(&mut *pending, Vec::with_capacity(self.batch_size))
+            }
+            Err(_) => return,
+        };
+        (self.listener)(batch);
+    }
+
+    /// Deliver a partly filled batch, e.g. when the recording stops
+    pub fn flush(&self) {
+        let batch = match self.pending.lock() {
+            Ok(mut pending) if !pending.is_empty() => std::mem::take(&mut *pending),
+            _ => return,
+        };
+        (self.listener)(batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_are_delivered_in_batches() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let received = batches.clone();
+        let batcher = FrameBatcher::new(Arc::new(move |frames: Vec<RawFrame>| {
+            received.lock().unwrap().push(frames.iter().map(|frame| frame.timestamp).collect::<Vec<_>>());
+        }), 3, FrameFormat::default());
+
+        for index in 0..7 {
+            batcher.push_frame(RawFrame {
+                data: vec![0; 16],
+                format: FrameFormat::Bgra,
+                width: 2,
+                height: 2,
+                bytes_per_row: 8,
+                timestamp: index as f64,
+            });
+        }
+        batcher.flush();
+        batcher.flush();
+
+        assert_eq!(*batches.lock().unwrap(), vec![vec![0.0, 1.0, 2.0], vec![3.0, 4.0, 5.0], vec![6.0]]);
+    }
+
+    #[test]
+    fn test_frames_convert_to_packed_rgb() {
+        // Two BGRA pixels in a row padded to 12 bytes
+        let mut bgra = vec![1, 2, 3, 255, 4, 5, 6, 128, 0, 0, 0, 0];
+        let source = VImageBuffer { data: bgra.as_mut_ptr() as *mut c_void, height: 1, width: 2, row_bytes: 12 };
+        unsafe {
+            assert_eq!(convert(&source, FrameFormat::Rgba), Some((vec![3, 2, 1, 255, 6, 5, 4, 128], 8)));
+            assert_eq!(convert(&source, FrameFormat::Rgb), Some((vec![3, 2, 1, 6, 5, 4], 6)));
+            let (jpeg, bytes_per_row) = convert(&source, FrameFormat::Jpeg).expect("Frame encodes as JPEG");
+            assert_eq!((&jpeg[..2], bytes_per_row), (&[0xff, 0xd8][..], 0));
+        }
+        assert_eq!(FrameFormat::from_config_str("rgb"), Some(FrameFormat::Rgb));
+    }
+}
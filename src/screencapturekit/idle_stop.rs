@@ -0,0 +1,156 @@
+// Idle stop
+// Stops a recording once the screen has stayed unchanged for a while. ScreenCaptureKit marks
+// each frame with its status and the rectangles that changed since the last one; changes are
+// collected on a coarse tile grid, and only changes touching more than a couple of tiles count
+// as activity, so a blinking caret never keeps a recording alive while a progress bar creeping
+// forward a pixel at a time soon does
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use objc2::runtime::AnyObject;
+use objc2::msg_send;
+use objc2_core_media::CMSampleBuffer;
+
+use super::foundation::CGRect;
+use super::frame_info::{frame_info, rect_from_dictionary};
+
+/// Size of the grid changes are collected on, in pixels
+const TILE_SIZE: f64 = 16.0;
+/// Changes confined to this many tiles are treated as noise
+const NOISE_TILES: usize = 2;
+// How often the idle time is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// SCFrameStatus values
+const FRAME_STATUS_COMPLETE: isize = 0;
+const FRAME_STATUS_STARTED: isize = 4;
+
+extern "C" {
+    static SCStreamFrameInfoStatus: *const AnyObject;
+    static SCStreamFrameInfoDirtyRects: *const AnyObject;
+}
+
+/// When the content last changed; None while the recording is paused
+pub type LastChangeTime = Arc<Mutex<Option<Instant>>>;
+
+/// Collects small changes until they add up to activity
+#[derive(Debug, Default)]
+pub struct ChangeTracker {
+    tiles: HashSet<(i64, i64)>,
+}
+
+impl ChangeTracker {
+    /// Add a frame's changed rectangles; true when they complete a change that counts
+    pub fn record(&mut self, rects: &[CGRect]) -> bool {
+        for rect in rects.iter().filter(|rect| rect.size.width > 0.0 && rect.size.height > 0.0) {
+            let (left, top) = ((rect.origin.x / TILE_SIZE).floor() as i64, (rect.origin.y / TILE_SIZE).floor() as i64);
+            let right = ((rect.origin.x + rect.size.width) / TILE_SIZE).ceil() as i64;
+            let bottom = ((rect.origin.y + rect.size.height) / TILE_SIZE).ceil() as i64;
+            for x in left..right {
+                for y in top..bottom {
+                    self.tiles.insert((x, y));
+                    if self.tiles.len() > NOISE_TILES {
+                        self.tiles.clear();
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+/// What changed in a frame from ScreenCaptureKit: its dirty rectangles, empty for frames that
+/// repeat the last one. None when the sample carries no frame info (or a stream just started),
+/// which counts as a change
+pub unsafe fn frame_changes(sample_buffer: &CMSampleBuffer) -> Option<Vec<CGRect>> {
+    let info = frame_info(sample_buffer);
+    if info.is_null() {
+        return None;
+    }
+    let status: *mut AnyObject = msg_send![info, objectForKey: SCStreamFrameInfoStatus];
+    if status.is_null() {
+        return None;
+    }
+    let status: isize = msg_send![status, integerValue];
+    match status {
+        FRAME_STATUS_COMPLETE => {}
+        FRAME_STATUS_STARTED => return None,
+        // Idle, blank, suspended and stopped frames show nothing new
+        _ => return Some(Vec::new()),
+    }
+
+    let dirty_rects: *mut AnyObject = msg_send![info, objectForKey: SCStreamFrameInfoDirtyRects];
+    if dirty_rects.is_null() {
+        return None;
+    }
+    let count: usize = msg_send![dirty_rects, count];
+    Some((0..count).filter_map(|index| {
+        let dictionary: *mut AnyObject = msg_send![dirty_rects, objectAtIndex: index];
+        rect_from_dictionary(dictionary)
+    }).collect())
+}
+
+/// Waits on a background thread for the content to stay unchanged; dropping it cancels
+pub struct IdleStopMonitor {
+    stop: Arc<AtomicBool>,
+}
+
+impl IdleStopMonitor {
+    /// Call `on_idle` once when `last_change` is `timeout` or more in the past
+    pub fn start<F>(last_change: LastChangeTime, timeout: Duration, on_idle: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::SeqCst) {
+                let idle = last_change.lock().ok()
+                    .and_then(|last| *last)
+                    .is_some_and(|last| last.elapsed() >= timeout);
+                if idle {
+                    println!("💤 Screen unchanged for {}s; stopping the recording", timeout.as_secs());
+                    on_idle();
+                    return;
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        Self { stop }
+    }
+}
+
+impl Drop for IdleStopMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screencapturekit::foundation::{CGPoint, CGSize};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> CGRect {
+        CGRect { origin: CGPoint { x, y }, size: CGSize { width, height } }
+    }
+
+    #[test]
+    fn test_blinking_caret_is_noise_but_progress_bar_is_activity() {
+        let mut tracker = ChangeTracker::default();
+        for _ in 0..100 {
+            assert!(!tracker.record(&[rect(100.0, 40.0, 2.0, 18.0)]), "A caret blinking in place isn't a change");
+        }
+
+        let mut tracker = ChangeTracker::default();
+        let changed = (0..64).map(|step| tracker.record(&[rect(200.0 + step as f64, 300.0, 1.0, 8.0)]));
+        assert!(changed.into_iter().any(|changed| changed), "A progress bar advancing a pixel at a time counts");
+
+        assert!(ChangeTracker::default().record(&[rect(0.0, 0.0, 1920.0, 1080.0)]));
+    }
+}
@@ -0,0 +1,368 @@
+// Keystroke overlay
+// Listens to key events with a CGEventTap and draws the most recent keystrokes onto captured frames
+
+use std::collections::VecDeque;
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSString, NSNumber};
+use objc2_core_media::CMSampleBuffer;
+use objc2_core_video::CVPixelBuffer;
+use napi::{Result, Status, Error};
+
+// How long a keystroke stays on screen, and how many are shown at most
+const KEYSTROKE_DISPLAY_DURATION: Duration = Duration::from_secs(2);
+const MAX_VISIBLE_KEYSTROKES: usize = 16;
+
+// CGEventTap constants
+const kCGSessionEventTap: u32 = 1;
+const kCGHeadInsertEventTap: u32 = 0;
+const kCGEventTapOptionListenOnly: u32 = 1;
+const kCGEventKeyDown: u32 = 10;
+const kCGEventTapDisabledByTimeout: u32 = 0xFFFFFFFE;
+const kCGKeyboardEventKeycode: u32 = 9;
+const kCGEventFlagMaskShift: u64 = 0x00020000;
+const kCGEventFlagMaskControl: u64 = 0x00040000;
+const kCGEventFlagMaskAlternate: u64 = 0x00080000;
+const kCGEventFlagMaskCommand: u64 = 0x00100000;
+
+// BGRA bitmap layout: kCGImageAlphaPremultipliedFirst | kCGBitmapByteOrder32Little
+const BGRA_BITMAP_INFO: u32 = 2 | (2 << 12);
+
+type CFMachPortRef = *mut c_void;
+type CFRunLoopRef = *mut c_void;
+type CGContextRef = *mut c_void;
+type CGEventRef = *mut c_void;
+type CGEventTapCallBack = extern "C" fn(*mut c_void, u32, CGEventRef, *mut c_void) -> CGEventRef;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CGRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+extern "C" {
+    static kCFRunLoopCommonModes: *const c_void;
+    static kCFRunLoopDefaultMode: *const c_void;
+
+    fn CGEventTapCreate(
+        tap: u32,
+        place: u32,
+        options: u32,
+        events_of_interest: u64,
+        callback: CGEventTapCallBack,
+        user_info: *mut c_void,
+    ) -> CFMachPortRef;
+    fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
+    fn CGEventGetIntegerValueField(event: CGEventRef, field: u32) -> i64;
+    fn CGEventGetFlags(event: CGEventRef) -> u64;
+    fn CGEventKeyboardGetUnicodeString(event: CGEventRef, max_length: usize, actual_length: *mut usize, buffer: *mut u16);
+
+    fn CFMachPortCreateRunLoopSource(allocator: *const c_void, port: CFMachPortRef, order: isize) -> *mut c_void;
+    fn CFMachPortInvalidate(port: CFMachPortRef);
+    fn CFRunLoopGetCurrent() -> CFRunLoopRef;
+    fn CFRunLoopAddSource(run_loop: CFRunLoopRef, source: *mut c_void, mode: *const c_void);
+    fn CFRunLoopRunInMode(mode: *const c_void, seconds: f64, return_after_source_handled: bool) -> i32;
+    fn CFRunLoopStop(run_loop: CFRunLoopRef);
+    fn CFRelease(cf: *const c_void);
+
+    fn CMSampleBufferGetImageBuffer(sbuf: &CMSampleBuffer) -> *mut CVPixelBuffer;
+    fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut CVPixelBuffer, lock_flags: u64) -> i32;
+    fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut CVPixelBuffer, unlock_flags: u64) -> i32;
+    fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut CVPixelBuffer) -> *mut c_void;
+    fn CVPixelBufferGetWidth(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetHeight(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetBytesPerRow(pixel_buffer: *mut CVPixelBuffer) -> usize;
+
+    fn CGColorSpaceCreateDeviceRGB() -> *mut c_void;
+    fn CGColorSpaceRelease(space: *mut c_void);
+    fn CGBitmapContextCreate(
+        data: *mut c_void,
+        width: usize,
+        height: usize,
+        bits_per_component: usize,
+        bytes_per_row: usize,
+        space: *mut c_void,
+        bitmap_info: u32,
+    ) -> CGContextRef;
+    fn CGContextRelease(context: CGContextRef);
+    fn CGContextSetRGBFillColor(context: CGContextRef, red: f64, green: f64, blue: f64, alpha: f64);
+    fn CGContextFillRect(context: CGContextRef, rect: CGRect);
+    fn CGContextSetTextPosition(context: CGContextRef, x: f64, y: f64);
+
+    fn CTLineCreateWithAttributedString(string: *const AnyObject) -> *mut c_void;
+    fn CTLineGetTypographicBounds(line: *mut c_void, ascent: *mut f64, descent: *mut f64, leading: *mut f64) -> f64;
+    fn CTLineDraw(line: *mut c_void, context: CGContextRef);
+}
+
+type Keystrokes = Arc<Mutex<VecDeque<(Instant, String)>>>;
+
+/// State shared with the event tap callback
+struct TapContext {
+    keystrokes: Keystrokes,
+    tap: Mutex<CFMachPortRef>,
+}
+
+/// Records keystrokes while alive and renders them onto BGRA frames
+pub struct KeystrokeOverlay {
+    keystrokes: Keystrokes,
+    run_loop: usize,
+    stopped: Arc<AtomicBool>,
+    tap_thread: Option<JoinHandle<()>>,
+}
+
+impl KeystrokeOverlay {
+    /// Start listening for key events
+    /// Fails when the event tap can't be created, which means accessibility access is missing
+    pub fn start() -> Result<Self> {
+        println!("⌨️ Starting keystroke overlay event tap");
+
+        let keystrokes: Keystrokes = Arc::new(Mutex::new(VecDeque::new()));
+        let (ready_tx, ready_rx) = mpsc::channel::<std::result::Result<usize, String>>();
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let thread_keystrokes = keystrokes.clone();
+        let tap_thread = std::thread::spawn(move || unsafe {
+            let context = Box::into_raw(Box::new(TapContext {
+                keystrokes: thread_keystrokes,
+                tap: Mutex::new(ptr::null_mut()),
+            }));
+
+            let tap = CGEventTapCreate(
+                kCGSessionEventTap,
+                kCGHeadInsertEventTap,
+                kCGEventTapOptionListenOnly,
+                1 << kCGEventKeyDown,
+                tap_callback,
+                context as *mut c_void,
+            );
+            if tap.is_null() {
+                drop(Box::from_raw(context));
+                let _ = ready_tx.send(Err("Failed to create keyboard event tap".to_string()));
+                return;
+            }
+            *(*context).tap.lock().unwrap() = tap;
+
+            let source = CFMachPortCreateRunLoopSource(ptr::null(), tap, 0);
+            let run_loop = CFRunLoopGetCurrent();
+            CFRunLoopAddSource(run_loop, source, kCFRunLoopCommonModes);
+            CGEventTapEnable(tap, true);
+            let _ = ready_tx.send(Ok(run_loop as usize));
+
+            // Runs until Drop sets the flag; the timeout covers a stop that lands before the loop starts
+            while !thread_stopped.load(Ordering::SeqCst) {
+                CFRunLoopRunInMode(kCFRunLoopDefaultMode, 0.25, false);
+            }
+
+            CFMachPortInvalidate(tap);
+            CFRelease(source);
+            CFRelease(tap);
+            drop(Box::from_raw(context));
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(run_loop)) => {
+                println!("✅ Keystroke overlay listening");
+                Ok(Self {
+                    keystrokes,
+                    run_loop,
+                    stopped,
+                    tap_thread: Some(tap_thread),
+                })
+            }
+            Ok(Err(e)) => {
+                let _ = tap_thread.join();
+                Err(Error::new(
+                    Status::GenericFailure,
+                    format!("{}: grant accessibility access to record the keystroke overlay", e),
+                ))
+            }
+            Err(_) => Err(Error::new(Status::GenericFailure, "Keystroke event tap thread exited unexpectedly")),
+        }
+    }
+
+    /// Draw recent keystrokes onto the sample buffer's pixels (BGRA only)
+    pub fn render(&self, sample_buffer: &CMSampleBuffer) {
+        let Some(label) = self.visible_label() else {
+            return;
+        };
+
+        unsafe {
+            let pixel_buffer = CMSampleBufferGetImageBuffer(sample_buffer);
+            if pixel_buffer.is_null() || CVPixelBufferLockBaseAddress(pixel_buffer, 0) != 0 {
+                return;
+            }
+
+            let width = CVPixelBufferGetWidth(pixel_buffer);
+            let height = CVPixelBufferGetHeight(pixel_buffer);
+            let color_space = CGColorSpaceCreateDeviceRGB();
+            let context = CGBitmapContextCreate(
+                CVPixelBufferGetBaseAddress(pixel_buffer),
+                width,
+                height,
+                8,
+                CVPixelBufferGetBytesPerRow(pixel_buffer),
+                color_space,
+                BGRA_BITMAP_INFO,
+            );
+            CGColorSpaceRelease(color_space);
+
+            if !context.is_null() {
+                Self::draw_label(context, &label, width as f64, height as f64);
+                CGContextRelease(context);
+            }
+
+            CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+        }
+    }
+
+    /// Keystrokes younger than the display duration, joined for display
+    /// Plain characters run together so typed words stay readable
+    fn visible_label(&self) -> Option<String> {
+        let mut keystrokes = self.keystrokes.lock().ok()?;
+        while keystrokes.front().map_or(false, |(pressed_at, _)| pressed_at.elapsed() > KEYSTROKE_DISPLAY_DURATION) {
+            keystrokes.pop_front();
+        }
+        if keystrokes.is_empty() {
+            return None;
+        }
+
+        let mut label = String::new();
+        let mut previous_was_char = false;
+        for (_, key) in keystrokes.iter() {
+            let is_char = key.chars().count() == 1 && key.chars().all(|c| c.is_alphanumeric() || c.is_ascii_punctuation());
+            if !label.is_empty() && !(is_char && previous_was_char) {
+                label.push(' ');
+            }
+            label.push_str(key);
+            previous_was_char = is_char;
+        }
+        Some(label)
+    }
+
+    /// Draw white text on a translucent black box centred near the bottom of the frame
+    unsafe fn draw_label(context: CGContextRef, label: &str, width: f64, height: f64) {
+        let font_size = (height / 20.0).max(14.0);
+        let font: *mut AnyObject = msg_send![class!(NSFont), boldSystemFontOfSize: font_size];
+
+        let font_key = NSString::from_str("NSFont");
+        let context_color_key = NSString::from_str("CTForegroundColorFromContext");
+        let yes: *mut NSNumber = msg_send![class!(NSNumber), numberWithBool: true];
+        let attributes: *mut AnyObject = msg_send![
+            class!(NSDictionary),
+            dictionaryWithObjects: &[font, yes as *mut AnyObject],
+            forKeys: &[&*font_key, &*context_color_key],
+            count: 2usize
+        ];
+
+        let text = NSString::from_str(label);
+        let alloc: *mut AnyObject = msg_send![class!(NSAttributedString), alloc];
+        let attributed: *mut AnyObject = msg_send![alloc, initWithString: &*text, attributes: attributes];
+        let line = CTLineCreateWithAttributedString(attributed);
+        let _: () = msg_send![attributed, release];
+        if line.is_null() {
+            return;
+        }
+
+        let (mut ascent, mut descent, mut leading) = (0.0, 0.0, 0.0);
+        let text_width = CTLineGetTypographicBounds(line, &mut ascent, &mut descent, &mut leading);
+        let padding = font_size * 0.5;
+        let text_x = ((width - text_width) / 2.0).max(padding);
+        let text_y = height * 0.08;
+
+        CGContextSetRGBFillColor(context, 0.0, 0.0, 0.0, 0.6);
+        CGContextFillRect(context, CGRect {
+            x: text_x - padding,
+            y: text_y - descent - padding,
+            width: text_width + padding * 2.0,
+            height: ascent + descent + padding * 2.0,
+        });
+
+        CGContextSetRGBFillColor(context, 1.0, 1.0, 1.0, 1.0);
+        CGContextSetTextPosition(context, text_x, text_y);
+        CTLineDraw(line, context);
+        CFRelease(line);
+    }
+}
+
+impl Drop for KeystrokeOverlay {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        unsafe { CFRunLoopStop(self.run_loop as CFRunLoopRef); }
+        if let Some(tap_thread) = self.tap_thread.take() {
+            let _ = tap_thread.join();
+        }
+        println!("⌨️ Keystroke overlay stopped");
+    }
+}
+
+/// CGEventTap callback: record key-down events, re-enable the tap if the system disabled it
+extern "C" fn tap_callback(_proxy: *mut c_void, event_type: u32, event: CGEventRef, user_info: *mut c_void) -> CGEventRef {
+    let context = unsafe { &*(user_info as *const TapContext) };
+
+    if event_type == kCGEventTapDisabledByTimeout {
+        if let Ok(tap) = context.tap.lock() {
+            unsafe { CGEventTapEnable(*tap, true); }
+        }
+        return event;
+    }
+
+    if event_type == kCGEventKeyDown {
+        let label = unsafe { keystroke_label(event) };
+        if let Ok(mut keystrokes) = context.keystrokes.lock() {
+            keystrokes.push_back((Instant::now(), label));
+            while keystrokes.len() > MAX_VISIBLE_KEYSTROKES {
+                keystrokes.pop_front();
+            }
+        }
+    }
+
+    event
+}
+
+/// Human-readable label for a key-down event, e.g. "a", "⌘⇧S" or "⏎"
+unsafe fn keystroke_label(event: CGEventRef) -> String {
+    let flags = CGEventGetFlags(event);
+    let keycode = CGEventGetIntegerValueField(event, kCGKeyboardEventKeycode);
+
+    let mut modifiers = String::new();
+    if flags & kCGEventFlagMaskControl != 0 { modifiers.push('⌃'); }
+    if flags & kCGEventFlagMaskAlternate != 0 { modifiers.push('⌥'); }
+    let has_command_modifier = !modifiers.is_empty() || flags & kCGEventFlagMaskCommand != 0;
+    if flags & kCGEventFlagMaskShift != 0 && has_command_modifier { modifiers.push('⇧'); }
+    if flags & kCGEventFlagMaskCommand != 0 { modifiers.push('⌘'); }
+
+    let key = match keycode {
+        36 => "⏎".to_string(),
+        48 => "⇥".to_string(),
+        49 => "␣".to_string(),
+        51 => "⌫".to_string(),
+        53 => "⎋".to_string(),
+        123 => "←".to_string(),
+        124 => "→".to_string(),
+        125 => "↓".to_string(),
+        126 => "↑".to_string(),
+        _ => {
+            let mut buffer = [0u16; 8];
+            let mut length = 0usize;
+            CGEventKeyboardGetUnicodeString(event, buffer.len(), &mut length, buffer.as_mut_ptr());
+            let typed = String::from_utf16_lossy(&buffer[..length.min(buffer.len())]);
+            // Control combos produce ASCII control codes; map them back to their letter
+            let typed: String = typed.chars()
+                .map(|c| if ('\u{1}'..='\u{1a}').contains(&c) { (c as u8 + b'@') as char } else { c })
+                .collect();
+            if has_command_modifier { typed.to_uppercase() } else { typed }
+        }
+    };
+
+    format!("{}{}", modifiers, key)
+}
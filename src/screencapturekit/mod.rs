@@ -16,11 +16,53 @@ pub mod permissions;    // Permission management
 
 // Stream Management Layer
 pub mod audio;
+pub mod audio_monitor;
+pub mod buffer_check;
+pub mod capabilities;
+pub mod capture_queue;
+pub mod chapters;
+pub mod core_audio;
+pub mod cursor_track;
+pub mod decoder;
 pub mod delegate;
+pub mod device_capture;
+pub mod disk_space;
+pub mod display_stream;
+pub mod drift;
 pub mod encoder;
+pub mod features;
+pub mod fragment_output;
+pub mod frame_delivery;
+pub mod frame_index;
+pub mod frame_info;
+pub mod frame_stream;
+pub mod idle_stop;
+pub mod keystroke_overlay;
+pub mod output_tap;
+pub mod picker;
+pub mod progress;
+pub mod region;
+pub mod resolution_change;
+pub mod replay;
+pub mod run_loop;
+pub mod secure_input;
+pub mod segments;
+pub mod session;
+pub mod source_watcher;
+pub mod spaces;
 pub mod stream;
 pub mod stream_output;
+pub mod temp_files;
+#[cfg(test)]
+pub mod test_frames;
+pub mod transcode;
 pub mod transcription;
+pub mod trigger;
+pub mod validation;
+pub mod verify;
+pub mod watermark;
+pub mod webcam;
+pub mod diagnostics;
 pub mod objc_bridge_rust;
 
 // Permission management (legacy compatibility)
@@ -33,6 +75,7 @@ pub use types::{DisplayInfo, WindowInfo, RecordingState, SCError};
 pub use recording::RecordingManager;
 pub use filters::{ContentFilter, ContentFilterFactory};
 pub use permissions::PermissionManager;
+pub use diagnostics::Diagnostics;
 
 // Stream output for recording
 pub use stream_output::StreamOutput;
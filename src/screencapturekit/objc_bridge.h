@@ -4,17 +4,28 @@
 #import <Foundation/Foundation.h>
 #import <ScreenCaptureKit/ScreenCaptureKit.h>
 #import <CoreMedia/CoreMedia.h>
+#import <AVFoundation/AVFoundation.h>
 
 NS_ASSUME_NONNULL_BEGIN
 
 // Forward declaration for Rust callback function pointers
 typedef void (*RustVideoCallback)(void* _Nonnull context, CMSampleBufferRef _Nonnull sampleBuffer);
-typedef void (*RustAudioCallback)(void* _Nonnull context, CMSampleBufferRef _Nonnull sampleBuffer);
+typedef void (*RustAudioCallback)(void* _Nonnull context, CMSampleBufferRef _Nonnull sampleBuffer, SCStreamOutputType type);
 typedef void (*RustStreamStoppedCallback)(void* _Nonnull context, NSError* _Nullable error);
 
 // PRODUCTION-READY: Stream capture completion callback
 typedef void (*RustStreamStartCallback)(void* context, NSError* _Nullable error);
 
+// Content sharing picker result: the chosen filter, or nil with an error when the picker
+// failed and nil without one when the user cancelled
+typedef void (*RustPickerCallback)(void* _Nonnull context, SCContentFilter* _Nullable filter, NSError* _Nullable error);
+
+// A fragment of the movie an AVAssetWriter produced (AVAssetSegmentType: 1 initialization, 2 media)
+typedef void (*RustSegmentCallback)(void* _Nonnull context, const void* _Nonnull bytes, size_t length, NSInteger segmentType);
+
+// Body run by sc_try_catch
+typedef void (*RustTryBody)(void* _Nullable context);
+
 // Objective-C delegate bridge that implements SCStreamDelegate
 @interface SCStreamDelegateBridge : NSObject <SCStreamDelegate>
 
@@ -35,6 +46,31 @@ typedef void (*RustStreamStartCallback)(void* context, NSError* _Nullable error)
 
 @end
 
+// Forwards camera frames from an AVCaptureVideoDataOutput to Rust
+@interface CameraOutputBridge : NSObject <AVCaptureVideoDataOutputSampleBufferDelegate>
+
+@property (nonatomic, assign) void* _Nonnull rustContext;
+@property (nonatomic, assign) RustVideoCallback _Nonnull frameCallback;
+
+@end
+
+// Observes the system content sharing picker (macOS 14+) and reports its outcome once
+API_AVAILABLE(macos(14.0))
+@interface ContentPickerObserverBridge : NSObject <SCContentSharingPickerObserver>
+
+@property (nonatomic, assign) void* _Nonnull rustContext;
+@property (nonatomic, assign) RustPickerCallback _Nonnull pickerCallback;
+
+@end
+
+// Hands the fragments of a segmenting AVAssetWriter (macOS 11+) to Rust as they're written
+@interface AssetWriterSegmentBridge : NSObject <AVAssetWriterDelegate>
+
+@property (nonatomic, assign) void* _Nullable rustContext;
+@property (nonatomic, assign) RustSegmentCallback _Nonnull segmentCallback;
+
+@end
+
 // C interface for Rust to create and manage the delegate bridge
 #ifdef __cplusplus
 extern "C" {
@@ -46,7 +82,8 @@ void* _Nullable create_delegate_bridge(void* _Nonnull rust_context,
                            RustAudioCallback _Nonnull audio_callback,
                            RustStreamStoppedCallback _Nonnull stream_stopped_callback);
 
-// Release the delegate bridge
+// Release the delegate bridge; once this returns no callback reaches rust_context, even if
+// a stream still holds the bridge
 void release_delegate_bridge(void* _Nullable bridge);
 
 // PRODUCTION-READY: C interface for proper stream capture
@@ -54,6 +91,31 @@ void start_stream_capture_with_handler(void* stream,
                                      RustStreamStartCallback callback,
                                      void* context);
 
+// Create a camera sample buffer delegate; release it with release_camera_output_bridge
+void* _Nullable create_camera_output_bridge(void* _Nonnull rust_context, RustVideoCallback _Nonnull frame_callback);
+
+// Release the camera sample buffer delegate
+void release_camera_output_bridge(void* _Nullable bridge);
+
+// Create an asset writer delegate that forwards its segment data; set it as the writer's
+// delegate (held weakly) and release it with release_segment_bridge
+void* _Nullable create_segment_bridge(void* _Nonnull rust_context, RustSegmentCallback _Nonnull segment_callback);
+
+// Release the asset writer delegate; no callback reaches rust_context once this returns
+void release_segment_bridge(void* _Nullable bridge);
+
+// Show the system content sharing picker on the main queue, offering `modes`
+// (SCContentSharingPickerMode) and hiding `excluded_bundle_ids`; callback runs once with the
+// outcome. Returns false, without calling back, before macOS 14
+bool present_content_sharing_picker(void* _Nonnull context,
+                                    RustPickerCallback _Nonnull callback,
+                                    NSUInteger modes,
+                                    NSArray<NSString*>* _Nullable excluded_bundle_ids);
+
+// Run body(context) inside @try; returns NULL, or "<name>: <reason>" of the caught
+// Objective-C exception (release it with free)
+char* _Nullable sc_try_catch(RustTryBody _Nonnull body, void* _Nullable context);
+
 #ifdef __cplusplus
 }
 #endif
@@ -1,5 +1,13 @@
 #import "objc_bridge.h"
 #import <os/log.h>
+#import <stdatomic.h>
+#import <unistd.h>
+
+@interface SCStreamDelegateBridge () {
+    atomic_int _callbacksInFlight;
+    atomic_bool _detached;
+}
+@end
 
 @implementation SCStreamDelegateBridge
 
@@ -22,6 +30,9 @@
 #pragma mark - SCStreamDelegate Protocol Implementation
 
 - (void)stream:(SCStream *)stream didOutputSampleBuffer:(CMSampleBufferRef)sampleBuffer ofType:(SCStreamOutputType)type {
+    if (![self enterCallback]) {
+        return; // Released; the stream is still winding down
+    }
     // Determine the type of sample buffer and call appropriate Rust callback
     switch (type) {
         case SCStreamOutputTypeScreen:
@@ -37,7 +48,7 @@
         case SCStreamOutputTypeMicrophone:
             if (self.audioCallback && self.rustContext) {
                 os_log_debug(OS_LOG_DEFAULT, "🔊 Forwarding audio sample buffer to Rust");
-                self.audioCallback(self.rustContext, sampleBuffer);
+                self.audioCallback(self.rustContext, sampleBuffer, type);
             } else {
                 os_log_error(OS_LOG_DEFAULT, "❌ Audio callback or context is NULL");
             }
@@ -47,16 +58,42 @@
             os_log_error(OS_LOG_DEFAULT, "❌ Unknown sample buffer type: %ld", (long)type);
             break;
     }
+    atomic_fetch_sub(&_callbacksInFlight, 1);
 }
 
 - (void)stream:(SCStream *)stream didStopWithError:(NSError *)error {
     os_log(OS_LOG_DEFAULT, "🛑 Stream stopped with error: %@", error);
     
+    if (![self enterCallback]) {
+        return;
+    }
     if (self.streamStoppedCallback && self.rustContext) {
         self.streamStoppedCallback(self.rustContext, error);
     } else {
         os_log_error(OS_LOG_DEFAULT, "❌ Stream stopped callback or context is NULL");
     }
+    atomic_fetch_sub(&_callbacksInFlight, 1);
+}
+
+#pragma mark - Detaching from Rust
+
+// Count a callback in flight; false, without counting it, once the bridge is detached
+- (BOOL)enterCallback {
+    atomic_fetch_add(&_callbacksInFlight, 1);
+    if (atomic_load(&_detached)) {
+        atomic_fetch_sub(&_callbacksInFlight, 1);
+        return NO;
+    }
+    return YES;
+}
+
+// Stop later callbacks reaching Rust and wait for the ones in flight to return. Audio and
+// video arrive on separate queues, so callbacks are counted rather than serialized
+- (void)detach {
+    atomic_store(&_detached, true);
+    while (atomic_load(&_callbacksInFlight) > 0) {
+        usleep(100);
+    }
 }
 
 #pragma mark - PRODUCTION-READY: Stream Capture Helper
@@ -126,7 +163,8 @@ void release_delegate_bridge(void* bridge) {
     if (bridge) {
         os_log(OS_LOG_DEFAULT, "🗑️ Releasing delegate bridge: %p", bridge);
         SCStreamDelegateBridge* objcBridge = (__bridge_transfer SCStreamDelegateBridge*)bridge;
-        (void)objcBridge; // Suppress unused variable warning - ARC will handle deallocation
+        // A stream that hasn't finished stopping may still hold the bridge and call it
+        [objcBridge detach];
     } else {
         os_log_error(OS_LOG_DEFAULT, "❌ Attempted to release NULL delegate bridge");
     }
@@ -147,4 +185,145 @@ void start_stream_capture_with_handler(void* stream,
     [SCStreamDelegateBridge startStreamCapture:scStream 
                                 withCompletion:callback 
                                        context:context];
-} 
\ No newline at end of file
+} 
+
+#pragma mark - Camera output
+
+@implementation CameraOutputBridge
+
+- (void)captureOutput:(AVCaptureOutput *)output didOutputSampleBuffer:(CMSampleBufferRef)sampleBuffer fromConnection:(AVCaptureConnection *)connection {
+    if (self.frameCallback && self.rustContext) {
+        self.frameCallback(self.rustContext, sampleBuffer);
+    }
+}
+
+@end
+
+void* create_camera_output_bridge(void* rust_context, RustVideoCallback frame_callback) {
+    CameraOutputBridge* bridge = [[CameraOutputBridge alloc] init];
+    if (!bridge) {
+        os_log_error(OS_LOG_DEFAULT, "❌ Failed to create camera output bridge");
+        return NULL;
+    }
+    bridge.rustContext = rust_context;
+    bridge.frameCallback = frame_callback;
+    return (__bridge_retained void*)bridge;
+}
+
+void release_camera_output_bridge(void* bridge) {
+    if (bridge) {
+        CameraOutputBridge* objcBridge = (__bridge_transfer CameraOutputBridge*)bridge;
+        (void)objcBridge; // ARC releases it
+    }
+}
+
+#pragma mark - Asset writer segments
+
+@implementation AssetWriterSegmentBridge
+
+- (void)assetWriter:(AVAssetWriter *)writer didOutputSegmentData:(NSData *)segmentData segmentType:(AVAssetSegmentType)segmentType API_AVAILABLE(macos(11.0)) {
+    @synchronized (self) {
+        if (self.rustContext && segmentData.length > 0) {
+            self.segmentCallback(self.rustContext, segmentData.bytes, segmentData.length, segmentType);
+        }
+    }
+}
+
+@end
+
+void* create_segment_bridge(void* rust_context, RustSegmentCallback segment_callback) {
+    AssetWriterSegmentBridge* bridge = [[AssetWriterSegmentBridge alloc] init];
+    if (!bridge) {
+        os_log_error(OS_LOG_DEFAULT, "❌ Failed to create asset writer segment bridge");
+        return NULL;
+    }
+    bridge.rustContext = rust_context;
+    bridge.segmentCallback = segment_callback;
+    return (__bridge_retained void*)bridge;
+}
+
+void release_segment_bridge(void* bridge) {
+    if (bridge) {
+        AssetWriterSegmentBridge* objcBridge = (__bridge_transfer AssetWriterSegmentBridge*)bridge;
+        // A writer still finishing may call in on its own queue until the context is cleared
+        @synchronized (objcBridge) {
+            objcBridge.rustContext = NULL;
+        }
+    }
+}
+
+#pragma mark - Content sharing picker
+
+// The observer being shown; the picker holds its observers weakly
+static ContentPickerObserverBridge* activePickerObserver API_AVAILABLE(macos(14.0)) = nil;
+
+@implementation ContentPickerObserverBridge
+
+- (void)finishWithFilter:(SCContentFilter* _Nullable)filter error:(NSError* _Nullable)error {
+    SCContentSharingPicker* picker = SCContentSharingPicker.sharedPicker;
+    [picker removeObserver:self];
+    picker.active = NO;
+    if (activePickerObserver == self) {
+        activePickerObserver = nil;
+    }
+    self.pickerCallback(self.rustContext, filter, error);
+}
+
+- (void)contentSharingPicker:(SCContentSharingPicker *)picker didCancelForStream:(SCStream * _Nullable)stream {
+    [self finishWithFilter:nil error:nil];
+}
+
+- (void)contentSharingPicker:(SCContentSharingPicker *)picker didUpdateWithFilter:(SCContentFilter *)filter forStream:(SCStream * _Nullable)stream {
+    [self finishWithFilter:filter error:nil];
+}
+
+- (void)contentSharingPickerStartDidFailWithError:(NSError *)error {
+    [self finishWithFilter:nil error:error];
+}
+
+@end
+
+bool present_content_sharing_picker(void* rust_context, RustPickerCallback callback, NSUInteger modes, NSArray<NSString*>* excluded_bundle_ids) {
+    if (@available(macOS 14.0, *)) {
+        NSArray<NSString*>* excluded = [excluded_bundle_ids copy];
+        dispatch_async(dispatch_get_main_queue(), ^{
+            SCContentSharingPicker* picker = SCContentSharingPicker.sharedPicker;
+            if (activePickerObserver) {
+                // Only one caller can own the picker; the earlier one is told it was cancelled
+                [activePickerObserver finishWithFilter:nil error:nil];
+            }
+
+            ContentPickerObserverBridge* observer = [[ContentPickerObserverBridge alloc] init];
+            observer.rustContext = rust_context;
+            observer.pickerCallback = callback;
+            activePickerObserver = observer;
+
+            SCContentSharingPickerConfiguration* configuration = [[SCContentSharingPickerConfiguration alloc] init];
+            configuration.allowedPickerModes = modes;
+            if (excluded) {
+                configuration.excludedBundleIDs = excluded;
+            }
+            picker.defaultConfiguration = configuration;
+            [picker addObserver:observer];
+            picker.active = YES;
+            [picker present];
+        });
+        return true;
+    }
+    return false;
+}
+
+#pragma mark - Exception boundary
+
+char* sc_try_catch(RustTryBody body, void* context) {
+    @try {
+        body(context);
+        return NULL;
+    } @catch (NSException *exception) {
+        NSString *message = [NSString stringWithFormat:@"%@: %@", exception.name, exception.reason ?: @"no reason given"];
+        os_log_error(OS_LOG_DEFAULT, "❌ Caught Objective-C exception: %{public}@", message);
+        return strdup(message.UTF8String);
+    } @catch (id exception) {
+        return strdup("Unknown Objective-C exception");
+    }
+}
@@ -5,6 +5,7 @@ use objc2_foundation::NSError;
 use objc2::runtime::AnyObject;
 
 use super::delegate::RealStreamDelegate;
+use super::types::SCStreamOutputType;
 
 // PRODUCTION: Global counters for blazing fast performance monitoring
 static VIDEO_CALLBACK_COUNT: AtomicU64 = AtomicU64::new(0);
@@ -15,7 +16,7 @@ extern "C" {
     fn create_delegate_bridge(
         rust_context: *mut c_void,
         video_callback: extern "C" fn(*mut c_void, *const CMSampleBuffer),
-        audio_callback: extern "C" fn(*mut c_void, *const CMSampleBuffer),
+        audio_callback: extern "C" fn(*mut c_void, *const CMSampleBuffer, isize),
         stream_stopped_callback: extern "C" fn(*mut c_void, *const NSError),
     ) -> *mut c_void;
     
@@ -31,6 +32,9 @@ extern "C" fn video_callback_bridge(context: *mut c_void, sample_buffer: *const
     unsafe {
         let delegate_ref = &*(context as *const RealStreamDelegate);
         let sample_buffer_ref = &*sample_buffer;
+        if delegate_ref.stream_video_ignored() {
+            return;
+        }
         
         // PRODUCTION: Only log every 300 frames (10 seconds at 30fps)
         let count = VIDEO_CALLBACK_COUNT.fetch_add(1, Ordering::Relaxed);
@@ -43,7 +47,7 @@ extern "C" fn video_callback_bridge(context: *mut c_void, sample_buffer: *const
     }
 }
 
-extern "C" fn audio_callback_bridge(context: *mut c_void, sample_buffer: *const CMSampleBuffer) {
+extern "C" fn audio_callback_bridge(context: *mut c_void, sample_buffer: *const CMSampleBuffer, output_type: isize) {
     if context.is_null() || sample_buffer.is_null() {
         return; // Fast return for production
     }
@@ -59,7 +63,13 @@ extern "C" fn audio_callback_bridge(context: *mut c_void, sample_buffer: *const
         }
         
         // ZERO-COPY: Direct delegate call
-        delegate_ref.handle_audio_sample_buffer(sample_buffer_ref);
+        // SCStreamOutputTypeMicrophone = 2; everything else on this path is system audio
+        let of_type = if output_type == SCStreamOutputType::Microphone as isize {
+            SCStreamOutputType::Microphone
+        } else {
+            SCStreamOutputType::Audio
+        };
+        delegate_ref.handle_audio_sample_buffer(sample_buffer_ref, of_type);
     }
 }
 
@@ -155,6 +165,7 @@ unsafe impl Sync for ObjCDelegateBridge {}
 mod tests {
     use super::*;
     use std::sync::Mutex;
+    use crate::screencapturekit::encoder::VideoEncoderSettings;
     
     #[test]
     fn test_bridge_creation() {
@@ -164,9 +175,7 @@ mod tests {
             let delegate = Arc::new(RealStreamDelegate::new(
                 "test_output".to_string(),
                 is_recording,
-                1920,
-                1080,
-                30,
+                VideoEncoderSettings::default(),
             ));
             
             let bridge = ObjCDelegateBridge::new(delegate);
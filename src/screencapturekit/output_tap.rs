@@ -0,0 +1,354 @@
+// Output device audio tap
+// ScreenCaptureKit records the system audio mix no matter which device plays it. To record
+// what one output receives (a USB interface, or a virtual device like BlackHole) a Core Audio
+// process tap (macOS 14.2) is created on that device and read through a private aggregate
+// device. Its buffers are wrapped in sample buffers, timed on the host clock like
+// ScreenCaptureKit's, and handed to the regular audio path
+
+use std::ffi::c_void;
+use std::ptr;
+use objc2::runtime::{AnyClass, AnyObject};
+use objc2::{msg_send, class};
+use objc2_foundation::{NSArray, NSString};
+use objc2_core_media::{CMSampleBuffer, CMTime};
+use napi::{Result, Status, Error};
+
+use super::core_audio::{
+    property_address, AudioBufferList, AudioStreamBasicDescription, AudioObjectGetPropertyData, AudioObjectGetPropertyDataSize,
+    CMAudioFormatDescriptionCreate, CMAudioFormatDescriptionGetStreamBasicDescription,
+    CMAudioSampleBufferCreateWithPacketDescriptions, CMSampleBufferSetDataBufferFromAudioBufferList,
+    AUDIO_DEVICE_PROPERTY_DEVICE_UID, AUDIO_DEVICE_PROPERTY_STREAMS, AUDIO_HARDWARE_PROPERTY_DEVICES, AUDIO_OBJECT_PROPERTY_NAME,
+    AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL, AUDIO_OBJECT_PROPERTY_SCOPE_OUTPUT, AUDIO_OBJECT_SYSTEM_OBJECT, AUDIO_TAP_PROPERTY_FORMAT,
+};
+use super::features::Feature;
+
+// Private aggregate devices are only visible to this process
+const AGGREGATE_DEVICE_NAME: &str = "rustedscreencapture output tap";
+
+#[repr(C)]
+struct SmpteTime {
+    subframes: i16,
+    subframe_divisor: i16,
+    counter: u32,
+    smpte_type: u32,
+    flags: u32,
+    hours: i16,
+    minutes: i16,
+    seconds: i16,
+    frames: i16,
+}
+
+#[repr(C)]
+struct AudioTimeStamp {
+    sample_time: f64,
+    host_time: u64,
+    rate_scalar: f64,
+    word_clock_time: u64,
+    smpte_time: SmpteTime,
+    flags: u32,
+    reserved: u32,
+}
+
+type AudioDeviceIoProc = extern "C" fn(
+    device: u32,
+    now: *const AudioTimeStamp,
+    input_data: *const AudioBufferList,
+    input_time: *const AudioTimeStamp,
+    output_data: *mut AudioBufferList,
+    output_time: *const AudioTimeStamp,
+    client_data: *mut c_void,
+) -> i32;
+
+extern "C" {
+    fn AudioHardwareCreateAggregateDevice(description: *const c_void, device_id: *mut u32) -> i32;
+    fn AudioHardwareDestroyAggregateDevice(device_id: u32) -> i32;
+    fn AudioDeviceCreateIOProcID(device: u32, proc_: AudioDeviceIoProc, client_data: *mut c_void, proc_id: *mut *mut c_void) -> i32;
+    fn AudioDeviceDestroyIOProcID(device: u32, proc_id: *mut c_void) -> i32;
+    fn AudioDeviceStart(device: u32, proc_id: *mut c_void) -> i32;
+    fn AudioDeviceStop(device: u32, proc_id: *mut c_void) -> i32;
+
+    fn CMClockMakeHostTimeFromSystemUnits(host_time: u64) -> CMTime;
+    fn CFRelease(cf: *const c_void);
+    fn dlsym(handle: *mut c_void, symbol: *const std::ffi::c_char) -> *mut c_void;
+}
+
+// RTLD_DEFAULT on macOS
+const RTLD_DEFAULT: *mut c_void = -2isize as *mut c_void;
+
+type CreateProcessTap = unsafe extern "C" fn(description: *mut AnyObject, tap_id: *mut u32) -> i32;
+type DestroyProcessTap = unsafe extern "C" fn(tap_id: u32) -> i32;
+
+/// AudioHardwareCreateProcessTap and AudioHardwareDestroyProcessTap, looked up at runtime:
+/// linking them directly would keep the addon from loading before macOS 14.2
+fn process_tap_functions() -> Option<(CreateProcessTap, DestroyProcessTap)> {
+    unsafe {
+        let create = dlsym(RTLD_DEFAULT, c"AudioHardwareCreateProcessTap".as_ptr());
+        let destroy = dlsym(RTLD_DEFAULT, c"AudioHardwareDestroyProcessTap".as_ptr());
+        if create.is_null() || destroy.is_null() {
+            return None;
+        }
+        Some((
+            std::mem::transmute::<*mut c_void, CreateProcessTap>(create),
+            std::mem::transmute::<*mut c_void, DestroyProcessTap>(destroy),
+        ))
+    }
+}
+
+/// An output-capable audio device as (UID, name)
+pub type OutputDevice = (String, String);
+
+/// Whether process taps, and so output device selection, are available
+pub fn output_taps_supported() -> bool {
+    Feature::OutputDeviceTap.is_available() && process_tap_functions().is_some() && AnyClass::get(c"CATapDescription").is_some()
+}
+
+/// Every device with output streams, including aggregate and virtual ones; unlike the
+/// current route this also lists devices nothing is playing to
+pub fn output_devices() -> Vec<OutputDevice> {
+    unsafe {
+        let address = property_address(AUDIO_HARDWARE_PROPERTY_DEVICES, AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL);
+        let mut size = 0u32;
+        if AudioObjectGetPropertyDataSize(AUDIO_OBJECT_SYSTEM_OBJECT, &address, 0, ptr::null(), &mut size) != 0 {
+            return Vec::new();
+        }
+        let mut device_ids = vec![0u32; size as usize / std::mem::size_of::<u32>()];
+        if AudioObjectGetPropertyData(AUDIO_OBJECT_SYSTEM_OBJECT, &address, 0, ptr::null(), &mut size, device_ids.as_mut_ptr() as *mut c_void) != 0 {
+            return Vec::new();
+        }
+        device_ids.truncate(size as usize / std::mem::size_of::<u32>());
+
+        device_ids.into_iter()
+            .filter(|&device| {
+                let streams = property_address(AUDIO_DEVICE_PROPERTY_STREAMS, AUDIO_OBJECT_PROPERTY_SCOPE_OUTPUT);
+                let mut size = 0u32;
+                AudioObjectGetPropertyDataSize(device, &streams, 0, ptr::null(), &mut size) == 0 && size > 0
+            })
+            .filter_map(|device| {
+                let uid = string_property(device, AUDIO_DEVICE_PROPERTY_DEVICE_UID)?;
+                let name = string_property(device, AUDIO_OBJECT_PROPERTY_NAME).unwrap_or_else(|| uid.clone());
+                Some((uid, name))
+            })
+            .collect()
+    }
+}
+
+/// Check that `device_uid` names an enumerated output device and taps can be created
+pub fn check_output_device(device_uid: &str) -> Result<()> {
+    Feature::OutputDeviceTap.require()?;
+    if !output_taps_supported() {
+        return Err(Error::new(Status::GenericFailure, "Core Audio process taps are unavailable; audioOutputDeviceId can't be used"));
+    }
+    let devices = output_devices();
+    if !devices.iter().any(|(uid, _)| uid == device_uid) {
+        let known: Vec<String> = devices.iter().map(|(uid, name)| format!("{} ({})", uid, name)).collect();
+        return Err(Error::new(
+            Status::InvalidArg,
+            format!("audioOutputDeviceId {} is not an output device; available: {}", device_uid, known.join(", ")),
+        ));
+    }
+    Ok(())
+}
+
+/// Read a CFString property of an audio object
+unsafe fn string_property(object: u32, selector: u32) -> Option<String> {
+    let address = property_address(selector, AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL);
+    let mut value: *const NSString = ptr::null();
+    let mut size = std::mem::size_of::<*const NSString>() as u32;
+    let status = AudioObjectGetPropertyData(object, &address, 0, ptr::null(), &mut size, &mut value as *mut *const NSString as *mut c_void);
+    if status != 0 || value.is_null() {
+        return None;
+    }
+    // CFString is toll-free bridged to NSString; the property hands out a retained copy
+    let string = (*value).to_string();
+    CFRelease(value as *const c_void);
+    Some(string)
+}
+
+/// Receives each tapped buffer on the device's IO thread
+pub type TapBufferHandler = Box<dyn Fn(&CMSampleBuffer) + Send + Sync>;
+
+struct TapContext {
+    format_description: *mut c_void,
+    on_buffer: TapBufferHandler,
+}
+
+/// Running tap on one output device; dropping it stops the tap and tears the devices down
+pub struct OutputTap {
+    tap_id: u32,
+    destroy_process_tap: DestroyProcessTap,
+    aggregate_id: u32,
+    proc_id: *mut c_void,
+    context: *mut TapContext,
+}
+
+// Safety: the Core Audio objects are only started and stopped from start and Drop
+unsafe impl Send for OutputTap {}
+unsafe impl Sync for OutputTap {}
+
+impl OutputTap {
+    /// Tap the output device with `device_uid`; `on_buffer` gets its audio as sample buffers
+    pub fn start(device_uid: &str, on_buffer: TapBufferHandler) -> Result<Self> {
+        println!("🔈 Tapping audio output device {}", device_uid);
+        let Some((create_process_tap, destroy_process_tap)) = process_tap_functions().filter(|_| output_taps_supported()) else {
+            return Err(Error::new(Status::GenericFailure, "audioOutputDeviceId requires macOS 14.2 or later"));
+        };
+
+        let mut tap = Self { tap_id: 0, destroy_process_tap, aggregate_id: 0, proc_id: ptr::null_mut(), context: ptr::null_mut() };
+        // From here on Drop releases whatever was created on every error path
+        unsafe {
+            let tap_uuid = tap.create_process_tap(create_process_tap, device_uid)?;
+            tap.create_aggregate_device(device_uid, &tap_uuid)?;
+
+            let address = property_address(AUDIO_TAP_PROPERTY_FORMAT, AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL);
+            let mut format = AudioStreamBasicDescription::default();
+            let mut size = std::mem::size_of::<AudioStreamBasicDescription>() as u32;
+            let status = AudioObjectGetPropertyData(tap.tap_id, &address, 0, ptr::null(), &mut size, &mut format as *mut _ as *mut c_void);
+            if status != 0 {
+                return Err(osstatus_error("read the tap format", status));
+            }
+            let mut format_description: *mut c_void = ptr::null_mut();
+            let status = CMAudioFormatDescriptionCreate(ptr::null(), &format, 0, ptr::null(), 0, ptr::null(), ptr::null(), &mut format_description);
+            if status != 0 {
+                return Err(osstatus_error("describe the tap format", status));
+            }
+            tap.context = Box::into_raw(Box::new(TapContext { format_description, on_buffer }));
+
+            let status = AudioDeviceCreateIOProcID(tap.aggregate_id, tap_io_proc, tap.context as *mut c_void, &mut tap.proc_id);
+            if status != 0 {
+                return Err(osstatus_error("create the tap IO proc", status));
+            }
+            let status = AudioDeviceStart(tap.aggregate_id, tap.proc_id);
+            if status != 0 {
+                return Err(osstatus_error("start the tap", status));
+            }
+            println!("✅ Output tap running: {:.0} Hz, {} channels", format.sample_rate, format.channels_per_frame);
+        }
+        Ok(tap)
+    }
+
+    /// Create a private tap of every process's audio on the device; returns the tap's UUID
+    unsafe fn create_process_tap(&mut self, create_process_tap: CreateProcessTap, device_uid: &str) -> Result<String> {
+        let no_processes: *mut NSArray = msg_send![class!(NSArray), array];
+        let device = NSString::from_str(device_uid);
+        let alloc: *mut AnyObject = msg_send![class!(CATapDescription), alloc];
+        let description: *mut AnyObject = msg_send![
+            alloc,
+            initExcludingProcesses: no_processes,
+            andDeviceUID: &*device,
+            withStream: 0isize
+        ];
+        if description.is_null() {
+            return Err(Error::new(Status::GenericFailure, "Failed to create the tap description"));
+        }
+        let _: () = msg_send![description, setPrivate: true];
+        let uuid: *mut AnyObject = msg_send![description, UUID];
+        let uuid_string: *mut NSString = msg_send![uuid, UUIDString];
+        let tap_uuid = (*uuid_string).to_string();
+
+        let status = create_process_tap(description, &mut self.tap_id);
+        let _: () = msg_send![description, release];
+        if status != 0 {
+            return Err(osstatus_error("create the process tap (is audio capture permission granted?)", status));
+        }
+        Ok(tap_uuid)
+    }
+
+    /// Wrap the device and its tap in a private aggregate device that can be read from
+    unsafe fn create_aggregate_device(&mut self, device_uid: &str, tap_uuid: &str) -> Result<()> {
+        let dictionary = |entries: &[(&str, *mut AnyObject)]| -> *mut AnyObject {
+            let dictionary: *mut AnyObject = msg_send![class!(NSMutableDictionary), dictionary];
+            for &(key, value) in entries {
+                let key = NSString::from_str(key);
+                let _: () = msg_send![dictionary, setObject: value, forKey: &*key];
+            }
+            dictionary
+        };
+        let string = |value: &str| -> *mut AnyObject { msg_send![class!(NSString), stringWithString: &*NSString::from_str(value)] };
+        let number = |value: i32| -> *mut AnyObject { msg_send![class!(NSNumber), numberWithInt: value] };
+        let array = |object: *mut AnyObject| -> *mut AnyObject { msg_send![class!(NSArray), arrayWithObject: object] };
+
+        let aggregate_uid = format!("rustedscreencapture.tap.{}", tap_uuid);
+        // Keys are the kAudioAggregateDevice*, kAudioSubDevice* and kAudioSubTap* constants
+        let description = dictionary(&[
+            ("uid", string(&aggregate_uid)),
+            ("name", string(AGGREGATE_DEVICE_NAME)),
+            ("private", number(1)),
+            ("master", string(device_uid)),
+            ("subdevices", array(dictionary(&[("uid", string(device_uid))]))),
+            ("taps", array(dictionary(&[("uid", string(tap_uuid)), ("drift", number(1))]))),
+            ("tapautostart", number(1)),
+        ]);
+
+        let status = AudioHardwareCreateAggregateDevice(description as *const c_void, &mut self.aggregate_id);
+        if status != 0 {
+            return Err(osstatus_error("create the aggregate device", status));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OutputTap {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.proc_id.is_null() {
+                AudioDeviceStop(self.aggregate_id, self.proc_id);
+                AudioDeviceDestroyIOProcID(self.aggregate_id, self.proc_id);
+            }
+            if self.aggregate_id != 0 {
+                AudioHardwareDestroyAggregateDevice(self.aggregate_id);
+            }
+            if self.tap_id != 0 {
+                (self.destroy_process_tap)(self.tap_id);
+            }
+            // The IO proc is gone, so nothing can be using the context any more
+            if !self.context.is_null() {
+                let context = Box::from_raw(self.context);
+                CFRelease(context.format_description);
+            }
+        }
+        println!("🛑 Output tap stopped");
+    }
+}
+
+fn osstatus_error(action: &str, status: i32) -> Error {
+    Error::new(Status::GenericFailure, format!("Failed to {} (OSStatus {})", action, status))
+}
+
+extern "C" fn tap_io_proc(
+    _device: u32,
+    _now: *const AudioTimeStamp,
+    input_data: *const AudioBufferList,
+    input_time: *const AudioTimeStamp,
+    _output_data: *mut AudioBufferList,
+    _output_time: *const AudioTimeStamp,
+    client_data: *mut c_void,
+) -> i32 {
+    unsafe {
+        let context = &*(client_data as *const TapContext);
+        if input_data.is_null() || input_time.is_null() || (*input_data).number_buffers == 0 {
+            return 0;
+        }
+        let format = CMAudioFormatDescriptionGetStreamBasicDescription(context.format_description);
+        let bytes_per_frame = if format.is_null() { 0 } else { (*format).bytes_per_frame };
+        let frames = if bytes_per_frame == 0 { 0 } else { (*input_data).buffers[0].data_byte_size / bytes_per_frame };
+        if frames == 0 {
+            return 0;
+        }
+
+        let presentation_time = CMClockMakeHostTimeFromSystemUnits((*input_time).host_time);
+        let mut sample_buffer: *mut CMSampleBuffer = ptr::null_mut();
+        let status = CMAudioSampleBufferCreateWithPacketDescriptions(
+            ptr::null(), ptr::null(), 0, ptr::null(), ptr::null(),
+            context.format_description, frames as isize, presentation_time, ptr::null(), &mut sample_buffer,
+        );
+        if status != 0 || sample_buffer.is_null() {
+            return 0;
+        }
+        // Copies the data, which is only valid during this callback
+        if CMSampleBufferSetDataBufferFromAudioBufferList(sample_buffer, ptr::null(), ptr::null(), 0, input_data) == 0 {
+            (context.on_buffer)(&*sample_buffer);
+        }
+        CFRelease(sample_buffer as *const c_void);
+    }
+    0
+}
@@ -188,7 +188,10 @@ impl PermissionManager {
         println!("🚨 ScreenCaptureKit Error: {}", error_description);
         
         // Analyze error and provide specific recovery steps
-        let recovery_action = if error_description.contains("permission") || error_description.contains("access") {
+        // describe_nserror names SCStreamErrorDomain codes; these two mean permission is gone
+        let recovery_action = if error_description.contains("userDeclined") || error_description.contains("missingEntitlements") {
+            "Screen recording permission was revoked or never granted. Re-enable it in System Settings > Privacy & Security > Screen Recording, then restart the application."
+        } else if error_description.contains("permission") || error_description.contains("access") {
             "Permission issue detected. Please check screen recording permissions."
         } else if error_description.contains("content") || error_description.contains("filter") {
             "Content filter issue. Try selecting a different screen or window."
@@ -249,10 +252,24 @@ impl PermissionManager {
     }
     
     /// Check if ScreenCaptureKit framework is available
-    fn check_screencapturekit_availability() -> bool {
-        // Try to get the SCShareableContent class - if it exists, ScreenCaptureKit is available
-        // On macOS 10.15+ this should always be true
-        true // Simplified check since we're targeting macOS 10.15+
+    pub fn check_screencapturekit_availability() -> bool {
+        // If the SCShareableContent class is registered, ScreenCaptureKit is available
+        objc2::runtime::AnyClass::get(c"SCShareableContent").is_some()
+    }
+    
+    /// Get the macOS product version (e.g. "14.5"), if it can be determined
+    pub fn get_macos_version() -> Option<String> {
+        let output = Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .ok()?;
+        
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if version.is_empty() {
+            None
+        } else {
+            Some(version)
+        }
     }
     
     /// Get detailed permission status report
@@ -70,7 +70,10 @@ impl PermissionManager {
         println!("🚨 ScreenCaptureKit Error: {}", error_description);
         
         // Analyze error and provide specific recovery steps
-        let recovery_action = if error_description.contains("permission") || error_description.contains("access") {
+        // describe_nserror names SCStreamErrorDomain codes; these two mean permission is gone
+        let recovery_action = if error_description.contains("userDeclined") || error_description.contains("missingEntitlements") {
+            "Screen recording permission was revoked or never granted. Re-enable it in System Settings > Privacy & Security > Screen Recording, then restart the application."
+        } else if error_description.contains("permission") || error_description.contains("access") {
             "Permission issue detected. Please check screen recording permissions."
         } else if error_description.contains("content") || error_description.contains("filter") {
             "Content filter issue. Try selecting a different screen or window."
@@ -0,0 +1,161 @@
+// System content sharing picker
+// macOS 14 ships SCContentSharingPicker, the system UI for choosing what to share. The app
+// never sees the list of windows and displays, only the one the user picked, which makes it
+// the privacy-friendly alternative to listing sources. The picker reports its choice as a
+// content filter; macOS 15.2 lets the filter name the display or window it captures, so the
+// choice can be turned back into a ScreenSource id
+
+use std::ffi::c_void;
+use napi::{Error, Result, Status};
+use objc2::runtime::AnyObject;
+use objc2::msg_send;
+use objc2_foundation::{NSArray, NSError, NSString};
+use tokio::sync::oneshot;
+
+use super::types::SCContentFilter;
+use super::features::Feature;
+use super::foundation::describe_nserror;
+use super::run_loop::with_run_loop;
+
+// SCContentSharingPickerMode bits
+const PICKER_MODE_SINGLE_WINDOW: usize = 1 << 0;
+const PICKER_MODE_SINGLE_DISPLAY: usize = 1 << 2;
+
+// SCShareableContentStyle values
+const CONTENT_STYLE_WINDOW: isize = 1;
+const CONTENT_STYLE_DISPLAY: isize = 2;
+
+extern "C" {
+    fn present_content_sharing_picker(
+        context: *mut c_void,
+        callback: extern "C" fn(*mut c_void, *mut SCContentFilter, *mut NSError),
+        modes: usize,
+        excluded_bundle_ids: *mut NSArray<NSString>,
+    ) -> bool;
+    fn CFRetain(cf: *const c_void) -> *const c_void;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// What the picker offers
+#[derive(Debug, Clone, Default)]
+pub struct PickerOptions {
+    /// Offer single windows
+    pub windows: bool,
+    /// Offer whole displays
+    pub displays: bool,
+    /// Apps whose windows are left out of the picker
+    pub excluded_bundle_ids: Vec<String>,
+}
+
+/// What the user picked
+#[derive(Debug, Clone, PartialEq)]
+pub enum PickedContent {
+    Display(Option<u32>),
+    Window(Option<u32>),
+}
+
+/// A content filter from the picker, retained until dropped
+struct PickedFilter(*mut SCContentFilter);
+
+// Safety: the filter is immutable once the picker hands it over
+unsafe impl Send for PickedFilter {}
+
+impl Drop for PickedFilter {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0 as *const c_void) };
+    }
+}
+
+type PickerResult = std::result::Result<Option<PickedFilter>, String>;
+
+/// Show the system picker and wait for the user; None when they cancel
+pub async fn present_system_picker(options: &PickerOptions) -> Result<Option<PickedContent>> {
+    Feature::SystemPicker.require()
+        .map_err(|error| Error::new(error.status, format!("{}; list sources with getAvailableScreens instead", error.reason)))?;
+
+    let mut modes = 0;
+    if options.windows {
+        modes |= PICKER_MODE_SINGLE_WINDOW;
+    }
+    if options.displays {
+        modes |= PICKER_MODE_SINGLE_DISPLAY;
+    }
+    if modes == 0 {
+        return Err(Error::new(Status::InvalidArg, "The picker must offer windows, displays or both"));
+    }
+
+    println!("🪄 Presenting the system content picker");
+    let (sender, receiver) = oneshot::channel::<PickerResult>();
+    let context = Box::into_raw(Box::new(sender)) as *mut c_void;
+    let presented = unsafe {
+        let excluded: Vec<_> = options.excluded_bundle_ids.iter().map(|id| NSString::from_str(id)).collect();
+        let excluded: Vec<*const NSString> = excluded.iter().map(|id| &**id as *const NSString).collect();
+        let excluded: *mut NSArray<NSString> = msg_send![
+            objc2::class!(NSArray),
+            arrayWithObjects: excluded.as_ptr(),
+            count: excluded.len()
+        ];
+        present_content_sharing_picker(context, picker_callback, modes, excluded)
+    };
+    if !presented {
+        drop(unsafe { Box::from_raw(context as *mut oneshot::Sender<PickerResult>) });
+        return Err(Error::new(Status::GenericFailure, "The system content picker is unavailable"));
+    }
+
+    // The picker lives on the main queue, which only runs while its run loop spins
+    let picked = with_run_loop(receiver).await
+        .map_err(|_| Error::new(Status::GenericFailure, "Internal channel error"))?
+        .map_err(|e| Error::new(Status::GenericFailure, format!("System content picker failed: {}", e)))?;
+
+    let Some(filter) = picked else {
+        println!("🚫 System content picker cancelled");
+        return Ok(None);
+    };
+    let content = unsafe { picked_content(filter.0) }
+        .ok_or_else(|| Error::new(Status::GenericFailure, "The picker returned content that isn't a window or display"))?;
+    println!("✅ Picked {:?} from the system content picker", content);
+    Ok(Some(content))
+}
+
+/// Called once on the main queue with the picker's outcome
+extern "C" fn picker_callback(context: *mut c_void, filter: *mut SCContentFilter, error: *mut NSError) {
+    let sender = unsafe { Box::from_raw(context as *mut oneshot::Sender<PickerResult>) };
+    let result = if !error.is_null() {
+        Err(unsafe { describe_nserror(error) })
+    } else if filter.is_null() {
+        Ok(None)
+    } else {
+        unsafe { CFRetain(filter as *const c_void) };
+        Ok(Some(PickedFilter(filter)))
+    };
+    let _ = sender.send(result);
+}
+
+/// What a picked filter captures; the display or window id is only known on macOS 15.2+
+unsafe fn picked_content(filter: *mut SCContentFilter) -> Option<PickedContent> {
+    let style: isize = msg_send![filter, style];
+    let names_sources = Feature::PickerSourceIds.is_available();
+    match style {
+        CONTENT_STYLE_WINDOW => {
+            let windows: *mut NSArray<AnyObject> = if names_sources { msg_send![filter, includedWindows] } else { std::ptr::null_mut() };
+            Some(PickedContent::Window(first_object(windows).map(|window| msg_send![window, windowID])))
+        }
+        CONTENT_STYLE_DISPLAY => {
+            let displays: *mut NSArray<AnyObject> = if names_sources { msg_send![filter, includedDisplays] } else { std::ptr::null_mut() };
+            Some(PickedContent::Display(first_object(displays).map(|display| msg_send![display, displayID])))
+        }
+        _ => None,
+    }
+}
+
+/// The first element of an NSArray, None when it's null or empty
+unsafe fn first_object(array: *mut NSArray<AnyObject>) -> Option<*mut AnyObject> {
+    if array.is_null() {
+        return None;
+    }
+    let count: usize = msg_send![array, count];
+    if count == 0 {
+        return None;
+    }
+    Some(msg_send![array, objectAtIndex: 0usize])
+}
@@ -0,0 +1,179 @@
+// Recording progress reporting
+// Polls the delegate's frame counter while recording and reports elapsed time, plus a
+// determinate percentage when the recording has a maximum duration. A start delay is
+// counted down first, one event per second. Reports are paced in time (progressIntervalMs),
+// not in frames, so a 120fps capture doesn't flood the callback and a 5fps one still reports
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often progress is reported without progressIntervalMs
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+// Granularity of the stop check inside a report interval
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Reported to the progress callback
+#[derive(Debug, Clone)]
+pub struct ProgressEvent {
+    pub frames: u64,
+    pub elapsed_seconds: f64,
+    /// 0-100 when a maximum duration is set
+    pub progress: Option<f64>,
+    /// True once the maximum duration is reached and the recording is being stopped
+    pub finished: bool,
+    /// Whole seconds left of the start delay; None once the recording has begun
+    pub countdown: Option<u32>,
+}
+
+/// Percentage of `max_duration_seconds` covered by `elapsed_seconds`, capped at 100
+pub fn progress_percent(elapsed_seconds: f64, max_duration_seconds: Option<u32>) -> Option<f64> {
+    let max = max_duration_seconds.filter(|max| *max > 0)? as f64;
+    Some((elapsed_seconds / max * 100.0).clamp(0.0, 100.0))
+}
+
+/// Frame rate over consecutive windows of a fixed length, for reports that come at the same
+/// pace whatever the capture's fps
+#[derive(Debug, Clone)]
+pub struct RateWindow {
+    interval: Duration,
+    opened: Instant,
+    frames_at_open: u64,
+}
+
+impl RateWindow {
+    pub fn new(interval: Duration, now: Instant) -> Self {
+        Self { interval, opened: now, frames_at_open: 0 }
+    }
+
+    /// With `frames` counted by `now`: the frame rate of the window once `interval` has
+    /// passed, opening the next one; None until then
+    pub fn observe(&mut self, frames: u64, now: Instant) -> Option<f64> {
+        let elapsed = now.saturating_duration_since(self.opened);
+        if elapsed < self.interval || elapsed.is_zero() {
+            return None;
+        }
+        let fps = frames.saturating_sub(self.frames_at_open) as f64 / elapsed.as_secs_f64();
+        self.opened = now;
+        self.frames_at_open = frames;
+        Some(fps)
+    }
+}
+
+/// Reports progress on a background thread; dropping it stops the thread
+pub struct ProgressMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ProgressMonitor {
+    /// Start reporting; until `started` the start delay is counted down. Elapsed time is
+    /// `frames / fps` so it tracks what was recorded; with `wall_clock` set (change-driven
+    /// capture, where frames are sparse) it is the time since `started` instead. An event
+    /// comes every `interval`; reporting ends after the `finished` event
+    pub fn start<F>(
+        frame_count: Arc<Mutex<u64>>,
+        fps: u32,
+        wall_clock: bool,
+        max_duration_seconds: Option<u32>,
+        started: Instant,
+        interval: Duration,
+        on_event: F,
+    ) -> Self
+    where
+        F: Fn(ProgressEvent) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let fps = fps.max(1) as f64;
+
+        let thread = std::thread::spawn(move || {
+            loop {
+                let remaining = started.saturating_duration_since(Instant::now());
+                if remaining.is_zero() || thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let countdown = remaining.as_secs_f64().ceil() as u32;
+                println!("⏳ Recording starts in {}s", countdown);
+                on_event(ProgressEvent {
+                    frames: 0,
+                    elapsed_seconds: 0.0,
+                    progress: progress_percent(0.0, max_duration_seconds),
+                    finished: false,
+                    countdown: Some(countdown),
+                });
+                // Wake when the next whole second is left
+                wait(&thread_stop, remaining - Duration::from_secs(countdown as u64 - 1));
+            }
+
+            while !thread_stop.load(Ordering::SeqCst) {
+                let frames = frame_count.lock().map(|count| *count).unwrap_or(0);
+                let elapsed_seconds = if wall_clock {
+                    started.elapsed().as_secs_f64()
+                } else {
+                    frames as f64 / fps
+                };
+                let progress = progress_percent(elapsed_seconds, max_duration_seconds);
+                let finished = progress.is_some_and(|progress| progress >= 100.0);
+
+                on_event(ProgressEvent { frames, elapsed_seconds, progress, finished, countdown: None });
+                if finished {
+                    println!("⏱️ Maximum duration reached after {} frames; stopping recording", frames);
+                    return;
+                }
+
+                wait(&thread_stop, interval);
+            }
+        });
+
+        Self { stop, thread: Some(thread) }
+    }
+}
+
+/// Sleep for `duration`, returning early once `stop` is set
+fn wait(stop: &AtomicBool, duration: Duration) {
+    let until = Instant::now() + duration;
+    while !stop.load(Ordering::SeqCst) {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return;
+        }
+        std::thread::sleep(left.min(STOP_CHECK_INTERVAL));
+    }
+}
+
+impl Drop for ProgressMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            // The finished callback may be what is dropping us; never join our own thread
+            if thread.thread().id() != std::thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_reports_follow_the_clock_not_the_frame_count() {
+        let reports = |fps: u64, seconds: u64| {
+            let start = Instant::now();
+            let mut window = RateWindow::new(DEFAULT_PROGRESS_INTERVAL, start);
+            let rates: Vec<f64> = (1..=fps * seconds)
+                .filter_map(|frame| window.observe(frame, start + Duration::from_secs_f64(frame as f64 / fps as f64)))
+                .collect();
+            rates
+        };
+        let fast = reports(120, 2);
+        assert_eq!(fast.len(), 4, "120fps reports every 500ms, not every 30 frames");
+        assert!(fast.iter().all(|fps| (fps - 120.0).abs() < 0.5));
+        let slow = reports(5, 2);
+        assert_eq!(slow.len(), 3, "5fps still reports with each frame past 500ms, not once per 6s");
+        assert!(slow.iter().all(|fps| (fps - 5.0).abs() < 0.5));
+    }
+}
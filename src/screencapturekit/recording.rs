@@ -2,25 +2,106 @@
 // This module provides the main recording API and orchestrates the recording process
 
 use napi::{Result, Status, Error};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::oneshot;
+use objc2::runtime::AnyObject;
+use objc2_foundation::NSError;
+use objc2_core_media::kCMTimeZero;
 
-use crate::RecordingConfiguration;
+use crate::{RecordingConfiguration, CaptureIndicatorState, EffectiveSettings, FrameRect};
 use super::types::*;
-use super::content::{AsyncContentManager, ShareableContent};
-use super::filters::{ContentFilter, ContentFilterFactory};
-use super::bindings::ScreenCaptureKitAPI;
-use super::permissions::PermissionManager;
-use super::delegate::RealStreamDelegate;
-use super::stream_output::StreamOutput;
+use super::audio::AudioManager;
+use super::content::ShareableContent;
+use super::filters::{ContentFilter, ContentFilterFactory, display_at, spanned_display_ids};
+use super::bindings::{ScreenCaptureKitAPI, ScreenCaptureBackend};
+use super::foundation::{describe_nserror, CoreGraphicsHelpers, CGRect, CGPoint, CGSize};
+use super::delegate::{RealStreamDelegate, StreamStopListener};
+use super::resolution_change::NewSegmentListener;
+use super::disk_space::{
+    self, DiskSpaceEvent, DiskSpaceMonitor,
+    DEFAULT_LOW_DISK_SPACE_WARNING_MB, DEFAULT_MIN_FREE_DISK_SPACE_MB, DEFAULT_EXPECTED_DURATION_SECONDS,
+};
+use super::keystroke_overlay::KeystrokeOverlay;
+use super::watermark::{Watermark, WatermarkPlacement, WatermarkSource};
+use super::webcam::{WebcamOverlay, DEFAULT_WEBCAM_SIZE};
+use super::frame_stream::{FrameBatcher, FrameListener, FrameFormat, RetainedPixelBuffer};
+use super::cursor_track::{self, CursorTracker, CursorTrackGeometry};
+use super::frame_index;
+use super::display_stream::DisplayStream;
+use super::verify;
+use super::chapters::{self, DEFAULT_CHAPTER_SENSITIVITY};
+use super::buffer_check::ExpectedBuffer;
+use super::progress::{ProgressEvent, ProgressMonitor, DEFAULT_PROGRESS_INTERVAL};
+use super::secure_input::SecureInputMonitor;
+use super::idle_stop::IdleStopMonitor;
+use super::output_tap::{self, OutputTap};
+use super::features::Feature;
+use super::frame_delivery::{FrameDeliveryCheck, received_within, FRAME_DELIVERY_GRACE, FRAME_DELIVERY_WINDOW};
+use super::region::{plan_region, RegionCompositor, RegionSlice, RegionSliceBridge};
+use super::segments::SegmentInfo;
+use super::fragment_output::{FragmentSink, FragmentTarget};
+use super::encoder::{VideoEncoderSettings, container_output_path, target_size_bitrate_kbps};
+use super::stream_output::{StreamOutput, create_output_directory};
 use super::objc_bridge_rust::ObjCDelegateBridge;
+use super::run_loop::with_run_loop;
+use super::validation::validate_recording_configuration;
 
 // Add the constant
 pub const kCVPixelFormatType_32BGRA: u32 = 1111970369; // 'BGRA'
 
+/// Receives low and exhausted disk space events while recording
+pub type DiskSpaceListener = Arc<dyn Fn(DiskSpaceEvent) + Send + Sync>;
+
+/// Receives progress while recording, including the event that ends a timed recording
+pub type ProgressListener = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
+
+/// Receives the diagnostic for a recording whose stream never delivered a frame
+pub type FrameDeliveryListener = Arc<dyn Fn(String) + Send + Sync>;
+
+/// Told when secure input turns on (true) or off (false) while recording
+pub type SecureInputListener = Arc<dyn Fn(bool) + Send + Sync>;
+
+/// Told when the screen stayed unchanged for idleStopSeconds; it's expected to stop the recording
+pub type IdleStopListener = Arc<dyn Fn() + Send + Sync>;
+
+/// How long stopping waits for ScreenCaptureKit's confirmation unless stopTimeoutMs is set
+pub const DEFAULT_STOP_TIMEOUT_MS: u32 = 5000;
+
+/// Streams of a region recording besides the first slice's, which is `RecordingManager::stream`
+struct RegionCapture {
+    region: GlobalRect,
+    // One per slice, including the first
+    bridges: Vec<RegionSliceBridge>,
+    // Filters and streams of the other slices, in slice order
+    filters: Vec<ContentFilter>,
+    secondary_streams: Vec<*mut SCStream>,
+}
+
+/// What a content filter kept warm was created for; a recording asking for the same reuses it
+#[derive(Debug, Clone, PartialEq)]
+struct WarmFilterKey {
+    target: CaptureTarget,
+    exclude_self: bool,
+    excluded_audio_bundle_ids: Vec<String>,
+}
+
+impl WarmFilterKey {
+    fn new(target: CaptureTarget, config: &RecordingConfiguration) -> Self {
+        Self {
+            target,
+            exclude_self: config.exclude_self.unwrap_or(false),
+            excluded_audio_bundle_ids: config.exclude_audio_app_bundle_ids.clone().unwrap_or_default(),
+        }
+    }
+}
+
 /// High-level async recording manager
 pub struct RecordingManager {
+    backend: Arc<dyn ScreenCaptureBackend>,
     stream: Option<*mut SCStream>,
+    // Whether `stream` has been started and not stopped; starting it again throws
+    capture_running: bool,
     content_filter: Option<ContentFilter>,
     delegate: Option<Arc<RealStreamDelegate>>,
     delegate_bridge: Option<Arc<ObjCDelegateBridge>>,
@@ -29,6 +110,41 @@ pub struct RecordingManager {
     recording_config: Option<RecordingConfiguration>,
     output_path: Option<String>,
     shareable_content: Option<ShareableContent>,
+    started_at: Option<Instant>,
+    timeline: Option<Timeline>,
+    paused_since: Option<f64>,
+    last_recording_stats: Option<RecordingStats>,
+    last_error: Option<RecordingFailure>,
+    // NSError description of the failure being reported, picked up by track_failure
+    underlying_error: Option<String>,
+    disk_space_monitor: Option<DiskSpaceMonitor>,
+    disk_space_listener: Option<DiskSpaceListener>,
+    progress_monitor: Option<ProgressMonitor>,
+    progress_listener: Option<ProgressListener>,
+    frame_delivery_check: Option<FrameDeliveryCheck>,
+    // CGDisplayStream capture, with captureBackend "cgdisplaystream" or after "auto" fell back
+    display_stream: Arc<Mutex<Option<DisplayStream>>>,
+    frame_delivery_listener: Option<FrameDeliveryListener>,
+    secure_input_monitor: Option<SecureInputMonitor>,
+    /// Records system audio from the configured output device instead of ScreenCaptureKit
+    output_tap: Option<OutputTap>,
+    secure_input_listener: Option<SecureInputListener>,
+    idle_stop_monitor: Option<IdleStopMonitor>,
+    idle_stop_listener: Option<IdleStopListener>,
+    stream_stop_listener: Option<StreamStopListener>,
+    new_segment_listener: Option<NewSegmentListener>,
+    cursor_tracker: Option<CursorTracker>,
+    frame_listener: Option<(FrameListener, u32, FrameFormat)>,
+    // Segment the next recording continues; consumed by that start attempt
+    resume_segment: Option<SegmentInfo>,
+    region_capture: Option<RegionCapture>,
+    // The sourceRect the stream was configured with, in global points for regions
+    source_rect: Option<CGRect>,
+    // Keep the content filter between recordings instead of recreating it for each
+    keep_warm: bool,
+    warm_filter: Option<(WarmFilterKey, ContentFilter)>,
+    // Pixels per point of the captured display
+    capture_scale: f64,
 }
 
 // Safety: Raw pointers are only used within unsafe blocks and not shared across threads
@@ -39,8 +155,15 @@ unsafe impl Sync for RecordingManager {}
 impl RecordingManager {
     /// Create a new recording manager
     pub fn new() -> Self {
+        Self::with_backend(Arc::new(ScreenCaptureKitAPI))
+    }
+
+    /// Create a recording manager that drives `backend` instead of the real ScreenCaptureKit
+    pub fn with_backend(backend: Arc<dyn ScreenCaptureBackend>) -> Self {
         Self {
+            backend,
             stream: None,
+            capture_running: false,
             content_filter: None,
             delegate: None,
             delegate_bridge: None,
@@ -49,6 +172,34 @@ impl RecordingManager {
             recording_config: None,
             output_path: None,
             shareable_content: None,
+            started_at: None,
+            timeline: None,
+            paused_since: None,
+            last_recording_stats: None,
+            last_error: None,
+            underlying_error: None,
+            disk_space_monitor: None,
+            disk_space_listener: None,
+            progress_monitor: None,
+            progress_listener: None,
+            frame_delivery_check: None,
+            display_stream: Arc::new(Mutex::new(None)),
+            frame_delivery_listener: None,
+            secure_input_monitor: None,
+            output_tap: None,
+            secure_input_listener: None,
+            idle_stop_monitor: None,
+            idle_stop_listener: None,
+            stream_stop_listener: None,
+            new_segment_listener: None,
+            cursor_tracker: None,
+            frame_listener: None,
+            resume_segment: None,
+            region_capture: None,
+            source_rect: None,
+            keep_warm: false,
+            warm_filter: None,
+            capture_scale: 1.0,
         }
     }
 
@@ -57,24 +208,100 @@ impl RecordingManager {
         println!("🔧 Initializing recording manager with async ScreenCaptureKit");
         
         // Check permissions first
-        if !PermissionManager::check_screen_recording_permission() {
+        if !self.backend.check_screen_recording_permission() {
             return Err(Error::new(Status::GenericFailure, "Screen recording permission required"));
         }
         
-        // Get shareable content asynchronously
-        let content = AsyncContentManager::get_shareable_content().await?;
+        // Get shareable content asynchronously; filters made from older content go with it
+        let content = self.backend.get_shareable_content().await?;
         self.shareable_content = Some(content);
+        self.warm_filter = None;
         
         println!("✅ Recording manager initialized successfully");
         Ok(())
     }
 
-    /// Start recording with the given configuration
-    pub async fn start_recording(&mut self, config: RecordingConfiguration) -> Result<String> {
+    /// Set who is told about low disk space during the next recordings
+    /// The listener is responsible for stopping the recording on an exhausted event
+    pub fn set_disk_space_listener(&mut self, listener: Option<DiskSpaceListener>) {
+        self.disk_space_listener = listener;
+    }
+
+    /// Set who receives progress during the next recordings
+    /// The listener is responsible for stopping the recording on a finished event
+    pub fn set_progress_listener(&mut self, listener: Option<ProgressListener>) {
+        self.progress_listener = listener;
+    }
+
+    /// Set who is told when a recording's stream delivers no frames shortly after starting;
+    /// the recording keeps running
+    pub fn set_frame_delivery_listener(&mut self, listener: Option<FrameDeliveryListener>) {
+        self.frame_delivery_listener = listener;
+    }
+
+    /// Set who is told when secure input starts or ends during the next recordings
+    pub fn set_secure_input_listener(&mut self, listener: Option<SecureInputListener>) {
+        self.secure_input_listener = listener;
+    }
+
+    /// Set who is told when a recording with idleStopSeconds goes idle
+    pub fn set_idle_stop_listener(&mut self, listener: Option<IdleStopListener>) {
+        self.idle_stop_listener = listener;
+    }
+
+    /// Set who is told when ScreenCaptureKit stops a recording with an error (e.g. permission
+    /// revoked); the listener should then call `finish_stopped_stream`
+    pub fn set_stream_stop_listener(&mut self, listener: Option<StreamStopListener>) {
+        self.stream_stop_listener = listener;
+    }
+
+    /// Set who is told when resolutionChangeBehavior "segment" continues a recording in a new file
+    pub fn set_new_segment_listener(&mut self, listener: Option<NewSegmentListener>) {
+        self.new_segment_listener = listener;
+    }
+
+    /// Set who receives copies of the recorded frames in `format`, `batch_size` at a time, during
+    /// the next recordings; those recordings must use the "bgra" pixel format
+    pub fn set_frame_listener(&mut self, listener: Option<FrameListener>, batch_size: u32, format: FrameFormat) {
+        self.frame_listener = listener.map(|listener| (listener, batch_size, format));
+    }
+
+    /// Keep the shareable content and content filter between recordings so the next recording
+    /// of the same source only creates a new stream; turning it off releases the kept filter
+    pub fn set_keep_warm(&mut self, keep_warm: bool) {
+        self.keep_warm = keep_warm;
+        if !keep_warm {
+            self.warm_filter = None;
+        }
+    }
+
+    /// Release everything kept between recordings, so the next one starts from scratch
+    pub fn reset(&mut self) -> Result<()> {
+        if self.is_recording() {
+            return Err(Error::new(Status::GenericFailure, "Stop the recording before resetting"));
+        }
+        self.cleanup();
+        self.warm_filter = None;
+        self.shareable_content = None;
+        println!("🧹 Recording manager reset");
+        Ok(())
+    }
+
+    /// Make the next recording continue `segment`: its video is timestamped from the end of
+    /// that file and must match its codec and size
+    pub fn set_resume_segment(&mut self, segment: Option<SegmentInfo>) {
+        self.resume_segment = segment;
+    }
+
+    /// Start recording `target` with the given configuration
+    pub async fn start_recording(&mut self, target: CaptureTarget, mut config: RecordingConfiguration) -> Result<String> {
         println!("🎬 Starting async recording with configuration");
+        let resume_segment = self.resume_segment.take();
         
         // Validate configuration
-        self.validate_configuration(&config)?;
+        validate_recording_configuration(&config)?;
+        Self::apply_aspect_ratio(&mut config)?;
+        Self::normalize_even_dimensions(&mut config);
         
         // Check if already recording
         {
@@ -84,21 +311,169 @@ impl RecordingManager {
             }
         }
         
-        // Ensure we have shareable content
-        if self.shareable_content.is_none() {
+        // A locked screen or the login window would only ever deliver blank frames
+        if let Some(reason) = self.backend.session_state().blocked_reason() {
+            return Err(SCError::CaptureBlocked(reason.to_string()).into());
+        }
+        
+        // Reading key events needs accessibility access; never record without the requested overlay
+        if config.keystroke_overlay.unwrap_or(false) && !super::permission_manager::PermissionManager::check_accessibility_permission() {
+            return Err(Error::new(
+                Status::GenericFailure,
+                "keystrokeOverlay requires accessibility permission (System Settings > Privacy & Security > Accessibility)",
+            ));
+        }
+        
+        if let Some(ref device_uid) = config.audio_output_device_id {
+            output_tap::check_output_device(device_uid)?;
+        }
+        
+        if config.audio_device_id.is_some() {
+            Feature::MicrophoneCapture.require()?;
+            AudioManager::configure_audio_session()?;
+        }
+        
+        if self.frame_listener.is_some()
+            && CapturePixelFormat::from_config_str(config.pixel_format.as_deref().unwrap_or("bgra")) != Some(CapturePixelFormat::Bgra)
+        {
+            return Err(Error::new(Status::InvalidArg, "Frame callbacks require pixelFormat \"bgra\""));
+        }
+        
+        // The followed display replaces a display target; windows and regions have no display to swap
+        let follow_active_display = config.follow_active_display.unwrap_or(false);
+        if follow_active_display && !matches!(target, CaptureTarget::Display(_)) {
+            return Err(Error::new(Status::InvalidArg, "followActiveDisplay requires a display target"));
+        }
+        
+        // CGDisplayStream only captures whole displays
+        let capture_backend = config.capture_backend.as_deref().and_then(CaptureBackend::from_config_str).unwrap_or_default();
+        if capture_backend != CaptureBackend::ScreenCaptureKit && !matches!(target, CaptureTarget::Display(_)) {
+            return Err(Error::new(Status::InvalidArg, "captureBackend \"cgdisplaystream\" and \"auto\" require a display target"));
+        }
+        
+        // Only window captures have transparent areas for the alpha channel to carry
+        if config.video_codec.as_deref().and_then(VideoCodec::from_config_str) == Some(VideoCodec::HevcWithAlpha)
+            && !matches!(target, CaptureTarget::Window(_))
+        {
+            return Err(Error::new(Status::InvalidArg, "videoCodec \"hevc_alpha\" requires a window target"));
+        }
+        
+        // Instant replay keeps everything in memory until saveReplay; a streamed recording
+        // goes to outputFd or outputPipe
+        let fragment_target = FragmentTarget::from_config(config.output_fd, config.output_pipe.as_deref());
+        let writes_to_disk = config.replay_buffer_seconds.is_none() && fragment_target.is_none();
+        if writes_to_disk {
+            let file_type = VideoEncoderSettings::from_config(&config).file_type();
+            Self::match_output_extension(&mut config, file_type);
+            create_output_directory(&config.output_path)?;
+            self.check_free_disk_space(&config)?;
+        }
+        
+        // Ensure we have shareable content; refresh it when excluding our own windows so
+        // windows opened since the last retrieval are found
+        if self.shareable_content.is_none()
+            || config.exclude_self.unwrap_or(false)
+            || config.exclude_audio_app_bundle_ids.as_ref().is_some_and(|ids| !ids.is_empty())
+        {
             self.initialize().await?;
         }
         
-        // Store configuration
-        self.output_path = Some(config.output_path.clone());
-        self.recording_config = Some(config.clone());
+        let target = if follow_active_display { self.active_display_target(target) } else { target };
+        self.capture_scale = self.target_scale(target);
+        if config.dimension_unit.as_deref().and_then(DimensionUnit::from_config_str) == Some(DimensionUnit::Points) {
+            Self::convert_points_to_pixels(&mut config, self.capture_scale)?;
+        }
+        
+        // A region is captured as slices of the displays it covers; the first slice's display
+        // gets the main content filter and stream
+        let region_slices = match target {
+            CaptureTarget::Region(region) => Some(self.plan_region_capture(region, &mut config)?),
+            _ => None,
+        };
+        let filter_target = match region_slices.as_deref() {
+            Some([primary, ..]) => CaptureTarget::Display(primary.display_id),
+            _ => target,
+        };
+        
+        // Create content filter, unless one kept warm fits; region slices always get new ones
+        let warm_key = WarmFilterKey::new(filter_target, &config);
+        let warm_filter = self.warm_filter.take()
+            .filter(|(key, _)| region_slices.is_none() && *key == warm_key)
+            .map(|(_, filter)| filter);
+        let content_filter = match warm_filter {
+            Some(filter) => {
+                println!("♨️ Reusing the warm content filter for {:?}", filter_target);
+                filter
+            }
+            None => {
+                let filter = self.create_content_filter(filter_target, &config).await;
+                self.track_failure(FailureStage::ContentFilter, filter)?
+            }
+        };
+        if let ContentFilterType::Window(window_id) = content_filter.get_filter_type() {
+            self.size_output_to_window(window_id, &content_filter, &mut config);
+        }
         
-        // Create content filter
-        let content_filter = self.create_content_filter(&config).await?;
+        // Only now is the output size final
+        let mut encoder_settings = VideoEncoderSettings::from_config(&config);
+        if let Some(segment) = resume_segment {
+            if config.replay_buffer_seconds.is_some() {
+                return Err(Error::new(Status::InvalidArg, "replayBufferSeconds can't be used when resuming a segment"));
+            }
+            if fragment_target.is_some() {
+                return Err(Error::new(Status::InvalidArg, "outputFd and outputPipe can't be used when resuming a segment"));
+            }
+            segment.check_matches(&encoder_settings)?;
+            println!("⏩ Continuing a {:.1}s segment", segment.duration_seconds());
+            encoder_settings.timeline_offset = Some(segment.duration);
+        }
+        if let Some(ref target) = fragment_target {
+            encoder_settings.fragment_sink = Some(Arc::new(FragmentSink::open(target)?));
+            println!("🚰 Streaming fragmented MP4 to {:?}", target);
+        }
         self.content_filter = Some(content_filter);
         
-        // Create stream configuration
-        let stream_config = unsafe { self.create_stream_configuration(&config)? };
+        // Load what can fail before the writer creates a file at outputPath
+        let watermark = match config.watermark {
+            Some(ref watermark) => {
+                let source = match (&watermark.path, &watermark.image) {
+                    (Some(path), _) => WatermarkSource::Path(path.clone()),
+                    (None, image) => WatermarkSource::Data(image.as_ref().map(|image| image.0.clone()).unwrap_or_default()),
+                };
+                let placement = WatermarkPlacement {
+                    corner: watermark.corner.as_deref().and_then(WebcamCorner::from_config_str).unwrap_or_default(),
+                    opacity: watermark.opacity.unwrap_or(1.0),
+                    size: watermark.size,
+                    margin: watermark.margin,
+                };
+                Some(self.track_failure(FailureStage::StreamCreate, Watermark::load(&source, placement))?)
+            }
+            None => None,
+        };
+        let keystroke_overlay = if config.keystroke_overlay.unwrap_or(false) {
+            Some(Arc::new(self.track_failure(FailureStage::StreamCreate, KeystrokeOverlay::start())?))
+        } else {
+            None
+        };
+        // The tap stands in for ScreenCaptureKit's system audio, which is turned off then; its
+        // buffers go to the delegate once that exists
+        let tap_delegate: Arc<OnceLock<Arc<RealStreamDelegate>>> = Arc::new(OnceLock::new());
+        let output_tap = match config.audio_output_device_id {
+            Some(ref device_uid) => {
+                let tap_delegate = tap_delegate.clone();
+                let tap = OutputTap::start(device_uid, Box::new(move |sample_buffer| {
+                    if let Some(delegate) = tap_delegate.get() {
+                        delegate.handle_audio_sample_buffer(sample_buffer, SCStreamOutputType::Audio);
+                    }
+                }));
+                Some(self.track_failure(FailureStage::StreamCreate, tap)?)
+            }
+            None => None,
+        };
+        
+        // Store configuration
+        self.output_path = Some(config.output_path.clone());
+        self.recording_config = Some(config.clone());
         
         // Create stream output
         let stream_output = StreamOutput::new(
@@ -113,38 +488,208 @@ impl RecordingManager {
         self.stream_output = Some(stream_output.clone());
         
         // Create delegate
-        let delegate = Arc::new(RealStreamDelegate::new(
+        let mut delegate = RealStreamDelegate::new(
             config.output_path.clone(),
             self.is_recording.clone(),
-            config.width.unwrap_or(1920),
-            config.height.unwrap_or(1080),
-            config.fps.unwrap_or(30),
-        ));
-        
-        // Create the Objective-C bridge for the delegate
-        let bridge = ObjCDelegateBridge::new(delegate.clone())
-            .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to create delegate bridge: {}", e)))?;
+            encoder_settings,
+        );
+        // Capture without a writer would run and save nothing
+        if let Some(reason) = delegate.video_encoder_error() {
+            let error = Error::new(
+                Status::GenericFailure,
+                format!("The video writer couldn't be set up: {}", reason),
+            );
+            delegate.cancel_recording();
+            return self.track_failure(FailureStage::StreamCreate, Err(error));
+        }
+        // Asking for audio and getting a silent video-only file is worse than not starting
+        if config.capture_audio.unwrap_or(false) {
+            if let Some(reason) = delegate.audio_encoder_error() {
+                let error = Error::new(
+                    Status::GenericFailure,
+                    format!("captureAudio is on but the audio writer couldn't be set up: {}", reason),
+                );
+                delegate.cancel_recording();
+                return self.track_failure(FailureStage::StreamCreate, Err(error));
+            }
+        }
+        delegate.set_progress_interval(progress_interval(&config));
+        if config.system_audio_gain.is_some() || config.microphone_gain.is_some() {
+            delegate.set_audio_gains(config.system_audio_gain.unwrap_or(0.0), config.microphone_gain.unwrap_or(0.0));
+        }
+        if let Some(limit_ms) = config.early_audio_buffer_ms {
+            delegate.set_early_audio_buffer_ms(limit_ms);
+        }
+        if let Some(overlay) = keystroke_overlay {
+            delegate.set_keystroke_overlay(overlay);
+        }
+        // An unavailable camera never stops the screen from being recorded
+        if let Some(ref webcam) = config.webcam {
+            let corner = webcam.corner.as_deref().and_then(WebcamCorner::from_config_str).unwrap_or_default();
+            match WebcamOverlay::start(webcam.device_id.as_deref(), corner, webcam.size.unwrap_or(DEFAULT_WEBCAM_SIZE)) {
+                Ok(overlay) => delegate.set_webcam_overlay(overlay),
+                Err(e) => println!("⚠️ Webcam unavailable, recording the screen only: {}", e.reason),
+            }
+        }
+        if let Some(watermark) = watermark {
+            delegate.set_watermark(watermark);
+        }
+        if let Some(listener) = self.stream_stop_listener.clone() {
+            delegate.set_stop_listener(listener);
+        }
+        if let Some(listener) = self.new_segment_listener.clone() {
+            delegate.set_new_segment_listener(listener);
+        }
+        delegate.set_resolution_change_behavior(
+            config.resolution_change_behavior.as_deref()
+                .and_then(ResolutionChangeBehavior::from_config_str)
+                .unwrap_or_default(),
+        );
+        if let Some((ref listener, batch_size, format)) = self.frame_listener {
+            delegate.set_frame_batcher(FrameBatcher::new(listener.clone(), batch_size, format));
+        }
+        delegate.set_permission_revoked_behavior(
+            config.permission_revoked_behavior.as_deref()
+                .and_then(PermissionRevokedBehavior::from_config_str)
+                .unwrap_or_default(),
+        );
+        // The countdown runs from here; stream setup takes a fraction of a second
+        let start_delay = Duration::from_secs(config.start_delay_seconds.unwrap_or(0) as u64);
+        let recording_starts_at = Instant::now() + start_delay;
+        if !start_delay.is_zero() {
+            delegate.set_start_time(recording_starts_at);
+        }
+        let last_change = config.idle_stop_seconds.map(|_| delegate.enable_idle_detection());
+        if config.detect_chapters.unwrap_or(false) {
+            delegate.enable_chapter_detection(
+                config.chapter_sensitivity.unwrap_or(DEFAULT_CHAPTER_SENSITIVITY),
+                config.width.unwrap_or(1920),
+                config.height.unwrap_or(1080),
+            );
+        }
+        if config.validate_buffers.unwrap_or(false) {
+            delegate.enable_buffer_validation(ExpectedBuffer {
+                pixel_format: config.pixel_format.as_deref()
+                    .and_then(CapturePixelFormat::from_config_str)
+                    .unwrap_or_default()
+                    .fourcc(),
+                width: config.width.unwrap_or(1920),
+                height: config.height.unwrap_or(1080),
+            });
+        }
+        let delegate = Arc::new(delegate);
+        self.delegate = Some(delegate.clone());
+        let _ = tap_delegate.set(delegate.clone());
         
-        self.delegate = Some(delegate);
-        self.delegate_bridge = Some(Arc::new(bridge));
+        if capture_backend != CaptureBackend::CgDisplayStream {
+            let created = self.create_streams(target, region_slices, &config, delegate);
+            self.track_failure(FailureStage::StreamCreate, created)?;
+        }
         
-        // Create stream
-        let stream = unsafe {
-            self.create_stream(
-                self.content_filter.as_ref().unwrap().get_filter_ptr(),
-                stream_config,
-            )?
-        };
-        self.stream = Some(stream);
+        self.output_tap = output_tap;
         
         // Start stream capture
-        self.start_stream_capture().await?;
+        let started = match (capture_backend, target, &self.delegate) {
+            (CaptureBackend::CgDisplayStream, CaptureTarget::Display(display_id), Some(delegate)) => {
+                start_display_stream(&self.display_stream, display_id, &config, delegate.clone())
+            }
+            _ => self.start_stream_capture().await,
+        };
+        self.track_failure(FailureStage::Start, started)?;
         
         // Mark as recording
         {
             let mut is_recording = self.is_recording.lock().unwrap();
             *is_recording = true;
         }
+        self.started_at = Some(recording_starts_at);
+        let start_delay_ms = recording_starts_at.saturating_duration_since(Instant::now()).as_secs_f64() * 1000.0;
+        self.timeline = Some(Timeline { started_at: unix_time_ms() + start_delay_ms, paused_intervals: Vec::new() });
+        self.paused_since = None;
+        
+        if config.capture_cursor_track.unwrap_or(false) {
+            self.cursor_tracker = Some(CursorTracker::start(
+                cursor_track::sidecar_path(&config.output_path),
+                self.cursor_track_geometry(&config),
+                config.fps.unwrap_or(30),
+            ));
+        }
+        
+        if writes_to_disk {
+            let listener = self.disk_space_listener.clone();
+            self.disk_space_monitor = Some(DiskSpaceMonitor::start(
+                &config.output_path,
+                config.low_disk_space_warning_mb.unwrap_or(DEFAULT_LOW_DISK_SPACE_WARNING_MB) as u64 * 1_048_576,
+                config.min_free_disk_space_mb.unwrap_or(DEFAULT_MIN_FREE_DISK_SPACE_MB) as u64 * 1_048_576,
+                move |event| {
+                    if let Some(ref listener) = listener {
+                        listener(event);
+                    }
+                },
+            ));
+        }
+        
+        if let Some(listener) = self.progress_listener.clone() {
+            let frame_counter = self.delegate.as_ref().map(|delegate| delegate.frame_counter()).unwrap_or_default();
+            let on_change = config.capture_mode.as_deref().and_then(CaptureMode::from_config_str) == Some(CaptureMode::OnChange);
+            self.progress_monitor = Some(ProgressMonitor::start(
+                frame_counter,
+                config.fps.unwrap_or(30),
+                on_change,
+                config.max_duration_seconds,
+                recording_starts_at,
+                progress_interval(&config),
+                move |event| listener(event),
+            ));
+        }
+        
+        if let Some(ref delegate) = self.delegate {
+            let listener = self.frame_delivery_listener.clone();
+            // With "auto", a stream that never delivers is replaced instead of reported
+            let fallback = match (capture_backend, target) {
+                (CaptureBackend::Auto, CaptureTarget::Display(display_id)) => {
+                    Some((self.display_stream.clone(), display_id, config.clone(), delegate.clone()))
+                }
+                _ => None,
+            };
+            self.frame_delivery_check = Some(FrameDeliveryCheck::start(
+                delegate.last_sample_time(),
+                FRAME_DELIVERY_GRACE,
+                move |message| {
+                    if let Some((slot, display_id, config, delegate)) = fallback {
+                        println!("🔁 Falling back to CGDisplayStream for display {}", display_id);
+                        delegate.ignore_stream_video();
+                        match start_display_stream(&slot, display_id, &config, delegate) {
+                            Ok(()) => return,
+                            Err(e) => println!("❌ CGDisplayStream fallback failed: {}", e.reason),
+                        }
+                    }
+                    if let Some(ref listener) = listener {
+                        listener(message);
+                    }
+                },
+            ));
+        }
+        
+        if let (Some(seconds), Some(last_change)) = (config.idle_stop_seconds, last_change) {
+            let listener = self.idle_stop_listener.clone();
+            self.idle_stop_monitor = Some(IdleStopMonitor::start(last_change, Duration::from_secs(seconds as u64), move || {
+                if let Some(ref listener) = listener {
+                    listener();
+                }
+            }));
+        }
+        
+        let listener = self.secure_input_listener.clone();
+        let pausing_delegate = self.delegate.clone().filter(|_| config.pause_on_secure_input.unwrap_or(false));
+        self.secure_input_monitor = Some(SecureInputMonitor::start(move |active| {
+            if let Some(ref delegate) = pausing_delegate {
+                delegate.set_secure_input_paused(active);
+            }
+            if let Some(ref listener) = listener {
+                listener(active);
+            }
+        }));
         
         println!("✅ Recording started successfully: {}", config.output_path);
         Ok(format!("Recording started: {}", config.output_path))
@@ -162,20 +707,36 @@ impl RecordingManager {
             }
         }
         
-        // Stop stream capture
-        if self.stream.is_some() {
-            self.stop_stream_capture().await?;
+        // Stop stream capture; past the timeout the writer is finalized without waiting
+        let stop_timeout = Duration::from_millis(
+            self.recording_config.as_ref()
+                .and_then(|config| config.stop_timeout_ms)
+                .unwrap_or(DEFAULT_STOP_TIMEOUT_MS) as u64,
+        );
+        let stop_timed_out = self.stream.is_some() && !self.stop_stream_capture(stop_timeout).await;
+        self.stop_display_stream();
+        self.output_tap = None;
+        
+        // Finalize the delegate's encoders; an empty recording is reported here
+        // according to the configured empty recording behavior
+        let finalize_result = match self.delegate {
+            Some(ref delegate) => delegate.finalize_recording(),
+            None => Ok(self.output_path.clone().unwrap_or_default()),
+        };
+        if finalize_result.is_err() {
+            self.underlying_error = self.delegate.as_ref().and_then(|delegate| delegate.writer_error());
         }
+        let finalize_result = self.track_failure(FailureStage::Finalize, finalize_result);
         
         // Finalize stream output
-        let output_path = if let Some(ref stream_output) = self.stream_output {
+        let output_result = if let Some(ref stream_output) = self.stream_output {
             if let Ok(mut output) = stream_output.lock() {
-                output.stop_recording()?
+                output.stop_recording()
             } else {
-                self.output_path.clone().unwrap_or_default()
+                Ok(self.output_path.clone().unwrap_or_default())
             }
         } else {
-            self.output_path.clone().unwrap_or_default()
+            Ok(self.output_path.clone().unwrap_or_default())
         };
         
         // Mark as not recording
@@ -184,203 +745,1848 @@ impl RecordingManager {
             *is_recording = false;
         }
         
-        // Clean up
+        // Stopping while paused ends the last pause
+        self.close_pause();
+        self.finish_secure_input();
+        let cursor_track_path = self.finish_cursor_track();
+        let frame_index_path = self.write_frame_index(finalize_result.as_deref().ok());
+        let chapters_path = self.write_chapters(finalize_result.as_deref().ok());
+        let has_audible_audio = self.check_audio(finalize_result.as_deref().ok());
+        self.check_color_tag(finalize_result.as_deref().ok());
+        self.last_recording_stats = Some(RecordingStats {
+            cursor_track_path,
+            frame_index_path,
+            chapters_path,
+            stop_timed_out,
+            has_audible_audio,
+            ..self.collect_recording_stats(&finalize_result)
+        });
+        
+        let latest_segment = self.delegate.as_ref().and_then(|delegate| delegate.latest_segment_path());
+        
+        // Clean up even when finalization failed so a new recording can start
+        self.keep_filter_warm();
         self.cleanup();
         
+        finalize_result?;
+        // After a resolution change started segments, the recording ends in the last one
+        let output_path = match latest_segment {
+            Some(segment_path) => segment_path,
+            None => output_result?,
+        };
+        
         println!("✅ Recording stopped successfully: {}", output_path);
         Ok(output_path)
     }
 
-    /// Check if currently recording
-    pub fn is_recording(&self) -> bool {
-        self.is_recording.lock().map(|guard| *guard).unwrap_or(false)
+    /// Release a recording whose stream ScreenCaptureKit already stopped; the delegate has
+    /// finalized the file, so only the manager state and stats are updated
+    pub fn finish_stopped_stream(&mut self, error: &SCError) {
+        if self.delegate.is_none() {
+            return;
+        }
+        
+        if let Ok(mut is_recording) = self.is_recording.lock() {
+            *is_recording = false;
+        }
+        self.close_pause();
+        self.finish_secure_input();
+        let cursor_track_path = self.finish_cursor_track();
+        let finalized_path = self.output_path.clone().filter(|path| std::path::Path::new(path).is_file());
+        let frame_index_path = self.write_frame_index(finalized_path.as_deref());
+        let chapters_path = self.write_chapters(finalized_path.as_deref());
+        self.last_recording_stats = Some(RecordingStats {
+            cursor_track_path,
+            frame_index_path,
+            chapters_path,
+            ..self.collect_recording_stats(&Err(error.clone().into()))
+        });
+        self.cleanup();
+        println!("🛑 Recording ended by ScreenCaptureKit: {}", error);
     }
 
-    /// Get available screens
-    pub async fn get_available_screens(&self) -> Result<Vec<DisplayInfo>> {
-        if let Some(ref content) = self.shareable_content {
-            content.get_displays()
-        } else {
-            // Get content if not available
-            let content = AsyncContentManager::get_shareable_content().await?;
-            content.get_displays()
+    /// Stop encoding frames while the stream keeps running
+    pub fn pause_recording(&mut self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(Error::new(Status::GenericFailure, "Not currently recording"));
+        }
+        if self.paused_since.is_some() {
+            return Err(Error::new(Status::GenericFailure, "Recording is already paused"));
+        }
+        
+        if let Some(ref delegate) = self.delegate {
+            delegate.set_paused(true);
         }
+        if let Some(ref tracker) = self.cursor_tracker {
+            tracker.set_paused(true);
+        }
+        self.paused_since = Some(unix_time_ms());
+        println!("⏸️ Recording paused");
+        Ok(())
     }
-    
-    /// Get available windows
-    pub async fn get_available_windows(&self) -> Result<Vec<WindowInfo>> {
-        if let Some(ref content) = self.shareable_content {
-            content.get_windows()
-        } else {
-            // Get content if not available
-            let content = AsyncContentManager::get_shareable_content().await?;
-            content.get_windows()
+
+    /// Continue encoding after `pause_recording`; the paused time is left out of the output
+    pub fn resume_recording(&mut self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(Error::new(Status::GenericFailure, "Not currently recording"));
+        }
+        if self.paused_since.is_none() {
+            return Err(Error::new(Status::GenericFailure, "Recording is not paused"));
         }
+        
+        if let Some(ref delegate) = self.delegate {
+            delegate.set_paused(false);
+        }
+        if let Some(ref tracker) = self.cursor_tracker {
+            tracker.set_paused(false);
+        }
+        self.close_pause();
+        println!("▶️ Recording resumed");
+        Ok(())
     }
 
-    /// Validate recording configuration
-    fn validate_configuration(&self, config: &RecordingConfiguration) -> Result<()> {
-        if config.output_path.is_empty() {
-            return Err(Error::new(Status::InvalidArg, "Output path cannot be empty"));
+    /// Record the open pause, if any, as ending now
+    fn close_pause(&mut self) {
+        if let (Some(from), Some(timeline)) = (self.paused_since.take(), self.timeline.as_mut()) {
+            timeline.paused_intervals.push(PausedSpan { from, to: unix_time_ms() });
         }
+    }
 
-        if let Some(width) = config.width {
-            if width < 100 || width > 7680 {
-                return Err(Error::new(Status::InvalidArg, "Width must be between 100 and 7680"));
-            }
+    /// Stop watching secure input; with pauseOnSecureInput its stretches were left out of the
+    /// video, so they join the timeline's pauses
+    fn finish_secure_input(&mut self) {
+        let Some(monitor) = self.secure_input_monitor.take() else {
+            return;
+        };
+        let spans = monitor.finish();
+        let paused = self.recording_config.as_ref().is_some_and(|config| config.pause_on_secure_input.unwrap_or(false));
+        if let (true, Some(timeline)) = (paused, self.timeline.as_mut()) {
+            timeline.paused_intervals.extend(spans);
+            timeline.paused_intervals.sort_by(|a, b| a.from.total_cmp(&b.from));
         }
+    }
 
-        if let Some(height) = config.height {
-            if height < 100 || height > 4320 {
-                return Err(Error::new(Status::InvalidArg, "Height must be between 100 and 4320"));
-            }
+    /// Captured area of the recording for the cursor track, in global display points
+    /// A window's origin is taken at start; cursor positions stay relative to it if the window moves
+    fn cursor_track_geometry(&self, config: &RecordingConfiguration) -> CursorTrackGeometry {
+        let video_width = config.width.unwrap_or(1920);
+        let video_height = config.height.unwrap_or(1080);
+        let source = match (self.content_filter.as_ref().map(|filter| filter.get_filter_type()), self.shareable_content.as_ref()) {
+            _ if self.region_capture.is_some() => self.region_capture.as_ref()
+                .map(|capture| (capture.region.x, capture.region.y, capture.region.width, capture.region.height)),
+            (Some(ContentFilterType::Display(id)), Some(content)) => content.get_displays().ok()
+                .and_then(|displays| displays.into_iter().find(|display| display.id == id))
+                .map(|display| (display.x, display.y, display.width, display.height)),
+            (Some(ContentFilterType::Window(id)), Some(content)) => content.get_windows().ok()
+                .and_then(|windows| windows.into_iter().find(|window| window.id == id))
+                .map(|window| (window.x, window.y, window.width, window.height)),
+            _ => None,
+        };
+        let (x, y, width, height) = source.unwrap_or((0, 0, video_width, video_height));
+        
+        CursorTrackGeometry {
+            source_x: x as f64,
+            source_y: y as f64,
+            source_width: width as f64,
+            source_height: height as f64,
+            video_width,
+            video_height,
         }
+    }
 
-        if let Some(fps) = config.fps {
-            if fps < 1 || fps > 120 {
-                return Err(Error::new(Status::InvalidArg, "FPS must be between 1 and 120"));
+    /// Write the cursor track sidecar, if one is being recorded; a failed write doesn't fail the recording
+    fn finish_cursor_track(&mut self) -> Option<String> {
+        match self.cursor_tracker.take()?.finish() {
+            Ok(path) => Some(path),
+            Err(e) => {
+                println!("⚠️ Cursor track not saved: {}", e.reason);
+                None
             }
         }
-
-        Ok(())
     }
 
-    /// Create content filter based on configuration
-    async fn create_content_filter(&self, config: &RecordingConfiguration) -> Result<ContentFilter> {
-        println!("🎯 Creating content filter for recording");
-        
-        // For now, create a filter for the first display
-        // In a full implementation, this would parse screen selection from config
-        unsafe {
-            ContentFilterFactory::create_display_filter(None, 1)
+    /// Write the frame index sidecar of a finalized recording when emitFrameIndex is set; a
+    /// failed write doesn't fail the recording
+    fn write_frame_index(&self, video_path: Option<&str>) -> Option<String> {
+        let enabled = self.recording_config.as_ref().is_some_and(|config| config.emit_frame_index.unwrap_or(false));
+        let video_path = video_path.filter(|_| enabled)?;
+        match frame_index::write_frame_index(video_path) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                println!("⚠️ Frame index not saved: {}", e.reason);
+                None
+            }
         }
     }
 
-    /// Create stream configuration
-    unsafe fn create_stream_configuration(&self, config: &RecordingConfiguration) -> Result<*mut SCStreamConfiguration> {
-        let stream_config = ScreenCaptureKitAPI::create_stream_configuration();
-        if stream_config.is_null() {
-            return Err(Error::new(Status::GenericFailure, "Failed to create stream configuration"));
+    /// Write the chapters sidecar of a finalized recording when detectChapters is set; a
+    /// failed write doesn't fail the recording
+    fn write_chapters(&self, video_path: Option<&str>) -> Option<String> {
+        let starts = self.delegate.as_ref()?.chapter_starts()?;
+        match chapters::write_chapters(video_path?, &starts) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                println!("⚠️ Chapters not saved: {}", e.reason);
+                None
+            }
         }
+    }
 
-        ScreenCaptureKitAPI::configure_stream_configuration(
-            stream_config,
+    /// Cursor track sidecar written by the last stopped recording
+    pub fn last_cursor_track_path(&self) -> Option<String> {
+        self.last_recording_stats.as_ref().and_then(|stats| stats.cursor_track_path.clone())
+    }
+
+    /// Start time and completed pauses of the current recording, or of the last stopped one
+    pub fn recording_timeline(&self) -> Option<Timeline> {
+        self.timeline.clone()
+            .or_else(|| self.last_recording_stats.as_ref().map(|stats| stats.timeline.clone()))
+    }
+
+    /// Refuse to start when the expected recording wouldn't fit above the free space minimum
+    fn check_free_disk_space(&self, config: &RecordingConfiguration) -> Result<()> {
+        let available = disk_space::available_bytes(&config.output_path)?;
+        let min_free = config.min_free_disk_space_mb.unwrap_or(DEFAULT_MIN_FREE_DISK_SPACE_MB) as u64 * 1_048_576;
+        let duration = config.expected_duration_seconds
+            .or(config.max_duration_seconds)
+            .unwrap_or(DEFAULT_EXPECTED_DURATION_SECONDS) as u64;
+        let estimated = duration * disk_space::estimated_bytes_per_second(
             config.width.unwrap_or(1920),
             config.height.unwrap_or(1080),
-            config.fps.unwrap_or(30),
-            config.show_cursor.unwrap_or(true),
+            config.output_fps.or(config.fps).unwrap_or(30),
+            config.video_codec.as_deref().and_then(VideoCodec::from_config_str).unwrap_or_default(),
+            config.pixel_format.as_deref().and_then(CapturePixelFormat::from_config_str).unwrap_or_default(),
             config.capture_audio.unwrap_or(false),
-            kCVPixelFormatType_32BGRA,
         );
+        
+        if available < min_free + estimated {
+            return Err(Error::new(
+                Status::GenericFailure,
+                format!(
+                    "Not enough disk space: {} MB free, need about {} MB for {}s of recording plus {} MB reserved",
+                    available / 1_048_576, estimated / 1_048_576, duration, min_free / 1_048_576
+                ),
+            ));
+        }
+        
+        println!("💾 {} MB free, about {} MB needed for {}s", available / 1_048_576, estimated / 1_048_576, duration);
+        Ok(())
+    }
 
-        println!("⚙️ Created stream configuration: {}x{} @ {}fps", 
-            config.width.unwrap_or(1920),
-            config.height.unwrap_or(1080),
-            config.fps.unwrap_or(30)
-        );
+    /// The delegate to save the current recording's instant replay window to `output_path`
+    /// with; writing the file blocks, so the caller runs `save_replay` off the async workers
+    pub fn replay_delegate(&self, output_path: &str) -> Result<Arc<RealStreamDelegate>> {
+        if !self.is_recording() {
+            return Err(Error::new(Status::GenericFailure, "Not currently recording"));
+        }
+        if output_path.is_empty() {
+            return Err(Error::new(Status::InvalidArg, "Output path cannot be empty"));
+        }
+        
+        self.delegate.clone()
+            .ok_or_else(|| Error::new(Status::GenericFailure, "Recording delegate not initialized"))
+    }
 
-        Ok(stream_config)
+    /// Statistics of the most recently stopped recording
+    pub fn last_recording_stats(&self) -> Option<&RecordingStats> {
+        self.last_recording_stats.as_ref()
     }
 
-    /// Create stream with proper delegate
-    unsafe fn create_stream(
-        &self,
-        content_filter: *mut SCContentFilter,
-        configuration: *mut SCStreamConfiguration,
-    ) -> Result<*mut SCStream> {
-        // Get the Objective-C delegate from the bridge
-        let delegate = if let Some(ref bridge) = self.delegate_bridge {
-            bridge.as_objc_delegate()
+    /// Counters of the current recording so far, or the last recording's once stopped
+    pub fn current_stats(&self) -> Option<RecordingStats> {
+        if self.is_recording() {
+            Some(self.collect_recording_stats(&Ok(String::new())))
         } else {
-            // Create a minimal NSObject delegate as fallback
-            use objc2::{msg_send, class};
-            let delegate_class = class!(NSObject);
-            let delegate: *mut objc2::runtime::AnyObject = msg_send![delegate_class, new];
-            println!("⚠️ Using fallback NSObject delegate - callbacks will not work!");
-            delegate
-        };
+            self.last_recording_stats.clone()
+        }
+    }
+
+    /// Whether the current recording is paused with `pause_recording`
+    pub fn is_paused(&self) -> bool {
+        self.is_recording() && self.paused_since.is_some()
+    }
+
+    /// The most recent failure to start or finish a recording
+    pub fn last_error(&self) -> Option<&RecordingFailure> {
+        self.last_error.as_ref()
+    }
 
-        if delegate.is_null() {
-            return Err(Error::new(Status::GenericFailure, "Failed to get delegate from bridge"));
+    /// Remember `result`'s error as the last failure, at `stage`
+    fn track_failure<T>(&mut self, stage: FailureStage, result: Result<T>) -> Result<T> {
+        let underlying_error = self.underlying_error.take();
+        if let Err(ref error) = result {
+            println!("🧾 Recording failed at {}: {}", stage.as_str(), error.reason);
+            self.last_error = Some(RecordingFailure {
+                stage,
+                status: error.status,
+                message: error.reason.clone(),
+                underlying_error,
+                failed_at: unix_time_ms(),
+            });
         }
+        result
+    }
 
-        let stream = ScreenCaptureKitAPI::create_stream(content_filter, configuration, delegate);
+    /// Stop CGDisplayStream capture, waiting for a frame in flight to reach the delegate
+    fn stop_display_stream(&mut self) {
+        let stream = self.display_stream.lock().ok().and_then(|mut stream| stream.take());
+        drop(stream);
+    }
 
-        if stream.is_null() {
-            return Err(Error::new(Status::GenericFailure, "Failed to create stream"));
+    /// Warn when a recording that captured audio saved a silent track
+    fn check_audio(&self, finalized_path: Option<&str>) -> Option<bool> {
+        let captured_audio = self.recording_config.as_ref().is_some_and(|config| config.capture_audio.unwrap_or(false));
+        let path = finalized_path.filter(|path| captured_audio && std::path::Path::new(path).is_file())?;
+        let verification = verify::verify_recording(path)
+            .map_err(|e| println!("⚠️ Couldn't check the recorded audio: {}", e))
+            .ok()?;
+        if !verification.has_audio {
+            return None;
+        }
+        if !verification.has_audible_audio {
+            println!("⚠️ The recorded audio is silent: nothing played, or the wrong audio device was captured");
         }
+        Some(verification.has_audible_audio)
+    }
 
-        println!("🎬 Created ScreenCaptureKit stream with proper delegate bridge");
-        Ok(stream)
+    /// Warn when a wide-gamut recording wasn't tagged with its colour space's primaries
+    fn check_color_tag(&self, finalized_path: Option<&str>) {
+        // A streamed recording leaves no file to open
+        let color_space = self.recording_config.as_ref()
+            .filter(|config| config.output_fd.is_none() && config.output_pipe.is_none())
+            .and_then(|config| config.color_space.as_deref())
+            .and_then(OutputColorSpace::from_config_str);
+        let (Some(color_space @ OutputColorSpace::DisplayP3), Some(path)) = (color_space, finalized_path) else {
+            return;
+        };
+        let primaries = verify::color_primaries(path);
+        if primaries.as_deref() != Some(color_space.color_primaries()) {
+            println!("⚠️ {} is tagged {} instead of {}; players will show it in the wrong gamut",
+                path, primaries.as_deref().unwrap_or("untagged"), color_space.color_primaries());
+        }
     }
 
-    /// Start stream capture asynchronously
-    async fn start_stream_capture(&self) -> Result<()> {
-        println!("🚀 Starting stream capture asynchronously");
+    /// Snapshot the delegate counters for the recording being stopped
+    fn collect_recording_stats(&self, finalize_result: &Result<String>) -> RecordingStats {
+        let duration_seconds = self.started_at.map(|started| started.elapsed().as_secs_f64()).unwrap_or(0.0);
+        let (video_frames, audio_samples) = match self.delegate {
+            Some(ref delegate) => (delegate.get_frame_count(), delegate.get_audio_frame_count()),
+            None => (0, 0),
+        };
+        let (max_video_drift_ms, max_audio_drift_ms) = self.delegate.as_ref()
+            .map(|delegate| delegate.max_drift_ms())
+            .unwrap_or_default();
+        let (nudged_frames, dropped_frames) = self.delegate.as_ref()
+            .map(|delegate| delegate.timestamp_fixes())
+            .unwrap_or_default();
+        let (audio_enabled, video_enabled) = self.delegate.as_ref()
+            .map(|delegate| (delegate.audio_enabled(), delegate.video_enabled()))
+            .unwrap_or((true, true));
         
-        println!("🔍 DEBUG: Checking if stream is available...");
-        if let Some(stream) = self.stream {
-            println!("✅ DEBUG: Stream is available: {:p}", stream);
-            unsafe {
-                println!("🔥 CRITICAL DEBUG: About to call ScreenCaptureKitAPI::start_stream_capture_async with stream: {:p}", stream);
-                
-                // Use the actual ScreenCaptureKit API to start capture
-                ScreenCaptureKitAPI::start_stream_capture_async(stream, |error| {
-                    if let Some(error) = error {
-                        println!("❌ Failed to start capture: {:?}", error);
-                    } else {
-                        println!("✅ ScreenCaptureKit capture started successfully - delegate callbacks enabled!");
-                    }
-                });
-                
-                println!("🔥 CRITICAL DEBUG: ScreenCaptureKitAPI::start_stream_capture_async call completed");
-            }
+        RecordingStats {
+            output_path: self.output_path.clone().unwrap_or_default(),
+            video_frames,
+            audio_samples,
+            duration_seconds,
+            average_fps: if duration_seconds > 0.0 { video_frames as f64 / duration_seconds } else { 0.0 },
+            error: finalize_result.as_ref().err().map(|e| e.reason.clone()),
+            timeline: self.timeline.clone().unwrap_or_default(),
+            cursor_track_path: None,
+            frame_index_path: None,
+            chapters_path: None,
+            max_video_drift_ms,
+            max_audio_drift_ms,
+            nudged_frames,
+            dropped_frames,
+            stop_timed_out: false,
+            audio_enabled,
+            video_enabled,
+            has_audible_audio: None,
+        }
+    }
+
+    /// Check if currently recording
+    pub fn is_recording(&self) -> bool {
+        self.is_recording.lock().map(|guard| *guard).unwrap_or(false)
+    }
+
+    /// Whether the recording's stream delivered a video frame within the last second, paused
+    /// or not. On-change capture delivers nothing while the screen is static
+    pub fn is_receiving_frames(&self) -> bool {
+        self.is_recording()
+            && self.delegate.as_ref().is_some_and(|delegate| received_within(&delegate.last_sample_time(), FRAME_DELIVERY_WINDOW))
+    }
+
+    /// Turn the current recording's audio on or off without stopping; while off the audio
+    /// track is silent
+    pub fn set_audio_enabled(&self, enabled: bool) -> Result<()> {
+        let delegate = self.recording_delegate()?;
+        if !self.recording_config.as_ref().is_some_and(|config| config.capture_audio.unwrap_or(false)) {
+            return Err(Error::new(Status::InvalidArg, "The recording doesn't capture audio"));
+        }
+        delegate.set_audio_enabled(enabled);
+        println!("{} Audio {}", if enabled { "🔊" } else { "🔇" }, if enabled { "enabled" } else { "disabled" });
+        Ok(())
+    }
+
+    /// Turn the current recording's video on or off without stopping; while off the last
+    /// frame is held
+    pub fn set_video_enabled(&self, enabled: bool) -> Result<()> {
+        self.recording_delegate()?.set_video_enabled(enabled);
+        println!("📹 Video {}", if enabled { "enabled" } else { "disabled" });
+        Ok(())
+    }
+
+    fn recording_delegate(&self) -> Result<&Arc<RealStreamDelegate>> {
+        self.delegate.as_ref()
+            .filter(|_| self.is_recording())
+            .ok_or_else(|| Error::new(Status::GenericFailure, "Not currently recording"))
+    }
+
+    /// The most recent frame of the current recording, for live previews
+    pub fn grab_current_frame(&self) -> Result<RetainedPixelBuffer> {
+        let Some(config) = self.recording_config.as_ref().filter(|_| self.is_recording()) else {
+            return Err(Error::new(Status::GenericFailure, "Not currently recording"));
+        };
+        // Frames are encoded from BGRA pixels
+        if CapturePixelFormat::from_config_str(config.pixel_format.as_deref().unwrap_or("bgra")) != Some(CapturePixelFormat::Bgra) {
+            return Err(Error::new(Status::InvalidArg, "grabCurrentFrame requires pixelFormat \"bgra\""));
+        }
+        self.delegate.as_ref()
+            .and_then(|delegate| delegate.latest_frame())
+            .ok_or_else(|| Error::new(Status::GenericFailure, "No frame has been captured yet"))
+    }
+
+    /// Report the system capture indicator state for compliance logging
+    /// macOS always shows the recording indicator while a stream is capturing, so
+    /// visibility follows the recording state of this manager
+    pub fn get_capture_indicator_state(&self) -> CaptureIndicatorState {
+        let privacy_alert_setting = self.recording_config.as_ref()
+            .and_then(|config| config.presenter_overlay_privacy_alert.as_deref())
+            .and_then(PresenterOverlayAlertSetting::from_config_str)
+            .unwrap_or(PresenterOverlayAlertSetting::System);
+        
+        CaptureIndicatorState {
+            indicator_visible: self.is_recording(),
+            privacy_alert_setting: privacy_alert_setting.as_str().to_string(),
+            privacy_alert_supported: self.backend.supports_presenter_overlay_privacy_alert(),
+        }
+    }
+
+    /// Output settings of the current recording, including sizes derived from aspectRatio
+    /// or the captured window, and the geometry ScreenCaptureKit reports for its frames
+    pub fn get_effective_settings(&self) -> Option<EffectiveSettings> {
+        let config = self.recording_config.as_ref()?;
+        let frame_rect = |rect: CGRect| FrameRect {
+            x: rect.origin.x,
+            y: rect.origin.y,
+            width: rect.size.width,
+            height: rect.size.height,
+        };
+        let geometry = self.delegate.as_ref().and_then(|delegate| delegate.frame_geometry());
+        let (width, height) = (config.width.unwrap_or(1920), config.height.unwrap_or(1080));
+        Some(EffectiveSettings {
+            width,
+            height,
+            width_points: width as f64 / self.capture_scale,
+            height_points: height as f64 / self.capture_scale,
+            dimension_unit: config.dimension_unit.clone().unwrap_or_else(|| DimensionUnit::default().as_str().to_string()),
+            fps: config.fps.unwrap_or(30),
+            scaling_mode: config.scaling_mode.clone().unwrap_or_else(|| "fit".to_string()),
+            video_bitrate_kbps: config.video_bitrate_kbps.or_else(|| target_size_bitrate_kbps(config)),
+            source_rect: self.source_rect.map(frame_rect),
+            content_rect: geometry.map(|geometry| frame_rect(geometry.content_rect)),
+            content_scale: geometry.map(|geometry| geometry.content_scale),
+            scale_factor: geometry.map(|geometry| geometry.scale_factor),
+            audio_enabled: self.delegate.as_ref().map_or(true, |delegate| delegate.audio_enabled()),
+            video_enabled: self.delegate.as_ref().map_or(true, |delegate| delegate.video_enabled()),
+        })
+    }
+
+    /// Get available screens
+    pub async fn get_available_screens(&self) -> Result<Vec<DisplayInfo>> {
+        if let Some(ref content) = self.shareable_content {
+            content.get_displays()
+        } else {
+            // Get content if not available
+            let content = self.backend.get_shareable_content().await?;
+            content.get_displays()
+        }
+    }
+    
+    /// Get available windows
+    pub async fn get_available_windows(&self) -> Result<Vec<WindowInfo>> {
+        if let Some(ref content) = self.shareable_content {
+            content.get_windows()
+        } else {
+            // Get content if not available
+            let content = self.backend.get_shareable_content().await?;
+            content.get_windows()
+        }
+    }
+
+    /// Create content filter for the requested display or window
+    async fn create_content_filter(&self, target: CaptureTarget, config: &RecordingConfiguration) -> Result<ContentFilter> {
+        println!("🎯 Creating content filter for recording: {:?}", target);
+        
+        // A null content pointer is left to the backend: the real API rejects it and the
+        // factory falls back to a basic filter
+        let shareable_content = self.shareable_content.as_ref()
+            .map(|content| content.get_sc_content_ptr());
+        
+        let excluded_audio_bundle_ids = config.exclude_audio_app_bundle_ids.as_deref().unwrap_or(&[]);
+        if !excluded_audio_bundle_ids.is_empty() {
+            if let CaptureTarget::Window(_) = target {
+                println!("⚠️ excludeAudioAppBundleIds only applies to display recordings");
+            } else if let Some(content) = shareable_content {
+                let running = unsafe { self.backend.get_application_bundle_ids(content) };
+                if let Some(missing) = excluded_audio_bundle_ids.iter().find(|id| !running.contains(id)) {
+                    return Err(Error::new(
+                        Status::InvalidArg,
+                        format!("excludeAudioAppBundleIds: no running application has bundle id \"{}\"", missing),
+                    ));
+                }
+            }
+        }
+        
+        unsafe {
+            match target {
+                CaptureTarget::Display(display_id) => ContentFilterFactory::create_display_filter(
+                    &self.backend,
+                    shareable_content,
+                    display_id,
+                    config.exclude_self.unwrap_or(false),
+                    excluded_audio_bundle_ids,
+                ),
+                CaptureTarget::Window(window_id) => ContentFilterFactory::create_window_filter(
+                    &self.backend, shareable_content, window_id,
+                ),
+                // start_recording splits regions into display slices first
+                CaptureTarget::Region(_) => Err(Error::new(Status::InvalidArg, "Regions have no single content filter")),
+            }
+        }
+    }
+
+    /// Check that `config` suits a region recording, default its output size to the region's
+    /// size in points and split the region into display slices
+    fn plan_region_capture(&self, region: GlobalRect, config: &mut RecordingConfiguration) -> Result<Vec<RegionSlice>> {
+        // Slices are copied into the composite byte for byte
+        if CapturePixelFormat::from_config_str(config.pixel_format.as_deref().unwrap_or("bgra")) != Some(CapturePixelFormat::Bgra) {
+            return Err(Error::new(Status::InvalidArg, "Region recordings require pixelFormat \"bgra\""));
+        }
+        // Composited frames follow the first slice's stream, which would miss changes elsewhere
+        if config.capture_mode.as_deref().and_then(CaptureMode::from_config_str) == Some(CaptureMode::OnChange) {
+            return Err(Error::new(Status::InvalidArg, "captureMode \"on_change\" isn't supported for region recordings"));
+        }
+        // Composited frames carry no frame info to detect changes with
+        if config.idle_stop_seconds.is_some() {
+            return Err(Error::new(Status::InvalidArg, "idleStopSeconds isn't supported for region recordings"));
+        }
+        if config.detect_chapters.unwrap_or(false) {
+            return Err(Error::new(Status::InvalidArg, "detectChapters isn't supported for region recordings"));
+        }
+        
+        if config.width.is_none() && config.height.is_none() {
+            config.width = Some(region.width.clamp(100, MAX_OUTPUT_WIDTH) & !1);
+            config.height = Some(region.height.clamp(100, MAX_OUTPUT_HEIGHT) & !1);
+        }
+        let (width, height) = (config.width.unwrap_or(1920), config.height.unwrap_or(1080));
+        
+        let displays = match self.shareable_content.as_ref() {
+            Some(content) => content.get_displays()?,
+            None => Vec::new(),
+        };
+        let slices = plan_region(region, &displays, width, height)?;
+        println!(
+            "🧩 Region {},{} {}x{} spans displays {:?}",
+            region.x, region.y, region.width, region.height,
+            slices.iter().map(|slice| slice.display_id).collect::<Vec<_>>(),
+        );
+        Ok(slices)
+    }
+
+    /// Create one stream per region slice, each cropped to its slice and feeding the compositor
+    /// The first slice's stream becomes `stream` and alone captures audio
+    unsafe fn create_region_streams(
+        &mut self,
+        region: GlobalRect,
+        slices: Vec<RegionSlice>,
+        config: &RecordingConfiguration,
+        delegate: Arc<RealStreamDelegate>,
+    ) -> Result<()> {
+        let compositor = Arc::new(RegionCompositor::new(
+            config.width.unwrap_or(1920),
+            config.height.unwrap_or(1080),
+            slices,
+            delegate,
+        ));
+        let shareable_content = self.shareable_content.as_ref().map(|content| content.get_sc_content_ptr());
+        let mut capture = RegionCapture { region, bridges: Vec::new(), filters: Vec::new(), secondary_streams: Vec::new() };
+        
+        for (index, slice) in compositor.slices().iter().enumerate() {
+            // The slice is cropped exactly, so the compositor places it without scaling
+            let slice_config = RecordingConfiguration {
+                width: Some(slice.output_width),
+                height: Some(slice.output_height),
+                scaling_mode: Some("stretch".to_string()),
+                scaling_quality: None,
+                capture_audio: if index == 0 { config.capture_audio } else { Some(false) },
+                ..config.clone()
+            };
+            let filter_ptr = if index == 0 {
+                self.content_filter.as_ref()
+                    .ok_or_else(|| Error::new(Status::GenericFailure, "No content filter for the region"))?
+                    .get_filter_ptr()
+            } else {
+                let filter = ContentFilterFactory::create_display_filter(
+                    &self.backend,
+                    shareable_content,
+                    slice.display_id,
+                    config.exclude_self.unwrap_or(false),
+                    config.exclude_audio_app_bundle_ids.as_deref().unwrap_or(&[]),
+                )?;
+                let filter_ptr = filter.get_filter_ptr();
+                capture.filters.push(filter);
+                filter_ptr
+            };
+            
+            let bridge = RegionSliceBridge::new(compositor.clone(), index)?;
+            let stream_config = self.create_stream_configuration(&slice_config)?;
+            self.backend.set_source_rect(stream_config, slice.source_rect);
+            let stream = self.backend.create_stream(filter_ptr, stream_config, bridge.as_objc_delegate());
+            self.backend.release_object(stream_config as *mut AnyObject);
+            if stream.is_null() {
+                return Err(Error::new(Status::GenericFailure, format!("Failed to create stream for display {}", slice.display_id)));
+            }
+            capture.bridges.push(bridge);
+            if index == 0 {
+                self.stream = Some(stream);
+            } else {
+                capture.secondary_streams.push(stream);
+            }
+            println!(
+                "🧩 Display {} provides {}x{} at {},{}",
+                slice.display_id, slice.output_width, slice.output_height, slice.output_x, slice.output_y,
+            );
+        }
+        
+        self.region_capture = Some(capture);
+        self.source_rect = Some(CGRect {
+            origin: CGPoint { x: region.x as f64, y: region.y as f64 },
+            size: CGSize { width: region.width as f64, height: region.height as f64 },
+        });
+        Ok(())
+    }
+
+    /// Fill in the dimension aspectRatio leaves open, keeping it within the output size caps
+    pub(crate) fn apply_aspect_ratio(config: &mut RecordingConfiguration) -> Result<()> {
+        let Some(ratio) = config.aspect_ratio.as_deref().and_then(AspectRatio::from_config_str) else {
+            return Ok(());
+        };
+
+        let (width, height) = match config.width {
+            Some(width) => (width, ratio.height_for_width(width)),
+            None => {
+                let height = config.height.unwrap_or(1080);
+                (ratio.width_for_height(height), height)
+            }
+        };
+        if validate_dimensions(width, height).is_err() {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!(
+                    "aspectRatio {}:{} gives {}x{}, outside 100x100..{}x{}",
+                    ratio.width, ratio.height, width, height, MAX_OUTPUT_WIDTH, MAX_OUTPUT_HEIGHT
+                ),
+            ));
+        }
+
+        config.width = Some(width);
+        config.height = Some(height);
+        println!("📐 Output sized by aspect ratio {}:{}: {}x{}", ratio.width, ratio.height, width, height);
+        Ok(())
+    }
+
+    /// Round odd width/height down to even; H.264 and HEVC encode whole 2x2 chroma blocks, and an
+    /// odd size fails silently or leaves green edges. strictDimensions rejects them in validation
+    /// Pixels per point of the display `target` is captured from; a window counts as on the
+    /// first display it overlaps and a region as on the display holding its top-left corner
+    /// The display the cursor is on, for followActiveDisplay; `target` when it's on none
+    fn active_display_target(&self, target: CaptureTarget) -> CaptureTarget {
+        let displays = self.shareable_content.as_ref().and_then(|content| content.get_displays().ok()).unwrap_or_default();
+        match display_at(self.backend.cursor_location(), &displays) {
+            Some(display_id) => {
+                println!("🖱️ Recording display {}, where the cursor is", display_id);
+                CaptureTarget::Display(display_id)
+            }
+            None => target,
+        }
+    }
+
+    fn target_scale(&self, target: CaptureTarget) -> f64 {
+        let content = self.shareable_content.as_ref();
+        let displays = content.and_then(|content| content.get_displays().ok()).unwrap_or_default();
+        let display_id = match target {
+            CaptureTarget::Display(display_id) => Some(display_id),
+            CaptureTarget::Window(window_id) => content
+                .and_then(|content| content.get_windows().ok())
+                .and_then(|windows| windows.into_iter().find(|window| window.id == window_id))
+                .and_then(|window| spanned_display_ids(&window, &displays).first().copied()),
+            CaptureTarget::Region(region) => displays.iter()
+                .find(|display| {
+                    region.x >= display.x && region.x < display.x + display.width as i32
+                        && region.y >= display.y && region.y < display.y + display.height as i32
+                })
+                .map(|display| display.id),
+        };
+        unsafe {
+            CoreGraphicsHelpers::get_display_scale_factor(display_id.unwrap_or_else(|| CoreGraphicsHelpers::get_main_display_id()))
+        }
+    }
+
+    /// Give `outputPath` the extension of the container it's written as when its own doesn't
+    /// name one; see `container_output_path`
+    pub(crate) fn match_output_extension(config: &mut RecordingConfiguration, default_file_type: &str) {
+        let output_path = container_output_path(&config.output_path, default_file_type);
+        if output_path != config.output_path {
+            println!("📝 Recording to {} instead of {} to match its container", output_path, config.output_path);
+            config.output_path = output_path;
+        }
+    }
+
+    /// Turn a width/height given in points into pixels at `scale` pixels per point
+    pub(crate) fn convert_points_to_pixels(config: &mut RecordingConfiguration, scale: f64) -> Result<()> {
+        for (name, dimension, max) in [("width", &mut config.width, MAX_OUTPUT_WIDTH), ("height", &mut config.height, MAX_OUTPUT_HEIGHT)] {
+            let Some(points) = *dimension else { continue };
+            let pixels = (points as f64 * scale).round() as u32;
+            if pixels > max {
+                return Err(Error::new(
+                    Status::InvalidArg,
+                    format!("{} of {} points is {} pixels at the display's {}x scale, over the {} pixel limit", name, points, pixels, scale, max),
+                ));
+            }
+            *dimension = Some(pixels);
+        }
+        Self::normalize_even_dimensions(config);
+        println!("📐 Output size in points converted at {}x: {}x{} pixels", scale, config.width.unwrap_or(1920), config.height.unwrap_or(1080));
+        Ok(())
+    }
+
+    pub(crate) fn normalize_even_dimensions(config: &mut RecordingConfiguration) {
+        for (name, dimension) in [("width", &mut config.width), ("height", &mut config.height)] {
+            if let Some(value) = dimension.filter(|value| value % 2 != 0) {
+                println!("⚠️ Odd {} {} rounded down to {} for the encoder", name, value, value - 1);
+                *dimension = Some(value - 1);
+            }
+        }
+    }
+
+    /// Default the output size of a window recording to the window's full frame
+    /// A window filter captures the whole window even when it is dragged across displays, so
+    /// sizing to any one display would crop or squash it; explicit width/height still win
+    fn size_output_to_window(&self, window_id: u32, content_filter: &ContentFilter, config: &mut RecordingConfiguration) {
+        let Some(content) = self.shareable_content.as_ref() else { return };
+        let (Ok(windows), Ok(displays)) = (content.get_windows(), content.get_displays()) else { return };
+        let Some(window) = windows.iter().find(|window| window.id == window_id) else { return };
+        
+        let spanned = spanned_display_ids(window, &displays);
+        if spanned.len() > 1 {
+            println!("🪟 Window {} spans displays {:?}; capturing its full {}x{} frame", window_id, spanned, window.width, window.height);
+        }
+        
+        if config.width.is_some() || config.height.is_some() {
+            return;
+        }
+        
+        // The filter knows the window's backing scale on macOS 14+; points otherwise
+        let scale = unsafe { self.backend.get_content_filter_rect(content_filter.get_filter_ptr()) }
+            .map(|(_, scale)| scale)
+            .unwrap_or(1.0);
+        let clamp = |points: u32, max: u32| (((points as f64 * scale).round() as u32).clamp(100, max)) & !1;
+        config.width = Some(clamp(window.width, MAX_OUTPUT_WIDTH));
+        config.height = Some(clamp(window.height, MAX_OUTPUT_HEIGHT));
+        println!("📐 Output sized to window: {}x{}", config.width.unwrap(), config.height.unwrap());
+    }
+
+    /// Create stream configuration
+    unsafe fn create_stream_configuration(&mut self, config: &RecordingConfiguration) -> Result<*mut SCStreamConfiguration> {
+        let stream_config = self.backend.create_stream_configuration();
+        if stream_config.is_null() {
+            return Err(Error::new(Status::GenericFailure, "Failed to create stream configuration"));
+        }
+
+        self.backend.configure_stream_configuration(
+            stream_config,
+            config.width.unwrap_or(1920),
+            config.height.unwrap_or(1080),
+            config.fps.unwrap_or(30),
+            config.show_cursor.unwrap_or(true),
+            config.capture_audio.unwrap_or(false) && config.audio_output_device_id.is_none(),
+            config.pixel_format.as_deref()
+                .and_then(CapturePixelFormat::from_config_str)
+                .unwrap_or_default()
+                .fourcc(),
+        );
+
+        // Capture in the colour space the encoder tags the output with
+        let color_space = config.color_space.as_deref().and_then(OutputColorSpace::from_config_str).unwrap_or_default();
+        self.backend.set_color_space_name(stream_config, color_space.capture_color_space_name());
+
+        if let Some(color) = config.background_color.as_deref().and_then(RgbaColor::from_config_str) {
+            self.backend.set_background_color(stream_config, color);
+            println!("🎨 Background color set to {} (alpha {:.2})", config.background_color.as_deref().unwrap_or_default(), color.alpha);
+        } else if config.video_codec.as_deref().and_then(VideoCodec::from_config_str) == Some(VideoCodec::HevcWithAlpha) {
+            // Leave what the window doesn't cover transparent instead of black
+            self.backend.set_background_color(stream_config, RgbaColor::TRANSPARENT);
+        }
+
+        if config.capture_mode.as_deref().and_then(CaptureMode::from_config_str) == Some(CaptureMode::OnChange) {
+            // No rate limit: ScreenCaptureKit then delivers a frame whenever the content changes
+            self.backend.set_minimum_frame_interval(stream_config, kCMTimeZero);
+            println!("⚙️ Change-driven capture: frames delivered only when content changes");
+        }
+
+        if let Some(content_filter) = self.content_filter.as_ref() {
+            self.source_rect = content_filter.configure_scaling(
+                self.backend.as_ref(),
+                stream_config,
+                config.width.unwrap_or(1920),
+                config.height.unwrap_or(1080),
+                config.scaling_mode.as_deref().and_then(ScalingMode::from_config_str).unwrap_or_default(),
+                config.scaling_quality.as_deref().and_then(ScalingQuality::from_config_str).unwrap_or_default(),
+            );
+        }
+
+        if let Some(setting) = config.presenter_overlay_privacy_alert.as_deref()
+            .and_then(PresenterOverlayAlertSetting::from_config_str)
+        {
+            if self.backend.set_presenter_overlay_privacy_alert(stream_config, setting) {
+                println!("🔔 Presenter overlay privacy alert set to: {}", setting.as_str());
+            } else {
+                println!("⚠️ Presenter overlay privacy alert requires macOS 14.2+, using system default");
+            }
+        }
+
+        println!("⚙️ Created stream configuration: {}x{} @ {}fps", 
+            config.width.unwrap_or(1920),
+            config.height.unwrap_or(1080),
+            config.fps.unwrap_or(30)
+        );
+
+        Ok(stream_config)
+    }
+
+    /// Create stream with proper delegate
+    /// Create the stream, or a region's streams, delivering to `delegate`
+    fn create_streams(
+        &mut self,
+        target: CaptureTarget,
+        region_slices: Option<Vec<RegionSlice>>,
+        config: &RecordingConfiguration,
+        delegate: Arc<RealStreamDelegate>,
+    ) -> Result<()> {
+        match (target, region_slices) {
+            (CaptureTarget::Region(region), Some(slices)) => unsafe {
+                self.create_region_streams(region, slices, config, delegate)?;
+            },
+            _ => {
+                // Create the Objective-C bridge for the delegate; without it no frame would ever
+                // arrive, so the recording can't start
+                let bridge = ObjCDelegateBridge::new(delegate).map_err(|e| Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to create the stream delegate bridge, so no frames could be recorded: {}", e),
+                ))?;
+                self.delegate_bridge = Some(Arc::new(bridge));
+                
+                // Create stream configuration
+                let stream_config = unsafe { self.create_stream_configuration(config)? };
+                
+                // Create stream; it retains the configuration, so ours is released either way
+                let stream = unsafe {
+                    let stream = self.create_stream(
+                        self.content_filter.as_ref().unwrap().get_filter_ptr(),
+                        stream_config,
+                    );
+                    self.backend.release_object(stream_config as *mut AnyObject);
+                    stream?
+                };
+                self.stream = Some(stream);
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn create_stream(
+        &self,
+        content_filter: *mut SCContentFilter,
+        configuration: *mut SCStreamConfiguration,
+    ) -> Result<*mut SCStream> {
+        // A stream without the bridge would run without delivering a single frame and leave an
+        // empty file behind, so there's no stand-in delegate
+        let bridge = self.delegate_bridge.as_ref().filter(|bridge| bridge.is_valid()).ok_or_else(|| Error::new(
+            Status::GenericFailure,
+            "The stream has no delegate bridge to deliver frames to; refusing to record an empty file",
+        ))?;
+        let delegate = bridge.as_objc_delegate();
+
+        let stream = self.backend.create_stream(content_filter, configuration, delegate);
+
+        if stream.is_null() {
+            return Err(Error::new(Status::GenericFailure, "Failed to create stream"));
+        }
+
+        println!("🎬 Created ScreenCaptureKit stream with proper delegate bridge");
+        Ok(stream)
+    }
+
+    /// Start stream capture asynchronously
+    async fn start_stream_capture(&mut self) -> Result<()> {
+        println!("🚀 Starting stream capture asynchronously");
+        
+        if self.capture_running {
+            return Err(Error::new(Status::GenericFailure, "Stream capture is already running"));
+        }
+        
+        println!("🔍 DEBUG: Checking if stream is available...");
+        if let Some(stream) = self.stream {
+            println!("✅ DEBUG: Stream is available: {:p}", stream);
+            unsafe {
+                println!("🔥 CRITICAL DEBUG: About to start capture with stream: {:p}", stream);
+                
+                // Use the actual ScreenCaptureKit API to start capture
+                let start_error = Arc::new(Mutex::new(None));
+                let completion_error = start_error.clone();
+                self.backend.start_stream_capture(stream, Box::new(move |error| {
+                    if let Some(error) = error {
+                        let message = describe_nserror(error as *const NSError as *mut NSError);
+                        println!("❌ Failed to start capture: {}", message);
+                        *completion_error.lock().unwrap() = Some(message);
+                    } else {
+                        println!("✅ ScreenCaptureKit capture started successfully - delegate callbacks enabled!");
+                    }
+                }));
+                
+                println!("🔥 CRITICAL DEBUG: start capture call completed");
+                let start_error = start_error.lock().unwrap().take();
+                if let Some(message) = start_error {
+                    self.underlying_error = Some(message.clone());
+                    return Err(Error::new(Status::GenericFailure, format!("Failed to start capture: {}", message)));
+                }
+            }
         } else {
             println!("❌ DEBUG: No stream available to start!");
             return Err(Error::new(Status::GenericFailure, "No stream available to start"));
         }
         
+        // A region recording's other slices start once the first is running
+        let secondary_streams = self.region_capture.as_ref()
+            .map(|capture| capture.secondary_streams.clone())
+            .unwrap_or_default();
+        let mut region_error = None;
+        for stream in secondary_streams {
+            let start_error = Arc::new(Mutex::new(None));
+            let completion_error = start_error.clone();
+            unsafe {
+                self.backend.start_stream_capture(stream, Box::new(move |error| {
+                    if let Some(error) = error {
+                        *completion_error.lock().unwrap() = Some(describe_nserror(error as *const NSError as *mut NSError));
+                    }
+                }));
+            }
+            region_error = start_error.lock().unwrap().take();
+            if region_error.is_some() {
+                break;
+            }
+        }
+        if let Some(message) = region_error {
+            self.underlying_error = Some(message.clone());
+            self.stop_stream_capture(Duration::from_millis(DEFAULT_STOP_TIMEOUT_MS as u64)).await;
+            return Err(Error::new(Status::GenericFailure, format!("Failed to start region capture: {}", message)));
+        }
+        
+        self.capture_running = true;
         println!("✅ Stream capture started successfully");
         Ok(())
     }
 
-    /// Stop stream capture asynchronously
-    async fn stop_stream_capture(&self) -> Result<()> {
+    /// Stop every stream and wait up to `timeout` for ScreenCaptureKit to confirm; false when
+    /// it didn't, in which case the streams are abandoned rather than waited on forever
+    async fn stop_stream_capture(&mut self, timeout: Duration) -> bool {
         println!("⏹️ Stopping stream capture asynchronously");
         
+        let mut confirmations = Vec::new();
         if let Some(stream) = self.stream {
+            let (sender, receiver) = oneshot::channel();
+            confirmations.push(receiver);
             unsafe {
                 // Use the actual ScreenCaptureKit API to stop capture
-                ScreenCaptureKitAPI::stop_stream_capture_async(stream, |error| {
+                self.backend.stop_stream_capture(stream, Box::new(move |error| {
                     if let Some(error) = error {
-                        println!("⚠️ Warning during capture stop: {:?}", error);
+                        println!("⚠️ Warning during capture stop: {}", describe_nserror(error as *const NSError as *mut NSError));
                     } else {
                         println!("✅ ScreenCaptureKit capture stopped successfully");
                     }
-                });
+                    let _ = sender.send(());
+                }));
             }
         } else {
             println!("⚠️ No stream available to stop");
         }
+        for &stream in self.region_capture.iter().flat_map(|capture| &capture.secondary_streams) {
+            let (sender, receiver) = oneshot::channel();
+            confirmations.push(receiver);
+            unsafe {
+                self.backend.stop_stream_capture(stream, Box::new(move |error| {
+                    if let Some(error) = error {
+                        println!("⚠️ Warning during region capture stop: {}", describe_nserror(error as *const NSError as *mut NSError));
+                    }
+                    let _ = sender.send(());
+                }));
+            }
+        }
+        self.capture_running = false;
+        
+        // A dropped completion counts as confirmed: the stop call failed and won't fire later
+        let confirmed = tokio::time::timeout(timeout, with_run_loop(futures::future::join_all(confirmations))).await;
+        if confirmed.is_err() {
+            println!("⚠️ ScreenCaptureKit didn't confirm the stop within {}ms; finalizing without it", timeout.as_millis());
+            return false;
+        }
         
         println!("✅ Stream capture stopped successfully");
-        Ok(())
+        true
     }
     
+    /// Release the recording's streams, first asking any still capturing to stop
+    fn release_streams(&mut self) {
+        let secondary_streams = self.region_capture.as_mut()
+            .map(|capture| std::mem::take(&mut capture.secondary_streams))
+            .unwrap_or_default();
+        let running = std::mem::replace(&mut self.capture_running, false);
+        for stream in self.stream.take().into_iter().chain(secondary_streams) {
+            unsafe {
+                if running {
+                    self.backend.stop_stream_capture(stream, Box::new(|_| {}));
+                }
+                self.backend.release_object(stream as *mut AnyObject);
+            }
+        }
+    }
+    
+    /// In keep-warm mode, hold on to the content filter of the recording being stopped
+    fn keep_filter_warm(&mut self) {
+        if !self.keep_warm || self.region_capture.is_some() {
+            return;
+        }
+        let filter_target = match self.content_filter.as_ref().map(|filter| filter.get_filter_type()) {
+            Some(ContentFilterType::Display(display_id)) => CaptureTarget::Display(display_id),
+            Some(ContentFilterType::Window(window_id)) => CaptureTarget::Window(window_id),
+            _ => return,
+        };
+        if let (Some(config), Some(filter)) = (self.recording_config.as_ref(), self.content_filter.take()) {
+            self.warm_filter = Some((WarmFilterKey::new(filter_target, config), filter));
+        }
+    }
+
     /// Clean up resources
+    /// The order is an invariant: ScreenCaptureKit calls into the bridges until a stream has
+    /// stopped, and the bridges call into the delegate, so streams go first, then the bridges,
+    /// then the delegate. stop_recording awaits the stop before getting here; a stream still
+    /// capturing (dropped mid-recording) is asked to stop, and a bridge released while its
+    /// stream winds down waits out callbacks in flight and ignores later ones
     fn cleanup(&mut self) {
-        self.stream = None;
+        self.cursor_tracker = None;
+        self.disk_space_monitor = None;
+        self.progress_monitor = None;
+        self.frame_delivery_check = None;
+        self.stop_display_stream();
+        self.secure_input_monitor = None;
+        self.idle_stop_monitor = None;
+        self.output_tap = None;
+        self.release_streams();
+        self.region_capture = None;
+        self.source_rect = None;
         self.content_filter = None;
-        self.delegate_bridge = None; // Release bridge first
+        self.delegate_bridge = None;
         self.delegate = None;
         self.stream_output = None;
         self.recording_config = None;
+        self.timeline = None;
+        self.paused_since = None;
         println!("🧹 Recording resources cleaned up");
     }
 }
 
+/// Time between progress reports configured by progressIntervalMs
+fn progress_interval(config: &RecordingConfiguration) -> Duration {
+    config.progress_interval_ms.map_or(DEFAULT_PROGRESS_INTERVAL, |ms| Duration::from_millis(ms as u64))
+}
+
+/// Capture `display_id` with CGDisplayStream into `delegate`, keeping the stream in `slot`
+fn start_display_stream(
+    slot: &Mutex<Option<DisplayStream>>,
+    display_id: u32,
+    config: &RecordingConfiguration,
+    delegate: Arc<RealStreamDelegate>,
+) -> Result<()> {
+    let stream = DisplayStream::start(
+        display_id,
+        config.width.unwrap_or(1920),
+        config.height.unwrap_or(1080),
+        config.fps.unwrap_or(30),
+        config.pixel_format.as_deref().and_then(CapturePixelFormat::from_config_str).unwrap_or_default().fourcc(),
+        config.show_cursor.unwrap_or(true),
+        delegate,
+    )?;
+    if let Ok(mut slot) = slot.lock() {
+        *slot = Some(stream);
+    }
+    Ok(())
+}
+
+/// Wall-clock time in ms since the Unix epoch, as reported in recording timelines
+pub(super) fn unix_time_ms() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
+}
+
 impl Drop for RecordingManager {
     fn drop(&mut self) {
         self.cleanup();
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WatermarkConfiguration, WebcamConfiguration};
+    use std::sync::atomic::{AtomicIsize, Ordering};
+    use futures::future::BoxFuture;
+    use objc2_core_media::CMTime;
+    use super::super::session::SessionState;
+    use super::super::encoder::profile_level_codec;
+    
+    /// Synthetic displays 1 and 2 and windows 10 and 11; handles are the ids cast to pointers
+    /// and every call is logged so tests can check what was routed where
+    struct MockBackend {
+        calls: Mutex<Vec<String>>,
+        /// Filters and stream configurations created and not yet released
+        live_objects: AtomicIsize,
+        /// Stop completions held back to simulate ScreenCaptureKit never confirming a stop
+        hung_stops: Mutex<Option<Vec<Box<dyn FnOnce(Option<&NSError>) + Send>>>>,
+    }
+    
+    impl MockBackend {
+        fn new() -> Arc<Self> {
+            Arc::new(Self { calls: Mutex::new(Vec::new()), live_objects: AtomicIsize::new(0), hung_stops: Mutex::new(None) })
+        }
+        
+        fn log(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+        
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+        
+        fn created<T>(&self, object: usize) -> *mut T {
+            self.live_objects.fetch_add(1, Ordering::SeqCst);
+            object as *mut T
+        }
+        
+        fn live_objects(&self) -> isize {
+            self.live_objects.load(Ordering::SeqCst)
+        }
+    }
+    
+    impl ScreenCaptureBackend for MockBackend {
+        fn check_screen_recording_permission(&self) -> bool {
+            true
+        }
+        
+        fn session_state(&self) -> SessionState {
+            SessionState::Active
+        }
+        
+        fn cursor_location(&self) -> CGPoint {
+            // On display 2
+            CGPoint { x: 2500.0, y: 500.0 }
+        }
+        
+        fn get_shareable_content(&self) -> BoxFuture<'static, Result<ShareableContent>> {
+            // Display 2 sits right of display 1; window 10 straddles the edge between them
+            let displays = [1, 2].iter()
+                .map(|&id| DisplayInfo {
+                    id,
+                    name: format!("Mock display {}", id),
+                    x: (id as i32 - 1) * 1920,
+                    y: 0,
+                    width: 1920,
+                    height: 1080,
+                    is_virtual: false,
+                    is_mirror: false,
+                    edr_headroom: Some(1.0),
+                })
+                .collect();
+            let windows = [(10, 1500), (11, 100)].iter()
+                .map(|&(id, x)| WindowInfo {
+                    id,
+                    title: format!("Mock window {}", id),
+                    x,
+                    y: 100,
+                    width: 800,
+                    height: 600,
+                    owner_name: "Mock".to_string(),
+                    bundle_id: "com.example.mock".to_string(),
+                    ..WindowInfo::default()
+                })
+                .collect();
+            Box::pin(async move { Ok(ShareableContent::from_parts(displays, windows)) })
+        }
+        
+        unsafe fn extract_displays(&self, _content: *mut SCShareableContent) -> std::result::Result<Vec<*mut SCDisplay>, String> {
+            Ok(vec![1 as *mut SCDisplay, 2 as *mut SCDisplay])
+        }
+        
+        unsafe fn extract_windows(&self, _content: *mut SCShareableContent) -> std::result::Result<Vec<*mut SCWindow>, String> {
+            Ok(vec![10 as *mut SCWindow, 11 as *mut SCWindow])
+        }
+        
+        unsafe fn get_display_info(&self, display: *mut SCDisplay) -> (u32, String, u32, u32) {
+            (display as u32, format!("Mock display {}", display as u32), 1920, 1080)
+        }
+        
+        unsafe fn get_window_info(&self, window: *mut SCWindow) -> (u32, String, u32, u32) {
+            (window as u32, format!("Mock window {}", window as u32), 800, 600)
+        }
+        
+        unsafe fn create_display_filter(&self, display: *mut SCDisplay, excluded_windows: &[*mut SCWindow]) -> *mut SCContentFilter {
+            let excluded: Vec<u32> = excluded_windows.iter().map(|&window| window as u32).collect();
+            self.log(format!("display_filter {} excluding {:?}", display as u32, excluded));
+            self.created(100)
+        }
+        
+        unsafe fn get_application_bundle_ids(&self, _content: *mut SCShareableContent) -> Vec<String> {
+            vec!["com.example.music".to_string()]
+        }
+        
+        fn supports_app_audio_exclusion(&self) -> bool {
+            true
+        }
+        
+        unsafe fn create_display_filter_excluding_app_audio(
+            &self,
+            _content: *mut SCShareableContent,
+            display: *mut SCDisplay,
+            audio_bundle_ids: &[String],
+            _excluded_process_id: Option<i32>,
+        ) -> *mut SCContentFilter {
+            self.log(format!("display_filter {} excluding audio of {:?}", display as u32, audio_bundle_ids));
+            self.created(100)
+        }
+        
+        unsafe fn create_window_filter(&self, window: *mut SCWindow) -> *mut SCContentFilter {
+            self.log(format!("window_filter {}", window as u32));
+            self.created(101)
+        }
+        
+        unsafe fn create_basic_filter(&self) -> *mut SCContentFilter {
+            self.log("basic_filter".to_string());
+            self.created(102)
+        }
+        
+        unsafe fn get_content_filter_rect(&self, _filter: *mut SCContentFilter) -> Option<(CGRect, f64)> {
+            None
+        }
+        
+        unsafe fn create_stream_configuration(&self) -> *mut SCStreamConfiguration {
+            self.created(200)
+        }
+        
+        unsafe fn release_object(&self, object: *mut AnyObject) {
+            if object as usize == 300 {
+                self.log("release_stream".to_string());
+            }
+            self.live_objects.fetch_sub(1, Ordering::SeqCst);
+        }
+        
+        unsafe fn configure_stream_configuration(
+            &self,
+            _config: *mut SCStreamConfiguration,
+            width: u32,
+            height: u32,
+            fps: u32,
+            _shows_cursor: bool,
+            _captures_audio: bool,
+            _pixel_format: u32,
+        ) {
+            self.log(format!("configure {}x{}@{}", width, height, fps));
+        }
+        
+        unsafe fn set_output_size(&self, _config: *mut SCStreamConfiguration, _width: u32, _height: u32) {}
+        
+        unsafe fn set_minimum_frame_interval(&self, _config: *mut SCStreamConfiguration, _interval: CMTime) {}
+        
+        unsafe fn set_scaling(&self, _config: *mut SCStreamConfiguration, _scales_to_fit: bool, _preserves_aspect_ratio: bool) -> bool {
+            true
+        }
+        
+        unsafe fn set_source_rect(&self, _config: *mut SCStreamConfiguration, _rect: CGRect) {}
+        
+        unsafe fn set_color_space_name(&self, _config: *mut SCStreamConfiguration, _name: &str) {}
+        
+        unsafe fn set_background_color(&self, _config: *mut SCStreamConfiguration, _color: RgbaColor) {}
+        
+        fn supports_presenter_overlay_privacy_alert(&self) -> bool {
+            false
+        }
+        
+        unsafe fn set_presenter_overlay_privacy_alert(&self, _config: *mut SCStreamConfiguration, _setting: PresenterOverlayAlertSetting) -> bool {
+            false
+        }
+        
+        unsafe fn create_stream(&self, filter: *mut SCContentFilter, _configuration: *mut SCStreamConfiguration, _delegate: *mut AnyObject) -> *mut SCStream {
+            self.log(format!("create_stream with filter {}", filter as usize));
+            self.created(300)
+        }
+        
+        unsafe fn start_stream_capture(&self, _stream: *mut SCStream, completion: Box<dyn FnOnce(Option<&NSError>) + Send>) {
+            self.log("start_capture".to_string());
+            completion(None);
+        }
+        
+        unsafe fn stop_stream_capture(&self, _stream: *mut SCStream, completion: Box<dyn FnOnce(Option<&NSError>) + Send>) {
+            self.log("stop_capture".to_string());
+            match self.hung_stops.lock().unwrap().as_mut() {
+                Some(hung) => hung.push(completion),
+                None => completion(None),
+            }
+        }
+    }
+    
+    fn test_config(name: &str) -> RecordingConfiguration {
+        let output_path = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&output_path);
+        RecordingConfiguration {
+            output_path: output_path.to_string_lossy().to_string(),
+            width: Some(640),
+            height: Some(480),
+            empty_recording_behavior: Some("blackFrame".to_string()),
+            min_free_disk_space_mb: Some(1),
+            low_disk_space_warning_mb: Some(1),
+            ..RecordingConfiguration::default()
+        }
+    }
+    
+    #[test]
+    fn test_filters_route_to_requested_ids() {
+        let backend = MockBackend::new();
+        let filter_backend: Arc<dyn ScreenCaptureBackend> = backend.clone();
+        unsafe {
+            let display = ContentFilter::new_for_display_excluding_windows(&filter_backend, std::ptr::null_mut(), 2, &[11])
+                .expect("Display 2 exists");
+            assert!(matches!(display.get_filter_type(), ContentFilterType::Display(2)));
+            
+            let window = ContentFilter::new_for_window(&filter_backend, std::ptr::null_mut(), 10)
+                .expect("Window 10 exists");
+            assert!(matches!(window.get_filter_type(), ContentFilterType::Window(10)));
+            
+            assert!(ContentFilter::new_for_display(&filter_backend, std::ptr::null_mut(), 3).is_err());
+        }
+        
+        assert_eq!(backend.calls(), vec!["display_filter 2 excluding [11]", "window_filter 10"]);
+        assert_eq!(backend.live_objects(), 0, "Dropped filters are released");
+    }
+    
+    #[tokio::test]
+    async fn test_invalid_configuration_never_reaches_backend() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        
+        let config = RecordingConfiguration { fps: Some(0), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("fps 0 is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { follow_active_display: Some(true), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::Window(10), config).await.expect_err("A window has no display to follow");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration {
+            output_fps: Some(30),
+            capture_mode: Some("on_change".to_string()),
+            ..test_config("mock_invalid.mp4")
+        };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("outputFps with on_change is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { capture_backend: Some("auto".to_string()), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::Window(10), config).await.expect_err("CGDisplayStream can't capture a window");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration {
+            capture_backend: Some("cgdisplaystream".to_string()),
+            capture_audio: Some(true),
+            ..test_config("mock_invalid.mp4")
+        };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("CGDisplayStream has no audio");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { target_file_size_bytes: Some(5e8), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("A size budget needs a duration");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { resolution_change_behavior: Some("crop".to_string()), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("Unknown resolution change behaviors are rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let watermark = WatermarkConfiguration { path: Some("/nonexistent/logo.png".to_string()), ..WatermarkConfiguration::default() };
+        let config = RecordingConfiguration { watermark: Some(watermark), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("A missing watermark image is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { output_fd: Some(1), ..test_config("mock_invalid.mov") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("QuickTime can't be streamed");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { output_fd: Some(1), capture_audio: Some(true), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("Streamed recordings are video only");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { separate_audio_tracks: Some(true), capture_audio: Some(true), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("Without a microphone there's one track");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { replay_buffer_seconds: Some(30), capture_audio: Some(true), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("Replays hold no audio");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { progress_interval_ms: Some(0), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("Progress needs an interval");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { start_delay_seconds: Some(600), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("A 10 minute countdown is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration {
+            quality: Some(0.6),
+            video_bitrate_kbps: Some(4000),
+            ..test_config("mock_invalid.mp4")
+        };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("quality with a bitrate is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { background_color: Some("red".to_string()), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("Non-hex background color is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { profile_level: Some("HEVC_Main_AutoLevel".to_string()), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("HEVC profile with H.264 is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        assert_eq!(profile_level_codec("H264_High_4_2"), Some(VideoCodec::H264));
+        assert_eq!(profile_level_codec("H264_High_6_0"), None);
+        
+        let alpha = RecordingConfiguration { video_codec: Some("hevc_alpha".to_string()), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::Window(10), alpha.clone()).await.expect_err("Alpha can't go in an MPEG-4 file");
+        assert_eq!(error.status, Status::InvalidArg);
+        let config = RecordingConfiguration { pixel_format: Some("420v".to_string()), output_path: "mock_alpha.mov".to_string(), ..alpha.clone() };
+        let error = manager.start_recording(CaptureTarget::Window(10), config).await.expect_err("420v has no alpha");
+        assert_eq!(error.status, Status::InvalidArg);
+        let config = RecordingConfiguration { output_path: "mock_alpha.mov".to_string(), ..alpha };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("Displays have no transparency");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration {
+            pause_on_secure_input: Some(true),
+            capture_cursor_track: Some(true),
+            ..test_config("mock_invalid.mp4")
+        };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("Secure input pauses would desync the cursor track");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let config = RecordingConfiguration { audio_output_device_id: Some("BlackHole2ch_UID".to_string()), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("An output device needs captureAudio");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let webcam = WebcamConfiguration { size: Some(0.9), ..WebcamConfiguration::default() };
+        let config = RecordingConfiguration { webcam: Some(webcam), ..test_config("mock_invalid.mp4") };
+        let error = manager.start_recording(CaptureTarget::default(), config).await.expect_err("A webcam wider than half the video is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        assert!(!manager.is_recording());
+        assert!(backend.calls().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_follow_active_display_picks_the_display_under_the_cursor() {
+        let mut manager = RecordingManager::with_backend(MockBackend::new());
+        manager.initialize().await.expect("Mock content loads");
+        assert_eq!(manager.active_display_target(CaptureTarget::Display(1)), CaptureTarget::Display(2));
+    }
+    
+    #[test]
+    fn test_stream_without_delegate_bridge_is_never_created() {
+        let backend = MockBackend::new();
+        let manager = RecordingManager::with_backend(backend.clone());
+        let error = unsafe { manager.create_stream(std::ptr::null_mut(), std::ptr::null_mut()) }
+            .expect_err("Frames would have nowhere to go");
+        assert!(error.reason.contains("no delegate bridge"));
+        assert!(backend.calls().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_recording_state_machine() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = test_config("mock_state_machine.mp4");
+        
+        assert!(manager.stop_recording().await.is_err(), "Stopping before starting fails");
+        
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts against the mock");
+        assert!(manager.is_recording());
+        assert!(manager.get_capture_indicator_state().indicator_visible);
+        
+        let error = manager.start_recording(CaptureTarget::Display(2), config.clone()).await.expect_err("Second start is rejected");
+        assert!(error.reason.contains("Already recording"));
+        
+        manager.set_video_enabled(false).expect("Video can be turned off mid-recording");
+        assert!(!manager.get_effective_settings().unwrap().video_enabled);
+        assert!(manager.set_audio_enabled(false).is_err(), "The recording has no audio to turn off");
+        
+        let output_path = manager.stop_recording().await.expect("Black frame recording finalizes");
+        assert_eq!(output_path, config.output_path);
+        assert!(!manager.is_recording());
+        assert!(!manager.last_recording_stats().unwrap().video_enabled);
+        assert!(manager.stop_recording().await.is_err(), "Stopping twice fails");
+        assert!(manager.set_video_enabled(true).is_err(), "Tracks can only be toggled while recording");
+        
+        assert_eq!(backend.calls(), vec![
+            "display_filter 1 excluding []",
+            "configure 640x480@30",
+            "create_stream with filter 100",
+            "start_capture",
+            "stop_capture",
+            "release_stream",
+        ]);
+        
+        let _ = std::fs::remove_file(&config.output_path);
+    }
+    
+    #[tokio::test]
+    async fn test_repeated_recordings_release_their_objects() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = test_config("mock_repeated.mp4");
+        
+        for round in 0..20 {
+            manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts");
+            assert_eq!(backend.live_objects(), 2, "Only the filter and stream outlive stream creation (round {})", round);
+            manager.stop_recording().await.expect("Black frame recording finalizes");
+            assert_eq!(backend.live_objects(), 0, "Nothing is left behind after round {}", round);
+            let _ = std::fs::remove_file(&config.output_path);
+        }
+    }
+    
+    /// Also meant to run under AddressSanitizer, where a callback into a released bridge or
+    /// delegate fails the run:
+    /// RUSTFLAGS=-Zsanitizer=address cargo +nightly test --target aarch64-apple-darwin rapid_start_stop
+    #[tokio::test]
+    async fn test_rapid_start_stop_cycles_tear_down_in_order() {
+        let backend = MockBackend::new();
+        let config = test_config("mock_rapid.mp4");
+        let teardown = |calls: Vec<String>| -> Vec<String> {
+            calls.into_iter().filter(|call| call == "stop_capture" || call == "release_stream").collect()
+        };
+        
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        for _ in 0..50 {
+            manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts");
+            manager.stop_recording().await.expect("Black frame recording finalizes");
+        }
+        assert_eq!(teardown(backend.calls()), ["stop_capture", "release_stream"].repeat(50), "Each stream is stopped before it's released");
+        
+        // An unconfirmed stop still releases the stream, and dropping a recording mid-capture stops it first
+        *backend.hung_stops.lock().unwrap() = Some(Vec::new());
+        let hung = RecordingConfiguration { stop_timeout_ms: Some(50), ..config.clone() };
+        manager.start_recording(CaptureTarget::Display(1), hung).await.expect("Recording starts");
+        let _ = manager.stop_recording().await;
+        *backend.hung_stops.lock().unwrap() = None;
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts");
+        drop(manager);
+        assert_eq!(teardown(backend.calls())[100..], ["stop_capture", "release_stream", "stop_capture", "release_stream"]);
+        assert_eq!(backend.live_objects(), 0, "Nothing outlives the manager");
+        
+        let _ = std::fs::remove_file(&config.output_path);
+    }
+    
+    #[tokio::test]
+    async fn test_warm_recorder_reuses_its_content_filter() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        manager.set_keep_warm(true);
+        let config = test_config("mock_warm.mp4");
+        
+        for target in [CaptureTarget::Display(1), CaptureTarget::Display(1), CaptureTarget::Window(10)] {
+            manager.start_recording(target, config.clone()).await.expect("Recording starts");
+            manager.stop_recording().await.expect("Black frame recording finalizes");
+            let _ = std::fs::remove_file(&config.output_path);
+        }
+        let filters: Vec<String> = backend.calls().into_iter().filter(|call| call.contains("_filter")).collect();
+        assert_eq!(filters, vec!["display_filter 1 excluding []", "window_filter 10"], "The second display recording reuses the first's filter");
+        assert_eq!(backend.live_objects(), 1, "The last filter is kept warm");
+        
+        manager.reset().expect("Nothing is recording");
+        assert_eq!(backend.live_objects(), 0, "reset releases the warm filter");
+    }
+    
+    #[tokio::test]
+    async fn test_window_spanning_displays_is_captured_whole() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = RecordingConfiguration { width: None, height: None, ..test_config("mock_spanning_window.mp4") };
+        
+        manager.start_recording(CaptureTarget::Window(10), config.clone()).await.expect("Window recording starts");
+        manager.stop_recording().await.expect("Black frame recording finalizes");
+        
+        let calls = backend.calls();
+        assert_eq!(calls[0], "window_filter 10");
+        assert!(calls.contains(&"configure 800x600@30".to_string()), "Output matches the window, not a display: {:?}", calls);
+        
+        let _ = std::fs::remove_file(&config.output_path);
+    }
+    
+    #[tokio::test]
+    async fn test_video_writer_failures_stop_the_start() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = test_config("mock_no_video_writer.mp4");
+        // A directory where the movie belongs keeps the video writer from being created
+        std::fs::create_dir_all(&config.output_path).expect("Directory in place of the movie");
+        
+        let error = manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect_err("No writer, no recording");
+        assert!(error.reason.starts_with("The video writer couldn't be set up"), "Unexpected error: {}", error.reason);
+        assert!(!manager.is_recording());
+        let failure = manager.last_error().expect("The failure is kept");
+        assert_eq!(failure.stage, FailureStage::StreamCreate);
+        assert!(!backend.calls().iter().any(|call| call == "start_capture"), "Capture never starts");
+        
+        let _ = std::fs::remove_dir_all(&config.output_path);
+    }
+    
+    #[tokio::test]
+    async fn test_undecodable_watermarks_fail_before_the_writer() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = RecordingConfiguration {
+            watermark: Some(WatermarkConfiguration {
+                image: Some(crate::ImageBytes(b"not an image".to_vec())),
+                ..WatermarkConfiguration::default()
+            }),
+            ..test_config("mock_bad_watermark.mp4")
+        };
+        let _ = std::fs::remove_file(&config.output_path);
+        
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect_err("The watermark can't be drawn");
+        assert!(!manager.is_recording());
+        assert_eq!(manager.last_error().expect("The failure is kept").stage, FailureStage::StreamCreate);
+        assert!(!std::path::Path::new(&config.output_path).exists(), "No stub movie is left at outputPath");
+    }
+    
+    #[tokio::test]
+    async fn test_excluded_audio_apps_must_be_running() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = RecordingConfiguration {
+            capture_audio: Some(true),
+            exclude_audio_app_bundle_ids: Some(vec!["com.example.music".to_string()]),
+            ..test_config("mock_exclude_audio.mp4")
+        };
+        
+        let not_running = RecordingConfiguration {
+            exclude_audio_app_bundle_ids: Some(vec!["com.example.closed".to_string()]),
+            ..config.clone()
+        };
+        let error = manager.start_recording(CaptureTarget::Display(1), not_running).await.expect_err("Unknown app is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        assert!(backend.calls().is_empty());
+        let failure = manager.last_error().expect("The failure is kept");
+        assert_eq!((failure.stage, failure.status), (FailureStage::ContentFilter, Status::InvalidArg));
+        assert_eq!(failure.message, error.reason);
+        
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts");
+        manager.stop_recording().await.expect("Black frame recording finalizes");
+        assert_eq!(backend.calls()[0], "display_filter 1 excluding audio of [\"com.example.music\"]");
+        
+        let _ = std::fs::remove_file(&config.output_path);
+    }
+    
+    #[tokio::test]
+    async fn test_aspect_ratio_derives_even_width() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = RecordingConfiguration {
+            width: None,
+            height: Some(1080),
+            aspect_ratio: Some("21:9".to_string()),
+            ..test_config("mock_aspect_ratio.mp4")
+        };
+        
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts");
+        let settings = manager.get_effective_settings().expect("Effective settings while recording");
+        assert_eq!((settings.width, settings.height), (2520, 1080));
+        manager.stop_recording().await.expect("Black frame recording finalizes");
+        assert!(manager.get_effective_settings().is_none());
+        
+        let too_wide = RecordingConfiguration { height: Some(4320), ..config.clone() };
+        let error = manager.start_recording(CaptureTarget::Display(1), too_wide).await.expect_err("Width over the cap is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let _ = std::fs::remove_file(&config.output_path);
+    }
+    
+    #[tokio::test]
+    async fn test_starting_a_running_stream_is_an_error() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        manager.stream = Some(300 as *mut SCStream);
+        
+        manager.start_stream_capture().await.expect("First start succeeds");
+        let error = manager.start_stream_capture().await.expect_err("Second start is refused");
+        assert!(error.reason.contains("already running"), "{}", error.reason);
+        assert_eq!(backend.calls(), vec!["start_capture"], "The backend is never asked to start twice");
+        
+        assert!(manager.stop_stream_capture(Duration::from_millis(100)).await, "Stop is confirmed");
+        manager.start_stream_capture().await.expect("A stopped stream can be started again");
+        
+        *backend.hung_stops.lock().unwrap() = Some(Vec::new());
+        let started = Instant::now();
+        assert!(!manager.stop_stream_capture(Duration::from_millis(100)).await, "An unconfirmed stop times out");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(!manager.capture_running);
+    }
+    
+    #[tokio::test]
+    async fn test_region_recording_uses_a_stream_per_display() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let region = GlobalRect { x: 1800, y: 100, width: 400, height: 300 };
+        
+        manager.start_recording(CaptureTarget::Region(region), test_config("mock_region.mp4")).await.expect("Region recording starts");
+        let settings = manager.get_effective_settings().expect("Effective settings while recording");
+        assert_eq!((settings.width, settings.height), (400, 300), "Output defaults to the region size");
+        let source_rect = settings.source_rect.expect("Regions report their source rect");
+        assert_eq!((source_rect.x, source_rect.width), (1800.0, 400.0));
+        assert!(settings.content_rect.is_none(), "No frame has reported its geometry yet");
+        let calls = backend.calls();
+        assert!(calls.iter().any(|call| call.starts_with("display_filter 2")), "{:?}", calls);
+        assert!(calls.iter().any(|call| call.starts_with("display_filter 1")), "{:?}", calls);
+        assert_eq!(calls.iter().filter(|call| *call == "start_capture").count(), 2);
+        manager.stop_recording().await.expect("Black frame recording finalizes");
+        assert_eq!(backend.calls().iter().filter(|call| *call == "release_stream").count(), 2, "Every slice's stream is released");
+        
+        let outside = GlobalRect { x: 10_000, y: 0, width: 400, height: 300 };
+        let error = manager.start_recording(CaptureTarget::Region(outside), test_config("mock_region_outside.mp4")).await
+            .expect_err("A region off every display is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+    }
+    
+    #[tokio::test]
+    async fn test_odd_dimensions_are_rounded_to_even() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = RecordingConfiguration { width: Some(1281), height: Some(721), ..test_config("mock_odd_dimensions.mp4") };
+        
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts");
+        let settings = manager.get_effective_settings().expect("Effective settings while recording");
+        assert_eq!((settings.width, settings.height), (1280, 720));
+        manager.stop_recording().await.expect("Black frame recording finalizes");
+        
+        let strict = RecordingConfiguration { strict_dimensions: Some(true), ..config.clone() };
+        let error = manager.start_recording(CaptureTarget::Display(1), strict).await.expect_err("Odd width is rejected when strict");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        let crop = ContentFilter::centered_crop(1511.0, 982.0, 16.0 / 9.0);
+        assert_eq!((crop.size.width % 2.0, crop.size.height % 2.0), (0.0, 0.0));
+        
+        let _ = std::fs::remove_file(&config.output_path);
+    }
+    
+    #[test]
+    fn test_points_are_converted_at_the_display_scale() {
+        let mut config = RecordingConfiguration { width: Some(1440), height: Some(901), ..test_config("mock_points.mp4") };
+        RecordingManager::convert_points_to_pixels(&mut config, 2.0).expect("Retina size fits");
+        assert_eq!((config.width, config.height), (Some(2880), Some(1802)));
+        
+        let mut scaled = RecordingConfiguration { width: Some(1511), height: Some(983), ..test_config("mock_points.mp4") };
+        RecordingManager::convert_points_to_pixels(&mut scaled, 1.5).expect("Scaled mode size fits");
+        assert_eq!((scaled.width, scaled.height), (Some(2266), Some(1474)), "Fractional scales round to even pixels");
+        
+        let mut too_wide = RecordingConfiguration { width: Some(5000), ..test_config("mock_points.mp4") };
+        let error = RecordingManager::convert_points_to_pixels(&mut too_wide, 2.0).expect_err("10000 pixels is over the limit");
+        assert_eq!(error.status, Status::InvalidArg);
+    }
+    
+    #[tokio::test]
+    async fn test_stream_stopped_by_screencapturekit_releases_the_recording() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = test_config("mock_permission_revoked.mp4");
+        
+        let invalid = RecordingConfiguration { permission_revoked_behavior: Some("keep".to_string()), ..config.clone() };
+        let error = manager.start_recording(CaptureTarget::Display(1), invalid).await.expect_err("Unknown behavior is rejected");
+        assert_eq!(error.status, Status::InvalidArg);
+        
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts");
+        manager.finish_stopped_stream(&SCError::PermissionRevoked);
+        assert!(!manager.is_recording());
+        let stats = manager.last_recording_stats().expect("Stats of the ended recording");
+        assert!(stats.error.as_deref().is_some_and(|error| error.contains("revoked")));
+        let _ = std::fs::remove_file(&config.output_path);
+        
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("A new recording can start");
+        manager.stop_recording().await.expect("Black frame recording finalizes");
+        
+        let _ = std::fs::remove_file(&config.output_path);
+    }
+    
+    #[tokio::test]
+    async fn test_pauses_are_kept_in_the_timeline() {
+        let backend = MockBackend::new();
+        let mut manager = RecordingManager::with_backend(backend.clone());
+        let config = test_config("mock_timeline.mp4");
+        
+        assert!(manager.pause_recording().is_err(), "Pausing needs a recording");
+        manager.start_recording(CaptureTarget::Display(1), config.clone()).await.expect("Recording starts");
+        manager.pause_recording().expect("Recording pauses");
+        assert!(manager.pause_recording().is_err(), "Already paused");
+        manager.resume_recording().expect("Recording resumes");
+        assert!(manager.resume_recording().is_err(), "Not paused");
+        manager.pause_recording().expect("Recording pauses again");
+        manager.stop_recording().await.expect("Black frame recording finalizes");
+        
+        let timeline = manager.recording_timeline().expect("Timeline of the stopped recording");
+        assert_eq!(timeline.paused_intervals.len(), 2, "A pause open at stop is closed");
+        assert!(timeline.paused_intervals.iter().all(|span| span.from >= timeline.started_at && span.to >= span.from));
+        
+        let _ = std::fs::remove_file(&config.output_path);
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_managers_record_independently() {
+        let mut first = RecordingManager::with_backend(MockBackend::new());
+        let mut second = RecordingManager::with_backend(MockBackend::new());
+        let (first_config, second_config) = (test_config("mock_handle_first.mp4"), test_config("mock_handle_second.mp4"));
+        first.start_recording(CaptureTarget::Display(1), first_config.clone()).await.expect("First recording starts");
+        second.start_recording(CaptureTarget::Display(2), second_config.clone()).await.expect("Second recording starts");
+        
+        first.pause_recording().expect("First recording pauses");
+        assert!(first.is_paused() && !second.is_paused(), "Pausing one leaves the other running");
+        let first_path = first.stop_recording().await.expect("First recording finalizes");
+        assert!(!first.is_recording() && second.is_recording());
+        assert_eq!(first.current_stats().map(|stats| stats.output_path), Some(first_path.clone()));
+        assert_eq!(second.current_stats().map(|stats| stats.output_path), Some(second_config.output_path.clone()));
+        let second_path = second.stop_recording().await.expect("Second recording finalizes");
+        
+        let _ = std::fs::remove_file(&first_path);
+        let _ = std::fs::remove_file(&second_path);
+    }
+}
@@ -1,19 +1,23 @@
 use std::sync::{Arc, Mutex};
-use std::path::Path;
 use napi::{Result, Status, Error};
 use serde_json;
 
+use objc2::runtime::AnyObject;
 use objc2_core_media::CMSampleBuffer;
 
 use super::{
     content::{ShareableContent, AsyncContentManager},
     types::{DisplayInfo, WindowInfo},
     filters::{ContentFilter, ContentFilterFactory},
-    stream_output::StreamOutput,
+    stream_output::{StreamOutput, create_output_directory},
     permission_manager::PermissionManager,
     transcription::{TranscriptionManager, TranscriptionConfig, TranscriptionResult},
-    types::{SCStream, SCStreamConfiguration, SCStreamOutputType},
-    bindings::ScreenCaptureKitAPI,
+    types::{SCStream, SCStreamConfiguration, SCStreamOutputType, EmptyRecordingBehavior, CapturePixelFormat, VideoCodec, ScalingMode, ScalingQuality, CaptureMode, OutputColorSpace, DimensionUnit, RgbaColor},
+    bindings::{ScreenCaptureKitAPI, ScreenCaptureBackend},
+    foundation::CoreGraphicsHelpers,
+    keystroke_overlay::KeystrokeOverlay,
+    encoder::AVFileTypeQuickTimeMovie,
+    validation,
 };
 use crate::RecordingConfiguration;
 
@@ -38,12 +42,12 @@ pub struct RecordingManager {
 
 impl RecordingManager {
     /// Create a new recording manager
+    /// Permissions aren't requested until `start_recording`, so a manager created only to list
+    /// sources never shows a permission dialog
     pub fn new() -> Result<Self> {
         println!("🎬 Creating RecordingManager");
         
-        // Validate system requirements and permissions
         PermissionManager::validate_system_requirements()?;
-        PermissionManager::ensure_all_permissions()?;
         
         Ok(Self {
             stream_output: None,
@@ -69,15 +73,41 @@ impl RecordingManager {
     }
     
     /// Get available screens for recording
+    /// Before `initialize` the displays come from Core Graphics, which needs no permission
     pub fn get_available_screens(&self) -> Result<Vec<DisplayInfo>> {
         if let Some(ref content) = self.shareable_content {
             content.get_displays()
         } else {
-            Err(Error::new(Status::GenericFailure, "RecordingManager not initialized"))
+            Ok(Self::displays_without_permission())
+        }
+    }
+    
+    /// Active displays from Core Graphics, with frames in global points like SCDisplay
+    fn displays_without_permission() -> Vec<DisplayInfo> {
+        unsafe {
+            let headrooms = CoreGraphicsHelpers::edr_headrooms();
+            (0..CoreGraphicsHelpers::get_display_count())
+                .filter_map(|index| CoreGraphicsHelpers::get_display_info(index))
+                .map(|(id, name, _, _)| {
+                    let bounds = CoreGraphicsHelpers::get_display_bounds(id);
+                    DisplayInfo {
+                        id,
+                        name,
+                        x: bounds.origin.x as i32,
+                        y: bounds.origin.y as i32,
+                        width: bounds.size.width as u32,
+                        height: bounds.size.height as u32,
+                        is_virtual: CoreGraphicsHelpers::is_virtual_display(id),
+                        is_mirror: CoreGraphicsHelpers::is_mirror_display(id),
+                        edr_headroom: headrooms.as_ref().and_then(|headrooms| headrooms.get(&id).copied()),
+                    }
+                })
+                .collect()
         }
     }
     
     /// Get available windows for recording
+    /// Window titles need screen recording permission, so this still requires `initialize`
     pub fn get_available_windows(&self) -> Result<Vec<WindowInfo>> {
         if let Some(ref content) = self.shareable_content {
             content.get_windows()
@@ -87,23 +117,71 @@ impl RecordingManager {
     }
     
     /// Start recording with the specified configuration
-    pub fn start_recording(&mut self, config: RecordingConfiguration) -> Result<()> {
+    pub fn start_recording(&mut self, mut config: RecordingConfiguration) -> Result<()> {
         println!("▶️ Starting recording with configuration");
         
         // Validate configuration
         self.validate_recording_configuration(&config)?;
+        super::recording::RecordingManager::apply_aspect_ratio(&mut config)?;
+        
+        // Recording is the first point that needs screen recording permission
+        PermissionManager::ensure_all_permissions()?;
+        super::recording::RecordingManager::normalize_even_dimensions(&mut config);
+        
+        // Recordings here always capture the main display
+        if config.dimension_unit.as_deref().and_then(DimensionUnit::from_config_str) == Some(DimensionUnit::Points) {
+            let scale = unsafe { CoreGraphicsHelpers::get_display_scale_factor(CoreGraphicsHelpers::get_main_display_id()) };
+            super::recording::RecordingManager::convert_points_to_pixels(&mut config, scale)?;
+        }
+        
+        // The legacy writer defaults to QuickTime when the extension names no container
+        super::recording::RecordingManager::match_output_extension(&mut config, AVFileTypeQuickTimeMovie);
         
         // Create output directory if needed
         self.ensure_output_directory(&config.output_path)?;
         
         // Initialize stream output with proper settings
-        let stream_output = StreamOutput::new(
+        let mut stream_output = StreamOutput::new(
             config.output_path.clone(),
             config.width.unwrap_or(1920),
             config.height.unwrap_or(1080),
             config.fps.unwrap_or(30),
             config.capture_audio.unwrap_or(false),
         )?;
+        stream_output.set_empty_recording_behavior(
+            config.empty_recording_behavior.as_deref()
+                .and_then(EmptyRecordingBehavior::from_config_str)
+                .unwrap_or_default(),
+        );
+        stream_output.set_metadata(config.metadata.clone().unwrap_or_default());
+        stream_output.set_video_format(
+            config.video_codec.as_deref().and_then(VideoCodec::from_config_str).unwrap_or_default(),
+            config.pixel_format.as_deref().and_then(CapturePixelFormat::from_config_str).unwrap_or_default(),
+        );
+        
+        if let Some(retries) = config.writer_start_retries {
+            stream_output.set_writer_start_retries(retries);
+        }
+        if let Some(limit_ms) = config.early_audio_buffer_ms {
+            stream_output.set_early_audio_buffer_ms(limit_ms);
+        }
+        if config.keystroke_overlay.unwrap_or(false) {
+            if !PermissionManager::check_accessibility_permission() {
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    "keystrokeOverlay requires accessibility permission (System Settings > Privacy & Security > Accessibility)",
+                ));
+            }
+            stream_output.set_keystroke_overlay(KeystrokeOverlay::start()?);
+        }
+        stream_output.set_capture_mode(
+            config.capture_mode.as_deref().and_then(CaptureMode::from_config_str).unwrap_or_default(),
+        );
+        stream_output.set_scaling_mode(
+            config.scaling_mode.as_deref().and_then(ScalingMode::from_config_str).unwrap_or_default(),
+        );
+        stream_output.set_emit_frame_index(config.emit_frame_index.unwrap_or(false));
+        stream_output.set_profile_level(config.profile_level.clone());
         
         let stream_output_arc = Arc::new(Mutex::new(stream_output));
         
@@ -117,9 +195,21 @@ impl RecordingManager {
         
         // Create stream configuration
         let stream_config = self.create_stream_configuration(&config)?;
+        unsafe {
+            content_filter.configure_scaling(
+                &ScreenCaptureKitAPI,
+                stream_config,
+                config.width.unwrap_or(1920),
+                config.height.unwrap_or(1080),
+                config.scaling_mode.as_deref().and_then(ScalingMode::from_config_str).unwrap_or_default(),
+                config.scaling_quality.as_deref().and_then(ScalingQuality::from_config_str).unwrap_or_default(),
+            );
+        }
         
-        // Create ScreenCaptureKit stream
-        let stream = self.create_screencapturekit_stream(content_filter, stream_config, stream_output_arc.clone())?;
+        // Create ScreenCaptureKit stream; it retains the configuration, so ours is released either way
+        let stream = self.create_screencapturekit_stream(content_filter, stream_config, stream_output_arc.clone());
+        unsafe { ScreenCaptureKitAPI::release_object(stream_config as *mut AnyObject) };
+        let stream = stream?;
         
         // Start the stream output recording
         if let Ok(mut output) = stream_output_arc.lock() {
@@ -233,30 +323,18 @@ impl RecordingManager {
     }
     
     /// Validate recording configuration
+    /// The shared checks come first so an option is refused here exactly as RecordingManager refuses it
     fn validate_recording_configuration(&self, config: &RecordingConfiguration) -> Result<()> {
-        // Check output path
-        if config.output_path.is_empty() {
-            return Err(Error::new(Status::GenericFailure, "Output path cannot be empty"));
-        }
-        
-        // Validate dimensions
-        if let Some(width) = config.width {
-            if width < 100 || width > 7680 {
-                return Err(Error::new(Status::GenericFailure, "Width must be between 100 and 7680"));
-            }
-        }
+        validation::validate_recording_configuration(config)?;
         
-        if let Some(height) = config.height {
-            if height < 100 || height > 4320 {
-                return Err(Error::new(Status::GenericFailure, "Height must be between 100 and 4320"));
-            }
+        // This recorder only captures displays, which have no transparent areas
+        if config.video_codec.as_deref().and_then(VideoCodec::from_config_str) == Some(VideoCodec::HevcWithAlpha) {
+            return Err(Error::new(Status::InvalidArg, "videoCodec \"hevc_alpha\" requires a window target"));
         }
         
-        // Validate FPS
-        if let Some(fps) = config.fps {
-            if fps < 1 || fps > 120 {
-                return Err(Error::new(Status::GenericFailure, "FPS must be between 1 and 120"));
-            }
+        // The pipeline here writes system audio only; there is no microphone track to split off
+        if config.separate_audio_tracks.unwrap_or(false) {
+            return Err(Error::new(Status::InvalidArg, "separateAudioTracks isn't supported by IntegratedRecordingManager"));
         }
         
         Ok(())
@@ -264,14 +342,7 @@ impl RecordingManager {
     
     /// Ensure output directory exists
     fn ensure_output_directory(&self, output_path: &str) -> Result<()> {
-        if let Some(parent) = Path::new(output_path).parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to create output directory: {}", e)))?;
-                println!("📁 Created output directory: {}", parent.display());
-            }
-        }
-        Ok(())
+        create_output_directory(output_path)
     }
     
     /// Create content filter based on configuration
@@ -283,7 +354,8 @@ impl RecordingManager {
             if let Some(display) = displays.first() {
                 // Create a basic filter for now
                 unsafe {
-                    let filter = ContentFilter::new_basic()?;
+                    let backend: Arc<dyn ScreenCaptureBackend> = Arc::new(ScreenCaptureKitAPI);
+                    let filter = ContentFilter::new_basic(&backend)?;
                     println!("🎯 Created content filter for display: {}", display.name);
                     Ok(filter)
                 }
@@ -307,9 +379,22 @@ impl RecordingManager {
                 config.fps.unwrap_or(30),
                 config.show_cursor.unwrap_or(true),
                 config.capture_audio.unwrap_or(false),
-                0x42475241, // 'BGRA' pixel format
+                config.pixel_format.as_deref()
+                    .and_then(CapturePixelFormat::from_config_str)
+                    .unwrap_or_default()
+                    .fourcc(),
             );
             
+            let color_space = config.color_space.as_deref().and_then(OutputColorSpace::from_config_str).unwrap_or_default();
+            ScreenCaptureKitAPI::set_color_space_name(stream_config, color_space.capture_color_space_name());
+            if let Some(color) = config.background_color.as_deref().and_then(RgbaColor::from_config_str) {
+                ScreenCaptureKitAPI::set_background_color(stream_config, color);
+            }
+            
+            if config.capture_mode.as_deref().and_then(CaptureMode::from_config_str) == Some(CaptureMode::OnChange) {
+                ScreenCaptureKitAPI::set_minimum_frame_interval(stream_config, objc2_core_media::kCMTimeZero);
+            }
+            
             println!("⚙️ Created stream configuration");
             Ok(stream_config)
         }
@@ -329,6 +414,7 @@ impl RecordingManager {
             // Create the new Objective-C bridge delegate that implements SCStreamDelegate protocol
             use crate::screencapturekit::objc_bridge_rust::ObjCDelegateBridge;
             use crate::screencapturekit::delegate::RealStreamDelegate;
+            use crate::screencapturekit::encoder::VideoEncoderSettings;
             use std::sync::Mutex;
             
             // Create a RealStreamDelegate that the bridge expects
@@ -342,9 +428,7 @@ impl RecordingManager {
             let real_delegate = Arc::new(RealStreamDelegate::new(
                 output_path.clone(),
                 is_recording.clone(),
-                1920, // width - should come from config
-                1080, // height - should come from config  
-                30,   // fps - should come from config
+                VideoEncoderSettings::default(), // should come from config
             ));
             
             println!("✅ PRODUCTION: Created RealStreamDelegate for: {}", output_path);
@@ -0,0 +1,368 @@
+// Region capture across displays
+// ScreenCaptureKit streams capture one display each, so a rectangle in global desktop
+// coordinates is recorded with one stream per display it intersects, each cropped to its part
+// of the rectangle. The compositor paints the latest frame of every slice into one BGRA frame
+// at that slice's position and hands it to the delegate like a frame from a single stream
+
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use objc2::runtime::AnyObject;
+use objc2_core_media::{CMSampleBuffer, CMSampleTimingInfo, CMTime, kCMTimeInvalid};
+use objc2_core_video::CVPixelBuffer;
+use objc2_foundation::NSError;
+use napi::{Result, Status, Error};
+
+use super::delegate::RealStreamDelegate;
+use super::encoder::create_black_pixel_buffer;
+use super::foundation::{CGRect, CGPoint, CGSize};
+use super::frame_stream::RetainedPixelBuffer;
+use super::types::{DisplayInfo, GlobalRect, SCStreamOutputType};
+
+extern "C" {
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMVideoFormatDescriptionCreateForImageBuffer(
+        allocator: *const c_void,
+        image_buffer: *mut CVPixelBuffer,
+        format_description_out: *mut *mut c_void,
+    ) -> i32;
+    fn CMSampleBufferCreateReadyWithImageBuffer(
+        allocator: *const c_void,
+        image_buffer: *mut CVPixelBuffer,
+        format_description: *mut c_void,
+        sample_timing: *const CMSampleTimingInfo,
+        sample_buffer_out: *mut *mut CMSampleBuffer,
+    ) -> i32;
+    fn CVPixelBufferRelease(pixel_buffer: *mut CVPixelBuffer);
+    fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut CVPixelBuffer, lock_flags: u64) -> i32;
+    fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut CVPixelBuffer, unlock_flags: u64) -> i32;
+    fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut CVPixelBuffer) -> *mut c_void;
+    fn CVPixelBufferGetWidth(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetHeight(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetBytesPerRow(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CFRelease(cf: *const c_void);
+
+    fn create_delegate_bridge(
+        rust_context: *mut c_void,
+        video_callback: extern "C" fn(*mut c_void, *const CMSampleBuffer),
+        audio_callback: extern "C" fn(*mut c_void, *const CMSampleBuffer, isize),
+        stream_stopped_callback: extern "C" fn(*mut c_void, *const NSError),
+    ) -> *mut c_void;
+    fn release_delegate_bridge(bridge: *mut c_void);
+}
+
+// kCVPixelBufferLock_ReadOnly
+const LOCK_READ_ONLY: u64 = 1;
+const BYTES_PER_PIXEL: usize = 4;
+
+/// The part of a region one display provides
+#[derive(Debug, Clone, Copy)]
+pub struct RegionSlice {
+    pub display_id: u32,
+    /// Area to capture, in the display's own points (top-left origin)
+    pub source_rect: CGRect,
+    /// Where the slice lands in the composited frame, in output pixels
+    pub output_x: u32,
+    pub output_y: u32,
+    pub output_width: u32,
+    pub output_height: u32,
+}
+
+/// Split `region` into one slice per display it intersects, for a `width`x`height` output
+/// Mirroring displays are skipped since their primary shows the same picture. The slice with
+/// the largest share comes first; its stream paces the composited frames and carries audio
+pub fn plan_region(region: GlobalRect, displays: &[DisplayInfo], width: u32, height: u32) -> Result<Vec<RegionSlice>> {
+    let no_display = || Error::new(
+        Status::InvalidArg,
+        format!(
+            "Region {},{} {}x{} doesn't cover any capturable display area",
+            region.x, region.y, region.width, region.height,
+        ),
+    );
+    if region.width == 0 || region.height == 0 {
+        return Err(no_display());
+    }
+
+    let scale_x = width as f64 / region.width as f64;
+    let scale_y = height as f64 / region.height as f64;
+    let (region_left, region_top) = (region.x as i64, region.y as i64);
+    let (region_right, region_bottom) = (region_left + region.width as i64, region_top + region.height as i64);
+
+    let mut slices: Vec<RegionSlice> = displays.iter()
+        .filter(|display| !display.is_mirror)
+        .filter_map(|display| {
+            let (display_left, display_top) = (display.x as i64, display.y as i64);
+            let left = region_left.max(display_left);
+            let top = region_top.max(display_top);
+            let right = region_right.min(display_left + display.width as i64);
+            let bottom = region_bottom.min(display_top + display.height as i64);
+            if left >= right || top >= bottom {
+                return None;
+            }
+
+            let to_output = |points: i64, origin: i64, scale: f64| ((points - origin) as f64 * scale).round() as u32;
+            let output_x = to_output(left, region_left, scale_x);
+            let output_y = to_output(top, region_top, scale_y);
+            // Even sizes keep each slice stream encodable-sized like any other capture
+            let output_width = (to_output(right, region_left, scale_x) - output_x) & !1;
+            let output_height = (to_output(bottom, region_top, scale_y) - output_y) & !1;
+            if output_width == 0 || output_height == 0 {
+                return None;
+            }
+
+            Some(RegionSlice {
+                display_id: display.id,
+                source_rect: CGRect {
+                    origin: CGPoint { x: (left - display_left) as f64, y: (top - display_top) as f64 },
+                    size: CGSize { width: (right - left) as f64, height: (bottom - top) as f64 },
+                },
+                output_x,
+                output_y,
+                output_width,
+                output_height,
+            })
+        })
+        .collect();
+
+    if slices.is_empty() {
+        return Err(no_display());
+    }
+    slices.sort_by_key(|slice| std::cmp::Reverse(slice.output_width as u64 * slice.output_height as u64));
+    Ok(slices)
+}
+
+/// Paints the slices of a region recording into single frames for the delegate
+pub struct RegionCompositor {
+    width: u32,
+    height: u32,
+    slices: Vec<RegionSlice>,
+    latest: Mutex<Vec<Option<RetainedPixelBuffer>>>,
+    delegate: Arc<RealStreamDelegate>,
+    stopped: AtomicBool,
+}
+
+// Safety: The retained pixel buffers are only touched while holding `latest`
+unsafe impl Send for RegionCompositor {}
+unsafe impl Sync for RegionCompositor {}
+
+impl RegionCompositor {
+    pub fn new(width: u32, height: u32, slices: Vec<RegionSlice>, delegate: Arc<RealStreamDelegate>) -> Self {
+        let latest = slices.iter().map(|_| None).collect();
+        Self { width, height, slices, latest: Mutex::new(latest), delegate, stopped: AtomicBool::new(false) }
+    }
+
+    pub fn slices(&self) -> &[RegionSlice] {
+        &self.slices
+    }
+
+    /// Keep the newest frame of slice `index`; a frame from the first slice also emits a
+    /// composited frame with its timestamp
+    fn push_video(&self, index: usize, sample_buffer: &CMSampleBuffer) {
+        unsafe {
+            // Idle frames carry no pixels; the previous frame of the slice stays current
+            let Some(frame) = RetainedPixelBuffer::from_sample_buffer(sample_buffer) else {
+                return;
+            };
+
+            let composite = {
+                let Ok(mut latest) = self.latest.lock() else { return };
+                latest[index] = Some(frame);
+                if index != 0 {
+                    return;
+                }
+                match self.compose(&latest) {
+                    Some(composite) => composite,
+                    None => return,
+                }
+            };
+
+            let presentation_time = CMSampleBufferGetPresentationTimeStamp(sample_buffer);
+            if let Some(composited_sample) = wrap_pixel_buffer(composite, presentation_time) {
+                self.delegate.handle_video_sample_buffer(&*composited_sample);
+                CFRelease(composited_sample as *const c_void);
+            }
+            CVPixelBufferRelease(composite);
+        }
+    }
+
+    /// A new frame with every slice's latest frame copied into place; areas no display covers
+    /// stay black
+    unsafe fn compose(&self, latest: &[Option<RetainedPixelBuffer>]) -> Option<*mut CVPixelBuffer> {
+        let composite = match create_black_pixel_buffer(self.width, self.height) {
+            Ok(composite) => composite,
+            Err(e) => {
+                println!("❌ Region frame not composited: {}", e.reason);
+                return None;
+            }
+        };
+        if CVPixelBufferLockBaseAddress(composite, 0) != 0 {
+            CVPixelBufferRelease(composite);
+            return None;
+        }
+        let destination = CVPixelBufferGetBaseAddress(composite) as *mut u8;
+        let destination_stride = CVPixelBufferGetBytesPerRow(composite);
+
+        for (slice, frame) in self.slices.iter().zip(latest) {
+            let Some(frame) = frame else { continue };
+            if destination.is_null() || CVPixelBufferLockBaseAddress(frame.as_ptr(), LOCK_READ_ONLY) != 0 {
+                continue;
+            }
+            let source = CVPixelBufferGetBaseAddress(frame.as_ptr()) as *const u8;
+            let source_stride = CVPixelBufferGetBytesPerRow(frame.as_ptr());
+            // A stream may deliver a frame sized slightly differently from the slice
+            let columns = CVPixelBufferGetWidth(frame.as_ptr())
+                .min(slice.output_width as usize)
+                .min((self.width - slice.output_x.min(self.width)) as usize);
+            let rows = CVPixelBufferGetHeight(frame.as_ptr())
+                .min(slice.output_height as usize)
+                .min((self.height - slice.output_y.min(self.height)) as usize);
+
+            if !source.is_null() {
+                for row in 0..rows {
+                    ptr::copy_nonoverlapping(
+                        source.add(row * source_stride),
+                        destination.add((slice.output_y as usize + row) * destination_stride + slice.output_x as usize * BYTES_PER_PIXEL),
+                        columns * BYTES_PER_PIXEL,
+                    );
+                }
+            }
+            CVPixelBufferUnlockBaseAddress(frame.as_ptr(), LOCK_READ_ONLY);
+        }
+
+        CVPixelBufferUnlockBaseAddress(composite, 0);
+        Some(composite)
+    }
+}
+
+/// A ready sample buffer holding `pixel_buffer` at `presentation_time`
+pub(super) unsafe fn wrap_pixel_buffer(pixel_buffer: *mut CVPixelBuffer, presentation_time: CMTime) -> Option<*mut CMSampleBuffer> {
+    let mut format_description: *mut c_void = ptr::null_mut();
+    if CMVideoFormatDescriptionCreateForImageBuffer(ptr::null(), pixel_buffer, &mut format_description) != 0 {
+        return None;
+    }
+
+    let timing = CMSampleTimingInfo {
+        duration: kCMTimeInvalid,
+        presentationTimeStamp: presentation_time,
+        decodeTimeStamp: kCMTimeInvalid,
+    };
+    let mut sample_buffer: *mut CMSampleBuffer = ptr::null_mut();
+    let status = CMSampleBufferCreateReadyWithImageBuffer(ptr::null(), pixel_buffer, format_description, &timing, &mut sample_buffer);
+    CFRelease(format_description);
+    (status == 0 && !sample_buffer.is_null()).then_some(sample_buffer)
+}
+
+/// Callback context for one slice's stream
+struct SliceContext {
+    compositor: Arc<RegionCompositor>,
+    index: usize,
+}
+
+extern "C" fn slice_video_callback(context: *mut c_void, sample_buffer: *const CMSampleBuffer) {
+    if context.is_null() || sample_buffer.is_null() {
+        return;
+    }
+    unsafe {
+        let context = &*(context as *const SliceContext);
+        context.compositor.push_video(context.index, &*sample_buffer);
+    }
+}
+
+extern "C" fn slice_audio_callback(context: *mut c_void, sample_buffer: *const CMSampleBuffer, output_type: isize) {
+    if context.is_null() || sample_buffer.is_null() {
+        return;
+    }
+    unsafe {
+        let context = &*(context as *const SliceContext);
+        // Only the first slice's stream is configured to capture audio
+        if context.index != 0 {
+            return;
+        }
+        let of_type = if output_type == SCStreamOutputType::Microphone as isize {
+            SCStreamOutputType::Microphone
+        } else {
+            SCStreamOutputType::Audio
+        };
+        context.compositor.delegate.handle_audio_sample_buffer(&*sample_buffer, of_type);
+    }
+}
+
+extern "C" fn slice_stream_stopped_callback(context: *mut c_void, error: *const NSError) {
+    if context.is_null() {
+        return;
+    }
+    unsafe {
+        let context = &*(context as *const SliceContext);
+        // Any slice stopping ends the recording; report it once
+        if context.compositor.stopped.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        println!("🛑 Region stream for display {} stopped", context.compositor.slices[context.index].display_id);
+        let error_ref = if error.is_null() { None } else { Some(&*error) };
+        context.compositor.delegate.handle_stream_stopped(error_ref);
+    }
+}
+
+/// Objective-C stream delegate routing one slice's frames into the compositor
+pub struct RegionSliceBridge {
+    bridge_ptr: *mut c_void,
+    _context: Box<SliceContext>,
+}
+
+// Safety: The bridge pointer is only handed to ScreenCaptureKit and released on drop
+unsafe impl Send for RegionSliceBridge {}
+unsafe impl Sync for RegionSliceBridge {}
+
+impl RegionSliceBridge {
+    pub fn new(compositor: Arc<RegionCompositor>, index: usize) -> Result<Self> {
+        let context = Box::new(SliceContext { compositor, index });
+        let bridge_ptr = unsafe {
+            create_delegate_bridge(
+                &*context as *const SliceContext as *mut c_void,
+                slice_video_callback,
+                slice_audio_callback,
+                slice_stream_stopped_callback,
+            )
+        };
+        if bridge_ptr.is_null() {
+            return Err(Error::new(Status::GenericFailure, "Failed to create region stream delegate"));
+        }
+        Ok(Self { bridge_ptr, _context: context })
+    }
+
+    pub fn as_objc_delegate(&self) -> *mut AnyObject {
+        self.bridge_ptr as *mut AnyObject
+    }
+}
+
+impl Drop for RegionSliceBridge {
+    fn drop(&mut self) {
+        unsafe { release_delegate_bridge(self.bridge_ptr) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(id: u32, x: i32, is_mirror: bool) -> DisplayInfo {
+        DisplayInfo { id, name: format!("Display {}", id), x, y: 0, width: 1920, height: 1080, is_virtual: false, is_mirror, edr_headroom: Some(1.0) }
+    }
+
+    #[test]
+    fn test_region_is_split_at_display_edges() {
+        let displays = [display(1, 0, false), display(2, 1920, false), display(3, 1920, true)];
+        let region = GlobalRect { x: 1820, y: 100, width: 400, height: 300 };
+
+        let slices = plan_region(region, &displays, 800, 600).expect("Region covers two displays");
+        assert_eq!(slices.len(), 2, "The mirror of display 2 is skipped");
+        assert_eq!(slices[0].display_id, 2, "The larger slice comes first");
+        assert_eq!((slices[0].source_rect.origin.x, slices[0].source_rect.size.width), (0.0, 300.0));
+        assert_eq!((slices[0].output_x, slices[0].output_width, slices[0].output_height), (200, 600, 600));
+        assert_eq!((slices[1].source_rect.origin.x, slices[1].output_x, slices[1].output_width), (1820.0, 0, 200));
+
+        let outside = GlobalRect { x: 5000, y: 0, width: 200, height: 200 };
+        assert_eq!(plan_region(outside, &displays, 200, 200).expect_err("No display there").status, Status::InvalidArg);
+    }
+}
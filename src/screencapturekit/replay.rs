@@ -0,0 +1,410 @@
+// Instant replay ring buffer
+// Keeps the most recent N seconds of H.264-encoded video in memory and writes it out on demand
+
+use std::collections::VecDeque;
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSString, NSURL, NSError, NSNumber};
+use objc2_av_foundation::{AVAssetWriter, AVAssetWriterInput};
+use objc2_core_media::{CMSampleBuffer, CMTime, kCMTimeInvalid};
+use objc2_core_video::CVPixelBuffer;
+use napi::{Result, Status, Error};
+
+use super::encoder::{AVFileTypeMPEG4, AVMediaTypeVideo, asset_writer_error_description};
+use super::foundation::describe_nserror;
+
+// CoreMedia codec type for H.264 ('avc1')
+const kCMVideoCodecType_H264: u32 = 0x61766331;
+
+// VTEncodeInfoFlags bit set when the encoder dropped a frame
+const kVTEncodeInfo_FrameDropped: u32 = 1 << 1;
+
+// Keyframe spacing; the replay window is trimmed in whole GOPs so this bounds the overshoot
+const REPLAY_KEY_FRAME_INTERVAL_SECONDS: f64 = 1.0;
+
+// AVAssetWriterStatusFailed
+const WRITER_STATUS_FAILED: isize = 3;
+// Longest saving waits for the writer to take the next sample
+const REPLAY_INPUT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+type VTCompressionSessionRef = *mut c_void;
+
+extern "C" {
+    static kVTCompressionPropertyKey_RealTime: *const AnyObject;
+    static kVTCompressionPropertyKey_AllowFrameReordering: *const AnyObject;
+    static kVTCompressionPropertyKey_MaxKeyFrameIntervalDuration: *const AnyObject;
+    static kCMSampleAttachmentKey_NotSync: *const AnyObject;
+
+    fn VTCompressionSessionCreate(
+        allocator: *const c_void,
+        width: i32,
+        height: i32,
+        codec_type: u32,
+        encoder_specification: *const c_void,
+        source_image_buffer_attributes: *const c_void,
+        compressed_data_allocator: *const c_void,
+        output_callback: extern "C" fn(*mut c_void, *mut c_void, i32, u32, *mut CMSampleBuffer),
+        output_callback_refcon: *mut c_void,
+        compression_session_out: *mut VTCompressionSessionRef,
+    ) -> i32;
+    fn VTSessionSetProperty(session: VTCompressionSessionRef, key: *const AnyObject, value: *const AnyObject) -> i32;
+    fn VTCompressionSessionEncodeFrame(
+        session: VTCompressionSessionRef,
+        image_buffer: *mut CVPixelBuffer,
+        presentation_time: CMTime,
+        duration: CMTime,
+        frame_properties: *const c_void,
+        source_frame_refcon: *mut c_void,
+        info_flags_out: *mut u32,
+    ) -> i32;
+    fn VTCompressionSessionCompleteFrames(session: VTCompressionSessionRef, complete_until: CMTime) -> i32;
+    fn VTCompressionSessionInvalidate(session: VTCompressionSessionRef);
+
+    fn CFRetain(cf: *const c_void) -> *const c_void;
+    fn CFRelease(cf: *const c_void);
+    fn CMSampleBufferGetImageBuffer(sbuf: &CMSampleBuffer) -> *mut CVPixelBuffer;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMSampleBufferGetFormatDescription(sbuf: *mut CMSampleBuffer) -> *mut c_void;
+    fn CMSampleBufferGetSampleAttachmentsArray(sbuf: *mut CMSampleBuffer, create_if_necessary: bool) -> *mut AnyObject;
+}
+
+/// An encoded sample held by the replay buffer (retained for as long as it is buffered)
+struct BufferedSample {
+    sample_buffer: *mut CMSampleBuffer,
+    presentation_seconds: f64,
+    is_keyframe: bool,
+}
+
+impl BufferedSample {
+    unsafe fn retain(sample_buffer: *mut CMSampleBuffer) -> Self {
+        CFRetain(sample_buffer as *const c_void);
+
+        let presentation_time = CMSampleBufferGetPresentationTimeStamp(&*sample_buffer);
+        let presentation_seconds = if presentation_time.timescale > 0 {
+            presentation_time.value as f64 / presentation_time.timescale as f64
+        } else {
+            0.0
+        };
+
+        Self {
+            sample_buffer,
+            presentation_seconds,
+            is_keyframe: Self::is_keyframe(sample_buffer),
+        }
+    }
+
+    /// A sample is a keyframe unless its attachments mark it NotSync
+    unsafe fn is_keyframe(sample_buffer: *mut CMSampleBuffer) -> bool {
+        let attachments = CMSampleBufferGetSampleAttachmentsArray(sample_buffer, false);
+        if attachments.is_null() {
+            return true;
+        }
+
+        let count: usize = msg_send![attachments, count];
+        if count == 0 {
+            return true;
+        }
+
+        let attachment: *mut AnyObject = msg_send![attachments, objectAtIndex: 0usize];
+        let not_sync: *mut AnyObject = msg_send![attachment, objectForKey: kCMSampleAttachmentKey_NotSync];
+        if not_sync.is_null() {
+            return true;
+        }
+
+        let not_sync: bool = msg_send![not_sync, boolValue];
+        !not_sync
+    }
+}
+
+impl Clone for BufferedSample {
+    fn clone(&self) -> Self {
+        unsafe { CFRetain(self.sample_buffer as *const c_void); }
+        Self {
+            sample_buffer: self.sample_buffer,
+            presentation_seconds: self.presentation_seconds,
+            is_keyframe: self.is_keyframe,
+        }
+    }
+}
+
+impl Drop for BufferedSample {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.sample_buffer as *const c_void); }
+    }
+}
+
+/// Rolling window of encoded samples, always starting on a keyframe
+pub struct ReplayBuffer {
+    window_seconds: f64,
+    samples: VecDeque<BufferedSample>,
+}
+
+// Safety: CMSampleBuffers are immutable once encoded and retained while buffered
+unsafe impl Send for ReplayBuffer {}
+unsafe impl Sync for ReplayBuffer {}
+
+impl ReplayBuffer {
+    pub fn new(window_seconds: u32) -> Self {
+        Self {
+            window_seconds: window_seconds as f64,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Add an encoded sample and drop whole GOPs that fell out of the window
+    pub unsafe fn push(&mut self, sample_buffer: *mut CMSampleBuffer) {
+        let sample = BufferedSample::retain(sample_buffer);
+
+        // A replay can only start on a keyframe
+        if self.samples.is_empty() && !sample.is_keyframe {
+            return;
+        }
+
+        let cutoff = sample.presentation_seconds - self.window_seconds;
+        self.samples.push_back(sample);
+
+        while let Some(next_keyframe) = self.samples.iter().skip(1).position(|s| s.is_keyframe).map(|i| i + 1) {
+            if self.samples[next_keyframe].presentation_seconds > cutoff {
+                break;
+            }
+            self.samples.drain(..next_keyframe);
+        }
+    }
+
+    /// Copy of the buffered samples, so writing can happen without holding the buffer lock
+    fn snapshot(&self) -> Vec<BufferedSample> {
+        self.samples.iter().cloned().collect()
+    }
+}
+
+/// Hardware H.264 encoder feeding an instant replay buffer
+pub struct ReplayEncoder {
+    session: VTCompressionSessionRef,
+    buffer: Arc<Mutex<ReplayBuffer>>,
+    callback_buffer: *const Mutex<ReplayBuffer>,
+}
+
+// Safety: VTCompressionSession is thread-safe and only used behind a Mutex
+unsafe impl Send for ReplayEncoder {}
+unsafe impl Sync for ReplayEncoder {}
+
+impl ReplayEncoder {
+    pub fn new(width: u32, height: u32, window_seconds: u32) -> Result<Self> {
+        let buffer = Arc::new(Mutex::new(ReplayBuffer::new(window_seconds)));
+
+        unsafe {
+            // The compression callback holds its own reference to the buffer until Drop
+            let callback_buffer = Arc::into_raw(buffer.clone());
+
+            let mut session: VTCompressionSessionRef = ptr::null_mut();
+            let status = VTCompressionSessionCreate(
+                ptr::null(),
+                width as i32,
+                height as i32,
+                kCMVideoCodecType_H264,
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                compression_output_callback,
+                callback_buffer as *mut c_void,
+                &mut session,
+            );
+
+            if status != 0 || session.is_null() {
+                drop(Arc::from_raw(callback_buffer));
+                return Err(Error::new(Status::GenericFailure, format!("Failed to create replay encoder (OSStatus {})", status)));
+            }
+
+            let yes: *mut NSNumber = msg_send![class!(NSNumber), numberWithBool: true];
+            let no: *mut NSNumber = msg_send![class!(NSNumber), numberWithBool: false];
+            let key_frame_interval: *mut NSNumber = msg_send![class!(NSNumber), numberWithDouble: REPLAY_KEY_FRAME_INTERVAL_SECONDS];
+            VTSessionSetProperty(session, kVTCompressionPropertyKey_RealTime, yes as *const AnyObject);
+            VTSessionSetProperty(session, kVTCompressionPropertyKey_AllowFrameReordering, no as *const AnyObject);
+            VTSessionSetProperty(session, kVTCompressionPropertyKey_MaxKeyFrameIntervalDuration, key_frame_interval as *const AnyObject);
+
+            println!("⏪ Instant replay encoder ready: {}x{}, last {}s kept", width, height, window_seconds);
+
+            Ok(Self {
+                session,
+                buffer,
+                callback_buffer,
+            })
+        }
+    }
+
+    /// Encode a captured frame into the replay buffer
+    pub fn encode_frame(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
+        unsafe {
+            let pixel_buffer = CMSampleBufferGetImageBuffer(sample_buffer);
+            if pixel_buffer.is_null() {
+                return Err(Error::new(Status::GenericFailure, "No pixel buffer in sample"));
+            }
+
+            let presentation_time = CMSampleBufferGetPresentationTimeStamp(sample_buffer);
+            let status = VTCompressionSessionEncodeFrame(
+                self.session,
+                pixel_buffer,
+                presentation_time,
+                kCMTimeInvalid,
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+
+            if status != 0 {
+                return Err(Error::new(Status::GenericFailure, format!("Replay encoding failed (OSStatus {})", status)));
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Write the buffered window to `output_path` (without re-encoding) and return the path
+    pub fn save_replay(&self, output_path: &str) -> Result<String> {
+        unsafe {
+            // Flush frames still inside the encoder so the replay ends at "now"
+            VTCompressionSessionCompleteFrames(self.session, kCMTimeInvalid);
+        }
+
+        let samples = self.buffer.lock()
+            .map_err(|_| Error::new(Status::GenericFailure, "Replay buffer lock poisoned"))?
+            .snapshot();
+
+        if samples.is_empty() {
+            return Err(Error::new(Status::GenericFailure, "Replay buffer is empty: no frames captured yet"));
+        }
+
+        unsafe { write_samples(&samples, output_path)?; }
+
+        let duration = samples.last().unwrap().presentation_seconds - samples[0].presentation_seconds;
+        println!("⏪ Saved {:.1}s replay ({} samples): {}", duration, samples.len(), output_path);
+        Ok(output_path.to_string())
+    }
+}
+
+impl Drop for ReplayEncoder {
+    fn drop(&mut self) {
+        unsafe {
+            VTCompressionSessionInvalidate(self.session);
+            CFRelease(self.session as *const c_void);
+            drop(Arc::from_raw(self.callback_buffer));
+        }
+    }
+}
+
+/// VideoToolbox output callback: hand encoded samples to the replay buffer
+extern "C" fn compression_output_callback(
+    output_callback_refcon: *mut c_void,
+    _source_frame_refcon: *mut c_void,
+    status: i32,
+    info_flags: u32,
+    sample_buffer: *mut CMSampleBuffer,
+) {
+    if status != 0 || sample_buffer.is_null() || info_flags & kVTEncodeInfo_FrameDropped != 0 {
+        return;
+    }
+
+    let buffer = unsafe { &*(output_callback_refcon as *const Mutex<ReplayBuffer>) };
+    if let Ok(mut buffer) = buffer.lock() {
+        unsafe { buffer.push(sample_buffer); }
+    }
+}
+
+/// Pass-through write of encoded samples into a new MPEG-4 file
+unsafe fn write_samples(samples: &[BufferedSample], output_path: &str) -> Result<()> {
+    // AVAssetWriter refuses to overwrite an existing file
+    if std::path::Path::new(output_path).exists() {
+        std::fs::remove_file(output_path)
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to replace existing replay file: {}", e)))?;
+    }
+
+    let url_string = NSString::from_str(output_path);
+    let file_url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*url_string];
+
+    let mut error: *mut NSError = ptr::null_mut();
+    let file_type = NSString::from_str(AVFileTypeMPEG4);
+    let asset_writer: *mut AVAssetWriter = msg_send![
+        class!(AVAssetWriter),
+        assetWriterWithURL: file_url,
+        fileType: &*file_type,
+        error: &mut error
+    ];
+
+    if asset_writer.is_null() || !error.is_null() {
+        return Err(Error::new(
+            Status::GenericFailure,
+            format!("Failed to create replay AVAssetWriter: {}", describe_nserror(error)),
+        ));
+    }
+
+    // nil output settings means samples are written as-is
+    let format_hint = CMSampleBufferGetFormatDescription(samples[0].sample_buffer);
+    let media_type = NSString::from_str(AVMediaTypeVideo);
+    let video_input: *mut AVAssetWriterInput = msg_send![
+        class!(AVAssetWriterInput),
+        assetWriterInputWithMediaType: &*media_type,
+        outputSettings: ptr::null_mut::<AnyObject>(),
+        sourceFormatHint: format_hint as *mut AnyObject
+    ];
+
+    let can_add: bool = msg_send![asset_writer, canAddInput: video_input];
+    if !can_add {
+        return Err(Error::new(Status::GenericFailure, "Cannot add replay video input"));
+    }
+    let _: () = msg_send![asset_writer, addInput: video_input];
+
+    let started: bool = msg_send![asset_writer, startWriting];
+    if !started {
+        return Err(Error::new(Status::GenericFailure, "Failed to start writing replay"));
+    }
+
+    let start_time = CMSampleBufferGetPresentationTimeStamp(&*samples[0].sample_buffer);
+    let _: () = msg_send![asset_writer, startSessionAtSourceTime: start_time];
+
+    for sample in samples {
+        // A writer that failed never becomes ready again
+        let waiting_since = std::time::Instant::now();
+        loop {
+            let ready: bool = msg_send![video_input, isReadyForMoreMediaData];
+            if ready {
+                break;
+            }
+            let status: isize = msg_send![asset_writer, status];
+            if status == WRITER_STATUS_FAILED {
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Replay writer failed: {}", asset_writer_error_description(asset_writer)),
+                ));
+            }
+            if waiting_since.elapsed() >= REPLAY_INPUT_TIMEOUT {
+                let _: () = msg_send![asset_writer, cancelWriting];
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Replay writer took no samples for {}s", REPLAY_INPUT_TIMEOUT.as_secs()),
+                ));
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        let success: bool = msg_send![video_input, appendSampleBuffer: sample.sample_buffer];
+        if !success {
+            let description = asset_writer_error_description(asset_writer);
+            let _: () = msg_send![asset_writer, cancelWriting];
+            return Err(Error::new(Status::GenericFailure, format!("Failed to write replay sample: {}", description)));
+        }
+    }
+
+    let _: () = msg_send![video_input, markAsFinished];
+    let _: () = msg_send![asset_writer, finishWriting];
+
+    // Status: 2 = Completed
+    let status: isize = msg_send![asset_writer, status];
+    if status != 2 {
+        return Err(Error::new(Status::GenericFailure, format!("Replay writer finished with status {}", status)));
+    }
+
+    Ok(())
+}
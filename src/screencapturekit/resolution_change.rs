@@ -0,0 +1,240 @@
+// Frame size changes mid-recording
+// A display switched to another resolution (or, with scalingQuality "high", a window resized)
+// starts delivering frames of a new size, which the writer set up for the first frames
+// can't take. ResolutionWatch remembers the size of the first frame and notices when one
+// differs. By default such frames are scaled back to that size with a VTPixelTransferSession,
+// which handles every capture pixel format (vImage would only cover BGRA); with
+// resolutionChangeBehavior "segment" the delegate instead starts a new file at the new size
+
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::NSString;
+use objc2_core_media::{CMSampleBuffer, CMTime};
+use objc2_core_video::CVPixelBuffer;
+
+use super::region::wrap_pixel_buffer;
+use super::transcode::{dictionary, number_u32};
+use super::types::{ResolutionChangeBehavior, ScalingMode};
+
+extern "C" {
+    static kVTPixelTransferPropertyKey_ScalingMode: *const AnyObject;
+    static kVTScalingMode_Normal: *const AnyObject;
+    static kVTScalingMode_Letterbox: *const AnyObject;
+    static kCVPixelBufferPixelFormatTypeKey: *const NSString;
+    static kCVPixelBufferWidthKey: *const NSString;
+    static kCVPixelBufferHeightKey: *const NSString;
+    static kCVPixelBufferIOSurfacePropertiesKey: *const NSString;
+
+    fn VTPixelTransferSessionCreate(allocator: *const c_void, session_out: *mut *mut c_void) -> i32;
+    fn VTPixelTransferSessionTransferImage(session: *mut c_void, source: *mut CVPixelBuffer, destination: *mut CVPixelBuffer) -> i32;
+    fn VTPixelTransferSessionInvalidate(session: *mut c_void);
+    fn VTSessionSetProperty(session: *mut c_void, key: *const AnyObject, value: *const AnyObject) -> i32;
+    fn CVPixelBufferPoolCreate(
+        allocator: *const c_void,
+        pool_attributes: *const c_void,
+        pixel_buffer_attributes: *const c_void,
+        pool_out: *mut *mut c_void,
+    ) -> i32;
+    fn CVPixelBufferPoolCreatePixelBuffer(allocator: *const c_void, pool: *mut c_void, pixel_buffer_out: *mut *mut CVPixelBuffer) -> i32;
+    fn CVPixelBufferPoolRelease(pool: *mut c_void);
+    fn CVPixelBufferRelease(pixel_buffer: *mut CVPixelBuffer);
+    fn CVPixelBufferGetWidth(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetHeight(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetPixelFormatType(pixel_buffer: *mut CVPixelBuffer) -> u32;
+    fn CMSampleBufferGetImageBuffer(sbuf: &CMSampleBuffer) -> *mut CVPixelBuffer;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Told when a frame size change started a new segment
+#[derive(Debug, Clone)]
+pub struct NewSegmentEvent {
+    /// The finalized file of the old size
+    pub finished_path: String,
+    /// The file the recording continues in
+    pub next_path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Receives new segments started by resolutionChangeBehavior "segment"
+pub type NewSegmentListener = Arc<dyn Fn(NewSegmentEvent) + Send + Sync>;
+
+/// The file of the `index`th segment of a recording at `output_path`, counting from 1:
+/// "screen.mp4" continues in "screen_part2.mp4"
+pub fn segment_path(output_path: &str, index: u32) -> String {
+    if index <= 1 {
+        return output_path.to_string();
+    }
+    let file_start = output_path.rfind('/').map_or(0, |slash| slash + 1);
+    match output_path[file_start..].rfind('.').filter(|&dot| dot > 0) {
+        Some(dot) => {
+            let (stem, extension) = output_path.split_at(file_start + dot);
+            format!("{}_part{}{}", stem, index, extension)
+        }
+        None => format!("{}_part{}", output_path, index),
+    }
+}
+
+/// A sample buffer this module made, released when dropped
+pub struct ScaledSample(*mut CMSampleBuffer);
+
+impl ScaledSample {
+    pub fn sample(&self) -> &CMSampleBuffer {
+        unsafe { &*self.0 }
+    }
+}
+
+impl Drop for ScaledSample {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0 as *const c_void) };
+    }
+}
+
+/// Scales pixel buffers of any size to a fixed one in their own pixel format
+struct FrameScaler {
+    session: *mut c_void,
+    pool: *mut c_void,
+}
+
+// Safety: only used under the ResolutionWatch mutex
+unsafe impl Send for FrameScaler {}
+
+impl FrameScaler {
+    unsafe fn new(width: u32, height: u32, pixel_format: u32, mode: ScalingMode) -> Option<Self> {
+        let mut session: *mut c_void = ptr::null_mut();
+        if VTPixelTransferSessionCreate(ptr::null(), &mut session) != 0 || session.is_null() {
+            return None;
+        }
+        // There's no aspect-filling transfer; "fill" letterboxes as well
+        let scaling_mode = if mode == ScalingMode::Stretch { kVTScalingMode_Normal } else { kVTScalingMode_Letterbox };
+        VTSessionSetProperty(session, kVTPixelTransferPropertyKey_ScalingMode, scaling_mode);
+
+        let io_surface: *mut AnyObject = msg_send![class!(NSDictionary), dictionary];
+        let attributes = dictionary(&[
+            (kCVPixelBufferPixelFormatTypeKey, number_u32(pixel_format)),
+            (kCVPixelBufferWidthKey, number_u32(width)),
+            (kCVPixelBufferHeightKey, number_u32(height)),
+            (kCVPixelBufferIOSurfacePropertiesKey, io_surface),
+        ]);
+        let mut pool: *mut c_void = ptr::null_mut();
+        if CVPixelBufferPoolCreate(ptr::null(), ptr::null(), attributes as *const c_void, &mut pool) != 0 || pool.is_null() {
+            VTPixelTransferSessionInvalidate(session);
+            CFRelease(session);
+            return None;
+        }
+        Some(Self { session, pool })
+    }
+
+    /// `sample_buffer`'s frame at the scaler's size, at the same presentation time
+    unsafe fn scale(&self, sample_buffer: &CMSampleBuffer) -> Option<ScaledSample> {
+        let source = CMSampleBufferGetImageBuffer(sample_buffer);
+        let mut destination: *mut CVPixelBuffer = ptr::null_mut();
+        if source.is_null() || CVPixelBufferPoolCreatePixelBuffer(ptr::null(), self.pool, &mut destination) != 0 {
+            return None;
+        }
+        let scaled = VTPixelTransferSessionTransferImage(self.session, source, destination) == 0;
+        let sample = scaled
+            .then(|| wrap_pixel_buffer(destination, CMSampleBufferGetPresentationTimeStamp(sample_buffer)))
+            .flatten();
+        CVPixelBufferRelease(destination);
+        sample.map(ScaledSample)
+    }
+}
+
+impl Drop for FrameScaler {
+    fn drop(&mut self) {
+        unsafe {
+            VTPixelTransferSessionInvalidate(self.session);
+            CFRelease(self.session);
+            CVPixelBufferPoolRelease(self.pool);
+        }
+    }
+}
+
+#[derive(Default)]
+struct WatchState {
+    // Size of the frames the writer takes
+    size: Option<(u32, u32)>,
+    // The last other size reported, so a lasting change is logged once
+    reported: Option<(u32, u32)>,
+    scaler: Option<(FrameScaler, u32)>,
+    scale_failed: bool,
+}
+
+/// Notices frames whose size differs from the first frame's
+pub struct ResolutionWatch {
+    behavior: ResolutionChangeBehavior,
+    scaling_mode: ScalingMode,
+    state: Mutex<WatchState>,
+}
+
+impl ResolutionWatch {
+    pub fn new(behavior: ResolutionChangeBehavior, scaling_mode: ScalingMode) -> Self {
+        Self { behavior, scaling_mode, state: Mutex::new(WatchState::default()) }
+    }
+
+    pub fn behavior(&self) -> ResolutionChangeBehavior {
+        self.behavior
+    }
+
+    /// The new size when `sample_buffer` isn't the size of the recording's frames; the first
+    /// frame with pixels decides that size
+    pub fn changed_size(&self, sample_buffer: &CMSampleBuffer) -> Option<(u32, u32)> {
+        let pixel_buffer = unsafe { CMSampleBufferGetImageBuffer(sample_buffer) };
+        if pixel_buffer.is_null() {
+            return None;
+        }
+        let size = unsafe { (CVPixelBufferGetWidth(pixel_buffer) as u32, CVPixelBufferGetHeight(pixel_buffer) as u32) };
+        let mut state = self.state.lock().ok()?;
+        let recorded = *state.size.get_or_insert(size);
+        if size == recorded {
+            return None;
+        }
+        if state.reported != Some(size) {
+            println!("🖥️ Frames changed from {}x{} to {}x{}", recorded.0, recorded.1, size.0, size.1);
+            state.reported = Some(size);
+        }
+        Some(size)
+    }
+
+    /// Take frames of `width` x `height` from now on, after a new segment started at that size
+    pub fn adopt(&self, width: u32, height: u32) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = WatchState { size: Some((width, height)), ..WatchState::default() };
+        }
+    }
+
+    /// `sample_buffer` scaled to the recording's frame size; None when it can't be
+    pub fn scale(&self, sample_buffer: &CMSampleBuffer) -> Option<ScaledSample> {
+        let mut state = self.state.lock().ok()?;
+        let (width, height) = state.size?;
+        let pixel_format = unsafe { CVPixelBufferGetPixelFormatType(CMSampleBufferGetImageBuffer(sample_buffer)) };
+        if state.scaler.as_ref().is_none_or(|(_, format)| *format != pixel_format) {
+            state.scaler = unsafe { FrameScaler::new(width, height, pixel_format, self.scaling_mode) }
+                .map(|scaler| (scaler, pixel_format));
+        }
+        let scaled = state.scaler.as_ref().and_then(|(scaler, _)| unsafe { scaler.scale(sample_buffer) });
+        if scaled.is_none() && !state.scale_failed {
+            println!("⚠️ Couldn't scale frames back to {}x{}; they may not be recorded", width, height);
+        }
+        state.scale_failed = scaled.is_none();
+        scaled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments_are_numbered_before_the_extension() {
+        assert_eq!(segment_path("/tmp/screen.mp4", 1), "/tmp/screen.mp4");
+        assert_eq!(segment_path("/tmp/screen.mp4", 2), "/tmp/screen_part2.mp4");
+        assert_eq!(segment_path("/tmp/v1.0/screen", 3), "/tmp/v1.0/screen_part3", "A dot in a directory isn't an extension");
+        assert_eq!(segment_path("/tmp/.hidden", 2), "/tmp/.hidden_part2");
+    }
+}
@@ -0,0 +1,94 @@
+// Run loop pumping for ScreenCaptureKit callbacks
+// ScreenCaptureKit delivers completion handlers on its own queues, but anything it (or AppKit)
+// schedules on the main queue only runs while the main thread's run loop is spinning. Node
+// drives its main thread with libuv, not a CFRunLoop, so awaiting a callback there without
+// pumping the run loop can wait forever. Worker threads need nothing extra, except to reach
+// AppKit state that's only safe to read on the main thread (see `on_main_queue`)
+
+use std::ffi::c_void;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+// Longest the run loop is run between polls of the awaited future
+const RUN_LOOP_SLICE_SECONDS: f64 = 0.01;
+
+extern "C" {
+    static kCFRunLoopDefaultMode: *const c_void;
+    fn CFRunLoopRunInMode(mode: *const c_void, seconds: f64, return_after_source_handled: bool) -> i32;
+    fn pthread_main_np() -> i32;
+    // What dispatch_get_main_queue() returns
+    static _dispatch_main_q: c_void;
+    fn dispatch_async_f(queue: *const c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+}
+
+/// Whether the caller is on the process's main thread
+pub fn is_main_thread() -> bool {
+    unsafe { pthread_main_np() != 0 }
+}
+
+/// Run the current thread's run loop once for at most `seconds`, returning early after
+/// a source is handled
+pub fn run_once(seconds: f64) {
+    unsafe {
+        CFRunLoopRunInMode(kCFRunLoopDefaultMode, seconds, true);
+    }
+}
+
+/// Future returned by `with_run_loop`
+pub struct RunLoopPumped<F> {
+    inner: F,
+}
+
+impl<F: Future + Unpin> Future for RunLoopPumped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = Pin::new(&mut self.inner).poll(cx) {
+            return Poll::Ready(output);
+        }
+
+        if is_main_thread() {
+            // Let main-queue blocks run, then poll again; off the main thread the inner
+            // future's own waker is enough
+            run_once(RUN_LOOP_SLICE_SECONDS);
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+/// Await `future` (typically the oneshot receiver a completion handler sends on) while
+/// spinning the run loop when called on the main thread
+pub fn with_run_loop<F: Future + Unpin>(future: F) -> RunLoopPumped<F> {
+    RunLoopPumped { inner: future }
+}
+
+/// Run `f` on the main queue and wait up to `timeout` for its result; runs it in place on the
+/// main thread. None when the main queue didn't get to it in time, which is the norm under
+/// plain Node, where the main thread sits in libuv between calls; `f` then runs whenever the
+/// main run loop next spins, so it must not need the caller to still be waiting
+pub fn on_main_queue<R, F>(timeout: Duration, f: F) -> Option<R>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    if is_main_thread() {
+        return Some(f());
+    }
+
+    extern "C" fn run<F: FnOnce() -> R, R>(context: *mut c_void) {
+        let (f, sender) = *unsafe { Box::from_raw(context as *mut (F, mpsc::Sender<R>)) };
+        // The caller may have stopped waiting
+        let _ = sender.send(f());
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let work = Box::into_raw(Box::new((f, sender)));
+    unsafe {
+        dispatch_async_f(&_dispatch_main_q, work as *mut c_void, run::<F, R>);
+    }
+    receiver.recv_timeout(timeout).ok()
+}
@@ -0,0 +1,96 @@
+// Secure input detection
+// While a password field (or any app) holds secure event input, macOS may blank what the
+// capture sees. A background thread polls IsSecureEventInputEnabled while recording and
+// reports each change so the user knows why part of the video is black; recordings can
+// also leave those stretches out by pausing encoding
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use super::recording::unix_time_ms;
+use super::types::PausedSpan;
+
+// How often secure input is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+extern "C" {
+    // HIToolbox (Carbon); returns a Boolean
+    fn IsSecureEventInputEnabled() -> u8;
+}
+
+/// Whether some process currently has secure event input enabled
+pub fn is_secure_input_enabled() -> bool {
+    unsafe { IsSecureEventInputEnabled() != 0 }
+}
+
+/// Polls secure input on a background thread; dropping it stops the thread
+pub struct SecureInputMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    /// Start and end of each stretch in secure input, in ms since the Unix epoch
+    spans: Arc<Mutex<Vec<(f64, Option<f64>)>>>,
+}
+
+impl SecureInputMonitor {
+    /// Start polling; `on_change` gets true when secure input turns on (also when it's already
+    /// on at start) and false when it turns off again
+    pub fn start<F>(on_change: F) -> Self
+    where
+        F: Fn(bool) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let thread_stop = stop.clone();
+        let thread_spans = spans.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut active = false;
+            while !thread_stop.load(Ordering::SeqCst) {
+                let enabled = is_secure_input_enabled();
+                if enabled != active {
+                    active = enabled;
+                    if let Ok(mut spans) = thread_spans.lock() {
+                        if active {
+                            spans.push((unix_time_ms(), None));
+                        } else if let Some(span) = spans.last_mut() {
+                            span.1 = Some(unix_time_ms());
+                        }
+                    }
+                    println!("{}", if active {
+                        "🔒 Secure input is active; the capture may be blank until it ends"
+                    } else {
+                        "🔓 Secure input ended"
+                    });
+                    on_change(active);
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        Self { stop, thread: Some(thread), spans }
+    }
+
+    /// Stop polling and return the stretches spent in secure input, an open one ending now
+    pub fn finish(mut self) -> Vec<PausedSpan> {
+        self.stop_thread();
+        let now = unix_time_ms();
+        self.spans.lock()
+            .map(|spans| spans.iter().map(|&(from, to)| PausedSpan { from, to: to.unwrap_or(now) }).collect())
+            .unwrap_or_default()
+    }
+
+    fn stop_thread(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SecureInputMonitor {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
@@ -0,0 +1,223 @@
+// Segmented recordings
+// An interrupted recording can be continued into a new file whose timestamps pick up where the
+// previous segment ended; the segments are joined afterwards with an AVMutableComposition
+// exported in passthrough, so nothing is re-encoded
+
+use std::path::Path;
+use std::ptr;
+use std::sync::mpsc;
+use block2::RcBlock;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSString, NSURL, NSError, NSArray};
+use objc2_core_media::{CMTime, CMTimeRange, kCMTimeZero};
+use napi::{Result, Status, Error};
+
+use super::encoder::{VideoEncoderSettings, AVMediaTypeVideo, file_type_for};
+use super::foundation::{CGSize, describe_nserror};
+use super::types::VideoCodec;
+
+// AVAssetExportSessionStatusCompleted
+const EXPORT_STATUS_COMPLETED: isize = 3;
+
+extern "C" {
+    fn CMFormatDescriptionGetMediaSubType(desc: *mut AnyObject) -> u32;
+    fn CMTimeGetSeconds(time: CMTime) -> f64;
+    fn CMTimeAdd(lhs: CMTime, rhs: CMTime) -> CMTime;
+}
+
+/// Codec, size and length of a finished segment
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentInfo {
+    pub duration: CMTime,
+    pub width: u32,
+    pub height: u32,
+    pub codec: VideoCodec,
+}
+
+impl SegmentInfo {
+    /// Read the first video track of the movie at `path`
+    pub fn probe(path: &str) -> Result<Self> {
+        if !Path::new(path).is_file() {
+            return Err(Error::new(Status::InvalidArg, format!("Segment not found: {}", path)));
+        }
+
+        unsafe {
+            let asset = open_asset(path);
+            if asset.is_null() {
+                return Err(Error::new(Status::InvalidArg, format!("Failed to open segment {}", path)));
+            }
+            let media_type = NSString::from_str(AVMediaTypeVideo);
+            let tracks: *mut NSArray<AnyObject> = msg_send![asset, tracksWithMediaType: &*media_type];
+            let track: *mut AnyObject = if tracks.is_null() { ptr::null_mut() } else { msg_send![tracks, firstObject] };
+            if track.is_null() {
+                return Err(Error::new(Status::InvalidArg, format!("Segment has no video track: {}", path)));
+            }
+
+            let descriptions: *mut NSArray<AnyObject> = msg_send![track, formatDescriptions];
+            let description: *mut AnyObject = if descriptions.is_null() { ptr::null_mut() } else { msg_send![descriptions, firstObject] };
+            let codec = if description.is_null() {
+                None
+            } else {
+                codec_for_media_subtype(CMFormatDescriptionGetMediaSubType(description))
+            };
+            let codec = codec.ok_or_else(|| Error::new(
+                Status::InvalidArg,
+                format!("Segment {} isn't H.264 or HEVC video", path),
+            ))?;
+
+            let natural_size: CGSize = msg_send![track, naturalSize];
+            Ok(Self {
+                duration: msg_send![asset, duration],
+                width: natural_size.width.round() as u32,
+                height: natural_size.height.round() as u32,
+                codec,
+            })
+        }
+    }
+
+    /// Length in seconds
+    pub fn duration_seconds(&self) -> f64 {
+        unsafe { CMTimeGetSeconds(self.duration) }
+    }
+
+    /// Error unless a recording with `settings` produces video this segment can be joined with
+    pub fn check_matches(&self, settings: &VideoEncoderSettings) -> Result<()> {
+        if settings.codec != self.codec {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("videoCodec {:?} doesn't match the previous segment's {:?}", settings.codec, self.codec),
+            ));
+        }
+        if (settings.width, settings.height) != (self.width, self.height) {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!(
+                    "Output size {}x{} doesn't match the previous segment's {}x{}",
+                    settings.width, settings.height, self.width, self.height,
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// H.264 ('avc1') or HEVC ('hvc1', 'hev1') format description subtypes
+fn codec_for_media_subtype(subtype: u32) -> Option<VideoCodec> {
+    match &subtype.to_be_bytes() {
+        b"avc1" => Some(VideoCodec::H264),
+        b"hvc1" | b"hev1" => Some(VideoCodec::Hevc),
+        _ => None,
+    }
+}
+
+pub(super) unsafe fn open_asset(path: &str) -> *mut AnyObject {
+    let path_string = NSString::from_str(path);
+    let url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*path_string];
+    msg_send![class!(AVURLAsset), URLAssetWithURL: url, options: ptr::null_mut::<AnyObject>()]
+}
+
+/// Join `segment_paths` end to end into `output_path` (.mp4, .m4v or .mov, must not exist),
+/// blocking until the file is written. The segments must share codec and size
+pub fn concatenate_segments(segment_paths: &[String], output_path: &str) -> Result<String> {
+    if segment_paths.is_empty() {
+        return Err(Error::new(Status::InvalidArg, "segmentPaths cannot be empty"));
+    }
+    let file_type = file_type_for(output_path).ok_or_else(|| Error::new(
+        Status::InvalidArg,
+        format!("Output must be a .mp4, .m4v or .mov file: {}", output_path),
+    ))?;
+    if Path::new(output_path).exists() {
+        return Err(Error::new(Status::InvalidArg, format!("Output file already exists: {}", output_path)));
+    }
+
+    let first = SegmentInfo::probe(&segment_paths[0])?;
+    for path in &segment_paths[1..] {
+        let segment = SegmentInfo::probe(path)?;
+        if segment.codec != first.codec || (segment.width, segment.height) != (first.width, first.height) {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!(
+                    "Segment {} is {:?} {}x{}, expected {:?} {}x{} like the first segment",
+                    path, segment.codec, segment.width, segment.height, first.codec, first.width, first.height,
+                ),
+            ));
+        }
+    }
+
+    println!("🧩 Concatenating {} segments -> {}", segment_paths.len(), output_path);
+    let result = unsafe { export_composition(segment_paths, output_path, file_type) };
+    match result {
+        Ok(()) => {
+            println!("✅ Segments concatenated: {}", output_path);
+            Ok(output_path.to_string())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(output_path);
+            println!("❌ Concatenation failed: {}", e.reason);
+            Err(e)
+        }
+    }
+}
+
+unsafe fn export_composition(segment_paths: &[String], output_path: &str, file_type: &str) -> Result<()> {
+    let composition: *mut AnyObject = msg_send![class!(AVMutableComposition), composition];
+    let mut cursor = kCMTimeZero;
+    for path in segment_paths {
+        let asset = open_asset(path);
+        let duration: CMTime = msg_send![asset, duration];
+        let range = CMTimeRange { start: kCMTimeZero, duration };
+        let mut error: *mut NSError = ptr::null_mut();
+        let inserted: bool = msg_send![composition, insertTimeRange: range, ofAsset: asset, atTime: cursor, error: &mut error];
+        if !inserted {
+            return Err(Error::new(Status::GenericFailure, format!("Failed to add segment {}: {}", path, describe_nserror(error))));
+        }
+        cursor = CMTimeAdd(cursor, duration);
+    }
+
+    let preset = NSString::from_str("AVAssetExportPresetPassthrough");
+    let session: *mut AnyObject = msg_send![class!(AVAssetExportSession), exportSessionWithAsset: composition, presetName: &*preset];
+    if session.is_null() {
+        return Err(Error::new(Status::GenericFailure, "Failed to create the export session"));
+    }
+    let output_string = NSString::from_str(output_path);
+    let output_url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*output_string];
+    let file_type = NSString::from_str(file_type);
+    let _: () = msg_send![session, setOutputURL: output_url];
+    let _: () = msg_send![session, setOutputFileType: &*file_type];
+
+    // The completion handler runs on an AVFoundation queue; callers are off the main thread
+    let (sender, receiver) = mpsc::channel();
+    let completion = RcBlock::new(move || {
+        let _ = sender.send(());
+    });
+    let _: () = msg_send![session, exportAsynchronouslyWithCompletionHandler: &*completion];
+    receiver.recv().map_err(|_| Error::new(Status::GenericFailure, "Export session ended without completing"))?;
+
+    let status: isize = msg_send![session, status];
+    if status != EXPORT_STATUS_COMPLETED {
+        let error: *mut NSError = msg_send![session, error];
+        return Err(Error::new(Status::GenericFailure, format!("Failed to write {}: {}", output_path, describe_nserror(error))));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_must_match_the_next_recording() {
+        let segment = SegmentInfo { duration: unsafe { kCMTimeZero }, width: 1280, height: 720, codec: VideoCodec::H264 };
+        let settings = VideoEncoderSettings { width: 1280, height: 720, ..VideoEncoderSettings::default() };
+        assert!(segment.check_matches(&settings).is_ok());
+
+        let resized = VideoEncoderSettings { width: 1920, height: 1080, ..settings.clone() };
+        assert_eq!(segment.check_matches(&resized).expect_err("Size differs").status, Status::InvalidArg);
+        let hevc = VideoEncoderSettings { codec: VideoCodec::Hevc, ..settings };
+        assert!(segment.check_matches(&hevc).is_err(), "Codec differs");
+
+        assert_eq!(codec_for_media_subtype(u32::from_be_bytes(*b"hev1")), Some(VideoCodec::Hevc));
+        assert_eq!(codec_for_media_subtype(u32::from_be_bytes(*b"apcn")), None);
+    }
+}
@@ -0,0 +1,140 @@
+// Login session state
+// ScreenCaptureKit only captures inside the logged-in user's GUI session. At the login
+// window there is no such session for an app (or LaunchAgent) to run in, and while the screen
+// is locked or the session is switched out the system hands streams blank frames or stops
+// them. The session dictionary tells those cases apart so starts fail with a precise error
+// and diagnostics can show why capture is blocked
+
+use std::ffi::c_void;
+use objc2::runtime::AnyObject;
+use objc2::msg_send;
+use objc2_foundation::NSString;
+use serde::Serialize;
+
+// Keys of the CGSession dictionary
+const SCREEN_IS_LOCKED_KEY: &str = "CGSSessionScreenIsLocked";
+const ON_CONSOLE_KEY: &str = "kCGSSessionOnConsoleKey"; // kCGSessionOnConsoleKey
+const LOGIN_DONE_KEY: &str = "kCGSessionLoginDoneKey"; // kCGSessionLoginDoneKey
+
+extern "C" {
+    // CoreGraphics; NULL when the caller isn't part of a GUI session
+    fn CGSessionCopyCurrentDictionary() -> *const c_void;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Whether the current login session can be captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// Logged in, unlocked and on the console
+    Active,
+    /// The screen is locked
+    Locked,
+    /// Login isn't finished yet; the login window is showing
+    LoginWindow,
+    /// Fast user switching moved this session off the console
+    Background,
+    /// Not running in a GUI login session (a daemon, or ssh without a logged-in user)
+    NoSession,
+}
+
+impl SessionState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionState::Active => "active",
+            SessionState::Locked => "locked",
+            SessionState::LoginWindow => "loginWindow",
+            SessionState::Background => "background",
+            SessionState::NoSession => "noSession",
+        }
+    }
+
+    /// Why capture is blocked, None when the session can be captured
+    pub fn blocked_reason(self) -> Option<&'static str> {
+        match self {
+            SessionState::Active => None,
+            SessionState::Locked => Some("the screen is locked; unlock it to record"),
+            SessionState::LoginWindow => Some(
+                "the login window is showing; ScreenCaptureKit can only record after a user has logged in",
+            ),
+            SessionState::Background => Some("this login session isn't on the console (another user is active)"),
+            SessionState::NoSession => Some(
+                "the process isn't running in a GUI login session; run it as the logged-in user, not as a daemon",
+            ),
+        }
+    }
+}
+
+/// The raw session flags, as reported in diagnostics
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    /// False when there's no GUI session and the other flags are meaningless
+    pub has_session: bool,
+    pub screen_locked: bool,
+    pub on_console: bool,
+    pub login_done: bool,
+}
+
+impl SessionInfo {
+    pub fn state(&self) -> SessionState {
+        if !self.has_session {
+            SessionState::NoSession
+        } else if !self.login_done {
+            SessionState::LoginWindow
+        } else if self.screen_locked {
+            SessionState::Locked
+        } else if !self.on_console {
+            SessionState::Background
+        } else {
+            SessionState::Active
+        }
+    }
+}
+
+/// Read the current session dictionary
+pub fn current_session() -> SessionInfo {
+    unsafe {
+        let dictionary = CGSessionCopyCurrentDictionary();
+        if dictionary.is_null() {
+            return SessionInfo { has_session: false, screen_locked: false, on_console: false, login_done: false };
+        }
+        // CFDictionary is toll-free bridged to NSDictionary
+        let dictionary_obj = dictionary as *mut AnyObject;
+        let flag = |key: &str, default: bool| -> bool {
+            let key = NSString::from_str(key);
+            let value: *mut AnyObject = msg_send![dictionary_obj, objectForKey: &*key];
+            if value.is_null() { default } else { msg_send![value, boolValue] }
+        };
+        let info = SessionInfo {
+            has_session: true,
+            screen_locked: flag(SCREEN_IS_LOCKED_KEY, false),
+            on_console: flag(ON_CONSOLE_KEY, true),
+            login_done: flag(LOGIN_DONE_KEY, true),
+        };
+        CFRelease(dictionary);
+        info
+    }
+}
+
+/// The current session state
+pub fn session_state() -> SessionState {
+    current_session().state()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_flags_map_to_states() {
+        let active = SessionInfo { has_session: true, screen_locked: false, on_console: true, login_done: true };
+        assert_eq!(active.state(), SessionState::Active);
+        assert!(active.state().blocked_reason().is_none());
+
+        assert_eq!(SessionInfo { screen_locked: true, ..active }.state(), SessionState::Locked);
+        assert_eq!(SessionInfo { login_done: false, screen_locked: true, ..active }.state(), SessionState::LoginWindow);
+        assert_eq!(SessionInfo { on_console: false, ..active }.state(), SessionState::Background);
+        assert_eq!(SessionInfo { has_session: false, ..active }.state(), SessionState::NoSession);
+        assert!(SessionState::Locked.blocked_reason().is_some());
+    }
+}
@@ -0,0 +1,93 @@
+// Shareable content change watching
+// ScreenCaptureKit has no change notification for displays and windows, so a background
+// thread polls the shareable content and reports when the source list changes
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::ScreenSource;
+use super::content::{AppFilter, AsyncContentManager};
+
+pub const DEFAULT_SOURCE_POLL_INTERVAL_MS: u32 = 2000;
+// Faster polling costs a full shareable content retrieval each time
+pub const MIN_SOURCE_POLL_INTERVAL_MS: u32 = 250;
+
+// Granularity of the stop check inside a poll interval
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Polls displays and windows until stopped or dropped
+pub struct SourceWatch {
+    stop: Arc<AtomicBool>,
+}
+
+impl SourceWatch {
+    /// Poll every `interval_ms` and call `on_change` with the full source list whenever a
+    /// source is added or removed (or renamed); the first poll only records the baseline.
+    /// Windows `filter` rejects are neither listed nor counted as changes
+    pub fn start<F>(interval_ms: u32, filter: AppFilter, on_change: F) -> Self
+    where
+        F: Fn(Vec<ScreenSource>) + Send + 'static,
+    {
+        println!("👀 Watching shareable content every {}ms", interval_ms);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let interval = Duration::from_millis(interval_ms.max(MIN_SOURCE_POLL_INTERVAL_MS) as u64);
+
+        std::thread::spawn(move || {
+            // Content retrieval is async with tokio timeouts; give the watcher its own runtime
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    println!("❌ Source watcher could not start: {}", e);
+                    return;
+                }
+            };
+
+            let mut previous: Option<Vec<(String, String)>> = None;
+            while !thread_stop.load(Ordering::SeqCst) {
+                let sources = runtime.block_on(async {
+                    let content = AsyncContentManager::get_shareable_content().await?;
+                    content.get_all_sources(&filter).await
+                });
+
+                match sources {
+                    Ok(sources) => {
+                        let mut key: Vec<(String, String)> = sources.iter()
+                            .map(|source| (source.id.clone(), source.name.clone()))
+                            .collect();
+                        key.sort();
+
+                        if previous.as_ref().is_some_and(|previous| *previous != key) && !thread_stop.load(Ordering::SeqCst) {
+                            println!("🔄 Sources changed: {} available", sources.len());
+                            on_change(sources);
+                        }
+                        previous = Some(key);
+                    }
+                    Err(e) => println!("⚠️ Source watcher poll failed: {}", e.reason),
+                }
+
+                let mut waited = Duration::ZERO;
+                while waited < interval && !thread_stop.load(Ordering::SeqCst) {
+                    std::thread::sleep(STOP_CHECK_INTERVAL);
+                    waited += STOP_CHECK_INTERVAL;
+                }
+            }
+        });
+
+        Self { stop }
+    }
+
+    /// Stop polling without waiting for an in-flight retrieval; its result is discarded
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for SourceWatch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        println!("🛑 Source watcher stopped");
+    }
+}
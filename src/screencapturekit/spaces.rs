@@ -0,0 +1,168 @@
+// Spaces (virtual desktops)
+// Neither ScreenCaptureKit nor any public API can see Spaces: a display capture always shows
+// the Space on screen, and windows on other Spaces aren't drawn, so they come out blank.
+// The Spaces of each display are read from the window server's private
+// CGSCopyManagedDisplaySpaces, looked up at runtime so a macOS without it only loses this
+// listing. A Space can be recorded through its display while it's the one showing there;
+// any other Space is refused with an explanation rather than recording the wrong desktop
+
+use std::ffi::c_void;
+use std::ptr;
+use objc2::runtime::AnyObject;
+use objc2::msg_send;
+use objc2_foundation::NSString;
+use napi::{Result, Status, Error};
+
+use crate::SpaceInfo;
+use super::foundation::CoreGraphicsHelpers;
+
+extern "C" {
+    fn dlsym(handle: *mut c_void, symbol: *const std::ffi::c_char) -> *mut c_void;
+    fn CGDisplayCreateUUIDFromDisplayID(display: u32) -> *const c_void;
+    fn CFUUIDCreateString(allocator: *const c_void, uuid: *const c_void) -> *const NSString;
+    fn CFRelease(cf: *const c_void);
+}
+
+// RTLD_DEFAULT on macOS
+const RTLD_DEFAULT: *mut c_void = -2isize as *mut c_void;
+
+// Space type of a full-screen app's Space; ordinary desktops are 0
+const SPACE_TYPE_FULLSCREEN: i64 = 4;
+
+// Display identifier used for every Space when "Displays have separate Spaces" is off
+const SHARED_SPACES_DISPLAY: &str = "Main";
+
+type MainConnectionId = unsafe extern "C" fn() -> i32;
+type CopyManagedDisplaySpaces = unsafe extern "C" fn(connection: i32) -> *const AnyObject;
+
+/// CGSMainConnectionID and CGSCopyManagedDisplaySpaces, when the window server exports them
+fn space_functions() -> Option<(MainConnectionId, CopyManagedDisplaySpaces)> {
+    unsafe {
+        let connection = dlsym(RTLD_DEFAULT, c"CGSMainConnectionID".as_ptr());
+        let copy_spaces = dlsym(RTLD_DEFAULT, c"CGSCopyManagedDisplaySpaces".as_ptr());
+        if connection.is_null() || copy_spaces.is_null() {
+            return None;
+        }
+        Some((
+            std::mem::transmute::<*mut c_void, MainConnectionId>(connection),
+            std::mem::transmute::<*mut c_void, CopyManagedDisplaySpaces>(copy_spaces),
+        ))
+    }
+}
+
+/// Every Space of every display, in Mission Control order
+pub fn spaces() -> Result<Vec<SpaceInfo>> {
+    let (main_connection, copy_spaces) = space_functions()
+        .ok_or_else(|| Error::new(Status::GenericFailure, "Listing Spaces isn't supported on this macOS"))?;
+    let mut spaces = Vec::new();
+    unsafe {
+        let displays = copy_spaces(main_connection());
+        if displays.is_null() {
+            return Err(Error::new(Status::GenericFailure, "The window server didn't report any Spaces"));
+        }
+        let display_count: usize = msg_send![displays, count];
+        for display_index in 0..display_count {
+            let display: *mut AnyObject = msg_send![displays, objectAtIndex: display_index];
+            let display_uuid = string_value(display, "Display Identifier").unwrap_or_default();
+            let display_id = display_for_uuid(&display_uuid);
+            let current = dictionary_value(display, "Current Space").and_then(|space| integer_value(space, "ManagedSpaceID"));
+
+            let Some(display_spaces) = dictionary_value(display, "Spaces") else { continue };
+            let space_count: usize = msg_send![display_spaces, count];
+            for space_index in 0..space_count {
+                let space: *mut AnyObject = msg_send![display_spaces, objectAtIndex: space_index];
+                let Some(id) = integer_value(space, "ManagedSpaceID") else { continue };
+                spaces.push(SpaceInfo {
+                    id,
+                    uuid: string_value(space, "uuid").unwrap_or_default(),
+                    display_id,
+                    index: space_index as u32 + 1,
+                    is_current: current == Some(id),
+                    is_fullscreen: integer_value(space, "type") == Some(SPACE_TYPE_FULLSCREEN),
+                });
+            }
+        }
+        CFRelease(displays as *const c_void);
+    }
+    Ok(spaces)
+}
+
+/// Ids of the Spaces showing now, one per display
+pub fn current_space_ids() -> Result<Vec<i64>> {
+    Ok(spaces()?.into_iter().filter(|space| space.is_current).map(|space| space.id).collect())
+}
+
+/// The display to capture to record Space `space_id` out of `spaces`; fails when the Space is
+/// unknown or not the one showing, which ScreenCaptureKit can't capture
+pub fn display_for_space(spaces: &[SpaceInfo], space_id: i64) -> Result<u32> {
+    let space = spaces.iter().find(|space| space.id == space_id)
+        .ok_or_else(|| Error::new(Status::InvalidArg, format!("No Space with id {}", space_id)))?;
+    if !space.is_current {
+        return Err(Error::new(
+            Status::GenericFailure,
+            format!(
+                "Space {} isn't showing; ScreenCaptureKit only captures the Space on screen, so switch to it before recording",
+                space_id,
+            ),
+        ));
+    }
+    space.display_id
+        .ok_or_else(|| Error::new(Status::GenericFailure, format!("The display of Space {} isn't connected", space_id)))
+}
+
+/// The active display with UUID `uuid`; "Main" is the main display
+fn display_for_uuid(uuid: &str) -> Option<u32> {
+    if uuid == SHARED_SPACES_DISPLAY {
+        return Some(unsafe { CoreGraphicsHelpers::get_main_display_id() });
+    }
+    let count = unsafe { CoreGraphicsHelpers::get_display_count() };
+    let mut displays = (0..count).filter_map(|index| unsafe { CoreGraphicsHelpers::get_display_info(index) }.map(|(id, ..)| id));
+    displays.find(|&display| unsafe {
+        let display_uuid = CGDisplayCreateUUIDFromDisplayID(display);
+        if display_uuid.is_null() {
+            return false;
+        }
+        let string = CFUUIDCreateString(ptr::null(), display_uuid);
+        let matches = !string.is_null() && (*string).to_string().eq_ignore_ascii_case(uuid);
+        if !string.is_null() {
+            CFRelease(string as *const c_void);
+        }
+        CFRelease(display_uuid);
+        matches
+    })
+}
+
+unsafe fn dictionary_value(dictionary: *mut AnyObject, key: &str) -> Option<*mut AnyObject> {
+    let key = NSString::from_str(key);
+    let value: *mut AnyObject = msg_send![dictionary, objectForKey: &*key];
+    (!value.is_null()).then_some(value)
+}
+
+unsafe fn string_value(dictionary: *mut AnyObject, key: &str) -> Option<String> {
+    dictionary_value(dictionary, key).map(|value| (*(value as *const NSString)).to_string())
+}
+
+unsafe fn integer_value(dictionary: *mut AnyObject, key: &str) -> Option<i64> {
+    dictionary_value(dictionary, key).map(|value| msg_send![value, longLongValue])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_showing_space_can_be_recorded() {
+        let space = |id, is_current| SpaceInfo {
+            id,
+            uuid: String::new(),
+            display_id: Some(1),
+            index: id as u32,
+            is_current,
+            is_fullscreen: false,
+        };
+        let spaces = [space(1, false), space(2, true)];
+        assert_eq!(display_for_space(&spaces, 2).unwrap(), 1);
+        assert_eq!(display_for_space(&spaces, 1).unwrap_err().status, Status::GenericFailure, "A Space off screen can't be captured");
+        assert_eq!(display_for_space(&spaces, 9).unwrap_err().status, Status::InvalidArg);
+    }
+}
@@ -1,54 +1,60 @@
-// FIXED content.rs - Eliminates segfault by avoiding object extraction
+// Legacy content entry points
+// ScreenCaptureKit only retrieves shareable content asynchronously, so these wrap
+// AsyncContentManager; the returned content always carries the SCShareableContent pointer
+// that filter creation needs
 
 use crate::ScreenSource;
 use napi::bindgen_prelude::*;
-use objc2::{msg_send, class};
-use objc2_foundation::{NSArray, NSString, NSDictionary, NSNumber};
-use std::ptr;
 
-use super::types::{SCShareableContent, SCDisplay, SCWindow, SCContentFilter};
-use super::bindings::ScreenCaptureKitAPI;
+use super::content::{AsyncContentManager, ShareableContent};
 
 pub struct ContentManager;
 
 impl ContentManager {
+    /// Block the current thread until ScreenCaptureKit returns its content
+    /// Fails when called from inside a tokio runtime; async callers use `get_shareable_content`
     pub fn get_shareable_content_sync() -> Result<ShareableContent> {
-        println!("🔍 Getting shareable content via ScreenCaptureKit APIs (sync)");
-        
-        let content = ShareableContent::new_with_real_data()?;
-        
-        println!("✅ Retrieved real shareable content");
-        Ok(content)
+        println!("🔍 Getting shareable content via ScreenCaptureKit APIs (blocking)");
+
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(Error::new(
+                Status::GenericFailure,
+                "Blocking content retrieval can't run inside an async runtime; await get_shareable_content instead",
+            ));
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to start content runtime: {}", e)))?;
+        runtime.block_on(AsyncContentManager::get_shareable_content())
     }
 
     pub async fn get_shareable_content() -> Result<ShareableContent> {
         println!("🔍 Getting shareable content via ScreenCaptureKit APIs");
-        Self::get_shareable_content_sync()
+        AsyncContentManager::get_shareable_content().await
     }
 
-    pub async fn get_shareable_content_async() -> Result<ShareableContent> {
-        println!("🔍 Getting shareable content via async ScreenCaptureKit APIs");
-        ShareableContent::new_with_timeout(5000)
-    }
-    
     pub fn extract_screen_sources(content: &ShareableContent) -> Result<Vec<ScreenSource>> {
         let mut sources = Vec::new();
-        
-        // Extract displays from real ScreenCaptureKit data
-        let displays = content.get_displays()?;
-        for display in displays {
+
+        for display in content.get_displays()? {
             sources.push(ScreenSource {
                 id: format!("display:{}", display.id),
                 name: display.name.clone(),
                 width: display.width,
                 height: display.height,
                 is_display: true,
+                is_virtual: display.is_virtual,
+                is_mirror: display.is_mirror,
+                is_minimized: false,
+                is_fullscreen: false,
+                is_active: false,
+                edr_headroom: display.edr_headroom,
             });
         }
-        
-        // Extract windows from real ScreenCaptureKit data
-        let windows = content.get_windows()?;
-        for window in windows {
+
+        for window in content.get_windows()? {
             // Skip windows with empty titles or that are too small
             if !window.title.is_empty() && window.width > 100 && window.height > 100 {
                 sources.push(ScreenSource {
@@ -57,496 +63,22 @@ impl ContentManager {
                     width: window.width,
                     height: window.height,
                     is_display: false,
+                    is_virtual: false,
+                    is_mirror: false,
+                    is_minimized: window.is_minimized,
+                    is_fullscreen: window.is_fullscreen,
+                    is_active: window.is_active,
+                    edr_headroom: None,
                 });
             }
         }
-        
-        println!("✅ Extracted {} screen sources from real ScreenCaptureKit data", sources.len());
+
+        println!("✅ Extracted {} screen sources from ScreenCaptureKit content", sources.len());
         Ok(sources)
     }
 
     pub async fn extract_screen_sources_async() -> Result<Vec<ScreenSource>> {
-        let content = Self::get_shareable_content_async().await?;
+        let content = Self::get_shareable_content().await?;
         Self::extract_screen_sources(&content)
     }
 }
-
-// Enhanced wrapper for SCShareableContent with thread-safe data access
-pub struct ShareableContent {
-    displays: Vec<DisplayInfo>,
-    windows: Vec<WindowInfo>,
-    // CRITICAL FIX: Store the raw ScreenCaptureKit content pointer
-    // This allows us to create content filters without extracting individual objects
-    sc_content_ptr: Option<*mut SCShareableContent>,
-}
-
-#[derive(Debug, Clone)]
-pub struct DisplayInfo {
-    pub id: u32,
-    pub name: String,
-    pub width: u32,
-    pub height: u32,
-}
-
-#[derive(Debug, Clone)]
-pub struct WindowInfo {
-    pub id: u32,
-    pub title: String,
-    pub width: u32,
-    pub height: u32,
-}
-
-impl ShareableContent {
-    pub fn new() -> Self {
-        Self {
-            displays: Vec::new(),
-            windows: Vec::new(),
-            sc_content_ptr: None,
-        }
-    }
-    
-    pub fn new_with_real_data() -> Result<Self> {
-        println!("🔍 Fetching real shareable content from ScreenCaptureKit (sync)");
-        
-        unsafe {
-            let mut content = Self::new();
-            
-            // Get the ScreenCaptureKit content pointer and store it
-            match Self::fetch_real_sc_shareable_content() {
-                Ok(sc_content) => {
-                    // Store the pointer for later content filter creation
-                    content.sc_content_ptr = Some(sc_content);
-                    
-                    // Use safe system content for display/window info
-                    let safe_content = Self::create_safe_system_content();
-                    content.displays = safe_content.displays;
-                    content.windows = safe_content.windows;
-                    
-                    println!("✅ Retrieved ScreenCaptureKit content with {} displays and {} windows", 
-                        content.displays.len(), content.windows.len());
-                    
-                    Ok(content)
-                }
-                Err(error) => {
-                    println!("⚠️ ScreenCaptureKit content retrieval failed: {}", error);
-                    println!("💡 Using safe system content only");
-                    
-                    // Use safe system content without ScreenCaptureKit pointer
-                    let safe_content = Self::create_safe_system_content();
-                    content.displays = safe_content.displays;
-                    content.windows = safe_content.windows;
-                    
-                    Ok(content)
-                }
-            }
-        }
-    }
-
-    /// Create safe system content using macOS system APIs instead of ScreenCaptureKit extraction
-    fn create_safe_system_content() -> Self {
-        println!("🔍 Creating safe system content using Core Graphics APIs");
-        
-        let mut content = Self::new();
-        
-        unsafe {
-            // Use Core Graphics to get display information safely
-            let display_count = Self::get_display_count_safe();
-            
-            for i in 0..display_count {
-                if let Some(display_info) = Self::get_display_info_safe(i) {
-                    content.displays.push(display_info);
-                }
-            }
-            
-            // Get real window information using Core Graphics APIs
-            content.windows.extend(Self::get_real_window_info());
-        }
-        
-        content
-    }
-
-    // ... [keep all the existing safe Core Graphics methods unchanged] ...
-    
-    unsafe fn get_display_count_safe() -> u32 {
-        extern "C" {
-            fn CGGetActiveDisplayList(maxDisplays: u32, activeDisplays: *mut u32, displayCount: *mut u32) -> i32;
-        }
-        
-        let mut display_count: u32 = 0;
-        let result = CGGetActiveDisplayList(0, ptr::null_mut(), &mut display_count);
-        
-        if result == 0 {
-            display_count
-        } else {
-            1 // Fallback to at least one display
-        }
-    }
-
-    unsafe fn get_display_info_safe(index: u32) -> Option<DisplayInfo> {
-        extern "C" {
-            fn CGGetActiveDisplayList(maxDisplays: u32, activeDisplays: *mut u32, displayCount: *mut u32) -> i32;
-            fn CGDisplayPixelsWide(display: u32) -> usize;
-            fn CGDisplayPixelsHigh(display: u32) -> usize;
-        }
-        
-        const MAX_DISPLAYS: u32 = 32;
-        let mut displays: [u32; MAX_DISPLAYS as usize] = [0; MAX_DISPLAYS as usize];
-        let mut display_count: u32 = 0;
-        
-        let result = CGGetActiveDisplayList(MAX_DISPLAYS, displays.as_mut_ptr(), &mut display_count);
-        
-        if result == 0 && index < display_count {
-            let display_id = displays[index as usize];
-            let width = CGDisplayPixelsWide(display_id) as u32;
-            let height = CGDisplayPixelsHigh(display_id) as u32;
-            
-            Some(DisplayInfo {
-                id: display_id,
-                name: if index == 0 {
-                    "Built-in Display".to_string()
-                } else {
-                    format!("Display {}", index + 1)
-                },
-                width,
-                height,
-            })
-        } else {
-            None
-        }
-    }
-
-    unsafe fn get_real_window_info() -> Vec<WindowInfo> {
-        println!("🔍 Getting real window information via Core Graphics APIs");
-        
-        extern "C" {
-            fn CGWindowListCopyWindowInfo(option: u32, relativeToWindow: u32) -> *mut objc2_foundation::NSArray;
-        }
-        
-        const kCGWindowListOptionOnScreenOnly: u32 = 1 << 0;
-        const kCGWindowListExcludeDesktopElements: u32 = 1 << 4;
-        
-        let mut windows = Vec::new();
-        
-        let window_list_raw = CGWindowListCopyWindowInfo(
-            kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements,
-            0
-        );
-        
-        if window_list_raw.is_null() {
-            return Self::get_fallback_window_info();
-        }
-        
-        let window_list: &NSArray = &*window_list_raw;
-        let count = window_list.count();
-        
-        for i in 0..count {
-            let window_dict_obj = window_list.objectAtIndex(i);
-            if let Ok(window_dict) = window_dict_obj.downcast::<NSDictionary>() {
-                if let Some(window_info) = Self::extract_window_info_from_dict(&window_dict, i as u32) {
-                    windows.push(window_info);
-                }
-            }
-        }
-        
-        objc2::rc::autoreleasepool(|_| {
-            std::ptr::drop_in_place(window_list_raw);
-        });
-        
-        if windows.is_empty() {
-            Self::get_fallback_window_info()
-        } else {
-            windows
-        }
-    }
-    
-    unsafe fn extract_window_info_from_dict(window_dict: &NSDictionary, fallback_id: u32) -> Option<WindowInfo> {
-        let window_number_key = NSString::from_str("kCGWindowNumber");
-        let window_name_key = NSString::from_str("kCGWindowName");
-        let window_owner_name_key = NSString::from_str("kCGWindowOwnerName");
-        let window_bounds_key = NSString::from_str("kCGWindowBounds");
-        
-        let window_id = if let Some(number_obj) = window_dict.objectForKey(&window_number_key) {
-            if let Ok(number) = number_obj.downcast::<NSNumber>() {
-                number.intValue() as u32
-            } else {
-                fallback_id
-            }
-        } else {
-            fallback_id
-        };
-        
-        let title = if let Some(name_obj) = window_dict.objectForKey(&window_name_key) {
-            if let Ok(name_str) = name_obj.downcast::<NSString>() {
-                let title_str = name_str.to_string();
-                if !title_str.is_empty() {
-                    title_str
-                } else {
-                    if let Some(owner_obj) = window_dict.objectForKey(&window_owner_name_key) {
-                        if let Ok(owner_str) = owner_obj.downcast::<NSString>() {
-                            owner_str.to_string()
-                        } else {
-                            "Unknown Window".to_string()
-                        }
-                    } else {
-                        "Unknown Window".to_string()
-                    }
-                }
-            } else {
-                "Unknown Window".to_string()
-            }
-        } else {
-            if let Some(owner_obj) = window_dict.objectForKey(&window_owner_name_key) {
-                if let Ok(owner_str) = owner_obj.downcast::<NSString>() {
-                    owner_str.to_string()
-                } else {
-                    "Unknown Window".to_string()
-                }
-            } else {
-                "Unknown Window".to_string()
-            }
-        };
-        
-        let (width, height) = if let Some(bounds_obj) = window_dict.objectForKey(&window_bounds_key) {
-            if let Ok(bounds_dict) = bounds_obj.downcast::<NSDictionary>() {
-                let width_key = NSString::from_str("Width");
-                let height_key = NSString::from_str("Height");
-                
-                let width = if let Some(width_obj) = bounds_dict.objectForKey(&width_key) {
-                    if let Ok(width_num) = width_obj.downcast::<NSNumber>() {
-                        width_num.intValue() as u32
-                    } else {
-                        800
-                    }
-                } else {
-                    800
-                };
-                
-                let height = if let Some(height_obj) = bounds_dict.objectForKey(&height_key) {
-                    if let Ok(height_num) = height_obj.downcast::<NSNumber>() {
-                        height_num.intValue() as u32
-                    } else {
-                        600
-                    }
-                } else {
-                    600
-                };
-                
-                (width, height)
-            } else {
-                (800, 600)
-            }
-        } else {
-            (800, 600)
-        };
-        
-        if title.is_empty() || width < 100 || height < 100 {
-            return None;
-        }
-        
-        Some(WindowInfo {
-            id: window_id,
-            title,
-            width,
-            height,
-        })
-    }
-    
-    fn get_fallback_window_info() -> Vec<WindowInfo> {
-        vec![
-            WindowInfo {
-                id: 1,
-                title: "Desktop".to_string(),
-                width: 1920,
-                height: 1080,
-            },
-            WindowInfo {
-                id: 2,
-                title: "Finder".to_string(),
-                width: 800,
-                height: 600,
-            },
-        ]
-    }
-
-    pub fn new_with_timeout(timeout_ms: u32) -> Result<Self> {
-        println!("🔍 Fetching real shareable content from ScreenCaptureKit with {}ms timeout", timeout_ms);
-        
-        unsafe {
-            let mut content = Self::new();
-            
-            // Use simpler approach without thread safety issues
-            match ScreenCaptureKitAPI::get_shareable_content_sync() {
-                Ok(sc_content) => {
-                    println!("✅ Got ScreenCaptureKit content synchronously within timeout");
-                    content.sc_content_ptr = Some(sc_content);
-                    
-                    // Use safe system content for display/window enumeration
-                    let safe_content = Self::create_safe_system_content();
-                    content.displays = safe_content.displays;
-                    content.windows = safe_content.windows;
-                    
-                    println!("✅ Retrieved {} displays and {} windows with ScreenCaptureKit content", 
-                        content.displays.len(), content.windows.len());
-                    
-                    Ok(content)
-                }
-                Err(_) => {
-                    println!("⚠️ ScreenCaptureKit sync failed, using safe content only");
-                    
-                    // Start async call for future use but don't wait
-                    ScreenCaptureKitAPI::get_shareable_content_async(|_content, _error| {
-                        println!("🔄 Background ScreenCaptureKit call completed");
-                    });
-                    
-                    let safe_content = Self::create_safe_system_content();
-                    content.displays = safe_content.displays;
-                    content.windows = safe_content.windows;
-                    
-                    Ok(content)
-                }
-            }
-        }
-    }
-    
-    unsafe fn fetch_real_sc_shareable_content() -> Result<*mut SCShareableContent> {
-        println!("🔍 Fetching real shareable content using ScreenCaptureKit API");
-        
-        // Use simpler approach without thread safety issues
-        match ScreenCaptureKitAPI::get_shareable_content_sync() {
-            Ok(content) => {
-                println!("✅ Got ScreenCaptureKit content synchronously");
-                Ok(content)
-            }
-            Err(e) => {
-                println!("⚠️ Synchronous approach failed: {}", e);
-                println!("💡 Using async approach without waiting (safer)");
-                
-                // Start the async call but don't wait for it to avoid thread safety issues
-                ScreenCaptureKitAPI::get_shareable_content_async(|_content, _error| {
-                    println!("🔄 Async ScreenCaptureKit call completed");
-                });
-                
-                // Return an error to indicate we should use the fallback approach
-                Err(Error::new(Status::GenericFailure, "Async ScreenCaptureKit requires fallback".to_string()))
-            }
-        }
-    }
-    
-    pub fn get_displays(&self) -> Result<Vec<DisplayInfo>> {
-        Ok(self.displays.clone())
-    }
-    
-    pub fn get_windows(&self) -> Result<Vec<WindowInfo>> {
-        Ok(self.windows.clone())
-    }
-    
-    pub fn find_display_by_id(&self, display_id: u32) -> Option<&DisplayInfo> {
-        self.displays.iter().find(|d| d.id == display_id)
-    }
-    
-    pub fn find_window_by_id(&self, window_id: u32) -> Option<&WindowInfo> {
-        self.windows.iter().find(|w| w.id == window_id)
-    }
-    
-    // CRITICAL FIX: Replace individual object extraction with content filter creation
-    // This avoids the segfault entirely by using ScreenCaptureKit's higher-level APIs
-    
-    /// Create a content filter for a display using the stored ScreenCaptureKit content
-    /// This bypasses the need to extract individual SCDisplay objects
-    pub unsafe fn create_display_content_filter(&self, display_id: u32) -> Result<*mut SCContentFilter> {
-        println!("🎯 Creating display content filter for display ID {} (segfault-safe)", display_id);
-        
-        // Verify we have the display in our safe enumeration
-        if self.find_display_by_id(display_id).is_none() {
-            return Err(Error::new(Status::InvalidArg, format!("Display ID {} not found", display_id)));
-        }
-        
-        // Check if we have the ScreenCaptureKit content pointer
-        match self.sc_content_ptr {
-            Some(sc_content) => {
-                println!("✅ Using stored ScreenCaptureKit content for display filter creation");
-                
-                // Use ScreenCaptureKit's high-level API to create a content filter
-                // This avoids extracting individual objects
-                let filter_class = class!(SCContentFilter);
-                let alloc: *mut objc2::runtime::AnyObject = msg_send![filter_class, alloc];
-                
-                // Create filter for main display (index 0) or specific display
-                // Use display index instead of extracted objects
-                let _display_index = if display_id > 0 && (display_id as usize) <= self.displays.len() {
-                    (display_id - 1) as usize
-                } else {
-                    0
-                };
-                
-                // Create content filter using ScreenCaptureKit's built-in methods
-                // This is safer than extracting individual objects
-                let content_filter: *mut SCContentFilter = msg_send![
-                    alloc,
-                    initWithDisplay: sc_content,  // Use the content directly
-                    excludingWindows: ptr::null::<objc2_foundation::NSArray>()
-                ];
-                
-                if content_filter.is_null() {
-                    return Err(Error::new(Status::GenericFailure, "Failed to create display content filter"));
-                }
-                
-                println!("✅ Successfully created display content filter (segfault-safe)");
-                Ok(content_filter)
-            }
-            None => {
-                println!("⚠️ No ScreenCaptureKit content available - cannot create real content filter");
-                Err(Error::new(Status::GenericFailure, "ScreenCaptureKit content not available"))
-            }
-        }
-    }
-    
-    /// Create a content filter for a window using the stored ScreenCaptureKit content
-    pub unsafe fn create_window_content_filter(&self, window_id: u32) -> Result<*mut SCContentFilter> {
-        println!("🎯 Creating window content filter for window ID {} (segfault-safe)", window_id);
-        
-        if self.find_window_by_id(window_id).is_none() {
-            return Err(Error::new(Status::InvalidArg, format!("Window ID {} not found", window_id)));
-        }
-        
-        match self.sc_content_ptr {
-            Some(sc_content) => {
-                let filter_class = class!(SCContentFilter);
-                let alloc: *mut objc2::runtime::AnyObject = msg_send![filter_class, alloc];
-                
-                // Create filter for specific window using high-level API
-                let content_filter: *mut SCContentFilter = msg_send![
-                    alloc,
-                    initWithDesktopIndependentWindow: sc_content
-                ];
-                
-                if content_filter.is_null() {
-                    return Err(Error::new(Status::GenericFailure, "Failed to create window content filter"));
-                }
-                
-                println!("✅ Successfully created window content filter (segfault-safe)");
-                Ok(content_filter)
-            }
-            None => {
-                Err(Error::new(Status::GenericFailure, "ScreenCaptureKit content not available"))
-            }
-        }
-    }
-    
-    // REMOVED: The problematic get_sc_display_by_id and get_sc_window_by_id methods
-    // These caused segfaults and are replaced with the safer content filter creation methods above
-    
-    /// Safe fallback - returns null to indicate object extraction is not supported
-    pub unsafe fn get_sc_display_by_id(&self, _display_id: u32) -> Option<*mut SCDisplay> {
-        println!("🚫 SCDisplay object extraction disabled to prevent segfaults");
-        println!("💡 Use create_display_content_filter() instead");
-        None
-    }
-    
-    pub unsafe fn get_sc_window_by_id(&self, _window_id: u32) -> Option<*mut SCWindow> {
-        println!("🚫 SCWindow object extraction disabled to prevent segfaults");
-        println!("💡 Use create_window_content_filter() instead");
-        None
-    }
-}
-
@@ -1,19 +1,194 @@
+use std::collections::{HashMap, VecDeque};
+use std::ffi::c_void;
 use std::sync::{Arc, Mutex};
 use std::path::Path;
 use objc2::runtime::AnyObject;
 use objc2::{msg_send, class};
 use objc2_foundation::{NSError, NSString, NSURL};
-use objc2_core_media::{CMSampleBuffer, CMTime};
+use objc2_core_media::{CMSampleBuffer, CMTime, kCMTimeZero};
 use objc2_core_video::{CVPixelBuffer};
 use objc2_av_foundation::{AVAssetWriter, AVAssetWriterInput, AVAssetWriterInputPixelBufferAdaptor};
 use napi::{Result, Status, Error};
 
-use super::types::{SCStream, SCStreamOutputType};
+use super::frame_index;
+use super::keystroke_overlay::KeystrokeOverlay;
+use super::foundation::describe_nserror;
+use super::drift;
+use super::types::{SCStream, SCStreamOutputType, EmptyRecordingBehavior, CapturePixelFormat, VideoCodec, ScalingMode, CaptureMode};
+use super::encoder::{
+    create_black_pixel_buffer, release_pixel_buffer, apply_metadata, asset_writer_error_description, host_time_now, file_type_for,
+    DEFAULT_WRITER_START_RETRIES, WRITER_START_RETRY_DELAY_MS,
+    AVVideoCodecTypeH264, AVVideoCodecTypeHEVC, AVVideoCodecTypeHEVCWithAlpha, AVVideoProfileLevelHEVCMain10AutoLevel,
+    AVFileTypeQuickTimeMovie,
+};
 
 // External CoreMedia functions
 extern "C" {
     fn CMSampleBufferGetImageBuffer(sbuf: &CMSampleBuffer) -> *mut CVPixelBuffer;
     fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CMTimeCompare(time1: CMTime, time2: CMTime) -> i32;
+    fn CMTimeAdd(lhs: CMTime, rhs: CMTime) -> CMTime;
+    fn CMSampleBufferGetDuration(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CFRetain(cf: *const c_void) -> *const c_void;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Audio held back while waiting for the first video frame to start the writer session
+pub const DEFAULT_EARLY_AUDIO_BUFFER_MS: u32 = 1000;
+
+/// Create the directory `output_path` goes in when it doesn't exist yet
+pub fn create_output_directory(output_path: &str) -> Result<()> {
+    let Some(parent) = Path::new(output_path).parent().filter(|parent| !parent.as_os_str().is_empty()) else {
+        return Ok(());
+    };
+    if parent.exists() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(parent).map_err(|e| output_directory_error(parent, &e))?;
+    println!("📁 Created output directory: {}", parent.display());
+    Ok(())
+}
+
+/// Sandboxed apps may only write where the user granted access, which the IO error alone
+/// doesn't tell them
+fn output_directory_error(directory: &Path, error: &std::io::Error) -> Error {
+    let message = if error.kind() == std::io::ErrorKind::PermissionDenied {
+        format!(
+            "Can't create output directory {}: the app isn't allowed to write here; choose a directory inside your Documents or grant access",
+            directory.display(),
+        )
+    } else {
+        format!("Failed to create output directory {}: {}", directory.display(), error)
+    };
+    Error::new(Status::GenericFailure, message)
+}
+
+/// Audio that arrived before the first video frame, held with the span it covers in seconds
+/// until the session start is known. Only the most recent `limit` is kept
+pub struct EarlyAudioBuffer<T> {
+    limit_seconds: f64,
+    samples: VecDeque<(T, f64, f64)>,
+}
+
+impl<T> EarlyAudioBuffer<T> {
+    pub fn new(limit_ms: u32) -> Self {
+        Self { limit_seconds: limit_ms as f64 / 1000.0, samples: VecDeque::new() }
+    }
+
+    /// Hold `sample`, covering `start` to `end` seconds; the oldest samples go once more than
+    /// the limit is held
+    pub fn push(&mut self, sample: T, start: f64, end: f64) {
+        if self.limit_seconds <= 0.0 {
+            return;
+        }
+        self.samples.push_back((sample, start, end));
+        while self.samples.front().is_some_and(|&(_, first_start, _)| end - first_start > self.limit_seconds) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The held samples that still play once the session starts at `session_start` seconds,
+    /// in arrival order. They keep their own timestamps, so they line up with the video; one
+    /// that starts before the session is trimmed by the writer and ones ending before it go
+    pub fn flush(&mut self, session_start: f64) -> Vec<T> {
+        self.samples.drain(..)
+            .filter(|&(_, _, end)| end > session_start)
+            .map(|(sample, _, _)| sample)
+            .collect()
+    }
+}
+
+/// A sample buffer kept alive while it waits in an `EarlyAudioBuffer`
+pub struct HeldSampleBuffer(*mut CMSampleBuffer);
+
+// Safety: Core Media sample buffers are immutable once delivered and safe to release on any thread
+unsafe impl Send for HeldSampleBuffer {}
+
+impl HeldSampleBuffer {
+    /// # Safety
+    /// `sample_buffer` must be a valid Core Media sample buffer
+    pub unsafe fn retain(sample_buffer: &CMSampleBuffer) -> Self {
+        let sample_buffer = sample_buffer as *const CMSampleBuffer as *mut CMSampleBuffer;
+        CFRetain(sample_buffer as *const c_void);
+        Self(sample_buffer)
+    }
+
+    pub fn sample(&self) -> &CMSampleBuffer {
+        unsafe { &*self.0 }
+    }
+}
+
+impl Drop for HeldSampleBuffer {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0 as *const c_void) };
+    }
+}
+
+/// What to do with an incoming video timestamp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimestampDecision {
+    /// Strictly after the last appended frame; append as-is
+    Accept(CMTime),
+    /// Duplicate of the last timestamp; append one frame interval later instead
+    Nudge(CMTime),
+    /// Earlier than the last appended frame; appending would fail the writer
+    Drop,
+}
+
+/// Keeps appended presentation timestamps strictly increasing
+pub struct PresentationTimeGuard {
+    last_time: Option<CMTime>,
+    frame_interval: CMTime,
+    nudged_frames: u64,
+    dropped_frames: u64,
+}
+
+impl PresentationTimeGuard {
+    pub fn new(fps: u32) -> Self {
+        Self {
+            last_time: None,
+            frame_interval: CMTime {
+                value: 1,
+                timescale: fps.max(1) as i32,
+                flags: objc2_core_media::CMTimeFlags(1), // kCMTimeFlags_Valid
+                epoch: 0,
+            },
+            nudged_frames: 0,
+            dropped_frames: 0,
+        }
+    }
+
+    /// Decide how to append `time`; accepted and nudged times become the new last timestamp
+    pub fn check(&mut self, time: CMTime) -> TimestampDecision {
+        let Some(last) = self.last_time else {
+            self.last_time = Some(time);
+            return TimestampDecision::Accept(time);
+        };
+
+        let ordering = unsafe { CMTimeCompare(time, last) };
+        if ordering > 0 {
+            self.last_time = Some(time);
+            TimestampDecision::Accept(time)
+        } else if ordering == 0 {
+            let nudged = unsafe { CMTimeAdd(last, self.frame_interval) };
+            self.nudged_frames += 1;
+            self.last_time = Some(nudged);
+            println!("⚠️ Duplicate video timestamp {}/{}; nudged forward one frame", { time.value }, { time.timescale });
+            TimestampDecision::Nudge(nudged)
+        } else {
+            self.dropped_frames += 1;
+            println!(
+                "⚠️ Out-of-order video timestamp {}/{} (last {}/{}); dropping frame",
+                { time.value }, { time.timescale }, { last.value }, { last.timescale }
+            );
+            TimestampDecision::Drop
+        }
+    }
+
+    /// (nudged, dropped) frame counts
+    pub fn stats(&self) -> (u64, u64) {
+        (self.nudged_frames, self.dropped_frames)
+    }
 }
 
 /// Real implementation of SCStreamOutput protocol that saves working audio/video files
@@ -27,16 +202,30 @@ pub struct StreamOutput {
     // Recording state
     output_path: String,
     is_recording: Arc<Mutex<bool>>,
+    recording_started: bool,
     
     // Statistics
     video_frame_count: Arc<Mutex<u64>>,
     audio_sample_count: Arc<Mutex<u64>>,
+    timestamp_guard: PresentationTimeGuard,
+    // Audio that arrived before the first video frame started the session
+    early_audio: EarlyAudioBuffer<HeldSampleBuffer>,
     
     // Configuration
     width: u32,
     height: u32,
     fps: u32,
     capture_audio: bool,
+    empty_recording_behavior: EmptyRecordingBehavior,
+    metadata: HashMap<String, String>,
+    codec: VideoCodec,
+    pixel_format: CapturePixelFormat,
+    scaling_mode: ScalingMode,
+    writer_start_retries: u32,
+    keystroke_overlay: Option<Arc<KeystrokeOverlay>>,
+    capture_mode: CaptureMode,
+    emit_frame_index: bool,
+    profile_level: Option<String>,
 }
 
 // Safety: Raw pointers in encoders are only used within unsafe blocks
@@ -55,36 +244,95 @@ impl StreamOutput {
             pixel_buffer_adaptor: None,
             output_path,
             is_recording: Arc::new(Mutex::new(false)),
+            recording_started: false,
             video_frame_count: Arc::new(Mutex::new(0)),
             audio_sample_count: Arc::new(Mutex::new(0)),
+            timestamp_guard: PresentationTimeGuard::new(fps),
+            early_audio: EarlyAudioBuffer::new(DEFAULT_EARLY_AUDIO_BUFFER_MS),
             width,
             height,
             fps,
             capture_audio,
+            empty_recording_behavior: EmptyRecordingBehavior::default(),
+            metadata: HashMap::new(),
+            codec: VideoCodec::default(),
+            pixel_format: CapturePixelFormat::default(),
+            scaling_mode: ScalingMode::default(),
+            writer_start_retries: DEFAULT_WRITER_START_RETRIES,
+            keystroke_overlay: None,
+            capture_mode: CaptureMode::default(),
+            emit_frame_index: false,
+            profile_level: None,
         })
     }
     
+    /// Choose what `stop_recording` does when no sample ever started the writer session
+    pub fn set_empty_recording_behavior(&mut self, behavior: EmptyRecordingBehavior) {
+        self.empty_recording_behavior = behavior;
+    }
+    
+    /// Set the codec and the stream's pixel format; takes effect in `initialize_asset_writer`
+    pub fn set_video_format(&mut self, codec: VideoCodec, pixel_format: CapturePixelFormat) {
+        self.codec = codec;
+        self.pixel_format = pixel_format;
+    }
+    
+    /// Pin the encoder's ProfileLevel; takes effect in `initialize_asset_writer`
+    pub fn set_profile_level(&mut self, profile_level: Option<String>) {
+        self.profile_level = profile_level;
+    }
+    
+    /// Set the capture mode; OnChange holds the last frame until the recording stops
+    pub fn set_capture_mode(&mut self, capture_mode: CaptureMode) {
+        self.capture_mode = capture_mode;
+    }
+    
+    /// Draw recent keystrokes onto every frame before it's appended
+    pub fn set_keystroke_overlay(&mut self, overlay: KeystrokeOverlay) {
+        self.keystroke_overlay = Some(Arc::new(overlay));
+    }
+    
+    /// Set how the writer scales frames that don't match the output size
+    pub fn set_scaling_mode(&mut self, scaling_mode: ScalingMode) {
+        self.scaling_mode = scaling_mode;
+    }
+    
+    /// Set how much audio arriving before the first video frame is held for it, in ms
+    pub fn set_early_audio_buffer_ms(&mut self, limit_ms: u32) {
+        self.early_audio = EarlyAudioBuffer::new(limit_ms);
+    }
+    
+    /// Set how many times a failed `startWriting` recreates the writer and tries again
+    pub fn set_writer_start_retries(&mut self, retries: u32) {
+        self.writer_start_retries = retries;
+    }
+    
+    /// Write a frame index sidecar once `stop_recording` has finished the file
+    pub fn set_emit_frame_index(&mut self, emit: bool) {
+        self.emit_frame_index = emit;
+    }
+    
+    /// Set metadata tags for the output file; takes effect in `initialize_asset_writer`
+    pub fn set_metadata(&mut self, metadata: HashMap<String, String>) {
+        self.metadata = metadata;
+    }
+    
     /// Initialize the AVAssetWriter with proper video/audio settings
     pub fn initialize_asset_writer(&mut self) -> Result<()> {
         
         println!("🔧 Initializing AVAssetWriter with fixed codec configuration");
         
         // Ensure output directory exists
-        if let Some(parent) = Path::new(&self.output_path).parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to create output directory: {}", e)))?;
-            }
-        }
+        create_output_directory(&self.output_path)?;
         
         unsafe {
             // Create file URL
             let url_string = NSString::from_str(&self.output_path);
             let file_url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*url_string];
             
-            // Create AVAssetWriter with fixed configuration
+            // Create AVAssetWriter in the container the extension names (QuickTime otherwise)
             let mut error: *mut NSError = std::ptr::null_mut();
-            let file_type = NSString::from_str("com.apple.quicktime-movie");
+            let file_type = NSString::from_str(file_type_for(&self.output_path).unwrap_or(AVFileTypeQuickTimeMovie));
             let asset_writer: *mut AVAssetWriter = msg_send![
                 class!(AVAssetWriter),
                 assetWriterWithURL: file_url,
@@ -93,9 +341,15 @@ impl StreamOutput {
             ];
             
             if asset_writer.is_null() || !error.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to create AVAssetWriter"));
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to create AVAssetWriter: {}", describe_nserror(error)),
+                ));
             }
             
+            // Metadata has to be attached before the writing session starts
+            apply_metadata(asset_writer, &self.metadata);
+            
             // Create video input with fixed settings (no problematic bitrate)
             let video_input = self.create_video_input()?;
             let can_add_video: bool = msg_send![asset_writer, canAddInput: video_input];
@@ -135,8 +389,16 @@ impl StreamOutput {
     
     /// Handle incoming video sample buffer from ScreenCaptureKit
     pub fn handle_video_sample(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
-        // Ensure recording session is started
-        self.ensure_recording_started(sample_buffer)?;
+        // Ensure recording session is started, then catch up on the audio that waited for it
+        if !self.recording_started {
+            self.ensure_recording_started(sample_buffer)?;
+            if self.recording_started {
+                let session_start = drift::seconds(unsafe { CMSampleBufferGetPresentationTimeStamp(sample_buffer) });
+                for held in self.early_audio.flush(session_start) {
+                    self.append_audio_sample(held.sample());
+                }
+            }
+        }
         
         // Update frame count for statistics
         if let Ok(mut count) = self.video_frame_count.lock() {
@@ -155,14 +417,21 @@ impl StreamOutput {
                     return Ok(()); // Skip frame if not ready
                 }
                 
+                if let Some(ref overlay) = self.keystroke_overlay {
+                    overlay.render(sample_buffer);
+                }
+                
                 // Get pixel buffer from sample buffer
                 let pixel_buffer: *mut CVPixelBuffer = CMSampleBufferGetImageBuffer(sample_buffer);
                 if pixel_buffer.is_null() {
                     return Ok(());
                 }
                 
-                // Get presentation time
-                let presentation_time = CMSampleBufferGetPresentationTimeStamp(sample_buffer);
+                // Get presentation time; the writer rejects anything not after the previous frame
+                let presentation_time = match self.timestamp_guard.check(CMSampleBufferGetPresentationTimeStamp(sample_buffer)) {
+                    TimestampDecision::Accept(time) | TimestampDecision::Nudge(time) => time,
+                    TimestampDecision::Drop => return Ok(()),
+                };
                 
                 // Append pixel buffer
                 let success: bool = msg_send![
@@ -186,9 +455,22 @@ impl StreamOutput {
             return Ok(());
         }
         
-        // Ensure recording session is started
-        self.ensure_recording_started(sample_buffer)?;
+        // Only video starts the session; audio before the first frame would offset the tracks
+        if !self.recording_started {
+            unsafe {
+                let start = drift::seconds(CMSampleBufferGetPresentationTimeStamp(sample_buffer));
+                let end = start + drift::seconds(CMSampleBufferGetDuration(sample_buffer));
+                self.early_audio.push(HeldSampleBuffer::retain(sample_buffer), start, end);
+            }
+            return Ok(());
+        }
         
+        self.append_audio_sample(sample_buffer);
+        Ok(())
+    }
+    
+    /// Append an audio sample to the started session
+    fn append_audio_sample(&mut self, sample_buffer: &CMSampleBuffer) {
         // Update sample count for statistics
         if let Ok(mut count) = self.audio_sample_count.lock() {
             *count += 1;
@@ -203,7 +485,7 @@ impl StreamOutput {
                 // Check if input is ready for more media data
                 let ready: bool = msg_send![audio_input, isReadyForMoreMediaData];
                 if !ready {
-                    return Ok(()); // Skip sample if not ready
+                    return; // Skip sample if not ready
                 }
                 
                 // Append sample buffer
@@ -214,8 +496,6 @@ impl StreamOutput {
                 }
             }
         }
-        
-        Ok(())
     }
     
     /// Start recording session
@@ -239,7 +519,27 @@ impl StreamOutput {
         }
         
         // Finalize the recording if we have an active writer
-        if let Some(asset_writer) = self.asset_writer {
+        if self.asset_writer.is_some() {
+            let extend_last_frame = self.capture_mode == CaptureMode::OnChange && self.recording_started;
+            if !self.recording_started {
+                match self.empty_recording_behavior {
+                    EmptyRecordingBehavior::Error => {
+                        println!("❌ Recording stopped before any frames were captured");
+                        self.print_final_stats();
+                        return Err(Error::new(
+                            Status::GenericFailure,
+                            "Recording too short: no video frames were captured",
+                        ));
+                    }
+                    EmptyRecordingBehavior::BlackFrame => {
+                        println!("⚠️ No frames captured, writing a single black frame");
+                        unsafe { self.write_black_frame()?; }
+                    }
+                }
+            }
+            
+            // Re-read the writer: a startWriting retry may have replaced it
+            let asset_writer = self.asset_writer.unwrap();
             unsafe {
                 // Check the writer status before trying to finalize
                 let status: i32 = msg_send![asset_writer, status];
@@ -258,10 +558,21 @@ impl StreamOutput {
                         let _: () = msg_send![audio_input, markAsFinished];
                     }
                     
+                    // Hold the last frame until now instead of ending at the last content change
+                    if extend_last_frame {
+                        let _: () = msg_send![asset_writer, endSessionAtSourceTime: host_time_now()];
+                    }
+                    
                     // Finish writing
                     let _: () = msg_send![asset_writer, finishWriting];
                     
                     println!("✅ Recording finalized successfully");
+                    
+                    if self.emit_frame_index {
+                        if let Err(e) = frame_index::write_frame_index(&self.output_path) {
+                            println!("⚠️ Frame index not saved: {}", e.reason);
+                        }
+                    }
                 } else {
                     println!("⚠️ Writer not in writing state (status: {}), skipping finalization", status);
                     println!("💡 This is expected when stream failed to start");
@@ -277,41 +588,93 @@ impl StreamOutput {
         Ok(self.output_path.clone())
     }
     
+    /// Start the writer session at time zero and append one black frame
+    unsafe fn write_black_frame(&mut self) -> Result<()> {
+        let asset_writer = self.start_writing_with_retry()?;
+        let _: () = msg_send![asset_writer, startSessionAtSourceTime: kCMTimeZero];
+        self.recording_started = true;
+        
+        if let Some(pixel_buffer_adaptor) = self.pixel_buffer_adaptor {
+            let pixel_buffer = create_black_pixel_buffer(self.width, self.height)?;
+            let success: bool = msg_send![
+                pixel_buffer_adaptor,
+                appendPixelBuffer: pixel_buffer,
+                withPresentationTime: kCMTimeZero
+            ];
+            release_pixel_buffer(pixel_buffer);
+            
+            if !success {
+                return Err(Error::new(Status::GenericFailure, "Failed to append black frame"));
+            }
+        }
+        
+        Ok(())
+    }
+    
     /// Ensure recording session is started with proper timing
     fn ensure_recording_started(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
-        if let Ok(mut is_recording) = self.is_recording.lock() {
-            if !*is_recording {
-                if let Some(asset_writer) = self.asset_writer {
-                    unsafe {
-                        // Check current status first
-                        let status: i32 = msg_send![asset_writer, status];
-                        println!("📊 AVAssetWriter status before starting: {}", status);
-                        
-                        // Start the writing session
-                        let started: bool = msg_send![asset_writer, startWriting];
-                        if !started {
-                            let error: *mut NSError = msg_send![asset_writer, error];
-                            if !error.is_null() {
-                                println!("❌ AVAssetWriter error: {:?}", &*error);
-                            }
-                            return Err(Error::new(Status::GenericFailure, "Failed to start writing session"));
-                        }
-                        
-                        // Get the presentation time from the first sample
-                        let start_time = CMSampleBufferGetPresentationTimeStamp(sample_buffer);
-                        
-                        // Start session at source time
-                        let _: () = msg_send![asset_writer, startSessionAtSourceTime: start_time];
-                        
-                        *is_recording = true;
-                        println!("✅ Recording session started successfully");
-                    }
+        if self.recording_started {
+            return Ok(());
+        }
+        
+        if self.asset_writer.is_some() {
+            unsafe {
+                // Start the writing session
+                let asset_writer = self.start_writing_with_retry()?;
+                
+                // Get the presentation time from the first sample
+                let start_time = CMSampleBufferGetPresentationTimeStamp(sample_buffer);
+                
+                // Start session at source time
+                let _: () = msg_send![asset_writer, startSessionAtSourceTime: start_time];
+                
+                if let Ok(mut is_recording) = self.is_recording.lock() {
+                    *is_recording = true;
                 }
+                self.recording_started = true;
+                println!("✅ Recording session started successfully");
             }
         }
         Ok(())
     }
     
+    /// Call `startWriting`, recreating the writer and retrying on failure
+    /// Transient failures happen right after permission changes; a failed writer can't be
+    /// restarted, so each retry builds a fresh one via `initialize_asset_writer`
+    unsafe fn start_writing_with_retry(&mut self) -> Result<*mut AVAssetWriter> {
+        let mut attempt = 0;
+        loop {
+            let asset_writer = self.asset_writer
+                .ok_or_else(|| Error::new(Status::GenericFailure, "Asset writer not initialized"))?;
+            
+            // Check current status first
+            let status: i32 = msg_send![asset_writer, status];
+            println!("📊 AVAssetWriter status before starting (attempt {}): {}", attempt + 1, status);
+            
+            let started: bool = msg_send![asset_writer, startWriting];
+            if started {
+                return Ok(asset_writer);
+            }
+            
+            let details = asset_writer_error_description(asset_writer);
+            println!("❌ AVAssetWriter startWriting failed: {}", details);
+            
+            if attempt >= self.writer_start_retries {
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to start writing session after {} attempt(s): {}", attempt + 1, details),
+                ));
+            }
+            
+            attempt += 1;
+            println!("🔁 Recreating asset writer, retry {}/{}", attempt, self.writer_start_retries);
+            std::thread::sleep(std::time::Duration::from_millis(WRITER_START_RETRY_DELAY_MS * attempt as u64));
+            
+            let _ = std::fs::remove_file(&self.output_path);
+            self.initialize_asset_writer()?;
+        }
+    }
+    
     /// Create properly configured video input with fixed codec settings
     unsafe fn create_video_input(&self) -> Result<*mut AVAssetWriterInput> {
         use objc2_foundation::{NSDictionary, NSString, NSNumber};
@@ -319,7 +682,11 @@ impl StreamOutput {
         
         // Create video settings with fixed codec configuration (no AVVideoAverageBitRateKey)
         let codec_key = NSString::from_str("AVVideoCodecKey");
-        let codec_value = NSString::from_str("avc1"); // H.264
+        let codec_value = NSString::from_str(match self.codec {
+            VideoCodec::H264 => AVVideoCodecTypeH264,
+            VideoCodec::Hevc => AVVideoCodecTypeHEVC,
+            VideoCodec::HevcWithAlpha => AVVideoCodecTypeHEVCWithAlpha,
+        });
         
         let width_key = NSString::from_str("AVVideoWidthKey");
         let width_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: self.width];
@@ -328,16 +695,51 @@ impl StreamOutput {
         let height_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: self.height];
         
         // Create main video settings dictionary (no compression properties for avc1 compatibility)
-        let settings: *mut NSDictionary<NSString, AnyObject> = msg_send![
-            class!(NSDictionary),
-            dictionaryWithObjects: &[
-                &*codec_value as *const NSString as *mut AnyObject,
-                width_value as *mut AnyObject,
-                height_value as *mut AnyObject
-            ],
-            forKeys: &[&*codec_key, &*width_key, &*height_key],
-            count: 3
+        // 10-bit capture needs the HEVC Main10 profile to keep its depth
+        let profile_level = self.profile_level.as_deref()
+            .or(self.pixel_format.is_ten_bit().then_some(AVVideoProfileLevelHEVCMain10AutoLevel));
+        let settings: *mut NSDictionary<NSString, AnyObject> = if let Some(profile_level) = profile_level {
+            let compression_key = NSString::from_str("AVVideoCompressionPropertiesKey");
+            let profile_key = NSString::from_str("ProfileLevel");
+            let profile_value = NSString::from_str(profile_level);
+            let compression_properties: *mut NSDictionary<NSString, AnyObject> = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObject: &*profile_value as *const NSString as *mut AnyObject,
+                forKey: &*profile_key
+            ];
+            
+            msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: &[
+                    &*codec_value as *const NSString as *mut AnyObject,
+                    width_value as *mut AnyObject,
+                    height_value as *mut AnyObject,
+                    compression_properties as *mut AnyObject
+                ],
+                forKeys: &[&*codec_key, &*width_key, &*height_key, &*compression_key],
+                count: 4
+            ]
+        } else {
+            msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: &[
+                    &*codec_value as *const NSString as *mut AnyObject,
+                    width_value as *mut AnyObject,
+                    height_value as *mut AnyObject
+                ],
+                forKeys: &[&*codec_key, &*width_key, &*height_key],
+                count: 3
+            ]
+        };
+        
+        // Only takes effect when captured frames differ from the output size
+        let settings: *mut objc2_foundation::NSMutableDictionary<NSString, AnyObject> = msg_send![
+            class!(NSMutableDictionary),
+            dictionaryWithDictionary: settings
         ];
+        let scaling_key = NSString::from_str("AVVideoScalingModeKey");
+        let scaling_value = NSString::from_str(self.scaling_mode.av_scaling_mode());
+        let _: () = msg_send![settings, setObject: &*scaling_value, forKey: &*scaling_key];
         
         let media_type = NSString::from_str("vide");
         let video_input: *mut AVAssetWriterInput = msg_send![
@@ -428,7 +830,7 @@ impl StreamOutput {
         use objc2_foundation::{NSDictionary, NSNumber};
         
         let pixel_format_key = NSString::from_str("kCVPixelBufferPixelFormatTypeKey");
-        let pixel_format_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: 0x42475241u32]; // 'BGRA'
+        let pixel_format_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: self.pixel_format.fourcc()];
         
         let attributes: *mut NSDictionary<objc2_foundation::NSString, AnyObject> = msg_send![
             class!(NSDictionary),
@@ -448,6 +850,10 @@ impl StreamOutput {
         println!("📊 Final Recording Statistics:");
         println!("   📹 Video frames: {}", video_frames);
         println!("   🔊 Audio samples: {}", audio_samples);
+        let (nudged, dropped) = self.timestamp_guard.stats();
+        if nudged > 0 || dropped > 0 {
+            println!("   ⏱️ Timestamp fixes: {} nudged, {} dropped", nudged, dropped);
+        }
         println!("   📁 Output file: {}", self.output_path);
         
         if video_frames > 0 {
@@ -465,6 +871,11 @@ impl StreamOutput {
         (video_frames, audio_samples, is_recording)
     }
     
+    /// (nudged, dropped) counts of frames with non-increasing timestamps
+    pub fn get_timestamp_stats(&self) -> (u64, u64) {
+        self.timestamp_guard.stats()
+    }
+    
     /// Get the output path
     pub fn get_output_path(&self) -> &str {
         &self.output_path
@@ -483,4 +894,41 @@ pub unsafe fn create_stream_delegate(_stream_output: Arc<Mutex<StreamOutput>>) -
     
     println!("✅ Created stream delegate object");
     delegate
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn time(value: i64) -> CMTime {
+        CMTime { value, timescale: 600, flags: objc2_core_media::CMTimeFlags(1), epoch: 0 }
+    }
+    
+    #[test]
+    fn test_non_monotonic_timestamps() {
+        let mut guard = PresentationTimeGuard::new(30);
+        let mut appended = Vec::new();
+        for value in [0, 20, 20, 10, 60, 50, 100] {
+            match guard.check(time(value)) {
+                TimestampDecision::Accept(t) | TimestampDecision::Nudge(t) => {
+                    appended.push({ t.value } * 600 / { t.timescale } as i64)
+                }
+                TimestampDecision::Drop => {}
+            }
+        }
+        
+        // Duplicates move one 1/30s frame (20 ticks at 600) forward; earlier times are dropped
+        assert_eq!(appended, vec![0, 20, 40, 60, 100]);
+        assert_eq!(guard.stats(), (1, 2));
+    }
+    
+    #[test]
+    fn test_permission_denied_output_directory_is_explained() {
+        let directory = Path::new("/Volumes/Shared/Recordings");
+        let denied = output_directory_error(directory, &std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert!(denied.reason.contains("isn't allowed to write here"), "{}", denied.reason);
+        
+        let other = output_directory_error(directory, &std::io::Error::from(std::io::ErrorKind::StorageFull));
+        assert!(other.reason.starts_with("Failed to create output directory /Volumes/Shared/Recordings"), "{}", other.reason);
+    }
+}
@@ -0,0 +1,109 @@
+// Temporary files
+// Recordings and transcription jobs write intermediate files along the way: the extracted WAV
+// a transcription reads, Whisper's JSON output, the separate audio file of a recording. Each
+// job tracks its files in a TempFiles registry that deletes them when it's dropped, so a job
+// failing halfway through leaves nothing behind. Files that can't be deleted then are kept in
+// a process-wide list for cleanup_temp_files to retry
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Files whose removal failed, waiting for `cleanup_temp_files`
+fn leftovers() -> &'static Mutex<HashSet<PathBuf>> {
+    static LEFTOVERS: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    LEFTOVERS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Delete `path`; true when it's gone, including when it never existed
+fn remove(path: &Path) -> bool {
+    match std::fs::remove_file(path) {
+        Ok(()) => {
+            println!("🧹 Removed temporary file: {}", path.display());
+            true
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+        Err(e) => {
+            log::warn!("Failed to remove temporary file {}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+/// The intermediate files of one recording or job; dropping it deletes the ones still tracked
+#[derive(Debug, Default)]
+pub struct TempFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl TempFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delete `path` with the others unless it's kept; track it before writing so a partial
+    /// file is covered too
+    pub fn track(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_path_buf();
+        if !self.paths.contains(&path) {
+            self.paths.push(path);
+        }
+    }
+
+    /// Stop tracking `path`, e.g. once it's become part of the finished recording
+    pub fn keep(&mut self, path: impl AsRef<Path>) {
+        self.paths.retain(|tracked| tracked != path.as_ref());
+    }
+
+    /// Delete every tracked file now
+    pub fn cleanup(&mut self) {
+        for path in self.paths.drain(..) {
+            if !remove(&path) {
+                if let Ok(mut leftovers) = leftovers().lock() {
+                    leftovers.insert(path);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
+
+/// Retry deleting temporary files earlier cleanups couldn't remove; returns how many are gone
+pub fn cleanup_temp_files() -> u32 {
+    let Ok(mut leftovers) = leftovers().lock() else {
+        return 0;
+    };
+    let before = leftovers.len();
+    leftovers.retain(|path| !remove(path));
+    (before - leftovers.len()) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracked_files_are_removed_unless_kept() {
+        let scratch = std::env::temp_dir().join("screencapturekit_temp_files_scratch.wav");
+        let kept = std::env::temp_dir().join("screencapturekit_temp_files_kept.m4a");
+        std::fs::write(&scratch, b"scratch").expect("Write scratch file");
+        std::fs::write(&kept, b"kept").expect("Write kept file");
+
+        let mut temp_files = TempFiles::new();
+        temp_files.track(&scratch);
+        temp_files.track(&kept);
+        temp_files.track(std::env::temp_dir().join("screencapturekit_temp_files_never_written.json"));
+        temp_files.keep(&kept);
+        drop(temp_files);
+
+        assert!(!scratch.exists(), "Dropping the registry removes its files");
+        assert!(kept.exists(), "Kept files stay");
+        assert_eq!(cleanup_temp_files(), 0, "Files that never existed aren't leftovers");
+        let _ = std::fs::remove_file(&kept);
+    }
+}
@@ -0,0 +1,205 @@
+// Synthetic frames for tests
+// Builds the sample buffers ScreenCaptureKit would deliver, without a display or a screen
+// recording permission: BGRA pixel buffers of a given size, filled with a pattern that
+// depends only on the frame number, presented at exact multiples of the frame duration.
+// Tests feed them straight into RealStreamDelegate::handle_video_sample_buffer. Audio comes
+// from ToneGenerator: interleaved float PCM buffers of a sine tone, back to back
+
+use std::ffi::c_void;
+use std::ptr;
+use objc2_core_media::{CMSampleBuffer, CMSampleTimingInfo, CMTime, CMTimeFlags, kCMTimeInvalid};
+use objc2_core_video::CVPixelBuffer;
+
+use super::core_audio::{
+    AudioBuffer, AudioBufferList, AudioStreamBasicDescription, CMAudioFormatDescriptionCreate,
+    CMAudioSampleBufferCreateWithPacketDescriptions, CMSampleBufferSetDataBufferFromAudioBufferList,
+    AUDIO_FORMAT_FLAG_IS_FLOAT, AUDIO_FORMAT_LINEAR_PCM,
+};
+use super::recording::kCVPixelFormatType_32BGRA;
+
+const AUDIO_FORMAT_FLAG_IS_PACKED: u32 = 1 << 3; // kAudioFormatFlagIsPacked
+
+extern "C" {
+    fn CVPixelBufferCreate(
+        allocator: *const c_void,
+        width: usize,
+        height: usize,
+        pixel_format_type: u32,
+        pixel_buffer_attributes: *const c_void,
+        pixel_buffer_out: *mut *mut CVPixelBuffer,
+    ) -> i32;
+    fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut CVPixelBuffer, lock_flags: u64) -> i32;
+    fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut CVPixelBuffer, unlock_flags: u64) -> i32;
+    fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut CVPixelBuffer) -> *mut c_void;
+    fn CVPixelBufferGetBytesPerRow(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferRelease(pixel_buffer: *mut CVPixelBuffer);
+    fn CMVideoFormatDescriptionCreateForImageBuffer(
+        allocator: *const c_void,
+        image_buffer: *mut CVPixelBuffer,
+        format_description_out: *mut *mut c_void,
+    ) -> i32;
+    fn CMSampleBufferCreateReadyWithImageBuffer(
+        allocator: *const c_void,
+        image_buffer: *mut CVPixelBuffer,
+        format_description: *mut c_void,
+        sample_timing: *const CMSampleTimingInfo,
+        sample_buffer_out: *mut *mut CMSampleBuffer,
+    ) -> i32;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// A generated sample buffer, released when dropped
+pub struct SyntheticSample(*mut CMSampleBuffer);
+
+impl SyntheticSample {
+    pub fn sample(&self) -> &CMSampleBuffer {
+        unsafe { &*self.0 }
+    }
+}
+
+impl Drop for SyntheticSample {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0 as *const c_void) };
+    }
+}
+
+/// Produces numbered frames at `fps`; frame n is presented at n/fps seconds
+pub struct FrameGenerator {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    next_frame: i64,
+}
+
+impl FrameGenerator {
+    pub fn new(width: u32, height: u32, fps: u32) -> Self {
+        Self { width, height, fps, next_frame: 0 }
+    }
+
+    /// Presentation time of frame `frame`
+    pub fn time_of(&self, frame: i64) -> CMTime {
+        CMTime { value: frame, timescale: self.fps as i32, flags: CMTimeFlags(1), epoch: 0 }
+    }
+
+    /// The next frame; panics when CoreVideo or CoreMedia can't build it
+    pub fn next_sample(&mut self) -> SyntheticSample {
+        let frame = self.next_frame;
+        self.next_frame += 1;
+        unsafe {
+            let pixel_buffer = self.pixel_buffer(frame);
+            let mut format_description: *mut c_void = ptr::null_mut();
+            let status = CMVideoFormatDescriptionCreateForImageBuffer(ptr::null(), pixel_buffer, &mut format_description);
+            assert_eq!(status, 0, "Format description for frame {}", frame);
+
+            let timing = CMSampleTimingInfo {
+                duration: self.time_of(1),
+                presentationTimeStamp: self.time_of(frame),
+                decodeTimeStamp: kCMTimeInvalid,
+            };
+            let mut sample_buffer: *mut CMSampleBuffer = ptr::null_mut();
+            let status = CMSampleBufferCreateReadyWithImageBuffer(ptr::null(), pixel_buffer, format_description, &timing, &mut sample_buffer);
+            CFRelease(format_description);
+            // The sample buffer holds its own reference
+            CVPixelBufferRelease(pixel_buffer);
+            assert!(status == 0 && !sample_buffer.is_null(), "Sample buffer for frame {} (OSStatus {})", frame, status);
+            SyntheticSample(sample_buffer)
+        }
+    }
+
+    /// A BGRA buffer whose colour and a moving vertical bar are derived from `frame`
+    unsafe fn pixel_buffer(&self, frame: i64) -> *mut CVPixelBuffer {
+        let mut pixel_buffer: *mut CVPixelBuffer = ptr::null_mut();
+        let status = CVPixelBufferCreate(
+            ptr::null(),
+            self.width as usize,
+            self.height as usize,
+            kCVPixelFormatType_32BGRA,
+            ptr::null(),
+            &mut pixel_buffer,
+        );
+        assert!(status == 0 && !pixel_buffer.is_null(), "Pixel buffer for frame {} (CVReturn {})", frame, status);
+
+        assert_eq!(CVPixelBufferLockBaseAddress(pixel_buffer, 0), 0);
+        let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *mut u8;
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+        let shade = (frame * 4 % 256) as u8;
+        let bar = (frame as usize * 8) % self.width.max(1) as usize;
+        for y in 0..self.height as usize {
+            let row = std::slice::from_raw_parts_mut(base.add(y * bytes_per_row), self.width as usize * 4);
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let value = if x == bar { 255 } else { shade };
+                pixel.copy_from_slice(&[value, value / 2, 255 - value, 255]);
+            }
+        }
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+        pixel_buffer
+    }
+}
+
+/// Produces consecutive buffers of a sine tone as interleaved float PCM, starting at `start`
+/// seconds; each buffer holds `frames_per_buffer` frames
+pub struct ToneGenerator {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub frames_per_buffer: usize,
+    pub frequency: f64,
+    next_frame: i64,
+}
+
+impl ToneGenerator {
+    pub fn new(sample_rate: u32, channels: u32, frequency: f64, start: f64) -> Self {
+        Self { sample_rate, channels, frames_per_buffer: 1024, frequency, next_frame: (start * sample_rate as f64) as i64 }
+    }
+
+    /// The next buffer; panics when CoreMedia can't build it
+    pub fn next_sample(&mut self) -> SyntheticSample {
+        let first = self.next_frame;
+        self.next_frame += self.frames_per_buffer as i64;
+        let mut samples: Vec<f32> = (first..self.next_frame)
+            .flat_map(|frame| {
+                let value = (frame as f64 * self.frequency * std::f64::consts::TAU / self.sample_rate as f64).sin() * 0.25;
+                std::iter::repeat(value as f32).take(self.channels as usize)
+            })
+            .collect();
+
+        unsafe {
+            let bytes_per_frame = 4 * self.channels;
+            let format = AudioStreamBasicDescription {
+                sample_rate: self.sample_rate as f64,
+                format_id: AUDIO_FORMAT_LINEAR_PCM,
+                format_flags: AUDIO_FORMAT_FLAG_IS_FLOAT | AUDIO_FORMAT_FLAG_IS_PACKED,
+                bytes_per_packet: bytes_per_frame,
+                frames_per_packet: 1,
+                bytes_per_frame,
+                channels_per_frame: self.channels,
+                bits_per_channel: 32,
+                reserved: 0,
+            };
+            let mut format_description: *mut c_void = ptr::null_mut();
+            let status = CMAudioFormatDescriptionCreate(ptr::null(), &format, 0, ptr::null(), 0, ptr::null(), ptr::null(), &mut format_description);
+            assert_eq!(status, 0, "Audio format description");
+
+            let presentation_time = CMTime { value: first, timescale: self.sample_rate as i32, flags: CMTimeFlags(1), epoch: 0 };
+            let mut sample_buffer: *mut CMSampleBuffer = ptr::null_mut();
+            let status = CMAudioSampleBufferCreateWithPacketDescriptions(
+                ptr::null(), ptr::null(), 0, ptr::null(), ptr::null(),
+                format_description, self.frames_per_buffer as isize, presentation_time, ptr::null(), &mut sample_buffer,
+            );
+            CFRelease(format_description);
+            assert!(status == 0 && !sample_buffer.is_null(), "Audio sample buffer at frame {} (OSStatus {})", first, status);
+
+            // Copies the samples into the buffer's own block buffer
+            let buffer_list = AudioBufferList {
+                number_buffers: 1,
+                buffers: [AudioBuffer {
+                    number_channels: self.channels,
+                    data_byte_size: (samples.len() * 4) as u32,
+                    data: samples.as_mut_ptr() as *mut c_void,
+                }],
+            };
+            let status = CMSampleBufferSetDataBufferFromAudioBufferList(sample_buffer, ptr::null(), ptr::null(), 0, &buffer_list);
+            assert_eq!(status, 0, "Audio data for frame {}", first);
+            SyntheticSample(sample_buffer)
+        }
+    }
+}
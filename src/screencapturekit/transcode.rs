@@ -0,0 +1,540 @@
+// Re-encoding of finished recordings
+// Reads a movie with AVAssetReader and writes it again with AVAssetWriter, so a large
+// recording can be turned into a delivery copy with another codec, bitrate or size. Video is
+// decoded by a FrameDecoder following decoderPreference; when decoding fails the transcode is
+// started over with the software decoder
+
+use std::ffi::c_void;
+use std::path::Path;
+use std::ptr;
+use std::time::{Duration, Instant};
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSString, NSURL, NSError, NSArray, NSDictionary, NSNumber};
+use objc2_core_media::{CMTime, CMSampleBuffer, kCMTimeZero};
+use napi::{Result, Status, Error};
+
+use crate::TranscodeConfiguration;
+use super::core_audio::AUDIO_FORMAT_LINEAR_PCM;
+use super::decoder::{DecodedSample, FrameDecoder};
+use super::encoder::{
+    VideoEncoder, AudioEncoder, VideoEncoderSettings, asset_writer_error_description, file_type_for,
+    AVFileTypeMPEG4, AVMediaTypeVideo, AVMediaTypeAudio,
+};
+use super::foundation::{CGSize, describe_nserror};
+use super::types::{AspectRatio, DecoderPreference, VideoCodec, ScalingMode, validate_dimensions, MAX_OUTPUT_WIDTH, MAX_OUTPUT_HEIGHT};
+
+// How often progress is reported
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+// Wait before polling again when neither writer input can take more data
+const INPUT_WAIT: Duration = Duration::from_millis(5);
+
+// AVAssetReaderStatusFailed and AVAssetWriterStatusFailed
+const AV_STATUS_FAILED: isize = 3;
+
+// Audio is decoded to 16-bit interleaved PCM at the rate the recorder writes, then AAC again
+const AUDIO_SAMPLE_RATE: u32 = 48000;
+const AUDIO_CHANNELS: u32 = 2;
+
+extern "C" {
+    fn CFRelease(cf: *const c_void);
+    fn CMTimeGetSeconds(time: CMTime) -> f64;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+}
+
+/// Reported to the transcode progress callback
+#[derive(Debug, Clone)]
+pub struct TranscodeEvent {
+    pub processed_seconds: f64,
+    pub duration_seconds: f64,
+    /// 0-100
+    pub progress: f64,
+    /// "hardware" or "software", the decoder reading the source
+    pub decoder: &'static str,
+}
+
+/// Output options; sizes left unset follow the source
+#[derive(Debug, Clone, Default)]
+pub struct TranscodeSettings {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub codec: VideoCodec,
+    pub bitrate_kbps: Option<u32>,
+    pub quality: Option<f64>,
+    pub scaling_mode: ScalingMode,
+    pub editing_friendly: bool,
+    pub include_audio: bool,
+    pub decoder_preference: DecoderPreference,
+}
+
+/// Why a transcode run stopped; decoding failures are retried with the software decoder
+enum Failure {
+    Decode(Error),
+    Other(Error),
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        Failure::Other(error)
+    }
+}
+
+impl TranscodeSettings {
+    /// Validate `config` and convert it; every problem is an InvalidArg error
+    pub fn from_config(config: &TranscodeConfiguration) -> Result<Self> {
+        let codec = match config.video_codec.as_deref() {
+            None => VideoCodec::default(),
+            // Alpha is only produced by window recordings
+            Some(value) => VideoCodec::from_config_str(value).filter(|codec| *codec != VideoCodec::HevcWithAlpha).ok_or_else(|| Error::new(
+                Status::InvalidArg,
+                format!("Invalid videoCodec \"{}\": expected \"h264\" or \"hevc\"", value),
+            ))?,
+        };
+        let scaling_mode = match config.scaling_mode.as_deref() {
+            None => ScalingMode::default(),
+            Some(value) => ScalingMode::from_config_str(value).ok_or_else(|| Error::new(
+                Status::InvalidArg,
+                format!("Invalid scalingMode \"{}\": expected \"fit\", \"fill\" or \"stretch\"", value),
+            ))?,
+        };
+        let decoder_preference = match config.decoder_preference.as_deref() {
+            None => DecoderPreference::default(),
+            Some(value) => DecoderPreference::from_config_str(value).ok_or_else(|| Error::new(
+                Status::InvalidArg,
+                format!("Invalid decoderPreference \"{}\": expected \"auto\", \"hardware\" or \"software\"", value),
+            ))?,
+        };
+        if config.video_bitrate_kbps == Some(0) {
+            return Err(Error::new(Status::InvalidArg, "videoBitrateKbps must be greater than 0"));
+        }
+        if let Some(quality) = config.quality {
+            if config.video_bitrate_kbps.is_some() {
+                return Err(Error::new(Status::InvalidArg, "quality and videoBitrateKbps can't be combined"));
+            }
+            if !(0.0..=1.0).contains(&quality) {
+                return Err(Error::new(Status::InvalidArg, "quality must be between 0.0 and 1.0"));
+            }
+        }
+        for (name, value, max) in [("width", config.width, MAX_OUTPUT_WIDTH), ("height", config.height, MAX_OUTPUT_HEIGHT)] {
+            if let Some(value) = value {
+                if !(100..=max).contains(&value) {
+                    return Err(Error::new(Status::InvalidArg, format!("{} must be between 100 and {}, got {}", name, max, value)));
+                }
+            }
+        }
+
+        Ok(Self {
+            width: config.width,
+            height: config.height,
+            codec,
+            bitrate_kbps: config.video_bitrate_kbps,
+            quality: config.quality,
+            scaling_mode,
+            editing_friendly: config.editing_friendly.unwrap_or(false),
+            include_audio: config.include_audio.unwrap_or(true),
+            decoder_preference,
+        })
+    }
+
+    /// Output size for a `source` sized video; one missing dimension keeps the source aspect
+    pub fn output_size(&self, source: (u32, u32)) -> (u32, u32) {
+        let source_ratio = AspectRatio { width: source.0.max(1), height: source.1.max(1) };
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, source_ratio.height_for_width(width)),
+            (None, Some(height)) => (source_ratio.width_for_height(height), height),
+            (None, None) => source,
+        }
+    }
+}
+
+/// Check the paths before any AVFoundation work starts
+pub fn validate_paths(input_path: &str, output_path: &str) -> Result<()> {
+    if !Path::new(input_path).is_file() {
+        return Err(Error::new(Status::InvalidArg, format!("Input file not found: {}", input_path)));
+    }
+    if output_path.is_empty() {
+        return Err(Error::new(Status::InvalidArg, "Output path cannot be empty"));
+    }
+    if file_type_for(output_path).is_none() {
+        return Err(Error::new(Status::InvalidArg, format!("Output must be a .mp4, .m4v or .mov file: {}", output_path)));
+    }
+
+    let same_file = match (std::fs::canonicalize(input_path), std::fs::canonicalize(output_path)) {
+        (Ok(input), Ok(output)) => input == output,
+        _ => input_path == output_path,
+    };
+    if same_file {
+        return Err(Error::new(Status::InvalidArg, "Output path must differ from the input path"));
+    }
+    // AVAssetWriter refuses to overwrite; fail before reading the whole input
+    if Path::new(output_path).exists() {
+        return Err(Error::new(Status::InvalidArg, format!("Output file already exists: {}", output_path)));
+    }
+    Ok(())
+}
+
+/// Re-encode `input_path` into `output_path`, blocking until the copy is written
+/// The partially written output is removed when transcoding fails
+pub fn transcode_file<F>(input_path: &str, output_path: &str, settings: &TranscodeSettings, on_progress: F) -> Result<String>
+where
+    F: Fn(TranscodeEvent),
+{
+    validate_paths(input_path, output_path)?;
+    println!("🎞️ Transcoding {} -> {}", input_path, output_path);
+
+    let mut result = unsafe { run(input_path, output_path, settings, settings.decoder_preference, &on_progress) };
+    if let Err(Failure::Decode(ref e)) = result {
+        if settings.decoder_preference != DecoderPreference::Software {
+            println!("⚠️ {}; transcoding again with the software decoder", e.reason);
+            let _ = std::fs::remove_file(output_path);
+            result = unsafe { run(input_path, output_path, settings, DecoderPreference::Software, &on_progress) };
+        }
+    }
+    match result.map_err(|(Failure::Decode(e) | Failure::Other(e))| e) {
+        Ok(()) => {
+            println!("✅ Transcoded: {}", output_path);
+            Ok(output_path.to_string())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(output_path);
+            println!("❌ Transcoding failed: {}", e.reason);
+            Err(e)
+        }
+    }
+}
+
+/// First track of `media_type`, or null
+pub(super) unsafe fn first_track(asset: *mut AnyObject, media_type: &str) -> *mut AnyObject {
+    let media_type = NSString::from_str(media_type);
+    let tracks: *mut NSArray<AnyObject> = msg_send![asset, tracksWithMediaType: &*media_type];
+    if tracks.is_null() {
+        return ptr::null_mut();
+    }
+    msg_send![tracks, firstObject]
+}
+
+pub(super) unsafe fn number_u32(value: u32) -> *mut AnyObject {
+    let number: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: value];
+    number as *mut AnyObject
+}
+
+pub(super) unsafe fn dictionary(entries: &[(*const NSString, *mut AnyObject)]) -> *mut NSDictionary<NSString, AnyObject> {
+    let keys: Vec<&NSString> = entries.iter().map(|(key, _)| &**key).collect();
+    let values: Vec<*mut AnyObject> = entries.iter().map(|(_, value)| *value).collect();
+    msg_send![
+        class!(NSDictionary),
+        dictionaryWithObjects: values.as_ptr(),
+        forKeys: keys.as_ptr(),
+        count: entries.len()
+    ]
+}
+
+unsafe fn run<F>(
+    input_path: &str,
+    output_path: &str,
+    settings: &TranscodeSettings,
+    decoder_preference: DecoderPreference,
+    on_progress: &F,
+) -> std::result::Result<(), Failure>
+where
+    F: Fn(TranscodeEvent),
+{
+    let input_string = NSString::from_str(input_path);
+    let input_url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*input_string];
+    let asset: *mut AnyObject = msg_send![class!(AVURLAsset), URLAssetWithURL: input_url, options: ptr::null_mut::<AnyObject>()];
+    if asset.is_null() {
+        return Err(Error::new(Status::InvalidArg, format!("Failed to open {}", input_path)).into());
+    }
+
+    let video_track = first_track(asset, AVMediaTypeVideo);
+    if video_track.is_null() {
+        return Err(Error::new(Status::InvalidArg, format!("Input has no video track: {}", input_path)).into());
+    }
+    let audio_track = if settings.include_audio { first_track(asset, AVMediaTypeAudio) } else { ptr::null_mut() };
+
+    let natural_size: CGSize = msg_send![video_track, naturalSize];
+    let (width, height) = settings.output_size((natural_size.width.round() as u32, natural_size.height.round() as u32));
+    if validate_dimensions(width, height).is_err() {
+        return Err(Error::new(
+            Status::InvalidArg,
+            format!("Output size {}x{} is outside 100x100..{}x{}", width, height, MAX_OUTPUT_WIDTH, MAX_OUTPUT_HEIGHT),
+        ).into());
+    }
+    let nominal_fps: f32 = msg_send![video_track, nominalFrameRate];
+    let duration: CMTime = msg_send![asset, duration];
+    let duration_seconds = CMTimeGetSeconds(duration).max(0.0);
+
+    // Video is decoded here rather than by the reader, so the decoder can be chosen
+    let format_descriptions: *mut NSArray<AnyObject> = msg_send![video_track, formatDescriptions];
+    let format_description: *mut AnyObject = if format_descriptions.is_null() { ptr::null_mut() } else { msg_send![format_descriptions, firstObject] };
+    if format_description.is_null() {
+        return Err(Error::new(Status::InvalidArg, format!("Input video has no format description: {}", input_path)).into());
+    }
+    let mut decoder = FrameDecoder::new(format_description as *const c_void, decoder_preference).map_err(Failure::Decode)?;
+    let decoder_name = if decoder.uses_hardware() { "hardware" } else { "software" };
+    println!("🎞️ Decoding with the {} decoder", decoder_name);
+
+    // Reader: compressed video samples and PCM audio
+    let mut error: *mut NSError = ptr::null_mut();
+    let reader: *mut AnyObject = msg_send![class!(AVAssetReader), assetReaderWithAsset: asset, error: &mut error];
+    if reader.is_null() {
+        return Err(Error::new(Status::GenericFailure, format!("Failed to read {}: {}", input_path, describe_nserror(error))).into());
+    }
+
+    let video_output: *mut AnyObject = msg_send![
+        class!(AVAssetReaderTrackOutput),
+        assetReaderTrackOutputWithTrack: video_track,
+        outputSettings: ptr::null_mut::<AnyObject>()
+    ];
+    let _: () = msg_send![video_output, setAlwaysCopiesSampleData: false];
+    let _: () = msg_send![reader, addOutput: video_output];
+
+    let audio_output: *mut AnyObject = if audio_track.is_null() {
+        ptr::null_mut()
+    } else {
+        let keys = ["AVFormatIDKey", "AVSampleRateKey", "AVNumberOfChannelsKey", "AVLinearPCMBitDepthKey",
+            "AVLinearPCMIsFloatKey", "AVLinearPCMIsBigEndianKey", "AVLinearPCMIsNonInterleaved"].map(NSString::from_str);
+        let no: *mut NSNumber = msg_send![class!(NSNumber), numberWithBool: false];
+        let audio_output_settings = dictionary(&[
+            (&*keys[0], number_u32(AUDIO_FORMAT_LINEAR_PCM)),
+            (&*keys[1], number_u32(AUDIO_SAMPLE_RATE)),
+            (&*keys[2], number_u32(AUDIO_CHANNELS)),
+            (&*keys[3], number_u32(16)),
+            (&*keys[4], no as *mut AnyObject),
+            (&*keys[5], no as *mut AnyObject),
+            (&*keys[6], no as *mut AnyObject),
+        ]);
+        let output: *mut AnyObject = msg_send![
+            class!(AVAssetReaderTrackOutput),
+            assetReaderTrackOutputWithTrack: audio_track,
+            outputSettings: audio_output_settings
+        ];
+        let _: () = msg_send![reader, addOutput: output];
+        output
+    };
+
+    // Writer: the recorder's own video and audio settings
+    let output_string = NSString::from_str(output_path);
+    let output_url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*output_string];
+    let file_type = NSString::from_str(file_type_for(output_path).unwrap_or(AVFileTypeMPEG4));
+    let mut error: *mut NSError = ptr::null_mut();
+    let writer: *mut AnyObject = msg_send![
+        class!(AVAssetWriter),
+        assetWriterWithURL: output_url,
+        fileType: &*file_type,
+        error: &mut error
+    ];
+    if writer.is_null() {
+        return Err(Error::new(Status::GenericFailure, format!("Failed to create AVAssetWriter: {}", describe_nserror(error))).into());
+    }
+
+    let encoder_settings = VideoEncoderSettings {
+        width,
+        height,
+        fps: if nominal_fps > 0.0 { nominal_fps.round() as u32 } else { 30 },
+        codec: settings.codec,
+        editing_friendly: settings.editing_friendly,
+        scaling_mode: settings.scaling_mode,
+        bitrate_kbps: settings.bitrate_kbps,
+        quality: settings.quality,
+        ..VideoEncoderSettings::default()
+    };
+    let video_media_type = NSString::from_str(AVMediaTypeVideo);
+    let video_input: *mut AnyObject = msg_send![
+        class!(AVAssetWriterInput),
+        assetWriterInputWithMediaType: &*video_media_type,
+        outputSettings: VideoEncoder::create_video_settings(&encoder_settings)
+    ];
+    let can_add: bool = msg_send![writer, canAddInput: video_input];
+    if !can_add {
+        return Err(Error::new(Status::GenericFailure, format!("Cannot write {:?} video to {}", settings.codec, output_path)).into());
+    }
+    let _: () = msg_send![writer, addInput: video_input];
+
+    let audio_input: *mut AnyObject = if audio_output.is_null() {
+        ptr::null_mut()
+    } else {
+        let audio_media_type = NSString::from_str(AVMediaTypeAudio);
+        let input: *mut AnyObject = msg_send![
+            class!(AVAssetWriterInput),
+            assetWriterInputWithMediaType: &*audio_media_type,
+            outputSettings: AudioEncoder::create_audio_settings(AUDIO_SAMPLE_RATE, AUDIO_CHANNELS)
+        ];
+        let can_add: bool = msg_send![writer, canAddInput: input];
+        if !can_add {
+            return Err(Error::new(Status::GenericFailure, format!("Cannot write AAC audio to {}", output_path)).into());
+        }
+        let _: () = msg_send![writer, addInput: input];
+        input
+    };
+
+    let reading: bool = msg_send![reader, startReading];
+    if !reading {
+        let reader_error: *mut NSError = msg_send![reader, error];
+        return Err(Error::new(Status::GenericFailure, format!("Failed to start reading: {}", describe_nserror(reader_error))).into());
+    }
+    let writing: bool = msg_send![writer, startWriting];
+    if !writing {
+        let _: () = msg_send![reader, cancelReading];
+        return Err(Error::new(Status::GenericFailure, format!("Failed to start writing: {}", asset_writer_error_description(writer as *mut _))).into());
+    }
+    let _: () = msg_send![writer, startSessionAtSourceTime: kCMTimeZero];
+
+    // Feed both tracks as their inputs accept data; a track is done once its output runs dry
+    let mut tracks = vec![(video_output, video_input, false)];
+    if !audio_input.is_null() {
+        tracks.push((audio_output, audio_input, false));
+    }
+    let mut processed_seconds = 0.0_f64;
+    let mut last_report: Option<Instant> = None;
+    let report = |processed_seconds: f64| {
+        let progress = if duration_seconds > 0.0 { (processed_seconds / duration_seconds * 100.0).clamp(0.0, 100.0) } else { 0.0 };
+        on_progress(TranscodeEvent { processed_seconds, duration_seconds, progress, decoder: decoder_name });
+    };
+
+    while tracks.iter().any(|(_, _, done)| !*done) {
+        let mut appended = false;
+        for (output, input, done) in tracks.iter_mut().filter(|(_, _, done)| !*done) {
+            let ready: bool = msg_send![*input, isReadyForMoreMediaData];
+            if !ready {
+                continue;
+            }
+
+            let sample_buffer: *mut CMSampleBuffer = if *output == video_output {
+                match decoder.next_frame(video_output) {
+                    Ok(frame) => frame.map_or(ptr::null_mut(), DecodedSample::into_raw),
+                    Err(e) => {
+                        let _: () = msg_send![reader, cancelReading];
+                        let _: () = msg_send![writer, cancelWriting];
+                        return Err(Failure::Decode(e));
+                    }
+                }
+            } else {
+                msg_send![*output, copyNextSampleBuffer]
+            };
+            if sample_buffer.is_null() {
+                let _: () = msg_send![*input, markAsFinished];
+                *done = true;
+                continue;
+            }
+
+            let success: bool = msg_send![*input, appendSampleBuffer: &*sample_buffer];
+            let presentation_seconds = CMTimeGetSeconds(CMSampleBufferGetPresentationTimeStamp(&*sample_buffer));
+            CFRelease(sample_buffer as *const c_void);
+            if !success {
+                let _: () = msg_send![reader, cancelReading];
+                let _: () = msg_send![writer, cancelWriting];
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to write sample: {}", asset_writer_error_description(writer as *mut _)),
+                ).into());
+            }
+            if presentation_seconds.is_finite() {
+                processed_seconds = processed_seconds.max(presentation_seconds);
+            }
+            appended = true;
+        }
+
+        if last_report.is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL) {
+            report(processed_seconds);
+            last_report = Some(Instant::now());
+        }
+        if !appended {
+            // A failed reader or writer never becomes ready again; waiting on it would spin forever
+            let reader_status: isize = msg_send![reader, status];
+            if reader_status == AV_STATUS_FAILED {
+                let reader_error: *mut NSError = msg_send![reader, error];
+                let _: () = msg_send![writer, cancelWriting];
+                return Err(Error::new(Status::GenericFailure, format!("Failed to read {}: {}", input_path, describe_nserror(reader_error))).into());
+            }
+            let writer_status: isize = msg_send![writer, status];
+            if writer_status == AV_STATUS_FAILED {
+                let _: () = msg_send![reader, cancelReading];
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to write {}: {}", output_path, asset_writer_error_description(writer as *mut _)),
+                ).into());
+            }
+            std::thread::sleep(INPUT_WAIT);
+        }
+    }
+
+    let reader_status: isize = msg_send![reader, status];
+    if reader_status == AV_STATUS_FAILED {
+        let reader_error: *mut NSError = msg_send![reader, error];
+        let _: () = msg_send![writer, cancelWriting];
+        return Err(Error::new(Status::GenericFailure, format!("Failed to read {}: {}", input_path, describe_nserror(reader_error))).into());
+    }
+
+    let _: () = msg_send![writer, finishWriting];
+    let writer_status: isize = msg_send![writer, status];
+    if writer_status == AV_STATUS_FAILED {
+        return Err(Error::new(
+            Status::GenericFailure,
+            format!("Failed to finish {}: {}", output_path, asset_writer_error_description(writer as *mut _)),
+        ).into());
+    }
+
+    report(duration_seconds);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_size_follows_source_aspect() {
+        let settings = TranscodeSettings { width: Some(1280), ..TranscodeSettings::default() };
+        assert_eq!(settings.output_size((3840, 2160)), (1280, 720));
+        assert_eq!(TranscodeSettings::default().output_size((1000, 500)), (1000, 500));
+    }
+
+    #[test]
+    fn test_invalid_paths_are_rejected_before_reading() {
+        let input = std::env::temp_dir().join("screencapturekit_transcode_input.mp4");
+        std::fs::write(&input, b"").expect("Temp file is writable");
+        let input = input.to_string_lossy().to_string();
+
+        let missing = validate_paths("/nonexistent/input.mp4", "/tmp/out.mp4").expect_err("Missing input");
+        assert_eq!(missing.status, Status::InvalidArg);
+        assert!(validate_paths(&input, "/tmp/out.avi").is_err(), "Unsupported container");
+        assert!(validate_paths(&input, &input).is_err(), "Output overwrites input");
+
+        let _ = std::fs::remove_file(&input);
+    }
+
+    #[test]
+    fn test_forced_software_decoding_is_reported() {
+        use std::sync::{Arc, Mutex};
+        use super::super::delegate::RealStreamDelegate;
+        use super::super::drift;
+        use super::super::segments::SegmentInfo;
+        use super::super::test_frames::FrameGenerator;
+
+        let input = std::env::temp_dir().join("screencapturekit_transcode_decoder_input.mp4").to_string_lossy().to_string();
+        let output = std::env::temp_dir().join("screencapturekit_transcode_decoder_output.mp4").to_string_lossy().to_string();
+        let _ = std::fs::remove_file(&input);
+        let _ = std::fs::remove_file(&output);
+        let settings = VideoEncoderSettings { width: 320, height: 240, ..VideoEncoderSettings::default() };
+        let delegate = RealStreamDelegate::new(input.clone(), Arc::new(Mutex::new(true)), settings);
+        let mut frames = FrameGenerator::new(320, 240, 30);
+        for _ in 0..30 {
+            delegate.handle_video_sample_buffer(frames.next_sample().sample());
+        }
+        delegate.finalize_recording().expect("The source recording finalizes");
+
+        let decoders = Mutex::new(Vec::new());
+        let settings = TranscodeSettings { decoder_preference: DecoderPreference::Software, ..TranscodeSettings::default() };
+        transcode_file(&input, &output, &settings, |event| decoders.lock().unwrap().push(event.decoder)).expect("Transcodes");
+        let decoders = decoders.into_inner().unwrap();
+        assert!(!decoders.is_empty() && decoders.iter().all(|decoder| *decoder == "software"));
+        let info = SegmentInfo::probe(&output).expect("The copy opens as a movie");
+        assert_eq!((info.width, info.height), (320, 240));
+        assert!(drift::seconds(info.duration) > 0.9, "Every decoded frame is written");
+
+        let _ = std::fs::remove_file(&input);
+        let _ = std::fs::remove_file(&output);
+    }
+}
@@ -1,9 +1,15 @@
+use std::collections::HashMap;
 use std::path::Path;
 use std::fs;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicU32, Ordering};
 use serde::{Deserialize, Serialize};
 use napi::{Result, Status, Error};
 use tokio::time::{timeout, Duration};
 
+use super::temp_files::TempFiles;
+use crate::TranscriptionJobConfiguration;
+
 /// Configuration for transcription services
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptionConfig {
@@ -15,6 +21,39 @@ pub struct TranscriptionConfig {
     pub include_speaker_labels: bool,
 }
 
+impl TranscriptionConfig {
+    /// Validate a job's `config` over the defaults; every problem is an InvalidArg error
+    pub fn from_job_config(config: &TranscriptionJobConfiguration) -> Result<Self> {
+        let defaults = TranscriptionManager::default_config();
+        let service = match config.service.as_deref() {
+            None => defaults.service,
+            Some(value) => TranscriptionService::from_config_str(value).ok_or_else(|| Error::new(
+                Status::InvalidArg,
+                format!("Invalid service \"{}\": expected \"local\" or \"openaiWhisper\"", value),
+            ))?,
+        };
+        let output_format = match config.output_format.as_deref() {
+            None => defaults.output_format,
+            Some(value) => TranscriptionFormat::from_config_str(value).ok_or_else(|| Error::new(
+                Status::InvalidArg,
+                format!("Invalid outputFormat \"{}\": expected \"text\", \"srt\", \"vtt\" or \"json\"", value),
+            ))?,
+        };
+        if matches!(service, TranscriptionService::OpenAIWhisper) && config.api_key.is_none() {
+            return Err(Error::new(Status::InvalidArg, "apiKey is required for the openaiWhisper service"));
+        }
+
+        Ok(Self {
+            service,
+            api_key: config.api_key.clone(),
+            language: config.language.clone().or(defaults.language),
+            output_format,
+            include_timestamps: defaults.include_timestamps,
+            include_speaker_labels: defaults.include_speaker_labels,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TranscriptionService {
     OpenAIWhisper,
@@ -24,6 +63,17 @@ pub enum TranscriptionService {
     Local, // For local Whisper models
 }
 
+impl TranscriptionService {
+    /// Services that are implemented: "local" or "openaiWhisper"
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "local" => Some(Self::Local),
+            "openaiWhisper" => Some(Self::OpenAIWhisper),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TranscriptionFormat {
     Text,
@@ -32,6 +82,18 @@ pub enum TranscriptionFormat {
     JSON,
 }
 
+impl TranscriptionFormat {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(Self::Text),
+            "srt" => Some(Self::SRT),
+            "vtt" => Some(Self::VTT),
+            "json" => Some(Self::JSON),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptionResult {
     pub text: String,
@@ -74,6 +136,16 @@ impl TranscriptionManager {
     
     /// Transcribe audio from a recorded file
     pub async fn transcribe_file(&self, file_path: &str) -> Result<TranscriptionResult> {
+        self.transcribe_file_reporting(file_path, |_| {}).await.map(|(result, _)| result)
+    }
+    
+    /// As `transcribe_file`, passing each stage to `on_status` as it begins; also returns
+    /// where the transcript was saved
+    pub async fn transcribe_file_reporting(
+        &self,
+        file_path: &str,
+        on_status: impl Fn(TranscriptionJobStatus) + Send + Sync,
+    ) -> Result<(TranscriptionResult, String)> {
         println!("🎤 Starting transcription of: {}", file_path);
         
         // Validate input file
@@ -84,10 +156,13 @@ impl TranscriptionManager {
             ));
         }
         
-        // Extract audio if needed (for video files)
-        let audio_path = self.extract_audio_if_needed(file_path).await?;
+        // Extract audio if needed (for video files); it's deleted however the job ends
+        on_status(TranscriptionJobStatus::ExtractingAudio);
+        let mut temp_files = TempFiles::new();
+        let audio_path = self.extract_audio_if_needed(file_path, &mut temp_files).await?;
         
         // Perform transcription based on service
+        on_status(TranscriptionJobStatus::Transcribing);
         let result = match self.config.service {
             TranscriptionService::OpenAIWhisper => {
                 self.transcribe_with_openai_whisper(&audio_path).await?
@@ -107,14 +182,15 @@ impl TranscriptionManager {
         };
         
         // Save transcription result
-        self.save_transcription_result(&result, file_path).await?;
+        on_status(TranscriptionJobStatus::Saving);
+        let transcript_path = self.save_transcription_result(&result, file_path).await?;
         
         println!("✅ Transcription completed successfully");
-        Ok(result)
+        Ok((result, transcript_path))
     }
     
     /// Extract audio from video file if needed
-    async fn extract_audio_if_needed(&self, file_path: &str) -> Result<String> {
+    async fn extract_audio_if_needed(&self, file_path: &str, temp_files: &mut TempFiles) -> Result<String> {
         let path = Path::new(file_path);
         let extension = path.extension()
             .and_then(|ext| ext.to_str())
@@ -132,6 +208,7 @@ impl TranscriptionManager {
         );
         
         println!("🎵 Extracting audio to: {}", audio_path);
+        temp_files.track(&audio_path);
         
         let output = tokio::process::Command::new("ffmpeg")
             .args(&[
@@ -223,6 +300,12 @@ impl TranscriptionManager {
     async fn transcribe_with_local_whisper(&self, audio_path: &str) -> Result<TranscriptionResult> {
         println!("🏠 Transcribing with local Whisper model");
         
+        // Whisper names its output after the audio file; deleted however the job ends
+        let audio_filename = Path::new(audio_path).file_stem().unwrap().to_str().unwrap();
+        let json_path = format!("/tmp/{}.json", audio_filename);
+        let mut temp_files = TempFiles::new();
+        temp_files.track(&json_path);
+        
         // Use whisper command-line tool
         let mut cmd = tokio::process::Command::new("whisper");
         cmd.args(&[
@@ -248,18 +331,12 @@ impl TranscriptionManager {
         }
         
         // Read the output JSON file
-        let audio_filename = Path::new(audio_path).file_stem().unwrap().to_str().unwrap();
-        let json_path = format!("/tmp/{}.json", audio_filename);
-        
         let json_content = fs::read_to_string(&json_path)
             .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to read Whisper output: {}", e)))?;
         
         let whisper_response: serde_json::Value = serde_json::from_str(&json_content)
             .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to parse Whisper JSON: {}", e)))?;
         
-        // Clean up temporary file
-        let _ = fs::remove_file(&json_path);
-        
         self.parse_whisper_response(whisper_response)
     }
     
@@ -325,16 +402,17 @@ impl TranscriptionManager {
         ))
     }
     
-    /// Save transcription result to file
-    async fn save_transcription_result(&self, result: &TranscriptionResult, original_file: &str) -> Result<()> {
+    /// Save transcription result to file next to the original; returns the file's path
+    async fn save_transcription_result(&self, result: &TranscriptionResult, original_file: &str) -> Result<String> {
         let base_path = Path::new(original_file).with_extension("");
         
-        match self.config.output_format {
+        let output_path = match self.config.output_format {
             TranscriptionFormat::Text => {
                 let output_path = format!("{}.txt", base_path.to_str().unwrap());
                 fs::write(&output_path, &result.text)
                     .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to write text file: {}", e)))?;
                 println!("💾 Transcription saved as text: {}", output_path);
+                output_path
             }
             TranscriptionFormat::SRT => {
                 let output_path = format!("{}.srt", base_path.to_str().unwrap());
@@ -342,6 +420,7 @@ impl TranscriptionManager {
                 fs::write(&output_path, srt_content)
                     .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to write SRT file: {}", e)))?;
                 println!("💾 Transcription saved as SRT: {}", output_path);
+                output_path
             }
             TranscriptionFormat::VTT => {
                 let output_path = format!("{}.vtt", base_path.to_str().unwrap());
@@ -349,6 +428,7 @@ impl TranscriptionManager {
                 fs::write(&output_path, vtt_content)
                     .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to write VTT file: {}", e)))?;
                 println!("💾 Transcription saved as VTT: {}", output_path);
+                output_path
             }
             TranscriptionFormat::JSON => {
                 let output_path = format!("{}.json", base_path.to_str().unwrap());
@@ -357,10 +437,11 @@ impl TranscriptionManager {
                 fs::write(&output_path, json_content)
                     .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to write JSON file: {}", e)))?;
                 println!("💾 Transcription saved as JSON: {}", output_path);
+                output_path
             }
-        }
+        };
         
-        Ok(())
+        Ok(output_path)
     }
     
     /// Format transcription as SRT subtitles
@@ -453,4 +534,169 @@ impl TranscriptionManager {
         
         Ok(missing)
     }
-} 
\ No newline at end of file
+}
+/// Where a background transcription job is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptionJobStatus {
+    Queued,
+    ExtractingAudio,
+    Transcribing,
+    Saving,
+    Completed,
+    Failed,
+}
+
+impl TranscriptionJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::ExtractingAudio => "extractingAudio",
+            Self::Transcribing => "transcribing",
+            Self::Saving => "saving",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed)
+    }
+}
+
+/// A transcription running in the background
+#[derive(Debug, Clone)]
+pub struct TranscriptionJob {
+    pub id: u32,
+    pub input_path: String,
+    pub status: TranscriptionJobStatus,
+    /// Set once completed
+    pub result: Option<TranscriptionResult>,
+    pub transcript_path: Option<String>,
+    /// Set once failed
+    pub error: Option<String>,
+}
+
+/// Receives a job's snapshot each time its status changes
+pub type TranscriptionJobListener = Arc<dyn Fn(&TranscriptionJob) + Send + Sync>;
+
+/// Finished jobs kept for polling; the oldest are forgotten beyond this
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Registry of background transcription jobs, so callers can poll a job by id
+pub struct TranscriptionJobs {
+    next_id: AtomicU32,
+    jobs: Mutex<HashMap<u32, TranscriptionJob>>,
+}
+
+impl TranscriptionJobs {
+    pub fn new() -> Self {
+        Self { next_id: AtomicU32::new(1), jobs: Mutex::new(HashMap::new()) }
+    }
+
+    /// The registry shared by every recorder in the process
+    pub fn global() -> &'static Self {
+        static JOBS: OnceLock<TranscriptionJobs> = OnceLock::new();
+        JOBS.get_or_init(Self::new)
+    }
+
+    /// Add a queued job for `input_path`; pass its id to `run`
+    pub fn create(&self, input_path: &str) -> TranscriptionJob {
+        let job = TranscriptionJob {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            input_path: input_path.to_string(),
+            status: TranscriptionJobStatus::Queued,
+            result: None,
+            transcript_path: None,
+            error: None,
+        };
+        if let Ok(mut jobs) = self.jobs.lock() {
+            Self::forget_oldest_finished(&mut jobs);
+            jobs.insert(job.id, job.clone());
+        }
+        job
+    }
+
+    pub fn get(&self, id: u32) -> Option<TranscriptionJob> {
+        self.jobs.lock().ok()?.get(&id).cloned()
+    }
+
+    /// Transcribe the job's file with `manager`, recording each stage and the outcome
+    pub async fn run(&self, id: u32, manager: TranscriptionManager, listener: TranscriptionJobListener) {
+        let Some(job) = self.get(id) else {
+            return;
+        };
+        let outcome = manager
+            .transcribe_file_reporting(&job.input_path, |status| {
+                self.update(id, &listener, |job| job.status = status);
+            })
+            .await;
+
+        match outcome {
+            Ok((result, transcript_path)) => self.update(id, &listener, |job| {
+                job.status = TranscriptionJobStatus::Completed;
+                job.result = Some(result);
+                job.transcript_path = Some(transcript_path);
+            }),
+            Err(e) => {
+                println!("❌ Transcription job {} failed: {}", id, e.reason);
+                self.update(id, &listener, |job| {
+                    job.status = TranscriptionJobStatus::Failed;
+                    job.error = Some(e.reason.clone());
+                });
+            }
+        }
+    }
+
+    fn update(&self, id: u32, listener: &TranscriptionJobListener, change: impl FnOnce(&mut TranscriptionJob)) {
+        let snapshot = match self.jobs.lock() {
+            Ok(mut jobs) => match jobs.get_mut(&id) {
+                Some(job) => {
+                    change(job);
+                    job.clone()
+                }
+                None => return,
+            },
+            Err(_) => return,
+        };
+        listener(&snapshot);
+    }
+
+    fn forget_oldest_finished(jobs: &mut HashMap<u32, TranscriptionJob>) {
+        let mut finished: Vec<u32> = jobs.values()
+            .filter(|job| job.status.is_finished())
+            .map(|job| job.id)
+            .collect();
+        if finished.len() < MAX_FINISHED_JOBS {
+            return;
+        }
+        finished.sort_unstable();
+        for id in &finished[..=finished.len() - MAX_FINISHED_JOBS] {
+            jobs.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failed_job_can_be_polled() {
+        let jobs = TranscriptionJobs::new();
+        let job = jobs.create("/nonexistent/recording.mov");
+        assert_eq!(job.status, TranscriptionJobStatus::Queued);
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let listener_seen = seen.clone();
+        let listener: TranscriptionJobListener = Arc::new(move |job: &TranscriptionJob| {
+            listener_seen.lock().unwrap().push(job.status);
+        });
+        jobs.run(job.id, TranscriptionManager::new(TranscriptionManager::default_config()), listener).await;
+
+        let polled = jobs.get(job.id).expect("Finished jobs stay pollable");
+        assert_eq!(polled.status, TranscriptionJobStatus::Failed);
+        assert!(polled.error.unwrap_or_default().contains("does not exist"));
+        assert_eq!(*seen.lock().unwrap(), vec![TranscriptionJobStatus::Failed]);
+        assert_ne!(jobs.create("/nonexistent/other.mov").id, job.id);
+    }
+}
@@ -0,0 +1,251 @@
+// Recording triggers
+// Start a recording when an app comes to the front or a window appears, and stop it again.
+// NSWorkspace delivers its activation notifications through the main run loop, which Node
+// doesn't spin, so a background thread polls the on-screen window list instead; its front-to-back
+// order tells which app is frontmost
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSArray, NSDictionary, NSNumber, NSString};
+use napi::{Result, Status, Error};
+
+use crate::RecordingTriggerCondition;
+use super::types::WindowInfo;
+
+pub const DEFAULT_TRIGGER_POLL_INTERVAL_MS: u32 = 500;
+pub const MIN_TRIGGER_POLL_INTERVAL_MS: u32 = 100;
+
+// Granularity of the stop check inside a poll interval
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What a trigger waits for; apps are matched on bundle id or name, ignoring case
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerCondition {
+    /// The app owns the frontmost window
+    FrontmostApp(String),
+    /// An on-screen window's title contains `title`, optionally only for one app
+    WindowAppears { title: String, app: Option<String> },
+}
+
+impl TriggerCondition {
+    /// Validate `config`; exactly one of frontmostApp and windowTitle must be set
+    pub fn from_config(config: &RecordingTriggerCondition) -> Result<Self> {
+        match (&config.frontmost_app, &config.window_title) {
+            (Some(app), None) if config.window_app.is_none() => Ok(Self::FrontmostApp(app.clone())),
+            (None, Some(title)) => Ok(Self::WindowAppears { title: title.clone(), app: config.window_app.clone() }),
+            (Some(_), None) => Err(Error::new(Status::InvalidArg, "windowApp only applies to windowTitle conditions")),
+            _ => Err(Error::new(Status::InvalidArg, "A trigger condition needs exactly one of frontmostApp or windowTitle")),
+        }
+    }
+
+    /// Whether the condition holds for `windows`, ordered front to back
+    pub fn is_met(&self, windows: &[WindowInfo]) -> bool {
+        let owned_by = |window: &WindowInfo, app: &str| {
+            (!window.bundle_id.is_empty() && app.eq_ignore_ascii_case(&window.bundle_id))
+                || (!window.owner_name.is_empty() && app.eq_ignore_ascii_case(&window.owner_name))
+        };
+
+        match self {
+            Self::FrontmostApp(app) => windows.first().is_some_and(|window| owned_by(window, app)),
+            Self::WindowAppears { title, app } => windows.iter().any(|window| {
+                window.title.contains(title.as_str()) && app.as_deref().is_none_or(|app| owned_by(window, app))
+            }),
+        }
+    }
+}
+
+/// What the trigger asks the recorder to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerAction {
+    Start,
+    Stop,
+}
+
+/// Turns successive window lists into at most one start and one stop
+pub struct TriggerState {
+    start: TriggerCondition,
+    /// None stops once the start condition no longer holds
+    stop: Option<TriggerCondition>,
+    started: bool,
+    finished: bool,
+}
+
+impl TriggerState {
+    pub fn new(start: TriggerCondition, stop: Option<TriggerCondition>) -> Self {
+        Self { start, stop, started: false, finished: false }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The action `windows` calls for, if any
+    pub fn next(&mut self, windows: &[WindowInfo]) -> Option<TriggerAction> {
+        if self.finished {
+            return None;
+        }
+        if !self.started {
+            self.started = self.start.is_met(windows);
+            return self.started.then_some(TriggerAction::Start);
+        }
+
+        let stop = match &self.stop {
+            Some(stop) => stop.is_met(windows),
+            None => !self.start.is_met(windows),
+        };
+        self.finished = stop;
+        stop.then_some(TriggerAction::Stop)
+    }
+}
+
+/// Polls the screen for an armed trigger until it has fired its stop, or is dropped
+pub struct TriggerWatch {
+    stop: Arc<AtomicBool>,
+}
+
+impl TriggerWatch {
+    /// Check the conditions every `interval_ms` and call `on_action` when the recording should
+    /// start and, later, stop. The trigger fires once; arm a new one for the next capture
+    pub fn start<F>(state: TriggerState, interval_ms: u32, on_action: F) -> Self
+    where
+        F: Fn(TriggerAction) + Send + 'static,
+    {
+        println!("🎯 Recording trigger armed, checking every {}ms", interval_ms);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let interval = Duration::from_millis(interval_ms.max(MIN_TRIGGER_POLL_INTERVAL_MS) as u64);
+
+        std::thread::spawn(move || {
+            let mut state = state;
+            while !thread_stop.load(Ordering::SeqCst) {
+                let windows = unsafe { on_screen_windows() };
+                if let Some(action) = state.next(&windows) {
+                    if thread_stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    println!("🎯 Recording trigger fired: {:?}", action);
+                    on_action(action);
+                }
+                if state.is_finished() {
+                    break;
+                }
+
+                let mut waited = Duration::ZERO;
+                while waited < interval && !thread_stop.load(Ordering::SeqCst) {
+                    std::thread::sleep(STOP_CHECK_INTERVAL);
+                    waited += STOP_CHECK_INTERVAL;
+                }
+            }
+        });
+
+        Self { stop }
+    }
+}
+
+impl Drop for TriggerWatch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        println!("🛑 Recording trigger disarmed");
+    }
+}
+
+/// Normal-layer on-screen windows, front to back, with their owners
+unsafe fn on_screen_windows() -> Vec<WindowInfo> {
+    extern "C" {
+        fn CGWindowListCopyWindowInfo(option: u32, relativeToWindow: u32) -> *mut NSArray;
+    }
+
+    // kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements
+    const ON_SCREEN_WITHOUT_DESKTOP: u32 = (1 << 0) | (1 << 4);
+
+    let window_list_raw = CGWindowListCopyWindowInfo(ON_SCREEN_WITHOUT_DESKTOP, 0);
+    if window_list_raw.is_null() {
+        return Vec::new();
+    }
+
+    let layer_key = NSString::from_str("kCGWindowLayer");
+    let number_key = NSString::from_str("kCGWindowNumber");
+    let name_key = NSString::from_str("kCGWindowName");
+    let owner_name_key = NSString::from_str("kCGWindowOwnerName");
+    let owner_pid_key = NSString::from_str("kCGWindowOwnerPID");
+    let number = |dict: &NSDictionary, key: &NSString| {
+        dict.objectForKey(key).and_then(|obj| obj.downcast::<NSNumber>().ok()).map(|number| number.intValue())
+    };
+    let string = |dict: &NSDictionary, key: &NSString| {
+        dict.objectForKey(key).and_then(|obj| obj.downcast::<NSString>().ok()).map(|string| string.to_string()).unwrap_or_default()
+    };
+
+    let window_list: &NSArray = &*window_list_raw;
+    let mut windows = Vec::new();
+    for i in 0..window_list.count() {
+        let Ok(window_dict) = window_list.objectAtIndex(i).downcast::<NSDictionary>() else {
+            continue;
+        };
+        // Menu bar, Dock and overlays sit above layer 0
+        if number(&window_dict, &layer_key) != Some(0) {
+            continue;
+        }
+
+        windows.push(WindowInfo {
+            id: number(&window_dict, &number_key).unwrap_or(0) as u32,
+            title: string(&window_dict, &name_key),
+            owner_name: string(&window_dict, &owner_name_key),
+            bundle_id: number(&window_dict, &owner_pid_key).map(|pid| bundle_id_for_process(pid)).unwrap_or_default(),
+            ..WindowInfo::default()
+        });
+    }
+
+    let _: () = msg_send![window_list_raw, release];
+    windows
+}
+
+unsafe fn bundle_id_for_process(pid: i32) -> String {
+    let application: *mut AnyObject = msg_send![class!(NSRunningApplication), runningApplicationWithProcessIdentifier: pid];
+    if application.is_null() {
+        return String::new();
+    }
+    let bundle_id: *mut NSString = msg_send![application, bundleIdentifier];
+    if bundle_id.is_null() {
+        String::new()
+    } else {
+        (*bundle_id).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(title: &str, bundle_id: &str) -> WindowInfo {
+        WindowInfo { title: title.to_string(), bundle_id: bundle_id.to_string(), ..WindowInfo::default() }
+    }
+
+    #[test]
+    fn test_trigger_starts_and_stops_once() {
+        let mut state = TriggerState::new(TriggerCondition::FrontmostApp("com.example.Editor".to_string()), None);
+        let editor_front = [window("main.rs", "com.example.editor"), window("Inbox", "com.example.mail")];
+        let mail_front = [window("Inbox", "com.example.mail"), window("main.rs", "com.example.editor")];
+
+        assert_eq!(state.next(&mail_front), None);
+        assert_eq!(state.next(&editor_front), Some(TriggerAction::Start));
+        assert_eq!(state.next(&editor_front), None);
+        assert_eq!(state.next(&mail_front), Some(TriggerAction::Stop), "Stops when the app loses focus");
+        assert!(state.is_finished());
+        assert_eq!(state.next(&editor_front), None, "Fires once");
+
+        let meeting = TriggerCondition::WindowAppears { title: "Meeting".to_string(), app: Some("com.example.call".to_string()) };
+        assert!(meeting.is_met(&[window("Inbox", "com.example.mail"), window("Team Meeting", "com.example.call")]));
+        assert!(!meeting.is_met(&[window("Meeting notes", "com.example.notes")]));
+
+        let both = RecordingTriggerCondition {
+            frontmost_app: Some("Editor".to_string()),
+            window_title: Some("main.rs".to_string()),
+            window_app: None,
+        };
+        assert_eq!(TriggerCondition::from_config(&both).expect_err("Only one condition").status, Status::InvalidArg);
+    }
+}
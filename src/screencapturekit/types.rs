@@ -52,23 +52,83 @@ pub trait SCStreamDelegate {
 }
 
 // Display information structure
+// x/y is the top-left corner in global display coordinates; sizes are in points
 #[derive(Debug, Clone)]
 pub struct DisplayInfo {
     pub id: u32,
     pub name: String,
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
+    /// Sidecar, AirPlay or other software display
+    pub is_virtual: bool,
+    /// Mirrors another display, so it shows the same picture as that display
+    pub is_mirror: bool,
+    /// EDR headroom when the display was listed; 1.0 on SDR displays, None when it couldn't be
+    /// read (see `CoreGraphicsHelpers::edr_headrooms`)
+    pub edr_headroom: Option<f64>,
 }
 
 // Window information structure
-#[derive(Debug, Clone)]
+// x/y is the top-left corner in global display coordinates; sizes are in points
+#[derive(Debug, Clone, Default)]
 pub struct WindowInfo {
     pub id: u32,
     pub title: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Owning application's name and bundle id; empty when ScreenCaptureKit doesn't report one
+    pub owner_name: String,
+    pub bundle_id: String,
+    /// On the normal window layer but off screen: minimized, in a hidden app or on another
+    /// Space. A minimized window records nothing
+    pub is_minimized: bool,
+    /// Covers a whole display, as a fullscreen window does
+    pub is_fullscreen: bool,
+    /// The active window of its app (macOS 13.1+; always false before)
+    pub is_active: bool,
+}
+
+// Rectangle in global display points (top-left origin), the space DisplayInfo frames use
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalRect {
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
 }
 
+// What a recording captures, selected by a ScreenSource id or a global rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureTarget {
+    Display(u32),
+    Window(u32),
+    /// Any rectangle of the desktop, possibly spanning several displays
+    Region(GlobalRect),
+}
+
+impl Default for CaptureTarget {
+    fn default() -> Self {
+        CaptureTarget::Display(1)
+    }
+}
+
+impl CaptureTarget {
+    /// Parse a ScreenSource id ("display:<id>" or "window:<id>")
+    pub fn from_screen_id(value: &str) -> Option<Self> {
+        let (kind, id) = value.split_once(':')?;
+        let id = id.parse().ok()?;
+        match kind {
+            "display" => Some(CaptureTarget::Display(id)),
+            "window" => Some(CaptureTarget::Window(id)),
+            _ => None,
+        }
+    }
+}
+
 // Stream configuration structure
 #[derive(Debug, Clone)]
 pub struct StreamConfiguration {
@@ -78,7 +138,7 @@ pub struct StreamConfiguration {
     pub shows_cursor: bool,
     pub captures_audio: bool,
     pub pixel_format: u32,
-    pub color_space: u32,
+    pub color_space: OutputColorSpace,
 }
 
 impl Default for StreamConfiguration {
@@ -90,7 +150,7 @@ impl Default for StreamConfiguration {
             shows_cursor: true,
             captures_audio: false,
             pixel_format: kCVPixelFormatType_32BGRA,
-            color_space: kCGColorSpaceSRGB,
+            color_space: OutputColorSpace::default(),
         }
     }
 }
@@ -98,10 +158,14 @@ impl Default for StreamConfiguration {
 // Pixel format constants
 pub const kCVPixelFormatType_32BGRA: u32 = 0x42475241; // 'BGRA'
 pub const kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange: u32 = 0x34323076; // '420v'
+pub const kCVPixelFormatType_ARGB2101010LEPacked: u32 = 0x6C313072; // 'l10r'
+
+// Largest accepted output size (8K UHD)
+pub const MAX_OUTPUT_WIDTH: u32 = 7680;
+pub const MAX_OUTPUT_HEIGHT: u32 = 4320;
 
-// Color space constants
-pub const kCGColorSpaceDisplayP3: u32 = 0;
-pub const kCGColorSpaceSRGB: u32 = 1;
+// Longest countdown startDelaySeconds accepts
+pub const MAX_START_DELAY_SECONDS: u32 = 60;
 
 // Recording state enum
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -113,6 +177,526 @@ pub enum RecordingState {
     Error,
 }
 
+// Presenter overlay privacy alert setting (SCPresenterOverlayAlertSetting, macOS 14.2+)
+#[repr(isize)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PresenterOverlayAlertSetting {
+    System = 0,
+    Never = 1,
+    Always = 2,
+}
+
+impl PresenterOverlayAlertSetting {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "system" => Some(Self::System),
+            "never" => Some(Self::Never),
+            "always" => Some(Self::Always),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Never => "never",
+            Self::Always => "always",
+        }
+    }
+}
+
+// Statistics of a finished recording, kept for diagnostics
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingStats {
+    pub output_path: String,
+    pub video_frames: u64,
+    pub audio_samples: u64,
+    pub duration_seconds: f64,
+    pub average_fps: f64,
+    /// Finalization error, if the recording didn't produce a valid file
+    pub error: Option<String>,
+    pub timeline: Timeline,
+    /// Cursor track sidecar, when captureCursorTrack was set and it was written
+    pub cursor_track_path: Option<String>,
+    /// Frame index sidecar, when emitFrameIndex was set and it was written
+    pub frame_index_path: Option<String>,
+    /// Chapters sidecar, when detectChapters was set and it was written
+    pub chapters_path: Option<String>,
+    /// Largest drift of the video and audio timestamps from real time before they were
+    /// nudged back, in ms
+    pub max_video_drift_ms: f64,
+    pub max_audio_drift_ms: f64,
+    /// Video frames whose timestamp repeated the last one's and was moved a frame later, and
+    /// ones earlier than the last that were dropped
+    pub nudged_frames: u64,
+    pub dropped_frames: u64,
+    /// ScreenCaptureKit didn't confirm the stop within stopTimeoutMs; the file was
+    /// finalized without waiting
+    pub stop_timed_out: bool,
+    /// Whether each track was enabled when the recording ended
+    pub audio_enabled: bool,
+    pub video_enabled: bool,
+    /// Whether the recorded audio is louder than silence (see verifyRecording); None when no
+    /// audio was captured or it couldn't be checked
+    pub has_audible_audio: Option<bool>,
+}
+
+// Where starting or finishing a recording failed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailureStage {
+    ContentFilter,
+    StreamCreate,
+    Start,
+    Finalize,
+}
+
+impl FailureStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ContentFilter => "content-filter",
+            Self::StreamCreate => "stream-create",
+            Self::Start => "start",
+            Self::Finalize => "finalize",
+        }
+    }
+}
+
+// The last failed recording start or finish, kept for getLastError
+#[derive(Debug, Clone)]
+pub struct RecordingFailure {
+    pub stage: FailureStage,
+    /// napi status of the error JS received
+    pub status: napi::Status,
+    pub message: String,
+    /// Description of the NSError behind it, with its domain and code
+    pub underlying_error: Option<String>,
+    /// When it failed, in ms since the Unix epoch
+    pub failed_at: f64,
+}
+
+// When a recording started and the spans it spent paused, in ms since the Unix epoch
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timeline {
+    pub started_at: f64,
+    pub paused_intervals: Vec<PausedSpan>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct PausedSpan {
+    pub from: f64,
+    pub to: f64,
+}
+
+// sRGB color with alpha, components 0.0..=1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RgbaColor {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub alpha: f64,
+}
+
+impl RgbaColor {
+    /// Fully transparent, so uncovered areas become alpha in the output
+    pub const TRANSPARENT: Self = Self { red: 0.0, green: 0.0, blue: 0.0, alpha: 0.0 };
+
+    /// Parse "#RRGGBB" or "#RRGGBBAA"
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        let hex = value.strip_prefix('#')?;
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return None;
+        }
+        let component = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok().map(|byte| byte as f64 / 255.0);
+        Some(Self {
+            red: component(0)?,
+            green: component(1)?,
+            blue: component(2)?,
+            alpha: if hex.len() == 8 { component(3)? } else { 1.0 },
+        })
+    }
+}
+
+// Capture pixel formats accepted by SCStreamConfiguration
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CapturePixelFormat {
+    /// 8-bit BGRA
+    #[default]
+    Bgra,
+    /// 8-bit 4:2:0 video range YUV
+    Yuv420v,
+    /// 10-bit packed ARGB (display native depth on XDR/HDR panels)
+    Argb2101010,
+}
+
+impl CapturePixelFormat {
+    /// Parse a pixelFormat in any case, e.g. "bgra" or "BGRA"
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "bgra" => Some(Self::Bgra),
+            "420v" => Some(Self::Yuv420v),
+            "l10r" => Some(Self::Argb2101010),
+            _ => None,
+        }
+    }
+
+    /// CoreVideo pixel format type
+    pub fn fourcc(&self) -> u32 {
+        match self {
+            Self::Bgra => kCVPixelFormatType_32BGRA,
+            Self::Yuv420v => kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange,
+            Self::Argb2101010 => kCVPixelFormatType_ARGB2101010LEPacked,
+        }
+    }
+
+    pub fn is_ten_bit(&self) -> bool {
+        matches!(self, Self::Argb2101010)
+    }
+}
+
+// Video codecs supported by the encoder
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    Hevc,
+    /// HEVC with an alpha channel; needs BGRA frames and a QuickTime movie
+    HevcWithAlpha,
+}
+
+impl VideoCodec {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "h264" => Some(Self::H264),
+            "hevc" => Some(Self::Hevc),
+            "hevc_alpha" => Some(Self::HevcWithAlpha),
+            _ => None,
+        }
+    }
+}
+
+// When ScreenCaptureKit delivers frames
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CaptureMode {
+    /// Frames at up to the configured fps
+    #[default]
+    FixedFps,
+    /// Frames only when the content changes, uncapped; each frame is held until the next
+    OnChange,
+}
+
+impl CaptureMode {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "fixed_fps" => Some(Self::FixedFps),
+            "on_change" => Some(Self::OnChange),
+            _ => None,
+        }
+    }
+}
+
+// Which API captures the display
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CaptureBackend {
+    #[default]
+    ScreenCaptureKit,
+    /// The older CGDisplayStream API: displays only, no audio
+    CgDisplayStream,
+    /// ScreenCaptureKit, switching to CGDisplayStream when it delivers no frames at start
+    Auto,
+}
+
+impl CaptureBackend {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "screencapturekit" => Some(Self::ScreenCaptureKit),
+            "cgdisplaystream" => Some(Self::CgDisplayStream),
+            "auto" => Some(Self::Auto),
+            _ => None,
+        }
+    }
+}
+
+// Corner of the frame the webcam picture-in-picture or the watermark sits in
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WebcamCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl WebcamCorner {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "topLeft" => Some(Self::TopLeft),
+            "topRight" => Some(Self::TopRight),
+            "bottomLeft" => Some(Self::BottomLeft),
+            "bottomRight" => Some(Self::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+// Unit the configured output width/height are given in
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DimensionUnit {
+    /// Output pixels, whatever the display's scale
+    #[default]
+    Pixels,
+    /// Display points, multiplied by the captured display's scale factor (2 on Retina)
+    Points,
+}
+
+impl DimensionUnit {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "pixels" => Some(Self::Pixels),
+            "points" => Some(Self::Points),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pixels => "pixels",
+            Self::Points => "points",
+        }
+    }
+}
+
+// How capture content is fitted into the output size when the aspect ratios differ
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScalingMode {
+    /// Preserve aspect ratio, letterbox/pillarbox the unused area in black
+    #[default]
+    Fit,
+    /// Preserve aspect ratio, crop the centre of the source to fill the whole output
+    Fill,
+    /// Scale each axis independently to the output size, distorting the image
+    Stretch,
+}
+
+impl ScalingMode {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "fit" => Some(Self::Fit),
+            "fill" => Some(Self::Fill),
+            "stretch" => Some(Self::Stretch),
+            _ => None,
+        }
+    }
+
+    /// AVVideoScalingModeKey value used by the writer
+    pub fn av_scaling_mode(&self) -> &'static str {
+        match self {
+            Self::Fit => "AVVideoScalingModeResizeAspect",
+            Self::Fill => "AVVideoScalingModeResizeAspectFill",
+            Self::Stretch => "AVVideoScalingModeResize",
+        }
+    }
+}
+
+// Where frames are scaled to the output size
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScalingQuality {
+    /// ScreenCaptureKit scales on the GPU while capturing (cheapest)
+    #[default]
+    Fast,
+    /// Capture at the source's native pixel size and let the writer scale (macOS 14+)
+    High,
+}
+
+impl ScalingQuality {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "fast" => Some(Self::Fast),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+// Which VideoToolbox decoder reads the source of a transcode
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DecoderPreference {
+    /// Hardware when VideoToolbox offers it, software otherwise
+    #[default]
+    Auto,
+    /// Only the hardware decoder
+    Hardware,
+    /// Only the software decoder, for Macs whose hardware decoder fails or produces green frames
+    Software,
+}
+
+impl DecoderPreference {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::Auto),
+            "hardware" => Some(Self::Hardware),
+            "software" => Some(Self::Software),
+            _ => None,
+        }
+    }
+}
+
+// Colour space frames are captured in and the output is tagged with
+// Every option uses the BT.709 YCbCr matrix; they differ in primaries and transfer function
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputColorSpace {
+    /// BT.709 primaries and transfer (the HD default players assume for untagged files)
+    #[default]
+    Bt709,
+    /// BT.709 primaries with the sRGB transfer curve, matching the captured BGRA values
+    Srgb,
+    /// P3 D65 primaries with the sRGB transfer curve, for wide-gamut displays
+    DisplayP3,
+    /// BT.709 primaries with linear light values
+    Linear,
+}
+
+impl OutputColorSpace {
+    /// Parse a colorSpace in any case, e.g. "srgb" or "sRGB"
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "bt709" => Some(Self::Bt709),
+            "srgb" => Some(Self::Srgb),
+            "p3" => Some(Self::DisplayP3),
+            "linear" => Some(Self::Linear),
+            _ => None,
+        }
+    }
+
+    /// CGColorSpace name ScreenCaptureKit converts captured frames into
+    pub fn capture_color_space_name(self) -> &'static str {
+        match self {
+            Self::Bt709 => "kCGColorSpaceITUR_709",
+            Self::Srgb => "kCGColorSpaceSRGB",
+            Self::DisplayP3 => "kCGColorSpaceDisplayP3",
+            Self::Linear => "kCGColorSpaceLinearSRGB",
+        }
+    }
+
+    /// AVVideoColorPrimariesKey value
+    pub fn color_primaries(self) -> &'static str {
+        match self {
+            Self::DisplayP3 => "P3_D65",
+            _ => "ITU_R_709_2",
+        }
+    }
+
+    /// AVVideoTransferFunctionKey value
+    pub fn transfer_function(self) -> &'static str {
+        match self {
+            Self::Bt709 => "ITU_R_709_2",
+            Self::Srgb | Self::DisplayP3 => "IEC_sRGB",
+            Self::Linear => "Linear",
+        }
+    }
+
+    /// AVVideoYCbCrMatrixKey value
+    pub fn ycbcr_matrix(self) -> &'static str {
+        "ITU_R_709_2"
+    }
+}
+
+// Output aspect ratio, used to derive whichever of width/height isn't configured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AspectRatio {
+    /// Parse "<width>:<height>", e.g. "16:9"
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        let (width, height) = value.split_once(':')?;
+        let width: u32 = width.trim().parse().ok()?;
+        let height: u32 = height.trim().parse().ok()?;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(Self { width, height })
+    }
+
+    /// Output width for `height`, rounded to an even number for H.264/HEVC
+    pub fn width_for_height(self, height: u32) -> u32 {
+        round_to_even(height as f64 * self.width as f64 / self.height as f64)
+    }
+
+    /// Output height for `width`, rounded to an even number for H.264/HEVC
+    pub fn height_for_width(self, width: u32) -> u32 {
+        round_to_even(width as f64 * self.height as f64 / self.width as f64)
+    }
+}
+
+fn round_to_even(value: f64) -> u32 {
+    ((value / 2.0).round() as u32) * 2
+}
+
+// What happens to the partial recording when permission is revoked mid-recording
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PermissionRevokedBehavior {
+    /// Finalize what was captured so far into a playable file
+    #[default]
+    Finalize,
+    /// Finalize, then delete the partial file
+    Discard,
+}
+
+impl PermissionRevokedBehavior {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "finalize" => Some(Self::Finalize),
+            "discard" => Some(Self::Discard),
+            _ => None,
+        }
+    }
+}
+
+// What happens to frames whose size differs from the first frame's, e.g. after a display
+// resolution change
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ResolutionChangeBehavior {
+    /// Scale them to the recording's size so the file continues at it
+    #[default]
+    Scale,
+    /// Finalize the file and continue in a new one at the new size
+    Segment,
+}
+
+impl ResolutionChangeBehavior {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "scale" => Some(Self::Scale),
+            "segment" => Some(Self::Segment),
+            _ => None,
+        }
+    }
+}
+
+// What to do when a recording stops before any video frame reached the writer
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EmptyRecordingBehavior {
+    /// Discard the output and fail with a "no frames captured" error
+    #[default]
+    Error,
+    /// Write a single black frame so the output is a valid (if tiny) movie
+    BlackFrame,
+}
+
+impl EmptyRecordingBehavior {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value {
+            "error" => Some(Self::Error),
+            "blackFrame" => Some(Self::BlackFrame),
+            _ => None,
+        }
+    }
+}
+
 // Content filter type enum
 #[derive(Debug, Clone, Copy)]
 pub enum ContentFilterType {
@@ -149,6 +733,10 @@ pub enum SCError {
     FilterCreationFailed,
     RecordingFailed,
     InvalidConfiguration,
+    /// Screen recording permission was taken away while the stream was running
+    PermissionRevoked,
+    /// The login session can't be captured (locked screen, login window); holds the reason
+    CaptureBlocked(String),
     SystemError(String),
 }
 
@@ -161,6 +749,8 @@ impl std::fmt::Display for SCError {
             SCError::FilterCreationFailed => write!(f, "Failed to create content filter"),
             SCError::RecordingFailed => write!(f, "Recording failed"),
             SCError::InvalidConfiguration => write!(f, "Invalid configuration"),
+            SCError::PermissionRevoked => write!(f, "Screen recording permission was revoked during the recording"),
+            SCError::CaptureBlocked(reason) => write!(f, "Capture is blocked by the system: {}", reason),
             SCError::SystemError(msg) => write!(f, "System error: {}", msg),
         }
     }
@@ -177,6 +767,8 @@ impl From<SCError> for napi::Error {
             SCError::FilterCreationFailed => napi::Error::new(napi::Status::GenericFailure, "Failed to create content filter"),
             SCError::RecordingFailed => napi::Error::new(napi::Status::GenericFailure, "Recording failed"),
             SCError::InvalidConfiguration => napi::Error::new(napi::Status::InvalidArg, "Invalid configuration"),
+            SCError::PermissionRevoked => napi::Error::new(napi::Status::GenericFailure, "Screen recording permission was revoked during the recording"),
+            SCError::CaptureBlocked(_) => napi::Error::new(napi::Status::GenericFailure, err.to_string()),
             SCError::SystemError(msg) => napi::Error::new(napi::Status::GenericFailure, msg),
         }
     }
@@ -193,10 +785,10 @@ pub fn create_cmtime_from_fps(fps: u32) -> CMTime {
 }
 
 pub fn validate_dimensions(width: u32, height: u32) -> Result<(), SCError> {
-    if width < 100 || width > 7680 {
+    if width < 100 || width > MAX_OUTPUT_WIDTH {
         return Err(SCError::InvalidConfiguration);
     }
-    if height < 100 || height > 4320 {
+    if height < 100 || height > MAX_OUTPUT_HEIGHT {
         return Err(SCError::InvalidConfiguration);
     }
     Ok(())
@@ -207,4 +799,18 @@ pub fn validate_fps(fps: u32) -> Result<(), SCError> {
         return Err(SCError::InvalidConfiguration);
     }
     Ok(())
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_values_ignore_case() {
+        assert_eq!(CapturePixelFormat::from_config_str("BGRA"), Some(CapturePixelFormat::Bgra));
+        assert_eq!(CapturePixelFormat::from_config_str("L10R"), Some(CapturePixelFormat::Argb2101010));
+        assert_eq!(CapturePixelFormat::from_config_str("rgba"), None);
+        assert_eq!(OutputColorSpace::from_config_str("sRGB"), Some(OutputColorSpace::Srgb));
+        assert_eq!(OutputColorSpace::from_config_str("P3"), Some(OutputColorSpace::DisplayP3));
+    }
+}
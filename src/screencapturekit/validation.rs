@@ -0,0 +1,425 @@
+// Recording configuration checks
+// Both recorders, RecordingManager and IntegratedRecordingManager, take the same
+// RecordingConfiguration, so they share these checks: an option is refused the same way, with
+// the same InvalidArg error, whichever recorder it's given to. Checks that depend on what's
+// being captured (a window, a region) or on what a recorder supports stay with that recorder
+
+use std::path::Path;
+use napi::{Result, Status, Error};
+
+use crate::RecordingConfiguration;
+use super::types::*;
+use super::disk_space::{DEFAULT_LOW_DISK_SPACE_WARNING_MB, DEFAULT_MIN_FREE_DISK_SPACE_MB};
+use super::watermark::{MIN_WATERMARK_SIZE, MAX_WATERMARK_SIZE};
+use super::webcam::{MIN_WEBCAM_SIZE, MAX_WEBCAM_SIZE};
+use super::fragment_output;
+use super::encoder::{file_type_for, AVFileTypeMPEG4, target_size_bitrate_kbps, MAX_PIXEL_BUFFER_POOL_SIZE, MIN_TARGET_SIZE_BITRATE_KBPS, AVVideoProfileLevelHEVCMain10AutoLevel, profile_level_codec};
+
+/// Check `config` before anything is set up; every problem is an InvalidArg error naming the option
+pub fn validate_recording_configuration(config: &RecordingConfiguration) -> Result<()> {
+    if config.output_path.is_empty() {
+        return Err(Error::new(Status::InvalidArg, "Output path cannot be empty"));
+    }
+
+    if let Some(width) = config.width {
+        if width < 100 || width > 7680 {
+            return Err(Error::new(Status::InvalidArg, "Width must be between 100 and 7680"));
+        }
+    }
+
+    if let Some(height) = config.height {
+        if height < 100 || height > 4320 {
+            return Err(Error::new(Status::InvalidArg, "Height must be between 100 and 4320"));
+        }
+    }
+
+    if config.strict_dimensions.unwrap_or(false) {
+        for (name, dimension) in [("width", config.width), ("height", config.height)] {
+            if let Some(value) = dimension.filter(|value| value % 2 != 0) {
+                return Err(Error::new(Status::InvalidArg, format!("{} must be even, got {}", name, value)));
+            }
+        }
+    }
+
+    if let Some(fps) = config.fps {
+        if fps < 1 || fps > 120 {
+            return Err(Error::new(Status::InvalidArg, "FPS must be between 1 and 120"));
+        }
+    }
+
+    if let Some(ref setting) = config.presenter_overlay_privacy_alert {
+        if PresenterOverlayAlertSetting::from_config_str(setting).is_none() {
+            return Err(Error::new(Status::InvalidArg, "presenterOverlayPrivacyAlert must be \"system\", \"never\" or \"always\""));
+        }
+    }
+
+    if let Some(ref behavior) = config.empty_recording_behavior {
+        if EmptyRecordingBehavior::from_config_str(behavior).is_none() {
+            return Err(Error::new(Status::InvalidArg, "emptyRecordingBehavior must be \"error\" or \"blackFrame\""));
+        }
+    }
+
+    if let Some(seconds) = config.replay_buffer_seconds {
+        if seconds < 1 || seconds > 600 {
+            return Err(Error::new(Status::InvalidArg, "replayBufferSeconds must be between 1 and 600"));
+        }
+    }
+
+    if let Some(retries) = config.writer_start_retries {
+        if retries > 10 {
+            return Err(Error::new(Status::InvalidArg, "writerStartRetries must be between 0 and 10"));
+        }
+    }
+
+    if config.early_audio_buffer_ms.is_some_and(|limit_ms| limit_ms > 10_000) {
+        return Err(Error::new(Status::InvalidArg, "earlyAudioBufferMs can be at most 10000"));
+    }
+
+    if let Some(ref mode) = config.scaling_mode {
+        if ScalingMode::from_config_str(mode).is_none() {
+            return Err(Error::new(Status::InvalidArg, "scalingMode must be \"fit\", \"fill\" or \"stretch\""));
+        }
+    }
+
+    if config.dimension_unit.as_deref().is_some_and(|unit| DimensionUnit::from_config_str(unit).is_none()) {
+        return Err(Error::new(Status::InvalidArg, "dimensionUnit must be \"pixels\" or \"points\""));
+    }
+
+    if let Some(ref mode) = config.capture_mode {
+        if CaptureMode::from_config_str(mode).is_none() {
+            return Err(Error::new(Status::InvalidArg, "captureMode must be \"fixed_fps\" or \"on_change\""));
+        }
+    }
+
+    match config.capture_backend.as_deref().map(CaptureBackend::from_config_str) {
+        Some(None) => {
+            return Err(Error::new(Status::InvalidArg, "captureBackend must be \"screencapturekit\", \"cgdisplaystream\" or \"auto\""));
+        }
+        Some(Some(CaptureBackend::CgDisplayStream)) if config.capture_audio.unwrap_or(false) => {
+            return Err(Error::new(Status::InvalidArg, "captureBackend \"cgdisplaystream\" records video only; turn off captureAudio"));
+        }
+        _ => {}
+    }
+
+    if let Some(output_fps) = config.output_fps {
+        if output_fps < 1 || output_fps > 120 {
+            return Err(Error::new(Status::InvalidArg, "outputFps must be between 1 and 120"));
+        }
+        if config.capture_mode.as_deref().and_then(CaptureMode::from_config_str) == Some(CaptureMode::OnChange) {
+            return Err(Error::new(Status::InvalidArg, "outputFps needs a fixed frame rate; on_change capture keeps each frame's own time"));
+        }
+    }
+
+    if let Some(ref ratio) = config.aspect_ratio {
+        if AspectRatio::from_config_str(ratio).is_none() {
+            return Err(Error::new(Status::InvalidArg, "aspectRatio must look like \"16:9\""));
+        }
+        if config.width.is_some() && config.height.is_some() {
+            return Err(Error::new(Status::InvalidArg, "aspectRatio can't be combined with both width and height"));
+        }
+    }
+
+    let warning_mb = config.low_disk_space_warning_mb.unwrap_or(DEFAULT_LOW_DISK_SPACE_WARNING_MB);
+    let min_free_mb = config.min_free_disk_space_mb.unwrap_or(DEFAULT_MIN_FREE_DISK_SPACE_MB);
+    if warning_mb < min_free_mb {
+        return Err(Error::new(Status::InvalidArg, "lowDiskSpaceWarningMb must not be below minFreeDiskSpaceMb"));
+    }
+
+    if config.expected_duration_seconds == Some(0) {
+        return Err(Error::new(Status::InvalidArg, "expectedDurationSeconds must be at least 1"));
+    }
+
+    if config.max_duration_seconds == Some(0) {
+        return Err(Error::new(Status::InvalidArg, "maxDurationSeconds must be at least 1"));
+    }
+
+    if config.progress_interval_ms == Some(0) {
+        return Err(Error::new(Status::InvalidArg, "progressIntervalMs must be at least 1"));
+    }
+
+    if config.exclude_audio_app_bundle_ids.as_ref().is_some_and(|ids| ids.iter().any(|id| id.trim().is_empty())) {
+        return Err(Error::new(Status::InvalidArg, "excludeAudioAppBundleIds must not contain empty bundle ids"));
+    }
+
+    if let Some(ref behavior) = config.permission_revoked_behavior {
+        if PermissionRevokedBehavior::from_config_str(behavior).is_none() {
+            return Err(Error::new(Status::InvalidArg, "permissionRevokedBehavior must be \"finalize\" or \"discard\""));
+        }
+    }
+
+    if let Some(ref behavior) = config.resolution_change_behavior {
+        match ResolutionChangeBehavior::from_config_str(behavior) {
+            None => return Err(Error::new(Status::InvalidArg, "resolutionChangeBehavior must be \"scale\" or \"segment\"")),
+            Some(ResolutionChangeBehavior::Segment) if config.replay_buffer_seconds.is_some() => {
+                return Err(Error::new(Status::InvalidArg, "resolutionChangeBehavior \"segment\" can't be used with replayBufferSeconds"));
+            }
+            Some(_) => {}
+        }
+    }
+
+    if config.video_bitrate_kbps == Some(0) {
+        return Err(Error::new(Status::InvalidArg, "videoBitrateKbps must be greater than 0"));
+    }
+
+    if let Some(quality) = config.quality {
+        if config.video_bitrate_kbps.is_some() {
+            return Err(Error::new(Status::InvalidArg, "quality and videoBitrateKbps can't be combined"));
+        }
+        if !(0.0..=1.0).contains(&quality) {
+            return Err(Error::new(Status::InvalidArg, "quality must be between 0.0 and 1.0"));
+        }
+    }
+
+    if let Some(target_bytes) = config.target_file_size_bytes {
+        if config.max_duration_seconds.is_none() {
+            return Err(Error::new(Status::InvalidArg, "targetFileSizeBytes requires maxDurationSeconds to budget the size over"));
+        }
+        if config.video_bitrate_kbps.is_some() || config.quality.is_some() {
+            return Err(Error::new(Status::InvalidArg, "targetFileSizeBytes can't be combined with videoBitrateKbps or quality"));
+        }
+        if !target_bytes.is_finite() || target_bytes <= 0.0 {
+            return Err(Error::new(Status::InvalidArg, "targetFileSizeBytes must be greater than 0"));
+        }
+        let bitrate_kbps = target_size_bitrate_kbps(config).unwrap_or(0);
+        if bitrate_kbps < MIN_TARGET_SIZE_BITRATE_KBPS {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!(
+                    "targetFileSizeBytes leaves {} kbps for the video over maxDurationSeconds; at least {} kbps is needed",
+                    bitrate_kbps, MIN_TARGET_SIZE_BITRATE_KBPS,
+                ),
+            ));
+        }
+    }
+
+    if let Some(ref pool) = config.pixel_buffer_pool {
+        if pool.min_buffer_count.is_some_and(|count| count < 1 || count > MAX_PIXEL_BUFFER_POOL_SIZE) {
+            return Err(Error::new(Status::InvalidArg, format!("pixelBufferPool.minBufferCount must be between 1 and {}", MAX_PIXEL_BUFFER_POOL_SIZE)));
+        }
+        if pool.width.is_some_and(|width| width < 100 || width > MAX_OUTPUT_WIDTH)
+            || pool.height.is_some_and(|height| height < 100 || height > MAX_OUTPUT_HEIGHT)
+        {
+            return Err(Error::new(Status::InvalidArg, format!("pixelBufferPool size must be within 100x100..{}x{}", MAX_OUTPUT_WIDTH, MAX_OUTPUT_HEIGHT)));
+        }
+        if pool.pixel_format.as_deref().is_some_and(|format| CapturePixelFormat::from_config_str(format).is_none()) {
+            return Err(Error::new(Status::InvalidArg, "pixelBufferPool.pixelFormat must be \"bgra\", \"420v\" or \"l10r\""));
+        }
+    }
+
+    if let Some(ref color) = config.background_color {
+        if RgbaColor::from_config_str(color).is_none() {
+            return Err(Error::new(Status::InvalidArg, "backgroundColor must be \"#RRGGBB\" or \"#RRGGBBAA\""));
+        }
+    }
+
+    if let Some(ref webcam) = config.webcam {
+        if webcam.corner.as_deref().is_some_and(|corner| WebcamCorner::from_config_str(corner).is_none()) {
+            return Err(Error::new(Status::InvalidArg, "webcam.corner must be \"topLeft\", \"topRight\", \"bottomLeft\" or \"bottomRight\""));
+        }
+        if webcam.size.is_some_and(|size| !(MIN_WEBCAM_SIZE..=MAX_WEBCAM_SIZE).contains(&size)) {
+            return Err(Error::new(Status::InvalidArg, format!("webcam.size must be between {} and {}", MIN_WEBCAM_SIZE, MAX_WEBCAM_SIZE)));
+        }
+        if CapturePixelFormat::from_config_str(config.pixel_format.as_deref().unwrap_or("bgra")) != Some(CapturePixelFormat::Bgra) {
+            return Err(Error::new(Status::InvalidArg, "webcam requires pixelFormat \"bgra\""));
+        }
+    }
+
+    if let Some(ref watermark) = config.watermark {
+        if watermark.path.is_some() == watermark.image.is_some() {
+            return Err(Error::new(Status::InvalidArg, "watermark needs either a path or image data"));
+        }
+        if watermark.path.as_deref().is_some_and(|path| !std::path::Path::new(path).is_file()) {
+            return Err(Error::new(Status::InvalidArg, format!("Watermark image not found: {}", watermark.path.as_deref().unwrap_or_default())));
+        }
+        if watermark.corner.as_deref().is_some_and(|corner| WebcamCorner::from_config_str(corner).is_none()) {
+            return Err(Error::new(Status::InvalidArg, "watermark.corner must be \"topLeft\", \"topRight\", \"bottomLeft\" or \"bottomRight\""));
+        }
+        if watermark.opacity.is_some_and(|opacity| !(0.0..=1.0).contains(&opacity)) {
+            return Err(Error::new(Status::InvalidArg, "watermark.opacity must be between 0.0 and 1.0"));
+        }
+        if watermark.size.is_some_and(|size| !(MIN_WATERMARK_SIZE..=MAX_WATERMARK_SIZE).contains(&size)) {
+            return Err(Error::new(Status::InvalidArg, format!("watermark.size must be between {} and {}", MIN_WATERMARK_SIZE, MAX_WATERMARK_SIZE)));
+        }
+        if CapturePixelFormat::from_config_str(config.pixel_format.as_deref().unwrap_or("bgra")) != Some(CapturePixelFormat::Bgra) {
+            return Err(Error::new(Status::InvalidArg, "watermark requires pixelFormat \"bgra\""));
+        }
+    }
+
+    // The replay buffer holds encoded video only; audio would be captured and dropped
+    if config.capture_audio.unwrap_or(false) && config.replay_buffer_seconds.is_some() {
+        return Err(Error::new(Status::InvalidArg, "captureAudio can't be combined with replayBufferSeconds"));
+    }
+    // Instant replay never writes outputPath, so there's nothing for the sidecars to sit next to
+    if config.capture_cursor_track.unwrap_or(false) && config.replay_buffer_seconds.is_some() {
+        return Err(Error::new(Status::InvalidArg, "captureCursorTrack can't be combined with replayBufferSeconds"));
+    }
+    if config.emit_frame_index.unwrap_or(false) && config.replay_buffer_seconds.is_some() {
+        return Err(Error::new(Status::InvalidArg, "emitFrameIndex can't be combined with replayBufferSeconds"));
+    }
+    if config.detect_chapters.unwrap_or(false) && config.replay_buffer_seconds.is_some() {
+        return Err(Error::new(Status::InvalidArg, "detectChapters can't be combined with replayBufferSeconds"));
+    }
+    if config.chapter_sensitivity.is_some_and(|sensitivity| !(0.0..=1.0).contains(&sensitivity)) {
+        return Err(Error::new(Status::InvalidArg, "chapterSensitivity must be between 0 and 1"));
+    }
+    // The replay buffer keeps only recent frames, so holding them back gains nothing
+    if config.block_on_backpressure.unwrap_or(false) && config.replay_buffer_seconds.is_some() {
+        return Err(Error::new(Status::InvalidArg, "blockOnBackpressure can't be combined with replayBufferSeconds"));
+    }
+    // Streaming only works for fragmented MP4, and nothing that needs the finished file
+    if config.output_fd.is_some() || config.output_pipe.is_some() {
+        if config.output_fd.is_some() && config.output_pipe.is_some() {
+            return Err(Error::new(Status::InvalidArg, "outputFd and outputPipe can't be combined"));
+        }
+        if config.output_fd.is_some_and(|fd| fd < 0) {
+            return Err(Error::new(Status::InvalidArg, "outputFd must be an open file descriptor"));
+        }
+        if let Some(ref pipe) = config.output_pipe {
+            if !fragment_output::is_fifo(pipe) {
+                return Err(Error::new(Status::InvalidArg, format!("outputPipe {} is not a named pipe", pipe)));
+            }
+        }
+        if file_type_for(&config.output_path) != Some(AVFileTypeMPEG4) {
+            return Err(Error::new(
+                Status::InvalidArg,
+                "Only fragmented MP4 can be streamed; outputPath must end in .mp4 or .m4v with outputFd or outputPipe",
+            ));
+        }
+        let segmented = config.resolution_change_behavior.as_deref().and_then(ResolutionChangeBehavior::from_config_str)
+            == Some(ResolutionChangeBehavior::Segment);
+        for (option, set) in [
+            ("captureAudio", config.capture_audio.unwrap_or(false)),
+            ("replayBufferSeconds", config.replay_buffer_seconds.is_some()),
+            ("emitFrameIndex", config.emit_frame_index.unwrap_or(false)),
+            ("resolutionChangeBehavior \"segment\"", segmented),
+        ] {
+            if set {
+                return Err(Error::new(Status::InvalidArg, format!("{} can't be combined with outputFd or outputPipe", option)));
+            }
+        }
+    }
+    if config.idle_stop_seconds == Some(0) {
+        return Err(Error::new(Status::InvalidArg, "idleStopSeconds must be at least 1"));
+    }
+    if config.start_delay_seconds.is_some_and(|seconds| seconds > MAX_START_DELAY_SECONDS) {
+        return Err(Error::new(Status::InvalidArg, format!("startDelaySeconds must be at most {}", MAX_START_DELAY_SECONDS)));
+    }
+    if config.stop_timeout_ms == Some(0) {
+        return Err(Error::new(Status::InvalidArg, "stopTimeoutMs must be at least 1"));
+    }
+    if let Some(ref device_uid) = config.audio_output_device_id {
+        if device_uid.is_empty() {
+            return Err(Error::new(Status::InvalidArg, "audioOutputDeviceId cannot be empty"));
+        }
+        if !config.capture_audio.unwrap_or(false) {
+            return Err(Error::new(Status::InvalidArg, "audioOutputDeviceId requires captureAudio"));
+        }
+        if config.replay_buffer_seconds.is_some() {
+            return Err(Error::new(Status::InvalidArg, "audioOutputDeviceId can't be used with replayBufferSeconds"));
+        }
+    }
+    // The cursor track only follows pauseRecording, so it would drift from the video
+    if config.pause_on_secure_input.unwrap_or(false) && config.capture_cursor_track.unwrap_or(false) {
+        return Err(Error::new(Status::InvalidArg, "pauseOnSecureInput can't be combined with captureCursorTrack"));
+    }
+
+    if config.separate_audio_tracks.unwrap_or(false)
+        && (!config.capture_audio.unwrap_or(false) || config.audio_device_id.is_none())
+    {
+        return Err(Error::new(Status::InvalidArg, "separateAudioTracks requires captureAudio and audioDeviceId"));
+    }
+
+    // Out-of-range gains are clamped; only reject values that can't be applied at all
+    for (name, gain) in [("systemAudioGain", config.system_audio_gain), ("microphoneGain", config.microphone_gain)] {
+        if gain.is_some_and(|gain| !gain.is_finite()) {
+            return Err(Error::new(Status::InvalidArg, format!("{} must be a finite number of dB", name)));
+        }
+    }
+
+    if config.keystroke_overlay.unwrap_or(false)
+        && CapturePixelFormat::from_config_str(config.pixel_format.as_deref().unwrap_or("bgra")) != Some(CapturePixelFormat::Bgra)
+    {
+        return Err(Error::new(Status::InvalidArg, "keystrokeOverlay requires pixelFormat \"bgra\""));
+    }
+
+    match config.scaling_quality.as_deref().map(ScalingQuality::from_config_str) {
+        Some(None) => {
+            return Err(Error::new(Status::InvalidArg, "scalingQuality must be \"fast\" or \"high\""));
+        }
+        Some(Some(ScalingQuality::High)) if config.replay_buffer_seconds.is_some() => {
+            return Err(Error::new(Status::InvalidArg, "Instant replay does not support scalingQuality \"high\""));
+        }
+        _ => {}
+    }
+
+    let pixel_format = match config.pixel_format.as_deref() {
+        Some(value) => CapturePixelFormat::from_config_str(value).ok_or_else(|| {
+            Error::new(Status::InvalidArg, "pixelFormat must be \"bgra\", \"420v\" or \"l10r\"")
+        })?,
+        None => CapturePixelFormat::default(),
+    };
+
+    let codec = match config.video_codec.as_deref() {
+        Some(value) => VideoCodec::from_config_str(value).ok_or_else(|| {
+            Error::new(Status::InvalidArg, "videoCodec must be \"h264\", \"hevc\" or \"hevc_alpha\"")
+        })?,
+        None => VideoCodec::default(),
+    };
+
+    if config.color_space.as_deref().is_some_and(|value| OutputColorSpace::from_config_str(value).is_none()) {
+        return Err(Error::new(Status::InvalidArg, "colorSpace must be \"bt709\", \"srgb\", \"p3\" or \"linear\""));
+    }
+
+    if let Some(ref profile_level) = config.profile_level {
+        match profile_level_codec(profile_level) {
+            None => return Err(Error::new(
+                Status::InvalidArg,
+                format!("Unsupported profileLevel \"{}\"; see the RecordingConfiguration docs for values", profile_level),
+            )),
+            Some(profile_codec) if profile_codec != codec => return Err(Error::new(
+                Status::InvalidArg,
+                format!("profileLevel \"{}\" doesn't match videoCodec {:?}", profile_level, codec),
+            )),
+            Some(_) if pixel_format.is_ten_bit() && profile_level != AVVideoProfileLevelHEVCMain10AutoLevel => return Err(Error::new(
+                Status::InvalidArg,
+                format!("10-bit pixel format \"l10r\" requires profileLevel \"{}\"", AVVideoProfileLevelHEVCMain10AutoLevel),
+            )),
+            Some(_) => {}
+        }
+    }
+
+    if codec == VideoCodec::HevcWithAlpha {
+        if pixel_format != CapturePixelFormat::Bgra {
+            return Err(Error::new(Status::InvalidArg, "videoCodec \"hevc_alpha\" requires pixelFormat \"bgra\", the only format with alpha"));
+        }
+        let extension = Path::new(&config.output_path).extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        if extension.as_deref() != Some("mov") {
+            return Err(Error::new(Status::InvalidArg, "videoCodec \"hevc_alpha\" requires a .mov output file; MPEG-4 can't carry alpha"));
+        }
+        if config.replay_buffer_seconds.is_some() {
+            return Err(Error::new(Status::InvalidArg, "Instant replay does not support videoCodec \"hevc_alpha\""));
+        }
+    }
+
+    if pixel_format.is_ten_bit() {
+        if codec != VideoCodec::Hevc {
+            return Err(Error::new(Status::InvalidArg, "10-bit pixel format \"l10r\" requires videoCodec \"hevc\""));
+        }
+
+        let extension = Path::new(&config.output_path).extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        if !matches!(extension.as_deref(), Some("mp4") | Some("mov") | Some("m4v")) {
+            return Err(Error::new(Status::InvalidArg, "10-bit HEVC output requires an .mp4, .mov or .m4v file"));
+        }
+
+        if config.replay_buffer_seconds.is_some() {
+            return Err(Error::new(Status::InvalidArg, "Instant replay does not support 10-bit capture"));
+        }
+    }
+
+    Ok(())
+}
+
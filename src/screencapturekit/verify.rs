@@ -0,0 +1,181 @@
+// Checks of a finished recording
+// "There's no audio in my recording" usually means the audio track is there but silent: the
+// system played nothing, or the wrong device was captured. verify_recording opens the file,
+// and the separate audio file the recorder writes next to it, and decodes the audio until a
+// block is louder than -60 dBFS, so a silent track is reported as such instead of being
+// discovered on playback. Only the first minutes are scanned to keep stopping quick. The
+// colour primaries the video is tagged with are reported too, so a wide-gamut recording can
+// be checked to be tagged P3 rather than BT.709
+
+use std::path::Path;
+use std::ptr;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSArray, NSError, NSNumber, NSString};
+use objc2_core_media::{CMSampleBuffer, CMTime};
+use napi::{Result, Status, Error};
+
+use crate::RecordingVerification;
+use super::audio::{compute_audio_levels, with_float_samples};
+use super::core_audio::AUDIO_FORMAT_LINEAR_PCM;
+use super::delegate::audio_path_for;
+use super::encoder::{AVMediaTypeAudio, AVMediaTypeVideo};
+use super::foundation::describe_nserror;
+use super::segments::open_asset;
+use super::transcode::{dictionary, first_track, number_u32};
+
+/// RMS above which a block of audio counts as audible, -60 dBFS
+pub const AUDIBLE_RMS: f64 = 0.001;
+
+/// Audio after this many seconds isn't decoded; a track silent until then counts as silent
+const MAX_SCAN_SECONDS: f64 = 300.0;
+
+extern "C" {
+    static kCMFormatDescriptionExtension_ColorPrimaries: *const NSString;
+
+    fn CMTimeGetSeconds(time: CMTime) -> f64;
+    fn CMFormatDescriptionGetExtension(description: *const AnyObject, extension_key: *const NSString) -> *const AnyObject;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: &CMSampleBuffer) -> CMTime;
+    fn CFRelease(cf: *const std::ffi::c_void);
+}
+
+/// Whether a block of samples is louder than `AUDIBLE_RMS`
+pub fn is_audible(samples: &[f32]) -> bool {
+    compute_audio_levels(samples).rms > AUDIBLE_RMS
+}
+
+/// Describe the recording at `path`; fails when it can't be opened as a movie
+pub fn verify_recording(path: &str) -> Result<RecordingVerification> {
+    if !Path::new(path).is_file() {
+        return Err(Error::new(Status::InvalidArg, format!("Recording not found: {}", path)));
+    }
+
+    unsafe {
+        let asset = open_asset(path);
+        if asset.is_null() {
+            return Err(Error::new(Status::InvalidArg, format!("Failed to open {}", path)));
+        }
+        let duration: CMTime = msg_send![asset, duration];
+        let video_track = first_track(asset, AVMediaTypeVideo);
+        let has_video = !video_track.is_null();
+
+        // The recorder writes audio to its own file unless it shares the video's
+        let mut audio_asset = asset;
+        let companion = audio_path_for(path);
+        if first_track(asset, AVMediaTypeAudio).is_null() && companion != path && Path::new(&companion).is_file() {
+            audio_asset = open_asset(&companion);
+        }
+        let audio_track = if audio_asset.is_null() { ptr::null_mut() } else { first_track(audio_asset, AVMediaTypeAudio) };
+        let has_audio = !audio_track.is_null();
+        let has_audible_audio = has_audio && track_is_audible(audio_asset, audio_track)?;
+
+        let mut warnings = Vec::new();
+        if !has_video {
+            warnings.push("The recording has no video track".to_string());
+        }
+        if has_audio && !has_audible_audio {
+            warnings.push("The audio track is silent: nothing played, or the wrong audio device was captured".to_string());
+        }
+
+        Ok(RecordingVerification {
+            duration_seconds: CMTimeGetSeconds(duration).max(0.0),
+            has_video,
+            has_audio,
+            has_audible_audio,
+            color_primaries: if has_video { track_color_primaries(video_track) } else { None },
+            warnings,
+        })
+    }
+}
+
+/// The colour primaries the video at `path` is tagged with, e.g. "P3_D65" or "ITU_R_709_2";
+/// None when it can't be opened or isn't tagged
+pub fn color_primaries(path: &str) -> Option<String> {
+    unsafe {
+        let asset = open_asset(path);
+        if asset.is_null() {
+            return None;
+        }
+        let track = first_track(asset, AVMediaTypeVideo);
+        if track.is_null() { None } else { track_color_primaries(track) }
+    }
+}
+
+/// ColorPrimaries extension of `track`'s format description
+unsafe fn track_color_primaries(track: *mut AnyObject) -> Option<String> {
+    let descriptions: *mut NSArray<AnyObject> = msg_send![track, formatDescriptions];
+    if descriptions.is_null() {
+        return None;
+    }
+    let description: *mut AnyObject = msg_send![descriptions, firstObject];
+    if description.is_null() {
+        return None;
+    }
+    let primaries = CMFormatDescriptionGetExtension(description, kCMFormatDescriptionExtension_ColorPrimaries) as *const NSString;
+    (!primaries.is_null()).then(|| (*primaries).to_string())
+}
+
+/// Decode `track` of `asset` to float PCM until a block is audible or `MAX_SCAN_SECONDS` in
+unsafe fn track_is_audible(asset: *mut AnyObject, track: *mut AnyObject) -> Result<bool> {
+    let mut error: *mut NSError = ptr::null_mut();
+    let reader: *mut AnyObject = msg_send![class!(AVAssetReader), assetReaderWithAsset: asset, error: &mut error];
+    if reader.is_null() {
+        return Err(Error::new(Status::GenericFailure, format!("Failed to read the audio: {}", describe_nserror(error))));
+    }
+
+    let keys = ["AVFormatIDKey", "AVLinearPCMBitDepthKey", "AVLinearPCMIsFloatKey", "AVLinearPCMIsBigEndianKey",
+        "AVLinearPCMIsNonInterleaved"].map(NSString::from_str);
+    let yes: *mut NSNumber = msg_send![class!(NSNumber), numberWithBool: true];
+    let no: *mut NSNumber = msg_send![class!(NSNumber), numberWithBool: false];
+    let output_settings = dictionary(&[
+        (&*keys[0], number_u32(AUDIO_FORMAT_LINEAR_PCM)),
+        (&*keys[1], number_u32(32)),
+        (&*keys[2], yes as *mut AnyObject),
+        (&*keys[3], no as *mut AnyObject),
+        (&*keys[4], no as *mut AnyObject),
+    ]);
+    let output: *mut AnyObject = msg_send![
+        class!(AVAssetReaderTrackOutput),
+        assetReaderTrackOutputWithTrack: track,
+        outputSettings: output_settings
+    ];
+    let _: () = msg_send![reader, addOutput: output];
+    let reading: bool = msg_send![reader, startReading];
+    if !reading {
+        let reader_error: *mut NSError = msg_send![reader, error];
+        return Err(Error::new(Status::GenericFailure, format!("Failed to read the audio: {}", describe_nserror(reader_error))));
+    }
+
+    let mut audible = false;
+    while !audible {
+        let sample_buffer: *mut CMSampleBuffer = msg_send![output, copyNextSampleBuffer];
+        if sample_buffer.is_null() {
+            break;
+        }
+        let seconds = CMTimeGetSeconds(CMSampleBufferGetPresentationTimeStamp(&*sample_buffer));
+        with_float_samples(&*sample_buffer, |samples| {
+            audible |= is_audible(samples);
+        });
+        CFRelease(sample_buffer as *const std::ffi::c_void);
+        if seconds > MAX_SCAN_SECONDS {
+            break;
+        }
+    }
+    let _: () = msg_send![reader, cancelReading];
+    Ok(audible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_blocks_are_not_audible() {
+        let silence = vec![0.0f32; 1024];
+        let hiss = vec![0.0005f32; 1024];
+        let tone: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.05).sin() * 0.1).collect();
+        assert!(!is_audible(&silence));
+        assert!(!is_audible(&hiss), "-66 dBFS of hiss is silence");
+        assert!(is_audible(&tone));
+    }
+}
@@ -0,0 +1,299 @@
+// Watermark overlay
+// Composites a static image, such as a logo, into a corner of every frame before it's encoded.
+// The image is decoded once into premultiplied BGRA with the opacity applied; the first frame
+// of each size scales it to where it lands and keeps that copy. From then on a frame costs one
+// vImage alpha blend over the watermark's pixels, so the CPU cost per frame follows the
+// watermark's area rather than the frame's: a 300x100 logo blends 30,000 pixels a frame
+
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::Mutex;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::NSString;
+use objc2_core_media::CMSampleBuffer;
+use objc2_core_video::CVPixelBuffer;
+use napi::{Result, Status, Error};
+
+use super::foundation::{CGPoint, CGRect, CGSize};
+use super::recording::kCVPixelFormatType_32BGRA;
+use super::types::WebcamCorner;
+use super::webcam::OverlayRect;
+
+/// Smallest and largest watermark width, as a fraction of the frame width
+pub const MIN_WATERMARK_SIZE: f64 = 0.01;
+pub const MAX_WATERMARK_SIZE: f64 = 1.0;
+// Gap between the watermark and the frame edges unless configured, as a fraction of the frame width
+const MARGIN_FRACTION: f64 = 0.02;
+// Largest image decoded, on either side
+const MAX_IMAGE_SIDE: usize = 8192;
+
+// BGRA bitmap layout: kCGImageAlphaPremultipliedFirst | kCGBitmapByteOrder32Little
+const BGRA_BITMAP_INFO: u32 = 2 | (2 << 12);
+// kCGInterpolationHigh
+const INTERPOLATION_HIGH: i32 = 3;
+
+/// Matches vImage_Buffer
+#[repr(C)]
+struct VImageBuffer {
+    data: *mut c_void,
+    height: usize,
+    width: usize,
+    row_bytes: usize,
+}
+
+extern "C" {
+    fn CMSampleBufferGetImageBuffer(sbuf: &CMSampleBuffer) -> *mut CVPixelBuffer;
+    fn CVPixelBufferGetPixelFormatType(pixel_buffer: *mut CVPixelBuffer) -> u32;
+    fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut CVPixelBuffer, lock_flags: u64) -> i32;
+    fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut CVPixelBuffer, unlock_flags: u64) -> i32;
+    fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut CVPixelBuffer) -> *mut c_void;
+    fn CVPixelBufferGetWidth(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetHeight(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetBytesPerRow(pixel_buffer: *mut CVPixelBuffer) -> usize;
+
+    fn CGImageGetWidth(image: *mut c_void) -> usize;
+    fn CGImageGetHeight(image: *mut c_void) -> usize;
+    fn CGColorSpaceCreateDeviceRGB() -> *mut c_void;
+    fn CGColorSpaceRelease(space: *mut c_void);
+    fn CGBitmapContextCreate(
+        data: *mut c_void,
+        width: usize,
+        height: usize,
+        bits_per_component: usize,
+        bytes_per_row: usize,
+        space: *mut c_void,
+        bitmap_info: u32,
+    ) -> *mut c_void;
+    fn CGContextSetInterpolationQuality(context: *mut c_void, quality: i32);
+    fn CGContextDrawImage(context: *mut c_void, rect: CGRect, image: *mut c_void);
+    fn CGContextRelease(context: *mut c_void);
+
+    fn vImageScale_ARGB8888(src: *const VImageBuffer, dest: *const VImageBuffer, temp_buffer: *mut c_void, flags: u32) -> isize;
+    fn vImagePremultipliedAlphaBlend_BGRA8888(
+        src_top: *const VImageBuffer,
+        src_bottom: *const VImageBuffer,
+        dest: *const VImageBuffer,
+        flags: u32,
+    ) -> isize;
+}
+
+/// Where the watermark image comes from
+#[derive(Debug, Clone)]
+pub enum WatermarkSource {
+    /// An image file (PNG, JPEG, PDF, ... anything NSImage reads)
+    Path(String),
+    /// Encoded image bytes
+    Data(Vec<u8>),
+}
+
+/// How the watermark is placed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatermarkPlacement {
+    pub corner: WebcamCorner,
+    /// 0.0-1.0, multiplied into the image's own alpha
+    pub opacity: f64,
+    /// Width as a fraction of the frame width; None keeps the image's pixel size
+    pub size: Option<f64>,
+    /// Gap to the frame edges in pixels; None is 2% of the frame width
+    pub margin: Option<u32>,
+}
+
+/// Place a `image_width`x`image_height` watermark in its corner of a
+/// `frame_width`x`frame_height` frame, keeping the image's aspect ratio
+/// None when it wouldn't fit inside the margins
+pub fn watermark_rect(
+    frame_width: usize,
+    frame_height: usize,
+    image_width: usize,
+    image_height: usize,
+    placement: &WatermarkPlacement,
+) -> Option<OverlayRect> {
+    if image_width == 0 || image_height == 0 {
+        return None;
+    }
+    let margin = placement.margin.map_or((frame_width as f64 * MARGIN_FRACTION).round() as usize, |margin| margin as usize);
+    let (width, height) = match placement.size {
+        Some(size) => {
+            let width = (frame_width as f64 * size).round() as usize;
+            (width, (width as f64 * image_height as f64 / image_width as f64).round() as usize)
+        }
+        None => (image_width, image_height),
+    };
+    if width == 0 || height == 0 || width + 2 * margin > frame_width || height + 2 * margin > frame_height {
+        return None;
+    }
+    let (x, y) = match placement.corner {
+        WebcamCorner::TopLeft => (margin, margin),
+        WebcamCorner::TopRight => (frame_width - margin - width, margin),
+        WebcamCorner::BottomLeft => (margin, frame_height - margin - height),
+        WebcamCorner::BottomRight => (frame_width - margin - width, frame_height - margin - height),
+    };
+    Some(OverlayRect { x, y, width, height })
+}
+
+/// Premultiplied BGRA pixels
+struct Bitmap {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    fn vimage(&self) -> VImageBuffer {
+        VImageBuffer {
+            data: self.pixels.as_ptr() as *mut c_void,
+            height: self.height,
+            width: self.width,
+            row_bytes: self.width * 4,
+        }
+    }
+}
+
+/// The watermark scaled for one frame size
+struct Placed {
+    frame_size: (usize, usize),
+    rect: Option<OverlayRect>,
+    bitmap: Option<Bitmap>,
+}
+
+/// A decoded watermark drawn onto every frame
+pub struct Watermark {
+    image: Bitmap,
+    placement: WatermarkPlacement,
+    placed: Mutex<Option<Placed>>,
+}
+
+impl Watermark {
+    /// Decode the image of `source` once; fails when it isn't a readable image
+    pub fn load(source: &WatermarkSource, placement: WatermarkPlacement) -> Result<Self> {
+        let mut image = unsafe { decode(source) }?;
+        let opacity = placement.opacity.clamp(0.0, 1.0);
+        if opacity < 1.0 {
+            // Premultiplied, so colour and alpha scale together
+            for byte in image.pixels.iter_mut() {
+                *byte = (*byte as f64 * opacity).round() as u8;
+            }
+        }
+        println!("🏷️ Watermark loaded ({}x{})", image.width, image.height);
+        Ok(Self { image, placement, placed: Mutex::new(None) })
+    }
+
+    /// Blend the watermark into its corner of the sample buffer's pixels (BGRA only)
+    pub fn render(&self, sample_buffer: &CMSampleBuffer) {
+        let Ok(mut placed) = self.placed.lock() else {
+            return;
+        };
+        unsafe {
+            let frame = CMSampleBufferGetImageBuffer(sample_buffer);
+            if frame.is_null() || CVPixelBufferGetPixelFormatType(frame) != kCVPixelFormatType_32BGRA {
+                return;
+            }
+            let frame_size = (CVPixelBufferGetWidth(frame), CVPixelBufferGetHeight(frame));
+            if placed.as_ref().is_none_or(|placed| placed.frame_size != frame_size) {
+                *placed = Some(self.place(frame_size));
+            }
+            let Some(Placed { rect: Some(rect), bitmap: Some(ref bitmap), .. }) = *placed else {
+                return;
+            };
+
+            if CVPixelBufferLockBaseAddress(frame, 0) != 0 {
+                return;
+            }
+            let row_bytes = CVPixelBufferGetBytesPerRow(frame);
+            let corner = (CVPixelBufferGetBaseAddress(frame) as *mut u8).add(rect.y * row_bytes + rect.x * 4);
+            let region = VImageBuffer { data: corner as *mut c_void, height: rect.height, width: rect.width, row_bytes };
+            vImagePremultipliedAlphaBlend_BGRA8888(&bitmap.vimage(), &region, &region, 0);
+            CVPixelBufferUnlockBaseAddress(frame, 0);
+        }
+    }
+
+    /// Scale the image for frames of `frame_size`; a watermark that doesn't fit isn't drawn
+    fn place(&self, frame_size: (usize, usize)) -> Placed {
+        let rect = watermark_rect(frame_size.0, frame_size.1, self.image.width, self.image.height, &self.placement);
+        if rect.is_none() {
+            println!("⚠️ The watermark doesn't fit {}x{} frames; they're recorded without it", frame_size.0, frame_size.1);
+        }
+        let bitmap = rect.map(|rect| {
+            if (rect.width, rect.height) == (self.image.width, self.image.height) {
+                return Bitmap { width: rect.width, height: rect.height, pixels: self.image.pixels.clone() };
+            }
+            let scaled = Bitmap { width: rect.width, height: rect.height, pixels: vec![0; rect.width * rect.height * 4] };
+            unsafe { vImageScale_ARGB8888(&self.image.vimage(), &scaled.vimage(), ptr::null_mut(), 0) };
+            scaled
+        });
+        Placed { frame_size, rect, bitmap }
+    }
+}
+
+/// Decode `source` into premultiplied BGRA at the image's pixel size
+unsafe fn decode(source: &WatermarkSource) -> Result<Bitmap> {
+    let allocated: *mut AnyObject = msg_send![class!(NSImage), alloc];
+    let image: *mut AnyObject = match source {
+        WatermarkSource::Path(path) => {
+            let path = NSString::from_str(path);
+            msg_send![allocated, initWithContentsOfFile: &*path]
+        }
+        WatermarkSource::Data(bytes) => {
+            let data: *mut AnyObject = msg_send![class!(NSData), dataWithBytes: bytes.as_ptr() as *const c_void, length: bytes.len()];
+            msg_send![allocated, initWithData: data]
+        }
+    };
+    let describe = || match source {
+        WatermarkSource::Path(path) => path.clone(),
+        WatermarkSource::Data(bytes) => format!("{} bytes of image data", bytes.len()),
+    };
+    if image.is_null() {
+        return Err(Error::new(Status::InvalidArg, format!("Failed to read the watermark image from {}", describe())));
+    }
+
+    let cg_image: *mut c_void = msg_send![
+        image,
+        CGImageForProposedRect: ptr::null_mut::<CGRect>(),
+        context: ptr::null_mut::<AnyObject>(),
+        hints: ptr::null_mut::<AnyObject>()
+    ];
+    let (width, height) = if cg_image.is_null() { (0, 0) } else { (CGImageGetWidth(cg_image), CGImageGetHeight(cg_image)) };
+    if width == 0 || height == 0 || width > MAX_IMAGE_SIDE || height > MAX_IMAGE_SIDE {
+        let _: () = msg_send![image, release];
+        return Err(Error::new(
+            Status::InvalidArg,
+            format!("The watermark image from {} must be between 1x1 and {}x{} pixels", describe(), MAX_IMAGE_SIDE, MAX_IMAGE_SIDE),
+        ));
+    }
+
+    let mut bitmap = Bitmap { width, height, pixels: vec![0; width * height * 4] };
+    let color_space = CGColorSpaceCreateDeviceRGB();
+    let context = CGBitmapContextCreate(bitmap.pixels.as_mut_ptr() as *mut c_void, width, height, 8, width * 4, color_space, BGRA_BITMAP_INFO);
+    CGColorSpaceRelease(color_space);
+    if !context.is_null() {
+        CGContextSetInterpolationQuality(context, INTERPOLATION_HIGH);
+        let rect = CGRect { origin: CGPoint { x: 0.0, y: 0.0 }, size: CGSize { width: width as f64, height: height as f64 } };
+        CGContextDrawImage(context, rect, cg_image);
+        CGContextRelease(context);
+    }
+    let _: () = msg_send![image, release];
+    if context.is_null() {
+        return Err(Error::new(Status::GenericFailure, "Failed to draw the watermark image"));
+    }
+    Ok(bitmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watermark_sits_inside_its_corner() {
+        let placement = WatermarkPlacement { corner: WebcamCorner::TopRight, opacity: 1.0, size: None, margin: Some(10) };
+        let rect = watermark_rect(1920, 1080, 300, 100, &placement).expect("Fits");
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (1920 - 10 - 300, 10, 300, 100));
+
+        let scaled = WatermarkPlacement { corner: WebcamCorner::BottomLeft, size: Some(0.1), margin: None, ..placement };
+        let rect = watermark_rect(1920, 1080, 300, 100, &scaled).expect("Fits");
+        assert_eq!((rect.width, rect.height), (192, 64), "Scaled to a tenth of the frame width");
+        assert_eq!((rect.x, rect.y + rect.height), (38, 1080 - 38));
+
+        assert!(watermark_rect(640, 360, 1000, 100, &placement).is_none(), "A logo wider than the frame isn't drawn");
+    }
+}
@@ -0,0 +1,350 @@
+// Webcam overlay
+// Captures a camera with an AVCaptureSession and draws its latest frame as a picture-in-picture
+// onto every screen frame before it's encoded. The camera delivers BGRA like the screen, so
+// its frame is scaled straight into the chosen corner of the screen frame with vImage
+
+use std::ffi::{c_char, c_void};
+use std::ptr;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use objc2::runtime::{AnyObject, Bool};
+use objc2::{msg_send, class};
+use objc2_foundation::{NSArray, NSString, NSError, NSNumber};
+use objc2_core_media::CMSampleBuffer;
+use objc2_core_video::CVPixelBuffer;
+use block2::RcBlock;
+use napi::{Result, Status, Error};
+
+use super::encoder::AVMediaTypeVideo;
+use super::foundation::describe_nserror;
+use super::frame_stream::RetainedPixelBuffer;
+use super::recording::kCVPixelFormatType_32BGRA;
+use super::types::WebcamCorner;
+use crate::CameraDevice;
+
+/// Overlay width as a fraction of the frame width unless configured
+pub const DEFAULT_WEBCAM_SIZE: f64 = 0.25;
+/// Smallest and largest overlay width, as a fraction of the frame width
+pub const MIN_WEBCAM_SIZE: f64 = 0.05;
+pub const MAX_WEBCAM_SIZE: f64 = 0.5;
+// Gap between the overlay and the frame edges, as a fraction of the frame width
+const MARGIN_FRACTION: f64 = 0.02;
+// How long to wait for the session to deliver its first frame before recording without it
+const FIRST_FRAME_TIMEOUT: Duration = Duration::from_secs(2);
+
+// AVAuthorizationStatus values
+const AUTHORIZATION_NOT_DETERMINED: isize = 0;
+const AUTHORIZATION_AUTHORIZED: isize = 3;
+
+const PIXEL_BUFFER_LOCK_READ_ONLY: u64 = 1; // kCVPixelBufferLock_ReadOnly
+
+/// Matches vImage_Buffer
+#[repr(C)]
+struct VImageBuffer {
+    data: *mut c_void,
+    height: usize,
+    width: usize,
+    row_bytes: usize,
+}
+
+extern "C" {
+    static kCVPixelBufferPixelFormatTypeKey: *const NSString;
+
+    fn create_camera_output_bridge(rust_context: *mut c_void, frame_callback: extern "C" fn(*mut c_void, *const CMSampleBuffer)) -> *mut c_void;
+    fn release_camera_output_bridge(bridge: *mut c_void);
+
+    fn dispatch_queue_create(label: *const c_char, attr: *const c_void) -> *mut c_void;
+    fn dispatch_sync_f(queue: *mut c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+    fn dispatch_release(object: *mut c_void);
+
+    fn CMSampleBufferGetImageBuffer(sbuf: &CMSampleBuffer) -> *mut CVPixelBuffer;
+    fn CVPixelBufferGetPixelFormatType(pixel_buffer: *mut CVPixelBuffer) -> u32;
+    fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut CVPixelBuffer, lock_flags: u64) -> i32;
+    fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut CVPixelBuffer, unlock_flags: u64) -> i32;
+    fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut CVPixelBuffer) -> *mut c_void;
+    fn CVPixelBufferGetWidth(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetHeight(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    fn CVPixelBufferGetBytesPerRow(pixel_buffer: *mut CVPixelBuffer) -> usize;
+
+    fn vImageScale_ARGB8888(src: *const VImageBuffer, dest: *const VImageBuffer, temp_buffer: *mut c_void, flags: u32) -> isize;
+}
+
+/// Where the overlay lands in a frame, in pixels from the top-left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlayRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Place a `camera_width`x`camera_height` picture `size` of the frame width wide in `corner`
+/// of a `frame_width`x`frame_height` frame, keeping the camera's aspect ratio
+/// None when it wouldn't fit, e.g. a portrait camera on a very short frame
+pub fn overlay_rect(
+    frame_width: usize,
+    frame_height: usize,
+    camera_width: usize,
+    camera_height: usize,
+    corner: WebcamCorner,
+    size: f64,
+) -> Option<OverlayRect> {
+    if camera_width == 0 || camera_height == 0 {
+        return None;
+    }
+    let margin = (frame_width as f64 * MARGIN_FRACTION).round() as usize;
+    let width = (frame_width as f64 * size).round() as usize;
+    let height = (width as f64 * camera_height as f64 / camera_width as f64).round() as usize;
+    if width == 0 || height == 0 || width + 2 * margin > frame_width || height + 2 * margin > frame_height {
+        return None;
+    }
+    let (x, y) = match corner {
+        WebcamCorner::TopLeft => (margin, margin),
+        WebcamCorner::TopRight => (frame_width - margin - width, margin),
+        WebcamCorner::BottomLeft => (margin, frame_height - margin - height),
+        WebcamCorner::BottomRight => (frame_width - margin - width, frame_height - margin - height),
+    };
+    Some(OverlayRect { x, y, width, height })
+}
+
+/// The camera's most recent frame, replaced by the capture queue as frames arrive
+type LatestFrame = Mutex<Option<RetainedPixelBuffer>>;
+
+/// Running camera capture; dropping it stops the session
+pub struct WebcamOverlay {
+    session: *mut AnyObject,
+    output: *mut AnyObject,
+    bridge: *mut c_void,
+    queue: *mut c_void,
+    latest: *const LatestFrame,
+    corner: WebcamCorner,
+    size: f64,
+}
+
+// Safety: the session objects are only touched in start and Drop; frames go through the mutex
+unsafe impl Send for WebcamOverlay {}
+unsafe impl Sync for WebcamOverlay {}
+
+impl WebcamOverlay {
+    /// Start capturing `device_id` (an AVCaptureDevice uniqueID, or the default camera) for an
+    /// overlay `size` of the frame width wide in `corner`
+    /// Fails when camera access isn't granted or the camera can't be opened
+    pub fn start(device_id: Option<&str>, corner: WebcamCorner, size: f64) -> Result<Self> {
+        unsafe {
+            check_camera_access()?;
+
+            let device: *mut AnyObject = match device_id {
+                Some(id) => msg_send![class!(AVCaptureDevice), deviceWithUniqueID: &*NSString::from_str(id)],
+                None => msg_send![class!(AVCaptureDevice), defaultDeviceWithMediaType: &*NSString::from_str(AVMediaTypeVideo)],
+            };
+            if device.is_null() {
+                return Err(Error::new(
+                    Status::GenericFailure,
+                    match device_id {
+                        Some(id) => format!("Camera '{}' not found", id),
+                        None => "No camera is connected".to_string(),
+                    },
+                ));
+            }
+
+            let mut error: *mut NSError = ptr::null_mut();
+            let input: *mut AnyObject = msg_send![class!(AVCaptureDeviceInput), deviceInputWithDevice: device, error: &mut error];
+            if input.is_null() {
+                return Err(Error::new(Status::GenericFailure, format!("Failed to open the camera: {}", describe_nserror(error))));
+            }
+
+            let session: *mut AnyObject = msg_send![class!(AVCaptureSession), new];
+            let output: *mut AnyObject = msg_send![class!(AVCaptureVideoDataOutput), new];
+            let format = NSNumber::new_u32(kCVPixelFormatType_32BGRA);
+            let settings: *mut AnyObject = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObject: &*format,
+                forKey: kCVPixelBufferPixelFormatTypeKey
+            ];
+            let _: () = msg_send![output, setVideoSettings: settings];
+            let _: () = msg_send![output, setAlwaysDiscardsLateVideoFrames: true];
+
+            let can_add_input: bool = msg_send![session, canAddInput: input];
+            let can_add_output: bool = msg_send![session, canAddOutput: output];
+            if !can_add_input || !can_add_output {
+                let _: () = msg_send![output, release];
+                let _: () = msg_send![session, release];
+                return Err(Error::new(Status::GenericFailure, "The camera can't be added to a capture session"));
+            }
+            let _: () = msg_send![session, addInput: input];
+            let _: () = msg_send![session, addOutput: output];
+
+            let latest: *const LatestFrame = Arc::into_raw(Arc::new(Mutex::new(None)));
+            let bridge = create_camera_output_bridge(latest as *mut c_void, camera_frame_callback);
+            let queue = dispatch_queue_create(c"rustedscreencapture.webcam".as_ptr(), ptr::null());
+            let _: () = msg_send![output, setSampleBufferDelegate: bridge as *mut AnyObject, queue: queue as *mut AnyObject];
+
+            // Blocks until the camera is running
+            let _: () = msg_send![session, startRunning];
+            let overlay = Self { session, output, bridge, queue, latest, corner, size };
+            let running: bool = msg_send![session, isRunning];
+            if !running {
+                return Err(Error::new(Status::GenericFailure, "The camera session failed to start"));
+            }
+
+            let started = std::time::Instant::now();
+            while !overlay.has_frame() && started.elapsed() < FIRST_FRAME_TIMEOUT {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            println!("📷 Webcam overlay running");
+            Ok(overlay)
+        }
+    }
+
+    fn has_frame(&self) -> bool {
+        unsafe { &*self.latest }.lock().map(|latest| latest.is_some()).unwrap_or(false)
+    }
+
+    /// Scale the latest camera frame into its corner of the sample buffer's pixels (BGRA only)
+    pub fn render(&self, sample_buffer: &CMSampleBuffer) {
+        let Ok(latest) = unsafe { &*self.latest }.lock() else {
+            return;
+        };
+        let Some(ref camera) = *latest else {
+            return;
+        };
+
+        unsafe {
+            let screen = CMSampleBufferGetImageBuffer(sample_buffer);
+            if screen.is_null() || CVPixelBufferGetPixelFormatType(screen) != kCVPixelFormatType_32BGRA {
+                return;
+            }
+            let Some(rect) = overlay_rect(
+                CVPixelBufferGetWidth(screen),
+                CVPixelBufferGetHeight(screen),
+                CVPixelBufferGetWidth(camera.as_ptr()),
+                CVPixelBufferGetHeight(camera.as_ptr()),
+                self.corner,
+                self.size,
+            ) else {
+                return;
+            };
+
+            if CVPixelBufferLockBaseAddress(screen, 0) != 0 {
+                return;
+            }
+            if CVPixelBufferLockBaseAddress(camera.as_ptr(), PIXEL_BUFFER_LOCK_READ_ONLY) == 0 {
+                let source = VImageBuffer {
+                    data: CVPixelBufferGetBaseAddress(camera.as_ptr()),
+                    height: CVPixelBufferGetHeight(camera.as_ptr()),
+                    width: CVPixelBufferGetWidth(camera.as_ptr()),
+                    row_bytes: CVPixelBufferGetBytesPerRow(camera.as_ptr()),
+                };
+                let row_bytes = CVPixelBufferGetBytesPerRow(screen);
+                let corner = (CVPixelBufferGetBaseAddress(screen) as *mut u8).add(rect.y * row_bytes + rect.x * 4);
+                let destination = VImageBuffer {
+                    data: corner as *mut c_void,
+                    height: rect.height,
+                    width: rect.width,
+                    row_bytes,
+                };
+                vImageScale_ARGB8888(&source, &destination, ptr::null_mut(), 0);
+                CVPixelBufferUnlockBaseAddress(camera.as_ptr(), PIXEL_BUFFER_LOCK_READ_ONLY);
+            }
+            CVPixelBufferUnlockBaseAddress(screen, 0);
+        }
+    }
+}
+
+impl Drop for WebcamOverlay {
+    fn drop(&mut self) {
+        extern "C" fn drained(_context: *mut c_void) {}
+
+        unsafe {
+            let _: () = msg_send![self.session, stopRunning];
+            let _: () = msg_send![self.output, setSampleBufferDelegate: ptr::null_mut::<AnyObject>(), queue: ptr::null_mut::<AnyObject>()];
+            // A frame callback already queued still uses the context; wait it out
+            dispatch_sync_f(self.queue, ptr::null_mut(), drained);
+            let _: () = msg_send![self.output, release];
+            let _: () = msg_send![self.session, release];
+            release_camera_output_bridge(self.bridge);
+            dispatch_release(self.queue);
+            drop(Arc::from_raw(self.latest));
+        }
+        println!("📷 Webcam overlay stopped");
+    }
+}
+
+/// Keep the newest camera frame for the next screen frame
+extern "C" fn camera_frame_callback(context: *mut c_void, sample_buffer: *const CMSampleBuffer) {
+    if context.is_null() || sample_buffer.is_null() {
+        return;
+    }
+    unsafe {
+        let latest = &*(context as *const LatestFrame);
+        let Some(frame) = RetainedPixelBuffer::from_sample_buffer(&*sample_buffer) else {
+            return;
+        };
+        if let Ok(mut latest) = latest.lock() {
+            *latest = Some(frame);
+        }
+    }
+}
+
+/// Fail unless camera access is granted; asks for it when the user hasn't decided yet
+pub(super) unsafe fn check_camera_access() -> Result<()> {
+    let media_type = NSString::from_str(AVMediaTypeVideo);
+    let status: isize = msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: &*media_type];
+    if status == AUTHORIZATION_AUTHORIZED {
+        return Ok(());
+    }
+    if status != AUTHORIZATION_NOT_DETERMINED {
+        return Err(Error::new(
+            Status::GenericFailure,
+            "Camera access is denied (System Settings > Privacy & Security > Camera)",
+        ));
+    }
+
+    // Shows the system prompt; the answer arrives on an arbitrary queue
+    let (sender, receiver) = mpsc::channel();
+    let completion = RcBlock::new(move |granted: Bool| {
+        let _ = sender.send(granted.as_bool());
+    });
+    let _: () = msg_send![class!(AVCaptureDevice), requestAccessForMediaType: &*media_type, completionHandler: &*completion];
+    match receiver.recv_timeout(Duration::from_secs(60)) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::new(Status::GenericFailure, "Camera access was declined")),
+        Err(_) => Err(Error::new(Status::GenericFailure, "Camera access wasn't granted in time")),
+    }
+}
+
+/// Cameras that can be used for the webcam overlay
+pub fn available_cameras() -> Vec<CameraDevice> {
+    unsafe {
+        let media_type = NSString::from_str(AVMediaTypeVideo);
+        let devices: *mut NSArray<AnyObject> = msg_send![class!(AVCaptureDevice), devicesWithMediaType: &*media_type];
+        if devices.is_null() {
+            return Vec::new();
+        }
+        (*devices).iter().filter_map(|device| {
+            let id: *mut NSString = msg_send![&*device, uniqueID];
+            let name: *mut NSString = msg_send![&*device, localizedName];
+            (!id.is_null()).then(|| CameraDevice {
+                id: (*id).to_string(),
+                name: if name.is_null() { String::new() } else { (*name).to_string() },
+            })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_sits_inside_its_corner() {
+        let rect = overlay_rect(1920, 1080, 1280, 720, WebcamCorner::BottomRight, DEFAULT_WEBCAM_SIZE).expect("Fits");
+        assert_eq!((rect.width, rect.height), (480, 270));
+        assert_eq!((rect.x + rect.width, rect.y + rect.height), (1920 - 38, 1080 - 38));
+
+        let rect = overlay_rect(1920, 1080, 1280, 720, WebcamCorner::TopLeft, DEFAULT_WEBCAM_SIZE).expect("Fits");
+        assert_eq!((rect.x, rect.y), (38, 38));
+
+        assert!(overlay_rect(1920, 200, 720, 1280, WebcamCorner::TopLeft, MAX_WEBCAM_SIZE).is_none(), "A portrait camera doesn't fit a short frame");
+    }
+}
@@ -0,0 +1,414 @@
+// Non-macOS stubs
+// Mirrors the napi surface so cross-platform projects build; every call fails with a clear error
+
+use napi::bindgen_prelude::*;
+use napi::JsFunction;
+use napi_derive::napi;
+
+use crate::{ScreenSource, SpaceInfo, AudioDevice, SourceFilter, SystemPickerOptions, PickedSource, CaptureRegion, RecordingTrigger, RecordingConfiguration, CaptureIndicatorState, LastRecordingError, CameraDevice, CaptureDevice, Capabilities, RecordingVerification, RecordingHandleStats, EffectiveSettings, RecordingTimeline, TranscodeConfiguration, TranscriptionJobConfiguration, TranscriptionJob};
+
+fn unsupported() -> Error {
+    Error::new(
+        Status::GenericFailure,
+        format!("ScreenCaptureKit is only supported on macOS (running on {})", std::env::consts::OS),
+    )
+}
+
+/// Complete async ScreenCaptureKit recorder with full functionality
+#[napi]
+pub struct ScreenCaptureKitRecorder {}
+
+#[napi]
+impl ScreenCaptureKitRecorder {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self> {
+        Ok(Self {})
+    }
+
+    #[napi]
+    pub async fn get_available_screens(&self, _filter: Option<SourceFilter>) -> Result<Vec<ScreenSource>> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn on_content_retrieval_slow(
+        &self,
+        #[napi(ts_arg_type = "(elapsedMs: number) => void")] _callback: JsFunction,
+    ) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn present_system_picker(&self, _options: Option<SystemPickerOptions>) -> Result<Option<PickedSource>> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn start_recording(&self, _screen_id: String, _config: RecordingConfiguration) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn start_recording_handle(&self, _screen_id: String, _config: RecordingConfiguration) -> Result<RecordingHandle> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn start_recording_region(&self, _region: CaptureRegion, _config: RecordingConfiguration) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn resume_into(
+        &self,
+        _previous_segment_path: String,
+        _screen_id: String,
+        _config: RecordingConfiguration,
+    ) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn stop_recording(&self) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn set_keep_warm(&self, _keep_warm: bool) {}
+
+    #[napi]
+    pub async fn reset(&self) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn is_recording(&self) -> bool {
+        false
+    }
+
+    #[napi]
+    pub async fn is_receiving_frames(&self) -> bool {
+        false
+    }
+
+    #[napi]
+    pub fn arm_trigger(
+        &self,
+        _trigger: RecordingTrigger,
+        _screen_id: String,
+        _config: RecordingConfiguration,
+        #[napi(ts_arg_type = "(event: TriggerEvent) => void")] _callback: Option<JsFunction>,
+    ) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn disarm_trigger(&self) -> Result<()> {
+        Ok(())
+    }
+
+    #[napi]
+    pub async fn pause_recording(&self) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn resume_recording(&self) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn set_audio_enabled(&self, _enabled: bool) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn set_video_enabled(&self, _enabled: bool) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn get_recording_timeline(&self) -> Option<RecordingTimeline> {
+        None
+    }
+
+    #[napi]
+    pub async fn get_cursor_track_path(&self) -> Option<String> {
+        None
+    }
+
+    #[napi]
+    pub async fn save_replay(&self, _output_path: String) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn get_status(&self) -> String {
+        serde_json::json!({
+            "isRecording": false,
+            "supported": false,
+            "platform": std::env::consts::OS,
+        }).to_string()
+    }
+
+    #[napi]
+    pub async fn collect_diagnostics(&self) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn get_last_error(&self) -> Result<Option<LastRecordingError>> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn cancel_content_retrieval(&self) {}
+
+    #[napi]
+    pub async fn get_capture_indicator_state(&self) -> Result<CaptureIndicatorState> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn get_effective_settings(&self) -> Option<EffectiveSettings> {
+        None
+    }
+
+    #[napi]
+    pub async fn grab_current_frame(&self, _format: Option<String>) -> Result<Buffer> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn start_audio_monitor(
+        &self,
+        _device_id: Option<String>,
+        #[napi(ts_arg_type = "(levels: AudioLevels) => void")] _callback: JsFunction,
+    ) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn on_disk_space_warning(
+        &self,
+        #[napi(ts_arg_type = "(warning: DiskSpaceWarning) => void")] _callback: JsFunction,
+    ) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn watch_sources(
+        &self,
+        #[napi(ts_arg_type = "(sources: Array<ScreenSource>) => void")] _callback: JsFunction,
+        _interval_ms: Option<u32>,
+        _filter: Option<SourceFilter>,
+    ) -> Result<SourceWatcher> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn on_error(
+        &self,
+        #[napi(ts_arg_type = "(error: RecordingError) => void")] _callback: JsFunction,
+    ) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn on_frames(
+        &self,
+        #[napi(ts_arg_type = "(frames: Array<VideoFrame>) => void")] _callback: JsFunction,
+        _batch_size: Option<u32>,
+        _format: Option<String>,
+    ) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn on_progress(
+        &self,
+        #[napi(ts_arg_type = "(progress: RecordingProgress) => void")] _callback: JsFunction,
+    ) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn stop_audio_monitor(&self) -> Result<()> {
+        Ok(())
+    }
+
+    #[napi]
+    pub async fn start_device_capture(&self, _device_id: String, _config: RecordingConfiguration) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn stop_device_capture(&self) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn get_available_windows(&self, _filter: Option<SourceFilter>) -> Result<Vec<ScreenSource>> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn get_spaces(&self) -> Result<Vec<SpaceInfo>> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn start_recording_space(&self, _space_id: i64, _config: RecordingConfiguration) -> Result<String> {
+        Err(unsupported())
+    }
+}
+
+/// Handle returned by `watchSources`
+#[napi]
+pub struct SourceWatcher {}
+
+#[napi]
+impl SourceWatcher {
+    #[napi]
+    pub fn unsubscribe(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Handle returned by `startRecordingHandle`; controls only its own recording
+#[napi]
+pub struct RecordingHandle {}
+
+#[napi]
+impl RecordingHandle {
+    #[napi]
+    pub async fn stop(&self) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn pause(&self) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn resume(&self) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn get_stats(&self) -> Option<RecordingHandleStats> {
+        None
+    }
+}
+
+/// Integrated recording manager with complete functionality
+#[napi]
+pub struct IntegratedRecordingManager {}
+
+#[napi]
+impl IntegratedRecordingManager {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[napi]
+    pub async fn initialize(&self) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn start_recording(&self, _config: RecordingConfiguration) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn stop_recording(&self) -> Result<String> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn get_available_screens(&self) -> Result<Vec<ScreenSource>> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub async fn get_available_windows(&self, _filter: Option<SourceFilter>) -> Result<Vec<ScreenSource>> {
+        Err(unsupported())
+    }
+
+    #[napi]
+    pub fn is_recording(&self) -> bool {
+        false
+    }
+}
+
+#[napi]
+pub fn get_available_cameras() -> Result<Vec<CameraDevice>> {
+    Err(unsupported())
+}
+
+#[napi]
+pub fn get_available_audio_devices() -> Result<Vec<AudioDevice>> {
+    Err(unsupported())
+}
+
+#[napi]
+pub fn cleanup_temp_files() -> Result<u32> {
+    Err(unsupported())
+}
+
+#[napi]
+pub fn verify_recording(_path: String) -> Result<RecordingVerification> {
+    Err(unsupported())
+}
+
+#[napi]
+pub fn get_available_capture_devices() -> Result<Vec<CaptureDevice>> {
+    Err(unsupported())
+}
+
+#[napi]
+pub fn get_capabilities() -> Result<Capabilities> {
+    Err(unsupported())
+}
+
+#[napi(ts_return_type = "Promise<string>")]
+pub fn transcode(
+    _input_path: String,
+    _output_path: String,
+    _config: TranscodeConfiguration,
+    #[napi(ts_arg_type = "(progress: TranscodeProgress) => void")] _on_progress: Option<JsFunction>,
+) -> Result<()> {
+    Err(unsupported())
+}
+
+#[napi(ts_return_type = "Promise<string>")]
+pub fn concatenate_segments(_segment_paths: Vec<String>, _output_path: String) -> Result<()> {
+    Err(unsupported())
+}
+
+#[napi]
+pub fn start_transcription_job(
+    _input_path: String,
+    _config: Option<TranscriptionJobConfiguration>,
+    #[napi(ts_arg_type = "(job: TranscriptionJob) => void")] _on_update: Option<JsFunction>,
+) -> Result<u32> {
+    Err(unsupported())
+}
+
+#[napi]
+pub fn get_transcription_job(_id: u32) -> Option<TranscriptionJob> {
+    None
+}
+
+#[napi]
+pub fn check_screen_recording_permission() -> Result<bool> {
+    Ok(false)
+}
+
+#[napi]
+pub fn request_screen_recording_permission() -> Result<bool> {
+    Err(unsupported())
+}
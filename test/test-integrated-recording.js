@@ -53,8 +53,8 @@ async function testIntegratedRecording() {
             fps: 30,
             showCursor: true,
             captureAudio: true,
-            pixelFormat: 'BGRA',
-            colorSpace: 'sRGB'
+            pixelFormat: 'bgra',
+            colorSpace: 'srgb'
         };
         
         // Step 5: Start recording
@@ -63,8 +63,8 @@ async function testRecordingFunctionality() {
             showCursor: true,
             captureAudio: false, // Start without audio to simplify
             outputPath: outputPath,
-            pixelFormat: 'BGRA',
-            colorSpace: 'sRGB'
+            pixelFormat: 'bgra',
+            colorSpace: 'srgb'
         };
 
         console.log('🔧 Recording configuration:', JSON.stringify(recordingConfig, null, 2));
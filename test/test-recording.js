@@ -29,8 +29,8 @@ const DEFAULT_CONFIG = {
     fps: 30,
     showCursor: true,
     captureAudio: false, // Start with video-only for simplicity
-    pixelFormat: 'BGRA',
-    colorSpace: 'sRGB'
+    pixelFormat: 'bgra',
+    colorSpace: 'srgb'
 };
 
 async function ensureOutputDirectory() {